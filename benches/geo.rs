@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use gremlin::geo::{Matrix, Vector};
+use gremlin::geo::{Degrees, Matrix, Vector};
 
 pub fn vector_min(c: &mut Criterion) {
     let u = Vector::splat(1.0);
@@ -65,7 +65,7 @@ pub fn matrix_scalar_mult(c: &mut Criterion) {
 }
 
 pub fn matrix_vector_mult(c: &mut Criterion) {
-    let m = Matrix::rotate(50.0, Vector::new(1.0, 2.0, 3.0).normalize());
+    let m = Matrix::rotate(Degrees(50.0), Vector::new(1.0, 2.0, 3.0).normalize());
     let v = Vector::new(1.0, 2.0, 3.0);
 
     c.bench_function("matrix vector mult", |b| {
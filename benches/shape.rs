@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use gremlin::{
+    camera::{Camera, ThinLens},
     geo::{Matrix, Point, Ray, Vector},
     shape::*,
     Float,
@@ -53,7 +54,7 @@ pub fn aggregate_direct_dispatch(c: &mut Criterion) {
 pub fn aggregate_enum_dispatch(c: &mut Criterion) {
     let agg: Vec<Surface> = random_spheres()
         .into_iter()
-        .map(|sphere| Surface::from(sphere))
+        .map(Surface::from)
         .collect();
     let ray = Ray::new(Point::new(0.0, 0.0, -20.0), Vector::Z_AXIS);
 
@@ -78,11 +79,73 @@ pub fn aggregate_dynamic_dispatch(c: &mut Criterion) {
     });
 }
 
+pub fn bvh_primary_rays(c: &mut Criterion) {
+    let bvh = Bvh::new(random_spheres());
+    let cam = ThinLens::builder((32, 32))
+        .move_to([5.0, 5.0, -20.0])
+        .look_at([5.0, 5.0, 5.0])
+        .build();
+    let mut rng = StdRng::seed_from_u64(1234);
+    let rays: Vec<Ray> = (0..32 * 32)
+        .map(|i| cam.ray(i % 32, i / 32, &mut rng))
+        .collect();
+    let mut hits = vec![None; rays.len()];
+
+    c.bench_function("bvh primary rays single", |b| {
+        b.iter(|| {
+            for (ray, hit) in rays.iter().zip(&mut hits) {
+                *hit = bvh.intersect(ray, 0.0, Float::INFINITY);
+            }
+            black_box(&hits);
+        })
+    });
+    c.bench_function("bvh primary rays packet", |b| {
+        b.iter(|| {
+            bvh.intersect_packet(&rays, 0.0, Float::INFINITY, &mut hits);
+            black_box(&hits);
+        })
+    });
+}
+
+pub fn grid_vs_bvh(c: &mut Criterion) {
+    let cam = ThinLens::builder((32, 32))
+        .move_to([5.0, 5.0, -20.0])
+        .look_at([5.0, 5.0, 5.0])
+        .build();
+    let mut rng = StdRng::seed_from_u64(1234);
+    let rays: Vec<Ray> = (0..32 * 32)
+        .map(|i| cam.ray(i % 32, i / 32, &mut rng))
+        .collect();
+
+    c.bench_function("grid build", |b| {
+        b.iter(|| black_box(UniformGrid::new(random_spheres())))
+    });
+    c.bench_function("bvh build", |b| {
+        b.iter(|| black_box(Bvh::new(random_spheres())))
+    });
+
+    let grid = UniformGrid::new(random_spheres());
+    let bvh = Bvh::new(random_spheres());
+    c.bench_function("grid primary rays", |b| {
+        b.iter(|| {
+            for ray in &rays {
+                black_box(grid.intersect(ray, 0.0, Float::INFINITY));
+            }
+        })
+    });
+    c.bench_function("bvh primary rays", |b| {
+        b.iter(|| {
+            for ray in &rays {
+                black_box(bvh.intersect(ray, 0.0, Float::INFINITY));
+            }
+        })
+    });
+}
+
 fn random_spheres() -> Vec<Sphere> {
     let mut rng = StdRng::seed_from_u64(1234);
     let m = Matrix::scale_uniform(10.0);
     (0..1024)
-        .into_iter()
         .map(|_| {
             let p = Point::new(rng.gen(), rng.gen(), rng.gen());
             Sphere::new(m * p, rng.gen())
@@ -98,5 +161,7 @@ criterion_group!(
     aggregate_direct_dispatch,
     aggregate_enum_dispatch,
     aggregate_dynamic_dispatch,
+    bvh_primary_rays,
+    grid_vs_bvh,
 );
 criterion_main!(shape);
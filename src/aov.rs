@@ -0,0 +1,268 @@
+//! # Arbitrary output variables.
+//!
+//! AOVs are auxiliary images rendered alongside the beauty pass, showing some
+//! property of the scene rather than its appearance. Each AOV here is an
+//! [`Integrator`], so it is rendered with the same [`Renderer`] and camera as
+//! the beauty pass and lines up with it pixel-for-pixel.
+//!
+//! ```no_run
+//! use gremlin::{aov::{self, Wireframe}, camera::ThinLens, color::RGB, film::RGBFilm};
+//! use gremlin::{integrator::Hacky, renderer::Renderer};
+//!
+//! let integrator = Hacky::default();
+//! let mut beauty = RGBFilm::new(800, 600);
+//! let mut wire = RGBFilm::new(800, 600);
+//! let cam = ThinLens::builder(beauty.dimensions()).build();
+//!
+//! let renderer = Renderer::new(16);
+//! renderer.render(&mut beauty, &cam, &integrator);
+//! renderer.render(&mut wire, &cam, &Wireframe::new(&integrator.surfaces));
+//!
+//! let out = aov::overlay(&beauty.to_snapshot(), &wire.to_snapshot(), RGB::from([1.0, 0.5, 0.0]));
+//! out.save_image("wireframe.png").unwrap();
+//! ```
+//!
+//! Scalar AOVs, such as depth or time per pixel, are easier to read in false
+//! color; see [`FalseColor`].
+//!
+//! [`Integrator`]: crate::integrator::Integrator
+//! [`Renderer`]: crate::renderer::Renderer
+
+use crate::{
+    color::{Color, RGB},
+    film::Buffer,
+    geo::{Ray, Vector},
+    integrator::Integrator,
+    shape::{Intersection, Shape, Surface},
+    Float,
+};
+use rand::Rng;
+
+mod false_color;
+pub use false_color::*;
+
+const SQRT_2: Float = std::f64::consts::SQRT_2 as Float;
+
+/// Default wireframe line width, in radians.
+const DEFAULT_WIRE_WIDTH: Float = 1e-3;
+
+/// An AOV marking pixels near triangle edges.
+///
+/// Evaluates to white where the first hit is within the line width of an edge
+/// of a triangle and black elsewhere, so averaging samples gives antialiased
+/// coverage. Spheres have no edges and are always black.
+///
+/// The width is angular, _i.e._ scaled by hit distance, so lines come out
+/// roughly the same width on screen regardless of depth.
+#[derive(Debug, Clone, Copy)]
+pub struct Wireframe<'a> {
+    surfaces: &'a [Surface],
+    width: Float,
+}
+
+impl<'a> Wireframe<'a> {
+    /// Create a wireframe AOV over the given surfaces.
+    pub fn new(surfaces: &'a [Surface]) -> Self {
+        Self {
+            surfaces,
+            width: DEFAULT_WIRE_WIDTH,
+        }
+    }
+
+    /// Set the line width, in radians. About one pixel is the camera's field
+    /// of view (in radians) divided by the film width.
+    pub fn width(mut self, width: Float) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Whether the first hit along the ray is near a triangle edge.
+    pub fn is_edge(&self, ray: &Ray) -> bool {
+        match closest(self.surfaces, ray) {
+            Some((Surface::Triangle(tri), isect)) => {
+                let dist = isect.t * ray.direction.len();
+                tri.edge_distance(isect.point) < self.width * dist
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Integrator<RGB> for Wireframe<'_> {
+    fn radiance(&self, ray: &Ray, _rng: &mut impl Rng) -> RGB {
+        match self.is_edge(ray) {
+            true => RGB::from([1.0, 1.0, 1.0]),
+            false => RGB::default(),
+        }
+    }
+}
+
+/// An AOV of surface normals, for post-processing such as toon shading.
+///
+/// Each component of the normal maps from `[-1, 1]` to `[0, 1]`, the usual
+/// normal map encoding, and misses are black. Surfaces are stored in world
+/// space, so these are also their object-space normals, pointing the way the
+/// shape faces rather than back at the camera.
+#[derive(Debug, Clone, Copy)]
+pub struct Normals<'a> {
+    surfaces: &'a [Surface],
+}
+
+impl<'a> Normals<'a> {
+    /// Create a normal AOV over the given surfaces.
+    pub fn new(surfaces: &'a [Surface]) -> Self {
+        Self { surfaces }
+    }
+}
+
+impl Integrator<RGB> for Normals<'_> {
+    fn radiance(&self, ray: &Ray, _rng: &mut impl Rng) -> RGB {
+        match closest(self.surfaces, ray) {
+            Some((_, isect)) => {
+                let n = isect.norm;
+                RGB::from([n.x(), n.y(), n.z()].map(|c| 0.5 * c + 0.5))
+            }
+            None => RGB::default(),
+        }
+    }
+}
+
+/// How sharply surfaces curve, from a [`Normals`] AOV.
+///
+/// Each pixel is how far the normal turns from one pixel to the next, over
+/// both image axes: `0` on flat surfaces, and `1` where it turns by a right
+/// angle along one of them. Creases and tight curves come out bright, for
+/// ink lines or edge highlights. It's unsigned, so convex and concave curves
+/// look the same.
+///
+/// Background (black) pixels are left black and don't count as neighbours,
+/// so silhouettes don't show up; find those from the normals' coverage.
+pub fn curvature(normals: &Buffer<RGB>) -> Buffer<RGB> {
+    let (width, height) = normals.dimensions();
+    let decode = |x: u32, y: u32| {
+        let c = normals[(y * width + x) as usize];
+        let [r, g, b] = <[Float; 3]>::from(c);
+        (!c.is_black()).then(|| Vector::new(2.0 * r - 1.0, 2.0 * g - 1.0, 2.0 * b - 1.0))
+    };
+
+    let mut out = Buffer::new(width, height);
+    for (x, y, pixel) in out.pixel_iter_mut() {
+        let Some(n) = decode(x, y) else {
+            continue;
+        };
+        // Central differences where both neighbours are surface, and one-sided
+        // otherwise
+        let slope = |prev: Option<Vector>, next: Option<Vector>| match (prev, next) {
+            (Some(p), Some(q)) => (q - p) / 2.0,
+            (Some(p), None) => n - p,
+            (None, Some(q)) => q - n,
+            (None, None) => Vector::ZERO,
+        };
+        let dx = slope(
+            x.checked_sub(1).and_then(|x| decode(x, y)),
+            (x + 1 < width).then(|| decode(x + 1, y)).flatten(),
+        );
+        let dy = slope(
+            y.checked_sub(1).and_then(|y| decode(x, y)),
+            (y + 1 < height).then(|| decode(x, y + 1)).flatten(),
+        );
+        // Turning by a right angle moves a unit normal by sqrt(2)
+        let k = (dx.dot(dx) + dy.dot(dy)).sqrt() / SQRT_2;
+        *pixel = RGB::from([k.min(1.0); 3]);
+    }
+    out
+}
+
+// The first surface along the ray, and where it's hit
+fn closest<'a>(surfaces: &'a [Surface], ray: &Ray) -> Option<(&'a Surface, Intersection)> {
+    let mut closest = None;
+    let mut t_max = Float::INFINITY;
+    for surface in surfaces {
+        if let Some(isect) = surface.intersect(ray, 0.0, t_max) {
+            t_max = isect.t;
+            closest = Some((surface, isect));
+        }
+    }
+    closest
+}
+
+/// Composite a coverage AOV (such as [`Wireframe`]) over a beauty image,
+/// blending towards `color` by the AOV's coverage (its largest component).
+///
+/// # Panics
+///
+/// Panics if the images have different dimensions.
+pub fn overlay<CS: Copy>(
+    beauty: &Buffer<Color<CS>>,
+    coverage: &Buffer<Color<CS>>,
+    color: Color<CS>,
+) -> Buffer<Color<CS>> {
+    assert_eq!(
+        beauty.dimensions(),
+        coverage.dimensions(),
+        "AOV size mismatch"
+    );
+
+    let mut out = Buffer::new(beauty.width(), beauty.height());
+    for ((out, &base), cov) in out.iter_mut().zip(beauty.iter()).zip(coverage.iter()) {
+        let a = cov.max_component().clamp(0.0, 1.0);
+        *out = base * (1.0 - a) + color * a;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::Point,
+        shape::{Sphere, Triangle},
+    };
+    use rand::prelude::*;
+
+    #[test]
+    fn wireframe_edges() {
+        let surfaces = vec![
+            Surface::from(Triangle::new(
+                [-1.0, -1.0, 0.0],
+                [1.0, -1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            )),
+            Surface::from(Sphere::new([5.0, 0.0, 0.0], 1.0)),
+        ];
+        let wire = Wireframe::new(&surfaces).width(0.01);
+        let ray = |x, y| Ray::new(Point::new(x, y, 5.0), Vector::new(0.0, 0.0, -1.0));
+
+        assert!(wire.is_edge(&ray(0.0, -0.99)));
+        assert!(!wire.is_edge(&ray(0.0, 0.0)));
+        assert!(!wire.is_edge(&ray(5.0, 0.0)));
+        assert!(!wire.is_edge(&ray(-5.0, 0.0)));
+    }
+
+    #[test]
+    fn normals_and_curvature() {
+        let surfaces = vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 1.0))];
+        let normals = Normals::new(&surfaces);
+        let mut rng = StdRng::seed_from_u64(1);
+        let ray = |x, y| Ray::new(Point::new(x, y, 5.0), Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            RGB::from([0.5, 0.5, 1.0]),
+            normals.radiance(&ray(0.0, 0.0), &mut rng)
+        );
+        assert!(normals.radiance(&ray(2.0, 0.0), &mut rng).is_black());
+
+        // Orthographic view of the sphere, one pixel per 0.1 units
+        let mut image = Buffer::new(24, 24);
+        for (x, y, pixel) in image.pixel_iter_mut() {
+            let r = ray(x as Float * 0.1 - 1.15, 1.15 - y as Float * 0.1);
+            *pixel = normals.radiance(&r, &mut rng);
+        }
+        let k = curvature(&image);
+        let at = |x: u32, y: u32| <[Float; 3]>::from(k[(y * 24 + x) as usize])[0];
+        // The normal turns 0.1 radians per pixel along both axes at the
+        // center, and faster towards the rim
+        assert!((at(12, 12) - 0.1).abs() < 0.02, "{}", at(12, 12));
+        assert!(at(20, 12) > 1.3 * at(12, 12));
+        assert_eq!(0.0, at(0, 0));
+    }
+}
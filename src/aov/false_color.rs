@@ -0,0 +1,245 @@
+use crate::{color::RGB, film::Buffer, Float};
+use image::ImageResult;
+use std::path::Path;
+
+/// A perceptually uniform color map, for [`FalseColor`] images.
+///
+/// Both run from dark to light with lightness increasing steadily, so they
+/// read correctly in greyscale and to colorblind viewers, unlike a rainbow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    /// Dark blue through green to yellow.
+    #[default]
+    Viridis,
+    /// Black through red and orange to pale yellow.
+    Inferno,
+}
+
+impl Colormap {
+    /// The color at `t` along the map, clamped to `[0, 1]`.
+    pub fn color(self, t: Float) -> RGB {
+        // Polynomial fits to matplotlib's tables, in sRGB, by Matt Zucker.
+        //
+        // See: <https://www.shadertoy.com/view/WlfXRN>
+        #[rustfmt::skip]
+        const VIRIDIS: [[Float; 3]; 7] = [
+            [ 0.2777273272234177,  0.005407344544966578,  0.3340998053353061],
+            [ 0.1050930431085774,  1.404613529898575,     1.384590162594685],
+            [-0.3308618287255563,  0.214847559468213,     0.09509516302823659],
+            [-4.634230498983486,  -5.799100973351585,   -19.33244095627987],
+            [ 6.228269936347081,  14.17993336680509,     56.69055260068105],
+            [ 4.776384997670288, -13.74514537774601,    -65.35303263337234],
+            [-5.435455855934631,   4.645852612178535,    26.3124352495832],
+        ];
+        #[rustfmt::skip]
+        const INFERNO: [[Float; 3]; 7] = [
+            [  0.0002189403691192265,   0.001651004631001012, -0.01948089843709184],
+            [  0.1065134194856116,      0.5639564367884091,    3.932712388889277],
+            [ 11.60249308247187,       -3.972853965665698,   -15.9423941062914],
+            [-41.70399613139459,       17.43639888205313,     44.35414519872813],
+            [ 77.162935699427,        -33.40235894210092,    -81.80730925738993],
+            [-71.31942824499214,       32.62606426397723,     73.20951985803202],
+            [ 25.13112622477341,      -12.24266895238567,    -23.07032500287172],
+        ];
+
+        let coeffs = match self {
+            Self::Viridis => &VIRIDIS,
+            Self::Inferno => &INFERNO,
+        };
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let srgb = [0, 1, 2].map(|i| {
+            let v = coeffs.iter().rev().fold(0.0, |acc, c| acc * t + c[i]);
+            v.clamp(0.0, 1.0)
+        });
+        RGB::from_srgb(srgb)
+    }
+}
+
+/// How [`FalseColor`] maps values onto its [`Colormap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FalseColorScale {
+    /// Evenly between the low and high ends of the range.
+    #[default]
+    Linear,
+    /// Evenly in the logarithm of the value, for values spanning orders of
+    /// magnitude such as time per pixel. Values that aren't positive get the
+    /// bottom of the map.
+    Log,
+    /// By rank: each value goes as far along the map as the fraction of
+    /// pixels below it, so every color covers about as many pixels. Brings
+    /// out detail wherever values cluster, at the cost of no longer showing
+    /// how far apart they are. Ignores the range.
+    Equalize,
+}
+
+/// Turns a scalar image, such as a depth or variance AOV, into a false-color
+/// image for quick diagnostics.
+///
+/// ```no_run
+/// use gremlin::{aov::{Colormap, FalseColor, FalseColorScale}, film::Buffer, Float};
+///
+/// let depth: Buffer<Float> = Buffer::new(800, 600);
+/// FalseColor::new(Colormap::Inferno)
+///     .scale(FalseColorScale::Equalize)
+///     .save_image(&depth, "depth.png")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FalseColor {
+    map: Colormap,
+    scale: FalseColorScale,
+    range: Option<(Float, Float)>,
+}
+
+impl FalseColor {
+    /// False color with the given map, scaled linearly over the image's range
+    /// of values.
+    pub fn new(map: Colormap) -> Self {
+        Self {
+            map,
+            scale: FalseColorScale::default(),
+            range: None,
+        }
+    }
+
+    /// Set how values map onto the colors.
+    pub fn scale(mut self, scale: FalseColorScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Map `low` and below to the bottom of the colormap, and `high` and
+    /// above to the top. Defaults to the smallest and largest finite values
+    /// in the image (the smallest positive one for [`Log`] scaling), which
+    /// changes from image to image; set it to compare images.
+    ///
+    /// [`Log`]: FalseColorScale::Log
+    pub fn range(mut self, low: Float, high: Float) -> Self {
+        self.range = Some((low, high));
+        self
+    }
+
+    /// Color the image. NaNs get the bottom of the map.
+    pub fn apply(&self, values: &Buffer<Float>) -> Buffer<RGB> {
+        let mut out = Buffer::new(values.width(), values.height());
+        out.set_pixel_aspect(values.pixel_aspect());
+
+        let positions: Vec<Float> = match self.scale {
+            FalseColorScale::Linear => {
+                let (low, high) = self.range_of(values, |_| true);
+                values.iter().map(|&v| (v - low) / (high - low)).collect()
+            }
+            FalseColorScale::Log => {
+                let (low, high) = self.range_of(values, |v| v > 0.0);
+                let (low, high) = (low.ln(), high.ln());
+                values
+                    .iter()
+                    .map(|&v| match v > 0.0 {
+                        true => (v.ln() - low) / (high - low),
+                        false => 0.0,
+                    })
+                    .collect()
+            }
+            FalseColorScale::Equalize => {
+                let mut sorted: Vec<Float> =
+                    values.iter().copied().filter(|v| !v.is_nan()).collect();
+                sorted.sort_by(Float::total_cmp);
+                let n = sorted.len().max(1) as Float;
+                values
+                    .iter()
+                    .map(|&v| {
+                        // Ties share the middle of their ranks
+                        let below = sorted.partition_point(|&s| s < v);
+                        let through = sorted.partition_point(|&s| s <= v);
+                        (below + through) as Float / (2.0 * n)
+                    })
+                    .collect()
+            }
+        };
+        for ((out, &v), t) in out.iter_mut().zip(values.iter()).zip(positions) {
+            // A flat image divides zero by zero; put it mid-map
+            let t = match (v.is_nan(), t.is_nan()) {
+                (false, true) => 0.5,
+                _ => t,
+            };
+            *out = self.map.color(t);
+        }
+        out
+    }
+
+    /// Color the image and save it. The format comes from the extension, as
+    /// for [`Buffer::save_image`].
+    pub fn save_image(&self, values: &Buffer<Float>, path: impl AsRef<Path>) -> ImageResult<()> {
+        self.apply(values).save_image(path)
+    }
+
+    // The set range, or the range of finite values passing `keep`
+    fn range_of(&self, values: &Buffer<Float>, keep: impl Fn(Float) -> bool) -> (Float, Float) {
+        self.range.unwrap_or_else(|| {
+            values
+                .iter()
+                .copied()
+                .filter(|&v| v.is_finite() && keep(v))
+                .fold((Float::INFINITY, Float::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(values: &[Float]) -> Buffer<Float> {
+        let mut buf = Buffer::new(values.len() as u32, 1);
+        buf.copy_from_slice(values);
+        buf
+    }
+
+    #[test]
+    fn colormaps_brighten() {
+        for map in [Colormap::Viridis, Colormap::Inferno] {
+            let lum = |t: Float| {
+                let [r, g, b] = <[Float; 3]>::from(map.color(t));
+                0.2126 * r + 0.7152 * g + 0.0722 * b
+            };
+            for i in 0..10 {
+                let t = i as Float / 10.0;
+                assert!(lum(t + 0.1) > lum(t), "{:?} at {}", map, t);
+            }
+        }
+        let top = RGB::from_srgb([0.993, 0.906, 0.144]);
+        let diff: [Float; 3] = (Colormap::Viridis.color(1.0) + top * -1.0).into();
+        assert!(diff.iter().all(|d| d.abs() < 0.02), "{:?}", diff);
+    }
+
+    #[test]
+    fn scales() {
+        let values = image(&[1.0, 10.0, 100.0, 1000.0, Float::NAN]);
+        let bottom = Colormap::Viridis.color(0.0);
+        let top = Colormap::Viridis.color(1.0);
+
+        let log = FalseColor::default()
+            .scale(FalseColorScale::Log)
+            .apply(&values);
+        let diff: [Float; 3] = (log[1] + Colormap::Viridis.color(1.0 / 3.0) * -1.0).into();
+        assert!(diff.iter().all(|d| d.abs() < 1e-6), "{:?}", diff);
+        assert_eq!((bottom, top, bottom), (log[0], log[3], log[4]));
+
+        let linear = FalseColor::default().range(0.0, 20.0).apply(&values);
+        assert_eq!(Colormap::Viridis.color(0.5), linear[1]);
+        assert_eq!(top, linear[2]);
+
+        // Rank, regardless of the gaps between values
+        let values = image(&[0.0, 1.0, 1.0, 1000.0]);
+        let eq = FalseColor::default()
+            .scale(FalseColorScale::Equalize)
+            .apply(&values);
+        let expected = [0.125, 0.5, 0.5, 0.875].map(|t| Colormap::Viridis.color(t));
+        assert_eq!(&expected[..], &eq[..]);
+
+        let flat = FalseColor::default().apply(&image(&[2.0; 3]));
+        assert_eq!(Colormap::Viridis.color(0.5), flat[0]);
+    }
+}
@@ -1,12 +1,253 @@
-use gremlin::geo::{Point, Vector};
+use gremlin::{
+    aov::{Normals, Wireframe},
+    film::RGBFilm,
+    geo::{Bounds, Unit, Vector},
+    integrator::{Hacky, Toon},
+    metrics::Timer,
+    probe::{ProbeGrid, ProbeVolume},
+    renderer::Renderer,
+    scene::{MemoryBudget, SceneFile},
+    shape::{Bounded, Bvh, CrossCheck},
+    Float,
+};
+use std::process;
+
+const USAGE: &str = "\
+Usage: gremlin [OPTIONS] SCENE
+
+Render a scene file (see `SceneFile` for the format) to an image.
+
+Options:
+  -r, --resolution WxH    image size [default: 800x600]
+      --pixel-aspect R    pixel width/height, for anamorphic output
+                          [default: 1]
+      --overscan N        render N extra pixels beyond each edge, widening
+                          the view to match [default: 0]
+  -s, --spp N             samples per pixel [default: 16]
+  -i, --integrator NAME   `hacky`, `wireframe`, `normals` or `toon`
+                          [default: hacky]
+  -t, --threads N         worker threads [default: one per core]
+  -o, --output PATH       output image [default: out.png]
+      --seed N            render deterministically with this seed
+      --memory-limit MB   fail if the scene would need more memory
+      --check-bvh         check BVH hits against brute force on a grid
+                          of camera rays, and print BVH quality stats,
+                          instead of rendering
+      --probes NxNxN      bake a grid of irradiance probes through the
+                          scene's bounds, each from `--spp` rays, instead
+                          of rendering. Written to `--output`, as JSON if
+                          it ends in `.json` and binary otherwise
+  -h, --help              print this message";
+
+struct Args {
+    scene: String,
+    resolution: (u32, u32),
+    pixel_aspect: Float,
+    overscan: u32,
+    spp: u32,
+    integrator: String,
+    threads: Option<usize>,
+    output: String,
+    seed: Option<u64>,
+    memory_limit: Option<usize>,
+    check_bvh: bool,
+    probes: Option<[u32; 3]>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut scene = None;
+        let mut parsed = Args {
+            scene: String::new(),
+            resolution: (800, 600),
+            pixel_aspect: 1.0,
+            overscan: 0,
+            spp: 16,
+            integrator: "hacky".to_string(),
+            threads: None,
+            output: "out.png".to_string(),
+            seed: None,
+            memory_limit: None,
+            check_bvh: false,
+            probes: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                "-r" | "--resolution" => {
+                    let value = value()?;
+                    parsed.resolution = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|&(w, h)| w > 0 && h > 0)
+                        .ok_or_else(|| format!("invalid resolution `{}`", value))?;
+                }
+                "--pixel-aspect" => {
+                    let value = value()?;
+                    parsed.pixel_aspect = number(&arg, &value)?;
+                    if !(parsed.pixel_aspect > 0.0 && parsed.pixel_aspect.is_finite()) {
+                        return Err(format!("invalid value `{}` for {}", value, arg));
+                    }
+                }
+                "--overscan" => parsed.overscan = number(&arg, &value()?)?,
+                "-s" | "--spp" => parsed.spp = number(&arg, &value()?)?,
+                "-i" | "--integrator" => parsed.integrator = value()?,
+                "-t" | "--threads" => parsed.threads = Some(number(&arg, &value()?)?),
+                "-o" | "--output" => parsed.output = value()?,
+                "--seed" => parsed.seed = Some(number(&arg, &value()?)?),
+                "--memory-limit" => parsed.memory_limit = Some(number(&arg, &value()?)?),
+                "--check-bvh" => parsed.check_bvh = true,
+                "--probes" => {
+                    let value = value()?;
+                    let counts: Vec<u32> =
+                        value.split('x').filter_map(|n| n.parse().ok()).collect();
+                    parsed.probes = match counts[..] {
+                        [x, y, z] if x > 0 && y > 0 && z > 0 => Some([x, y, z]),
+                        _ => return Err(format!("invalid probe counts `{}`", value)),
+                    };
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if scene.is_none() => scene = Some(arg),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+
+        parsed.scene = scene.ok_or("missing scene file")?;
+        Ok(parsed)
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{}` for {}", value, flag))
+}
 
 fn main() {
-    println!("Hello from gremlin!");
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    let mut budget = match args.memory_limit {
+        Some(mb) => MemoryBudget::new(mb.saturating_mul(1 << 20)),
+        None => MemoryBudget::unlimited(),
+    };
+    let file = SceneFile::load_with_budget(&args.scene, &mut budget).unwrap_or_else(|e| {
+        eprintln!("{}: {}", args.scene, e);
+        process::exit(1);
+    });
+    let report = file.validate(&[]);
+    if !report.is_ok() {
+        eprint!("{}: warning: scene has problems\n{}", args.scene, report);
+    }
+
+    if let Some(counts) = args.probes {
+        let clip = file.scene.clip().clone();
+        let (surfaces, _materials) = file.scene.into_parts();
+        let bounds = surfaces
+            .iter()
+            .fold(Bounds::EMPTY, |b, s| b.union(&s.bounds()));
+        if bounds.is_empty() {
+            eprintln!("{}: scene is empty, nowhere to place probes", args.scene);
+            process::exit(1);
+        }
+        let integrator = Hacky {
+            background: file.background,
+            surfaces,
+            clip,
+            ..Default::default()
+        };
+        let timer = Timer::tick();
+        let grid = ProbeGrid::new(bounds, counts);
+        let volume = ProbeVolume::bake(grid, &integrator, args.spp, args.seed.unwrap_or(0));
+        println!("Baked {} probes in {:?}", grid.len(), timer.tock());
+        if let Err(e) = volume.save(&args.output) {
+            eprintln!("{}: {}", args.output, e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let cam = file
+        .camera_builder(args.resolution)
+        .pixel_aspect(args.pixel_aspect)
+        .overscan(args.overscan, args.overscan)
+        .build();
+    let (width, height) = cam.film_dimensions();
+    let mut img = RGBFilm::new(width, height);
+    img.set_pixel_aspect(args.pixel_aspect);
+    if args.check_bvh {
+        let (surfaces, _materials) = file.scene.into_parts();
+        let bvh = Bvh::new(surfaces);
+        print!("{}", bvh.stats());
+        let check = CrossCheck::camera(&bvh, bvh.shapes(), &cam, img.dimensions(), 4, 1e-6);
+        print!("{}", check);
+        process::exit(if check.is_ok() { 0 } else { 1 });
+    }
 
-    let p1 = Point::splat(1.0);
+    let mut renderer = Renderer::new(args.spp);
+    if let Some(seed) = args.seed {
+        renderer = renderer.deterministic(seed);
+    }
+    if let Some(threads) = args.threads {
+        renderer = renderer.with_threads(threads);
+    }
 
-    let v1 = Vector::new(-1.0, -2.0, -3.0);
-    let point_plus_vec = p1 + v1;
+    let timer = Timer::tick();
+    let stats = match args.integrator.as_str() {
+        "hacky" => {
+            let clip = file.scene.clip().clone();
+            let (surfaces, _materials) = file.scene.into_parts();
+            let integrator = Hacky {
+                background: file.background,
+                surfaces,
+                clip,
+                ..Default::default()
+            };
+            renderer.render(&mut img, &cam, &integrator)
+        }
+        "wireframe" => renderer.render(&mut img, &cam, &Wireframe::new(file.scene.surfaces())),
+        "normals" => renderer.render(&mut img, &cam, &Normals::new(file.scene.surfaces())),
+        "toon" => {
+            // A fixed light, from above at an angle
+            let light = Unit::try_from(Vector::new(1.0, 2.0, 1.0)).unwrap();
+            let toon = Toon::new(&file.scene, light).background(file.background);
+            renderer.render(&mut img, &cam, &toon)
+        }
+        other => {
+            eprintln!("error: unknown integrator `{}`\n\n{}", other, USAGE);
+            process::exit(2);
+        }
+    };
+    println!("Rendered {} in {:?}", args.scene, timer.tock());
+    for failed in &stats.failed_tiles {
+        let t = &failed.tile;
+        eprintln!(
+            "warning: tile at ({}, {}) size {}x{} failed: {}",
+            t.x0,
+            t.y0,
+            t.width,
+            t.height,
+            failed.message.as_deref().unwrap_or("unknown panic")
+        );
+    }
 
-    println!("{:?} + {:?} = {:?}", p1, v1, point_plus_vec);
+    let mut metadata = renderer.metadata();
+    metadata
+        .extend(cam.metadata())
+        .insert("gremlin.scene", &args.scene)
+        .insert("gremlin.integrator", &args.integrator);
+    if let Err(e) = img
+        .to_snapshot()
+        .save_image_with_metadata(&args.output, &metadata)
+    {
+        eprintln!("{}: {}", args.output, e);
+        process::exit(1);
+    }
 }
@@ -1,33 +1,40 @@
 use gremlin::{
-    camera::ThinLens,
-    color::RGB,
-    film::RGBFilm,
-    geo::{Point, Ray, Vector},
+    camera::{CameraPath, ThinLens},
+    integrator::Integrator,
+    material::{Lambertian, RayType},
     metrics::{Counter, Timer},
     prelude::*,
-    shape::{Sphere, Surface},
+    renderer::Renderer,
+    scene::Scene,
+    shape::Sphere,
 };
 use rand::prelude::*;
-use rand_distr::UnitSphere;
-use rayon::prelude::*;
 
 static RAY_COUNT: Counter = Counter::new();
 
+// Frames are rendered deterministically, so any range of an animation can be
+// re-rendered to match
+const SEED: u64 = 0x5254_4f57;
+const SPP: u32 = 128;
+
 const WHITE: [Float; 3] = [1.0, 1.0, 1.0];
 const BLUE: [Float; 3] = [0.3, 0.5, 1.0];
 const BLACK: [Float; 3] = [0.0, 0.0, 0.0];
 
-fn ray_color(ray: Ray, surfaces: &impl Shape, depth: usize, rng: &mut impl Rng) -> RGB {
+fn ray_color(ray: &Ray, scene: &Scene, depth: usize, rng: &mut impl Rng) -> RGB {
     RAY_COUNT.inc();
 
-    if let Some(isect) = surfaces.intersect(&ray, 0.001, Float::INFINITY) {
-        if depth < 50 {
-            let rand_vec = Vector::from(UnitSphere.sample(rng));
-            let target = isect.point + isect.norm.into() + rand_vec;
-            let ray = Ray::new(isect.point, target - isect.point);
-            ray_color(ray, surfaces, depth + 1, rng) * 0.5
-        } else {
-            RGB::from(BLACK)
+    if let Some(hit) = scene.intersect(ray, 0.0, Float::INFINITY) {
+        let material = hit.material.for_ray(RayType::from_depth(depth));
+        let wo = Unit::try_from(-ray.direction()).ok();
+        let sample = wo.and_then(|wo| material.sample_f(wo, &hit.isect, rng));
+        match sample {
+            Some(bs) if depth < 50 && bs.pdf > 0.0 => {
+                let cos = Vector::from(bs.wi).dot(hit.isect.norm.into()).abs();
+                let ray = Ray::spawn(hit.isect.point, bs.wi.into(), hit.isect.norm);
+                ray_color(&ray, scene, depth + 1, rng) * bs.f * (cos / bs.pdf)
+            }
+            _ => RGB::from(BLACK),
         }
     } else {
         let dir = ray.direction().normalize();
@@ -36,30 +43,68 @@ fn ray_color(ray: Ray, surfaces: &impl Shape, depth: usize, rng: &mut impl Rng)
     }
 }
 
+struct RayColor<'a>(&'a Scene);
+
+impl Integrator<RGB> for RayColor<'_> {
+    fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> RGB {
+        ray_color(ray, self.0, 0, rng)
+    }
+}
+
+// Usage: `rtow [CAMERA_PATH]`
+//
+// With no arguments, renders a single still. Given a camera path (CSV or
+// JSON, see `CameraPath`), renders every frame in the path instead.
 fn main() {
     let mut img = RGBFilm::new(800, 600);
-    let cam = ThinLens::builder(img.dimensions())
+    let mut template = ThinLens::builder(img.dimensions());
+    template
         .move_to([1.0, 0.5, 1.0])
         .look_at([0.0, 0.0, -1.0])
-        .fov(55.0)
+        .fov(Degrees(55.0))
         .aperture(0.25)
-        .auto_focus()
-        .build();
+        .auto_focus();
 
-    let surfaces: Vec<Surface> = vec![
-        Surface::from(Sphere::new(Point::new(-0.5, 0.0, -1.0), 0.5)),
-        Surface::from(Sphere::new(Point::new(-0.5, 0.0, -2.0), 0.5)),
-        Surface::from(Sphere::new(Point::new(0.5, 0.0, -1.0), 0.5)),
-        Surface::from(Sphere::new(Point::new(0.0, -100.5, -1.0), 100.0)),
-    ];
+    let diffuse = |r, g, b| Lambertian::new(RGB::from([r, g, b]));
+    let mut scene = Scene::default();
+    scene.add_primitive(
+        Sphere::new(Point::new(-0.5, 0.0, -1.0), 0.5),
+        diffuse(0.7, 0.3, 0.3),
+    );
+    scene.add_primitive(
+        Sphere::new(Point::new(-0.5, 0.0, -2.0), 0.5),
+        diffuse(0.5, 0.5, 0.5),
+    );
+    scene.add_primitive(
+        Sphere::new(Point::new(0.5, 0.0, -1.0), 0.5),
+        diffuse(0.3, 0.4, 0.7),
+    );
+    scene.add_primitive(
+        Sphere::new(Point::new(0.0, -100.5, -1.0), 100.0),
+        diffuse(0.8, 0.8, 0.0),
+    );
 
     let timer = Timer::tick();
-    for _ in 0..128 {
-        img.par_pixel_iter_mut()
-            .for_each_init(rand::thread_rng, |rng, (px, py, pixel)| {
-                let ray = cam.ray(px, py, rng);
-                pixel.add_sample(ray_color(ray, &surfaces, 0, rng));
+    match std::env::args().nth(1) {
+        Some(file) => {
+            let path = CameraPath::load(&file).unwrap_or_else(|e| {
+                eprintln!("{}: {}", file, e);
+                std::process::exit(1);
             });
+            for frame in path.frame_range() {
+                img = RGBFilm::new(800, 600);
+                let renderer = Renderer::new(SPP).deterministic(SEED).frame(frame as u64);
+                renderer.render(&mut img, &path.camera(frame, &template), &RayColor(&scene));
+                img.to_snapshot()
+                    .save_image(format!("rtow-{:04}.png", frame))
+                    .unwrap();
+            }
+        }
+        None => {
+            let renderer = Renderer::new(SPP).deterministic(SEED);
+            renderer.render(&mut img, &template.build(), &RayColor(&scene));
+            img.to_snapshot().save_image("rtow-thinlens.png").unwrap();
+        }
     }
 
     println!("Traced {} rays in {:?}", RAY_COUNT.get(), timer.tock());
@@ -67,6 +112,4 @@ fn main() {
         "{} Rays/Sec",
         RAY_COUNT.get() as f64 / timer.tock().as_secs_f64()
     );
-
-    img.to_snapshot().save_image("rtow-thinlens.png").unwrap();
 }
@@ -19,32 +19,125 @@
 //! ```
 
 use crate::{
-    geo::{Matrix, Point, Ray, Vector},
+    film::{Buffer, Metadata},
+    geo::{
+        CoordinateSystem, Degrees, Length, Matrix, Point, Radians, Ray, RayDifferential, Unit,
+        Vector,
+    },
+    sampler::{SampleDimension, Sampler},
     Float,
 };
 use rand::prelude::*;
 use rand_distr::UnitDisc;
+use std::ops::MulAssign;
+
+mod path;
+pub use path::*;
 
 const DEFAULT_LOOK_FROM: Point = Point::new(0.0, 0.0, -1.0);
 const DEFAULT_LOOK_AT: Point = Point::ORIGIN;
-const DEFAULT_FOV: Float = 75.0;
+const DEFAULT_FOV: Degrees = Degrees(75.0);
+
+const PI: Float = std::f64::consts::PI as Float;
 
 /// The core trait for objects which generate rays.
 pub trait Camera: Send + Sync {
     /// Generate a ray for the pixel at coordinates `(px, py)`.
     fn ray(&self, px: u32, py: u32, rng: &mut impl Rng) -> Ray;
+
+    /// Generate a ray for the pixel at coordinates `(px, py)`, with the
+    /// position in the pixel and on the lens drawn from the sampler's
+    /// [`SampleDimension::Pixel`] and [`SampleDimension::Lens`] dimensions.
+    ///
+    /// The sampler must have been started on this pixel sample. `rng` is
+    /// left for any further decisions the camera makes. By default the
+    /// sampler is ignored and this is just [`ray`].
+    ///
+    /// [`ray`]: Self::ray
+    fn ray_sampled(&self, px: u32, py: u32, sampler: &mut impl Sampler, rng: &mut impl Rng) -> Ray {
+        let _ = sampler;
+        self.ray(px, py, rng)
+    }
+
+    /// Generate a ray with differentials for the pixel at coordinates
+    /// `(px, py)`.
+    ///
+    /// The main ray must be the one [`ray`] would generate from the same
+    /// random numbers. By default there are no differentials.
+    ///
+    /// [`ray`]: Self::ray
+    fn ray_differential(&self, px: u32, py: u32, rng: &mut impl Rng) -> RayDifferential {
+        RayDifferential::new(self.ray(px, py, rng))
+    }
+
+    /// Sample a direction from `point` towards the camera, and the pixel
+    /// light arriving along it would land in. This is how paths traced from
+    /// the lights reach the film.
+    ///
+    /// Returns `None` if the point can't be seen by the camera. By default
+    /// no point can, for cameras that only generate rays.
+    fn sample_importance(&self, point: Point, rng: &mut impl Rng) -> Option<ImportanceSample> {
+        let _ = (point, rng);
+        None
+    }
+}
+
+/// A direction from a point in the scene towards the camera, sampled by
+/// [`Camera::sample_importance`].
+///
+/// The camera's response to light is its importance. It's normalized per
+/// pixel, so splatting `L * importance * |cos| / pdf` for each of `n` paths
+/// traced from the lights and dividing by `n` gives each pixel the same
+/// value rendering from the camera would.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportanceSample {
+    /// The direction from the point towards the lens.
+    pub wi: Unit,
+    /// The importance of light arriving at the lens along `-wi`.
+    pub importance: Float,
+    /// The solid-angle density with which `wi` was chosen, or `1` for
+    /// pinhole cameras.
+    pub pdf: Float,
+    /// The distance to the lens along `wi`, for shadow rays.
+    pub dist: Float,
+    /// The continuous raster position the light lands at.
+    pub raster: (Float, Float),
 }
 
 /// An idealized thin-lens camera.
+///
+/// Beyond the ideal lens, it can model a few flaws of real ones, all off by
+/// default:
+///
+/// - Radial [`distortion`], bending straight lines near the edges of the
+///   frame.
+/// - An aperture with straight [`blades`], giving polygonal bokeh rather
+///   than perfect discs.
+/// - [`cat_eye`] vignetting, where the lens barrel cuts off part of the
+///   aperture for points away from the center of the frame. Out-of-focus
+///   highlights there take on a cat's-eye shape, and the corners of the
+///   frame darken. Rays only sample the uncut part of the aperture, so the
+///   darkening is applied afterwards, with [`vignette`].
+///
+/// [`distortion`]: ThinLensBuilder::distortion
+/// [`blades`]: ThinLensBuilder::blades
+/// [`cat_eye`]: ThinLensBuilder::cat_eye
+/// [`vignette`]: Self::vignette
 #[derive(Debug, Clone)]
 pub struct ThinLens {
     resolution_width: Float,
     resolution_height: Float,
     aspect_ratio: Float,
+    pixel_aspect: Float,
+    overscan: (u32, u32),
     tan_half_fov: Float,
     focus_distance: Float,
     half_aperture: Float,
+    distortion: [Float; 2],
+    blades: u32,
+    cat_eye: Float,
     cam_to_world: Matrix,
+    world_to_cam: Matrix,
 }
 
 impl ThinLens {
@@ -54,43 +147,393 @@ impl ThinLens {
     pub fn builder((width, height): (u32, u32)) -> ThinLensBuilder {
         ThinLensBuilder::new(width, height)
     }
+
+    /// The pixel aspect ratio (the `width`/`height` of a single pixel).
+    pub fn pixel_aspect(&self) -> Float {
+        self.pixel_aspect
+    }
+
+    /// The overscan margin, in pixels, added on the left and right, and on
+    /// the top and bottom.
+    pub fn overscan(&self) -> (u32, u32) {
+        self.overscan
+    }
+
+    /// The camera's settings, for embedding in saved images. Keys are
+    /// prefixed with `gremlin.camera.`.
+    pub fn metadata(&self) -> Metadata {
+        let eye = self.cam_to_world * Point::ORIGIN;
+        let forward = self.cam_to_world * Vector::new(0.0, 0.0, -1.0);
+        let mut metadata = Metadata::new();
+        metadata
+            .insert("gremlin.camera.model", "thin lens")
+            .insert(
+                "gremlin.camera.position",
+                format!("{} {} {}", eye.x, eye.y, eye.z),
+            )
+            .insert(
+                "gremlin.camera.direction",
+                format!("{} {} {}", forward.x, forward.y, forward.z),
+            )
+            .insert(
+                "gremlin.camera.fov",
+                2.0 * self.tan_half_fov.atan().to_degrees(),
+            )
+            .insert("gremlin.camera.aperture", 2.0 * self.half_aperture)
+            .insert("gremlin.camera.focus_distance", self.focus_distance)
+            .insert(
+                "gremlin.camera.resolution",
+                format!("{}x{}", self.resolution_width, self.resolution_height),
+            )
+            .insert("gremlin.camera.pixel_aspect", self.pixel_aspect);
+        if self.overscan != (0, 0) {
+            metadata.insert(
+                "gremlin.camera.overscan",
+                format!("{} {}", self.overscan.0, self.overscan.1),
+            );
+        }
+        if self.distortion != [0.0; 2] {
+            metadata.insert(
+                "gremlin.camera.distortion",
+                format!("{} {}", self.distortion[0], self.distortion[1]),
+            );
+        }
+        if self.blades != 0 {
+            metadata.insert("gremlin.camera.blades", self.blades);
+        }
+        if self.cat_eye != 0.0 {
+            metadata.insert("gremlin.camera.cat_eye", self.cat_eye);
+        }
+        metadata
+    }
+
+    /// The fraction of the aperture the lens barrel leaves open at the
+    /// continuous raster position `(fx, fy)`: `1` unless the camera has
+    /// [`cat_eye`] vignetting. Exact for round apertures, and close for
+    /// bladed ones.
+    ///
+    /// [`cat_eye`]: ThinLensBuilder::cat_eye
+    pub fn vignetting(&self, fx: Float, fy: Float) -> Float {
+        if self.cat_eye == 0.0 || self.half_aperture == 0.0 {
+            return 1.0;
+        }
+        // The overlap of two unit circles a distance `d` apart
+        let [x, y] = self.barrel_center(self.screen_point(fx, fy));
+        let d = (x * x + y * y).sqrt().min(2.0);
+        let overlap = 2.0 * (d / 2.0).acos() - d / 2.0 * (4.0 - d * d).sqrt();
+        overlap / PI
+    }
+
+    /// Darken an image rendered with this camera by the [`vignetting`] at
+    /// each pixel's center.
+    ///
+    /// [`vignetting`]: Self::vignetting
+    pub fn vignette<P>(&self, buf: &mut Buffer<P>)
+    where
+        P: MulAssign<Float>,
+    {
+        for (px, py, p) in buf.pixel_iter_mut() {
+            *p *= self.vignetting(px as Float + 0.5, py as Float + 0.5);
+        }
+    }
+
+    /// The dimensions of the film to render into: the resolution the camera
+    /// was built with, plus the [`overscan`] margin on every side.
+    ///
+    /// [`overscan`]: Self::overscan
+    pub fn film_dimensions(&self) -> (u32, u32) {
+        (
+            self.resolution_width as u32 + 2 * self.overscan.0,
+            self.resolution_height as u32 + 2 * self.overscan.1,
+        )
+    }
 }
 
-impl Camera for ThinLens {
-    fn ray(&self, px: u32, py: u32, rng: &mut impl Rng) -> Ray {
-        // Pick a random point in pixel and convert to NDC space
-        let u = ((px as Float) + rng.gen::<Float>()) / self.resolution_width;
-        let v = ((py as Float) + rng.gen::<Float>()) / self.resolution_height;
-
-        // Express that "random point in the pixel"'s location in screen space
-        let screen_pt = Vector {
-            x: (2.0 * u - 1.0) * self.aspect_ratio * self.tan_half_fov,
-            y: (1.0 - 2.0 * v) * self.tan_half_fov,
-            z: -1.0,
-        };
+impl ThinLens {
+    // Generate a camera-space ray through the continuous raster position
+    // `(fx, fy)`, leaving the lens at `lens` (in units of the aperture radius)
+    fn camera_ray(&self, fx: Float, fy: Float, lens: [Float; 2]) -> Ray {
+        let [x, y] = self.screen_point(fx, fy);
+        let screen_pt = Vector { x, y, z: -1.0 };
 
         // Project it into the focal plane. Since our camera origin is at
         // the coordinate space origin, this is simply scaling by the focal
         // distance
         let focal_pt = screen_pt * self.focus_distance;
 
-        // The ray originates from a random point in the unit disk, centered at
-        // the origin and scaled by the aperture size
-        let rand_in_disc: [Float; 2] = UnitDisc.sample(rng);
-        let origin_pt = Vector::new(rand_in_disc[0], rand_in_disc[1], 0.0) * self.half_aperture;
+        // The ray originates from the lens sample, scaled by the aperture size
+        let origin_pt = Vector::new(lens[0], lens[1], 0.0) * self.half_aperture;
+
+        Ray::new(origin_pt.into(), focal_pt - origin_pt)
+    }
+
+    // Where the continuous raster position `(fx, fy)` looks, on the z = -1
+    // plane of camera space
+    fn screen_point(&self, fx: Float, fy: Float) -> [Float; 2] {
+        // Convert the raster position to NDC space. The overscan margin
+        // falls outside `[0, 1]`.
+        let u = (fx - self.overscan.0 as Float) / self.resolution_width;
+        let v = (fy - self.overscan.1 as Float) / self.resolution_height;
+
+        // Express that point's location in screen space
+        let x = (2.0 * u - 1.0) * self.aspect_ratio * self.tan_half_fov;
+        let y = (1.0 - 2.0 * v) * self.tan_half_fov;
+        let g = self.distortion_scale(self.field_radius([x, y]));
+        [x * g, y * g]
+    }
+
+    // The inverse of `screen_point`
+    fn raster_point(&self, [x, y]: [Float; 2]) -> (Float, Float) {
+        let g = self.undistortion_scale(self.field_radius([x, y]));
+        let (x, y) = (x * g, y * g);
+        let u = (x / (self.aspect_ratio * self.tan_half_fov) + 1.0) / 2.0;
+        let v = (1.0 - y / self.tan_half_fov) / 2.0;
+        (
+            u * self.resolution_width + self.overscan.0 as Float,
+            v * self.resolution_height + self.overscan.1 as Float,
+        )
+    }
+
+    // Distance of a screen point from the center, as a fraction of the
+    // distance to the corners
+    #[inline]
+    fn field_radius(&self, [x, y]: [Float; 2]) -> Float {
+        let corner = self.tan_half_fov * (self.aspect_ratio * self.aspect_ratio + 1.0).sqrt();
+        (x * x + y * y).sqrt() / corner
+    }
+
+    // How much the distortion scales screen points `r` out from the center
+    #[inline]
+    fn distortion_scale(&self, r: Float) -> Float {
+        let [k1, k2] = self.distortion;
+        let r2 = r * r;
+        1.0 + k1 * r2 + k2 * r2 * r2
+    }
+
+    // How much to scale a distorted screen point `r` out from the center to
+    // undo the distortion. Solves `r' * scale(r') = r` by Newton's method.
+    fn undistortion_scale(&self, r: Float) -> Float {
+        if self.distortion == [0.0; 2] || r == 0.0 {
+            return 1.0;
+        }
+        let [k1, k2] = self.distortion;
+        let mut x = r;
+        for _ in 0..8 {
+            let x2 = x * x;
+            let f = x * self.distortion_scale(x) - r;
+            let df = 1.0 + 3.0 * k1 * x2 + 5.0 * k2 * x2 * x2;
+            x -= f / df;
+        }
+        x / r
+    }
+
+    // How much the distortion stretches areas at the distorted screen point
+    fn distortion_stretch(&self, screen: [Float; 2]) -> Float {
+        if self.distortion == [0.0; 2] {
+            return 1.0;
+        }
+        let [k1, k2] = self.distortion;
+        let r = self.field_radius(screen) * self.undistortion_scale(self.field_radius(screen));
+        let r2 = r * r;
+        self.distortion_scale(r) * (1.0 + 3.0 * k1 * r2 + 5.0 * k2 * r2 * r2)
+    }
+
+    // Center of the lens barrel's opening as seen from the screen point, in
+    // units of the aperture radius
+    #[inline]
+    fn barrel_center(&self, screen: [Float; 2]) -> [Float; 2] {
+        let corner = self.tan_half_fov * (self.aspect_ratio * self.aspect_ratio + 1.0).sqrt();
+        screen.map(|c| c / corner * self.cat_eye)
+    }
+
+    // Whether the barrel lets through light from the lens point to the
+    // screen point
+    #[inline]
+    fn unblocked(&self, screen: [Float; 2], lens: [Float; 2]) -> bool {
+        let [cx, cy] = self.barrel_center(screen);
+        let (dx, dy) = (lens[0] - cx, lens[1] - cy);
+        dx * dx + dy * dy <= 1.0
+    }
+
+    // Pick a point uniformly on the aperture, in units of its radius
+    fn sample_aperture(&self, rng: &mut impl Rng) -> [Float; 2] {
+        if self.blades < 3 {
+            return UnitDisc.sample(rng);
+        }
+        // Pick a blade's triangle, then a point in it. The first corner
+        // points straight up.
+        let n = self.blades as Float;
+        let blade = (rng.gen::<Float>() * n).floor().min(n - 1.0);
+        let corner = |i: Float| {
+            let angle = PI / 2.0 + 2.0 * PI * i / n;
+            [angle.cos(), angle.sin()]
+        };
+        let (a, b) = (corner(blade), corner(blade + 1.0));
+        let (s, t): (Float, Float) = (rng.gen::<Float>().sqrt(), rng.gen());
+        [0, 1].map(|i| s * ((1.0 - t) * a[i] + t * b[i]))
+    }
 
-        // This is our final ray, in camera space
-        let ray = Ray::new(origin_pt.into(), focal_pt - origin_pt);
+    // The aperture's area, in units of its radius squared
+    fn aperture_area(&self) -> Float {
+        match self.blades {
+            0..=2 => PI,
+            n => n as Float / 2.0 * (2.0 * PI / n as Float).sin(),
+        }
+    }
 
-        // The is our ray in world space
-        self.cam_to_world * ray
+    // Map a point in the unit square onto the aperture, in units of its
+    // radius, keeping stratification: Shirley and Chiu's concentric mapping
+    // for a disc, and for blades, `u[0]` picks the blade's triangle and is
+    // reused for the point in it
+    fn aperture_point(&self, u: [Float; 2]) -> [Float; 2] {
+        if self.blades < 3 {
+            let [a, b] = u.map(|v| 2.0 * v - 1.0);
+            let (r, theta) = match (a, b) {
+                (a, b) if a == 0.0 && b == 0.0 => return [0.0; 2],
+                (a, b) if a.abs() > b.abs() => (a, PI / 4.0 * (b / a)),
+                (a, b) => (b, PI / 2.0 - PI / 4.0 * (a / b)),
+            };
+            return [r * theta.cos(), r * theta.sin()];
+        }
+        let n = self.blades as Float;
+        let blade = (u[0] * n).floor().min(n - 1.0);
+        let corner = |i: Float| {
+            let angle = PI / 2.0 + 2.0 * PI * i / n;
+            [angle.cos(), angle.sin()]
+        };
+        let (a, b) = (corner(blade), corner(blade + 1.0));
+        let (s, t) = ((u[0] * n - blade).min(1.0).sqrt(), u[1]);
+        [0, 1].map(|i| s * ((1.0 - t) * a[i] + t * b[i]))
+    }
+
+    // Pick a random point in the pixel, and on the part of the lens the
+    // barrel leaves open for it
+    fn sample(&self, px: u32, py: u32, rng: &mut impl Rng) -> (Float, Float, [Float; 2]) {
+        let fx = (px as Float) + rng.gen::<Float>();
+        let fy = (py as Float) + rng.gen::<Float>();
+        let lens = self.sample_aperture(rng);
+        self.open_lens(fx, fy, lens, rng)
+    }
+
+    // As `sample`, but with the first choice of points from the sampler
+    fn sample_with(
+        &self,
+        px: u32,
+        py: u32,
+        sampler: &mut impl Sampler,
+        rng: &mut impl Rng,
+    ) -> (Float, Float, [Float; 2]) {
+        let [u, v] = sampler.get_2d(SampleDimension::Pixel);
+        let lens = self.aperture_point(sampler.get_2d(SampleDimension::Lens));
+        self.open_lens(px as Float + u, py as Float + v, lens, rng)
+    }
+
+    // Keep the lens point if the barrel leaves it open from the raster
+    // point, or pick random ones until one is
+    fn open_lens(
+        &self,
+        fx: Float,
+        fy: Float,
+        mut lens: [Float; 2],
+        rng: &mut impl Rng,
+    ) -> (Float, Float, [Float; 2]) {
+        if self.cat_eye != 0.0 && self.half_aperture > 0.0 {
+            let screen = self.screen_point(fx, fy);
+            for _ in 0..64 {
+                if self.unblocked(screen, lens) {
+                    break;
+                }
+                lens = self.sample_aperture(rng);
+            }
+        }
+        (fx, fy, lens)
+    }
+}
+
+impl Camera for ThinLens {
+    fn ray(&self, px: u32, py: u32, rng: &mut impl Rng) -> Ray {
+        let (fx, fy, lens) = self.sample(px, py, rng);
+        self.cam_to_world * self.camera_ray(fx, fy, lens)
+    }
+
+    fn ray_sampled(&self, px: u32, py: u32, sampler: &mut impl Sampler, rng: &mut impl Rng) -> Ray {
+        let (fx, fy, lens) = self.sample_with(px, py, sampler, rng);
+        self.cam_to_world * self.camera_ray(fx, fy, lens)
+    }
+
+    /// Differential rays pass through the focal plane one pixel over, so
+    /// footprints shrink to a pixel's width at the focus distance. They also
+    /// leave the lens one aperture diameter over, since a single sample
+    /// stands for the whole lens, so away from the focus distance footprints
+    /// grow like the circle of confusion. The lens offsets are chosen so the
+    /// two footprint axes stay perpendicular and never collapse.
+    fn ray_differential(&self, px: u32, py: u32, rng: &mut impl Rng) -> RayDifferential {
+        let (fx, fy, lens) = self.sample(px, py, rng);
+        RayDifferential::with_differentials(
+            self.cam_to_world * self.camera_ray(fx, fy, lens),
+            self.cam_to_world * self.camera_ray(fx + 1.0, fy, [lens[0], lens[1] + 2.0]),
+            self.cam_to_world * self.camera_ray(fx, fy + 1.0, [lens[0] + 2.0, lens[1]]),
+        )
+    }
+
+    /// Picks a point on the lens uniformly, and follows the ray from it
+    /// through `point` back to the focal plane to find the pixel. The lens
+    /// counts as having unit area when the aperture is closed.
+    fn sample_importance(&self, point: Point, rng: &mut impl Rng) -> Option<ImportanceSample> {
+        // Work in camera space, where the camera looks down -z
+        let lens = self.sample_aperture(rng);
+        let origin = Vector::new(lens[0], lens[1], 0.0) * self.half_aperture;
+        let d = Vector::from(self.world_to_cam * point) - origin;
+        if d.z >= 0.0 {
+            return None;
+        }
+
+        // Where the ray crosses the focal plane, scaled back to the z = -1
+        // plane that `camera_ray` starts from
+        let focal = origin + d * (self.focus_distance / -d.z);
+        let screen = [focal.x, focal.y].map(|c| c / self.focus_distance);
+        let (fx, fy) = self.raster_point(screen);
+        let (width, height) = self.film_dimensions();
+        if !(0.0..width as Float).contains(&fx) || !(0.0..height as Float).contains(&fy) {
+            return None;
+        }
+        // Light the barrel blocks is left out of the vignetting, which is
+        // applied afterwards, so it's made up for by the light let through
+        let open = match self.cat_eye != 0.0 && self.half_aperture > 0.0 {
+            true if !self.unblocked(screen, lens) => return None,
+            true => self.vignetting(fx, fy),
+            false => 1.0,
+        };
+        if open <= 0.0 {
+            return None;
+        }
+
+        let dist = d.len();
+        let cos = -d.z / dist;
+        let lens_area = match self.half_aperture > 0.0 {
+            true => self.aperture_area() * self.half_aperture * self.half_aperture,
+            false => 1.0,
+        };
+        // A pixel's area on the z = -1 plane
+        let pixel_area = (2.0 * self.aspect_ratio * self.tan_half_fov / self.resolution_width)
+            * (2.0 * self.tan_half_fov / self.resolution_height)
+            * self.distortion_stretch(screen);
+        Some(ImportanceSample {
+            wi: Unit::try_from(self.cam_to_world * -d).ok()?,
+            importance: 1.0 / (pixel_area * lens_area * cos.powi(4) * open),
+            pdf: dist * dist / (cos * lens_area),
+            dist,
+            raster: (fx, fy),
+        })
     }
 }
 
 /// Builder for creating [`ThinLens`] camera instances.
+#[derive(Debug, Clone)]
 pub struct ThinLensBuilder {
     look_from: Point,
     look_at: Point,
+    coords: CoordinateSystem,
     inner: ThinLens,
 }
 
@@ -108,14 +551,21 @@ impl ThinLensBuilder {
         let mut builder = Self {
             look_from: DEFAULT_LOOK_FROM,
             look_at: DEFAULT_LOOK_AT,
+            coords: CoordinateSystem::NATIVE,
             inner: ThinLens {
                 resolution_width,
                 resolution_height,
                 aspect_ratio,
+                pixel_aspect: 1.0,
+                overscan: (0, 0),
                 half_aperture: 0.0,
+                distortion: [0.0; 2],
+                blades: 0,
+                cat_eye: 0.0,
                 focus_distance: 1.0,
                 tan_half_fov: 0.5,              // temporary!
                 cam_to_world: Matrix::IDENTITY, // temporary!
+                world_to_cam: Matrix::IDENTITY, // temporary!
             },
         };
 
@@ -125,6 +575,12 @@ impl ThinLensBuilder {
     }
 
     /// Move the camera to a new location.
+    ///
+    /// The camera can't look at its own position, or straight up or down,
+    /// so while it would, it keeps its previous placement. This lets
+    /// `move_to` and [`look_at`] be called in either order.
+    ///
+    /// [`look_at`]: Self::look_at
     pub fn move_to(&mut self, eye: impl Into<Point>) -> &mut Self {
         self.look_from = eye.into();
         self.recalculate_look_matrix();
@@ -132,27 +588,110 @@ impl ThinLensBuilder {
     }
 
     /// Point the camera at a new location.
+    ///
+    /// See [`move_to`](Self::move_to) for the placements that are ignored.
     pub fn look_at(&mut self, target: impl Into<Point>) -> &mut Self {
         self.look_at = target.into();
         self.recalculate_look_matrix();
         self
     }
 
-    /// Set the field-of-view, in degrees.
-    pub fn fov(&mut self, fov: Float) -> &mut Self {
-        self.inner.tan_half_fov = (fov / 2.0).to_radians().tan();
+    /// Set the coordinate system that [`move_to`] and [`look_at`] positions
+    /// are given in. Defaults to [`CoordinateSystem::NATIVE`].
+    ///
+    /// [`move_to`]: Self::move_to
+    /// [`look_at`]: Self::look_at
+    pub fn coordinate_system(&mut self, coords: CoordinateSystem) -> &mut Self {
+        self.coords = coords;
+        self.recalculate_look_matrix();
+        self
+    }
+
+    /// Set the field-of-view.
+    pub fn fov(&mut self, fov: impl Into<Radians>) -> &mut Self {
+        self.inner.tan_half_fov = (fov.into() / 2.0).tan();
+        self
+    }
+
+    /// Set the pixel aspect ratio (the `width`/`height` of a single pixel).
+    /// Defaults to `1`, square pixels.
+    ///
+    /// Pixels wider than they are tall cover more of the scene horizontally,
+    /// for anamorphic formats that get stretched back out on display. The
+    /// vertical field of view stays the same.
+    pub fn pixel_aspect(&mut self, pixel_aspect: Float) -> &mut Self {
+        let inner = &mut self.inner;
+        inner.pixel_aspect = pixel_aspect;
+        inner.aspect_ratio = inner.resolution_width * pixel_aspect / inner.resolution_height;
         self
     }
 
-    /// Set the aperture.
-    pub fn aperture(&mut self, aperture: Float) -> &mut Self {
-        self.inner.half_aperture = aperture * 0.5;
+    /// Render a margin of `x` extra pixels on the left and right, and `y` on
+    /// the top and bottom, beyond the resolution. Defaults to none.
+    ///
+    /// The frustum widens to match, so the pixels inside the margin see
+    /// exactly what they would without it; the margin gives stabilization
+    /// and reframing done afterwards room to move the frame around. The film
+    /// must be [`film_dimensions`] in size, and [`Buffer::crop`] recovers
+    /// the unpadded image.
+    ///
+    /// [`film_dimensions`]: ThinLens::film_dimensions
+    /// [`Buffer::crop`]: crate::film::Buffer::crop
+    pub fn overscan(&mut self, x: u32, y: u32) -> &mut Self {
+        self.inner.overscan = (x, y);
         self
     }
 
-    /// Set the focal length.
-    pub fn focal_length(&mut self, len: Float) -> &mut Self {
-        self.inner.focus_distance = len;
+    /// Set the aperture's diameter. Bare numbers are in world units.
+    pub fn aperture(&mut self, aperture: impl Into<Length>) -> &mut Self {
+        self.inner.half_aperture = aperture.into().as_meters() * 0.5;
+        self
+    }
+
+    /// Set the radial distortion coefficients `k1` and `k2`. Defaults to
+    /// none.
+    ///
+    /// A point `r` out from the center of the frame, with `r = 1` at the
+    /// corners, looks `r * (1 + k1 r² + k2 r⁴)` out instead. Positive
+    /// coefficients widen the view at the edges, squeezing the scene there
+    /// for barrel distortion; negative ones give pincushion distortion. Keep
+    /// them small enough that this keeps growing out to the corners.
+    pub fn distortion(&mut self, k1: Float, k2: Float) -> &mut Self {
+        self.inner.distortion = [k1, k2];
+        self
+    }
+
+    /// Set the number of straight blades forming the aperture, giving
+    /// polygonal bokeh, with a corner at the top. Fewer than `3` means a
+    /// round aperture, the default.
+    ///
+    /// The aperture's corners stay on the circle its diameter gives, so it
+    /// lets through a little less light than a round one.
+    pub fn blades(&mut self, blades: u32) -> &mut Self {
+        self.inner.blades = blades;
+        self
+    }
+
+    /// Set the strength of cat-eye vignetting. Defaults to `0`, none.
+    ///
+    /// The lens barrel's opening is modeled as a circle the size of the
+    /// aperture, which shifts off-center as points move away from the
+    /// center of the frame, reaching `strength` aperture radii at the
+    /// corners. Only the overlap lets light through. Values around `0.5`
+    /// to `1` are typical of fast lenses wide open; `2` or more closes the
+    /// corners off entirely.
+    ///
+    /// Only has an effect with a nonzero [`aperture`].
+    ///
+    /// [`aperture`]: Self::aperture
+    pub fn cat_eye(&mut self, strength: Float) -> &mut Self {
+        self.inner.cat_eye = strength.max(0.0);
+        self
+    }
+
+    /// Set the focal length. Bare numbers are in world units.
+    pub fn focal_length(&mut self, len: impl Into<Length>) -> &mut Self {
+        self.inner.focus_distance = len.into().as_meters();
         self
     }
 
@@ -160,7 +699,9 @@ impl ThinLensBuilder {
     ///
     /// [`look_at`]: Self::look_at
     pub fn auto_focus(&mut self) -> &mut Self {
-        self.inner.focus_distance = (self.look_at - self.look_from).len();
+        let from = self.coords.convert_point(self.look_from);
+        let to = self.coords.convert_point(self.look_at);
+        self.inner.focus_distance = (to - from).len();
         self
     }
 
@@ -170,6 +711,278 @@ impl ThinLensBuilder {
     }
 
     fn recalculate_look_matrix(&mut self) {
-        self.inner.cam_to_world = Matrix::look_at(self.look_from, self.look_at, Vector::Y_AXIS);
+        let from = self.coords.convert_point(self.look_from);
+        let to = self.coords.convert_point(self.look_at);
+        if Unit::try_from(Vector::Y_AXIS.cross(from - to)).is_err() {
+            return;
+        }
+        self.inner.cam_to_world = Matrix::look_at(from, to, Vector::Y_AXIS);
+        self.inner.world_to_cam = self
+            .inner
+            .cam_to_world
+            .inverse()
+            .unwrap_or(Matrix::IDENTITY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn pixel_aspect() {
+        let mut builder = ThinLens::builder((100, 100));
+        builder.fov(Degrees(90.0));
+        let square = builder.build();
+        let anamorphic = builder.pixel_aspect(2.0).build();
+        assert_eq!(2.0, anamorphic.pixel_aspect());
+
+        // The right edge is twice as far out, the top edge stays put
+        let right = |cam: &ThinLens| cam.camera_ray(100.0, 50.0, [0.0; 2]).direction;
+        let top = |cam: &ThinLens| cam.camera_ray(50.0, 0.0, [0.0; 2]).direction;
+        assert_relative_eq!(2.0 * right(&square).x, right(&anamorphic).x);
+        assert_eq!(top(&square), top(&anamorphic));
+    }
+
+    #[test]
+    fn overscan() {
+        let mut builder = ThinLens::builder((100, 50));
+        let plain = builder.build();
+        let padded = builder.overscan(10, 5).build();
+        assert_eq!((120, 60), padded.film_dimensions());
+
+        // The same view inside the margin, and wider outside it
+        let dir = |cam: &ThinLens, fx, fy| cam.camera_ray(fx, fy, [0.0; 2]).direction;
+        assert_eq!(dir(&plain, 0.0, 0.0), dir(&padded, 10.0, 5.0));
+        assert_eq!(dir(&plain, 100.0, 50.0), dir(&padded, 110.0, 55.0));
+        assert!(dir(&padded, 0.0, 0.0).x < dir(&plain, 0.0, 0.0).x);
+    }
+
+    #[test]
+    fn metadata() {
+        let cam = ThinLens::builder((64, 48))
+            .move_to([1.0, 2.0, 3.0])
+            .fov(Degrees(60.0))
+            .aperture(0.5)
+            .build();
+        let metadata = cam.metadata();
+        assert_eq!(Some("1 2 3"), metadata.get("gremlin.camera.position"));
+        assert_eq!(Some("64x48"), metadata.get("gremlin.camera.resolution"));
+        assert_eq!(Some("0.5"), metadata.get("gremlin.camera.aperture"));
+        let fov: Float = metadata.get("gremlin.camera.fov").unwrap().parse().unwrap();
+        assert_relative_eq!(60.0, fov, epsilon = 1e-4);
+
+        // Lengths in other units are converted to world units
+        let metadata = ThinLens::builder((64, 48))
+            .aperture(Length::millimeters(500.0))
+            .focal_length(Length::feet(10.0))
+            .build()
+            .metadata();
+        let get = |key| -> Float { metadata.get(key).unwrap().parse().unwrap() };
+        assert_relative_eq!(0.5, get("gremlin.camera.aperture"), epsilon = 1e-6);
+        assert_relative_eq!(3.048, get("gremlin.camera.focus_distance"), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn thin_lens_differentials() {
+        let mut builder = ThinLens::builder((100, 100));
+        builder
+            .move_to([0.0, 0.0, -10.0])
+            .fov(Degrees(90.0))
+            .auto_focus();
+        let pinhole = builder.build();
+        let lens = builder.aperture(1.0).build();
+
+        // The main ray is the same as `ray` generates
+        let ray = lens.ray(50, 50, &mut StdRng::seed_from_u64(1));
+        let diff = lens.ray_differential(50, 50, &mut StdRng::seed_from_u64(1));
+        assert_eq!(Vector::from(ray.origin), Vector::from(diff.ray.origin));
+        assert_eq!(ray.direction, diff.ray.direction);
+
+        // A pixel spans 2 * tan(45°) * 10 / 100 = 0.2 units at the focus
+        // distance, with or without an aperture
+        let focused = |cam: &ThinLens, seed| {
+            let diff = cam.ray_differential(50, 50, &mut StdRng::seed_from_u64(seed));
+            let t = 10.0 / diff.ray.direction.len();
+            diff.footprint(t).unwrap()
+        };
+        assert_relative_eq!(0.2, focused(&pinhole, 2), epsilon = 1e-3);
+        assert_relative_eq!(0.2, focused(&lens, 3), epsilon = 1e-3);
+
+        // Away from the focus distance, footprints grow with the aperture:
+        // at the lens itself they're the aperture's diameter
+        let mut rng = StdRng::seed_from_u64(4);
+        let near =
+            |cam: &ThinLens, rng: &mut StdRng| cam.ray_differential(50, 50, rng).footprint(0.0);
+        assert_relative_eq!(0.0, near(&pinhole, &mut rng).unwrap());
+        assert_relative_eq!(1.0, near(&lens, &mut rng).unwrap(), epsilon = 1e-9);
+        let far = |cam: &ThinLens, rng: &mut StdRng| {
+            let diff = cam.ray_differential(50, 50, rng);
+            diff.footprint(30.0 / diff.ray.direction.len()).unwrap()
+        };
+        assert_relative_eq!(0.6, far(&pinhole, &mut rng), epsilon = 1e-3);
+        assert!(far(&lens, &mut rng) > 2.0);
+
+        let mut diff = lens.ray_differential(50, 50, &mut rng);
+        let before = diff.footprint(10.0).unwrap();
+        diff.scale_differentials(0.5);
+        assert_relative_eq!(0.5 * before, diff.footprint(10.0).unwrap(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn importance_finds_pixel() {
+        let mut builder = ThinLens::builder((100, 50));
+        builder
+            .move_to([1.0, 2.0, -10.0])
+            .look_at([0.0, 0.0, 0.0])
+            .pixel_aspect(1.5)
+            .overscan(4, 2)
+            .auto_focus();
+        let pinhole = builder.build();
+        let lens = builder.aperture(0.5).build();
+        let mut rng = StdRng::seed_from_u64(5);
+
+        // Points along a camera ray land back where the ray started, at any
+        // distance for a pinhole and at the focus distance through a lens
+        for (fx, fy) in [(0.5, 0.5), (37.25, 21.5), (107.0, 53.9)] {
+            let ray = pinhole.cam_to_world * pinhole.camera_ray(fx, fy, [0.0; 2]);
+            let s = pinhole.sample_importance(ray.at(0.3), &mut rng).unwrap();
+            assert_relative_eq!(fx, s.raster.0, epsilon = 1e-6);
+            assert_relative_eq!(fy, s.raster.1, epsilon = 1e-6);
+            assert_relative_eq!(0.3 * ray.direction.len(), s.dist, epsilon = 1e-9);
+            let back = Vector::from(s.wi) + Vector::from(ray.direction.normalize());
+            assert_relative_eq!(0.0, back.len(), epsilon = 1e-9);
+
+            let ray = lens.cam_to_world * lens.camera_ray(fx, fy, [0.6, -0.3]);
+            let s = lens.sample_importance(ray.at(1.0), &mut rng).unwrap();
+            assert_relative_eq!(fx, s.raster.0, epsilon = 1e-6);
+            assert_relative_eq!(fy, s.raster.1, epsilon = 1e-6);
+        }
+
+        // Nothing behind the camera or outside the film
+        let behind = pinhole.cam_to_world * Point::new(0.0, 0.0, 1.0);
+        assert!(pinhole.sample_importance(behind, &mut rng).is_none());
+        let outside = pinhole.cam_to_world * pinhole.camera_ray(-1.0, 10.0, [0.0; 2]);
+        assert!(pinhole
+            .sample_importance(outside.at(2.0), &mut rng)
+            .is_none());
+    }
+
+    #[test]
+    fn bladed_aperture() {
+        let hexagon = ThinLens::builder((10, 10)).aperture(1.0).blades(6).build();
+        let mut rng = StdRng::seed_from_u64(6);
+        let apothem = (PI / 6.0).cos();
+        let mut beyond = 0;
+        for _ in 0..1000 {
+            let [x, y] = hexagon.sample_aperture(&mut rng);
+            // Inside every edge, and sometimes past the inscribed circle
+            for i in 0..6 {
+                let normal = PI / 2.0 + PI / 6.0 + i as Float * PI / 3.0;
+                assert!(x * normal.cos() + y * normal.sin() <= apothem + 1e-9);
+            }
+            beyond += ((x * x + y * y).sqrt() > apothem) as u32;
+        }
+        assert!(beyond > 0);
+
+        // Sampled points land inside too, on every blade
+        let disc = ThinLens::builder((10, 10)).aperture(1.0).build();
+        let mut blades = [false; 6];
+        for i in 0..16 {
+            for j in 0..16 {
+                let u = [i as Float / 16.0, j as Float / 16.0 + 0.01];
+                let [x, y] = hexagon.aperture_point(u);
+                for i in 0..6 {
+                    let normal = PI / 2.0 + PI / 6.0 + i as Float * PI / 3.0;
+                    assert!(x * normal.cos() + y * normal.sin() <= apothem + 1e-9);
+                }
+                let angle = (y.atan2(x) - PI / 2.0).rem_euclid(2.0 * PI);
+                blades[(angle / (PI / 3.0)) as usize % 6] = true;
+                let [x, y] = disc.aperture_point(u);
+                assert!(x * x + y * y <= 1.0 + 1e-9);
+            }
+        }
+        assert_eq!([true; 6], blades);
+        assert_relative_eq!(1.5 * (3.0 as Float).sqrt(), hexagon.aperture_area());
+        assert_eq!(Some("6"), hexagon.metadata().get("gremlin.camera.blades"));
+    }
+
+    #[test]
+    fn sampled_rays() {
+        // Always the middle of the pixel and lens
+        struct Middle;
+        impl Sampler for Middle {
+            fn start_pixel_sample(&mut self, _px: u32, _py: u32, _index: u64) {}
+            fn get_1d(&mut self, _dim: SampleDimension) -> Float {
+                0.5
+            }
+            fn get_2d(&mut self, _dim: SampleDimension) -> [Float; 2] {
+                [0.5; 2]
+            }
+        }
+
+        let mut builder = ThinLens::builder((40, 30));
+        builder
+            .move_to([1.0, 2.0, -10.0])
+            .aperture(0.5)
+            .auto_focus();
+        let cam = builder.build();
+        let mut rng = StdRng::seed_from_u64(9);
+        let ray = cam.ray_sampled(7, 3, &mut Middle, &mut rng);
+        let expected = cam.cam_to_world * cam.camera_ray(7.5, 3.5, [0.0; 2]);
+        assert_relative_eq!(expected.origin, ray.origin);
+        assert_relative_eq!(expected.direction, ray.direction);
+    }
+
+    #[test]
+    fn distortion() {
+        let mut builder = ThinLens::builder((100, 50));
+        builder.move_to([1.0, 2.0, -10.0]).fov(Degrees(60.0));
+        let plain = builder.build();
+        let barrel = builder.distortion(0.2, 0.05).build();
+
+        // The center stays put, the edges see further out
+        let dir = |cam: &ThinLens, fx, fy| cam.camera_ray(fx, fy, [0.0; 2]).direction;
+        assert_eq!(dir(&plain, 50.0, 25.0), dir(&barrel, 50.0, 25.0));
+        assert!(dir(&barrel, 100.0, 25.0).x > 1.1 * dir(&plain, 100.0, 25.0).x);
+
+        // And points seen through a pixel still map back to it
+        let mut rng = StdRng::seed_from_u64(7);
+        for (fx, fy) in [(0.5, 0.5), (37.25, 21.5), (99.0, 49.9)] {
+            let ray = barrel.cam_to_world * barrel.camera_ray(fx, fy, [0.0; 2]);
+            let s = barrel.sample_importance(ray.at(0.3), &mut rng).unwrap();
+            assert_relative_eq!(fx, s.raster.0, epsilon = 1e-6);
+            assert_relative_eq!(fy, s.raster.1, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn cat_eye() {
+        let mut builder = ThinLens::builder((100, 50));
+        builder.aperture(0.5).cat_eye(1.0);
+        let cam = builder.build();
+
+        // Open in the middle, darkening into the corners
+        assert_eq!(1.0, cam.vignetting(50.0, 25.0));
+        let (edge, corner) = (cam.vignetting(100.0, 25.0), cam.vignetting(100.0, 50.0));
+        assert!(edge < 1.0 && corner < edge, "{} {}", edge, corner);
+        assert_relative_eq!(0.391, corner, epsilon = 1e-3);
+        assert_eq!(1.0, builder.aperture(0.0).build().vignetting(100.0, 50.0));
+
+        // Corner rays only leave through the open part of the lens
+        let mut rng = StdRng::seed_from_u64(8);
+        let screen = cam.screen_point(99.5, 49.5);
+        for _ in 0..100 {
+            let (fx, fy, lens) = cam.sample(99, 49, &mut rng);
+            assert!(cam.unblocked(cam.screen_point(fx, fy), lens));
+        }
+        assert!(!cam.unblocked(screen, [-0.9, 0.4]));
+
+        let mut buf: Buffer<Float> = Buffer::new(100, 50);
+        buf.iter_mut().for_each(|p| *p = 1.0);
+        cam.vignette(&mut buf);
+        assert!(buf[25 * 100 + 50] > 0.99);
+        assert!(buf[49 * 100 + 99] < 0.4);
     }
 }
@@ -0,0 +1,537 @@
+use super::{ThinLens, ThinLensBuilder};
+use crate::{
+    geo::{Degrees, Point},
+    Float,
+};
+use std::{error::Error, fmt, fs, io, ops::RangeInclusive, path::Path};
+
+/// Errors that can occur while loading a camera path.
+#[derive(Debug)]
+pub enum CameraPathError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The data couldn't be parsed. Line numbers start at `1`.
+    Parse { line: usize, msg: &'static str },
+    /// The path contained no keyframes.
+    Empty,
+}
+
+impl fmt::Display for CameraPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read camera path: {}", e),
+            Self::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+            Self::Empty => write!(f, "camera path contains no keyframes"),
+        }
+    }
+}
+
+impl Error for CameraPathError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CameraPathError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// The camera placement at a single frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub frame: u32,
+    pub position: Point,
+    pub target: Point,
+    /// Field of view, in degrees.
+    pub fov: Float,
+}
+
+/// A camera animation, as a sequence of keyframes.
+///
+/// Paths are typically exported from a DCC tool with one keyframe per frame,
+/// but keyframes may be sparse: in-between frames are linearly interpolated.
+///
+/// ```no_run
+/// use gremlin::{camera::{CameraPath, ThinLens}, film::RGBFilm, renderer::Renderer};
+/// # use gremlin::integrator::Hacky;
+/// # let integrator = Hacky::default();
+///
+/// let path = CameraPath::load("flythrough.csv").unwrap();
+/// let mut template = ThinLens::builder((800, 600));
+/// template.aperture(0.1);
+///
+/// for frame in path.frame_range() {
+///     let mut film = RGBFilm::new(800, 600);
+///     let cam = path.camera(frame, &template);
+///     Renderer::new(16)
+///         .deterministic(1234)
+///         .frame(frame as u64)
+///         .render(&mut film, &cam, &integrator);
+///     film.to_snapshot()
+///         .save_image(format!("frame-{:04}.png", frame))
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    keys: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    /// Create a camera path from the given keyframes.
+    ///
+    /// Keyframes needn't be sorted. Returns an error if there are none.
+    pub fn new(mut keys: Vec<Keyframe>) -> Result<Self, CameraPathError> {
+        if keys.is_empty() {
+            return Err(CameraPathError::Empty);
+        }
+        keys.sort_by_key(|k| k.frame);
+        Ok(Self { keys })
+    }
+
+    /// Load a camera path from a file.
+    ///
+    /// Files with a `.json` extension are parsed with [`parse_json`], and
+    /// anything else with [`parse_csv`].
+    ///
+    /// [`parse_json`]: Self::parse_json
+    /// [`parse_csv`]: Self::parse_csv
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CameraPathError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::parse_json(&data),
+            _ => Self::parse_csv(&data),
+        }
+    }
+
+    /// Parse a camera path from CSV.
+    ///
+    /// Each line is a keyframe with 8 columns: the frame number, the camera
+    /// position (`x, y, z`), the target it looks at (`x, y, z`), and the field
+    /// of view in degrees. Blank lines and `#` comments are ignored, as is a
+    /// single header line at the start of the data.
+    ///
+    /// ```
+    /// use gremlin::camera::CameraPath;
+    ///
+    /// let data = "frame,px,py,pz,tx,ty,tz,fov\n0,0,0,5,0,0,0,45\n10,5,0,0,0,0,0,45\n";
+    /// let path = CameraPath::parse_csv(data).unwrap();
+    /// assert_eq!(0..=10, path.frame_range());
+    /// ```
+    pub fn parse_csv(data: &str) -> Result<Self, CameraPathError> {
+        let mut keys = Vec::new();
+        let mut seen_data = false;
+
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |msg| CameraPathError::Parse { line: idx + 1, msg };
+
+            let fields: Result<Vec<Float>, _> = line.split(',').map(|f| f.trim().parse()).collect();
+            let fields = match fields {
+                Ok(fields) => fields,
+                Err(_) if !seen_data => {
+                    // Header line; only allowed before any data
+                    seen_data = true;
+                    continue;
+                }
+                Err(_) => return Err(err("expected a number")),
+            };
+            seen_data = true;
+            if fields.len() != 8 {
+                return Err(err("expected 8 columns"));
+            }
+            keys.push(
+                keyframe(fields[0], &fields[1..4], &fields[4..7], fields[7])
+                    .ok_or_else(|| err("invalid keyframe"))?,
+            );
+        }
+
+        Self::new(keys)
+    }
+
+    /// Parse a camera path from JSON.
+    ///
+    /// The data is an array of keyframe objects (optionally wrapped in an
+    /// object, under a `"frames"` key), each with a `frame` number,
+    /// `position` and `target` arrays and a `fov` in degrees:
+    ///
+    /// ```
+    /// use gremlin::camera::CameraPath;
+    ///
+    /// let data = r#"[
+    ///     {"frame": 1, "position": [0, 0, 5], "target": [0, 0, 0], "fov": 45},
+    ///     {"frame": 3, "position": [0, 0, 3], "target": [0, 0, 0], "fov": 45}
+    /// ]"#;
+    /// let path = CameraPath::parse_json(data).unwrap();
+    /// assert_eq!([0.0, 0.0, 4.0], <[_; 3]>::from(path.at(2).position));
+    /// ```
+    pub fn parse_json(data: &str) -> Result<Self, CameraPathError> {
+        let mut parser = json::Parser::new(data);
+        let value = parser.parse()?;
+        let frames = match &value {
+            json::Value::Object(_) => value.get("frames"),
+            _ => Some(&value),
+        };
+        let frames = match frames {
+            Some(json::Value::Array(frames)) => frames,
+            _ => return Err(parser.error("expected an array of keyframes")),
+        };
+
+        let keys = frames
+            .iter()
+            .map(|f| {
+                let num = |key| f.get(key).and_then(json::Value::as_number);
+                let vec = |key| f.get(key).and_then(json::Value::as_numbers);
+                keyframe(
+                    num("frame")?,
+                    &vec("position")?,
+                    &vec("target")?,
+                    num("fov")?,
+                )
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| parser.error("invalid keyframe"))?;
+
+        Self::new(keys)
+    }
+
+    /// The keyframes, sorted by frame.
+    #[inline]
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    /// The range of frames covered by the path.
+    #[inline]
+    pub fn frame_range(&self) -> RangeInclusive<u32> {
+        self.keys[0].frame..=self.keys[self.keys.len() - 1].frame
+    }
+
+    /// The camera placement at the given frame, interpolating between
+    /// keyframes. Frames outside the path are clamped to its ends.
+    pub fn at(&self, frame: u32) -> Keyframe {
+        let i = self.keys.partition_point(|k| k.frame <= frame);
+        if i == 0 {
+            return Keyframe {
+                frame,
+                ..self.keys[0]
+            };
+        }
+        let k0 = self.keys[i - 1];
+        let k1 = match self.keys.get(i) {
+            Some(&k1) => k1,
+            None => return Keyframe { frame, ..k0 },
+        };
+
+        let t = (frame - k0.frame) as Float / (k1.frame - k0.frame) as Float;
+        Keyframe {
+            frame,
+            position: k0.position + (k1.position - k0.position) * t,
+            target: k0.target + (k1.target - k0.target) * t,
+            fov: k0.fov + (k1.fov - k0.fov) * t,
+        }
+    }
+
+    /// Create the camera for the given frame.
+    ///
+    /// Position, target and field of view come from the path; everything else
+    /// (resolution, aperture, focal length, coordinate system) comes from the
+    /// `template` builder.
+    pub fn camera(&self, frame: u32, template: &ThinLensBuilder) -> ThinLens {
+        let key = self.at(frame);
+        template
+            .clone()
+            .move_to(key.position)
+            .look_at(key.target)
+            .fov(Degrees(key.fov))
+            .build()
+    }
+}
+
+fn keyframe(frame: Float, position: &[Float], target: &[Float], fov: Float) -> Option<Keyframe> {
+    let point = |v: &[Float]| match *v {
+        [x, y, z] if v.iter().all(|c| c.is_finite()) => Some(Point::new(x, y, z)),
+        _ => None,
+    };
+    let valid_frame = frame >= 0.0 && frame.fract() == 0.0 && frame <= u32::MAX as Float;
+    let valid_fov = fov > 0.0 && fov < 180.0;
+    match valid_frame && valid_fov {
+        true => Some(Keyframe {
+            frame: frame as u32,
+            position: point(position)?,
+            target: point(target)?,
+            fov,
+        }),
+        false => None,
+    }
+}
+
+/// A minimal JSON reader, just enough for camera paths.
+mod json {
+    use super::CameraPathError;
+    use crate::Float;
+
+    // Camera paths are only a few levels deep; anything deeper is rejected
+    // before the recursion can overflow the stack
+    const MAX_DEPTH: usize = 64;
+
+    // Only numbers and structure are needed, so other values aren't kept.
+    pub enum Value {
+        Null,
+        Bool,
+        Number(Float),
+        String,
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            match self {
+                Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+
+        pub fn as_number(&self) -> Option<Float> {
+            match *self {
+                Self::Number(n) => Some(n),
+                _ => None,
+            }
+        }
+
+        pub fn as_numbers(&self) -> Option<Vec<Float>> {
+            match self {
+                Self::Array(vals) => vals.iter().map(Self::as_number).collect(),
+                _ => None,
+            }
+        }
+    }
+
+    pub struct Parser<'a> {
+        data: &'a str,
+        pos: usize,
+        depth: usize,
+    }
+
+    impl<'a> Parser<'a> {
+        pub fn new(data: &'a str) -> Self {
+            Self {
+                data,
+                pos: 0,
+                depth: 0,
+            }
+        }
+
+        pub fn parse(&mut self) -> Result<Value, CameraPathError> {
+            let value = self.value()?;
+            self.skip_ws();
+            match self.pos == self.data.len() {
+                true => Ok(value),
+                false => Err(self.error("trailing characters")),
+            }
+        }
+
+        pub fn error(&self, msg: &'static str) -> CameraPathError {
+            let line = self.data[..self.pos].matches('\n').count() + 1;
+            CameraPathError::Parse { line, msg }
+        }
+
+        fn value(&mut self) -> Result<Value, CameraPathError> {
+            self.skip_ws();
+            match self.peek() {
+                Some(b'{' | b'[') if self.depth == MAX_DEPTH => {
+                    Err(self.error("nested too deeply"))
+                }
+                Some(b'{') => self.nested(Self::object),
+                Some(b'[') => self.nested(Self::array),
+                Some(b'"') => self.string().map(|_| Value::String),
+                Some(b't') => self.literal("true", Value::Bool),
+                Some(b'f') => self.literal("false", Value::Bool),
+                Some(b'n') => self.literal("null", Value::Null),
+                Some(_) => self.number(),
+                None => Err(self.error("unexpected end of data")),
+            }
+        }
+
+        fn nested(
+            &mut self,
+            parse: fn(&mut Self) -> Result<Value, CameraPathError>,
+        ) -> Result<Value, CameraPathError> {
+            self.depth += 1;
+            let value = parse(self);
+            self.depth -= 1;
+            value
+        }
+
+        fn object(&mut self) -> Result<Value, CameraPathError> {
+            self.pos += 1;
+            let mut fields = Vec::new();
+            self.skip_ws();
+            if self.eat(b'}') {
+                return Ok(Value::Object(fields));
+            }
+            loop {
+                self.skip_ws();
+                let key = self.string()?;
+                self.skip_ws();
+                if !self.eat(b':') {
+                    return Err(self.error("expected ':'"));
+                }
+                fields.push((key, self.value()?));
+                self.skip_ws();
+                if self.eat(b'}') {
+                    return Ok(Value::Object(fields));
+                }
+                if !self.eat(b',') {
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+
+        fn array(&mut self) -> Result<Value, CameraPathError> {
+            self.pos += 1;
+            let mut vals = Vec::new();
+            self.skip_ws();
+            if self.eat(b']') {
+                return Ok(Value::Array(vals));
+            }
+            loop {
+                vals.push(self.value()?);
+                self.skip_ws();
+                if self.eat(b']') {
+                    return Ok(Value::Array(vals));
+                }
+                if !self.eat(b',') {
+                    return Err(self.error("expected ',' or ']'"));
+                }
+            }
+        }
+
+        // Escapes other than `\"` and `\\` aren't needed for keys, so they're
+        // passed through as-is.
+        fn string(&mut self) -> Result<String, CameraPathError> {
+            if !self.eat(b'"') {
+                return Err(self.error("expected a string"));
+            }
+            let mut out = String::new();
+            let mut chars = self.data[self.pos..].char_indices();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.pos += i + 1;
+                        return Ok(out);
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, c)) => out.push(c),
+                        None => break,
+                    },
+                    c => out.push(c),
+                }
+            }
+            self.pos = self.data.len();
+            Err(self.error("unterminated string"))
+        }
+
+        fn number(&mut self) -> Result<Value, CameraPathError> {
+            let rest = &self.data[self.pos..];
+            let len = rest
+                .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                .unwrap_or(rest.len());
+            let n = rest[..len]
+                .parse()
+                .map_err(|_| self.error("expected a value"))?;
+            self.pos += len;
+            Ok(Value::Number(n))
+        }
+
+        fn literal(&mut self, word: &str, value: Value) -> Result<Value, CameraPathError> {
+            match self.data[self.pos..].starts_with(word) {
+                true => {
+                    self.pos += word.len();
+                    Ok(value)
+                }
+                false => Err(self.error("expected a value")),
+            }
+        }
+
+        fn skip_ws(&mut self) {
+            let rest = &self.data[self.pos..];
+            self.pos += rest.len() - rest.trim_start().len();
+        }
+
+        fn peek(&self) -> Option<u8> {
+            self.data.as_bytes().get(self.pos).copied()
+        }
+
+        fn eat(&mut self, b: u8) -> bool {
+            let matched = self.peek() == Some(b);
+            if matched {
+                self.pos += 1;
+            }
+            matched
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate() {
+        let data = "# exported path\n0, 0,0,10, 0,0,0, 40\n4, 4,0,10, 0,0,0, 60\n";
+        let path = CameraPath::parse_csv(data).unwrap();
+        let key = path.at(1);
+        assert_eq!(Point::new(1.0, 0.0, 10.0), key.position);
+        assert_eq!(45.0, key.fov);
+        assert_eq!(path.keyframes()[1].position, path.at(100).position);
+    }
+
+    #[test]
+    fn csv_errors() {
+        assert!(matches!(
+            CameraPath::parse_csv(""),
+            Err(CameraPathError::Empty)
+        ));
+        assert!(matches!(
+            CameraPath::parse_csv("0,0,0,1,0,0,0,45\n1,0,0\n"),
+            Err(CameraPathError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            CameraPath::parse_csv("0,0,0,1,0,0,0,45\n1,a,0,1,0,0,0,45\n"),
+            Err(CameraPathError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn json_wrapped() {
+        let data = r#"{"name": "flythrough", "frames": [
+            {"frame": 2, "position": [1, 2, 3], "target": [0, 0, 0], "fov": 55.5}
+        ]}"#;
+        let path = CameraPath::parse_json(data).unwrap();
+        assert_eq!(2..=2, path.frame_range());
+        assert_eq!(55.5, path.at(2).fov);
+
+        let bad = r#"[{"frame": 2, "position": [1, 2], "target": [0, 0, 0], "fov": 55}]"#;
+        assert!(CameraPath::parse_json(bad).is_err());
+        assert!(CameraPath::parse_json("[{\"frame\": 1,\n").is_err());
+        assert!(matches!(
+            CameraPath::parse_json(&"[".repeat(200_000)),
+            Err(CameraPathError::Parse { line: 1, .. })
+        ));
+        assert!(matches!(
+            CameraPath::parse_json(&"{\"a\":".repeat(200_000)),
+            Err(CameraPathError::Parse { line: 1, .. })
+        ));
+    }
+}
@@ -5,9 +5,19 @@
 //! color values within a color space is supported, while preventing arithmetic
 //! on color values in different spaces.
 //!
-//! Two color spaces are supported: [`CIE1931`] and [`LinearRGB`]. Convenience
-//! typedefs ([`XYZ`] and [`RGB`], respectively) make it easy to construct and
-//! refer to values in these spaces.
+//! The main color spaces are [`CIE1931`] and [`LinearRGB`] (linear sRGB).
+//! Convenience typedefs ([`XYZ`] and [`RGB`], respectively) make it easy to
+//! construct and refer to values in these spaces. Wide-gamut RGB spaces
+//! ([`ACEScg`], [`Rec2020`]) are also available via the [`ColorSpace`] trait,
+//! which also handles chromatic adaptation between white points.
+//!
+//! Rendered values are scene-referred, with no upper limit. An
+//! [`OutputTransform`] adjusts their exposure and applies a [`ToneCurve`] to
+//! fit them to a display, such as the filmic ACES curve, built from the
+//! operators in [`tonemap`]. For physically calibrated scenes, the exposure
+//! can come from a [`CameraExposure`]'s ISO, shutter speed and aperture.
+//!
+//! [`tonemap`]: crate::tonemap
 //!
 //! ```
 //! use gremlin::color::{RGB, XYZ};
@@ -22,12 +32,26 @@
 //! //let invalid = rgb + xyz;
 //! ```
 
-use crate::{geo::Vector, spectrum::Sampled, Float};
+use crate::{
+    geo::Vector,
+    math::blue_noise,
+    spectrum::{Sampled, SingleWavelength},
+    Float,
+};
 use std::{
     marker::PhantomData,
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign},
 };
 
+mod exposure;
+pub use exposure::*;
+
+mod output;
+pub use output::*;
+
+mod space;
+pub use space::*;
+
 /// sRGB conversion trait.
 ///
 /// Most libraries that write image files to disk, such as the [`image`] crate
@@ -85,6 +109,35 @@ impl<CS> AddAssign for Color<CS> {
     }
 }
 
+impl<CS> Mul for Color<CS> {
+    type Output = Self;
+
+    /// Component-wise multiplication, as used for filtering one color by
+    /// another (_e.g._ attenuation by an albedo).
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            vals: Vector::new(
+                self.vals.x * rhs.vals.x,
+                self.vals.y * rhs.vals.y,
+                self.vals.z * rhs.vals.z,
+            ),
+            _colorspace: PhantomData,
+        }
+    }
+}
+
+impl<CS> MulAssign for Color<CS> {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        self.vals = Vector::new(
+            self.vals.x * rhs.vals.x,
+            self.vals.y * rhs.vals.y,
+            self.vals.z * rhs.vals.z,
+        );
+    }
+}
+
 impl<CS> Mul<Float> for Color<CS> {
     type Output = Self;
 
@@ -123,6 +176,48 @@ impl<CS> DivAssign<Float> for Color<CS> {
     }
 }
 
+impl<CS> Color<CS> {
+    /// A color with all three components equal to `v`.
+    #[inline]
+    pub const fn splat(v: Float) -> Self {
+        Self {
+            vals: Vector::splat(v),
+            _colorspace: PhantomData,
+        }
+    }
+
+    /// The largest of the three components.
+    #[inline]
+    pub fn max_component(&self) -> Float {
+        self.vals.max_component()
+    }
+
+    /// The smallest of the three components.
+    #[inline]
+    pub fn min_component(&self) -> Float {
+        self.vals.min_component()
+    }
+
+    /// Returns `true` if no component is infinite or NaN.
+    #[inline]
+    pub fn is_finite(&self) -> bool {
+        self.vals.is_finite()
+    }
+
+    /// Returns `true` if all components are zero.
+    #[inline]
+    pub fn is_black(&self) -> bool {
+        self.vals == Vector::ZERO
+    }
+}
+
+impl<CS> From<Color<CS>> for [Float; 3] {
+    #[inline]
+    fn from(color: Color<CS>) -> Self {
+        color.vals.into()
+    }
+}
+
 impl<CS> From<[Float; 3]> for Color<CS> {
     #[inline]
     fn from(vals: [Float; 3]) -> Self {
@@ -145,6 +240,14 @@ impl SRGB for XYZ {
     }
 }
 
+impl XYZ {
+    /// The luminance, _i.e._ the `Y` component.
+    #[inline]
+    pub fn luminance(&self) -> Float {
+        self.vals.y
+    }
+}
+
 // TODO: Consider moving to Spectrum module?
 impl From<Sampled> for XYZ {
     /// Converts a sampled spectrum to XYZ by integrating against the CIE color-
@@ -165,6 +268,24 @@ impl From<Sampled> for XYZ {
     }
 }
 
+impl From<SingleWavelength> for XYZ {
+    /// Converts a single-wavelength sample to XYZ.
+    ///
+    /// Assumes the wavelength was chosen uniformly over the sampled range, so
+    /// the expected value over many samples equals the XYZ of the underlying
+    /// spectrum.
+    #[inline]
+    fn from(sample: SingleWavelength) -> Self {
+        let w = sample.wavelength;
+        let scale = sample.value * Sampled::COUNT as Float * consts::CIE_NORM;
+        XYZ::from([
+            consts::CIE_X.at(w),
+            consts::CIE_Y.at(w),
+            consts::CIE_Z.at(w),
+        ]) * scale
+    }
+}
+
 /// A linear RGB color value.
 pub type RGB = Color<LinearRGB>;
 
@@ -180,18 +301,64 @@ impl RGB {
             1.055 * v.powf(0.41667) - 0.055
         }
     }
+
+    // Inverse of `gamma`, taking sRGB to linear RGB.
+    fn inverse_gamma(v: Float) -> Float {
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    /// Converts sRGB components in `[0, 1]`, as stored in most 8- and 16-bit
+    /// image files, to linear RGB.
+    #[inline]
+    pub fn from_srgb(vals: [Float; 3]) -> Self {
+        Self::from(vals.map(Self::inverse_gamma))
+    }
 }
 
 impl SRGB for RGB {
     /// Converts a linear RGB to sRGB by applying gamma correction.
     fn to_srgb(&self) -> [u8; 3] {
-        // Implementation note:
-        //
-        // This is more-or-less a direct port of John Walker's code from his
-        // _Colour Rendering of Spectra_ page:
-        // * <https://www.fourmilab.ch/documents/specrend/>
-        // * <https://www.fourmilab.ch/documents/specrend/specrend.c>
+        // Scale by 255 and convert to u8
+        let vals = self.encode_srgb() * 255.0;
+        [vals.x as u8, vals.y as u8, vals.z as u8]
+    }
+}
+
+impl RGB {
+    /// As [`to_srgb`], but dithered with the [`blue_noise`] mask at pixel
+    /// `(x, y)`.
+    ///
+    /// Rounding every pixel the same way turns smooth gradients, such as a
+    /// sky, into visible bands a level apart. Dithering rounds each pixel up
+    /// or down at random, in proportion to how close it is to each level, so
+    /// the average over an area keeps the exact value and the bands
+    /// dissolve into fine, even noise. Each channel reads the mask at a
+    /// different offset, so the noise isn't tinted.
+    ///
+    /// [`to_srgb`]: SRGB::to_srgb
+    /// [`blue_noise`]: crate::math::blue_noise
+    pub fn to_srgb_dithered(&self, x: u32, y: u32) -> [u8; 3] {
+        const OFFSETS: [(u32, u32); 3] = [(0, 0), (23, 41), (47, 13)];
+        let vals: [Float; 3] = (self.encode_srgb() * 255.0).into();
+        let mut out = [0; 3];
+        for ((o, v), (dx, dy)) in out.iter_mut().zip(vals).zip(OFFSETS) {
+            let t = blue_noise(x.wrapping_add(dx), y.wrapping_add(dy));
+            *o = (v + t).floor().min(255.0) as u8;
+        }
+        out
+    }
 
+    // Gamma correct, and bring back into gamut, for quantizing to sRGB.
+    //
+    // This is more-or-less a direct port of John Walker's code from his
+    // _Colour Rendering of Spectra_ page:
+    // * <https://www.fourmilab.ch/documents/specrend/>
+    // * <https://www.fourmilab.ch/documents/specrend/specrend.c>
+    fn encode_srgb(&self) -> Vector {
         // Convert linear RGB to sRGB by applying gamma
         let mut vals = self.vals.apply(Self::gamma);
 
@@ -206,10 +373,7 @@ impl SRGB for RGB {
         if max > 1.0 {
             vals /= max;
         }
-
-        // Scale by 255 and convert to u8
-        vals *= 255.0;
-        [vals.x as u8, vals.y as u8, vals.z as u8]
+        vals
     }
 }
 
@@ -236,7 +400,7 @@ mod consts {
         [ 3.2404542, -1.5371385, -0.4985314, 0.0],
         [-0.9692660,  1.8760108,  0.0415560, 0.0],
         [ 0.0556434, -0.2040259,  1.0572252, 0.0],
-        [ 0.0,        0.0,        0.0,       0.0]
+        [ 0.0,        0.0,        0.0,       1.0]
     ]);
 
     #[rustfmt::skip]
@@ -326,6 +490,24 @@ mod test {
         assert_eq!(XYZ::from([0.25, 0.25, 0.25]), xyz);
     }
 
+    #[test]
+    fn single_wavelength_estimate() {
+        // Averaging single-wavelength samples over every bin recovers the
+        // full-spectrum conversion
+        let spec = Sampled::splat(1.0);
+        let mut avg = XYZ::default();
+        for (w, &v) in spec.enumerate_values() {
+            avg += XYZ::from(SingleWavelength {
+                wavelength: w,
+                value: v,
+            });
+        }
+        avg /= Sampled::COUNT as Float;
+
+        let expected = XYZ::from(spec);
+        assert!((avg.vals - expected.vals).len() < 1e-9);
+    }
+
     #[test]
     fn type_system() {
         let xyz1 = XYZ::from([0.25, 0.5, 0.75]);
@@ -0,0 +1,101 @@
+use crate::Float;
+
+/// The exposure settings of a physical camera: sensor sensitivity, shutter
+/// speed and aperture.
+///
+/// With these, rendered values are taken to be luminance in `cd/m²` (nits),
+/// and scaled so a surface as bright as the camera's settings can record
+/// just reaches white, as a real camera's meter would. Scenes lit with
+/// physical values, such as a `100 000 lux` sun or a light given in watts,
+/// then come out at a sensible brightness with the settings a photographer
+/// would use, instead of needing an arbitrary exposure found by trial and
+/// error.
+///
+/// Each setting trades off against the others: halving the shutter time,
+/// halving the ISO or stopping the aperture down by a factor of `√2` all
+/// darken the image by one stop. Use it through [`OutputTransform::camera`].
+///
+/// ```
+/// use gremlin::{color::CameraExposure, Float};
+///
+/// // The "sunny 16" rule: f/16 at a shutter speed of one over the ISO
+/// let sunny = CameraExposure::new(100.0, 1.0 / 100.0, 16.0);
+/// assert!((sunny.ev100() - 14.6).abs() < 0.05);
+///
+/// // A sunlit white wall
+/// let wall: Float = 100_000.0 * 0.8 / 3.14159;
+/// assert!((0.5..1.0).contains(&(wall * sunny.scale())));
+/// ```
+///
+/// See: <https://seblagarde.files.wordpress.com/2015/07/course_notes_moving_frostbite_to_pbr_v32.pdf>
+/// (section 5.1)
+///
+/// [`OutputTransform::camera`]: super::OutputTransform::camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraExposure {
+    /// Sensor sensitivity, as an ISO speed.
+    pub iso: Float,
+    /// Shutter time, in seconds.
+    pub shutter: Float,
+    /// Aperture, as an f-number: the focal length over the aperture's
+    /// diameter.
+    pub f_number: Float,
+}
+
+impl Default for CameraExposure {
+    /// ISO 100 at `1/125s` and `f/8`, about right for an overcast day.
+    fn default() -> Self {
+        Self::new(100.0, 1.0 / 125.0, 8.0)
+    }
+}
+
+impl CameraExposure {
+    /// Exposure settings with the given ISO, shutter time in seconds and
+    /// f-number.
+    pub const fn new(iso: Float, shutter: Float, f_number: Float) -> Self {
+        Self {
+            iso,
+            shutter,
+            f_number,
+        }
+    }
+
+    /// The exposure value of the settings, relative to ISO 100. Each step
+    /// of `1` is a stop less light reaching the image.
+    pub fn ev100(&self) -> Float {
+        (self.f_number * self.f_number / self.shutter * 100.0 / self.iso).log2()
+    }
+
+    /// The factor taking luminance in nits to image values, with `1` for
+    /// the brightest luminance the settings record.
+    ///
+    /// Uses the saturation-based speed of ISO 12232, with the usual lens
+    /// and vignetting factor `q = 0.65`: the brightest luminance is
+    /// `78 / (q · ISO) · N² / t`, which is `1.2 · 2^EV100`.
+    pub fn scale(&self) -> Float {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_trade_off() {
+        let base = CameraExposure::new(100.0, 1.0, 1.0);
+        assert!(base.ev100().abs() < 1e-9);
+        assert!((base.scale() - 1.0 / 1.2).abs() < 1e-9);
+
+        // One stop darker each
+        let darker = [
+            CameraExposure::new(50.0, 1.0, 1.0),
+            CameraExposure::new(100.0, 0.5, 1.0),
+            CameraExposure::new(100.0, 1.0, (2.0 as Float).sqrt()),
+        ];
+        for cam in darker {
+            assert!((cam.ev100() - 1.0).abs() < 1e-9, "{:?}", cam);
+            assert!((cam.scale() * 2.0 - base.scale()).abs() < 1e-9, "{:?}", cam);
+        }
+    }
+}
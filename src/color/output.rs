@@ -0,0 +1,199 @@
+use super::{CameraExposure, RGB, SRGB};
+use crate::{
+    tonemap::{AcesFilmic, Exposure, Reinhard, ToneMap},
+    Float,
+};
+
+/// The curve taking scene-referred linear RGB to display-referred values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneCurve {
+    /// Leave values as they are, so anything brighter than `1` clips when
+    /// converted to sRGB.
+    #[default]
+    Clamp,
+    /// A fitted approximation of the ACES reference rendering transform and
+    /// sRGB output device transform (RRT+ODT). Highlights roll off smoothly
+    /// towards white instead of clipping, and very bright saturated colors
+    /// desaturate the way film does.
+    ///
+    /// The curve darkens midtones: mid-grey `0.18` comes out at about `0.11`.
+    /// Raising the exposure by about half a stop brings it back to where
+    /// [`Clamp`] leaves it.
+    ///
+    /// See [`AcesFilmic`].
+    ///
+    /// [`Clamp`]: Self::Clamp
+    Aces,
+    /// Reinhard's global operator on luminance. Gentler than [`Aces`] in the
+    /// shadows, but highlights turn grey rather than white.
+    ///
+    /// See [`Reinhard`].
+    ///
+    /// [`Aces`]: Self::Aces
+    Reinhard,
+}
+
+/// How rendered images are turned into displayable ones: an exposure
+/// adjustment followed by a [`ToneCurve`].
+///
+/// The exposure is set in stops, on top of an optional [`CameraExposure`]
+/// for scenes in physical units. Images quantized to 8 bits through it can
+/// also be [`dither`]ed, to keep smooth gradients from banding.
+///
+/// ```
+/// use gremlin::color::{OutputTransform, ToneCurve, RGB};
+///
+/// let display = OutputTransform::new(ToneCurve::Aces).exposure(-1.0);
+/// let highlight = display.apply(RGB::splat(8.0));
+/// assert!(highlight.max_component() < 1.0);
+/// ```
+///
+/// [`dither`]: Self::dither
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OutputTransform {
+    exposure: Float,
+    camera: Option<CameraExposure>,
+    curve: ToneCurve,
+    dither: bool,
+}
+
+impl OutputTransform {
+    /// An output transform applying the given curve, at the default exposure.
+    pub fn new(curve: ToneCurve) -> Self {
+        Self {
+            exposure: 0.0,
+            camera: None,
+            curve,
+            dither: false,
+        }
+    }
+
+    /// Set the exposure adjustment, in stops. Each stop doubles the
+    /// brightness. Defaults to `0`.
+    pub fn exposure(mut self, stops: Float) -> Self {
+        self.exposure = stops;
+        self
+    }
+
+    /// Expose as a physical camera with the given settings would, taking
+    /// rendered values as luminance in nits. The [`exposure`] then
+    /// compensates on top. Defaults to no camera, leaving values as they
+    /// are.
+    ///
+    /// [`exposure`]: Self::exposure
+    pub fn camera(mut self, camera: CameraExposure) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    /// Dither 8-bit output with a blue-noise mask, so smooth gradients
+    /// dissolve into fine noise instead of banding. See
+    /// [`RGB::to_srgb_dithered`]. Defaults to off.
+    pub fn dither(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Take a scene-referred color to display-referred linear RGB.
+    pub fn apply(&self, color: impl Into<RGB>) -> RGB {
+        let camera = self.camera.map_or(1.0, |c| c.scale());
+        let rgb = Exposure(self.exposure).map(color.into()) * camera;
+        match self.curve {
+            ToneCurve::Clamp => rgb,
+            ToneCurve::Aces => AcesFilmic.map(rgb),
+            ToneCurve::Reinhard => Reinhard::new().map(rgb),
+        }
+    }
+
+    /// Take a scene-referred color to an 8-bit sRGB triple.
+    ///
+    /// This never dithers, having no pixel to dither for; see
+    /// [`to_srgb_at`].
+    ///
+    /// [`to_srgb_at`]: Self::to_srgb_at
+    #[inline]
+    pub fn to_srgb(&self, color: impl Into<RGB>) -> [u8; 3] {
+        self.apply(color).to_srgb()
+    }
+
+    /// Take the scene-referred color of pixel `(x, y)` to an 8-bit sRGB
+    /// triple, dithered if enabled.
+    #[inline]
+    pub fn to_srgb_at(&self, color: impl Into<RGB>, x: u32, y: u32) -> [u8; 3] {
+        let rgb = self.apply(color);
+        match self.dither {
+            true => rgb.to_srgb_dithered(x, y),
+            false => rgb.to_srgb(),
+        }
+    }
+}
+
+impl ToneMap for OutputTransform {
+    #[inline]
+    fn map(&self, rgb: RGB) -> RGB {
+        self.apply(rgb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PI: Float = std::f64::consts::PI as Float;
+
+    #[test]
+    fn curves_match_operators() {
+        let c = RGB::from([4.0, 1.0, 0.25]);
+        let aces = OutputTransform::new(ToneCurve::Aces).exposure(0.5);
+        assert_eq!((Exposure(0.5), AcesFilmic).map(c), aces.apply(c));
+        let reinhard = OutputTransform::new(ToneCurve::Reinhard);
+        assert_eq!(Reinhard::new().map(c), reinhard.apply(c));
+        assert_eq!(c, OutputTransform::default().apply(c));
+    }
+
+    #[test]
+    fn exposure_in_stops() {
+        let clamp = OutputTransform::default().exposure(2.0);
+        assert_eq!(RGB::splat(0.4), clamp.apply(RGB::splat(0.1)));
+        assert_eq!(
+            RGB::splat(0.1).to_srgb(),
+            clamp.exposure(0.0).to_srgb(RGB::splat(0.1))
+        );
+
+        // A sunlit grey card, at sunny 16, lands near mid-grey
+        let sunny = CameraExposure::new(400.0, 1.0 / 400.0, 16.0);
+        let card = OutputTransform::default().camera(sunny);
+        let grey = card.apply(RGB::splat(100_000.0 * 0.18 / PI));
+        assert!((grey.max_component() - 0.18).abs() < 0.03, "{:?}", grey);
+        assert_eq!(
+            card.exposure(1.0).apply(RGB::splat(1.0)),
+            card.apply(RGB::splat(2.0))
+        );
+    }
+
+    #[test]
+    fn dither_keeps_average() {
+        // A level a third of the way between two 8-bit values
+        let level = 100.3;
+        let color = RGB::from_srgb([level / 255.0; 3]);
+        let plain = OutputTransform::default();
+        assert_eq!([100; 3], plain.to_srgb_at(color, 5, 7));
+
+        // Dithered, an area averages out to the exact level in every
+        // channel, only ever rounding to the neighbouring values
+        let dithered = plain.dither(true);
+        let mut sums = [0.0; 3];
+        for y in 0..64 {
+            for x in 0..64 {
+                let srgb = dithered.to_srgb_at(color, x, y);
+                assert!(srgb.iter().all(|&c| c == 100 || c == 101), "{:?}", srgb);
+                for (sum, c) in sums.iter_mut().zip(srgb) {
+                    *sum += c as Float / 4096.0;
+                }
+            }
+        }
+        for mean in sums {
+            assert!((mean - level).abs() < 0.01, "{}", mean);
+        }
+    }
+}
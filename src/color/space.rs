@@ -0,0 +1,283 @@
+use super::{consts, Color, LinearRGB, CIE1931, XYZ};
+use crate::{geo::Matrix, Float};
+use std::marker::PhantomData;
+
+/// A CIE 1931 `xy` chromaticity coordinate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticity {
+    pub x: Float,
+    pub y: Float,
+}
+
+impl Chromaticity {
+    /// CIE standard illuminant D65 (noon daylight), the white point of sRGB
+    /// and Rec. 2020.
+    pub const D65: Chromaticity = Chromaticity::new(0.3127, 0.3290);
+
+    /// CIE standard illuminant D50, the white point used by ICC profiles.
+    pub const D50: Chromaticity = Chromaticity::new(0.3457, 0.3585);
+
+    /// The ACES white point, approximately D60.
+    pub const ACES: Chromaticity = Chromaticity::new(0.32168, 0.33767);
+
+    /// The equal-energy illuminant E.
+    pub const E: Chromaticity = Chromaticity::new(1.0 / 3.0, 1.0 / 3.0);
+
+    /// Creates a new chromaticity coordinate.
+    #[inline]
+    pub const fn new(x: Float, y: Float) -> Self {
+        Self { x, y }
+    }
+
+    /// The XYZ color with this chromaticity and unit luminance.
+    #[inline]
+    pub fn to_xyz(self) -> XYZ {
+        XYZ::from([self.x / self.y, 1.0, (1.0 - self.x - self.y) / self.y])
+    }
+}
+
+/// An RGB color space, defined by its primaries and white point.
+///
+/// Implemented by the marker types that parameterize [`Color`], so
+/// conversions between spaces are checked by the type system. The matrices
+/// are supplied as constants rather than derived from the primaries on every
+/// conversion; [`rgb_to_xyz_matrix`] can derive them for new spaces.
+pub trait ColorSpace {
+    /// Chromaticities of the red, green and blue primaries.
+    const PRIMARIES: [Chromaticity; 3];
+
+    /// The white point, _i.e._ the chromaticity of `[1, 1, 1]`.
+    const WHITE: Chromaticity;
+
+    /// Matrix taking linear RGB in this space to XYZ.
+    const RGB_TO_XYZ: Matrix;
+
+    /// Matrix taking XYZ to linear RGB in this space.
+    const XYZ_TO_RGB: Matrix;
+}
+
+/// The ACEScg color space (ACES AP1 primaries), the usual working space for
+/// VFX and animation pipelines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ACEScg;
+
+/// The ITU-R Rec. 2020 color space, used for wide-gamut (UHD) output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rec2020;
+
+impl ColorSpace for LinearRGB {
+    const PRIMARIES: [Chromaticity; 3] = [
+        Chromaticity::new(0.64, 0.33),
+        Chromaticity::new(0.30, 0.60),
+        Chromaticity::new(0.15, 0.06),
+    ];
+    const WHITE: Chromaticity = Chromaticity::D65;
+
+    // Values from Bruce Lindbloom's page
+    // http://www.brucelindbloom.com/
+    #[rustfmt::skip]
+    const RGB_TO_XYZ: Matrix = Matrix::new([
+        [0.4124564, 0.3575761, 0.1804375, 0.0],
+        [0.2126729, 0.7151522, 0.0721750, 0.0],
+        [0.0193339, 0.1191920, 0.9503041, 0.0],
+        [0.0,       0.0,       0.0,       1.0],
+    ]);
+    const XYZ_TO_RGB: Matrix = consts::XYZ_TO_RGB;
+}
+
+impl ColorSpace for ACEScg {
+    const PRIMARIES: [Chromaticity; 3] = [
+        Chromaticity::new(0.713, 0.293),
+        Chromaticity::new(0.165, 0.830),
+        Chromaticity::new(0.128, 0.044),
+    ];
+    const WHITE: Chromaticity = Chromaticity::ACES;
+
+    // Values from the ACES specification, S-2014-004
+    #[rustfmt::skip]
+    const RGB_TO_XYZ: Matrix = Matrix::new([
+        [ 0.6624541811, 0.1340042065, 0.1561876870, 0.0],
+        [ 0.2722287168, 0.6740817658, 0.0536895174, 0.0],
+        [-0.0055746495, 0.0040607335, 1.0103391003, 0.0],
+        [ 0.0,          0.0,          0.0,          1.0],
+    ]);
+    #[rustfmt::skip]
+    const XYZ_TO_RGB: Matrix = Matrix::new([
+        [ 1.6410233797, -0.3248032942, -0.2364246952, 0.0],
+        [-0.6636628587,  1.6153315917,  0.0167563477, 0.0],
+        [ 0.0117218943, -0.0082844420,  0.9883948585, 0.0],
+        [ 0.0,           0.0,           0.0,          1.0],
+    ]);
+}
+
+impl ColorSpace for Rec2020 {
+    const PRIMARIES: [Chromaticity; 3] = [
+        Chromaticity::new(0.708, 0.292),
+        Chromaticity::new(0.170, 0.797),
+        Chromaticity::new(0.131, 0.046),
+    ];
+    const WHITE: Chromaticity = Chromaticity::D65;
+
+    #[rustfmt::skip]
+    const RGB_TO_XYZ: Matrix = Matrix::new([
+        [0.6369580, 0.1446169, 0.1688810, 0.0],
+        [0.2627002, 0.6779981, 0.0593017, 0.0],
+        [0.0,       0.0280727, 1.0609851, 0.0],
+        [0.0,       0.0,       0.0,       1.0],
+    ]);
+    #[rustfmt::skip]
+    const XYZ_TO_RGB: Matrix = Matrix::new([
+        [ 1.7166512, -0.3556708, -0.2533663, 0.0],
+        [-0.6666844,  1.6164812,  0.0157685, 0.0],
+        [ 0.0176399, -0.0427706,  0.9421031, 0.0],
+        [ 0.0,        0.0,        0.0,       1.0],
+    ]);
+}
+
+impl<CS: ColorSpace> Color<CS> {
+    /// Converts an XYZ color to this space.
+    ///
+    /// The XYZ value is assumed to be relative to this space's white point,
+    /// so no chromatic adaptation is done. See [`from_xyz_adapted`] otherwise.
+    ///
+    /// [`from_xyz_adapted`]: Self::from_xyz_adapted
+    #[inline]
+    pub fn from_xyz(xyz: XYZ) -> Self {
+        Self {
+            vals: CS::XYZ_TO_RGB * xyz.vals,
+            _colorspace: PhantomData,
+        }
+    }
+
+    /// Converts an XYZ color relative to the given white point to this space,
+    /// adapting it to this space's white point with [`bradford`].
+    ///
+    /// For example, spectral renders produce XYZ relative to the equal-energy
+    /// white [`Chromaticity::E`]; adapting means a spectrally flat surface
+    /// comes out neutral rather than slightly pink.
+    #[inline]
+    pub fn from_xyz_adapted(xyz: XYZ, white: Chromaticity) -> Self {
+        Self {
+            vals: CS::XYZ_TO_RGB * bradford(white, CS::WHITE) * xyz.vals,
+            _colorspace: PhantomData,
+        }
+    }
+
+    /// Converts this color to XYZ, relative to this space's white point.
+    #[inline]
+    pub fn to_xyz(self) -> XYZ {
+        Color::<CIE1931> {
+            vals: CS::RGB_TO_XYZ * self.vals,
+            _colorspace: PhantomData,
+        }
+    }
+
+    /// Converts this color to another RGB color space, adapting between white
+    /// points if they differ.
+    pub fn convert<To: ColorSpace>(self) -> Color<To> {
+        let mut m = To::XYZ_TO_RGB;
+        if CS::WHITE != To::WHITE {
+            m = m * bradford(CS::WHITE, To::WHITE);
+        }
+        Color {
+            vals: m * CS::RGB_TO_XYZ * self.vals,
+            _colorspace: PhantomData,
+        }
+    }
+}
+
+/// Derive the matrix taking linear RGB to XYZ from a color space's primaries
+/// and white point.
+///
+/// See: <http://www.brucelindbloom.com/index.html?Eqn_RGB_XYZ_Matrix.html>
+pub fn rgb_to_xyz_matrix(primaries: [Chromaticity; 3], white: Chromaticity) -> Matrix {
+    let [r, g, b] = primaries.map(|p| <[Float; 3]>::from(p.to_xyz()));
+    #[rustfmt::skip]
+    let m = Matrix::new([
+        [r[0], g[0], b[0], 0.0],
+        [r[1], g[1], b[1], 0.0],
+        [r[2], g[2], b[2], 0.0],
+        [0.0,  0.0,  0.0,  1.0],
+    ]);
+    // Scale each primary so that RGB white maps to the white point
+    let inv = m.inverse().expect("primaries are not linearly independent");
+    let [sr, sg, sb]: [Float; 3] = (inv * white.to_xyz().vals).into();
+    m * Matrix::scale(sr, sg, sb)
+}
+
+/// The Bradford chromatic adaptation transform between two white points.
+///
+/// Takes XYZ colors as seen under the `from` white to the corresponding
+/// colors under the `to` white. Works by scaling in a sharpened cone
+/// response space, which predicts corresponding colors better than scaling
+/// XYZ directly (the "wrong von Kries" transform).
+///
+/// See: <http://www.brucelindbloom.com/index.html?Eqn_ChromAdapt.html>
+pub fn bradford(from: Chromaticity, to: Chromaticity) -> Matrix {
+    #[rustfmt::skip]
+    const BRADFORD: Matrix = Matrix::new([
+        [ 0.8951,  0.2664, -0.1614, 0.0],
+        [-0.7502,  1.7135,  0.0367, 0.0],
+        [ 0.0389, -0.0685,  1.0296, 0.0],
+        [ 0.0,     0.0,     0.0,    1.0],
+    ]);
+    #[rustfmt::skip]
+    const BRADFORD_INV: Matrix = Matrix::new([
+        [ 0.9869929, -0.1470543, 0.1599627, 0.0],
+        [ 0.4323053,  0.5183603, 0.0492912, 0.0],
+        [-0.0085287,  0.0400428, 0.9684867, 0.0],
+        [ 0.0,        0.0,       0.0,       1.0],
+    ]);
+
+    let src = BRADFORD * from.to_xyz().vals;
+    let dst = BRADFORD * to.to_xyz().vals;
+    BRADFORD_INV * Matrix::scale(dst.x / src.x, dst.y / src.y, dst.z / src.z) * BRADFORD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::RGB;
+    use approx::assert_relative_eq;
+
+    fn check_matrices<CS: ColorSpace>() {
+        // Published matrices differ slightly in how they round the white point
+        let m = rgb_to_xyz_matrix(CS::PRIMARIES, CS::WHITE);
+        assert_relative_eq!(m, CS::RGB_TO_XYZ, epsilon = 1e-3);
+        assert_relative_eq!(
+            Matrix::IDENTITY,
+            CS::XYZ_TO_RGB * CS::RGB_TO_XYZ,
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn matrices_match_primaries() {
+        check_matrices::<LinearRGB>();
+        check_matrices::<ACEScg>();
+        check_matrices::<Rec2020>();
+    }
+
+    #[test]
+    fn bradford_adapts_white() {
+        assert_relative_eq!(
+            Matrix::IDENTITY,
+            bradford(Chromaticity::D65, Chromaticity::D65),
+            epsilon = 1e-6
+        );
+        let adapted =
+            bradford(Chromaticity::D65, Chromaticity::D50) * Chromaticity::D65.to_xyz().vals;
+        assert_relative_eq!(Chromaticity::D50.to_xyz().vals, adapted, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn convert_preserves_white() {
+        let white = RGB::from([1.0, 1.0, 1.0]);
+        let aces = white.convert::<ACEScg>();
+        assert_relative_eq!(crate::geo::Vector::splat(1.0), aces.vals, epsilon = 1e-3);
+
+        let rgb = RGB::from([0.2, 0.5, 0.8]);
+        let round_trip = rgb.convert::<Rec2020>().convert::<LinearRGB>();
+        assert_relative_eq!(rgb.vals, round_trip.vals, epsilon = 1e-5);
+    }
+}
@@ -0,0 +1,667 @@
+//! # A/B comparison renders.
+//!
+//! Comparing two integrators (or two sets of render settings) by eye is only
+//! fair if they see the same camera, the same resolution and, ideally, the
+//! same random numbers. A [`Comparison`] renders both configurations under
+//! the same camera, then combines them into one film, either split-screen or
+//! interleaved pixel-by-pixel, along with a difference image.
+//!
+//! ```no_run
+//! use gremlin::{camera::ThinLens, compare::{Comparison, Layout}, integrator::Hacky};
+//! use gremlin::renderer::Renderer;
+//!
+//! let integrator = Hacky::default();
+//! let cam = ThinLens::builder((800, 600)).build();
+//! let fast = Renderer::new(4).deterministic(1);
+//! let slow = Renderer::new(64).deterministic(1);
+//!
+//! let cmp: Comparison<_> = Comparison::render(
+//!     (800, 600),
+//!     &cam,
+//!     (&fast, &integrator),
+//!     (&slow, &integrator),
+//! );
+//! cmp.composite(Layout::Split(0.5)).to_snapshot().save_image("ab.png").unwrap();
+//! cmp.difference().save_image("diff.png").unwrap();
+//! ```
+//!
+//! Give both renderers the same [`deterministic`] seed, so any difference
+//! comes from the configurations rather than from the noise.
+//!
+//! Eyeballing only goes so far, though. A [`ConvergenceCurve`] measures how
+//! quickly a configuration's error falls as samples are added, against a
+//! high sample count reference, for quantitative comparisons.
+//!
+//! To choose between configurations that differ in speed as well as noise,
+//! give each the same time instead: [`EqualTime`] renders each for a fixed
+//! wall-clock budget and reports how noisy each ends up.
+//!
+//! [`deterministic`]: crate::renderer::Renderer::deterministic
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    film::{Buffer, Film},
+    integrator::Integrator,
+    metrics::{self, Timer},
+    renderer::{RenderStats, Renderer},
+    Float,
+};
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+/// How the two renders of a [`Comparison`] are combined into one film.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// `A` on the left and `B` on the right, divided at the given fraction of
+    /// the film's width.
+    Split(Float),
+    /// A checkerboard of single pixels, `A` where `x + y` is even. Both
+    /// configurations cover the whole frame, so spatially varying effects
+    /// are compared evenly; view at 1:1 or the pattern aliases.
+    Interleaved,
+}
+
+/// Two renders of the same scene and camera, for side-by-side comparison.
+pub struct Comparison<CS> {
+    /// The render from the first configuration.
+    pub a: Film<CS>,
+    /// The render from the second configuration.
+    pub b: Film<CS>,
+    pub stats_a: RenderStats,
+    pub stats_b: RenderStats,
+}
+
+impl<CS: Copy> Comparison<CS> {
+    /// Render both configurations at the given resolution.
+    ///
+    /// Each configuration is a renderer and integrator pair, so samplers,
+    /// sample counts and integrators can all differ between the two.
+    pub fn render<La, Lb>(
+        (width, height): (u32, u32),
+        cam: &impl Camera,
+        (renderer_a, integrator_a): (&Renderer, &impl Integrator<La>),
+        (renderer_b, integrator_b): (&Renderer, &impl Integrator<Lb>),
+    ) -> Self
+    where
+        Color<CS>: From<La> + From<Lb> + Copy + Send,
+    {
+        let mut a = Film::new(width, height);
+        let stats_a = renderer_a.render(&mut a, cam, integrator_a);
+        let mut b = Film::new(width, height);
+        let stats_b = renderer_b.render(&mut b, cam, integrator_b);
+        Self {
+            a,
+            b,
+            stats_a,
+            stats_b,
+        }
+    }
+
+    /// Combine both renders into a single film.
+    pub fn composite(&self, layout: Layout) -> Film<CS> {
+        let mut out = Film::new(self.a.width(), self.a.height());
+        let split = match layout {
+            Layout::Split(at) => (at.clamp(0.0, 1.0) * self.a.width() as Float) as u32,
+            Layout::Interleaved => 0,
+        };
+        let sources = self.a.iter().zip(self.b.iter());
+        for ((px, py, out), (a, b)) in out.pixel_iter_mut().zip(sources) {
+            let use_b = match layout {
+                Layout::Split(_) => px >= split,
+                Layout::Interleaved => (px + py) % 2 == 1,
+            };
+            *out = match use_b {
+                true => *b,
+                false => *a,
+            };
+        }
+        out
+    }
+
+    /// The per-channel absolute difference between the two renders.
+    pub fn difference(&self) -> Buffer<Color<CS>> {
+        let mut out = Buffer::new(self.a.width(), self.a.height());
+        for ((out, a), b) in out.iter_mut().zip(self.a.iter()).zip(self.b.iter()) {
+            let a: [Float; 3] = a.to_color().into();
+            let b: [Float; 3] = b.to_color().into();
+            *out = Color::from([
+                (a[0] - b[0]).abs(),
+                (a[1] - b[1]).abs(),
+                (a[2] - b[2]).abs(),
+            ]);
+        }
+        out
+    }
+}
+
+/// The mean squared error between an image and a reference, averaged over
+/// pixels and channels.
+///
+/// # Panics
+///
+/// Panics if the images have different dimensions.
+pub fn mse<CS: Copy>(image: &Buffer<Color<CS>>, reference: &Buffer<Color<CS>>) -> Float {
+    assert_eq!(
+        image.dimensions(),
+        reference.dimensions(),
+        "image size mismatch"
+    );
+    let errors: Vec<f64> = image
+        .iter()
+        .zip(reference.iter())
+        .map(|(&c, &r)| {
+            let c: [Float; 3] = c.into();
+            let r: [Float; 3] = r.into();
+            #[allow(clippy::unnecessary_cast)]
+            (0..3).map(|i| ((c[i] - r[i]) as f64).powi(2)).sum()
+        })
+        .collect();
+    (metrics::pairwise_sum(&errors) / (3 * errors.len()).max(1) as f64) as Float
+}
+
+/// One measurement on a [`ConvergenceCurve`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvergencePoint {
+    pub spp: u32,
+    /// Mean squared error against the reference. See [`mse`].
+    pub mse: Float,
+    /// Wall-clock render time.
+    pub time: Duration,
+}
+
+/// Error against a reference image as a function of samples per pixel.
+///
+/// Unbiased Monte Carlo error falls as `1 / spp`, so on a log-log plot the
+/// curves of two configurations are parallel lines and the gap between them
+/// is their difference in efficiency. A curve that flattens out means the
+/// configuration converges to something other than the reference: bias.
+///
+/// ```no_run
+/// use gremlin::{camera::ThinLens, compare::ConvergenceCurve, integrator::Hacky};
+/// use gremlin::renderer::Renderer;
+///
+/// let integrator = Hacky::default();
+/// let cam = ThinLens::builder((400, 300)).build();
+/// let reference = ConvergenceCurve::reference((400, 300), &cam, 4096, &integrator);
+///
+/// let curve = ConvergenceCurve::measure(
+///     &reference,
+///     &cam,
+///     |spp| Renderer::new(spp).deterministic(1),
+///     &integrator,
+///     [1, 2, 4, 8, 16, 32, 64],
+/// );
+/// curve.save_csv("hacky.csv").unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConvergenceCurve {
+    pub points: Vec<ConvergencePoint>,
+}
+
+impl ConvergenceCurve {
+    /// Render a reference image with the given number of samples per pixel.
+    ///
+    /// The reference's own noise puts a floor under the measured error, so
+    /// it should have many more samples than any point on the curve.
+    pub fn reference<CS, Li>(
+        (width, height): (u32, u32),
+        cam: &impl Camera,
+        spp: u32,
+        integrator: &impl Integrator<Li>,
+    ) -> Buffer<Color<CS>>
+    where
+        Color<CS>: From<Li> + Copy + Send,
+        CS: Copy,
+    {
+        let mut film = Film::new(width, height);
+        Renderer::new(spp).render(&mut film, cam, integrator);
+        film.to_snapshot()
+    }
+
+    /// Render at each sample count and measure the error against
+    /// `reference`.
+    ///
+    /// `renderer` builds the configuration to test for a given sample count.
+    /// Each point is rendered from scratch, so it's exactly what that
+    /// configuration would produce, at the cost of rendering some samples
+    /// more than once. With doubling sample counts that's at most twice the
+    /// work of the last point.
+    pub fn measure<CS, Li>(
+        reference: &Buffer<Color<CS>>,
+        cam: &impl Camera,
+        renderer: impl Fn(u32) -> Renderer,
+        integrator: &impl Integrator<Li>,
+        spp: impl IntoIterator<Item = u32>,
+    ) -> Self
+    where
+        Color<CS>: From<Li> + Copy + Send,
+        CS: Copy,
+    {
+        let points = spp
+            .into_iter()
+            .map(|spp| {
+                let mut film = Film::new(reference.width(), reference.height());
+                let timer = Timer::tick();
+                renderer(spp).render(&mut film, cam, integrator);
+                let time = timer.tock();
+                ConvergencePoint {
+                    spp,
+                    mse: mse(&film.to_snapshot(), reference),
+                    time,
+                }
+            })
+            .collect();
+        Self { points }
+    }
+
+    /// Write the curve as CSV, with columns `spp`, `mse` and `seconds`.
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "spp,mse,seconds")?;
+        for p in &self.points {
+            writeln!(w, "{},{:e},{}", p.spp, p.mse, p.time.as_secs_f64())?;
+        }
+        Ok(())
+    }
+
+    /// Save the curve as a CSV file. See [`write_csv`].
+    ///
+    /// [`write_csv`]: Self::write_csv
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut w)?;
+        w.flush()
+    }
+}
+
+/// One configuration's result in an [`EqualTime`] comparison.
+pub struct EqualTimeEntry<CS> {
+    pub label: String,
+    pub film: Film<CS>,
+    /// Samples per pixel reached in the time.
+    pub spp: u32,
+    /// Wall-clock render time, which overshoots the budget by up to one pass.
+    pub time: Duration,
+    /// The estimated variance of the render, averaged over pixels and
+    /// channels: its expected [`mse`] against the converged image, if
+    /// unbiased.
+    pub noise: Float,
+    pub stats: RenderStats,
+}
+
+impl<CS> EqualTimeEntry<CS> {
+    /// Render efficiency: the reciprocal of noise times seconds. Higher is
+    /// better, and doubling the time doubles neither.
+    #[inline]
+    pub fn efficiency(&self) -> Float {
+        1.0 / (self.noise * self.time.as_secs_f64() as Float)
+    }
+}
+
+/// Renders of several configurations, each given the same wall-clock time.
+///
+/// A cheap integrator that takes many noisy samples can beat an expensive
+/// one that takes a few good ones, and only an equal-time comparison shows
+/// which. Each configuration renders passes of its renderer's sample count
+/// until the time is up, alternating between two half films. The difference
+/// between the halves gives the noise, without needing a reference image.
+///
+/// ```no_run
+/// use gremlin::{camera::ThinLens, compare::EqualTime, integrator::Hacky};
+/// use gremlin::renderer::Renderer;
+/// use std::time::Duration;
+///
+/// let cam = ThinLens::builder((400, 300)).build();
+/// let shallow = Hacky::default();
+/// let mut deep = Hacky::default();
+/// deep.bounces.diffuse = 16;
+///
+/// let mut cmp = EqualTime::new((400, 300), Duration::from_secs(10));
+/// cmp.run("shallow", &cam, &Renderer::new(1), &shallow);
+/// cmp.run("deep", &cam, &Renderer::new(1), &deep);
+/// cmp.side_by_side().save_image("equal-time.png").unwrap();
+/// cmp.save_csv("equal-time.csv").unwrap();
+/// ```
+pub struct EqualTime<CS> {
+    size: (u32, u32),
+    budget: Duration,
+    pub entries: Vec<EqualTimeEntry<CS>>,
+}
+
+impl<CS: Copy> EqualTime<CS> {
+    /// Compare at the given resolution, giving each configuration `budget`.
+    pub fn new(size: (u32, u32), budget: Duration) -> Self {
+        Self {
+            size,
+            budget,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Render a configuration for the time budget, and add it to the
+    /// comparison under `label`.
+    ///
+    /// At least two passes are always rendered, to estimate noise. A
+    /// [`deterministic`] renderer has its [`frame`] index advanced each pass,
+    /// so the passes have different samples.
+    ///
+    /// [`deterministic`]: Renderer::deterministic
+    /// [`frame`]: Renderer::frame
+    pub fn run<Li>(
+        &mut self,
+        label: impl Into<String>,
+        cam: &impl Camera,
+        renderer: &Renderer,
+        integrator: &impl Integrator<Li>,
+    ) -> &EqualTimeEntry<CS>
+    where
+        Color<CS>: From<Li> + Copy + Send,
+    {
+        let (width, height) = self.size;
+        let mut halves = [Film::new(width, height), Film::new(width, height)];
+        let mut passes = [0u32; 2];
+        let mut stats = RenderStats::default();
+        let timer = Timer::tick();
+        let mut pass = 0u32;
+        while pass < 2 || timer.tock() < self.budget {
+            let half = pass as usize % 2;
+            let renderer = renderer.clone().frame(pass as u64);
+            let pass_stats = renderer.render(&mut halves[half], cam, integrator);
+            stats.failed_tiles.extend(pass_stats.failed_tiles);
+            passes[half] += 1;
+            pass += 1;
+        }
+        let time = timer.tock();
+
+        // The halves differ by the sum of their variances. Scale that down to
+        // the variance of their merged average.
+        let (na, nb) = (passes[0] as Float, passes[1] as Float);
+        let diff = mse(&halves[0].to_snapshot(), &halves[1].to_snapshot());
+        let noise = diff * na * nb / ((na + nb) * (na + nb));
+
+        let [mut film, other] = halves;
+        film.merge(&other);
+        self.entries.push(EqualTimeEntry {
+            label: label.into(),
+            film,
+            spp: pass * renderer.spp(),
+            time,
+            noise,
+            stats,
+        });
+        self.entries.last().unwrap()
+    }
+
+    /// The renders next to each other, left to right in the order they were
+    /// run, each labeled with its name and sample count in the top left.
+    pub fn side_by_side(&self) -> Buffer<Color<CS>> {
+        let (width, height) = self.size;
+        let count = self.entries.len() as u32;
+        let mut out = Buffer::new(width * count, height);
+        for (i, entry) in self.entries.iter().enumerate() {
+            let snapshot = entry.film.to_snapshot();
+            let x0 = i as u32 * width;
+            for (x, y, &color) in snapshot.pixel_iter() {
+                out[(y * width * count + x0 + x) as usize] = color;
+            }
+            let label = format!("{} {}spp", entry.label, entry.spp);
+            draw_label(&mut out, x0, width, &label);
+        }
+        out
+    }
+
+    /// Write the noise statistics as CSV, with columns `label`, `spp`,
+    /// `seconds`, `noise` and `efficiency`. Labels are quoted if need be.
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "label,spp,seconds,noise,efficiency")?;
+        for e in &self.entries {
+            writeln!(
+                w,
+                "{},{},{},{:e},{:e}",
+                csv_field(&e.label),
+                e.spp,
+                e.time.as_secs_f64(),
+                e.noise,
+                e.efficiency()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Save the noise statistics as a CSV file. See [`write_csv`].
+    ///
+    /// [`write_csv`]: Self::write_csv
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut w)?;
+        w.flush()
+    }
+}
+
+// Quote a CSV field if it has commas, quotes or line breaks
+fn csv_field(field: &str) -> Cow<'_, str> {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")).into(),
+        false => field.into(),
+    }
+}
+
+// Glyphs 3 pixels wide and 5 tall, a row of 3 bits each, top first. Lower
+// case letters are drawn as upper case, and anything missing as `?`.
+const GLYPHS: &[(char, [u8; 5])] = &[
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+    (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('?', [0b111, 0b001, 0b010, 0b000, 0b010]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+];
+
+// Draw `text` in white with a black shadow, in the top left corner of the
+// panel `panel_width` wide starting at column `x0`. Glyphs are scaled up with
+// the image, and clipped to the panel.
+fn draw_label<CS>(out: &mut Buffer<Color<CS>>, x0: u32, panel_width: u32, text: &str) {
+    let (width, height) = out.dimensions();
+    let right = (x0 + panel_width).min(width);
+    let scale = (height / 150).max(1);
+    let glyph = |c: char| {
+        let c = c.to_ascii_uppercase();
+        let idx = GLYPHS
+            .binary_search_by_key(&c, |&(g, _)| g)
+            .or_else(|_| GLYPHS.binary_search_by_key(&'?', |&(g, _)| g))
+            .unwrap();
+        GLYPHS[idx].1
+    };
+    let margin = 2 * scale;
+    for (pass, color) in [(1, 0.0), (0, 1.0)] {
+        for (i, c) in text.chars().enumerate() {
+            let rows = glyph(c);
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    let gx = x0 + margin + (i as u32 * 4 + col) * scale + pass * scale;
+                    let gy = margin + row as u32 * scale + pass * scale;
+                    for y in gy..(gy + scale).min(height) {
+                        for x in gx..(gx + scale).min(right) {
+                            out[(y * width + x) as usize] = Color::from([color; 3]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::ThinLens, color::RGB, film::RGBFilm, geo::Ray};
+    use rand::Rng;
+
+    struct Flat(Float);
+
+    impl Integrator<RGB> for Flat {
+        fn radiance(&self, _ray: &Ray, _rng: &mut impl Rng) -> RGB {
+            RGB::from([self.0, self.0, self.0])
+        }
+    }
+
+    // Uniform noise in [0, 1), with variance 1/12
+    struct Noise;
+
+    impl Integrator<RGB> for Noise {
+        fn radiance(&self, _ray: &Ray, rng: &mut impl Rng) -> RGB {
+            let v = rng.gen();
+            RGB::from([v, v, v])
+        }
+    }
+
+    #[test]
+    fn layouts_and_difference() {
+        let cam = ThinLens::builder((4, 2)).build();
+        let renderer = Renderer::new(1).deterministic(3);
+        let cmp: Comparison<_> = Comparison::render(
+            (4, 2),
+            &cam,
+            (&renderer, &Flat(0.25)),
+            (&renderer, &Flat(1.0)),
+        );
+        assert!(cmp.stats_a.is_ok() && cmp.stats_b.is_ok());
+
+        let value = |film: &RGBFilm| -> Vec<Float> {
+            film.iter().map(|p| p.to_color().max_component()).collect()
+        };
+        assert_eq!(
+            vec![0.25, 0.25, 1.0, 1.0, 0.25, 0.25, 1.0, 1.0],
+            value(&cmp.composite(Layout::Split(0.5)))
+        );
+        assert_eq!(
+            vec![0.25, 1.0, 0.25, 1.0, 1.0, 0.25, 1.0, 0.25],
+            value(&cmp.composite(Layout::Interleaved))
+        );
+        assert!(cmp
+            .difference()
+            .iter()
+            .all(|&c| c == RGB::from([0.75, 0.75, 0.75])));
+    }
+
+    #[test]
+    fn convergence_curve() {
+        let cam = ThinLens::builder((32, 32)).build();
+        let reference: Buffer<RGB> = ConvergenceCurve::reference((32, 32), &cam, 1, &Flat(0.5));
+        assert_eq!(0.0, mse(&reference, &reference));
+
+        let curve = ConvergenceCurve::measure(
+            &reference,
+            &cam,
+            |spp| Renderer::new(spp).deterministic(5),
+            &Noise,
+            [1, 16],
+        );
+        let (low, high) = (curve.points[0], curve.points[1]);
+        assert_eq!((1, 16), (low.spp, high.spp));
+        assert!((low.mse - 1.0 / 12.0).abs() < 0.01);
+        assert!((high.mse - 1.0 / 192.0).abs() < 0.001);
+
+        let mut csv = Vec::new();
+        curve.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(3, csv.lines().count());
+        assert!(csv.starts_with("spp,mse,seconds\n1,"));
+    }
+
+    #[test]
+    fn equal_time() {
+        let cam = ThinLens::builder((32, 16)).build();
+        let mut cmp = EqualTime::new((32, 16), Duration::ZERO);
+        let renderer = Renderer::new(4).deterministic(2);
+        let flat = cmp.run("flat", &cam, &renderer, &Flat(0.5));
+        assert_eq!((8, 0.0), (flat.spp, flat.noise));
+        let noisy = cmp.run("noisy", &cam, &renderer, &Noise);
+        assert_eq!(8, noisy.spp);
+        // Uniform noise has variance 1/12, averaged over 8 samples
+        let expected = 1.0 / 96.0;
+        assert!(
+            (noisy.noise - expected).abs() < 0.2 * expected,
+            "{}",
+            noisy.noise
+        );
+
+        // Labeled panels, next to each other
+        let image = cmp.side_by_side();
+        assert_eq!((64, 16), image.dimensions());
+        let value = |x: u32, y: u32| image[(y * 64 + x) as usize].max_component();
+        assert_eq!(0.5, value(20, 12));
+        assert_eq!(1.0, value(2, 2));
+        assert_eq!(0.0, value(3, 3));
+        assert_eq!(1.0, value(34, 2));
+
+        let mut csv = Vec::new();
+        cmp.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("label,spp,seconds,noise,efficiency\nflat,8,"));
+        assert!(csv.contains("\nnoisy,8,"));
+
+        cmp.run("say \"hi\", twice", &cam, &renderer, &Flat(0.5));
+        let mut csv = Vec::new();
+        cmp.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains("\n\"say \"\"hi\"\", twice\",8,"), "{}", csv);
+    }
+
+    #[test]
+    fn labels_clipped_to_panel() {
+        let mut out = Buffer::<RGB>::new(40, 10);
+        draw_label(&mut out, 0, 20, "wwwwwwwwww");
+        assert!(out.pixel_iter().any(|(_, _, p)| p.max_component() > 0.0));
+        assert!(out
+            .pixel_iter()
+            .all(|(x, _, p)| x < 20 || p.max_component() == 0.0));
+    }
+}
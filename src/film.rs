@@ -8,6 +8,8 @@
 //! easier to use the [`RGBFilm`] or [`SpectralFilm`] typedefs, as they support
 //! the most common operations needed for raytracing (aggregating color values
 //! on a per-pixel basis, and taking snapshots of average pixel values ).
+//! [`SplatFilm`] instead collects light added from many threads at arbitrary
+//! positions, for integrators that trace paths from the lights.
 //!
 //! ```no_run
 //! use gremlin::film::RGBFilm;
@@ -26,20 +28,34 @@
 //! the upper-left to `(width-1, height-1)` in the lower right.
 
 use crate::{
-    color::{Color, LinearRGB, CIE1931, SRGB},
+    aov::{Colormap, FalseColor},
+    color::{Color, LinearRGB, OutputTransform, CIE1931, RGB, SRGB},
+    metrics::Counter,
     Float,
 };
-use image::{ImageResult, Rgb, RgbImage};
+use image::{ImageError, ImageResult, Rgb, RgbImage};
 use rayon::prelude::*;
 use std::{
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
     ops::{Deref, DerefMut},
     path::Path,
 };
 
+mod metadata;
+pub use metadata::*;
+
+mod splat;
+pub use splat::*;
+
+mod tiled;
+pub use tiled::*;
+
 /// A rectangular grid of pixels.
 pub struct Buffer<P> {
     width: u32,
     height: u32,
+    pixel_aspect: Float,
     pixels: Vec<P>,
 }
 
@@ -55,6 +71,7 @@ impl<P> Buffer<P> {
         Self {
             width,
             height,
+            pixel_aspect: 1.0,
             pixels,
         }
     }
@@ -78,19 +95,165 @@ impl<P> Buffer<P> {
         self.width as Float / self.height as Float
     }
 
+    /// The pixel aspect ratio (the `width`/`height` of a single pixel).
+    ///
+    /// `1` for square pixels, the default. Anamorphic and some broadcast
+    /// formats store images with wider or narrower pixels, stretched back
+    /// out on display.
+    pub fn pixel_aspect(&self) -> Float {
+        self.pixel_aspect
+    }
+
+    /// Set the pixel aspect ratio. It doesn't affect the pixels themselves,
+    /// just the metadata written by [`save_image`], so should match the
+    /// camera's.
+    ///
+    /// [`save_image`]: Self::save_image
+    pub fn set_pixel_aspect(&mut self, pixel_aspect: Float) {
+        self.pixel_aspect = pixel_aspect;
+    }
+
+    /// The aspect ratio the buffer is displayed at, taking non-square pixels
+    /// into account.
+    pub fn display_aspect_ratio(&self) -> Float {
+        self.aspect_ratio() * self.pixel_aspect
+    }
+
     /// Save the buffer as an image at the path specified.
     ///
-    /// Image format is derived from the file extension.
+    /// Image format is derived from the file extension. PNGs record a
+    /// non-square [`pixel_aspect`] in their `pHYs` chunk; other formats
+    /// don't record it.
+    ///
+    /// [`pixel_aspect`]: Self::pixel_aspect
     pub fn save_image<Q>(&self, path: Q) -> ImageResult<()>
     where
         Q: AsRef<Path>,
         P: SRGB,
+    {
+        let path = path.as_ref();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if is_png && self.pixel_aspect != 1.0 {
+            return self.save_png(path, None);
+        }
+        self.to_image().save(path)
+    }
+
+    /// Save the buffer as an image at the path specified, embedding the
+    /// given render metadata.
+    ///
+    /// As [`save_image`], except PNGs also store the [`Metadata`] entries as
+    /// text chunks. Other formats are saved without it.
+    ///
+    /// [`save_image`]: Self::save_image
+    pub fn save_image_with_metadata<Q>(&self, path: Q, metadata: &Metadata) -> ImageResult<()>
+    where
+        Q: AsRef<Path>,
+        P: SRGB,
+    {
+        let path = path.as_ref();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        match is_png {
+            true => self.save_png(path, Some(metadata)),
+            false => self.to_image().save(path),
+        }
+    }
+
+    // The image crate can't write `pHYs` or text chunks, so go through the
+    // png crate
+    fn save_png(&self, path: &Path, metadata: Option<&Metadata>) -> ImageResult<()>
+    where
+        P: SRGB,
+    {
+        let to_image_err = |e: png::EncodingError| match e {
+            png::EncodingError::IoError(e) => ImageError::IoError(e),
+            e => ImageError::IoError(io::Error::other(e)),
+        };
+        let w = BufWriter::new(fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(to_image_err)?;
+
+        if self.pixel_aspect != 1.0 {
+            // Pixels per unit, so wide pixels mean fewer of them along `x`
+            let (xppu, yppu) = pixels_per_unit(self.pixel_aspect);
+            let mut phys = [0u8; 9];
+            phys[..4].copy_from_slice(&xppu.to_be_bytes());
+            phys[4..8].copy_from_slice(&yppu.to_be_bytes());
+            writer
+                .write_chunk(png::chunk::pHYs, &phys)
+                .map_err(to_image_err)?;
+        }
+        if let Some(metadata) = metadata {
+            metadata.write_png(&mut writer).map_err(to_image_err)?;
+        }
+        writer
+            .write_image_data(&self.to_image())
+            .map_err(to_image_err)
+    }
+
+    /// Convert the buffer to an 8-bit sRGB image.
+    pub fn to_image(&self) -> RgbImage
+    where
+        P: SRGB,
     {
         RgbImage::from_fn(self.width, self.height, |x, y| {
             let idx = ((y * self.width) + x) as usize;
             Rgb::<u8>::from(self.pixels[idx].to_srgb())
         })
-        .save(path)
+    }
+
+    /// Convert the buffer to an 8-bit sRGB image through an output
+    /// transform, such as the ACES tone curve, rather than clipping. A
+    /// transform set to [`dither`] removes the banding smooth gradients,
+    /// like a sky, otherwise get.
+    ///
+    /// Typically called on a [`to_snapshot`] of a film.
+    ///
+    /// [`to_snapshot`]: Buffer::to_snapshot
+    /// [`dither`]: OutputTransform::dither
+    pub fn to_image_with(&self, transform: &OutputTransform) -> RgbImage
+    where
+        P: Copy + Into<RGB>,
+    {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let idx = ((y * self.width) + x) as usize;
+            Rgb::<u8>::from(transform.to_srgb_at(self.pixels[idx], x, y))
+        })
+    }
+
+    /// Copy out the region from `(x0, y0)` (inclusive) to `(x1, y1)`
+    /// (exclusive) as a new buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is empty or extends outside the buffer.
+    pub fn crop(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> Self
+    where
+        P: Clone,
+    {
+        assert!(x0 < x1 && x1 <= self.width, "crop x range out of bounds");
+        assert!(y0 < y1 && y1 <= self.height, "crop y range out of bounds");
+
+        let pixels = (y0..y1)
+            .flat_map(|y| {
+                let row = (y * self.width) as usize;
+                self.pixels[row + x0 as usize..row + x1 as usize]
+                    .iter()
+                    .cloned()
+            })
+            .collect();
+        Self {
+            width: x1 - x0,
+            height: y1 - y0,
+            pixel_aspect: self.pixel_aspect,
+            pixels,
+        }
     }
 
     /// Returns an iterator over the pixels.
@@ -142,6 +305,17 @@ impl<P> Buffer<P> {
     }
 }
 
+// `pHYs` pixels per unit for a pixel aspect ratio. Only the ratio matters,
+// since the unit is unspecified.
+fn pixels_per_unit(pixel_aspect: Float) -> (u32, u32) {
+    const SCALE: Float = 10_000.0;
+    if pixel_aspect >= 1.0 {
+        (SCALE as u32, (pixel_aspect * SCALE).round() as u32)
+    } else {
+        ((SCALE / pixel_aspect).round() as u32, SCALE as u32)
+    }
+}
+
 // DEREFS
 
 impl<P> Deref for Buffer<P> {
@@ -158,11 +332,55 @@ impl<P> DerefMut for Buffer<P> {
     }
 }
 
+/// Number of samples with a NaN or infinite component added to any pixel.
+pub static NONFINITE_SAMPLES: Counter = Counter::new();
+
+/// Number of samples with a negative component added to any pixel.
+///
+/// Not always a bug: converting a saturated spectral color to RGB can
+/// legitimately give a negative component. But radiance itself is never
+/// negative, so a sudden jump here is worth investigating.
+pub static NEGATIVE_SAMPLES: Counter = Counter::new();
+
+/// What to do with samples that have a NaN or infinite component.
+///
+/// A single NaN sample poisons a pixel's average for the rest of the render,
+/// and an infinite one turns it white, so by default such samples are
+/// replaced with black (as PBRT does). Either way they are counted in
+/// [`NONFINITE_SAMPLES`] and in the pixel's [`invalid_count`].
+///
+/// [`invalid_count`]: Pixel::invalid_count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidSamples {
+    /// Accumulate the sample as-is. Useful to make problem pixels stand out.
+    Keep,
+    /// Replace the sample with black, still counting it towards the average.
+    #[default]
+    Zero,
+    /// Drop the sample entirely.
+    Discard,
+}
+
 /// A pixel that aggregates values from a given color space.
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Pixel<CS> {
     sum: Color<CS>,
+    // Sum of the samples' squares, component-wise, for the variance
+    sum_sq: Color<CS>,
     count: u32,
+    invalid: u32,
+}
+
+impl<CS> Default for Pixel<CS> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            sum: Color::default(),
+            sum_sq: Color::default(),
+            count: 0,
+            invalid: 0,
+        }
+    }
 }
 
 impl<CS: Copy> Pixel<CS> {
@@ -173,16 +391,194 @@ impl<CS: Copy> Pixel<CS> {
     }
 
     /// Add a sample to this pixel.
+    ///
+    /// NaN and infinite samples are replaced with black; see
+    /// [`InvalidSamples`].
     #[inline]
     pub fn add_sample<S>(&mut self, sample: S)
     where
         Color<CS>: From<S>,
     {
-        self.sum += sample.into();
-        self.count += 1;
+        self.add_sample_with(sample, InvalidSamples::Zero);
+    }
+
+    /// Add a sample to this pixel, handling NaN and infinite samples with the
+    /// given policy.
+    #[inline]
+    pub fn add_sample_with<S>(&mut self, sample: S, policy: InvalidSamples)
+    where
+        Color<CS>: From<S>,
+    {
+        if let Some(sample) = self.check(Color::from(sample), policy) {
+            self.sum += sample;
+            self.sum_sq += sample * sample;
+            self.count += 1;
+        }
+    }
+
+    /// Add a batch of samples, rejecting outliers with a median of means.
+    ///
+    /// The samples are dealt into `buckets` groups and each group averaged.
+    /// The pixel then gets the median of those averages, component by
+    /// component, as if every sample had that value. A firefly lands in a
+    /// single group, and the median ignores it where the plain average would
+    /// be dragged up by it. With one bucket this is the plain average.
+    ///
+    /// Unlike clamping, this needs no threshold to tune. But it's still
+    /// biased, darkening pixels whose light mostly comes from rare bright
+    /// paths, such as caustics, so each bucket needs a fair number of
+    /// samples. NaN and infinite samples are handled with `policy` first.
+    pub fn add_median_of_means<S>(
+        &mut self,
+        samples: impl IntoIterator<Item = S>,
+        buckets: usize,
+        policy: InvalidSamples,
+    ) where
+        Color<CS>: From<S>,
+    {
+        let samples: Vec<_> = samples
+            .into_iter()
+            .filter_map(|s| self.check(Color::from(s), policy))
+            .collect();
+        if samples.is_empty() {
+            return;
+        }
+
+        let buckets = buckets.clamp(1, samples.len());
+        let means: Vec<[Float; 3]> = (0..buckets)
+            .map(|b| {
+                let group = samples.iter().skip(b).step_by(buckets);
+                let n = group.len() as Float;
+                (group.fold(Color::default(), |sum, &c| sum + c) / n).into()
+            })
+            .collect();
+        let median = |i: usize| {
+            let mut vals: Vec<Float> = means.iter().map(|m| m[i]).collect();
+            vals.sort_by(Float::total_cmp);
+            let mid = vals.len() / 2;
+            match vals.len() % 2 {
+                0 => (vals[mid - 1] + vals[mid]) / 2.0,
+                _ => vals[mid],
+            }
+        };
+
+        let n = samples.len() as u32;
+        let median = <Color<CS> as From<[Float; 3]>>::from([median(0), median(1), median(2)]);
+        self.sum += median * n as Float;
+        self.sum_sq += median * median * n as Float;
+        self.count += n;
+    }
+
+    // Count a sample, and apply `policy` if it's NaN or infinite. `None` if
+    // it should be dropped.
+    #[inline]
+    fn check(&mut self, mut sample: Color<CS>, policy: InvalidSamples) -> Option<Color<CS>> {
+        if !sample.is_finite() {
+            NONFINITE_SAMPLES.inc();
+            self.invalid += 1;
+            match policy {
+                InvalidSamples::Keep => {}
+                InvalidSamples::Zero => sample = Color::default(),
+                InvalidSamples::Discard => return None,
+            }
+        } else if sample.min_component() < 0.0 {
+            NEGATIVE_SAMPLES.inc();
+        }
+        Some(sample)
+    }
+
+    /// Fold the samples of another pixel into this one.
+    #[inline]
+    pub fn merge(&mut self, other: &Self) {
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        self.count += other.count;
+        self.invalid += other.invalid;
+    }
+
+    /// The number of samples added to this pixel.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The sample variance of the samples added to this pixel, component by
+    /// component. Zero with fewer than two samples.
+    ///
+    /// Samples added by [`add_median_of_means`] count as the median they were
+    /// replaced with, so show no spread.
+    ///
+    /// [`add_median_of_means`]: Self::add_median_of_means
+    #[inline]
+    pub fn variance(&self) -> Color<CS> {
+        if self.count < 2 {
+            return Color::default();
+        }
+        let n = self.count as Float;
+        let mean: [Float; 3] = (self.sum / n).into();
+        let mean_sq: [Float; 3] = (self.sum_sq / n).into();
+        // Rounding can leave a tiny negative where there's no spread
+        Color::from([0, 1, 2].map(|i| ((mean_sq[i] - mean[i] * mean[i]) * n / (n - 1.0)).max(0.0)))
+    }
+
+    /// The number of NaN or infinite samples added to this pixel, whether or
+    /// not they were kept.
+    #[inline]
+    pub fn invalid_count(&self) -> u32 {
+        self.invalid
+    }
+
+    // Serialized as 3 `f64` components of the sum, then of the sum of
+    // squares, then `u32` sample and invalid sample counts, all
+    // little-endian, regardless of the `Float` type.
+    fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        let sum: [Float; 3] = self.sum.into();
+        let sum_sq: [Float; 3] = self.sum_sq.into();
+        for c in sum.into_iter().chain(sum_sq) {
+            #[allow(clippy::unnecessary_cast)]
+            w.write_all(&(c as f64).to_le_bytes())?;
+        }
+        w.write_all(&self.count.to_le_bytes())?;
+        w.write_all(&self.invalid.to_le_bytes())
+    }
+
+    fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        Self::read_versioned(r, true)
+    }
+
+    // Read a pixel, which in film states before version 3 has no sum of
+    // squares
+    fn read_versioned(r: &mut impl Read, with_sum_sq: bool) -> io::Result<Self> {
+        let read_color = |r: &mut dyn Read| -> io::Result<Color<CS>> {
+            let mut buf = [0u8; 3 * 8];
+            r.read_exact(&mut buf)?;
+            let component =
+                |i: usize| f64::from_le_bytes(buf[i * 8..i * 8 + 8].try_into().unwrap()) as Float;
+            Ok(Color::from([component(0), component(1), component(2)]))
+        };
+        let sum = read_color(r)?;
+        let sum_sq = match with_sum_sq {
+            true => read_color(r)?,
+            false => Color::default(),
+        };
+        let mut counts = [0u8; 8];
+        r.read_exact(&mut counts)?;
+        Ok(Self {
+            sum,
+            sum_sq,
+            count: u32::from_le_bytes(counts[..4].try_into().unwrap()),
+            invalid: u32::from_le_bytes(counts[4..].try_into().unwrap()),
+        })
     }
 }
 
+/// Leading bytes of a saved film state file.
+const STATE_MAGIC: &[u8; 4] = b"GRMF";
+
+/// Version of the film state format. Version 1 had no pixel aspect, and
+/// versions before 3 no per-pixel sums of squares.
+const STATE_VERSION: u32 = 3;
+
 /// Convenience typedef for a buffer of pixels in a given color space.
 pub type Film<CS> = Buffer<Pixel<CS>>;
 
@@ -197,20 +593,310 @@ pub type RGBFilm = Buffer<Pixel<LinearRGB>>;
 pub type SpectralFilm = Buffer<Pixel<CIE1931>>;
 
 impl<CS: Copy> Buffer<Pixel<CS>> {
+    /// Fold the samples of another film into this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the films have different dimensions.
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(self.dimensions(), other.dimensions(), "film size mismatch");
+        for (pixel, other) in self.pixels.iter_mut().zip(&other.pixels) {
+            pixel.merge(other);
+        }
+    }
+
+    /// Merge a collection of films into one, deterministically.
+    ///
+    /// Floating-point addition isn't associative, so the result of merging
+    /// depends on the order films are combined in. This always reduces in the
+    /// same fixed pairwise tree (`0+1`, `2+3`, ... then again on the results),
+    /// so the output is bit-exact for a given input order no matter how many
+    /// threads do the work. Pairwise summation also accumulates less rounding
+    /// error than a running sum.
+    ///
+    /// Returns `None` if `films` is empty.
+    pub fn merge_pairwise(mut films: Vec<Self>) -> Option<Self>
+    where
+        CS: Send + Sync,
+    {
+        while films.len() > 1 {
+            let mut iter = films.into_iter();
+            let mut pairs = Vec::new();
+            let mut leftover = None;
+            while let Some(lhs) = iter.next() {
+                match iter.next() {
+                    Some(rhs) => pairs.push((lhs, rhs)),
+                    None => leftover = Some(lhs),
+                }
+            }
+            films = pairs
+                .into_par_iter()
+                .map(|(mut lhs, rhs)| {
+                    lhs.merge(&rhs);
+                    lhs
+                })
+                .collect();
+            films.extend(leftover);
+        }
+        films.pop()
+    }
+
+    /// The raster coordinates of every pixel that received a NaN or infinite
+    /// sample, for tracking down where they come from.
+    pub fn invalid_pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.pixel_iter()
+            .filter(|(_, _, p)| p.invalid > 0)
+            .map(|(px, py, _)| (px, py))
+    }
+
+    /// The number of samples each pixel has received, as a scalar image.
+    pub fn sample_counts(&self) -> Buffer<Float> {
+        let mut counts = Buffer::new(self.width, self.height);
+        counts.set_pixel_aspect(self.pixel_aspect);
+        for (out, pixel) in counts.iter_mut().zip(&self.pixels) {
+            *out = pixel.count as Float;
+        }
+        counts
+    }
+
+    /// A heatmap of where the renderer spent its samples: black for pixels
+    /// with none, through to pale yellow for the most sampled.
+    ///
+    /// With a fixed sample count it's a flat color, apart from crop windows,
+    /// failed tiles and [`Discard`]ed samples. To compare films, or pick
+    /// another colormap, color the [`sample_counts`] with a [`FalseColor`]
+    /// directly.
+    ///
+    /// [`Discard`]: InvalidSamples::Discard
+    /// [`sample_counts`]: Self::sample_counts
+    pub fn sample_heatmap(&self) -> Buffer<RGB> {
+        let max = self.pixels.iter().map(|p| p.count).max().unwrap_or(0);
+        FalseColor::new(Colormap::Inferno)
+            .range(0.0, max.max(1) as Float)
+            .apply(&self.sample_counts())
+    }
+
+    /// The variance of each pixel's average, the mean of its components'
+    /// [`variance`]s over its sample count, as a scalar image. This is how
+    /// noisy each pixel still is.
+    ///
+    /// [`variance`]: Pixel::variance
+    pub fn sample_variances(&self) -> Buffer<Float> {
+        let mut variances = Buffer::new(self.width, self.height);
+        variances.set_pixel_aspect(self.pixel_aspect);
+        for (out, pixel) in variances.iter_mut().zip(&self.pixels) {
+            let v: [Float; 3] = pixel.variance().into();
+            *out = v.iter().sum::<Float>() / 3.0 / (pixel.count as Float).max(1.0);
+        }
+        variances
+    }
+
+    /// A heatmap of where the image is still noisy, from the
+    /// [`sample_variances`]: black for pixels with no variance, through to
+    /// pale yellow for the noisiest.
+    ///
+    /// [`sample_variances`]: Self::sample_variances
+    pub fn variance_heatmap(&self) -> Buffer<RGB> {
+        let variances = self.sample_variances();
+        let max = variances.iter().copied().fold(0.0, Float::max);
+        let max = if max > 0.0 { max } else { 1.0 };
+        FalseColor::new(Colormap::Inferno)
+            .range(0.0, max)
+            .apply(&variances)
+    }
+
+    /// Save the film's accumulated state to a file, so the render can be
+    /// resumed later with [`load_state`].
+    ///
+    /// Unlike a [`snapshot`], this keeps per-pixel sums, sums of squares and
+    /// sample counts, so further samples (or other films, via [`merge`]) can
+    /// be added on top with the correct weighting.
+    ///
+    /// [`load_state`]: Self::load_state
+    /// [`snapshot`]: Self::to_snapshot
+    /// [`merge`]: Self::merge
+    pub fn save_state(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BufWriter::new(fs::File::create(path)?);
+        w.write_all(STATE_MAGIC)?;
+        w.write_all(&STATE_VERSION.to_le_bytes())?;
+        w.write_all(&self.width.to_le_bytes())?;
+        w.write_all(&self.height.to_le_bytes())?;
+        #[allow(clippy::unnecessary_cast)]
+        w.write_all(&(self.pixel_aspect as f64).to_le_bytes())?;
+        for pixel in &self.pixels {
+            pixel.write_to(&mut w)?;
+        }
+        w.flush()
+    }
+
+    /// Load a film's state saved with [`save_state`].
+    ///
+    /// States saved before the [`pixel_aspect`] was recorded load with square
+    /// pixels, and those saved before sums of squares were recorded report no
+    /// [`variance`][Pixel::variance] for the samples taken so far.
+    ///
+    /// [`pixel_aspect`]: Buffer::pixel_aspect
+    /// [`save_state`]: Self::save_state
+    pub fn load_state(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut r = BufReader::new(fs::File::open(path)?);
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != STATE_MAGIC {
+            return Err(invalid("not a film state file"));
+        }
+        let mut word = [0u8; 4];
+        let mut read_u32 = |r: &mut BufReader<fs::File>| -> io::Result<u32> {
+            r.read_exact(&mut word)?;
+            Ok(u32::from_le_bytes(word))
+        };
+        let version = read_u32(&mut r)?;
+        if !(1..=STATE_VERSION).contains(&version) {
+            return Err(invalid("unsupported film state version"));
+        }
+        let width = read_u32(&mut r)?;
+        let height = read_u32(&mut r)?;
+        let pixel_aspect = match version {
+            1 => 1.0,
+            _ => {
+                let mut buf = [0u8; 8];
+                r.read_exact(&mut buf)?;
+                f64::from_le_bytes(buf) as Float
+            }
+        };
+        if !pixel_aspect.is_normal() || pixel_aspect < 0.0 {
+            return Err(invalid("pixel aspect must be positive"));
+        }
+
+        let pixels = (0..(width as usize * height as usize))
+            .map(|_| Pixel::read_versioned(&mut r, version >= 3))
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            width,
+            height,
+            pixel_aspect,
+            pixels,
+        })
+    }
+
     /// Creates a snapshot of the buffer's values.
     pub fn to_snapshot(&self) -> Buffer<Color<CS>> {
         Buffer {
             width: self.width,
             height: self.height,
+            pixel_aspect: self.pixel_aspect,
             pixels: self.pixels.iter().map(|p| p.to_color()).collect(),
         }
     }
+
+    /// Write a snapshot of the buffer's values into `buf`, without
+    /// allocating.
+    ///
+    /// For embedding in applications that own the framebuffer. Pixels are
+    /// written row by row from the top, tightly packed in the given
+    /// `format`. A `u8` buffer gets 8-bit sRGB, as [`to_image`] produces; an
+    /// `f32` buffer gets linear RGB, unclamped, for the caller to tone map.
+    /// Alpha is always opaque.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` isn't exactly `width * height` pixels long.
+    ///
+    /// [`to_image`]: Buffer::to_image
+    pub fn snapshot_into<T>(&self, buf: &mut [T], format: SnapshotFormat)
+    where
+        T: SnapshotChannel,
+        Color<CS>: SRGB + Into<RGB>,
+        CS: Send + Sync,
+    {
+        let channels = format.channels();
+        assert_eq!(
+            self.pixels.len() * channels,
+            buf.len(),
+            "snapshot buffer size mismatch"
+        );
+        buf.par_chunks_mut(channels)
+            .zip(self.pixels.par_iter())
+            .for_each(|(out, pixel)| {
+                let [r, g, b] = T::encode(pixel.to_color());
+                match format {
+                    SnapshotFormat::Rgb => out.copy_from_slice(&[r, g, b]),
+                    SnapshotFormat::Rgba => out.copy_from_slice(&[r, g, b, T::OPAQUE]),
+                    SnapshotFormat::Bgra => out.copy_from_slice(&[b, g, r, T::OPAQUE]),
+                }
+            });
+    }
+}
+
+/// Channel layout of the pixels written by [`snapshot_into`].
+///
+/// [`snapshot_into`]: Buffer::snapshot_into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotFormat {
+    #[default]
+    Rgb,
+    Rgba,
+    /// Common for window system and GPU surface framebuffers.
+    Bgra,
+}
+
+impl SnapshotFormat {
+    /// The number of channels per pixel.
+    #[inline]
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::Rgb => 3,
+            Self::Rgba | Self::Bgra => 4,
+        }
+    }
+}
+
+/// A channel type that [`snapshot_into`] can write.
+///
+/// [`snapshot_into`]: Buffer::snapshot_into
+pub trait SnapshotChannel: Copy + Send {
+    /// The value of a fully opaque alpha channel.
+    const OPAQUE: Self;
+
+    /// Encode a color as red, green and blue channels.
+    fn encode<CS>(color: Color<CS>) -> [Self; 3]
+    where
+        Color<CS>: SRGB + Into<RGB>;
+}
+
+/// 8-bit sRGB.
+impl SnapshotChannel for u8 {
+    const OPAQUE: Self = u8::MAX;
+
+    #[inline]
+    fn encode<CS>(color: Color<CS>) -> [Self; 3]
+    where
+        Color<CS>: SRGB + Into<RGB>,
+    {
+        color.to_srgb()
+    }
+}
+
+/// Linear RGB.
+impl SnapshotChannel for f32 {
+    const OPAQUE: Self = 1.0;
+
+    #[inline]
+    fn encode<CS>(color: Color<CS>) -> [Self; 3]
+    where
+        Color<CS>: SRGB + Into<RGB>,
+    {
+        let rgb: [Float; 3] = color.into().into();
+        #[allow(clippy::unnecessary_cast)]
+        rgb.map(|c| c as f32)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::color::{RGB, XYZ};
+    use crate::color::{ToneCurve, RGB, XYZ};
 
     #[test]
     fn pixel_aggregation() {
@@ -222,6 +908,229 @@ mod test {
         assert_eq!(pix.to_color(), RGB::from([0.5, 0.5, 0.5]));
     }
 
+    #[test]
+    fn invalid_samples() {
+        let nan = RGB::from([Float::NAN, 0.0, 0.0]);
+        let inf = RGB::from([0.0, Float::INFINITY, 0.0]);
+
+        let mut pix = Pixel::default();
+        pix.add_sample(RGB::from([1.0, 1.0, 1.0]));
+        pix.add_sample(nan);
+        assert_eq!(RGB::from([0.5, 0.5, 0.5]), pix.to_color());
+        assert_eq!(1, pix.invalid_count());
+
+        let mut pix = Pixel::default();
+        pix.add_sample_with(RGB::from([1.0, 1.0, 1.0]), InvalidSamples::Discard);
+        pix.add_sample_with(inf, InvalidSamples::Discard);
+        assert_eq!(RGB::from([1.0, 1.0, 1.0]), pix.to_color());
+        assert_eq!((1, 1), (pix.count(), pix.invalid_count()));
+
+        let mut film = RGBFilm::new(3, 2);
+        film[4].add_sample_with(nan, InvalidSamples::Keep);
+        assert!(!film[4].to_color().is_finite());
+        assert_eq!(vec![(1, 1)], film.invalid_pixels().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn median_of_means() {
+        let mut samples = vec![RGB::splat(1.0); 16];
+        samples[5] = RGB::from([1000.0, 1.0, 1.0]);
+
+        let mut plain = Pixel::default();
+        plain.add_median_of_means(samples.clone(), 1, InvalidSamples::Zero);
+        assert!(plain.to_color().max_component() > 60.0);
+
+        let mut robust = Pixel::default();
+        robust.add_median_of_means(samples, 4, InvalidSamples::Zero);
+        assert_eq!(RGB::splat(1.0), robust.to_color());
+        assert_eq!(16, robust.count());
+
+        // Invalid samples are handled before bucketing
+        let mut pix = Pixel::default();
+        let samples = [RGB::splat(2.0), RGB::from([Float::NAN, 0.0, 0.0])];
+        pix.add_median_of_means(samples, 2, InvalidSamples::Discard);
+        assert_eq!(
+            (RGB::splat(2.0), 1, 1),
+            (pix.to_color(), pix.count(), pix.invalid_count())
+        );
+    }
+
+    #[test]
+    fn sample_heatmap() {
+        let mut film = RGBFilm::new(3, 1);
+        for (px, _, pixel) in film.pixel_iter_mut() {
+            for _ in 0..px * 2 {
+                pixel.add_sample(RGB::splat(1.0));
+            }
+        }
+        assert_eq!(&[0.0, 2.0, 4.0], &film.sample_counts()[..]);
+
+        let heatmap = film.sample_heatmap();
+        assert_eq!(Colormap::Inferno.color(0.5), heatmap[1]);
+        assert_eq!(Colormap::Inferno.color(1.0), heatmap[2]);
+        assert!(heatmap[0].max_component() < 1e-3);
+    }
+
+    #[test]
+    fn variance_heatmap() {
+        let mut film = RGBFilm::new(3, 1);
+        film[0].add_sample(RGB::splat(1.0));
+        film[0].add_sample(RGB::splat(1.0));
+        film[1].add_sample(RGB::splat(0.0));
+        film[1].add_sample(RGB::splat(2.0));
+        film[2].add_sample(RGB::from([0.0, 0.0, 4.0]));
+        film[2].add_sample(RGB::from([0.0, 0.0, 0.0]));
+
+        assert_eq!(RGB::splat(2.0), film[1].variance());
+        assert_eq!(RGB::default(), film[0].variance());
+        assert_eq!(&[0.0, 1.0, 4.0 / 3.0], &film.sample_variances()[..]);
+
+        let heatmap = film.variance_heatmap();
+        assert_eq!(Colormap::Inferno.color(0.75), heatmap[1]);
+        assert_eq!(Colormap::Inferno.color(1.0), heatmap[2]);
+
+        // Merged films keep their spread
+        let mut merged = RGBFilm::new(3, 1);
+        merged.merge(&film);
+        assert_eq!(film.sample_variances()[..], merged.sample_variances()[..]);
+    }
+
+    #[test]
+    fn save_and_load_state() {
+        let mut film = RGBFilm::new(3, 2);
+        for (px, py, pixel) in film.pixel_iter_mut() {
+            for _ in 0..=px {
+                pixel.add_sample(RGB::from([px as Float, py as Float, 0.5]));
+            }
+        }
+        film[5].add_sample(RGB::from([Float::NAN, 0.0, 0.0]));
+        film.set_pixel_aspect(4.0 / 3.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("film-state.bin");
+        film.save_state(&path).unwrap();
+        let loaded = RGBFilm::load_state(&path).unwrap();
+
+        assert_eq!(film.dimensions(), loaded.dimensions());
+        assert_eq!(film.pixel_aspect(), loaded.pixel_aspect());
+        assert!(film.iter().eq(loaded.iter()));
+
+        // Version 1 states, from before the pixel aspect was saved, load
+        // with square pixels
+        let mut v1 = Vec::new();
+        v1.extend_from_slice(STATE_MAGIC);
+        for word in [1u32, 1, 1] {
+            v1.extend_from_slice(&word.to_le_bytes());
+        }
+        for c in [0.0f64, 0.0, 0.5] {
+            v1.extend_from_slice(&c.to_le_bytes());
+        }
+        for count in [1u32, 0] {
+            v1.extend_from_slice(&count.to_le_bytes());
+        }
+        let path = dir.path().join("film-state-v1.bin");
+        fs::write(&path, v1).unwrap();
+        let loaded = RGBFilm::load_state(&path).unwrap();
+        assert_eq!((1, 1), loaded.dimensions());
+        assert_eq!(1.0, loaded.pixel_aspect());
+        assert_eq!(film[0].to_color(), loaded[0].to_color());
+        assert_eq!(1, loaded[0].count());
+    }
+
+    #[test]
+    fn snapshot_into() {
+        let mut film = RGBFilm::new(2, 1);
+        film[0].add_sample(RGB::from([2.0, 0.5, 0.0]));
+        film[1].add_sample(RGB::from([0.0, 0.0, 1.0]));
+
+        let mut linear = [0.0f32; 8];
+        film.snapshot_into(&mut linear, SnapshotFormat::Rgba);
+        assert_eq!([2.0, 0.5, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0], linear);
+
+        let mut srgb = [0u8; 8];
+        film.snapshot_into(&mut srgb, SnapshotFormat::Bgra);
+        let image = film.to_snapshot().to_image();
+        for (px, out) in srgb.chunks(4).enumerate() {
+            let [r, g, b] = image.get_pixel(px as u32, 0).0;
+            assert_eq!([b, g, r, 255], out);
+        }
+    }
+
+    #[test]
+    fn output_transform() {
+        let mut film = RGBFilm::new(2, 1);
+        film[0].add_sample(RGB::from([2.0, 2.0, 2.0]));
+        film[1].add_sample(RGB::from([4.0, 4.0, 4.0]));
+        let snapshot = film.to_snapshot();
+        assert_eq!(
+            snapshot.to_image(),
+            snapshot.to_image_with(&OutputTransform::default())
+        );
+
+        // Clipped highlights keep their detail through the ACES curve
+        let aces = snapshot.to_image_with(&OutputTransform::new(ToneCurve::Aces));
+        let clamped = snapshot.to_image();
+        assert_eq!(clamped.get_pixel(0, 0), clamped.get_pixel(1, 0));
+        assert!(aces.get_pixel(0, 0).0[0] < aces.get_pixel(1, 0).0[0]);
+    }
+
+    #[test]
+    fn pixel_aspect_metadata() {
+        let mut film = RGBFilm::new(4, 2);
+        film.set_pixel_aspect(0.5);
+        assert_eq!(1.0, film.display_aspect_ratio());
+        let snapshot = film.crop(0, 0, 2, 2).to_snapshot();
+        assert_eq!(0.5, snapshot.pixel_aspect());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pixel-aspect.png");
+        film.to_snapshot().save_image(&path).unwrap();
+        let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        assert_eq!((20_000, 10_000), (dims.xppu, dims.yppu));
+    }
+
+    #[test]
+    fn embedded_metadata() {
+        let mut metadata = Metadata::new();
+        metadata
+            .insert("gremlin.spp", 16)
+            .insert("gremlin.scene", "cornell box ✓")
+            .insert("gremlin.spp", 32);
+        assert_eq!(Some("32"), metadata.get("gremlin.spp"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metadata.png");
+        let film = RGBFilm::new(2, 2);
+        film.to_snapshot()
+            .save_image_with_metadata(&path, &metadata)
+            .unwrap();
+        let read = Metadata::read_png(&path).unwrap();
+
+        assert_eq!(Some(Metadata::SOFTWARE), read.get("Software"));
+        assert_eq!(Some("32"), read.get("gremlin.spp"));
+        assert_eq!(Some("cornell box ✓"), read.get("gremlin.scene"));
+    }
+
+    #[test]
+    fn merge_pairwise() {
+        let films: Vec<RGBFilm> = (0..5)
+            .map(|i| {
+                let mut film = RGBFilm::new(2, 2);
+                film.iter_mut()
+                    .for_each(|p| p.add_sample(RGB::from([i as Float, 0.0, 0.0])));
+                film
+            })
+            .collect();
+
+        let merged = RGBFilm::merge_pairwise(films).unwrap();
+        for pixel in merged.iter() {
+            assert_eq!(5, pixel.count());
+            assert_eq!(RGB::from([2.0, 0.0, 0.0]), pixel.to_color());
+        }
+    }
+
     #[test]
     fn add_sample_conv() {
         let mut pix = Pixel::default();
@@ -0,0 +1,116 @@
+use png::text_metadata::{ITXtChunk, TEXtChunk};
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, BufReader},
+    path::Path,
+};
+
+/// Key-value render metadata, embedded in saved images.
+///
+/// Recording the seed, sample count, camera and so on in the image itself
+/// keeps renders reproducible long after the command line that made them is
+/// gone. [`Renderer::metadata`] and [`ThinLens::metadata`] describe their own
+/// settings; anything else can be [`insert`]ed by hand.
+///
+/// PNGs store each entry as a text chunk, along with a `Software` entry
+/// naming this version of gremlin. Other formats don't store metadata.
+///
+/// [`Renderer::metadata`]: crate::renderer::Renderer::metadata
+/// [`ThinLens::metadata`]: crate::camera::ThinLens::metadata
+/// [`insert`]: Self::insert
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    entries: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// The value of the `Software` entry written to every image.
+    pub const SOFTWARE: &'static str = concat!("gremlin ", env!("CARGO_PKG_VERSION"));
+
+    /// No metadata.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value for `key`, replacing any previous value.
+    ///
+    /// Keys should be 1-79 printable ASCII characters, as PNG requires;
+    /// other keys are skipped when saving.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Display) -> &mut Self {
+        let key = key.into();
+        let value = value.to_string();
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.entries.push((key, value)),
+        }
+        self
+    }
+
+    /// Remove the entry for `key`, returning its value if it was set.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let idx = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(idx).1)
+    }
+
+    /// Add all the entries of `other`, replacing any with the same key.
+    pub fn extend(&mut self, other: Metadata) -> &mut Self {
+        for (key, value) in other.entries {
+            self.insert(key, value);
+        }
+        self
+    }
+
+    /// The value for `key`, if set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The entries, in the order they were first inserted.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Read the text entries of a PNG file.
+    pub fn read_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        let decoder = png::Decoder::new(BufReader::new(fs::File::open(path)?));
+        let reader = decoder.read_info().map_err(io::Error::other)?;
+        let info = reader.info();
+
+        let mut metadata = Self::new();
+        for chunk in &info.uncompressed_latin1_text {
+            metadata.insert(chunk.keyword.clone(), &chunk.text);
+        }
+        for chunk in &info.utf8_text {
+            let text = chunk.get_text().map_err(io::Error::other)?;
+            metadata.insert(chunk.keyword.clone(), text);
+        }
+        Ok(metadata)
+    }
+
+    // Write the entries, and `Software`, as PNG text chunks. ASCII values go
+    // in plain `tEXt` chunks, which more tools display, and the rest in UTF-8
+    // `iTXt` chunks.
+    pub(super) fn write_png<W: io::Write>(
+        &self,
+        writer: &mut png::Writer<W>,
+    ) -> Result<(), png::EncodingError> {
+        let software = ("Software", Self::SOFTWARE);
+        let entries = self.iter().filter(|(k, _)| *k != "Software");
+        for (key, value) in std::iter::once(software).chain(entries) {
+            let valid_key =
+                (1..80).contains(&key.len()) && key.bytes().all(|b| (32..127).contains(&b));
+            if !valid_key {
+                continue;
+            }
+            match value.is_ascii() {
+                true => writer.write_text_chunk(&TEXtChunk::new(key, value))?,
+                false => writer.write_text_chunk(&ITXtChunk::new(key, value))?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use super::Buffer;
+use crate::{color::RGB, Float};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A film that many threads can add light to at once, anywhere on it.
+///
+/// Integrators that trace from the camera know which pixel they're working
+/// on, so each thread can own its pixels. Those tracing from the lights
+/// only find out which pixel a path lands in at the end, and any thread can
+/// land in any pixel, so instead they [`splat`] onto a shared film. Each
+/// channel is an atomic `f64`, so splats never block one another and small
+/// contributions aren't lost to rounding, even in `f32` builds.
+///
+/// [`splat`]: Self::splat
+///
+/// ```
+/// use gremlin::{color::RGB, film::SplatFilm};
+///
+/// let film = SplatFilm::new(2, 2);
+/// film.splat((1.5, 0.5), RGB::splat(2.0));
+/// film.splat((1.9, 0.1), RGB::splat(1.0));
+/// assert_eq!(RGB::splat(1.5), film.to_buffer(0.5)[1]);
+/// ```
+#[derive(Debug)]
+pub struct SplatFilm {
+    width: u32,
+    height: u32,
+    pixels: Vec<[AtomicU64; 3]>,
+}
+
+impl SplatFilm {
+    /// Create a new, black film with the given width and height.
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixels = (0..width * height)
+            .map(|_| [0.0f64; 3].map(|c| AtomicU64::new(c.to_bits())))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The width of the film
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the film
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The `(width, height)` of the film
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Add `color` to the pixel containing the continuous raster position
+    /// `(fx, fy)`. Positions off the film, and colors that aren't finite,
+    /// are dropped.
+    pub fn splat(&self, (fx, fy): (Float, Float), color: RGB) {
+        if !(fx >= 0.0 && fy >= 0.0 && color.is_finite()) || color.is_black() {
+            return;
+        }
+        let (px, py) = (fx as u32, fy as u32);
+        if px >= self.width || py >= self.height {
+            return;
+        }
+        let pixel = &self.pixels[(py * self.width + px) as usize];
+        for (channel, c) in pixel.iter().zip(<[Float; 3]>::from(color)) {
+            #[allow(clippy::unnecessary_cast)]
+            let c = c as f64;
+            let _ = channel.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + c).to_bits())
+            });
+        }
+    }
+
+    /// Everything splatted so far, times `scale`. Light tracers scale by
+    /// one over the number of paths traced.
+    pub fn to_buffer(&self, scale: Float) -> Buffer<RGB> {
+        let mut buf = Buffer::new(self.width, self.height);
+        for (out, pixel) in buf.iter_mut().zip(&self.pixels) {
+            let [r, g, b] = pixel
+                .each_ref()
+                .map(|c| f64::from_bits(c.load(Ordering::Relaxed)));
+            *out = RGB::from([r as Float, g as Float, b as Float]) * scale;
+        }
+        buf
+    }
+
+    /// Set every pixel back to black.
+    pub fn clear(&mut self) {
+        for channel in self.pixels.iter_mut().flatten() {
+            *channel.get_mut() = 0.0f64.to_bits();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn concurrent_splats() {
+        let mut film = SplatFilm::new(4, 3);
+        (0..10_000).into_par_iter().for_each(|i| {
+            let fx = (i % 4) as Float + 0.5;
+            film.splat((fx, 2.25), RGB::from([1.0, 0.5, 0.0]));
+            film.splat((-0.5, 1.0), RGB::splat(1.0));
+            film.splat((4.0, 1.0), RGB::splat(1.0));
+        });
+
+        let buf = film.to_buffer(1.0);
+        for (px, py, &c) in buf.pixel_iter() {
+            let expected = match py {
+                2 => RGB::from([2500.0, 1250.0, 0.0]),
+                _ => RGB::default(),
+            };
+            assert_eq!(expected, c, "({}, {})", px, py);
+        }
+
+        film.clear();
+        assert!(film.to_buffer(1.0).iter().all(|c| c.is_black()));
+    }
+}
@@ -0,0 +1,234 @@
+use super::{Buffer, Film, Pixel};
+use crate::color::{Color, SRGB};
+use image::{Rgb, RgbImage};
+use std::{
+    fs,
+    io::{self, BufReader, BufWriter, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// A rectangular region of a [`TiledFilm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Index of the tile, in row-major order.
+    pub index: usize,
+    /// Raster coordinates of the tile's upper-left pixel.
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An out-of-core film, for renders too large to accumulate in memory.
+///
+/// The film is split into square tiles, which live on disk in a scratch
+/// directory. Tiles are loaded as small in-memory [`Film`]s while being
+/// worked on and stored again when done, so only the active tiles take up
+/// memory. A 16K render's accumulation buffer is several gigabytes; this
+/// needs a few tiles per thread.
+///
+/// Tile files are removed when the film is dropped.
+///
+/// ```no_run
+/// use gremlin::{camera::ThinLens, color::LinearRGB, film::TiledFilm, renderer::Renderer};
+/// # use gremlin::integrator::Hacky;
+/// # let integrator = Hacky::default();
+///
+/// let mut film = TiledFilm::<LinearRGB>::create(16384, 8192, 256, "/tmp/gremlin").unwrap();
+/// let cam = ThinLens::builder(film.dimensions()).build();
+/// Renderer::new(16).render_tiled(&mut film, &cam, &integrator).unwrap();
+/// film.to_image().unwrap().save("out.png").unwrap();
+/// ```
+pub struct TiledFilm<CS> {
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    dir: PathBuf,
+    _colorspace: PhantomData<CS>,
+}
+
+impl<CS: Copy> TiledFilm<CS> {
+    /// Create a new tiled film, storing tiles in the given directory.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn create(
+        width: u32,
+        height: u32,
+        tile_size: u32,
+        dir: impl AsRef<Path>,
+    ) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            width,
+            height,
+            tile_size: tile_size.max(1),
+            dir,
+            _colorspace: PhantomData,
+        })
+    }
+
+    /// The width of the film.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the film.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The tiles making up the film, in row-major order. Tiles along the
+    /// right and bottom edges may be smaller than the tile size.
+    pub fn tiles(&self) -> Vec<Tile> {
+        let size = self.tile_size;
+        let cols = self.width.div_ceil(size);
+        let rows = self.height.div_ceil(size);
+        (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .enumerate()
+            .map(|(index, (row, col))| {
+                let x0 = col * size;
+                let y0 = row * size;
+                Tile {
+                    index,
+                    x0,
+                    y0,
+                    width: size.min(self.width - x0),
+                    height: size.min(self.height - y0),
+                }
+            })
+            .collect()
+    }
+
+    /// Load a tile into memory. Tiles that haven't been stored yet are empty.
+    pub fn load_tile(&self, tile: &Tile) -> io::Result<Film<CS>> {
+        let mut film = Film::new(tile.width, tile.height);
+        let file = match fs::File::open(self.tile_path(tile)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(film),
+            Err(e) => return Err(e),
+        };
+
+        let mut reader = BufReader::new(file);
+        for pixel in film.iter_mut() {
+            *pixel = Pixel::read_from(&mut reader)?;
+        }
+        Ok(film)
+    }
+
+    /// Write a tile back to disk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the film's dimensions don't match the tile's.
+    pub fn store_tile(&self, tile: &Tile, film: &Film<CS>) -> io::Result<()> {
+        assert_eq!(
+            (tile.width, tile.height),
+            film.dimensions(),
+            "tile size mismatch"
+        );
+
+        let mut writer = BufWriter::new(fs::File::create(self.tile_path(tile))?);
+        for pixel in film.iter() {
+            pixel.write_to(&mut writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Convert the film to an 8-bit sRGB image, one tile at a time.
+    pub fn to_image(&self) -> io::Result<RgbImage>
+    where
+        Color<CS>: SRGB,
+    {
+        let mut img = RgbImage::new(self.width, self.height);
+        for tile in self.tiles() {
+            let film = self.load_tile(&tile)?;
+            for (px, py, pixel) in film.pixel_iter() {
+                let srgb = pixel.to_color().to_srgb();
+                img.put_pixel(tile.x0 + px, tile.y0 + py, Rgb::<u8>::from(srgb));
+            }
+        }
+        Ok(img)
+    }
+
+    /// Assemble the whole film in memory.
+    ///
+    /// Mostly useful for small films and testing, since it defeats the point
+    /// of tiling.
+    pub fn to_film(&self) -> io::Result<Film<CS>> {
+        let mut film: Film<CS> = Buffer::new(self.width, self.height);
+        for tile in self.tiles() {
+            let src = self.load_tile(&tile)?;
+            for (px, py, pixel) in src.pixel_iter() {
+                let idx = ((tile.y0 + py) * self.width + tile.x0 + px) as usize;
+                film[idx] = *pixel;
+            }
+        }
+        Ok(film)
+    }
+
+    fn tile_path(&self, tile: &Tile) -> PathBuf {
+        self.dir.join(format!("tile-{}.bin", tile.index))
+    }
+}
+
+impl<CS> Drop for TiledFilm<CS> {
+    fn drop(&mut self) {
+        let size = self.tile_size;
+        let count = (self.width.div_ceil(size)) * (self.height.div_ceil(size));
+        for index in 0..count {
+            let _ = fs::remove_file(self.dir.join(format!("tile-{}.bin", index)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::{LinearRGB, RGB},
+        Float,
+    };
+
+    #[test]
+    fn tiles_cover_film() {
+        let dir = tempfile::tempdir().unwrap();
+        let film = TiledFilm::<LinearRGB>::create(10, 7, 4, dir.path()).unwrap();
+        let tiles = film.tiles();
+        assert_eq!(6, tiles.len());
+        let area: u32 = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(70, area);
+        assert_eq!((2, 3), (tiles[5].width, tiles[5].height));
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let film = TiledFilm::<LinearRGB>::create(5, 5, 3, dir.path()).unwrap();
+        for tile in film.tiles() {
+            let mut buf = film.load_tile(&tile).unwrap();
+            for (px, py, pixel) in buf.pixel_iter_mut() {
+                pixel.add_sample(RGB::from([
+                    (tile.x0 + px) as Float,
+                    (tile.y0 + py) as Float,
+                    0.1,
+                ]));
+            }
+            film.store_tile(&tile, &buf).unwrap();
+        }
+
+        let full = film.to_film().unwrap();
+        for (px, py, pixel) in full.pixel_iter() {
+            assert_eq!(1, pixel.count());
+            assert_eq!(RGB::from([px as Float, py as Float, 0.1]), pixel.to_color());
+        }
+        drop(film);
+        assert!(!dir.path().join("tile-0.bin").exists());
+    }
+}
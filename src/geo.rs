@@ -12,14 +12,14 @@
 //! nor a fully-featured linear algebra library. There are already libraries for
 //! that, such as:
 //! * [`cgmath`](https://github.com/rustgd/cgmath) - Defines traits for general
-//! linear-algebraic structures like vector spaces, inner-product spaces, normed
-//! spaces, etc, and the implements them in generic structs.
+//!   linear-algebraic structures like vector spaces, inner-product spaces,
+//!   normed spaces, etc, and the implements them in generic structs.
 //! * [`glam-rs`](https://github.com/bitshifter/glam-rs) - Fast float-valued
-//! vector, matrix, quaterion and affine structures with SIMD implementations.
+//!   vector, matrix, quaterion and affine structures with SIMD implementations.
 //! * [`nalgebra`](https://nalgebra.org) - A really impressive linear algebra
-//! library for Rust
+//!   library for Rust
 //! * [`ndarray`](https://github.com/rust-ndarray/ndarray) - An equally
-//! impressive, Numpy-like N-dimensional array library.
+//!   impressive, Numpy-like N-dimensional array library.
 //!
 //! Instead, the goal is to be mathematically correct, while speaking in the
 //! "domain language" of ray tracing. So, *e.g.* separate `Point`, `Vector` and
@@ -61,12 +61,24 @@
 
 // MODULES AND RE-EXPORTS
 
+mod angle;
+pub use self::angle::*;
+
+mod axes;
+pub use self::axes::*;
+
 mod bounds;
 pub use self::bounds::*;
 
 mod coords;
 pub use self::coords::*;
 
+mod frame;
+pub use self::frame::*;
+
+mod length;
+pub use self::length::*;
+
 mod matrix;
 pub use self::matrix::*;
 
@@ -76,6 +88,9 @@ pub use self::point::*;
 mod ray;
 pub use self::ray::*;
 
+mod transform;
+pub use self::transform::*;
+
 mod unit;
 pub use self::unit::*;
 
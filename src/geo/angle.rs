@@ -0,0 +1,146 @@
+use crate::Float;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// An angle in radians.
+///
+/// APIs that take an angle accept `impl Into<Radians>`, so either unit can be
+/// passed, but a bare number can't: whether `90.0` meant degrees or radians
+/// is spelled out at the call site.
+///
+/// ```
+/// use gremlin::geo::{Degrees, Radians};
+///
+/// let right = Radians::from(Degrees(90.0));
+/// assert!((right.0 - 1.5707963).abs() < 1e-6);
+/// assert!((Degrees::from(right * 2.0).0 - 180.0).abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Radians(pub Float);
+
+/// An angle in degrees. See [`Radians`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Degrees(pub Float);
+
+impl Radians {
+    /// The sine of the angle.
+    #[inline]
+    pub fn sin(self) -> Float {
+        self.0.sin()
+    }
+
+    /// The cosine of the angle.
+    #[inline]
+    pub fn cos(self) -> Float {
+        self.0.cos()
+    }
+
+    /// The tangent of the angle.
+    #[inline]
+    pub fn tan(self) -> Float {
+        self.0.tan()
+    }
+}
+
+impl From<Degrees> for Radians {
+    #[inline]
+    fn from(d: Degrees) -> Self {
+        Self(d.0.to_radians())
+    }
+}
+
+impl From<Radians> for Degrees {
+    #[inline]
+    fn from(r: Radians) -> Self {
+        Self(r.0.to_degrees())
+    }
+}
+
+impl Add for Radians {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Radians {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Radians {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Mul<Float> for Radians {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Float) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Div<Float> for Radians {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Float) -> Self {
+        Self(self.0 / rhs)
+    }
+}
+
+impl Add for Degrees {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Degrees {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Degrees {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Mul<Float> for Degrees {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Float) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Div<Float> for Degrees {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Float) -> Self {
+        Self(self.0 / rhs)
+    }
+}
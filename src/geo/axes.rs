@@ -0,0 +1,221 @@
+use super::{Matrix, Point, Unit, Vector};
+use crate::Float;
+
+/// Which axis points "up" in a coordinate system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpAxis {
+    /// The y-axis points up (Maya, glTF, most game engines).
+    #[default]
+    Y,
+    /// The z-axis points up (Blender, 3ds Max, most CAD packages).
+    Z,
+}
+
+/// The handedness of a coordinate system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+/// The length unit assets were authored in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LengthUnit {
+    Millimeters,
+    Centimeters,
+    #[default]
+    Meters,
+    Inches,
+    Feet,
+    /// An arbitrary unit, given as the number of meters per unit.
+    Custom(Float),
+}
+
+impl LengthUnit {
+    /// The number of meters in one of this unit.
+    #[inline]
+    pub fn meters(&self) -> Float {
+        match *self {
+            Self::Millimeters => 0.001,
+            Self::Centimeters => 0.01,
+            Self::Meters => 1.0,
+            Self::Inches => 0.0254,
+            Self::Feet => 0.3048,
+            Self::Custom(m) => m,
+        }
+    }
+}
+
+/// Describes the axis and unit conventions of imported assets.
+///
+/// Gremlin itself works in a right-handed, y-up world (the camera looks down
+/// its own negative z-axis), measured in meters. Assets authored under other
+/// conventions can be brought into that world with [`to_world`], which
+/// returns the conversion matrix.
+///
+/// Since the conversion is a rotation or reflection plus a uniform scale, the
+/// same matrix applies to points, vectors, and (after renormalizing) normals
+/// alike. Changing handedness is a reflection though, which reverses triangle
+/// winding; check [`flips_winding`] when converting index buffers.
+///
+/// ```
+/// use gremlin::geo::{CoordinateSystem, Handedness, Point, UpAxis};
+///
+/// let blender = CoordinateSystem::new(UpAxis::Z, Handedness::Right);
+/// let up = blender.convert_point(Point::new(0.0, 0.0, 1.0));
+/// assert_eq!(Point::new(0.0, 1.0, 0.0), up);
+/// ```
+///
+/// [`to_world`]: Self::to_world
+/// [`flips_winding`]: Self::flips_winding
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CoordinateSystem {
+    pub up: UpAxis,
+    pub handedness: Handedness,
+    pub units: LengthUnit,
+}
+
+impl CoordinateSystem {
+    /// Gremlin's native convention: right-handed, y-up, in meters.
+    pub const NATIVE: Self = Self::new(UpAxis::Y, Handedness::Right);
+
+    /// Creates a new coordinate system description, measured in meters.
+    #[inline]
+    pub const fn new(up: UpAxis, handedness: Handedness) -> Self {
+        Self {
+            up,
+            handedness,
+            units: LengthUnit::Meters,
+        }
+    }
+
+    /// Set the length unit.
+    #[inline]
+    pub const fn with_units(mut self, units: LengthUnit) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// The matrix taking coordinates in this system to Gremlin's world space.
+    #[rustfmt::skip]
+    pub fn to_world(&self) -> Matrix {
+        // First mirror into a right-handed system by negating one of the
+        // horizontal axes...
+        let mirror = match (self.handedness, self.up) {
+            (Handedness::Right, _) => Matrix::IDENTITY,
+            (Handedness::Left, UpAxis::Y) => Matrix::scale(1.0, 1.0, -1.0),
+            (Handedness::Left, UpAxis::Z) => Matrix::scale(1.0, -1.0, 1.0),
+        };
+
+        // ...then rotate the up axis onto +y. Rotating -90 degrees about x
+        // takes (x, y, z) to (x, z, -y).
+        let rotate = match self.up {
+            UpAxis::Y => Matrix::IDENTITY,
+            UpAxis::Z => Matrix::new([
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, -1.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+        };
+
+        Matrix::scale_uniform(self.units.meters()) * rotate * mirror
+    }
+
+    /// The matrix taking Gremlin's world space back to this system. The
+    /// inverse of [`to_world`][Self::to_world].
+    pub fn from_world(&self) -> Matrix {
+        // Each factor is trivially invertible: the mirror is its own inverse,
+        // the rotation's inverse is its transpose
+        let to_world = self.to_world() * Matrix::scale_uniform(self.units.meters().recip());
+        Matrix::scale_uniform(self.units.meters().recip()) * to_world.transpose()
+    }
+
+    /// Convert a transform authored in this system (_e.g._ an object-to-world
+    /// matrix from an imported file) so it acts the same way in world space.
+    #[inline]
+    pub fn convert_transform(&self, m: Matrix) -> Matrix {
+        self.to_world() * m * self.from_world()
+    }
+
+    /// Returns `true` if converting reverses triangle winding order.
+    #[inline]
+    pub fn flips_winding(&self) -> bool {
+        self.handedness == Handedness::Left
+    }
+
+    /// Convert a point into world space.
+    #[inline]
+    pub fn convert_point(&self, p: Point) -> Point {
+        self.to_world() * p
+    }
+
+    /// Convert a vector into world space.
+    #[inline]
+    pub fn convert_vector(&self, v: Vector) -> Vector {
+        self.to_world() * v
+    }
+
+    /// Convert a normal into world space.
+    #[inline]
+    pub fn convert_normal(&self, n: Unit) -> Unit {
+        // Orthogonal up to a uniform scale, so the inverse-transpose is
+        // proportional to the matrix itself
+        (self.to_world() * n).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn native_is_identity() {
+        assert_eq!(Matrix::IDENTITY, CoordinateSystem::NATIVE.to_world());
+        assert!(!CoordinateSystem::NATIVE.flips_winding());
+    }
+
+    #[test]
+    fn z_up_right() {
+        let cs = CoordinateSystem::new(UpAxis::Z, Handedness::Right);
+        assert_eq!(Vector::Y_AXIS, cs.convert_vector(Vector::Z_AXIS));
+        assert_eq!(Vector::X_AXIS, cs.convert_vector(Vector::X_AXIS));
+        // Blender's "forward" (+y) ends up facing away from a default camera
+        assert_eq!(-Vector::Z_AXIS, cs.convert_vector(Vector::Y_AXIS));
+    }
+
+    #[test]
+    fn units() {
+        let cs =
+            CoordinateSystem::new(UpAxis::Z, Handedness::Right).with_units(LengthUnit::Centimeters);
+        assert_eq!(
+            Point::new(0.0, 2.0, 0.0),
+            cs.convert_point(Point::new(0.0, 0.0, 200.0))
+        );
+        assert_eq!(Unit::Y_AXIS, cs.convert_normal(Unit::Z_AXIS));
+    }
+
+    #[test]
+    fn transforms() {
+        let cs =
+            CoordinateSystem::new(UpAxis::Z, Handedness::Left).with_units(LengthUnit::Millimeters);
+        assert_relative_eq!(Matrix::IDENTITY, cs.to_world() * cs.from_world());
+
+        // Moving "up" by 1000mm is moving up by 1m
+        let m = cs.convert_transform(Matrix::shift(Vector::new(0.0, 0.0, 1000.0)));
+        assert_relative_eq!(Point::new(0.0, 1.0, 0.0), m * Point::ORIGIN);
+    }
+
+    #[test]
+    fn left_handed() {
+        let cs = CoordinateSystem::new(UpAxis::Y, Handedness::Left);
+        assert_eq!(-Vector::Z_AXIS, cs.convert_vector(Vector::Z_AXIS));
+        assert!(cs.flips_winding());
+
+        let cs = CoordinateSystem::new(UpAxis::Z, Handedness::Left);
+        assert_eq!(Vector::Y_AXIS, cs.convert_vector(Vector::Z_AXIS));
+        assert_eq!(Vector::Z_AXIS, cs.convert_vector(Vector::Y_AXIS));
+    }
+}
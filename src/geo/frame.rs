@@ -0,0 +1,142 @@
+use super::{Unit, Vector};
+use crate::Float;
+
+/// An orthonormal basis, for moving directions in and out of a local shading
+/// space.
+///
+/// BSDFs are much simpler to write in a frame where the surface normal is the
+/// `z` axis: `cos θ` is just the `z` component, and the tangent plane is `xy`.
+/// A `Frame` maps world-space directions into that space and back. Since the
+/// basis is orthonormal, the mapping is a rotation, so lengths (and unit
+/// vectors) are preserved.
+///
+/// ```
+/// use gremlin::geo::{Frame, Unit, Vector};
+///
+/// let frame = Frame::from_normal(Unit::Y_AXIS);
+/// let local = frame.to_local(Vector::new(0.0, 2.0, 0.0));
+/// assert!((local.z - 2.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    x: Unit,
+    y: Unit,
+    z: Unit,
+}
+
+impl Frame {
+    /// Build a frame around the given normal, which becomes the local `z`
+    /// axis. The tangents are arbitrary, and flip where the normal crosses
+    /// the `xy` plane.
+    ///
+    /// Uses the branchless construction of Duff et al.
+    ///
+    /// See: <https://jcgt.org/published/0006/01/01/>
+    #[inline]
+    pub fn from_normal(n: Unit) -> Self {
+        let (nx, ny, nz) = (n.x(), n.y(), n.z());
+        let sign = (1.0 as Float).copysign(nz);
+        let a = -1.0 / (sign + nz);
+        let b = nx * ny * a;
+        Self {
+            x: Unit::new(1.0 + sign * nx * nx * a, sign * b, -sign * nx),
+            y: Unit::new(b, sign + ny * ny * a, -ny),
+            z: n,
+        }
+    }
+
+    /// The local `x` axis (a tangent), in world space.
+    #[inline]
+    pub fn x(&self) -> Unit {
+        self.x
+    }
+
+    /// The local `y` axis (a tangent), in world space.
+    #[inline]
+    pub fn y(&self) -> Unit {
+        self.y
+    }
+
+    /// The local `z` axis (the normal), in world space.
+    #[inline]
+    pub fn z(&self) -> Unit {
+        self.z
+    }
+
+    /// Express a world-space vector in this frame.
+    #[inline]
+    pub fn to_local(&self, v: Vector) -> Vector {
+        Vector::new(
+            v.dot(self.x.into()),
+            v.dot(self.y.into()),
+            v.dot(self.z.into()),
+        )
+    }
+
+    /// Express a vector in this frame in world space.
+    #[inline]
+    pub fn to_world(&self, v: Vector) -> Vector {
+        Vector::from(self.x) * v.x + Vector::from(self.y) * v.y + Vector::from(self.z) * v.z
+    }
+
+    /// Express a world-space unit vector in this frame.
+    #[inline]
+    pub fn to_local_unit(&self, u: Unit) -> Unit {
+        let v = self.to_local(u.into());
+        Unit::new(v.x, v.y, v.z)
+    }
+
+    /// Express a unit vector in this frame in world space.
+    #[inline]
+    pub fn to_world_unit(&self, u: Unit) -> Unit {
+        let v = self.to_world(u.into());
+        Unit::new(v.x, v.y, v.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn orthonormal() {
+        let normals = [
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(1.0, 2.0, 3.0),
+            Vector::new(-0.3, 0.1, -5.0),
+            Vector::new(1e-8, 0.0, -1.0),
+        ];
+        for n in normals {
+            let frame = Frame::from_normal(n.normalize());
+            let (x, y, z) = (
+                Vector::from(frame.x()),
+                Vector::from(frame.y()),
+                Vector::from(frame.z()),
+            );
+            assert_relative_eq!(1.0, x.len(), epsilon = 1e-6);
+            assert_relative_eq!(1.0, y.len(), epsilon = 1e-6);
+            assert_relative_eq!(0.0, x.dot(y), epsilon = 1e-6);
+            assert_relative_eq!(0.0, x.dot(z), epsilon = 1e-6);
+            assert_relative_eq!(0.0, y.dot(z), epsilon = 1e-6);
+            // Right-handed
+            assert_relative_eq!(z, x.cross(y), epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let frame = Frame::from_normal(Vector::new(0.5, -1.0, 2.0).normalize());
+        let v = Vector::new(3.0, -2.0, 1.0);
+        assert_relative_eq!(v, frame.to_world(frame.to_local(v)), epsilon = 1e-6);
+
+        let n = Vector::from(frame.z());
+        assert_relative_eq!(v.dot(n), frame.to_local(v).z, epsilon = 1e-6);
+
+        let local = frame.to_local_unit(frame.z());
+        assert_relative_eq!(Vector::Z_AXIS, Vector::from(local), epsilon = 1e-6);
+        let world = frame.to_world_unit(Unit::Z_AXIS);
+        assert_relative_eq!(n, Vector::from(world), epsilon = 1e-6);
+    }
+}
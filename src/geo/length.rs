@@ -0,0 +1,137 @@
+use super::LengthUnit;
+use crate::Float;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// A length, such as a camera's aperture or focus distance.
+///
+/// Settings that take a length accept `impl Into<Length>`. A bare number is
+/// taken to be in world units (meters), as before, so typing lengths is
+/// optional; but when values come from assets or tools with other
+/// conventions, naming the unit avoids being off by a factor of a hundred
+/// or a thousand.
+///
+/// ```
+/// use gremlin::geo::{Length, LengthUnit};
+///
+/// let aperture = Length::millimeters(35.0);
+/// assert!((aperture.as_meters() - 0.035).abs() < 1e-9);
+/// assert!((aperture.in_units(LengthUnit::Centimeters) - 3.5).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Length(Float);
+
+impl Length {
+    /// A length in meters, Gremlin's world unit.
+    #[inline]
+    pub const fn meters(m: Float) -> Self {
+        Self(m)
+    }
+
+    /// A length in centimeters.
+    #[inline]
+    pub fn centimeters(cm: Float) -> Self {
+        Self::new(cm, LengthUnit::Centimeters)
+    }
+
+    /// A length in millimeters.
+    #[inline]
+    pub fn millimeters(mm: Float) -> Self {
+        Self::new(mm, LengthUnit::Millimeters)
+    }
+
+    /// A length in inches.
+    #[inline]
+    pub fn inches(inches: Float) -> Self {
+        Self::new(inches, LengthUnit::Inches)
+    }
+
+    /// A length in feet.
+    #[inline]
+    pub fn feet(feet: Float) -> Self {
+        Self::new(feet, LengthUnit::Feet)
+    }
+
+    /// A length of `value` in the given unit.
+    #[inline]
+    pub fn new(value: Float, unit: LengthUnit) -> Self {
+        Self(value * unit.meters())
+    }
+
+    /// The length in meters, and so in world units.
+    #[inline]
+    pub const fn as_meters(self) -> Float {
+        self.0
+    }
+
+    /// The length in the given unit.
+    #[inline]
+    pub fn in_units(self, unit: LengthUnit) -> Float {
+        self.0 / unit.meters()
+    }
+}
+
+impl From<Float> for Length {
+    /// A length in world units (meters).
+    #[inline]
+    fn from(m: Float) -> Self {
+        Self(m)
+    }
+}
+
+impl Add for Length {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Length {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Mul<Float> for Length {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Float) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Div<Float> for Length {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Float) -> Self {
+        Self(self.0 / rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn conversions() {
+        assert_relative_eq!(0.3048, Length::feet(1.0).as_meters());
+        assert_relative_eq!(12.0, Length::feet(1.0).in_units(LengthUnit::Inches));
+        assert_relative_eq!(2.54, Length::inches(1.0).in_units(LengthUnit::Centimeters));
+        assert_relative_eq!(
+            2.0,
+            Length::millimeters(1.0).in_units(LengthUnit::Custom(0.0005))
+        );
+        assert_eq!(Length::meters(1.5), Length::from(1.5));
+        assert_relative_eq!(
+            1.0,
+            (Length::centimeters(50.0) * 3.0 - Length::meters(0.5)).as_meters()
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::Float;
 
-use super::{Point, Ray, Unit, Vector};
+use super::{Point, Radians, Ray, Unit, Vector};
 use approx::{AbsDiffEq, RelativeEq, UlpsEq};
 use std::ops::{Add, Mul, Neg, Sub};
 
@@ -93,7 +93,7 @@ impl Matrix {
 
     /// Construct a matrix representing rotation about the given axis.
     ///
-    /// Assumes `theta` is given in degrees and internally converts to radians.
+    /// The angle may be given in [`Radians`] or [`Degrees`].
     ///
     /// Note that for inverses, it is much faster to use the identity:
     ///
@@ -102,14 +102,16 @@ impl Matrix {
     /// ```
     ///
     /// See: <https://www.pbr-book.org/3ed-2018/Geometry_and_Transformations/Transformations#RotationaroundanArbitraryAxis>
+    ///
+    /// [`Degrees`]: super::Degrees
     #[rustfmt::skip]
-    pub fn rotate(theta: Float, axis: Unit) -> Self {
+    pub fn rotate(theta: impl Into<Radians>, axis: Unit) -> Self {
         // Covert angle to radians and axis to vector (so we can get components)
-        let theta = theta.to_radians();
+        let theta: Radians = theta.into();
         let axis = Vector::from(axis);
 
         // Precompute some constants
-        let (sin_theta, cos_theta) = theta.sin_cos();
+        let (sin_theta, cos_theta) = theta.0.sin_cos();
 
         // Rotation of first basis vector
         let d00 = axis.x * axis.x + (1.0 - axis.x * axis.x) * cos_theta;
@@ -165,7 +167,7 @@ impl Matrix {
             [x_axis[0], y_axis[0], z_axis[0], from.x],
             [x_axis[1], y_axis[1], z_axis[1], from.y],
             [x_axis[2], y_axis[2], z_axis[2], from.z],
-            [0.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
         ])
     }
 
@@ -383,6 +385,15 @@ impl Mul<Vector> for Matrix {
     }
 }
 
+impl Mul<Unit> for Matrix {
+    type Output = Vector;
+
+    #[inline]
+    fn mul(self, rhs: Unit) -> Self::Output {
+        self * Vector::from(rhs)
+    }
+}
+
 impl Mul<Point> for Matrix {
     type Output = Point;
 
@@ -555,4 +566,14 @@ mod tests {
             max_relative = 1e-5
         );
     }
+
+    #[test]
+    fn matrix_look_at_inverse() {
+        let from = Point::new(1.0, 2.0, 3.0);
+        let m = Matrix::look_at(from, Point::ORIGIN, Vector::Y_AXIS);
+        let m_inv = m.inverse().unwrap();
+
+        assert_relative_eq!(Matrix::IDENTITY, m * m_inv, epsilon = 1e-6);
+        assert_relative_eq!(Vector::ZERO, Vector::from(m_inv * from), epsilon = 1e-6);
+    }
 }
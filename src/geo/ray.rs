@@ -1,4 +1,4 @@
-use super::{Point, Vector};
+use super::{Point, Unit, Vector};
 use crate::Float;
 
 /// A geometric ray.
@@ -32,4 +32,368 @@ impl Ray {
     pub const fn origin(&self) -> Point {
         self.origin
     }
+
+    /// The component-wise reciprocal of the ray's direction.
+    ///
+    /// Computed on each call. When testing the same ray against many boxes,
+    /// convert it to a [`SlabRay`] once instead.
+    #[inline]
+    pub fn inv_direction(&self) -> Vector {
+        self.direction.apply(Float::recip)
+    }
+
+    /// A ray leaving a surface at `origin`, with the surface `normal` there.
+    ///
+    /// Hit points are only known to within floating-point error, so a ray
+    /// starting exactly at one can hit the surface it's leaving again
+    /// ("shadow acne"). This moves the origin off the surface, to the side
+    /// `direction` heads into, by [`SpawnOffset::default`]; the ray can then
+    /// be intersected from `t = 0`.
+    #[inline]
+    pub fn spawn(origin: Point, direction: Vector, normal: Unit) -> Self {
+        SpawnOffset::default().spawn(origin, direction, normal)
+    }
+}
+
+/// How far [`Ray::spawn`] moves a ray's origin off the surface it leaves.
+///
+/// Too small, and rays re-hit the surface they leave, speckling it with
+/// false shadows ("shadow acne"). Too large, and rays start past nearby
+/// geometry, letting light leak through thin walls and into corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnOffset {
+    /// Offset by a bound on the floating-point error of the hit position,
+    /// times `scale`. Scales with the distance from the world origin, so
+    /// works for scenes of any size. Increase `scale` if shapes with
+    /// imprecise intersection routines still show acne.
+    ErrorBound { scale: Float },
+    /// As [`ErrorBound`], but never less than the error bound for
+    /// coordinates of size `extent`, the largest coordinate of the shape
+    /// being left. A hit is computed from the shape's own coordinates, such
+    /// as a big triangle's far-flung vertices, so it's no more precise than
+    /// those even where the hit point itself is near the origin.
+    ///
+    /// [`ErrorBound`]: Self::ErrorBound
+    ShapeErrorBound { scale: Float, extent: Float },
+    /// Offset by a fixed distance, in world units.
+    Fixed(Float),
+}
+
+impl SpawnOffset {
+    // Relative error bound on hit point coordinates. Generous, since shapes
+    // don't track their own error: the sphere test loses a few bits to
+    // cancellation near the surface.
+    const RELATIVE_ERROR: Float = 64.0 * Float::EPSILON;
+
+    /// The distance to move a ray leaving the surface at `point` along the
+    /// `normal`.
+    #[inline]
+    pub fn distance(&self, point: Point, normal: Unit) -> Float {
+        match *self {
+            Self::ErrorBound { scale } => Self::error_bound(point, normal, scale, 0.0),
+            Self::ShapeErrorBound { scale, extent } => {
+                Self::error_bound(point, normal, scale, extent.abs())
+            }
+            Self::Fixed(distance) => distance,
+        }
+    }
+
+    // The error bound on `point` along the normal, treating each coordinate
+    // as at least `extent` in size
+    #[inline]
+    fn error_bound(point: Point, normal: Unit, scale: Float, extent: Float) -> Float {
+        let err = Vector::from(point).apply(|c| c.abs().max(extent)) * Self::RELATIVE_ERROR;
+        let n = Vector::from(normal).apply(Float::abs);
+        // Never quite zero, so points at the origin move too
+        scale * n.dot(err).max(Float::MIN_POSITIVE)
+    }
+
+    /// A ray leaving the surface at `origin`; see [`Ray::spawn`].
+    #[inline]
+    pub fn spawn(&self, origin: Point, direction: Vector, normal: Unit) -> Ray {
+        let n = Vector::from(normal);
+        let offset = match n.dot(direction) < 0.0 {
+            true => -n,
+            false => n,
+        } * self.distance(origin, normal);
+        Ray::new(origin + offset, direction)
+    }
+}
+
+impl Default for SpawnOffset {
+    #[inline]
+    fn default() -> Self {
+        Self::ErrorBound { scale: 1.0 }
+    }
+}
+
+/// A ray along with its offset rays one pixel over in `x` and `y`.
+///
+/// The differential rays estimate how much of the scene a sample covers: at
+/// distance `t` along the ray, the main ray and an offset ray are a pixel's
+/// footprint apart. Texture lookups use that to filter over the right area,
+/// rather than point sampling and aliasing.
+#[derive(Debug)]
+pub struct RayDifferential {
+    pub ray: Ray,
+    /// The offset rays for the neighbouring pixels in `x` and `y`, if the
+    /// camera generates them.
+    pub differentials: Option<(Ray, Ray)>,
+}
+
+impl RayDifferential {
+    /// A ray without differentials.
+    #[inline]
+    pub const fn new(ray: Ray) -> Self {
+        Self {
+            ray,
+            differentials: None,
+        }
+    }
+
+    /// A ray with the given offset rays for the neighbouring pixels in `x`
+    /// and `y`.
+    #[inline]
+    pub const fn with_differentials(ray: Ray, rx: Ray, ry: Ray) -> Self {
+        Self {
+            ray,
+            differentials: Some((rx, ry)),
+        }
+    }
+
+    /// Scale the offsets between the main ray and the differential rays.
+    ///
+    /// With `spp` samples per pixel, each sample stands for a smaller area
+    /// than the whole pixel, so scale by `1 / sqrt(spp)` to keep texture
+    /// filtering from blurring more than necessary.
+    pub fn scale_differentials(&mut self, s: Float) {
+        if let Some((rx, ry)) = &mut self.differentials {
+            for r in [rx, ry] {
+                r.origin = self.ray.origin + (r.origin - self.ray.origin) * s;
+                r.direction = self.ray.direction + (r.direction - self.ray.direction) * s;
+            }
+        }
+    }
+
+    /// The width of the ray's footprint at distance `t` along it, or `None`
+    /// without differentials.
+    ///
+    /// Measured perpendicular to the rays rather than on any surface, so it's
+    /// the footprint on a surface facing the camera; grazing surfaces see a
+    /// larger one.
+    pub fn footprint(&self, t: Float) -> Option<Float> {
+        let (rx, ry) = self.differentials.as_ref()?;
+        let p = self.ray.at(t);
+        Some((rx.at(t) - p).len().max((ry.at(t) - p).len()))
+    }
+}
+
+/// The cone of space a ray stands for, as it travels and bounces.
+///
+/// A cheaper stand-in for [`RayDifferential`] once a path has bounced: two
+/// numbers carried along with the ray instead of two extra rays. The cone
+/// starts `width` across at the ray's origin and widens by `spread` (in
+/// radians) per unit of distance travelled. Each bounce starts a new cone
+/// where the last one hit, and surfaces that blur what they reflect, such as
+/// curved or rough ones, widen it further.
+///
+/// The width where a ray hits is the area its sample covers there, which
+/// picks how much to filter textures by (see [`TextureContext::footprint`])
+/// and how much geometric detail could possibly show.
+///
+/// See: Akenine-Möller _et al._, "Texture Level of Detail Strategies for
+/// Real-Time Ray Tracing", _Ray Tracing Gems_ (2019).
+///
+/// [`TextureContext::footprint`]: crate::texture::TextureContext::footprint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayCone {
+    pub width: Float,
+    pub spread: Float,
+}
+
+impl RayCone {
+    /// A cone `width` across at its origin, widening by `spread` radians.
+    #[inline]
+    pub const fn new(width: Float, spread: Float) -> Self {
+        Self { width, spread }
+    }
+
+    /// The cone through a ray and its differentials, or `None` without
+    /// differentials.
+    ///
+    /// The spread is the wider of the angles between the main ray and each
+    /// offset ray.
+    pub fn from_differential(diff: &RayDifferential) -> Option<Self> {
+        let (rx, ry) = diff.differentials.as_ref()?;
+        let d = diff.ray.direction;
+        let angle = |r: &Ray| {
+            let cos = d.dot(r.direction) / (d.len() * r.direction.len());
+            cos.clamp(-1.0, 1.0).acos()
+        };
+        Some(Self {
+            width: diff.footprint(0.0)?,
+            spread: angle(rx).max(angle(ry)),
+        })
+    }
+
+    /// The width of the cone `distance` along it.
+    ///
+    /// Uses the small-angle approximation, as spreads are a pixel or so.
+    #[inline]
+    pub fn width_at(&self, distance: Float) -> Float {
+        (self.width + self.spread * distance).abs()
+    }
+
+    /// The width of the cone's footprint on a surface `distance` along it,
+    /// where the cosine between the ray and the surface normal is
+    /// `cos_theta`.
+    ///
+    /// Grazing surfaces stretch the footprint along the ray, so this is
+    /// wider than [`width_at`]; it's capped at 16 times wider, so surfaces
+    /// seen exactly edge-on don't blur away entirely.
+    ///
+    /// [`width_at`]: Self::width_at
+    #[inline]
+    pub fn footprint(&self, distance: Float, cos_theta: Float) -> Float {
+        self.width_at(distance) / cos_theta.abs().max(1.0 / 16.0)
+    }
+
+    /// The cone after travelling `distance` and bouncing off a surface that
+    /// widens it by a further `spread` radians.
+    ///
+    /// Use [`curvature_spread`] for mirror reflections off curved surfaces,
+    /// and something like the lobe width for rough ones.
+    ///
+    /// [`curvature_spread`]: Self::curvature_spread
+    #[inline]
+    pub fn bounce(&self, distance: Float, spread: Float) -> Self {
+        Self {
+            width: self.width_at(distance),
+            spread: self.spread + spread,
+        }
+    }
+
+    /// The extra spread from reflecting off a surface with the given
+    /// `curvature` (the reciprocal of its radius, positive for convex), with
+    /// the cone `width` across where it hits.
+    #[inline]
+    pub fn curvature_spread(curvature: Float, width: Float) -> Float {
+        2.0 * curvature * width
+    }
+}
+
+/// A ray prepared for repeated slab tests against axis-aligned boxes.
+///
+/// Traversing an acceleration structure tests one ray against many boxes.
+/// The slab test divides by each direction component, so this precomputes
+/// the reciprocals (and their signs) once per ray rather than once per box
+/// per axis. Zero direction components give infinite reciprocals, which the
+/// slab test handles correctly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlabRay {
+    pub origin: Point,
+    pub inv_direction: Vector,
+    /// Whether each direction component is negative, so the near and far
+    /// slab planes can be picked without comparing.
+    pub dir_is_neg: [bool; 3],
+}
+
+impl From<&Ray> for SlabRay {
+    #[inline]
+    fn from(ray: &Ray) -> Self {
+        let inv_direction = ray.inv_direction();
+        Self {
+            origin: ray.origin,
+            inv_direction,
+            dir_is_neg: [
+                inv_direction.x < 0.0,
+                inv_direction.y < 0.0,
+                inv_direction.z < 0.0,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Shape, Sphere};
+    use rand::prelude::*;
+    use rand_distr::UnitSphere;
+
+    #[test]
+    fn cone() {
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS * 2.0);
+        let rx = Ray::new(Point::new(0.01, 0.0, 0.0), Vector::new(0.02, 0.0, 2.0));
+        let ry = Ray::new(Point::ORIGIN, Vector::new(0.0, 0.01, 2.0));
+        let diff = RayDifferential::with_differentials(ray, rx, ry);
+        let cone = RayCone::from_differential(&diff).unwrap();
+        assert_eq!(0.01, cone.width);
+        assert!((cone.spread - 0.01).abs() < 1e-5);
+        assert!(RayCone::from_differential(&RayDifferential::new(diff.ray)).is_none());
+
+        let cone = RayCone::new(0.01, 0.001);
+        assert!((cone.width_at(10.0) - 0.02).abs() < 1e-12);
+        assert!((cone.footprint(10.0, -0.5) - 0.04).abs() < 1e-12);
+        assert!((cone.footprint(10.0, 0.0) - 0.32).abs() < 1e-12);
+
+        // A convex mirror spreads reflections out; the cone starts afresh at
+        // the hit
+        let spread = RayCone::curvature_spread(1.0, cone.width_at(10.0));
+        let bounced = cone.bounce(10.0, spread);
+        assert!((bounced.width_at(0.0) - 0.02).abs() < 1e-12);
+        assert!((bounced.spread - 0.041).abs() < 1e-12);
+    }
+
+    #[test]
+    fn spawn_clears_surface() {
+        // Far from the origin, where fixed epsilons are too small
+        let center = Point::new(1e4, -2e4, 5e3);
+        let sphere = Sphere::new(center, 50.0);
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let norm = Unit::try_from(Vector::from(UnitSphere.sample(&mut rng))).unwrap();
+            let probe = Ray::new(center + Vector::from(norm) * 100.0, -Vector::from(norm));
+            let Some(isect) = sphere.intersect(&probe, 0.0, Float::INFINITY) else {
+                continue;
+            };
+
+            // Leaving outwards never re-hits; leaving inwards always does
+            let out = Vector::from(UnitSphere.sample(&mut rng));
+            let out = match out.dot(isect.norm.into()) < 0.0 {
+                true => -out,
+                false => out,
+            };
+            let ray = Ray::spawn(isect.point, out, isect.norm);
+            assert!(!sphere.intersects(&ray, 0.0, Float::INFINITY));
+            let ray = Ray::spawn(isect.point, -out, isect.norm);
+            assert!(sphere.intersect(&ray, 0.0, Float::INFINITY).unwrap().t > 0.0);
+        }
+    }
+
+    #[test]
+    fn spawn_offsets() {
+        let point = Point::new(0.0, 0.0, 1.0);
+        let ray = SpawnOffset::Fixed(0.5).spawn(point, Vector::new(1.0, 0.0, -1.0), Unit::Z_AXIS);
+        assert_eq!(Point::new(0.0, 0.0, 0.5), ray.origin);
+
+        // Scaled with the coordinates along the normal
+        let near = SpawnOffset::default().distance(point, Unit::Z_AXIS);
+        let far = SpawnOffset::default().distance(Point::new(0.0, 0.0, 1e3), Unit::Z_AXIS);
+        let sideways = SpawnOffset::default().distance(Point::new(1e3, 0.0, 1.0), Unit::Z_AXIS);
+        assert!(near > 0.0 && far > 100.0 * near);
+        assert_eq!(near, sideways);
+
+        // Leaving a big shape near the origin moves as far as leaving it at
+        // its edge
+        let shape = SpawnOffset::ShapeErrorBound {
+            scale: 1.0,
+            extent: 1e3,
+        };
+        assert_eq!(far, shape.distance(point, Unit::Z_AXIS));
+        assert_eq!(
+            far,
+            shape.distance(Point::new(0.0, 0.0, -1e3), Unit::Z_AXIS)
+        );
+    }
 }
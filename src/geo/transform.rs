@@ -0,0 +1,186 @@
+use super::{Matrix, Point, Radians, Ray, Unit, Vector};
+use crate::Float;
+use std::ops::Mul;
+
+/// An invertible transformation, stored with its inverse.
+///
+/// Shapes and cameras need to map both ways between world and local space,
+/// often for every ray. Inverting a [`Matrix`] each time is slow and loses
+/// precision, so a `Transform` computes the inverse once, when it's created.
+/// The named constructors use closed-form inverses and skip Gauss-Jordan
+/// entirely.
+///
+/// Transforms compose with `*` like matrices: `(a * b).apply_point(p)` is
+/// `a.apply_point(b.apply_point(p))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: Matrix,
+    m_inv: Matrix,
+}
+
+impl Transform {
+    /// The identity transform.
+    pub const IDENTITY: Transform = Self {
+        m: Matrix::IDENTITY,
+        m_inv: Matrix::IDENTITY,
+    };
+
+    /// Create a transform from a matrix, or `None` if it isn't invertible.
+    pub fn new(m: Matrix) -> Option<Self> {
+        Some(Self {
+            m,
+            m_inv: m.inverse()?,
+        })
+    }
+
+    /// Translation by the given vector. See [`Matrix::shift`].
+    pub fn shift(v: Vector) -> Self {
+        Self {
+            m: Matrix::shift(v),
+            m_inv: Matrix::shift(-v),
+        }
+    }
+
+    /// Scaling by the given magnitudes. See [`Matrix::scale`].
+    ///
+    /// Zero scales give a non-finite inverse.
+    pub fn scale(x: Float, y: Float, z: Float) -> Self {
+        Self {
+            m: Matrix::scale(x, y, z),
+            m_inv: Matrix::scale(x.recip(), y.recip(), z.recip()),
+        }
+    }
+
+    /// Rotation by `theta` about the given axis. See [`Matrix::rotate`].
+    pub fn rotate(theta: impl Into<Radians>, axis: Unit) -> Self {
+        let m = Matrix::rotate(theta, axis);
+        Self {
+            m,
+            m_inv: m.transpose(),
+        }
+    }
+
+    /// Camera-to-world look-at transform. See [`Matrix::look_at`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` and `to` coincide, or the view direction is parallel
+    /// to `up`.
+    pub fn look_at(from: Point, to: Point, up: Vector) -> Self {
+        Self::new(Matrix::look_at(from, to, up)).expect("look-at matrix is not invertible")
+    }
+
+    /// The inverse transform. This is free, since both matrices are stored.
+    #[inline]
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    /// The transform's matrix.
+    #[inline]
+    pub fn matrix(&self) -> &Matrix {
+        &self.m
+    }
+
+    /// The matrix of the inverse transform.
+    #[inline]
+    pub fn inverse_matrix(&self) -> &Matrix {
+        &self.m_inv
+    }
+
+    #[inline]
+    pub fn apply_point(&self, p: Point) -> Point {
+        self.m * p
+    }
+
+    #[inline]
+    pub fn apply_vector(&self, v: Vector) -> Vector {
+        self.m * v
+    }
+
+    /// Transform a surface normal.
+    ///
+    /// Normals transform by the inverse transpose, so they stay perpendicular
+    /// to transformed surfaces under non-uniform scaling. Returns `None` if
+    /// the result is degenerate.
+    #[inline]
+    pub fn apply_normal(&self, n: Unit) -> Option<Unit> {
+        Unit::try_from(self.m_inv.transpose() * n).ok()
+    }
+
+    /// Transform a ray's origin and direction. The direction isn't
+    /// renormalized, so ray parameters `t` are preserved.
+    #[inline]
+    pub fn apply_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.m * ray.origin, self.m * ray.direction)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            m: self.m * rhs.m,
+            m_inv: rhs.m_inv * self.m_inv,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Degrees;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn composed_inverse() {
+        let t = Transform::shift(Vector::new(1.0, 2.0, 3.0))
+            * Transform::rotate(Degrees(30.0), Unit::Y_AXIS)
+            * Transform::scale(2.0, 3.0, 4.0);
+        let p = Point::new(-1.0, 0.5, 2.0);
+
+        let round_trip = t.inverse().apply_point(t.apply_point(p));
+        assert_relative_eq!(Vector::from(p), Vector::from(round_trip), epsilon = 1e-6);
+        assert_relative_eq!(
+            Matrix::IDENTITY,
+            *t.matrix() * *t.inverse_matrix(),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            t.inverse_matrix(),
+            &t.matrix().inverse().unwrap(),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        let t = Transform::scale(1.0, 4.0, 1.0) * Transform::rotate(Degrees(45.0), Unit::Z_AXIS);
+        // A surface containing the x and z axes, with normal y
+        let tangent = t.apply_vector(Vector::X_AXIS);
+        let normal = Vector::from(t.apply_normal(Unit::Y_AXIS).unwrap());
+        assert_relative_eq!(0.0, tangent.dot(normal), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn look_at_round_trip() {
+        let from = Point::new(1.0, 2.0, 3.0);
+        let t = Transform::look_at(from, Point::ORIGIN, Vector::Y_AXIS);
+        assert_eq!(from, t.apply_point(Point::ORIGIN));
+
+        let ray = Ray::new(from, Vector::new(-1.0, -2.0, -3.0));
+        let local = t.inverse().apply_ray(&ray);
+        assert_relative_eq!(Vector::ZERO, Vector::from(local.origin), epsilon = 1e-6);
+    }
+}
@@ -52,8 +52,9 @@ impl Unit {
     /// The unit vector along the z-axis.
     pub const Z_AXIS: Unit = Unit::new(0.0, 0.0, 1.0);
 
+    // Callers must guarantee unit length
     #[inline]
-    const fn new(x: Float, y: Float, z: Float) -> Self {
+    pub(super) const fn new(x: Float, y: Float, z: Float) -> Self {
         Self { x, y, z }
     }
 
@@ -74,6 +75,59 @@ impl Unit {
     pub const fn z(&self) -> Float {
         self.z
     }
+
+    /// Compute the dot product with a [`Vector`] or another [`Unit`]. For
+    /// two units, this is the cosine of the angle between them.
+    #[inline]
+    pub fn dot(&self, rhs: impl Into<Vector>) -> Float {
+        Vector::from(*self).dot(rhs.into())
+    }
+
+    /// Compute the cross product with a [`Vector`] or another [`Unit`]. The
+    /// result is generally not unit-length, so it's a [`Vector`].
+    #[inline]
+    pub fn cross(&self, rhs: impl Into<Vector>) -> Vector {
+        Vector::from(*self).cross(rhs.into())
+    }
+}
+
+/// Reflect `wo` about the normal `n`.
+///
+/// Follows the shading convention that `wo` points away from the surface, so
+/// the result does too: it's the mirror direction on the same side as `wo`.
+#[inline]
+pub fn reflect(wo: Unit, n: Unit) -> Unit {
+    let r = Vector::from(n) * (2.0 * wo.dot(n)) - wo.into();
+    Unit::new(r.x, r.y, r.z)
+}
+
+/// Refract `wo` through a surface with normal `n`, by Snell's law.
+///
+/// `wo` points away from the surface and `n` should be on the same side as
+/// it. `eta` is the ratio of the index of refraction on the far side of the
+/// surface to that on `wo`'s side. Returns the transmitted direction, pointing
+/// away from the surface on the far side, or `None` on total internal
+/// reflection.
+#[inline]
+pub fn refract(wo: Unit, n: Unit, eta: Float) -> Option<Unit> {
+    let cos_o = wo.dot(n);
+    let sin2_t = (1.0 - cos_o * cos_o).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let t = -Vector::from(wo) / eta + Vector::from(n) * (cos_o / eta - cos_t);
+    Unit::try_from(t).ok()
+}
+
+/// Map a point `u` in the unit square to the unit sphere, preserving area, so
+/// uniform points give uniform directions.
+#[inline]
+pub fn uniform_sphere(u: [Float; 2]) -> Unit {
+    let z = 1.0 - 2.0 * u[0];
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI as Float * u[1];
+    Unit::try_from(Vector::new(r * phi.cos(), r * phi.sin(), z)).unwrap_or(Unit::Z_AXIS)
 }
 
 // OPERATORS
@@ -130,3 +184,40 @@ impl TryFrom<Vector> for Unit {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn dot_and_cross() {
+        assert_eq!(0.0, Unit::X_AXIS.dot(Unit::Y_AXIS));
+        assert_eq!(2.0, Unit::X_AXIS.dot(Vector::new(2.0, 3.0, 4.0)));
+        assert_eq!(Vector::Z_AXIS, Unit::X_AXIS.cross(Unit::Y_AXIS));
+    }
+
+    #[test]
+    fn reflect_and_refract() {
+        let wo = Vector::new(1.0, 1.0, 0.0).normalize();
+        let r = reflect(wo, Unit::Y_AXIS);
+        assert_relative_eq!(
+            Vector::new(-1.0, 1.0, 0.0) / 2.0_f64.sqrt() as Float,
+            r.into()
+        );
+
+        // Snell's law: sin θo = eta sin θt
+        let eta = 1.5;
+        let t = refract(wo, Unit::Y_AXIS, eta).unwrap();
+        assert!(t.y() < 0.0);
+        let sin_o = wo.cross(Unit::Y_AXIS).len();
+        let sin_t = t.cross(Unit::Y_AXIS).len();
+        assert_relative_eq!(sin_o, eta * sin_t, epsilon = 1e-6);
+        // Head-on rays pass straight through
+        let straight = refract(Unit::Y_AXIS, Unit::Y_AXIS, eta).unwrap();
+        assert_relative_eq!(-Vector::Y_AXIS, straight.into());
+
+        // Total internal reflection going from glass to air at 45 degrees
+        assert_eq!(None, refract(wo, Unit::Y_AXIS, 1.0 / eta));
+    }
+}
@@ -1,68 +1,152 @@
 use crate::{
-    camera::Camera,
-    color::{Color, RGB},
-    film::Film,
-    geo::{Ray, Vector},
-    shape::{Shape, Surface},
+    color::RGB,
+    geo::{Ray, SpawnOffset, Vector},
+    light::Environment,
+    material::BSDFFlags,
+    sampler::Sampler,
+    shape::{Clip, Surface},
     Float,
 };
 use rand::prelude::*;
 use rand_distr::UnitSphere;
-use rayon::prelude::*;
 
+mod clamp;
+pub use clamp::*;
+
+mod fog;
+pub use fog::*;
+
+mod irradiance;
+pub use irradiance::*;
+
+mod light_path;
+pub use light_path::*;
+
+mod light_tracer;
+pub use light_tracer::*;
+
+mod limits;
+pub use limits::*;
+
+mod path;
+pub use path::*;
+
+mod roulette;
+pub use roulette::*;
+
+mod shadow;
+pub use shadow::*;
+
+mod sppm;
+pub use sppm::*;
+
+mod toon;
+pub use toon::*;
+
+/// Finds the light arriving along camera rays, one pixel sample at a time.
+///
+/// `Li` is what's measured: usually [`RGB`] radiance, but AOVs return
+/// normals, depths and the like. Integrators that trace from the lights,
+/// such as [`LightTracer`] and [`Sppm`], work on the whole film at once and
+/// don't implement it.
 pub trait Integrator<Li>: Send + Sync {
     fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> Li;
+
+    /// Find the light arriving along `ray`, drawing the decisions made at
+    /// each bounce from `sampler`.
+    ///
+    /// The sampler must have been started on this pixel sample, and values
+    /// are taken from its per-bounce [`SampleDimension`]s, so every path of a
+    /// low-discrepancy sequence spends the same dimension on the same
+    /// decision. `rng` is left for anything else. By default the sampler is
+    /// ignored and this is just [`radiance`].
+    ///
+    /// [`SampleDimension`]: crate::sampler::SampleDimension
+    /// [`radiance`]: Self::radiance
+    fn radiance_sampled(&self, ray: &Ray, sampler: &mut impl Sampler, rng: &mut impl Rng) -> Li {
+        let _ = sampler;
+        self.radiance(ray, rng)
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct Hacky {
-    pub background: RGB,
+    pub background: Environment,
     pub surfaces: Vec<Surface>,
+    pub limits: WorkLimits,
+    pub bounces: BounceLimits,
+    pub roulette: Roulette,
+    /// Caps on path contributions, to suppress fireflies.
+    pub clamp: RadianceClamp,
+    pub clip: Clip,
+    /// Treat every surface as two-sided, so hits always scatter back to
+    /// the side the ray came from. Off by default; see [`TwoSided`] to
+    /// choose surface by surface.
+    ///
+    /// [`TwoSided`]: crate::shape::TwoSided
+    pub two_sided: bool,
+    /// How far bounce rays start off the surface they leave.
+    pub offset: SpawnOffset,
 }
 
 impl Hacky {
-    fn ray_color(&self, ray: &Ray, rng: &mut impl Rng, depth: usize) -> RGB {
-        if let Some(isect) = self.surfaces.intersect(ray, 0.001, Float::INFINITY) {
-            if depth < 50 {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        rng: &mut impl Rng,
+        budget: &mut PathBudget,
+        counts: &mut BounceCounts,
+        throughput: RGB,
+        depth: usize,
+    ) -> RGB {
+        if !budget.query() {
+            return RGB::from([0.0, 0.0, 0.0]);
+        }
+
+        let isect = self
+            .clip
+            .intersect(&self.surfaces, ray, 0.0, Float::INFINITY);
+        if let Some(mut isect) = isect {
+            if self.two_sided {
+                isect = isect.facing(ray.direction);
+            }
+            let mut weight = throughput * 0.5;
+            if budget.bounce()
+                && counts.scatter(BSDFFlags::REFLECTION | BSDFFlags::DIFFUSE)
+                && self.roulette.survive(&mut weight, depth, rng)
+            {
                 let rand_vec = Vector::from(UnitSphere.sample(rng));
-                let target = isect.point + isect.norm.into() + rand_vec;
-                let ray = Ray::new(isect.point, target - isect.point);
-                self.ray_color(&ray, rng, depth + 1) * 0.5
+                let dir = Vector::from(isect.norm) + rand_vec;
+                let ray = self.offset.spawn(isect.point, dir, isect.norm);
+                self.ray_color(&ray, rng, budget, counts, weight, depth + 1)
             } else {
                 RGB::from([0.0, 0.0, 0.0])
             }
         } else {
-            self.background
+            let contribution = throughput * self.background.radiance(ray.direction);
+            self.clamp.apply(contribution, depth)
         }
     }
 }
 
 impl Integrator<RGB> for Hacky {
     fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> RGB {
-        loop {
-            if let Some(isect) = self.surfaces.intersect(ray, 0.001, Float::INFINITY) {}
-        }
-        self.ray_color(ray, rng, 0)
+        self.ray_color(
+            ray,
+            rng,
+            &mut self.limits.budget(),
+            &mut self.bounces.start(),
+            RGB::splat(1.0),
+            0,
+        )
     }
 }
 
-pub fn render<CS, Li>(film: &mut Film<CS>, cam: &impl Camera, integrator: &impl Integrator<Li>)
-where
-    Color<CS>: From<Li> + Copy + Send,
-    CS: Copy,
-{
-    film.par_pixel_iter_mut()
-        .for_each_init(rand::thread_rng, |rng, (px, py, pixel)| {
-            let ray = cam.ray(px, py, rng);
-            let rad = integrator.radiance(&ray, rng);
-            pixel.add_sample(rad);
-        });
-}
-
+#[cfg(test)]
 fn scope(s: String) {
     let mut s = s;
     while s.len() > 1 {
-        print!("{}\n", s);
+        println!("{}", s);
         let mut chars = s.chars();
         chars.next();
         s = String::from(chars.as_str());
@@ -0,0 +1,72 @@
+use crate::{color::RGB, metrics::Counter, Float};
+
+/// Number of path contributions scaled down by a [`RadianceClamp`].
+pub static CLAMPED_SAMPLES: Counter = Counter::new();
+
+/// Caps on the radiance a single path contribution can carry, to suppress
+/// fireflies.
+///
+/// Fireflies are rare paths that find a bright light through an unlikely
+/// bounce, such as a small light seen in a glossy reflection off a diffuse
+/// wall, and carry a huge weight to make up for how rarely they happen. They
+/// take millions of samples to average out. Clamping caps each contribution's
+/// largest component, keeping its hue, so they never get bright enough to
+/// stand out.
+///
+/// This is biased: the energy clamped away is lost, so clamped images come
+/// out darker. Light arriving directly at the camera is rarely the culprit,
+/// so the usual setup only clamps the [`indirect`] light, leaving light
+/// sources and directly lit surfaces exact. Both default to no clamping.
+///
+/// [`indirect`]: Self::indirect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadianceClamp {
+    /// Maximum contribution of light found by camera rays.
+    pub direct: Float,
+    /// Maximum contribution of light found after one or more bounces.
+    pub indirect: Float,
+}
+
+impl Default for RadianceClamp {
+    fn default() -> Self {
+        Self {
+            direct: Float::INFINITY,
+            indirect: Float::INFINITY,
+        }
+    }
+}
+
+impl RadianceClamp {
+    /// Clamp the contribution of light found `depth` bounces into a path.
+    #[inline]
+    pub fn apply(&self, contribution: RGB, depth: usize) -> RGB {
+        let limit = match depth {
+            0 => self.direct,
+            _ => self.indirect,
+        };
+        let max = contribution.max_component();
+        if max > limit {
+            CLAMPED_SAMPLES.inc();
+            contribution * (limit / max)
+        } else {
+            contribution
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_hue() {
+        let clamp = RadianceClamp {
+            indirect: 2.0,
+            ..Default::default()
+        };
+        let firefly = RGB::from([40.0, 20.0, 0.0]);
+        assert_eq!(firefly, clamp.apply(firefly, 0));
+        assert_eq!(RGB::from([2.0, 1.0, 0.0]), clamp.apply(firefly, 1));
+        assert_eq!(RGB::splat(1.0), clamp.apply(RGB::splat(1.0), 3));
+    }
+}
@@ -0,0 +1,501 @@
+use super::{sppm::is_specular_only, Integrator, Roulette};
+use crate::{
+    color::RGB,
+    geo::{Frame, Point, Ray, SpawnOffset, Unit, Vector},
+    light::{Environment, SpotLight},
+    material::{Material, RayType, BSDF},
+    scene::{Scene, SurfaceInteraction},
+    Float,
+};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    sync::{RwLock, RwLockReadGuard},
+};
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// Irradiance caching, for scenes lit mostly by diffuse interreflection.
+///
+/// In a room lit through a window, most of the light on any wall has
+/// bounced off the other walls, and a path tracer needs many samples per
+/// pixel to get it smooth. But that indirect light changes slowly across a
+/// diffuse surface, so it's wasteful to find it from scratch at every
+/// pixel. Irradiance caching finds it carefully at a few scattered points,
+/// by tracing a few hundred rays over the hemisphere, and interpolates
+/// everywhere in between.
+///
+/// Each of those cache records also estimates how fast its irradiance
+/// changes as the point moves and the surface turns, and is extrapolated
+/// along those gradients. How far a record reaches is set by how close the
+/// surrounding geometry is, since light changes fastest near other
+/// surfaces, and by the size of its gradient. Lower [`error`] values make
+/// records reach less far, so more are computed.
+///
+/// Records are computed lazily, the first time a point isn't covered, and
+/// kept for later pixels and frames; [`clear`] them when the scene changes.
+/// Direct light from [`SpotLight`]s is found by shadow rays at every pixel,
+/// and light reflected by specular surfaces is followed from the camera, so
+/// only the slowly changing part is cached. Indirect light is reflected as
+/// if the surface were diffuse.
+///
+/// See: Ward, Rubinstein and Clear, "A Ray Tracing Solution for Diffuse
+/// Interreflection" (1988); Ward and Heckbert, "Irradiance Gradients"
+/// (1992).
+///
+/// [`error`]: Self::error
+/// [`clear`]: Self::clear
+pub struct IrradianceCache<'a> {
+    scene: &'a Scene,
+    lights: Vec<SpotLight>,
+    background: Environment,
+    error: Float,
+    min_spacing: Float,
+    max_spacing: Float,
+    strata: (usize, usize),
+    max_depth: usize,
+    roulette: Roulette,
+    cache: RwLock<Records>,
+}
+
+/// The cache records, and a uniform grid over the regions they cover.
+#[derive(Debug, Default)]
+struct Records {
+    records: Vec<Record>,
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+/// Irradiance found at a point, and how it changes nearby.
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    point: Point,
+    normal: Unit,
+    irradiance: RGB,
+    // How far the record can be trusted, before the error threshold
+    radius: Float,
+    // Per color channel
+    rotation: [Vector; 3],
+    translation: [Vector; 3],
+}
+
+impl Record {
+    // Ward's weight: infinite at the record, falling off with distance
+    // relative to the record's radius and with the normals' divergence
+    fn weight(&self, point: Point, normal: Unit) -> Float {
+        let d = point - self.point;
+        let turn = (1.0 - normal.dot(self.normal)).max(0.0).sqrt();
+        1.0 / (d.len() / self.radius + turn).max(1e-9)
+    }
+
+    // The irradiance at a nearby point and normal, following the gradients
+    fn extrapolate(&self, point: Point, normal: Unit) -> RGB {
+        let d = point - self.point;
+        let turn = self.normal.cross(normal);
+        let e: [Float; 3] = self.irradiance.into();
+        RGB::from(
+            [0, 1, 2]
+                .map(|c| (e[c] + turn.dot(self.rotation[c]) + d.dot(self.translation[c])).max(0.0)),
+        )
+    }
+}
+
+impl<'a> IrradianceCache<'a> {
+    /// Render `scene`, lit by `lights` and the background.
+    pub fn new(scene: &'a Scene, lights: Vec<SpotLight>) -> Self {
+        Self {
+            scene,
+            lights,
+            background: Environment::default(),
+            error: 0.2,
+            min_spacing: 0.05,
+            max_spacing: 2.0,
+            strata: (12, 36),
+            max_depth: 4,
+            roulette: Roulette::default(),
+            cache: RwLock::default(),
+        }
+    }
+
+    /// Set the environment seen by rays that escape. Defaults to black.
+    pub fn background(mut self, background: impl Into<Environment>) -> Self {
+        self.background = background.into();
+        self
+    }
+
+    /// Set the error threshold, greater than `0`. Records reach the error
+    /// times their radius, for points facing the same way. Defaults to
+    /// `0.2`.
+    pub fn error(mut self, error: Float) -> Self {
+        self.error = error.max(Float::EPSILON);
+        self
+    }
+
+    /// Set the smallest and largest record radius, in world units. Defaults
+    /// to `0.05` and `2`, for scenes modeled in meters.
+    ///
+    /// The smallest keeps records from crowding into corners, where nearby
+    /// geometry would otherwise shrink them without limit. The largest
+    /// keeps them from reaching across open spaces.
+    pub fn spacing(mut self, min: Float, max: Float) -> Self {
+        self.max_spacing = max.max(Float::EPSILON);
+        self.min_spacing = min.clamp(0.0, self.max_spacing);
+        self
+    }
+
+    /// Set how finely the hemisphere is divided for each record: the number
+    /// of strata in elevation and azimuth. One ray is traced per stratum.
+    /// Defaults to `12` by `36`.
+    pub fn strata(mut self, theta: usize, phi: usize) -> Self {
+        self.strata = (theta.max(1), phi.max(1));
+        self
+    }
+
+    /// Set the most surfaces a path visits, including the one the camera
+    /// sees. Defaults to `4`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set the Russian roulette for the paths traced from each record.
+    pub fn roulette(mut self, roulette: Roulette) -> Self {
+        self.roulette = roulette;
+        self
+    }
+
+    /// The number of records computed so far.
+    pub fn len(&self) -> usize {
+        self.records().records.len()
+    }
+
+    /// Returns `true` if no records have been computed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Throw away every record, for when the scene has changed.
+    pub fn clear(&mut self) {
+        *self.cache.get_mut().unwrap_or_else(|e| e.into_inner()) = Records::default();
+    }
+
+    // Follow a camera path through specular surfaces, and shade the first
+    // surface that isn't
+    fn li(&self, ray: &Ray, depth: usize, rng: &mut impl Rng) -> RGB {
+        let Some(hit) = self.scene.intersect(ray, 0.0, Float::INFINITY) else {
+            return self.background.radiance(ray.direction);
+        };
+        let Ok(wo) = Unit::try_from(-ray.direction) else {
+            return RGB::default();
+        };
+        let isect = &hit.isect;
+        let material = hit.material.for_ray(RayType::from_depth(depth));
+
+        if is_specular_only(material) {
+            if depth + 1 >= self.max_depth {
+                return RGB::default();
+            }
+            let Some(s) = material.sample_f(wo, isect, rng) else {
+                return RGB::default();
+            };
+            let ray = hit.spawn(s.wi.into());
+            let beta = s.f * (s.wi.dot(isect.norm).abs() / s.pdf);
+            return beta * self.li(&ray, depth + 1, rng);
+        }
+
+        let diffuse = material.f(wo, isect.norm, isect);
+        let indirect = match diffuse.is_black() {
+            true => RGB::default(),
+            false => diffuse * self.irradiance(isect.point, isect.norm, hit.offset, rng),
+        };
+        self.direct(material, wo, &hit) + indirect
+    }
+
+    // Light arriving straight from the spot lights
+    fn direct(&self, material: &Material, wo: Unit, hit: &SurfaceInteraction) -> RGB {
+        let isect = &hit.isect;
+        let mut total = RGB::default();
+        for light in &self.lights {
+            let Some(s) = light.sample(isect.point, [0.5, 0.5]) else {
+                continue;
+            };
+            let f = material.f(wo, s.wi, isect);
+            if f.is_black() {
+                continue;
+            }
+            let shadow = hit.spawn(s.wi.into());
+            if !self.scene.occluded(&shadow, 0.0, s.dist * (1.0 - 1e-4)) {
+                total += f * s.li * (s.wi.dot(isect.norm).abs() / s.pdf);
+            }
+        }
+        total
+    }
+
+    // Indirect irradiance, from the cache if it covers the point
+    fn irradiance(
+        &self,
+        point: Point,
+        normal: Unit,
+        offset: SpawnOffset,
+        rng: &mut impl Rng,
+    ) -> RGB {
+        if let Some(e) = self.lookup(point, normal) {
+            return e;
+        }
+        let record = self.record(point, normal, offset, rng);
+        self.insert(record);
+        record.irradiance
+    }
+
+    // Blend every record that covers the point
+    fn lookup(&self, point: Point, normal: Unit) -> Option<RGB> {
+        let cache = self.records();
+        let (mut total, mut weights) = (RGB::default(), 0.0);
+        for &idx in cache.cells.get(&self.cell_of(point))? {
+            let record = &cache.records[idx];
+            let w = record.weight(point, normal);
+            if w <= 1.0 / self.error {
+                continue;
+            }
+            // Skip records in front of the point, which see light it can't
+            let mid = Vector::from(normal) + Vector::from(record.normal);
+            if (point - record.point).dot(mid) < -0.02 * record.radius {
+                continue;
+            }
+            total += record.extrapolate(point, normal) * w;
+            weights += w;
+        }
+        (weights > 0.0).then(|| total / weights)
+    }
+
+    fn insert(&self, record: Record) {
+        let mut cache = self.cache.write().unwrap_or_else(|e| e.into_inner());
+        let idx = cache.records.len();
+        cache.records.push(record);
+
+        let reach = self.error * record.radius;
+        let extent = Vector::new(reach, reach, reach);
+        let lo = self.cell_of(record.point + -extent);
+        let hi = self.cell_of(record.point + extent);
+        for x in lo[0]..=hi[0] {
+            for y in lo[1]..=hi[1] {
+                for z in lo[2]..=hi[2] {
+                    cache.cells.entry([x, y, z]).or_default().push(idx);
+                }
+            }
+        }
+    }
+
+    // Cells are as big as the furthest a record can reach, so each record
+    // covers at most eight
+    fn cell_of(&self, p: Point) -> [i64; 3] {
+        let cell = self.error * self.max_spacing;
+        [p.x, p.y, p.z].map(|c| (c / cell).floor() as i64)
+    }
+
+    fn records(&self) -> RwLockReadGuard<'_, Records> {
+        self.cache.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Sample the hemisphere above the point in cosine-weighted strata, and
+    // estimate the irradiance and its gradients from the samples
+    fn record(
+        &self,
+        point: Point,
+        normal: Unit,
+        offset: SpawnOffset,
+        rng: &mut impl Rng,
+    ) -> Record {
+        let frame = Frame::from_normal(normal);
+        let (m, n) = self.strata;
+        let (mf, nf) = (m as Float, n as Float);
+        let mut radiance = vec![[0.0; 3]; m * n];
+        let mut dist = vec![Float::INFINITY; m * n];
+        let mut sin_theta = vec![0.0; m];
+
+        for j in 0..m {
+            for k in 0..n {
+                let (u, v): (Float, Float) = rng.gen();
+                let sin2 = (j as Float + u) / mf;
+                let (sin, cos) = (sin2.sqrt(), (1.0 - sin2).max(0.0).sqrt());
+                let (sin_phi, cos_phi) = (2.0 * PI * (k as Float + v) / nf).sin_cos();
+                let local = Vector::new(sin * cos_phi, sin * sin_phi, cos);
+                let ray = offset.spawn(point, frame.to_world(local), normal);
+                let (l, t) = self.trace(ray, rng);
+                radiance[j * n + k] = l.into();
+                dist[j * n + k] = t;
+                sin_theta[j] += sin / nf;
+            }
+        }
+
+        let mut irradiance = [0.0; 3];
+        let mut rotation = [Vector::ZERO; 3];
+        let mut translation = [Vector::ZERO; 3];
+        for k in 0..n {
+            let azimuth = |phi: Float| {
+                let (sin, cos) = phi.sin_cos();
+                frame.to_world(Vector::new(cos, sin, 0.0))
+            };
+            let center = 2.0 * PI * (k as Float + 0.5) / nf;
+            let u_k = azimuth(center);
+            // Turning the normal towards a direction weights it more
+            let turn = normal.cross(u_k);
+            let v_edge = azimuth(2.0 * PI * k as Float / nf + PI / 2.0);
+            let prev = (k + n - 1) % n;
+
+            for j in 0..m {
+                let l = radiance[j * n + k];
+                let tan = sin_theta[j] / (1.0 - sin_theta[j] * sin_theta[j]).max(1e-9).sqrt();
+
+                // Across the boundary with the stratum below in elevation
+                let below = match j {
+                    0 => None,
+                    _ => {
+                        let sin2 = j as Float / mf;
+                        let r = dist[j * n + k].min(dist[(j - 1) * n + k]);
+                        Some((sin2.sqrt() * (1.0 - sin2) / r, radiance[(j - 1) * n + k]))
+                    }
+                };
+                // Across the boundary with the previous stratum in azimuth
+                let r = dist[j * n + k].min(dist[j * n + prev]);
+                let side = (((j + 1) as Float / mf).sqrt() - (j as Float / mf).sqrt()) / r;
+                let beside = radiance[j * n + prev];
+
+                for c in 0..3 {
+                    irradiance[c] += l[c] * PI / (mf * nf);
+                    rotation[c] += turn * (tan * l[c] * PI / (mf * nf));
+                    if let Some((scale, below)) = below {
+                        translation[c] += u_k * (2.0 * PI / nf * scale * (l[c] - below[c]));
+                    }
+                    translation[c] += v_edge * (side * (l[c] - beside[c]));
+                }
+            }
+        }
+
+        // Reach as far as the harmonic mean distance to the surroundings,
+        // but no further than the gradient says the irradiance would take
+        // to change by its own value
+        let inverse: Float = dist.iter().map(|d| 1.0 / d).sum();
+        let mut radius = (m * n) as Float / inverse;
+        for c in 0..3 {
+            let gradient = translation[c].len();
+            if gradient > 0.0 && irradiance[c] > 0.0 {
+                radius = radius.min(irradiance[c] / gradient);
+            }
+        }
+        Record {
+            point,
+            normal,
+            irradiance: RGB::from(irradiance),
+            radius: radius.clamp(self.min_spacing.max(Float::EPSILON), self.max_spacing),
+            rotation,
+            translation,
+        }
+    }
+
+    // Path trace the radiance arriving back along a ray leaving a record,
+    // and how far the ray went
+    fn trace(&self, mut ray: Ray, rng: &mut impl Rng) -> (RGB, Float) {
+        let (mut total, mut beta) = (RGB::default(), RGB::splat(1.0));
+        let mut first = Float::INFINITY;
+        // The record's own surface is the first
+        for depth in 1.. {
+            let Some(hit) = self.scene.intersect(&ray, 0.0, Float::INFINITY) else {
+                total += beta * self.background.radiance(ray.direction);
+                break;
+            };
+            if depth == 1 {
+                first = hit.isect.t * ray.direction.len();
+            }
+            let Ok(wo) = Unit::try_from(-ray.direction) else {
+                break;
+            };
+            if depth >= self.max_depth {
+                break;
+            }
+            let isect = &hit.isect;
+            let material = hit.material.for_ray(RayType::Indirect);
+            total += beta * self.direct(material, wo, &hit);
+
+            let Some(s) = material.sample_f(wo, isect, rng) else {
+                break;
+            };
+            beta *= s.f * (s.wi.dot(isect.norm).abs() / s.pdf);
+            if beta.is_black() || !self.roulette.survive(&mut beta, depth, rng) {
+                break;
+            }
+            ray = hit.spawn(s.wi.into());
+        }
+        (total, first)
+    }
+}
+
+impl Integrator<RGB> for IrradianceCache<'_> {
+    fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> RGB {
+        self.li(ray, 0, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, shape::Triangle};
+    use rand::prelude::*;
+
+    fn floor() -> Triangle {
+        Triangle::new([-10.0, 1.0, -10.0], [0.0, 1.0, 10.0], [10.0, 1.0, -10.0])
+    }
+
+    #[test]
+    fn open_sky() {
+        // Under a uniform sky, a floor gets irradiance π everywhere, so
+        // reflects its albedo; one record covers the whole neighborhood
+        let mut scene = Scene::default();
+        scene.add_primitive(floor(), Lambertian::new(RGB::splat(0.5)));
+        let cache = IrradianceCache::new(&scene, Vec::new()).background(RGB::splat(1.0));
+        let mut rng = StdRng::seed_from_u64(1);
+        for i in 0..50 {
+            let x = i as Float * 0.002;
+            let ray = Ray::new(Point::new(x, 2.0, 0.0), Vector::new(0.0, -1.0, 0.1));
+            let l = cache.radiance(&ray, &mut rng);
+            assert!((l.max_component() - 0.5).abs() < 1e-9, "{:?}", l);
+        }
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn gradients_extrapolate() {
+        // Next to a low wall, less sky is seen the closer the point gets, or
+        // the more it turns towards the wall
+        let grey = || Lambertian::new(RGB::splat(0.5));
+        let mut scene = Scene::default();
+        scene.add_primitive(floor(), grey());
+        let (a, b) = ([0.5, 1.0, -10.0], [0.5, 1.5, 10.0]);
+        scene.add_primitive(Triangle::new(a, [0.5, 1.0, 10.0], b), grey());
+        scene.add_primitive(Triangle::new(a, b, [0.5, 1.5, -10.0]), grey());
+        let cache = IrradianceCache::new(&scene, Vec::new())
+            .background(RGB::splat(1.0))
+            .strata(40, 120)
+            .max_depth(1);
+        let offset = scene.spawn_offset(0);
+        let mut rng = StdRng::seed_from_u64(2);
+
+        let up = Unit::Y_AXIS;
+        let (p0, p1) = (Point::new(0.0, 1.0, 0.0), Point::new(-0.05, 1.0, 0.0));
+        let r0 = cache.record(p0, up, offset, &mut rng);
+        let e0 = r0.irradiance.max_component();
+        let e1 = cache
+            .record(p1, up, offset, &mut rng)
+            .irradiance
+            .max_component();
+        let moved = r0.extrapolate(p1, up).max_component();
+        assert!(e1 > e0);
+        assert!((moved - e1).abs() < 0.3 * (e1 - e0), "{}", moved);
+
+        let tilted = Unit::try_from(Vector::new(0.1, 1.0, 0.0)).unwrap();
+        let et = cache.record(p0, tilted, offset, &mut rng).irradiance;
+        let et = et.max_component();
+        let turned = r0.extrapolate(p0, tilted).max_component();
+        assert!(et < e0);
+        assert!((turned - et).abs() < 0.3 * (e0 - et), "{}", turned);
+    }
+}
@@ -0,0 +1,412 @@
+use std::{error::Error, fmt, str::FromStr};
+
+/// One step of a light path, as seen by a [`LightPathExpr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathEvent {
+    /// The camera, where every path starts. Written `C`.
+    Camera,
+    /// Scattering off a diffuse surface. Written `D`.
+    Diffuse,
+    /// Scattering off a glossy surface. Written `G`.
+    Glossy,
+    /// Scattering off a specular surface, such as a mirror or glass.
+    /// Written `S`.
+    Specular,
+    /// Scattering in a volume, such as [`Fog`]. Written `V`.
+    ///
+    /// [`Fog`]: super::Fog
+    Volume,
+    /// A light source, where every path ends: a light reached by a shadow
+    /// ray, or the environment. Written `L`.
+    Light,
+}
+
+impl PathEvent {
+    const ALL: [Self; 6] = [
+        Self::Camera,
+        Self::Diffuse,
+        Self::Glossy,
+        Self::Specular,
+        Self::Volume,
+        Self::Light,
+    ];
+
+    fn from_symbol(c: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.symbol() == c)
+    }
+
+    /// The letter standing for the event in expressions.
+    pub const fn symbol(self) -> char {
+        match self {
+            Self::Camera => 'C',
+            Self::Diffuse => 'D',
+            Self::Glossy => 'G',
+            Self::Specular => 'S',
+            Self::Volume => 'V',
+            Self::Light => 'L',
+        }
+    }
+
+    #[inline]
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// An error in a light path expression, at the given character offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightPathError {
+    pub pos: usize,
+    pub msg: &'static str,
+}
+
+impl fmt::Display for LightPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.pos + 1, self.msg)
+    }
+}
+
+impl Error for LightPathError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+    One,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Item {
+    // Bitmask of accepted events
+    events: u8,
+    repeat: Repeat,
+}
+
+impl Item {
+    #[inline]
+    fn accepts(self, event: PathEvent) -> bool {
+        self.events & event.bit() != 0
+    }
+}
+
+/// A light path expression, picking out paths by the sequence of events
+/// along them, from the camera to a light.
+///
+/// Expressions are regular expressions over the event letters of
+/// [`PathEvent`], written from the camera end, so every useful one starts
+/// with `C` and ends with `L`. A `.` matches any event, `[DG]` any of those
+/// listed and `[^S]` any but those listed, and `?`, `*` and `+` repeat the
+/// event before them as usual. Whitespace is ignored.
+///
+/// The usual render layers are:
+///
+/// | Expression  | Layer                                                  |
+/// |-------------|--------------------------------------------------------|
+/// | `CL`        | Emission: lights and the environment seen directly     |
+/// | `CDL`       | Direct diffuse                                         |
+/// | `CD.+L`     | Indirect diffuse                                       |
+/// | `C[GS].*L`  | Specular: everything seen in glossy or mirror surfaces |
+/// | `CV.*L`     | Volumetric: light scattered towards the camera by fog  |
+///
+/// Between them they cover every path, each exactly once, so they add up
+/// to the full image.
+///
+/// ```
+/// use gremlin::integrator::{LightPathExpr, PathEvent::*};
+///
+/// let indirect: LightPathExpr = "CD.+L".parse().unwrap();
+/// assert!(indirect.matches(&[Camera, Diffuse, Specular, Light]));
+/// assert!(!indirect.matches(&[Camera, Diffuse, Light]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightPathExpr(Vec<Item>);
+
+impl LightPathExpr {
+    /// Parse an expression.
+    pub fn parse(src: &str) -> Result<Self, LightPathError> {
+        let err = |pos, msg| LightPathError { pos, msg };
+        let mut items = Vec::new();
+        let mut chars = src
+            .char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .peekable();
+        while let Some((pos, c)) = chars.next() {
+            let events = match c {
+                '.' => u8::MAX,
+                '[' => {
+                    let mut events = 0;
+                    let mut negate = false;
+                    loop {
+                        match chars.next() {
+                            Some((_, ']')) if events != 0 => break,
+                            Some((_, '^')) if events == 0 && !negate => negate = true,
+                            Some((pos, c)) => match PathEvent::from_symbol(c) {
+                                Some(e) => events |= e.bit(),
+                                None => return Err(err(pos, "expected an event in set")),
+                            },
+                            None => return Err(err(src.len(), "unclosed '['")),
+                        }
+                    }
+                    match negate {
+                        true => !events,
+                        false => events,
+                    }
+                }
+                '?' | '*' | '+' => return Err(err(pos, "nothing to repeat")),
+                c => match PathEvent::from_symbol(c) {
+                    Some(e) => e.bit(),
+                    None => return Err(err(pos, "expected an event")),
+                },
+            };
+            let repeat = match chars.peek().map(|&(_, c)| c) {
+                Some('?') => Repeat::Optional,
+                Some('*') => Repeat::ZeroOrMore,
+                Some('+') => Repeat::OneOrMore,
+                _ => Repeat::One,
+            };
+            if repeat != Repeat::One {
+                chars.next();
+            }
+            items.push(Item { events, repeat });
+        }
+        Ok(Self(items))
+    }
+
+    /// Whether a complete path, from [`Camera`] to [`Light`], matches.
+    ///
+    /// [`Camera`]: PathEvent::Camera
+    /// [`Light`]: PathEvent::Light
+    pub fn matches(&self, path: &[PathEvent]) -> bool {
+        matches(&self.0, path)
+    }
+}
+
+// Backtracking match of the remaining items against the remaining events
+fn matches(items: &[Item], path: &[PathEvent]) -> bool {
+    let Some((&item, rest)) = items.split_first() else {
+        return path.is_empty();
+    };
+    let (min, max) = match item.repeat {
+        Repeat::One => (1, 1),
+        Repeat::Optional => (0, 1),
+        Repeat::ZeroOrMore => (0, usize::MAX),
+        Repeat::OneOrMore => (1, usize::MAX),
+    };
+    let run = path
+        .iter()
+        .take(max)
+        .take_while(|&&e| item.accepts(e))
+        .count();
+    (min..=run).rev().any(|n| matches(rest, &path[n..]))
+}
+
+impl FromStr for LightPathExpr {
+    type Err = LightPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// An error in a [`RenderLayers`] config, on the given line (from `1`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderLayersError {
+    /// The line isn't of the form `name = expr`.
+    Syntax { line: usize },
+    /// An earlier line already has a layer with this name.
+    Duplicate { line: usize },
+    /// The layer's expression is malformed.
+    Expr { line: usize, error: LightPathError },
+}
+
+impl fmt::Display for RenderLayersError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { line } => write!(f, "line {}: expected 'name = expression'", line),
+            Self::Duplicate { line } => write!(f, "line {}: duplicate layer name", line),
+            Self::Expr { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl Error for RenderLayersError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Expr { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A set of named render layers, each the light along the paths a
+/// [`LightPathExpr`] matches.
+///
+/// Rendered in one pass, each into its own film, by a [`PathTracer`]'s
+/// [`layers`] integrator and [`Renderer::render_layers`]. Layers are kept in
+/// the order they were added, which is the order of the films.
+///
+/// A config has one layer per line, as `name = expr`. Blank lines and `#`
+/// comments are ignored.
+///
+/// ```
+/// use gremlin::integrator::RenderLayers;
+///
+/// let layers: RenderLayers = "
+///     emission = CL
+///     direct   = CDL   # lit straight from a light
+///     indirect = CD.+L
+/// "
+/// .parse()
+/// .unwrap();
+/// assert_eq!(vec!["emission", "direct", "indirect"], layers.names().collect::<Vec<_>>());
+/// ```
+///
+/// [`PathTracer`]: super::PathTracer
+/// [`layers`]: super::PathTracer::layers
+/// [`Renderer::render_layers`]: crate::renderer::Renderer::render_layers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderLayers(Vec<(String, LightPathExpr)>);
+
+impl RenderLayers {
+    /// An empty set of layers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a layer, replacing any with the same name.
+    pub fn add(mut self, name: impl Into<String>, expr: LightPathExpr) -> Self {
+        let name = name.into();
+        match self.0.iter_mut().find(|(n, _)| *n == name) {
+            Some(layer) => layer.1 = expr,
+            None => self.0.push((name, expr)),
+        }
+        self
+    }
+
+    /// Parse a layer config.
+    pub fn parse(src: &str) -> Result<Self, RenderLayersError> {
+        let mut layers = Self::new();
+        for (idx, line) in src.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((name, expr)) = line.split_once('=') else {
+                return Err(RenderLayersError::Syntax { line: line_no });
+            };
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(RenderLayersError::Syntax { line: line_no });
+            }
+            if layers.names().any(|n| n == name) {
+                return Err(RenderLayersError::Duplicate { line: line_no });
+            }
+            let expr = LightPathExpr::parse(expr).map_err(|error| RenderLayersError::Expr {
+                line: line_no,
+                error,
+            })?;
+            layers = layers.add(name, expr);
+        }
+        Ok(layers)
+    }
+
+    /// The number of layers.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no layers.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The layers' names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.0.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The layers' expressions, in order.
+    pub fn exprs(&self) -> impl Iterator<Item = &LightPathExpr> + '_ {
+        self.0.iter().map(|(_, expr)| expr)
+    }
+}
+
+impl FromStr for RenderLayers {
+    type Err = RenderLayersError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PathEvent::*, *};
+
+    fn path(s: &str) -> Vec<PathEvent> {
+        s.chars()
+            .map(|c| PathEvent::from_symbol(c).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn usual_layers_partition_paths() {
+        let layers = ["CL", "CDL", "CD.+L", "C[GS].*L", "CV.*L"]
+            .map(|s| s.parse::<LightPathExpr>().unwrap());
+        for p in [
+            "CL", "CDL", "CGL", "CSL", "CDDL", "CDSGL", "CSDL", "CGGDL", "CVL", "CSVL",
+        ] {
+            let hits = layers.iter().filter(|l| l.matches(&path(p))).count();
+            assert_eq!(1, hits, "{}", p);
+        }
+
+        let not_glass: LightPathExpr = "C [^S]* L".parse().unwrap();
+        assert!(not_glass.matches(&path("CDGL")));
+        assert!(!not_glass.matches(&path("CDSL")));
+        let optional: LightPathExpr = "CS?DL".parse().unwrap();
+        assert!(optional.matches(&path("CDL")) && optional.matches(&path("CSDL")));
+        assert!(!optional.matches(&[Camera, Specular, Specular, Diffuse, Light]));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |s: &str| LightPathExpr::parse(s).unwrap_err();
+        assert_eq!(
+            LightPathError {
+                pos: 1,
+                msg: "expected an event"
+            },
+            err("CX")
+        );
+        assert_eq!(2, err("C*+").pos);
+        assert_eq!("unclosed '['", err("C[DG").msg);
+        assert_eq!("expected an event in set", err("C[]L").msg);
+    }
+
+    #[test]
+    fn layer_config() {
+        let layers =
+            RenderLayers::parse("# passes\ndirect = CDL\n\nrest=C[^D].*L # others\n").unwrap();
+        assert_eq!(2, layers.len());
+        let exprs: Vec<_> = layers.exprs().collect();
+        assert!(exprs[0].matches(&path("CDL")));
+        assert!(exprs[1].matches(&path("CSDL")));
+
+        let err = |s: &str| RenderLayers::parse(s).unwrap_err();
+        assert_eq!(RenderLayersError::Syntax { line: 2 }, err("a = CL\nCDL"));
+        assert_eq!(RenderLayersError::Syntax { line: 1 }, err("a b = CL"));
+        assert_eq!(
+            RenderLayersError::Duplicate { line: 3 },
+            err("a = CL\n\na = CDL")
+        );
+        assert!(matches!(
+            err("a = CL\nb = CX"),
+            RenderLayersError::Expr {
+                line: 2,
+                error: LightPathError { pos: 2, .. }
+            }
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+use super::{sppm::is_specular_only, Roulette};
+use crate::{
+    camera::Camera,
+    color::RGB,
+    film::SplatFilm,
+    geo::{Ray, Unit},
+    light::SpotLight,
+    material::{RayType, BSDF},
+    math::hash_keys,
+    scene::Scene,
+    Float,
+};
+use rand::prelude::*;
+use rayon::prelude::*;
+
+// Distinguishes light paths from other streams with the same indices
+const LIGHT_STREAM: u64 = 0x4c54_5243;
+
+/// Light tracing, also called particle tracing.
+///
+/// Traces paths from the lights instead of the camera. At every surface a
+/// path reaches it connects to the camera with a shadow ray, and splats
+/// what the camera would see along it onto a [`SplatFilm`], wherever on the
+/// film that lands.
+///
+/// It's the mirror image of a path tracer: with the same scene the two
+/// converge to the same image, which makes it a check on both halves of
+/// the renderer. It also renders caustics on diffuse surfaces, such as
+/// light focused by glass onto a table, directly and without the fireflies
+/// a path tracer gives them. What it can't do is see specular surfaces, or
+/// anything through them, as no path can connect to the camera from one.
+///
+/// Only [`SpotLight`]s emit paths. Like [`Sppm`], this isn't an
+/// [`Integrator`], since it doesn't work pixel by pixel.
+///
+/// ```no_run
+/// use gremlin::{
+///     camera::ThinLens, color::RGB, film::SplatFilm, geo::Unit,
+///     integrator::LightTracer, light::SpotLight, scene::Scene, Float,
+/// };
+///
+/// let scene = Scene::default();
+/// let light = SpotLight::new([0.0, 4.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0));
+/// let film = SplatFilm::new(800, 600);
+/// let cam = ThinLens::builder(film.dimensions()).build();
+///
+/// let paths = 1_000_000;
+/// LightTracer::new(&scene, vec![light]).render(&film, &cam, paths);
+/// let img = film.to_buffer(1.0 / paths as Float);
+/// ```
+///
+/// [`Integrator`]: super::Integrator
+/// [`Sppm`]: super::Sppm
+pub struct LightTracer<'a> {
+    scene: &'a Scene,
+    lights: Vec<SpotLight>,
+    max_depth: usize,
+    roulette: Roulette,
+    seed: u64,
+}
+
+impl<'a> LightTracer<'a> {
+    /// Light trace `scene`, lit by `lights`.
+    pub fn new(scene: &'a Scene, lights: Vec<SpotLight>) -> Self {
+        Self {
+            scene,
+            lights,
+            max_depth: 8,
+            roulette: Roulette::default(),
+            seed: 0,
+        }
+    }
+
+    /// Set the most bounces a path takes. Defaults to `8`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set the Russian roulette for paths.
+    pub fn roulette(mut self, roulette: Roulette) -> Self {
+        self.roulette = roulette;
+        self
+    }
+
+    /// Set the seed. Render more paths into the same film with a different
+    /// seed for each call.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Trace `paths` paths, splatting them onto `film`. The film should be
+    /// the camera's size; scale it by one over the total number of paths
+    /// traced into it to get the image.
+    pub fn render(&self, film: &SplatFilm, cam: &impl Camera, paths: usize) {
+        let powers: Vec<Float> = self
+            .lights
+            .iter()
+            .map(|l| l.power().max_component())
+            .collect();
+        let total: Float = powers.iter().sum();
+        if total <= 0.0 {
+            return;
+        }
+
+        (0..paths).into_par_iter().for_each(|i| {
+            let keys = [self.seed, i as u64, LIGHT_STREAM];
+            let mut rng = StdRng::seed_from_u64(hash_keys(&keys));
+
+            // Pick a light in proportion to its power
+            let mut pick = rng.gen::<Float>() * total;
+            let idx = powers
+                .iter()
+                .position(|&p| {
+                    pick -= p;
+                    pick < 0.0
+                })
+                .unwrap_or(powers.len() - 1);
+            let pdf = powers[idx] / total;
+            if let Some(e) = self.lights[idx].sample_emission(rng.gen()) {
+                self.trace(film, cam, e.ray, e.weight / pdf, &mut rng);
+            }
+        });
+    }
+
+    // Follow a path from a light, connecting each vertex to the camera
+    fn trace(
+        &self,
+        film: &SplatFilm,
+        cam: &impl Camera,
+        mut ray: Ray,
+        emitted: RGB,
+        rng: &mut impl Rng,
+    ) {
+        let mut throughput = RGB::splat(1.0);
+        for depth in 0..self.max_depth {
+            let Some(hit) = self.scene.intersect(&ray, 0.0, Float::INFINITY) else {
+                return;
+            };
+            let Ok(wo) = Unit::try_from(-ray.direction) else {
+                return;
+            };
+            let isect = &hit.isect;
+
+            // The camera sees this surface directly, through its camera
+            // material
+            let seen = hit.material.for_ray(RayType::Camera);
+            if !is_specular_only(seen) {
+                if let Some(s) = cam.sample_importance(isect.point, rng) {
+                    let f = seen.f(wo, s.wi, isect);
+                    let shadow = hit.spawn(s.wi.into());
+                    if !f.is_black() && !self.scene.occluded(&shadow, 0.0, s.dist * (1.0 - 1e-4)) {
+                        let weight = s.importance * s.wi.dot(isect.norm).abs() / s.pdf;
+                        film.splat(s.raster, emitted * throughput * f * weight);
+                    }
+                }
+            }
+
+            let material = hit.material.for_ray(RayType::Indirect);
+            let Some(s) = material.sample_f(wo, isect, rng) else {
+                return;
+            };
+            throughput *= s.f * (s.wi.dot(isect.norm).abs() / s.pdf);
+            if throughput.is_black() || !self.roulette.survive(&mut throughput, depth, rng) {
+                return;
+            }
+            ray = hit.spawn(s.wi.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::ThinLens,
+        geo::Degrees,
+        material::{Dielectric, Lambertian},
+        shape::Triangle,
+    };
+
+    const PI: Float = std::f64::consts::PI as Float;
+
+    // A triangle at height `y` covering the spot light's cone, facing up or
+    // down. Kept off `y = 0`, where spawned rays would barely move.
+    fn plane(y: Float, up: bool) -> Triangle {
+        let (a, b) = ([0.0, y, 10.0], [10.0, y, -10.0]);
+        match up {
+            true => Triangle::new([-10.0, y, -10.0], a, b),
+            false => Triangle::new([-10.0, y, -10.0], b, a),
+        }
+    }
+
+    fn render(scene: &Scene) -> Float {
+        let light = SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0))
+            .cone(Degrees(8.0))
+            .falloff(Degrees(0.0));
+        let film = SplatFilm::new(4, 4);
+        let cam = ThinLens::builder(film.dimensions())
+            .move_to([0.0, 1.5, -0.5])
+            .look_at([0.0, 1.0, 0.0])
+            .fov(Degrees(20.0))
+            .build();
+        let paths = 100_000;
+        LightTracer::new(scene, vec![light])
+            .seed(3)
+            .render(&film, &cam, paths);
+        let img = film.to_buffer(1.0 / paths as Float);
+        img.iter().map(|c| c.max_component()).sum::<Float>() / img.len() as Float
+    }
+
+    #[test]
+    fn direct_and_caustic() {
+        // Straight below the light, a diffuse floor has the radiance a path
+        // tracer would find, `albedo / π * I / d^2`
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(1.0, true), Lambertian::new(RGB::splat(0.5)));
+        let expected = 0.5 / PI * 10.0 / 16.0;
+        let direct = render(&scene);
+        assert!((direct - expected).abs() < 0.03 * expected, "{}", direct);
+
+        // The caustic through a glass slab, less what the slab's faces
+        // reflect
+        scene.add_primitive(plane(3.1, true), Dielectric::new(1.5));
+        scene.add_primitive(plane(3.0, false), Dielectric::new(1.5));
+        let caustic = render(&scene);
+        let expected = expected * 0.96 * 0.96;
+        assert!((caustic - expected).abs() < 0.03 * expected, "{}", caustic);
+    }
+}
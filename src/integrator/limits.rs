@@ -0,0 +1,207 @@
+use super::ShadowRays;
+use crate::{material::BSDFFlags, metrics::Counter};
+
+/// Number of paths cut short because they exhausted their [`WorkLimits`].
+///
+/// A steadily climbing value usually means the scene has a pathological
+/// configuration, such as a ray trapped bouncing inside a glass object.
+pub static TRUNCATED_PATHS: Counter = Counter::new();
+
+/// Safety caps on the work done tracing a single camera path.
+///
+/// These exist to keep pathological scenes from hanging a render thread, not
+/// as a quality control. Paths that exceed a limit are terminated (returning
+/// whatever radiance was gathered so far) and counted in [`TRUNCATED_PATHS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkLimits {
+    /// Maximum number of scattering events along a path.
+    pub max_bounces: usize,
+    /// Maximum number of scene intersection queries along a path, including
+    /// shadow rays and their continuation through transparent surfaces (see
+    /// [`ShadowRays`]).
+    pub max_queries: usize,
+}
+
+impl Default for WorkLimits {
+    fn default() -> Self {
+        Self {
+            max_bounces: 50,
+            max_queries: 1024,
+        }
+    }
+}
+
+impl WorkLimits {
+    /// Start tracking work for a new path.
+    #[inline]
+    pub fn budget(&self) -> PathBudget {
+        PathBudget {
+            limits: *self,
+            bounces: 0,
+            queries: 0,
+            exhausted: false,
+        }
+    }
+}
+
+/// Tracks work spent on a single path against its [`WorkLimits`].
+#[derive(Debug, Clone)]
+pub struct PathBudget {
+    limits: WorkLimits,
+    bounces: usize,
+    queries: usize,
+    exhausted: bool,
+}
+
+impl PathBudget {
+    /// Record a scattering event. Returns `false` if the path should stop.
+    #[inline]
+    pub fn bounce(&mut self) -> bool {
+        self.bounces += 1;
+        self.check(self.bounces <= self.limits.max_bounces)
+    }
+
+    /// Record an intersection query. Returns `false` if the path should stop.
+    #[inline]
+    pub fn query(&mut self) -> bool {
+        self.queries += 1;
+        self.check(self.queries <= self.limits.max_queries)
+    }
+
+    /// Returns `true` if any limit has been hit.
+    #[inline]
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    fn check(&mut self, ok: bool) -> bool {
+        if !ok && !self.exhausted {
+            self.exhausted = true;
+            TRUNCATED_PATHS.inc();
+        }
+        ok
+    }
+}
+
+/// Maximum path depth for each kind of scattering event.
+///
+/// Unlike [`WorkLimits`], these are a quality/cost trade-off, as in production
+/// renderers: a scene lit mostly by indirect diffuse light needs plenty of
+/// diffuse bounces, while a glass-heavy one needs transmission bounces, and
+/// there's no point paying for deep paths of a kind that contributes little.
+/// Paths stopped by these limits are not counted as truncated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BounceLimits {
+    /// Reflections off diffuse lobes.
+    pub diffuse: usize,
+    /// Reflections off glossy or specular lobes.
+    pub glossy: usize,
+    /// Refractions through surfaces.
+    pub transmission: usize,
+    /// Transparent surfaces (such as alpha cutouts) a shadow ray may pass
+    /// through. See [`shadow_rays`].
+    ///
+    /// [`shadow_rays`]: Self::shadow_rays
+    pub transparency: usize,
+}
+
+impl Default for BounceLimits {
+    fn default() -> Self {
+        Self {
+            diffuse: 4,
+            glossy: 8,
+            transmission: 12,
+            transparency: 8,
+        }
+    }
+}
+
+impl BounceLimits {
+    /// Start counting bounces for a new path.
+    #[inline]
+    pub fn start(&self) -> BounceCounts {
+        BounceCounts {
+            limits: *self,
+            diffuse: 0,
+            glossy: 0,
+            transmission: 0,
+        }
+    }
+
+    /// Shadow ray settings honoring the [`transparency`] limit.
+    ///
+    /// [`transparency`]: Self::transparency
+    pub fn shadow_rays(&self) -> ShadowRays {
+        ShadowRays {
+            max_depth: self.transparency,
+            ..ShadowRays::default()
+        }
+    }
+}
+
+/// Counts the bounces of each kind taken by a single path, against its
+/// [`BounceLimits`].
+#[derive(Debug, Clone)]
+pub struct BounceCounts {
+    limits: BounceLimits,
+    diffuse: usize,
+    glossy: usize,
+    transmission: usize,
+}
+
+impl BounceCounts {
+    /// Record a scattering event off a lobe with the given flags, as returned
+    /// in a [`BSDFSample`]. Returns `false` if the path should stop.
+    ///
+    /// Transmission takes precedence, so rough refraction counts against the
+    /// transmission limit rather than the glossy one.
+    ///
+    /// [`BSDFSample`]: crate::material::BSDFSample
+    #[inline]
+    pub fn scatter(&mut self, flags: BSDFFlags) -> bool {
+        let (count, limit) = if flags.contains(BSDFFlags::TRANSMISSION) {
+            (&mut self.transmission, self.limits.transmission)
+        } else if flags.contains(BSDFFlags::DIFFUSE) {
+            (&mut self.diffuse, self.limits.diffuse)
+        } else {
+            (&mut self.glossy, self.limits.glossy)
+        };
+        *count += 1;
+        *count <= limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounce_limit() {
+        let limits = WorkLimits {
+            max_bounces: 2,
+            ..Default::default()
+        };
+        let mut budget = limits.budget();
+        assert!(budget.bounce());
+        assert!(budget.bounce());
+        assert!(!budget.is_exhausted());
+        assert!(!budget.bounce());
+        assert!(budget.is_exhausted());
+    }
+
+    #[test]
+    fn bounce_kinds() {
+        let limits = BounceLimits {
+            diffuse: 1,
+            glossy: 0,
+            ..Default::default()
+        };
+        let mut counts = limits.start();
+        assert!(counts.scatter(BSDFFlags::REFLECTION | BSDFFlags::DIFFUSE));
+        assert!(counts.scatter(BSDFFlags::TRANSMISSION | BSDFFlags::SPECULAR));
+        assert!(!counts.scatter(BSDFFlags::REFLECTION | BSDFFlags::DIFFUSE));
+        assert!(!limits
+            .start()
+            .scatter(BSDFFlags::REFLECTION | BSDFFlags::SPECULAR));
+    }
+}
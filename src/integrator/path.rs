@@ -0,0 +1,1104 @@
+use super::{
+    sppm::is_specular_only, BounceLimits, Fog, Integrator, LightPathExpr, PathBudget, PathEvent,
+    RadianceClamp, RenderLayers, Roulette, ShadowRays, WorkLimits,
+};
+use crate::{
+    color::RGB,
+    geo::{Point, Ray, Unit, Vector},
+    light::{Environment, Portals, SpotLight},
+    material::{BSDFFlags, Material, RayType, BSDF},
+    sampler::{SampleDimension, Sampler},
+    scene::{Scene, SurfaceInteraction},
+    spectrum::{Sampled, SingleWavelength, SpectrumKind},
+    Float,
+};
+use rand::prelude::*;
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// A unidirectional path tracer over a [`Scene`].
+///
+/// Paths start at the camera and bounce by sampling each surface's
+/// [`BSDF`]. At every surface that isn't purely specular they connect to
+/// each [`SpotLight`] with a shadow ray, which passes through glass and
+/// alpha cutouts as [`Scene::transmittance`] allows (see [`shadow_rays`]);
+/// the environment is found by paths that escape the scene. If the
+/// environment has [`Portals`], such as the windows of an interior, those
+/// surfaces also sample a direction through them, and the two ways of
+/// finding the environment are weighted by multiple importance sampling.
+/// These shadow rays are blocked by glass, whose light is found by paths
+/// refracting through it. With [`fog`], every stretch of a path through it
+/// also gathers the light the fog scatters towards it.
+///
+/// Paths stop at the [`max_depth`] or their kind's [`bounce_limits`],
+/// whichever comes first, and are cut short by the [`work_limits`] in
+/// pathological scenes. A [`clamp`] caps each contribution to suppress
+/// fireflies. Traced with a [`Sampler`], through
+/// [`radiance_sampled`][Integrator::radiance_sampled], each bounce's BSDF,
+/// light and roulette decisions come from that bounce's dimensions.
+///
+/// # Render layers
+///
+/// Given a [`layer`], only light arriving along paths the
+/// [`LightPathExpr`] matches is counted, splitting the image into layers
+/// such as direct and indirect diffuse that can be adjusted separately in
+/// compositing. To render several layers at once, the [`layers`] integrator
+/// sorts each path's light into every [`RenderLayers`] entry it matches, and
+/// [`Renderer::render_layers`] gathers them into one film per layer. Every
+/// layer then sees the same paths, so layers that partition the paths add up
+/// to the full image exactly.
+///
+/// A surface reached by a shadow ray counts as [`Diffuse`] if it has any
+/// diffuse lobe, and [`Glossy`] otherwise; a bounce counts as the lobe it
+/// sampled.
+///
+/// # Spectral rendering
+///
+/// The [`spectral`] integrator traces each sample at a single wavelength,
+/// chosen at random per camera ray, into a [`SpectralFilm`]. Colors along
+/// the path are converted to their spectra's values at that wavelength, and
+/// [`DispersiveDielectric`]s refract by their index there, so glass splits
+/// white light into its colors.
+///
+/// ```no_run
+/// use gremlin::{
+///     camera::ThinLens, color::RGB, film::RGBFilm, geo::Unit,
+///     integrator::{PathTracer, RenderLayers}, light::SpotLight, renderer::Renderer,
+///     scene::Scene,
+/// };
+///
+/// let scene = Scene::default();
+/// let light = SpotLight::new([0.0, 4.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0));
+/// let layers: RenderLayers = "direct = CDL\nindirect = CD.+L".parse().unwrap();
+/// let mut films: Vec<_> = layers.names().map(|_| RGBFilm::new(800, 600)).collect();
+/// let cam = ThinLens::builder(films[0].dimensions()).build();
+///
+/// let tracer = PathTracer::new(&scene, vec![light]);
+/// Renderer::new(64).render_layers(&mut films, &cam, &tracer.layers(&layers));
+/// for (name, film) in layers.names().zip(&films) {
+///     film.to_snapshot().save_image(format!("{}.png", name)).unwrap();
+/// }
+/// ```
+///
+/// [`shadow_rays`]: Self::shadow_rays
+/// [`max_depth`]: Self::max_depth
+/// [`bounce_limits`]: Self::bounce_limits
+/// [`work_limits`]: Self::work_limits
+/// [`clamp`]: Self::clamp
+/// [`spectral`]: Self::spectral
+/// [`SpectralFilm`]: crate::film::SpectralFilm
+/// [`DispersiveDielectric`]: crate::material::DispersiveDielectric
+/// [`fog`]: Self::fog
+/// [`layer`]: Self::layer
+/// [`layers`]: Self::layers
+/// [`Renderer::render_layers`]: crate::renderer::Renderer::render_layers
+/// [`Diffuse`]: PathEvent::Diffuse
+/// [`Glossy`]: PathEvent::Glossy
+pub struct PathTracer<'a> {
+    scene: &'a Scene,
+    lights: Vec<SpotLight>,
+    background: Environment,
+    max_depth: usize,
+    bounces: BounceLimits,
+    limits: WorkLimits,
+    roulette: Roulette,
+    clamp: RadianceClamp,
+    two_sided: bool,
+    shadow: ShadowRays,
+    fog: Option<Fog>,
+    layer: Option<LightPathExpr>,
+}
+
+impl<'a> PathTracer<'a> {
+    /// Path trace `scene`, lit by `lights`.
+    pub fn new(scene: &'a Scene, lights: Vec<SpotLight>) -> Self {
+        Self {
+            scene,
+            lights,
+            background: Environment::default(),
+            max_depth: 8,
+            bounces: BounceLimits::default(),
+            limits: WorkLimits::default(),
+            roulette: Roulette::default(),
+            clamp: RadianceClamp::default(),
+            two_sided: false,
+            shadow: ShadowRays::default(),
+            fog: None,
+            layer: None,
+        }
+    }
+
+    /// Set the light arriving from outside the scene.
+    pub fn background(mut self, background: impl Into<Environment>) -> Self {
+        self.background = background.into();
+        self
+    }
+
+    /// Set the most bounces a path takes. Defaults to `8`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set the most bounces of each kind a path takes, and how many
+    /// transparent surfaces its shadow rays pass through.
+    pub fn bounce_limits(mut self, bounces: BounceLimits) -> Self {
+        self.bounces = bounces;
+        self.shadow.max_depth = bounces.transparency;
+        self
+    }
+
+    /// Set the caps on the work done tracing a single path.
+    pub fn work_limits(mut self, limits: WorkLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the Russian roulette for paths.
+    pub fn roulette(mut self, roulette: Roulette) -> Self {
+        self.roulette = roulette;
+        self
+    }
+
+    /// Cap the light each path contribution can carry. Light seen directly
+    /// and surfaces lit straight from a light or the environment count as
+    /// direct; everything else as indirect.
+    pub fn clamp(mut self, clamp: RadianceClamp) -> Self {
+        self.clamp = clamp;
+        self
+    }
+
+    /// Treat every surface as two-sided, so hits always scatter back to the
+    /// side the ray came from. See [`TwoSided`] to choose surface by surface.
+    ///
+    /// [`TwoSided`]: crate::shape::TwoSided
+    pub fn two_sided(mut self, two_sided: bool) -> Self {
+        self.two_sided = two_sided;
+        self
+    }
+
+    /// Set how shadow rays pass through transparent surfaces on their way
+    /// to the lights. This replaces the [`bounce_limits`]' transparency.
+    ///
+    /// [`bounce_limits`]: Self::bounce_limits
+    pub fn shadow_rays(mut self, shadow: ShadowRays) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    /// Fill part of the scene with fog.
+    pub fn fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    /// Only count light along paths matching `expr`. See [render layers].
+    ///
+    /// [render layers]: Self#render-layers
+    pub fn layer(mut self, expr: LightPathExpr) -> Self {
+        self.layer = Some(expr);
+        self
+    }
+
+    /// The same path tracer, measuring each sample's light in each of
+    /// `layers`, in order. See [render layers].
+    ///
+    /// [render layers]: Self#render-layers
+    pub fn layers<'l>(&self, layers: &'l RenderLayers) -> Layers<'_, 'a, 'l> {
+        Layers(self, layers)
+    }
+
+    /// The same path tracer, measuring each sample's light by bounce
+    /// rather than in total.
+    pub fn by_bounce(&self) -> ByBounce<'_, 'a> {
+        ByBounce(self)
+    }
+
+    /// The same path tracer, tracing each sample at a single wavelength. See
+    /// [spectral rendering].
+    ///
+    /// [spectral rendering]: Self#spectral-rendering
+    pub fn spectral(&self) -> Spectral<'_, 'a> {
+        Spectral(self)
+    }
+
+    // Trace a path in `band`, passing each contribution the layer takes to
+    // `gather` along with the complete path it arrived by. With a `sampler`,
+    // each bounce's decisions are drawn from its dimensions for that bounce
+    fn trace(
+        &self,
+        ray: &Ray,
+        band: Band,
+        mut sampler: Option<&mut dyn Sampler>,
+        rng: &mut impl Rng,
+        mut gather: impl FnMut(&[PathEvent], RGB),
+    ) {
+        let mut path = Vec::with_capacity(self.max_depth + 3);
+        path.push(PathEvent::Camera);
+        let mut gather = |path: &mut Vec<PathEvent>, li: RGB| {
+            // Light reaching the first thing the camera sees is direct
+            let bounces = path.len().saturating_sub(2);
+            let li = self.clamp.apply(li, bounces);
+            path.push(PathEvent::Light);
+            if self.layer.as_ref().is_none_or(|l| l.matches(path)) {
+                gather(path, li);
+            }
+            path.pop();
+        };
+        let mut budget = self.limits.budget();
+        let mut counts = self.bounces.start();
+        let mut throughput = RGB::splat(1.0);
+        let mut ray = Ray::new(ray.origin, ray.direction);
+        let portals = self.background.portals();
+        // Where the last bounce was sampled from, and its density, unless it
+        // was specular
+        let mut bounced_from: Option<(Point, Float)> = None;
+
+        for depth in 0..=self.max_depth {
+            let bounce = depth as u32;
+            if !budget.query() {
+                break;
+            }
+            let hit = self.scene.intersect(&ray, 0.0, Float::INFINITY);
+            if let Some(fog) = &self.fog {
+                let t_max = hit.as_ref().map_or(Float::INFINITY, |h| h.isect.t);
+                throughput *= self.in_scatter(
+                    fog,
+                    &ray,
+                    t_max,
+                    throughput,
+                    band,
+                    &mut path,
+                    &mut budget,
+                    rng,
+                    &mut gather,
+                );
+            }
+            let Some(mut hit) = hit else {
+                let mut li = throughput * band.emission(self.background.radiance(ray.direction));
+                if let (Some(portals), Some((point, pdf)), Ok(wi)) =
+                    (portals, bounced_from, Unit::try_from(ray.direction))
+                {
+                    li *= power_heuristic(pdf, portals.pdf(point, wi));
+                }
+                gather(&mut path, li);
+                break;
+            };
+            if self.two_sided {
+                hit.isect = hit.isect.facing(ray.direction);
+            }
+            let Ok(wo) = Unit::try_from(-ray.direction) else {
+                break;
+            };
+            let dispersed;
+            let mut material = hit.material.for_ray(RayType::from_depth(depth));
+            if let (Band::Wavelength(w), Material::Dispersive(glass)) = (band, material) {
+                dispersed = Material::from(glass.at(w));
+                material = &dispersed;
+            }
+            let isect = &hit.isect;
+
+            // Shadow rays to the lights, which paths can never hit
+            if !is_specular_only(material) {
+                path.push(match material.lobes().contains(BSDFFlags::DIFFUSE) {
+                    true => PathEvent::Diffuse,
+                    false => PathEvent::Glossy,
+                });
+                for light in &self.lights {
+                    let u = match &mut sampler {
+                        Some(sampler) => sampler.get_2d(SampleDimension::Light(bounce)),
+                        None => rng.gen(),
+                    };
+                    let Some(s) = light.sample(isect.point, u) else {
+                        continue;
+                    };
+                    let f = band.scale(material.f(wo, s.wi, isect));
+                    if f.is_black() {
+                        continue;
+                    }
+                    let shadow = hit.spawn(s.wi.into());
+                    let dist = (light.position() - shadow.origin).len();
+                    let tr = self.scene.transmittance(
+                        &shadow,
+                        0.0,
+                        dist * (1.0 - 1e-4),
+                        &self.shadow,
+                        &mut budget,
+                        rng,
+                    );
+                    if !tr.is_black() {
+                        let li = throughput
+                            * band.scale(tr)
+                            * f
+                            * band.emission(s.li)
+                            * (s.wi.dot(isect.norm).abs() / s.pdf);
+                        gather(&mut path, li);
+                    }
+                }
+                if let Some(portals) = portals {
+                    let u = match &mut sampler {
+                        Some(sampler) => {
+                            let [u1, u2] = sampler.get_2d(SampleDimension::Light(bounce));
+                            [sampler.get_1d(SampleDimension::LightSelect(bounce)), u1, u2]
+                        }
+                        None => rng.gen(),
+                    };
+                    let li =
+                        self.through_portals(portals, &hit, material, wo, band, u, &mut budget);
+                    if !li.is_black() {
+                        gather(&mut path, throughput * li);
+                    }
+                }
+                path.pop();
+            }
+            if depth == self.max_depth {
+                break;
+            }
+
+            let s = match &mut sampler {
+                Some(sampler) => {
+                    let u = sampler.get_2d(SampleDimension::Bsdf(bounce));
+                    material.sample_f_with(wo, isect, u, rng)
+                }
+                None => material.sample_f(wo, isect, rng),
+            };
+            let Some(s) = s else {
+                break;
+            };
+            if !budget.bounce() || !counts.scatter(s.flags) {
+                break;
+            }
+            path.push(if s.flags.is_specular() {
+                PathEvent::Specular
+            } else if s.flags.contains(BSDFFlags::GLOSSY) {
+                PathEvent::Glossy
+            } else {
+                PathEvent::Diffuse
+            });
+            bounced_from = (!s.flags.is_specular()).then_some((isect.point, s.pdf));
+            throughput *= band.scale(s.f) * (s.wi.dot(isect.norm).abs() / s.pdf);
+            let survived = match &mut sampler {
+                Some(sampler) => {
+                    let u = sampler.get_1d(SampleDimension::Roulette(bounce));
+                    self.roulette.survive_with(&mut throughput, depth, u)
+                }
+                None => self.roulette.survive(&mut throughput, depth, rng),
+            };
+            if throughput.is_black() || !survived {
+                break;
+            }
+            ray = hit.spawn(s.wi.into());
+        }
+    }
+}
+
+/// The light a path tracer found for one sample, split by the number of
+/// bounces it took, for debugging.
+///
+/// Measured by a [`PathTracer`]'s [`by_bounce`] integrator. Get them for a
+/// pixel's samples with [`Renderer::render_pixel`] to see where an
+/// outlier's light came from.
+///
+/// [`by_bounce`]: PathTracer::by_bounce
+/// [`Renderer::render_pixel`]: crate::renderer::Renderer::render_pixel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BounceBreakdown {
+    /// The light arriving at the camera, summed over all bounces.
+    pub total: RGB,
+    /// The light that reflected off `n` surfaces on its way to the camera,
+    /// at index `n`. Index `0` is light seen directly.
+    pub bounces: Vec<RGB>,
+}
+
+impl PathTracer<'_> {
+    // The environment's light through a direction sampled through the
+    // portals from `u`, weighted against finding it by sampling the BSDF
+    #[allow(clippy::too_many_arguments)]
+    fn through_portals(
+        &self,
+        portals: &Portals,
+        hit: &SurfaceInteraction,
+        material: &Material,
+        wo: Unit,
+        band: Band,
+        u: [Float; 3],
+        budget: &mut PathBudget,
+    ) -> RGB {
+        let isect = &hit.isect;
+        let Some(s) = portals.sample(isect.point, u) else {
+            return RGB::default();
+        };
+        let f = band.scale(material.f(wo, s.wi, isect));
+        if f.is_black() {
+            return RGB::default();
+        }
+        let shadow = hit.spawn(s.wi.into());
+        if !budget.query() || self.scene.occluded(&shadow, 0.0, Float::INFINITY) {
+            return RGB::default();
+        }
+        let fog = match &self.fog {
+            Some(fog) => match fog.bounds().intsersects(&shadow, 0.0, Float::INFINITY) {
+                Some((t0, t1)) => fog.transmittance(t1 - t0),
+                None => 1.0,
+            },
+            None => 1.0,
+        };
+        let weight = power_heuristic(s.pdf, material.pdf(wo, s.wi, isect));
+        f * band.emission(self.background.radiance(s.wi.into()))
+            * (s.wi.dot(isect.norm).abs() / s.pdf * weight * fog)
+    }
+
+    // Gather the light the fog scatters towards the ray before `t_max`, one
+    // sample per light, returning the fog's transmittance
+    #[allow(clippy::too_many_arguments)]
+    fn in_scatter(
+        &self,
+        fog: &Fog,
+        ray: &Ray,
+        t_max: Float,
+        throughput: RGB,
+        band: Band,
+        path: &mut Vec<PathEvent>,
+        budget: &mut PathBudget,
+        rng: &mut impl Rng,
+        gather: &mut impl FnMut(&mut Vec<PathEvent>, RGB),
+    ) -> Float {
+        let (Some((t0, t1)), Ok(dir)) = (
+            fog.bounds().intsersects(ray, 0.0, t_max),
+            Unit::try_from(ray.direction),
+        ) else {
+            return 1.0;
+        };
+        let len = ray.direction.len();
+        let (d0, d1) = (t0 * len, t1 * len);
+
+        path.push(PathEvent::Volume);
+        for light in &self.lights {
+            let u: Float = rng.gen();
+            let Some(d) = fog.sample_distance(ray.origin, dir, light.position(), (d0, d1), u)
+            else {
+                continue;
+            };
+            let point = ray.origin + Vector::from(dir) * d.dist;
+            let Some(s) = light.sample(point, rng.gen()) else {
+                continue;
+            };
+            let shadow = Ray::new(point, s.wi.into());
+            let tr = self.scene.transmittance(
+                &shadow,
+                0.0,
+                s.dist * (1.0 - 1e-4),
+                &self.shadow,
+                budget,
+                rng,
+            );
+            if tr.is_black() {
+                continue;
+            }
+            let to_light = match fog.bounds().intsersects(&shadow, 0.0, s.dist) {
+                Some((t0, t1)) => fog.transmittance(t1 - t0),
+                None => 1.0,
+            };
+            // Scattering evenly in every direction
+            let phase = 1.0 / (4.0 * PI);
+            let weight = fog.transmittance(d.dist - d0) * to_light * phase / (d.pdf * s.pdf);
+            let scattered = band.scale(tr) * band.scale(fog.scattering()) * band.emission(s.li);
+            gather(path, throughput * scattered * weight);
+        }
+        path.pop();
+        fog.transmittance(d1 - d0)
+    }
+}
+
+impl Integrator<RGB> for PathTracer<'_> {
+    fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> RGB {
+        let mut total = RGB::default();
+        self.trace(ray, Band::Rgb, None, rng, |_, li| total += li);
+        total
+    }
+
+    fn radiance_sampled(&self, ray: &Ray, sampler: &mut impl Sampler, rng: &mut impl Rng) -> RGB {
+        let mut total = RGB::default();
+        self.trace(ray, Band::Rgb, Some(sampler), rng, |_, li| total += li);
+        total
+    }
+}
+
+/// A [`PathTracer`] measuring [`BounceBreakdown`]s, from
+/// [`PathTracer::by_bounce`].
+#[derive(Clone, Copy)]
+pub struct ByBounce<'t, 'a>(&'t PathTracer<'a>);
+
+impl Integrator<BounceBreakdown> for ByBounce<'_, '_> {
+    fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> BounceBreakdown {
+        self.measure(ray, None, rng)
+    }
+
+    fn radiance_sampled(
+        &self,
+        ray: &Ray,
+        sampler: &mut impl Sampler,
+        rng: &mut impl Rng,
+    ) -> BounceBreakdown {
+        self.measure(ray, Some(sampler), rng)
+    }
+}
+
+impl ByBounce<'_, '_> {
+    fn measure(
+        &self,
+        ray: &Ray,
+        sampler: Option<&mut dyn Sampler>,
+        rng: &mut impl Rng,
+    ) -> BounceBreakdown {
+        let mut breakdown = BounceBreakdown::default();
+        self.0.trace(ray, Band::Rgb, sampler, rng, |path, li| {
+            // Everything between the camera and the light is a bounce
+            let n = path.len() - 2;
+            if breakdown.bounces.len() <= n {
+                breakdown.bounces.resize(n + 1, RGB::default());
+            }
+            breakdown.bounces[n] += li;
+            breakdown.total += li;
+        });
+        breakdown
+    }
+}
+
+/// A [`PathTracer`] measuring the light in each of several
+/// [`RenderLayers`], from [`PathTracer::layers`].
+#[derive(Clone, Copy)]
+pub struct Layers<'t, 'a, 'l>(&'t PathTracer<'a>, &'l RenderLayers);
+
+impl Integrator<Vec<RGB>> for Layers<'_, '_, '_> {
+    fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> Vec<RGB> {
+        self.measure(ray, None, rng)
+    }
+
+    fn radiance_sampled(
+        &self,
+        ray: &Ray,
+        sampler: &mut impl Sampler,
+        rng: &mut impl Rng,
+    ) -> Vec<RGB> {
+        self.measure(ray, Some(sampler), rng)
+    }
+}
+
+impl Layers<'_, '_, '_> {
+    fn measure(
+        &self,
+        ray: &Ray,
+        sampler: Option<&mut dyn Sampler>,
+        rng: &mut impl Rng,
+    ) -> Vec<RGB> {
+        let mut layers = vec![RGB::default(); self.1.len()];
+        self.0.trace(ray, Band::Rgb, sampler, rng, |path, li| {
+            for (expr, layer) in self.1.exprs().zip(&mut layers) {
+                if expr.matches(path) {
+                    *layer += li;
+                }
+            }
+        });
+        layers
+    }
+}
+
+/// A [`PathTracer`] tracing each sample at a single wavelength, from
+/// [`PathTracer::spectral`].
+#[derive(Clone, Copy)]
+pub struct Spectral<'t, 'a>(&'t PathTracer<'a>);
+
+impl Integrator<SingleWavelength> for Spectral<'_, '_> {
+    fn radiance(&self, ray: &Ray, rng: &mut impl Rng) -> SingleWavelength {
+        self.measure(ray, None, rng)
+    }
+
+    fn radiance_sampled(
+        &self,
+        ray: &Ray,
+        sampler: &mut impl Sampler,
+        rng: &mut impl Rng,
+    ) -> SingleWavelength {
+        self.measure(ray, Some(sampler), rng)
+    }
+}
+
+impl Spectral<'_, '_> {
+    // The wavelength is drawn from `rng`, having no dimension of its own
+    fn measure(
+        &self,
+        ray: &Ray,
+        sampler: Option<&mut dyn Sampler>,
+        rng: &mut impl Rng,
+    ) -> SingleWavelength {
+        let wavelength = Sampled::sample_wavelength(rng.gen());
+        let mut value = 0.0;
+        self.0
+            .trace(ray, Band::Wavelength(wavelength), sampler, rng, |_, li| {
+                value += <[Float; 3]>::from(li)[0]
+            });
+        SingleWavelength { wavelength, value }
+    }
+}
+
+// The weight for a sample from a strategy with density `pdf`, combined with
+// another with density `other`
+#[inline]
+fn power_heuristic(pdf: Float, other: Float) -> Float {
+    let (a, b) = (pdf * pdf, other * other);
+    match a + b > 0.0 {
+        true => a / (a + b),
+        false => 0.0,
+    }
+}
+
+// What a path carries light in. At a single wavelength, each color along the
+// path is replaced by the grey of its spectrum's value there, so the same
+// arithmetic serves both.
+#[derive(Debug, Clone, Copy)]
+enum Band {
+    Rgb,
+    Wavelength(Float),
+}
+
+impl Band {
+    // A color of light, from a light or the environment
+    fn emission(self, rgb: RGB) -> RGB {
+        match self {
+            Self::Wavelength(w) if !rgb.is_black() => {
+                RGB::splat(Sampled::from_rgb(rgb, SpectrumKind::Illuminant).at(w))
+            }
+            _ => rgb,
+        }
+    }
+
+    // A color that scales light, such as a BSDF's value. Greys, which every
+    // clear glass has, are flat spectra and pass through as they are. Other
+    // colors are converted at unit brightness, since reflectance spectra are
+    // kept within [0, 1], and scaled back.
+    fn scale(self, rgb: RGB) -> RGB {
+        let Self::Wavelength(w) = self else {
+            return rgb;
+        };
+        let [r, g, b]: [Float; 3] = rgb.into();
+        let max = r.max(g).max(b);
+        if r == g && g == b {
+            rgb
+        } else if max <= 0.0 {
+            RGB::default()
+        } else {
+            let spec = Sampled::from_rgb(rgb * max.recip(), SpectrumKind::Reflectance);
+            RGB::splat(max * spec.at(w))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::XYZ,
+        geo::{Bounds, Degrees, Point, Vector},
+        integrator::FogSampling,
+        light::Portal,
+        material::{Dielectric, DispersiveDielectric, Lambertian, Plastic},
+        math::CounterRng,
+        sampler::{HaltonSampler, IndependentSampler},
+        shape::Triangle,
+    };
+
+    // A triangle at height `y` facing up, covering everything near the
+    // y-axis
+    fn plane(y: Float) -> Triangle {
+        Triangle::new([-10.0, y, -10.0], [0.0, y, 10.0], [10.0, y, -10.0])
+    }
+
+    fn assert_close(a: RGB, b: RGB) {
+        let (a, b) = (<[Float; 3]>::from(a), <[Float; 3]>::from(b));
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < 1e-9 * x.max(1.0), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn layers_add_up() {
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(1.0), Lambertian::new(RGB::splat(0.5)));
+        let light = SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0));
+        let tracer = |scene| {
+            PathTracer::new(scene, vec![light.clone().cone(Degrees(60.0))])
+                .background(RGB::splat(0.2))
+        };
+
+        // Straight down onto the floor, below the light
+        let ray = Ray::new(Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let layer = |expr: &str| -> RGB {
+            PathTracer::new(&scene, vec![light.clone()])
+                .layer(expr.parse().unwrap())
+                .radiance(&ray, &mut CounterRng::new(0))
+        };
+        let expected = 0.5 / PI * 10.0 / 16.0;
+        assert!((layer("CDL").max_component() - expected).abs() < 1e-6);
+        assert!(layer("CDSL").is_black());
+
+        // A coated floor and a diffuse wall, for paths of every kind
+        let mut scene = Scene::default();
+        scene.add_primitive(
+            plane(1.0),
+            Plastic::new(Lambertian::new(RGB::splat(0.5)), 1.5),
+        );
+        let wall = Triangle::new([2.0, 0.0, -10.0], [2.0, 0.0, 10.0], [2.0, 10.0, 0.0]);
+        scene.add_primitive(wall, Lambertian::new(RGB::splat(0.8)));
+
+        let layers: [LightPathExpr; 4] =
+            ["CL", "CDL", "CD.+L", "C[GS].*L"].map(|e| e.parse().unwrap());
+        let mut totals = [RGB::default(); 4];
+        for i in 0..200 {
+            let dir = Vector::new(i as Float / 100.0 - 1.0, -1.0, 0.2);
+            let ray = Ray::new(Point::new(0.0, 3.0, 0.0), dir);
+            let rng = || CounterRng::from_keys(&[i]);
+
+            let full: RGB = tracer(&scene).radiance(&ray, &mut rng());
+            let mut sum = RGB::default();
+            let mut separate = Vec::new();
+            for (layer, total) in layers.iter().zip(&mut totals) {
+                let li: RGB = tracer(&scene)
+                    .layer(layer.clone())
+                    .radiance(&ray, &mut rng());
+                *total += li;
+                sum += li;
+                separate.push(li);
+            }
+            assert_close(full, sum);
+
+            // All at once, from the same path
+            let config = "camera = CL\ndirect = CDL\nindirect = CD.+L\nshiny = C[GS].*L";
+            let layered = tracer(&scene)
+                .layers(&config.parse().unwrap())
+                .radiance(&ray, &mut rng());
+            assert_eq!(separate, layered);
+
+            // The same path, by bounce
+            let breakdown = tracer(&scene).by_bounce().radiance(&ray, &mut rng());
+            assert_eq!(full, breakdown.total);
+            assert!(breakdown.bounces[0].is_black());
+            assert_close(
+                full,
+                breakdown.bounces.iter().fold(RGB::default(), |a, &b| a + b),
+            );
+        }
+        // Nothing's seen directly but the floor and wall
+        assert!(totals[0].is_black());
+        assert!(totals[1..].iter().all(|t| !t.is_black()), "{:?}", totals);
+    }
+
+    #[test]
+    fn shadows_through_glass() {
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(1.0), Lambertian::new(RGB::splat(0.5)));
+        let light = SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0));
+        let ray = Ray::new(Point::new(0.0, 2.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let direct = |scene: &Scene| -> RGB {
+            PathTracer::new(scene, vec![light.clone()])
+                .layer("CDL".parse().unwrap())
+                .radiance(&ray, &mut CounterRng::new(0))
+        };
+        let lit = direct(&scene);
+
+        // A pane of green glass between the floor and the light tints the
+        // light through it, less the 4% it reflects
+        let green = RGB::from([0.2, 0.9, 0.2]);
+        scene.add_primitive(plane(3.0), Dielectric::new(1.5).tint(green));
+        assert_close(lit * green * 0.96, direct(&scene));
+
+        // Light shafts through the pane are tinted the same way
+        let across = Ray::new(Point::new(-5.0, 2.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let room = Bounds::from_corners(Point::new(-10.0, 1.0, -10.0), Point::splat(10.0));
+        let fog = Fog::new(room, 0.1).sampling(FogSampling::Equiangular);
+        let shaft = |scene: &Scene| -> RGB {
+            PathTracer::new(scene, vec![light.clone()])
+                .fog(fog)
+                .layer("CVL".parse().unwrap())
+                .radiance(&across, &mut CounterRng::new(0))
+        };
+        let tinted = shaft(&scene);
+        assert!(!tinted.is_black());
+        assert!(tinted.min_component() < 0.5 * tinted.max_component());
+
+        // Anything opaque still blocks it
+        scene.add_primitive(plane(4.0), Lambertian::new(RGB::splat(0.5)));
+        assert!(direct(&scene).is_black());
+        assert!(shaft(&scene).is_black());
+    }
+
+    #[test]
+    fn light_shaft() {
+        let scene = Scene::default();
+        let light =
+            SpotLight::new([0.0, 2.5, 0.0], -Unit::Y_AXIS, RGB::splat(10.0)).cone(Degrees(85.0));
+        let room = Bounds::from_corners(Point::splat(-10.0), Point::splat(10.0));
+        let ray = Ray::new(Point::new(-5.0, 2.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+
+        // Both ways of sampling the shaft agree, but the equiangular one is
+        // far less noisy close to the light
+        let stats = |sampling| {
+            let fog = Fog::new(room, 0.1).sampling(sampling);
+            let tracer = PathTracer::new(&scene, vec![light.clone()]).fog(fog);
+            let n = 20_000;
+            let (mut sum, mut sum_sq) = (0.0, 0.0);
+            for i in 0..n {
+                let li: RGB = tracer.radiance(&ray, &mut CounterRng::from_keys(&[i]));
+                sum += li.max_component();
+                sum_sq += li.max_component().powi(2);
+            }
+            let mean = sum / n as Float;
+            (mean, sum_sq / n as Float - mean * mean)
+        };
+        let (mean, var) = stats(FogSampling::Equiangular);
+        let (reference, reference_var) = stats(FogSampling::Transmittance);
+        assert!(mean > 0.0);
+        assert!(
+            (mean - reference).abs() < 0.05 * mean,
+            "{} {}",
+            mean,
+            reference
+        );
+        assert!(var * 10.0 < reference_var, "{} {}", var, reference_var);
+
+        // All of it scattered by the fog
+        let fog = Fog::new(room, 0.1);
+        let tracer = PathTracer::new(&scene, vec![light]).fog(fog);
+        let full: RGB = tracer.radiance(&ray, &mut CounterRng::new(0));
+        let shaft: RGB = tracer
+            .layer("CVL".parse().unwrap())
+            .radiance(&ray, &mut CounterRng::new(0));
+        assert_eq!(full, shaft);
+    }
+
+    #[test]
+    fn settings() {
+        // Looking up at the underside of a floor under a white sky
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(1.0), Lambertian::new(RGB::splat(0.5)));
+        let ray = Ray::new(Point::ORIGIN, Vector::new(0.0, 1.0, 0.0));
+        let tracer = || PathTracer::new(&scene, vec![]).background(RGB::splat(1.0));
+        let li = |tracer: PathTracer| -> RGB { tracer.radiance(&ray, &mut CounterRng::new(0)) };
+
+        // The floor is one-sided unless told otherwise, and is then lit
+        // straight from the sky
+        assert!(li(tracer()).is_black());
+        assert_close(RGB::splat(0.5), li(tracer().two_sided(true)));
+
+        // That counts as direct light for clamping
+        let clamp = |direct, indirect| RadianceClamp { direct, indirect };
+        let clamped = li(tracer().two_sided(true).clamp(clamp(Float::INFINITY, 0.1)));
+        assert_close(RGB::splat(0.5), clamped);
+        let clamped = li(tracer().two_sided(true).clamp(clamp(0.1, Float::INFINITY)));
+        assert_close(RGB::splat(0.1), clamped);
+
+        // Limits stop the path before it finds the sky
+        let bounces = BounceLimits {
+            diffuse: 0,
+            ..Default::default()
+        };
+        assert!(li(tracer().two_sided(true).bounce_limits(bounces)).is_black());
+        let limits = WorkLimits {
+            max_queries: 1,
+            ..Default::default()
+        };
+        assert!(li(tracer().two_sided(true).work_limits(limits)).is_black());
+
+        // Shadow rays are queries too
+        let light = SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0));
+        let down = Ray::new(Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let direct = |max_queries| -> RGB {
+            let limits = WorkLimits {
+                max_queries,
+                ..Default::default()
+            };
+            PathTracer::new(&scene, vec![light.clone()])
+                .max_depth(0)
+                .work_limits(limits)
+                .radiance(&down, &mut CounterRng::new(0))
+        };
+        assert!(direct(1).is_black());
+        assert!(!direct(2).is_black());
+    }
+
+    #[test]
+    fn spectral_white() {
+        // White light averaged over many wavelengths comes out white
+        let scene = Scene::default();
+        let tracer = PathTracer::new(&scene, vec![]).background(RGB::splat(1.0));
+        let ray = Ray::new(Point::ORIGIN, Vector::new(0.0, 0.0, 1.0));
+        let mut rng = StdRng::seed_from_u64(1);
+        let n = 20_000;
+        let mut sum = XYZ::default();
+        for _ in 0..n {
+            sum += XYZ::from(tracer.spectral().radiance(&ray, &mut rng));
+        }
+        let rgb: [Float; 3] = RGB::from(sum * (n as Float).recip()).into();
+        assert!(rgb.iter().all(|c| (c - 1.0).abs() < 0.05), "{:?}", rgb);
+    }
+
+    #[test]
+    fn dispersion() {
+        // Through glass at 45°, blue bends further than red, so a cover far
+        // below can hide where blue lands but not where red does
+        let glass = DispersiveDielectric::BK7;
+        let (blue, red, depth) = (450.0, 650.0, 10.0);
+        let landing = |wavelength: Float| {
+            let sin_t = (0.5 as Float).sqrt() / glass.ior(wavelength);
+            1.0 + depth * sin_t / (1.0 - sin_t * sin_t).sqrt()
+        };
+        let edge = (landing(blue) + landing(red)) / 2.0;
+        assert!(landing(blue) < edge && edge < landing(red));
+
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(0.0), glass);
+        let cover = Triangle::new(
+            [-100.0, -depth, -100.0],
+            [edge, -depth, 100.0],
+            [edge, -depth, -100.0],
+        );
+        scene.add_primitive(cover, Lambertian::new(RGB::default()));
+        let tracer = PathTracer::new(&scene, vec![]).background(RGB::splat(1.0));
+        let ray = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(1.0, -1.0, 0.0));
+
+        // The light seen at a wavelength, relative to the background's
+        let seen = |wavelength: Float| {
+            let mut rng = StdRng::seed_from_u64(1);
+            let mut total = 0.0;
+            for _ in 0..1000 {
+                tracer.trace(
+                    &ray,
+                    Band::Wavelength(wavelength),
+                    None,
+                    &mut rng,
+                    |_, li| total += <[Float; 3]>::from(li)[0],
+                );
+            }
+            let white = Sampled::from_rgb(RGB::splat(1.0), SpectrumKind::Illuminant);
+            total / 1000.0 / white.at(wavelength)
+        };
+        // Blue only sees the few percent reflected off the glass
+        let (blue, red) = (seen(blue), seen(red));
+        assert!(blue < 0.1 && red > 4.0 * blue, "{} {}", blue, red);
+
+        // In RGB, the glass refracts by its d-line index, past the cover too
+        let rgb: RGB = tracer.radiance(&ray, &mut StdRng::seed_from_u64(1));
+        assert!(rgb.min_component() > 4.0 * blue, "{:?}", rgb);
+    }
+
+    #[test]
+    fn window_portal() {
+        // A 4x2x4 room with a 1x1 window in the wall at `z = 2`, under a
+        // white sky
+        let mut scene = Scene::default();
+        let mut quad = |corner: [Float; 3], e0: Vector, e1: Vector| {
+            let p = Point::from(corner);
+            let grey = || Lambertian::new(RGB::splat(0.5));
+            scene.add_primitive(Triangle::new(p, p + e0, p + e1), grey());
+            scene.add_primitive(Triangle::new(p + e0, p + e0 + e1, p + e1), grey());
+        };
+        let (x, y, z) = (Vector::X_AXIS, Vector::Y_AXIS, Vector::Z_AXIS);
+        quad([-2.0, 0.0, -2.0], x * 4.0, z * 4.0);
+        quad([-2.0, 2.0, -2.0], x * 4.0, z * 4.0);
+        quad([-2.0, 0.0, -2.0], x * 4.0, y * 2.0);
+        quad([-2.0, 0.0, -2.0], z * 4.0, y * 2.0);
+        quad([2.0, 0.0, -2.0], z * 4.0, y * 2.0);
+        // Around the window
+        quad([-2.0, 0.0, 2.0], x * 4.0, y * 0.5);
+        quad([-2.0, 1.5, 2.0], x * 4.0, y * 0.5);
+        quad([-2.0, 0.5, 2.0], x * 1.5, y);
+        quad([0.5, 0.5, 2.0], x * 1.5, y);
+
+        let window = Portal::new([-0.5, 0.5, 2.0], [Vector::Y_AXIS, Vector::X_AXIS]);
+        let sky = Environment::from(RGB::splat(1.0));
+        let tracer = |background: Environment| {
+            PathTracer::new(&scene, vec![])
+                .background(background)
+                .two_sided(true)
+                .max_depth(1)
+                .layer("CDL".parse().unwrap())
+        };
+
+        // The floor lit straight through the window, looking down at the
+        // middle of it
+        let ray = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let stats = |tracer: PathTracer| {
+            let n = 20000;
+            let samples: Vec<Float> = (0..n)
+                .map(|i| {
+                    let li: RGB = tracer.radiance(&ray, &mut CounterRng::from_keys(&[i]));
+                    li.max_component()
+                })
+                .collect();
+            let mean = samples.iter().sum::<Float>() / n as Float;
+            let var = samples.iter().map(|s| (s - mean).powi(2)).sum::<Float>() / n as Float;
+            (mean, var)
+        };
+        let (mean, var) = stats(tracer(sky.clone().with_portals(Portals(vec![window]))));
+        let (reference, reference_var) = stats(tracer(sky));
+        assert!(mean > 0.0);
+        assert!(
+            (mean - reference).abs() < 0.05 * mean,
+            "{} {}",
+            mean,
+            reference
+        );
+        assert!(var * 10.0 < reference_var, "{} {}", var, reference_var);
+    }
+
+    #[test]
+    fn sampled_bounces() {
+        // The floor under a light shining up at the ceiling, so it's lit
+        // only by the light bouncing back down
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(0.0), Lambertian::new(RGB::splat(0.5)));
+        let ceiling = Triangle::new([-10.0, 2.0, -10.0], [10.0, 2.0, -10.0], [0.0, 2.0, 10.0]);
+        scene.add_primitive(ceiling, Lambertian::new(RGB::splat(0.8)));
+        let light = SpotLight::new([0.0, 1.0, 0.0], Unit::Y_AXIS, RGB::splat(10.0));
+        let tracer = || {
+            PathTracer::new(&scene, vec![light.clone().cone(Degrees(60.0))])
+                .two_sided(true)
+                .max_depth(1)
+        };
+        let ray = Ray::new(Point::new(0.5, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let direct: RGB = tracer()
+            .layer("CDL".parse().unwrap())
+            .radiance(&ray, &mut CounterRng::new(0));
+        assert!(direct.is_black());
+
+        // Each pixel's estimate from 16 samples, and their spread
+        fn stats(tracer: &PathTracer, ray: &Ray, mut sampler: impl Sampler) -> (Float, Float) {
+            let n = 1000;
+            let estimates: Vec<Float> = (0..n)
+                .map(|px| {
+                    let mut sum = 0.0;
+                    for i in 0..16 {
+                        sampler.start_pixel_sample(px, 0, i);
+                        let mut rng = CounterRng::from_keys(&[px as u64, i]);
+                        let li = tracer.radiance_sampled(ray, &mut sampler, &mut rng);
+                        sum += li.max_component();
+                    }
+                    sum / 16.0
+                })
+                .collect();
+            let mean = estimates.iter().sum::<Float>() / n as Float;
+            let var = estimates.iter().map(|e| (e - mean).powi(2)).sum::<Float>() / n as Float;
+            (mean, var)
+        }
+        let (mean, var) = stats(&tracer(), &ray, HaltonSampler::new(3));
+        let (reference, reference_var) = stats(&tracer(), &ray, IndependentSampler::new(3));
+        assert!(mean > 0.0);
+        assert!(
+            (mean - reference).abs() < 0.05 * mean,
+            "{} {}",
+            mean,
+            reference
+        );
+        assert!(var * 4.0 < reference_var, "{} {}", var, reference_var);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{color::RGB, Float};
+use rand::Rng;
+
+/// How [`Roulette`] turns a path's throughput into a survival probability.
+///
+/// The choice matters most in scenes with strongly colored albedos. A path
+/// bouncing around a red room keeps a high red throughput while its
+/// luminance drops quickly, so [`Luminance`] kills it early and the
+/// survivors, reweighted by a large factor, show up as fireflies in the red
+/// channel. [`MaxComponent`] keeps such paths alive for longer, at the cost
+/// of tracing more of them.
+///
+/// [`Luminance`]: Self::Luminance
+/// [`MaxComponent`]: Self::MaxComponent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RouletteStrategy {
+    /// Survive in proportion to the throughput's luminance.
+    Luminance,
+    /// Survive in proportion to the throughput's largest component.
+    MaxComponent,
+    /// Survive with the same probability whatever the throughput.
+    Fixed(Float),
+}
+
+/// Settings for terminating camera paths with Russian roulette.
+///
+/// Once a path is [`start_depth`] bounces deep, it's terminated at each
+/// bounce with a probability chosen by [`strategy`], and the survivors'
+/// throughput is scaled up to make up for the ones lost. This keeps the
+/// estimate unbiased while spending fewer rays on paths that carry little
+/// light.
+///
+/// [`start_depth`]: Self::start_depth
+/// [`strategy`]: Self::strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Roulette {
+    /// How the survival probability is chosen.
+    pub strategy: RouletteStrategy,
+    /// Number of bounces before roulette kicks in.
+    pub start_depth: usize,
+    /// Minimum survival probability once roulette is active. Too low a
+    /// floor lets a few survivors get very large weights.
+    pub min_survival: Float,
+}
+
+impl Default for Roulette {
+    fn default() -> Self {
+        Self {
+            strategy: RouletteStrategy::MaxComponent,
+            start_depth: 3,
+            min_survival: 0.05,
+        }
+    }
+}
+
+impl Roulette {
+    /// The probability that a path with the given `throughput` survives its
+    /// bounce at `depth`, counting from `0`.
+    pub fn survival(&self, throughput: RGB, depth: usize) -> Float {
+        if depth < self.start_depth {
+            return 1.0;
+        }
+        let p = match self.strategy {
+            RouletteStrategy::Luminance => throughput.to_xyz().luminance(),
+            RouletteStrategy::MaxComponent => throughput.max_component(),
+            RouletteStrategy::Fixed(p) => p,
+        };
+        p.clamp(self.min_survival, 1.0)
+    }
+
+    /// Play roulette for a path at `depth`. Returns `false` if the path
+    /// should stop, and otherwise scales `throughput` up by the inverse of
+    /// the survival probability.
+    pub fn survive(&self, throughput: &mut RGB, depth: usize, rng: &mut impl Rng) -> bool {
+        if self.survival(*throughput, depth) >= 1.0 {
+            return true;
+        }
+        self.survive_with(throughput, depth, rng.gen())
+    }
+
+    /// Play roulette for a path at `depth`, as [`survive`] does, deciding
+    /// with a uniform sample `u` in `[0, 1)` from a [`Sampler`].
+    ///
+    /// [`survive`]: Self::survive
+    /// [`Sampler`]: crate::sampler::Sampler
+    pub fn survive_with(&self, throughput: &mut RGB, depth: usize, u: Float) -> bool {
+        let survival = self.survival(*throughput, depth);
+        if survival >= 1.0 {
+            return true;
+        }
+        if u >= survival {
+            return false;
+        }
+        *throughput /= survival;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn strategies() {
+        let red = RGB::from([0.8, 0.05, 0.05]);
+        let with = |strategy| Roulette {
+            strategy,
+            ..Default::default()
+        };
+
+        // Nothing is lost before the start depth
+        assert_eq!(1.0, Roulette::default().survival(red, 2));
+        assert_eq!(0.8, with(RouletteStrategy::MaxComponent).survival(red, 3));
+        let lum = with(RouletteStrategy::Luminance).survival(red, 3);
+        assert!((lum - 0.2095).abs() < 1e-3, "{}", lum);
+        assert_eq!(0.5, with(RouletteStrategy::Fixed(0.5)).survival(red, 3));
+        assert_eq!(0.05, with(RouletteStrategy::Fixed(0.0)).survival(red, 3));
+
+        // Survivors are reweighted so the expected throughput is unchanged
+        let mut rng = StdRng::seed_from_u64(3);
+        for strategy in [
+            RouletteStrategy::Luminance,
+            RouletteStrategy::MaxComponent,
+            RouletteStrategy::Fixed(0.3),
+        ] {
+            let roulette = with(strategy);
+            let mut total = RGB::default();
+            for _ in 0..100000 {
+                let mut throughput = red;
+                if roulette.survive(&mut throughput, 5, &mut rng) {
+                    total += throughput;
+                }
+            }
+            let mean = total.max_component() / 100000.0;
+            assert!((mean - 0.8).abs() < 0.02, "{:?}: {}", strategy, mean);
+        }
+    }
+}
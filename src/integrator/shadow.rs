@@ -0,0 +1,249 @@
+use super::PathBudget;
+use crate::{
+    color::RGB,
+    geo::{Ray, SpawnOffset},
+    metrics::Counter,
+    shape::Intersection,
+    shape::Shape,
+    Float,
+};
+use rand::Rng;
+
+/// Number of shadow rays treated as blocked because they crossed
+/// [`ShadowRays::max_depth`] surfaces.
+///
+/// Shadow rays that run out of their path's [`PathBudget`] are counted in
+/// [`TRUNCATED_PATHS`] instead, with the path.
+///
+/// [`TRUNCATED_PATHS`]: super::TRUNCATED_PATHS
+pub static TRUNCATED_SHADOW_RAYS: Counter = Counter::new();
+
+/// Settings for transmissive shadow rays.
+///
+/// Rather than treating any hit as full occlusion, a transmissive shadow ray
+/// walks through every surface between the shading point and the light,
+/// attenuating by each surface's transmittance. This is what lets foliage
+/// cards and thin glass cast soft, colored shadows under next-event
+/// estimation.
+///
+/// Walking through an unbounded number of surfaces can get expensive, so after
+/// [`roulette_depth`] surfaces have been crossed the ray is subject to Russian
+/// roulette, with survival probability proportional to the remaining
+/// throughput. This keeps the estimate unbiased while bounding the expected
+/// cost. Every surface found is an intersection query against the path's
+/// [`PathBudget`], and a shadow ray that exhausts it is treated as blocked.
+///
+/// [`roulette_depth`]: Self::roulette_depth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowRays {
+    /// Number of surfaces crossed before roulette kicks in.
+    pub roulette_depth: usize,
+    /// Minimum survival probability once roulette is active.
+    pub min_survival: Float,
+    /// Hard cap on surfaces crossed. Anything beyond is treated as opaque and
+    /// counted in [`TRUNCATED_SHADOW_RAYS`].
+    pub max_depth: usize,
+    /// How far past each surface the ray continues from, to avoid re-hitting
+    /// it.
+    pub offset: SpawnOffset,
+}
+
+impl Default for ShadowRays {
+    fn default() -> Self {
+        Self {
+            roulette_depth: 3,
+            min_survival: 0.05,
+            max_depth: 64,
+            offset: SpawnOffset::default(),
+        }
+    }
+}
+
+impl ShadowRays {
+    /// Compute the transmittance along `ray` over `[t_min, t_max]`.
+    ///
+    /// `transmittance` reports how much light passes through the surface at a
+    /// given intersection: black for opaque surfaces, white for fully
+    /// transparent (_e.g._ alpha-cutout) ones, and a color for tinted thin
+    /// dielectrics.
+    ///
+    /// Each query counts against `budget`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transmittance<F>(
+        &self,
+        shapes: &impl Shape,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        transmittance: F,
+        budget: &mut PathBudget,
+        rng: &mut impl Rng,
+    ) -> RGB
+    where
+        F: Fn(&Intersection) -> RGB,
+    {
+        self.transmittance_by(
+            ray,
+            t_min,
+            t_max,
+            |ray, t_min, t_max| {
+                let isect = shapes.intersect(ray, t_min, t_max)?;
+                Some((isect, transmittance(&isect)))
+            },
+            budget,
+            rng,
+        )
+    }
+
+    /// Compute the transmittance along `ray` over `[t_min, t_max]`, where
+    /// `hit` finds the nearest surface along a ray within an interval, along
+    /// with how much light passes through it.
+    ///
+    /// For geometry that isn't a single [`Shape`], such as a [`Scene`],
+    /// whose materials decide the transmittance.
+    ///
+    /// [`Scene`]: crate::scene::Scene
+    pub fn transmittance_by<H>(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        mut hit: H,
+        budget: &mut PathBudget,
+        rng: &mut impl Rng,
+    ) -> RGB
+    where
+        H: FnMut(&Ray, Float, Float) -> Option<(Intersection, RGB)>,
+    {
+        let mut throughput = RGB::from([1.0, 1.0, 1.0]);
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        let mut ray = Ray::new(ray.origin, ray.direction);
+
+        for depth in 0..self.max_depth {
+            if !budget.query() {
+                return RGB::default();
+            }
+            let (isect, transmittance) = match hit(&ray, t_min, t_max) {
+                Some(hit) => hit,
+                None => return throughput,
+            };
+
+            throughput *= transmittance;
+            if throughput.is_black() {
+                return throughput;
+            }
+
+            if depth >= self.roulette_depth {
+                let survival = throughput.max_component().clamp(self.min_survival, 1.0);
+                if rng.gen::<Float>() >= survival {
+                    return RGB::default();
+                }
+                throughput /= survival;
+            }
+
+            // Continue from just past the surface. The direction is unchanged,
+            // so distances along the new ray match the old one's.
+            ray = self.offset.spawn(isect.point, ray.direction, isect.norm);
+            t_max -= isect.t;
+            t_min = 0.0;
+        }
+
+        TRUNCATED_SHADOW_RAYS.inc();
+        RGB::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::{Point, Vector},
+        integrator::WorkLimits,
+        shape::Sphere,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn spheres() -> Vec<Sphere> {
+        vec![
+            Sphere::new(Point::new(0.0, 0.0, 5.0), 1.0),
+            Sphere::new(Point::new(0.0, 0.0, 10.0), 1.0),
+        ]
+    }
+
+    #[test]
+    fn opaque_blocks() {
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+        let mut rng = StdRng::seed_from_u64(1234);
+        let tr = ShadowRays::default().transmittance(
+            &spheres(),
+            &ray,
+            0.0,
+            Float::INFINITY,
+            |_| RGB::default(),
+            &mut WorkLimits::default().budget(),
+            &mut rng,
+        );
+        assert_eq!(RGB::default(), tr);
+    }
+
+    #[test]
+    fn tinted_attenuates() {
+        // Four surfaces crossed (in and out of each sphere)
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+        let mut rng = StdRng::seed_from_u64(1234);
+        let settings = ShadowRays {
+            roulette_depth: 8,
+            ..Default::default()
+        };
+        let tr = settings.transmittance(
+            &spheres(),
+            &ray,
+            0.0,
+            Float::INFINITY,
+            |_| RGB::from([1.0, 0.5, 0.0]),
+            &mut WorkLimits::default().budget(),
+            &mut rng,
+        );
+        assert_eq!(RGB::from([1.0, 0.0625, 0.0]), tr);
+    }
+
+    #[test]
+    fn limits() {
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+        let mut rng = StdRng::seed_from_u64(1234);
+        let clear = |_: &Intersection| RGB::from([1.0, 1.0, 1.0]);
+
+        // Four surfaces and the miss beyond them take five queries
+        let limits = WorkLimits {
+            max_queries: 5,
+            ..Default::default()
+        };
+        let mut budget = limits.budget();
+        let settings = ShadowRays::default();
+        let tr = settings.transmittance(&spheres(), &ray, 0.0, 100.0, clear, &mut budget, &mut rng);
+        assert_eq!(RGB::from([1.0, 1.0, 1.0]), tr);
+        assert!(!budget.is_exhausted());
+        let tr = settings.transmittance(&spheres(), &ray, 0.0, 100.0, clear, &mut budget, &mut rng);
+        assert!(tr.is_black());
+        assert!(budget.is_exhausted());
+
+        // Too many surfaces counts as a truncated shadow ray
+        let settings = ShadowRays {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let before = TRUNCATED_SHADOW_RAYS.get();
+        let tr = settings.transmittance(
+            &spheres(),
+            &ray,
+            0.0,
+            100.0,
+            clear,
+            &mut WorkLimits::default().budget(),
+            &mut rng,
+        );
+        assert!(tr.is_black());
+        assert!(TRUNCATED_SHADOW_RAYS.get() > before);
+    }
+}
@@ -0,0 +1,405 @@
+use super::Roulette;
+use crate::{
+    camera::Camera,
+    color::RGB,
+    film::Buffer,
+    geo::{Point, Ray, Unit},
+    light::{Environment, SpotLight},
+    material::{BSDFFlags, Material, RayType, BSDF},
+    math::{hash_keys, HashGrid},
+    scene::{Scene, SurfaceInteraction},
+    shape::Intersection,
+    Float,
+};
+use rand::prelude::*;
+use rayon::prelude::*;
+
+const PI: Float = std::f64::consts::PI as Float;
+
+// Distinguishes photon streams from camera streams with the same indices
+const PHOTON_STREAM: u64 = 0x5050_4d50;
+
+/// Stochastic progressive photon mapping.
+///
+/// Path tracing finds caustics, such as light focused through a glass onto
+/// a table, only when a path from the camera happens to bounce off the
+/// table, through the glass and into the light. With small lights that's
+/// so unlikely the caustic comes out as scattered fireflies. Photon mapping
+/// traces the other way, shooting photons from the lights and gathering
+/// those landing near each point the camera sees.
+///
+/// Each pass traces one path per pixel from the camera, through any
+/// specular surfaces, to the first surface that isn't purely specular: its
+/// visible point. Direct light there is found by shadow rays to each light,
+/// and everything else from the photons of that pass landing within the
+/// pixel's gather radius. The radius shrinks a little every pass, so the
+/// estimate converges while each pass stays cheap.
+///
+/// Only [`SpotLight`]s shoot photons, so the environment lights nothing:
+/// it's only seen directly and through specular surfaces. Glossy and
+/// diffuse lobes of a material are gathered; specular coats on top of them
+/// aren't followed from the camera.
+///
+/// This isn't an [`Integrator`], since pixels share photons and so can't be
+/// rendered independently; use [`render`] instead.
+///
+/// See: Hachisuka and Jensen, "Stochastic Progressive Photon Mapping"
+/// (2009).
+///
+/// [`Integrator`]: super::Integrator
+/// [`render`]: Self::render
+pub struct Sppm<'a> {
+    scene: &'a Scene,
+    lights: Vec<SpotLight>,
+    background: Environment,
+    photons: usize,
+    radius: Float,
+    alpha: Float,
+    max_depth: usize,
+    roulette: Roulette,
+    seed: u64,
+}
+
+/// What a pixel has gathered so far, across passes.
+#[derive(Debug, Clone, Copy)]
+struct PixelState {
+    radius: Float,
+    photons: Float,
+    flux: RGB,
+    direct: RGB,
+}
+
+/// A photon landing on a surface.
+#[derive(Debug, Clone, Copy)]
+struct Photon {
+    // Pointing back the way the photon came
+    wi: Unit,
+    power: RGB,
+}
+
+impl<'a> Sppm<'a> {
+    /// Photon map `scene`, lit by `lights`.
+    pub fn new(scene: &'a Scene, lights: Vec<SpotLight>) -> Self {
+        Self {
+            scene,
+            lights,
+            background: Environment::default(),
+            photons: 100_000,
+            radius: 0.1,
+            alpha: 2.0 / 3.0,
+            max_depth: 8,
+            roulette: Roulette::default(),
+            seed: 0,
+        }
+    }
+
+    /// Set the environment seen by rays that escape. Defaults to black.
+    pub fn background(mut self, background: impl Into<Environment>) -> Self {
+        self.background = background.into();
+        self
+    }
+
+    /// Set the number of photons shot each pass. Defaults to `100_000`.
+    pub fn photons(mut self, photons: usize) -> Self {
+        self.photons = photons;
+        self
+    }
+
+    /// Set the starting gather radius, in world units. Defaults to `0.1`.
+    ///
+    /// Too small, and few photons land in it, so the first passes are
+    /// noisy; too big, and light is blurred across edges until the radius
+    /// has shrunk.
+    pub fn radius(mut self, radius: Float) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Set the fraction of each pass's photons that are kept, between `0`
+    /// and `1`, which sets how fast the radius shrinks. Lower shrinks it
+    /// faster, trading noise for blur. Defaults to `2/3`.
+    pub fn alpha(mut self, alpha: Float) -> Self {
+        self.alpha = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the most bounces a camera path or photon takes. Defaults to `8`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set the Russian roulette for photons.
+    pub fn roulette(mut self, roulette: Roulette) -> Self {
+        self.roulette = roulette;
+        self
+    }
+
+    /// Set the seed. The same seed, settings and pass count always give the
+    /// same image.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Render `passes` passes into `img`, overwriting it.
+    pub fn render(&self, img: &mut Buffer<RGB>, cam: &impl Camera, passes: u32) {
+        let (width, _) = img.dimensions();
+        let mut states = vec![
+            PixelState {
+                radius: self.radius,
+                photons: 0.0,
+                flux: RGB::default(),
+                direct: RGB::default(),
+            };
+            img.len()
+        ];
+
+        for pass in 0..passes {
+            let max_radius = states.iter().map(|s| s.radius).fold(0.0, Float::max);
+            let photons = self.shoot(pass);
+            let grid = HashGrid::new(photons, max_radius.max(Float::EPSILON));
+
+            states.par_iter_mut().enumerate().for_each(|(idx, state)| {
+                let (px, py) = (idx as u32 % width, idx as u32 / width);
+                let keys = [self.seed, pass as u64, px as u64, py as u64];
+                let mut rng = StdRng::seed_from_u64(hash_keys(&keys));
+                self.gather(state, &cam.ray(px, py, &mut rng), &grid, &mut rng);
+            });
+        }
+
+        let shot = (passes as usize * self.photons) as Float;
+        for (pixel, state) in img.iter_mut().zip(&states) {
+            let area = PI * state.radius * state.radius;
+            let indirect = match shot > 0.0 && area > 0.0 {
+                true => state.flux / (shot * area),
+                false => RGB::default(),
+            };
+            *pixel = state.direct / passes.max(1) as Float + indirect;
+        }
+    }
+
+    // Trace a camera path to its visible point, and gather light there
+    fn gather(
+        &self,
+        state: &mut PixelState,
+        ray: &Ray,
+        grid: &HashGrid<Photon>,
+        rng: &mut impl Rng,
+    ) {
+        let mut ray = Ray::new(ray.origin, ray.direction);
+        let mut beta = RGB::splat(1.0);
+        for depth in 0..self.max_depth {
+            let Some(hit) = self.scene.intersect(&ray, 0.0, Float::INFINITY) else {
+                state.direct += beta * self.background.radiance(ray.direction);
+                return;
+            };
+            let Ok(wo) = Unit::try_from(-ray.direction) else {
+                return;
+            };
+            let material = hit.material.for_ray(RayType::from_depth(depth));
+
+            if !is_specular_only(material) {
+                state.direct += beta * self.direct(material, wo, &hit, rng);
+                self.update(state, beta, material, wo, &hit.isect, grid);
+                return;
+            }
+
+            let Some(s) = material.sample_f(wo, &hit.isect, rng) else {
+                return;
+            };
+            beta *= s.f * (s.wi.dot(hit.isect.norm).abs() / s.pdf);
+            if beta.is_black() {
+                return;
+            }
+            ray = hit.spawn(s.wi.into());
+        }
+    }
+
+    // Light arriving straight from the lights, treating every surface as
+    // opaque since light refracted by glass comes from the photons
+    fn direct(
+        &self,
+        material: &Material,
+        wo: Unit,
+        hit: &SurfaceInteraction,
+        rng: &mut impl Rng,
+    ) -> RGB {
+        let isect = &hit.isect;
+        let mut total = RGB::default();
+        for light in &self.lights {
+            let Some(s) = light.sample(isect.point, rng.gen()) else {
+                continue;
+            };
+            let f = material.f(wo, s.wi, isect);
+            if f.is_black() {
+                continue;
+            }
+            let shadow = hit.spawn(s.wi.into());
+            let dist = (light.position() - shadow.origin).len();
+            if !self.scene.occluded(&shadow, 0.0, dist * (1.0 - 1e-4)) {
+                total += f * s.li * (s.wi.dot(isect.norm).abs() / s.pdf);
+            }
+        }
+        total
+    }
+
+    // Fold this pass's photons into a pixel, shrinking its radius
+    fn update(
+        &self,
+        state: &mut PixelState,
+        beta: RGB,
+        material: &Material,
+        wo: Unit,
+        isect: &Intersection,
+        grid: &HashGrid<Photon>,
+    ) {
+        let mut flux = RGB::default();
+        let mut found = 0.0;
+        grid.query(isect.point, state.radius, |_, photon| {
+            flux += photon.power * material.f(wo, photon.wi, isect);
+            found += 1.0;
+        });
+        if found == 0.0 {
+            return;
+        }
+        let photons = state.photons + self.alpha * found;
+        let radius = state.radius * (photons / (state.photons + found)).sqrt();
+        let shrink = (radius * radius) / (state.radius * state.radius);
+        state.flux = (state.flux + beta * flux) * shrink;
+        state.photons = photons;
+        state.radius = radius;
+    }
+
+    // Shoot a pass's photons, returning where they land
+    fn shoot(&self, pass: u32) -> Vec<(Point, Photon)> {
+        let powers: Vec<Float> = self
+            .lights
+            .iter()
+            .map(|l| l.power().max_component())
+            .collect();
+        let total: Float = powers.iter().sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+
+        (0..self.photons)
+            .into_par_iter()
+            .flat_map_iter(|i| {
+                let keys = [self.seed, pass as u64, i as u64, PHOTON_STREAM];
+                let mut rng = StdRng::seed_from_u64(hash_keys(&keys));
+
+                // Pick a light in proportion to its power
+                let mut pick = rng.gen::<Float>() * total;
+                let idx = powers
+                    .iter()
+                    .position(|&p| {
+                        pick -= p;
+                        pick < 0.0
+                    })
+                    .unwrap_or(powers.len() - 1);
+                let pdf = powers[idx] / total;
+                let emitted = self.lights[idx].sample_emission(rng.gen());
+                let path = emitted.map(|e| (e.ray, e.weight / pdf));
+                self.trace_photon(path, &mut rng)
+            })
+            .collect()
+    }
+
+    // Follow a photon through the scene, recording where it lands
+    fn trace_photon(&self, path: Option<(Ray, RGB)>, rng: &mut impl Rng) -> Vec<(Point, Photon)> {
+        let mut landed = Vec::new();
+        let Some((mut ray, emitted)) = path else {
+            return landed;
+        };
+        let mut throughput = RGB::splat(1.0);
+        for depth in 0..self.max_depth {
+            let Some(hit) = self.scene.intersect(&ray, 0.0, Float::INFINITY) else {
+                break;
+            };
+            let Ok(wo) = Unit::try_from(-ray.direction) else {
+                break;
+            };
+            let material = hit.material.for_ray(RayType::Indirect);
+
+            // Direct light is found with shadow rays instead
+            if depth > 0 && !is_specular_only(material) {
+                let power = emitted * throughput;
+                landed.push((hit.isect.point, Photon { wi: wo, power }));
+            }
+
+            let Some(s) = material.sample_f(wo, &hit.isect, rng) else {
+                break;
+            };
+            throughput *= s.f * (s.wi.dot(hit.isect.norm).abs() / s.pdf);
+            if throughput.is_black() || !self.roulette.survive(&mut throughput, depth, rng) {
+                break;
+            }
+            ray = hit.spawn(s.wi.into());
+        }
+        landed
+    }
+}
+
+// Whether the material only has delta lobes, which can't gather photons
+#[inline]
+pub(super) fn is_specular_only(material: &Material) -> bool {
+    let lobes = material.lobes();
+    !lobes.contains(BSDFFlags::DIFFUSE) && !lobes.contains(BSDFFlags::GLOSSY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::ThinLens,
+        geo::Degrees,
+        material::{Dielectric, Lambertian},
+        shape::Triangle,
+    };
+
+    // A triangle at height `y` covering the spot light's cone, facing up or
+    // down. Kept off `y = 0`, where spawned rays would barely move.
+    fn plane(y: Float, up: bool) -> Triangle {
+        let (a, b) = ([0.0, y, 10.0], [10.0, y, -10.0]);
+        match up {
+            true => Triangle::new([-10.0, y, -10.0], a, b),
+            false => Triangle::new([-10.0, y, -10.0], b, a),
+        }
+    }
+
+    fn render(scene: &Scene) -> Float {
+        let light =
+            SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0)).falloff(Degrees(0.0));
+        let mut img = Buffer::new(4, 4);
+        let cam = ThinLens::builder(img.dimensions())
+            .move_to([0.0, 1.5, -0.5])
+            .look_at([0.0, 1.0, 0.0])
+            .fov(Degrees(5.0))
+            .build();
+        Sppm::new(scene, vec![light])
+            .photons(20_000)
+            .radius(0.2)
+            .seed(3)
+            .render(&mut img, &cam, 4);
+        img.iter().map(|c| c.max_component()).sum::<Float>() / img.len() as Float
+    }
+
+    #[test]
+    fn direct_and_caustic() {
+        // Straight below the light, a diffuse floor sees `I / d^2` directly
+        let mut scene = Scene::default();
+        scene.add_primitive(plane(1.0, true), Lambertian::new(RGB::splat(0.5)));
+        let expected = 0.5 / PI * 10.0 / 16.0;
+        let direct = render(&scene);
+        assert!((direct - expected).abs() < 1e-3 * expected, "{}", direct);
+
+        // Through a glass slab the shadow ray is blocked, so it all comes
+        // from photons, less what the slab's faces reflect
+        scene.add_primitive(plane(3.1, true), Dielectric::new(1.5));
+        scene.add_primitive(plane(3.0, false), Dielectric::new(1.5));
+        let caustic = render(&scene);
+        let expected = expected * 0.96 * 0.96;
+        assert!((caustic - expected).abs() < 0.05 * expected, "{}", caustic);
+    }
+}
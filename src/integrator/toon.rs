@@ -0,0 +1,228 @@
+use super::Integrator;
+use crate::{
+    color::RGB,
+    geo::{Frame, Radians, Ray, Unit, Vector},
+    light::Environment,
+    material::BSDF,
+    scene::Scene,
+    shape::Intersection,
+    Float,
+};
+use rand::Rng;
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// A non-photorealistic "cel shading" integrator.
+///
+/// Surfaces are lit by a single directional light, with the diffuse shading
+/// snapped to a few flat bands of color as in hand-drawn animation, and
+/// outlined in ink where the depth or normal jumps: around silhouettes,
+/// where one object passes in front of another, and along sharp creases.
+///
+/// Each surface's color is its material's reflectance straight on, so it
+/// renders any [`Scene`] without extra setup. There are no bounces, so it's
+/// fast enough for previews.
+///
+/// Outlines are found by tracing four extra rays around each camera ray,
+/// `outline_width` radians away, and comparing what they hit. Like
+/// [`Wireframe`], the width is angular, so lines keep the same width on
+/// screen at any depth, and averaging samples antialiases them.
+///
+/// [`Wireframe`]: crate::aov::Wireframe
+pub struct Toon<'a> {
+    scene: &'a Scene,
+    light: Unit,
+    bands: u32,
+    ambient: Float,
+    shadows: bool,
+    background: Environment,
+    outline_width: Float,
+    outline_color: RGB,
+    crease_cos: Float,
+}
+
+impl<'a> Toon<'a> {
+    /// Shade `scene` lit from the direction `light` (pointing from the
+    /// surfaces towards the light), with three bands and thin black lines.
+    pub fn new(scene: &'a Scene, light: Unit) -> Self {
+        Self {
+            scene,
+            light,
+            bands: 3,
+            ambient: 0.2,
+            shadows: true,
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            outline_width: 1e-3,
+            outline_color: RGB::default(),
+            crease_cos: 0.5,
+        }
+    }
+
+    /// Set the number of shading bands, from unlit to fully lit. Defaults to
+    /// `3`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bands` is less than `2`.
+    pub fn bands(mut self, bands: u32) -> Self {
+        assert!(bands >= 2, "Need at least two bands, got {}", bands);
+        self.bands = bands;
+        self
+    }
+
+    /// Set how bright the unlit band is, relative to fully lit. Defaults to
+    /// `0.2`.
+    pub fn ambient(mut self, ambient: Float) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    /// Set whether surfaces cast shadows, which fall in the unlit band.
+    /// Defaults to `true`.
+    pub fn shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Set what rays that miss everything see, such as a color or a
+    /// [`Sky`]. Defaults to white.
+    ///
+    /// [`Sky`]: crate::light::Sky
+    pub fn background(mut self, background: impl Into<Environment>) -> Self {
+        self.background = background.into();
+        self
+    }
+
+    /// Set the outline width, in radians, and color. A width of `0` turns
+    /// outlines off. Defaults to `1e-3` and black.
+    pub fn outline(mut self, width: Float, color: RGB) -> Self {
+        self.outline_width = width;
+        self.outline_color = color;
+        self
+    }
+
+    /// Set the smallest angle between neighbouring normals that's outlined
+    /// as a crease. Defaults to 60 degrees.
+    pub fn crease_angle(mut self, angle: impl Into<Radians>) -> Self {
+        self.crease_cos = angle.into().cos();
+        self
+    }
+
+    // The nearest hit, facing back along the ray
+    #[inline]
+    fn hit(&self, ray: &Ray) -> Option<Intersection> {
+        let hit = self.scene.intersect(ray, 0.0, Float::INFINITY)?;
+        Some(hit.isect.facing(ray.direction))
+    }
+
+    /// Whether an outline passes through the ray's first hit.
+    pub fn is_outline(&self, ray: &Ray) -> bool {
+        if self.outline_width <= 0.0 {
+            return false;
+        }
+        let center = self.hit(ray);
+        let frame = Frame::from_normal(ray.direction.normalize());
+        let (x, y) = (Vector::from(frame.x()), Vector::from(frame.y()));
+        let len = ray.direction.len();
+        let offset = self.outline_width * len;
+
+        [x, -x, y, -y].into_iter().any(|side| {
+            let probe = Ray::new(ray.origin, ray.direction + side * offset);
+            match (center, self.hit(&probe)) {
+                (None, None) => false,
+                (Some(a), Some(b)) => {
+                    let (da, db) = (a.t * len, b.t * probe.direction.len());
+                    let depth_jump = (da - db).abs() > 0.05 * da.min(db);
+                    depth_jump || a.norm.dot(b.norm) < self.crease_cos
+                }
+                _ => true,
+            }
+        })
+    }
+
+    // The banded diffuse brightness at a hit
+    fn shade(&self, isect: &Intersection) -> Float {
+        let cos = isect.norm.dot(self.light).max(0.0);
+        let lit = match self.shadows && cos > 0.0 {
+            true => {
+                let ray = Ray::spawn(isect.point, self.light.into(), isect.norm);
+                self.scene.intersect(&ray, 0.0, Float::INFINITY).is_none()
+            }
+            false => true,
+        };
+        let top = (self.bands - 1) as Float;
+        let band = match lit {
+            true => (cos * self.bands as Float).floor().min(top),
+            false => 0.0,
+        };
+        self.ambient + (1.0 - self.ambient) * band / top
+    }
+}
+
+impl Integrator<RGB> for Toon<'_> {
+    fn radiance(&self, ray: &Ray, _rng: &mut impl Rng) -> RGB {
+        if self.is_outline(ray) {
+            return self.outline_color;
+        }
+        let Some(hit) = self.scene.intersect(ray, 0.0, Float::INFINITY) else {
+            return self.background.radiance(ray.direction);
+        };
+        let isect = hit.isect.facing(ray.direction);
+        let color = hit.material.f(isect.norm, isect.norm, &isect) * PI;
+        color * self.shade(&isect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::Point,
+        material::Lambertian,
+        shape::{Sphere, Triangle},
+    };
+    use rand::prelude::*;
+
+    fn scene() -> Scene {
+        let mut scene = Scene::default();
+        let red = Lambertian::new(RGB::from([0.8, 0.2, 0.2]));
+        scene.add_primitive(Sphere::new([0.0, 0.0, 0.0], 1.0), red);
+        let grey = Lambertian::new(RGB::from([0.5, 0.5, 0.5]));
+        let floor = Triangle::new([-50.0, -1.0, -50.0], [50.0, -1.0, -50.0], [0.0, -1.0, 50.0]);
+        scene.add_primitive(floor, grey);
+        scene
+    }
+
+    #[test]
+    fn cel_shading() {
+        let scene = scene();
+        let toon = Toon::new(&scene, Unit::Y_AXIS).outline(0.01, RGB::default());
+        let mut rng = StdRng::seed_from_u64(3);
+        let ray = |x: Float, y: Float| Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(x, y, -1.0));
+        let mut shade = |ray: &Ray| <[Float; 3]>::from(toon.radiance(ray, &mut rng));
+
+        // The top of the sphere faces the light, and is its full color
+        let top = shade(&ray(0.0, 0.18));
+        assert!((top[0] - 0.8).abs() < 1e-9, "{:?}", top);
+
+        // Its middle is lit at a grazing angle, so in the unlit band
+        let side = shade(&ray(0.0, 0.0));
+        assert!((side[0] - 0.8 * 0.2).abs() < 1e-9, "{:?}", side);
+
+        // The floor is fully lit, except in the sphere's shadow
+        let sunny = Ray::new(Point::new(10.0, 5.0, 0.0), -Vector::Y_AXIS);
+        assert!((shade(&sunny)[0] - 0.5).abs() < 1e-9);
+        let under = Ray::new(Point::new(3.0, -0.9, 0.0), Vector::new(-2.5, -0.1, 0.0));
+        let shadowed = toon.hit(&under).unwrap();
+        assert!((toon.shade(&shadowed) - 0.2).abs() < 1e-9);
+
+        // Silhouette and background
+        assert!(toon.is_outline(&ray(0.2045, 0.0)));
+        assert!(!toon.is_outline(&ray(0.0, 0.18)));
+        assert!(!toon.is_outline(&ray(1.0, 1.0)));
+        assert_eq!([1.0; 3], shade(&ray(1.0, 1.0)));
+
+        let toon = toon.shadows(false);
+        assert_eq!(1.0, toon.shade(&shadowed));
+    }
+}
@@ -1,18 +1,45 @@
 //! # Gremlin
 //!
 //! Gremlin is a ray tracer
+//!
+//! Most programs start with `use gremlin::prelude::*`, which brings in the
+//! types and traits nearly everything needs; see [`prelude`].
+//!
+//! Every module is public on purpose. Besides the rendering pipeline itself,
+//! a few supporting modules are public because their types turn up in the
+//! rest of the API: [`math`] (spectra, probes and deterministic renders use
+//! its types), [`metrics`] (the counters integrators report through, such as
+//! [`TRUNCATED_PATHS`]), [`sampler`] (what [`Camera::ray_sampled`] and
+//! [`Integrator::radiance_sampled`] draw from) and [`probe`] (baked by the
+//! `gremlin` binary for game engines to load). Their internals, such as the SIMD lanes behind packet tests and
+//! photon mapping's hash grid, are private to the crate.
+//!
+//! [`TRUNCATED_PATHS`]: integrator::TRUNCATED_PATHS
+//! [`Camera::ray_sampled`]: camera::Camera::ray_sampled
+//! [`Integrator::radiance_sampled`]: integrator::Integrator::radiance_sampled
 
+pub mod aov;
 pub mod camera;
 pub mod color;
+pub mod compare;
 pub mod film;
 pub mod geo;
 pub mod integrator;
+pub mod light;
 pub mod material;
+pub mod math;
 pub mod metrics;
 pub mod prelude;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod probe;
+pub mod renderer;
+pub mod sampler;
 pub mod scene;
 pub mod shape;
 pub mod spectrum;
+pub mod texture;
+pub mod tonemap;
 
 use camera::Camera;
 use color::Color;
@@ -48,6 +75,12 @@ pub type Float = f32;
 #[cfg(not(feature = "f32"))]
 pub type Float = f64;
 
+/// Render one sample per pixel into `film`, on all cores.
+///
+/// The simplest way to drive an integrator. See [`Renderer`] for more
+/// samples, tiling, progress reporting and reproducible seeds.
+///
+/// [`Renderer`]: renderer::Renderer
 pub fn render<CS, Li>(film: &mut Film<CS>, cam: &impl Camera, integrator: &impl Integrator<Li>)
 where
     Color<CS>: From<Li> + Copy + Send,
@@ -57,5 +90,6 @@ where
         .for_each_init(rand::thread_rng, |rng, (px, py, pixel)| {
             let ray = cam.ray(px, py, rng);
             let rad = integrator.radiance(&ray, rng);
+            pixel.add_sample(rad);
         });
 }
@@ -0,0 +1,23 @@
+//! # Lights.
+//!
+//! Light sources, such as the [`Environment`] seen by rays that miss the
+//! scene and [`SpotLight`]s, and building blocks for sampling them directly,
+//! for integrators that do next-event estimation.
+
+mod environment;
+pub use environment::*;
+
+mod ies;
+pub use ies::*;
+
+mod portal;
+pub use portal::*;
+
+mod sample;
+pub use sample::*;
+
+mod sky;
+pub use sky::*;
+
+mod spot;
+pub use spot::*;
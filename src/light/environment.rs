@@ -0,0 +1,72 @@
+use super::{Portals, Sky};
+use crate::{color::RGB, geo::Vector};
+
+/// The light arriving from infinitely far away, seen by rays that miss
+/// everything in the scene.
+///
+/// An interior lit through its windows should be given [`Portals`] over
+/// them, with [`with_portals`], so integrators that sample the environment
+/// directly aim for the openings rather than the walls.
+///
+/// [`with_portals`]: Self::with_portals
+#[derive(Debug, Clone, PartialEq)]
+pub enum Environment {
+    /// The same color in every direction.
+    Constant(RGB),
+    /// A daylight sky.
+    Sky(Box<Sky>),
+    /// Another environment, seen through portals.
+    Portaled(Box<Environment>, Portals),
+}
+
+impl Default for Environment {
+    /// A black environment, so only the scene's own lights light it.
+    fn default() -> Self {
+        Self::Constant(RGB::default())
+    }
+}
+
+impl Environment {
+    /// The radiance arriving from direction `w`, which needn't be
+    /// normalized.
+    #[inline]
+    pub fn radiance(&self, w: Vector) -> RGB {
+        match self {
+            Self::Constant(rgb) => *rgb,
+            Self::Sky(sky) => sky.radiance(w),
+            Self::Portaled(env, _) => env.radiance(w),
+        }
+    }
+
+    /// The same environment, seen through `portals`, replacing any it
+    /// already had. See [`Portal`].
+    ///
+    /// [`Portal`]: super::Portal
+    pub fn with_portals(self, portals: Portals) -> Self {
+        match self {
+            Self::Portaled(env, _) => Self::Portaled(env, portals),
+            env => Self::Portaled(Box::new(env), portals),
+        }
+    }
+
+    /// The portals the environment is seen through, if any.
+    #[inline]
+    pub fn portals(&self) -> Option<&Portals> {
+        match self {
+            Self::Portaled(_, portals) if !portals.0.is_empty() => Some(portals),
+            _ => None,
+        }
+    }
+}
+
+impl From<RGB> for Environment {
+    fn from(rgb: RGB) -> Self {
+        Self::Constant(rgb)
+    }
+}
+
+impl From<Sky> for Environment {
+    fn from(sky: Sky) -> Self {
+        Self::Sky(Box::new(sky))
+    }
+}
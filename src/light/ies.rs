@@ -0,0 +1,270 @@
+use crate::Float;
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// Errors that can occur while loading an IES photometric profile.
+#[derive(Debug)]
+pub enum IesLoadError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file isn't a valid profile.
+    Parse(&'static str),
+}
+
+impl fmt::Display for IesLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read IES profile: {}", e),
+            Self::Parse(msg) => write!(f, "invalid IES profile: {}", msg),
+        }
+    }
+}
+
+impl Error for IesLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for IesLoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A measured light distribution, from an IES LM-63 photometric file.
+///
+/// Manufacturers publish these for real fixtures, giving the luminous
+/// intensity (in candela) over a grid of directions. Vertical angles are
+/// measured from the fixture's nadir, the direction it points, and
+/// horizontal angles around it. Only type C photometry, which nearly every
+/// architectural fixture uses, is supported. Tilt data is ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    // In degrees, both increasing
+    vertical: Vec<Float>,
+    horizontal: Vec<Float>,
+    // One row of vertical samples per horizontal angle
+    candela: Vec<Float>,
+    max: Float,
+}
+
+impl IesProfile {
+    /// Parse a profile from the contents of an `.ies` file.
+    pub fn parse(data: &str) -> Result<Self, IesLoadError> {
+        // Everything up to the `TILT=` line is header and keywords
+        let mut lines = data.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find_map(|line| line.strip_prefix("TILT="))
+            .ok_or(IesLoadError::Parse("missing TILT line"))?;
+        let rest: Vec<&str> = lines.collect();
+        let mut numbers = rest
+            .iter()
+            .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<Float>()
+                    .map_err(|_| IesLoadError::Parse("expected a number"))
+            });
+        let mut next = || {
+            numbers
+                .next()
+                .unwrap_or(Err(IesLoadError::Parse("truncated")))
+        };
+
+        if tilt.trim() == "INCLUDE" {
+            // Lamp geometry, then paired angles and multiplying factors
+            next()?;
+            let pairs = count(next()?)?;
+            let values = pairs
+                .checked_mul(2)
+                .ok_or(IesLoadError::Parse("too many tilt angles"))?;
+            for _ in 0..values {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let n_vertical = count(next()?)?;
+        let n_horizontal = count(next()?)?;
+        if next()? != 1.0 {
+            return Err(IesLoadError::Parse("only type C photometry is supported"));
+        }
+        // Units, dimensions, ballast factor, reserved and input watts
+        for _ in 0..7 {
+            next()?;
+        }
+        if n_vertical == 0 || n_horizontal == 0 {
+            return Err(IesLoadError::Parse("no angles"));
+        }
+        let n_candela = n_vertical
+            .checked_mul(n_horizontal)
+            .ok_or(IesLoadError::Parse("too many angles"))?;
+
+        let mut read = |n: usize| (0..n).map(|_| next()).collect::<Result<Vec<_>, _>>();
+        let vertical = read(n_vertical)?;
+        let horizontal = read(n_horizontal)?;
+        let candela: Vec<Float> = read(n_candela)?
+            .into_iter()
+            .map(|c| c * multiplier)
+            .collect();
+        let increasing = |a: &[Float]| a.windows(2).all(|w| w[0] < w[1]);
+        if !increasing(&vertical) || !increasing(&horizontal) {
+            return Err(IesLoadError::Parse("angles must be increasing"));
+        }
+
+        let max = candela.iter().copied().fold(0.0, Float::max);
+        Ok(Self {
+            vertical,
+            horizontal,
+            candela,
+            max,
+        })
+    }
+
+    /// Load a profile from an `.ies` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IesLoadError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The brightest intensity in the profile, in candela.
+    #[inline]
+    pub fn max_candela(&self) -> Float {
+        self.max
+    }
+
+    /// The intensity in candela at `theta` radians from the nadir and `phi`
+    /// radians around it, interpolated between the measured angles.
+    /// Directions beyond the measured vertical range are dark.
+    pub fn candela(&self, theta: Float, phi: Float) -> Float {
+        let theta = theta.to_degrees();
+        if theta < self.vertical[0] || theta > *self.vertical.last().unwrap() {
+            return 0.0;
+        }
+
+        // Profiles only cover as much of the circle as their symmetry needs
+        let mut phi = phi.to_degrees().rem_euclid(360.0);
+        let last = *self.horizontal.last().unwrap();
+        if last <= 90.0 && phi > 180.0 {
+            phi = 360.0 - phi;
+        }
+        if last <= 90.0 && phi > 90.0 {
+            phi = 180.0 - phi;
+        }
+        if last <= 180.0 && phi > 180.0 {
+            phi = 360.0 - phi;
+        }
+
+        let (h, t) = segment(&self.horizontal, phi);
+        match t > 0.0 {
+            true => self.lookup(h, theta) * (1.0 - t) + self.lookup(h + 1, theta) * t,
+            false => self.lookup(h, theta),
+        }
+    }
+
+    /// The intensity relative to the brightest direction, from `0` to `1`.
+    #[inline]
+    pub fn relative(&self, theta: Float, phi: Float) -> Float {
+        match self.max > 0.0 {
+            true => self.candela(theta, phi) / self.max,
+            false => 0.0,
+        }
+    }
+
+    // Interpolate along the vertical angles of one horizontal row
+    fn lookup(&self, h: usize, theta: Float) -> Float {
+        let row = &self.candela[h * self.vertical.len()..][..self.vertical.len()];
+        let (v, t) = segment(&self.vertical, theta);
+        match t > 0.0 {
+            true => row[v] * (1.0 - t) + row[v + 1] * t,
+            false => row[v],
+        }
+    }
+}
+
+// A non-negative whole number, as the counts in the file
+fn count(n: Float) -> Result<usize, IesLoadError> {
+    match n >= 0.0 && n.fract() == 0.0 {
+        true => Ok(n as usize),
+        false => Err(IesLoadError::Parse("expected a count")),
+    }
+}
+
+// The segment of the increasing `xs` containing `x`, and how far along it
+// `x` is, clamped to the ends
+fn segment(xs: &[Float], x: Float) -> (usize, Float) {
+    let i = xs.partition_point(|&a| a <= x);
+    if i == 0 {
+        return (0, 0.0);
+    }
+    if i == xs.len() {
+        return (xs.len() - 1, 0.0);
+    }
+    (i - 1, (x - xs[i - 1]) / (xs[i] - xs[i - 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PI: Float = std::f64::consts::PI as Float;
+
+    // A downlight, brightest straight down and fading out by 90 degrees, and
+    // twice as bright along the 90 degree plane as along 0
+    const DOWNLIGHT: &str = "\
+IESNA:LM-63-2002
+[TEST] Downlight
+[MANUFAC] Nobody
+TILT=NONE
+1 1000 2 4 2 1 2 0.1 0.1 0.0
+1.0 1.0 20
+0 30 60 90
+0 90
+500 400 100 0
+1000 800 200 0
+";
+
+    #[test]
+    fn parse_and_interpolate() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        assert_eq!(2000.0, profile.max_candela());
+        assert_eq!(1000.0, profile.candela(0.0, 0.0));
+        assert_eq!(900.0, profile.candela(PI / 12.0, 0.0));
+
+        // Quadrant symmetry, so 90 and 270 match, as do 0 and 180
+        let close = |a: Float, b: Float| (a - b).abs() < 1e-6;
+        assert!(close(2000.0, profile.candela(0.0, PI / 2.0)));
+        assert!(close(2000.0, profile.candela(0.0, 1.5 * PI)));
+        assert!(close(1000.0, profile.candela(0.0, PI)));
+        assert!(close(1500.0, profile.candela(0.0, PI / 4.0)));
+        assert!(close(0.5, profile.relative(0.0, 0.0)));
+
+        // Beyond the measured range
+        assert_eq!(0.0, profile.candela(PI / 2.0, 0.0));
+        assert_eq!(0.0, profile.candela(PI, 0.0));
+
+        // Tilt data is skipped
+        let tilted = DOWNLIGHT.replace("TILT=NONE", "TILT=INCLUDE\n1\n2\n0 90\n1 1");
+        assert_eq!(profile, IesProfile::parse(&tilted).unwrap());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |data: &str| IesProfile::parse(data).unwrap_err().to_string();
+        assert!(err("IESNA:LM-63-2002\n1 2 3").contains("TILT"));
+        assert!(err(&DOWNLIGHT.replace("0 0\n1000", "0\n")).contains("truncated"));
+        assert!(err(&DOWNLIGHT.replace("0.1 0.1", "0.1 x")).contains("number"));
+        assert!(err(&DOWNLIGHT.replace(" 2 1 2 ", " 2 2 2 ")).contains("type C"));
+        assert!(err(&DOWNLIGHT.replace("0 30 60", "0 60 30")).contains("increasing"));
+        let tilted = DOWNLIGHT.replace("TILT=NONE", "TILT=INCLUDE\n1\n1e300\n");
+        assert!(err(&tilted).contains("too many tilt angles"));
+        let huge = DOWNLIGHT.replace(" 2 4 2 1 ", " 2 1e300 1e300 1 ");
+        assert!(err(&huge).contains("too many angles"));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    geo::{Point, Ray, Unit, Vector},
+    Float,
+};
+
+/// An opening, such as a window or doorway, that light from the environment
+/// comes through.
+///
+/// In an interior lit from outside, most directions sampled from the whole
+/// environment hit a wall, so their light samples are wasted. Portals fix
+/// this by only sampling directions through the openings. They're hints
+/// rather than geometry: a portal isn't rendered, and light through
+/// openings without one is still found by bounce rays. Attach them to the
+/// [`Environment`] with [`Environment::with_portals`].
+///
+/// A portal is a parallelogram, with one `corner` and two `edges`. Its
+/// normal, the cross product of the edges, faces into the room. Only points
+/// on that side see the environment through it.
+///
+/// [`Environment`]: super::Environment
+/// [`Environment::with_portals`]: super::Environment::with_portals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Portal {
+    corner: Point,
+    edges: [Vector; 2],
+    normal: Unit,
+    area: Float,
+}
+
+/// A direction sampled through a [`Portal`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortalSample {
+    /// The direction from the shading point through the portal.
+    pub wi: Unit,
+    /// The solid-angle density with which `wi` was chosen.
+    pub pdf: Float,
+    /// The distance to the portal along `wi`. Shadow rays must reach past
+    /// it, into the environment.
+    pub dist: Float,
+}
+
+impl Portal {
+    /// A portal with the given `corner`, spanning `edges`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the edges are parallel, or either is zero.
+    pub fn new(corner: impl Into<Point>, edges: [Vector; 2]) -> Self {
+        let cross = edges[0].cross(edges[1]);
+        let normal = Unit::try_from(cross).expect("Portal edges must span an area");
+        Self {
+            corner: corner.into(),
+            edges,
+            normal,
+            area: cross.len(),
+        }
+    }
+
+    /// The normal, facing into the room.
+    #[inline]
+    pub fn normal(&self) -> Unit {
+        self.normal
+    }
+
+    /// The portal's area.
+    #[inline]
+    pub fn area(&self) -> Float {
+        self.area
+    }
+
+    /// Sample a direction from `point` through the portal, given a uniform
+    /// sample `u` from the unit square.
+    ///
+    /// Points are chosen uniformly over the portal's area. Returns `None` if
+    /// `point` is on the outside, or in the portal's plane.
+    pub fn sample(&self, point: Point, u: [Float; 2]) -> Option<PortalSample> {
+        let target = self.corner + self.edges[0] * u[0] + self.edges[1] * u[1];
+        let d = target - point;
+        let dist = d.len();
+        let wi = Unit::try_from(d).ok()?;
+        let cos = -wi.dot(self.normal);
+        if cos <= 0.0 {
+            return None;
+        }
+        Some(PortalSample {
+            wi,
+            pdf: dist * dist / (self.area * cos),
+            dist,
+        })
+    }
+
+    /// The solid-angle density with which [`sample`] would choose `wi` from
+    /// `point`. Zero for directions that miss the portal.
+    ///
+    /// [`sample`]: Self::sample
+    pub fn pdf(&self, point: Point, wi: Unit) -> Float {
+        match self.hit(point, wi) {
+            Some(dist) => dist * dist / (self.area * -wi.dot(self.normal)),
+            None => 0.0,
+        }
+    }
+
+    /// The distance from `point` along `wi` to the portal, if the direction
+    /// passes through it from the inside.
+    pub fn hit(&self, point: Point, wi: Unit) -> Option<Float> {
+        let n = Vector::from(self.normal);
+        let speed = -Vector::from(wi).dot(n);
+        let height = (point - self.corner).dot(n);
+        if speed <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let dist = height / speed;
+        let p = Ray::new(point, wi.into()).at(dist) - self.corner;
+
+        // Coordinates along the edges, from the dual basis
+        let [e0, e1] = self.edges;
+        let along = |a: Vector, b: Vector| b.cross(n).dot(p) / b.cross(n).dot(a);
+        let (s, t) = (along(e0, e1), along(e1, e0));
+        ((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)).then_some(dist)
+    }
+
+    /// Roughly how much of the view from `point` the portal fills, as a
+    /// solid angle. Exact when the portal is small and far away. Zero from
+    /// the outside.
+    ///
+    /// Used to choose between portals; see [`Portals`].
+    pub fn visibility(&self, point: Point) -> Float {
+        let center = self.corner + (self.edges[0] + self.edges[1]) * 0.5;
+        let d = center - point;
+        let cos = -d.normalize().dot(self.normal);
+        match cos > 0.0 {
+            true => self.area * cos / d.len_squared(),
+            false => 0.0,
+        }
+    }
+}
+
+/// A set of portals, sampled together.
+///
+/// Each sample picks one portal in proportion to its [`visibility`] from the
+/// shading point, so nearby large windows get most of the samples and those
+/// facing away get none.
+///
+/// [`visibility`]: Portal::visibility
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Portals(pub Vec<Portal>);
+
+impl Portals {
+    /// Sample a direction from `point` through one of the portals, given a
+    /// uniform sample `u` from the unit cube.
+    ///
+    /// The density accounts for every portal the direction passes through,
+    /// so overlapping portals are fine.
+    pub fn sample(&self, point: Point, u: [Float; 3]) -> Option<PortalSample> {
+        let weights: Vec<Float> = self.0.iter().map(|p| p.visibility(point)).collect();
+        let total: Float = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut pick = u[0] * total;
+        let idx = weights
+            .iter()
+            .position(|&w| {
+                pick -= w;
+                pick < 0.0
+            })
+            .unwrap_or_else(|| weights.iter().rposition(|&w| w > 0.0).unwrap());
+        let sample = self.0[idx].sample(point, [u[1], u[2]])?;
+        Some(PortalSample {
+            pdf: self.pdf_with(point, sample.wi, &weights, total),
+            ..sample
+        })
+    }
+
+    /// The solid-angle density with which [`sample`] would choose `wi` from
+    /// `point`.
+    ///
+    /// [`sample`]: Self::sample
+    pub fn pdf(&self, point: Point, wi: Unit) -> Float {
+        let weights: Vec<Float> = self.0.iter().map(|p| p.visibility(point)).collect();
+        let total: Float = weights.iter().sum();
+        match total > 0.0 {
+            true => self.pdf_with(point, wi, &weights, total),
+            false => 0.0,
+        }
+    }
+
+    fn pdf_with(&self, point: Point, wi: Unit, weights: &[Float], total: Float) -> Float {
+        self.0
+            .iter()
+            .zip(weights)
+            .map(|(portal, &w)| w / total * portal.pdf(point, wi))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    // A 2x1 window in the wall at `z = 4`, facing back into the room
+    fn window() -> Portal {
+        Portal::new(
+            [-1.0, 0.0, 4.0],
+            [Vector::Y_AXIS, Vector::new(2.0, 0.0, 0.0)],
+        )
+    }
+
+    #[test]
+    fn sample_and_pdf() {
+        let portal = window();
+        assert_eq!(-Unit::Z_AXIS, portal.normal());
+        assert_eq!(2.0, portal.area());
+
+        let point = Point::new(0.0, 0.5, 0.0);
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut solid_angle = 0.0;
+        for _ in 0..20000 {
+            let s = portal.sample(point, rng.gen()).unwrap();
+            assert!((s.pdf - portal.pdf(point, s.wi)).abs() < 1e-9 * s.pdf);
+            assert!((portal.hit(point, s.wi).unwrap() - s.dist).abs() < 1e-9);
+            solid_angle += 1.0 / s.pdf;
+        }
+
+        // Estimates the portal's solid angle, for a rectangle seen
+        // head-on: 4 asin(ab / sqrt((a^2 + d^2)(b^2 + d^2)))
+        let solid_angle = solid_angle / 20000.0;
+        let exact = 4.0 * Float::asin(0.5 / Float::sqrt(17.0 * 16.25));
+        assert!(
+            (solid_angle - exact).abs() < 1e-3 * exact,
+            "{}",
+            solid_angle
+        );
+        assert!((portal.visibility(point) - exact).abs() < 0.05 * exact);
+
+        // Directions that miss, and points outside, get nothing
+        assert_eq!(0.0, portal.pdf(point, Unit::X_AXIS));
+        assert_eq!(0.0, portal.pdf(point, -Unit::Z_AXIS));
+        let outside = Point::new(0.0, 0.5, 6.0);
+        assert!(portal.sample(outside, [0.5, 0.5]).is_none());
+        assert_eq!(0.0, portal.visibility(outside));
+    }
+
+    #[test]
+    fn portal_set() {
+        // The same window, and one in the opposite wall
+        let behind = Portal::new(
+            [-1.0, 0.0, -4.0],
+            [Vector::new(2.0, 0.0, 0.0), Vector::Y_AXIS],
+        );
+        let portals = Portals(vec![window(), behind]);
+        let inside = Point::new(0.0, 0.5, 0.0);
+
+        // Past the first window, only the second one faces the point
+        let past = Point::new(0.0, 0.5, 5.0);
+        let s = portals.sample(past, [0.0, 0.5, 0.5]).unwrap();
+        assert!(s.wi.z() < 0.0);
+        assert_eq!(0.0, portals.pdf(past, Unit::Z_AXIS));
+        assert!(Portals::default().sample(inside, [0.5; 3]).is_none());
+
+        // From inside, both face the point, so each is sampled
+        let mut rng = StdRng::seed_from_u64(9);
+        let (mut front, mut back) = (0, 0);
+        for _ in 0..1000 {
+            let s = portals.sample(inside, rng.gen()).unwrap();
+            assert!((s.pdf - portals.pdf(inside, s.wi)).abs() < 1e-9 * s.pdf);
+            match s.wi.z() > 0.0 {
+                true => front += 1,
+                false => back += 1,
+            }
+        }
+        assert!(front > 400 && back > 400, "{} {}", front, back);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{
+    color::RGB,
+    geo::{Ray, Unit},
+    Float,
+};
+
+/// Light arriving at a shading point, sampled from a light source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// The direction from the shading point towards the light.
+    pub wi: Unit,
+    /// The radiance arriving along `wi`, if nothing is in the way.
+    pub li: RGB,
+    /// The solid-angle density with which `wi` was chosen, or `1` for
+    /// lights that are delta distributions.
+    pub pdf: Float,
+    /// The distance to the light along `wi`, for shadow rays.
+    pub dist: Float,
+}
+
+/// A ray of light leaving a light source, for tracing paths from the lights
+/// rather than the camera.
+#[derive(Debug)]
+pub struct EmissionSample {
+    /// The ray leaving the light.
+    pub ray: Ray,
+    /// The light carried along the ray, divided by the density with which it
+    /// was chosen: the power of the light, as far as this one sample can
+    /// tell.
+    pub weight: RGB,
+}
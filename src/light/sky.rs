@@ -0,0 +1,232 @@
+use crate::{
+    color::{RGB, XYZ},
+    geo::{Unit, Vector},
+    spectrum::{Sampled, SpectrumKind},
+    Float,
+};
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// A clear daytime sky, from the Preetham analytic model.
+///
+/// The sky's brightness and color follow from where the sun is and how hazy
+/// the air is, as measured by turbidity: `2` is a very clear day, `3` a
+/// typical one and `6` or more a hazy, washed out one. The model is fitted
+/// for turbidities from `2` to `10`, with the sun above the horizon.
+///
+/// Only the sky is modelled, not the sun's disk itself, so pair it with a
+/// light for direct sunlight. Directions below the horizon see the
+/// [`ground`] color.
+///
+/// See: Preetham, Shirley and Smits, "A Practical Analytic Model for
+/// Daylight" (1999).
+///
+/// [`ground`]: Self::ground
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    sun: Unit,
+    up: Unit,
+    turbidity: Float,
+    scale: Float,
+    ground: RGB,
+    // Luminance and chromaticity at the zenith, and the Perez distribution
+    // coefficients `A` to `E` for each, scaled so the distribution is `1` at
+    // the zenith
+    zenith: [Float; 3],
+    perez: [[Float; 5]; 3],
+    norm: [Float; 3],
+}
+
+impl Sky {
+    /// A sky lit by the sun in direction `sun` (pointing towards it), with
+    /// `+y` up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `turbidity` is below `1`, where the model breaks down.
+    pub fn new(sun: Unit, turbidity: Float) -> Self {
+        assert!(
+            turbidity >= 1.0,
+            "Turbidity must be at least 1, got {}",
+            turbidity
+        );
+        let mut sky = Self {
+            sun,
+            up: Unit::Y_AXIS,
+            turbidity,
+            scale: 0.1,
+            ground: RGB::default(),
+            zenith: [0.0; 3],
+            perez: [[0.0; 5]; 3],
+            norm: [0.0; 3],
+        };
+        sky.update();
+        sky
+    }
+
+    /// Set the up direction. Defaults to `+y`.
+    pub fn up(mut self, up: Unit) -> Self {
+        self.up = up;
+        self.update();
+        self
+    }
+
+    /// Set the factor from the model's units (thousands of candela per
+    /// square meter) to radiance. Defaults to `0.1`, so the zenith of a
+    /// clear sky with the sun well up is around `1`.
+    pub fn scale(mut self, scale: Float) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the radiance of directions below the horizon. Defaults to black.
+    pub fn ground(mut self, ground: RGB) -> Self {
+        self.ground = ground;
+        self
+    }
+
+    /// The direction towards the sun.
+    #[inline]
+    pub fn sun(&self) -> Unit {
+        self.sun
+    }
+
+    /// The sky's luminance and color in direction `w`, in CIE XYZ.
+    pub fn xyz(&self, w: Unit) -> Option<XYZ> {
+        let cos_theta = w.dot(self.up);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let gamma = w.dot(self.sun).clamp(-1.0, 1.0).acos();
+        let [lum, x, y] = [0, 1, 2]
+            .map(|i| self.zenith[i] * perez(&self.perez[i], cos_theta, gamma) / self.norm[i]);
+        if y <= 0.0 {
+            return Some(XYZ::default());
+        }
+        let lum = lum * self.scale;
+        Some(XYZ::from([x / y * lum, lum, (1.0 - x - y) / y * lum]))
+    }
+
+    /// The sky's radiance in direction `w`, which needn't be normalized.
+    pub fn radiance(&self, w: Vector) -> RGB {
+        let Ok(w) = Unit::try_from(w) else {
+            return self.ground;
+        };
+        match self.xyz(w) {
+            Some(xyz) => RGB::from(xyz),
+            None => self.ground,
+        }
+    }
+
+    /// The sky's radiance in direction `w` as a spectrum, for spectral
+    /// rendering.
+    pub fn spectrum(&self, w: Vector) -> Sampled {
+        Sampled::from_rgb(self.radiance(w), SpectrumKind::Illuminant)
+    }
+
+    // Recompute the model's coefficients for the sun and turbidity
+    fn update(&mut self) {
+        let t = self.turbidity;
+        let theta_s = self.sun.dot(self.up).clamp(-1.0, 1.0).acos().min(PI / 2.0);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let lum = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chroma = |m: [[Float; 4]; 3]| {
+            let th = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let row = |r: [Float; 4]| r.iter().zip(th).map(|(a, b)| a * b).sum::<Float>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let x = chroma([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chroma([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        self.zenith = [lum.max(0.0), x, y];
+
+        let coeffs = |c: [[Float; 2]; 5]| c.map(|[a, b]| a * t + b);
+        self.perez = [
+            coeffs([
+                [0.1787, -1.4630],
+                [-0.3554, 0.4275],
+                [-0.0227, 5.3251],
+                [0.1206, -2.5771],
+                [-0.0670, 0.3703],
+            ]),
+            coeffs([
+                [-0.0193, -0.2592],
+                [-0.0665, 0.0008],
+                [-0.0004, 0.2125],
+                [-0.0641, -0.8989],
+                [-0.0033, 0.0452],
+            ]),
+            coeffs([
+                [-0.0167, -0.2608],
+                [-0.0950, 0.0092],
+                [-0.0079, 0.2102],
+                [-0.0441, -1.6537],
+                [-0.0109, 0.0529],
+            ]),
+        ];
+        self.norm = self.perez.map(|p| perez(&p, 1.0, theta_s));
+    }
+}
+
+// The Perez sky distribution, for a view `theta` from the zenith and `gamma`
+// from the sun
+#[inline]
+fn perez([a, b, c, d, e]: &[Float; 5], cos_theta: Float, gamma: Float) -> Float {
+    // Clamped so the horizon doesn't blow up
+    let cos_theta = cos_theta.max(0.01);
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(x: Float, y: Float, z: Float) -> Unit {
+        Unit::try_from(Vector::new(x, y, z)).unwrap()
+    }
+
+    #[test]
+    fn daylight() {
+        let sky = Sky::new(unit(0.0, 1.0, 1.0), 3.0);
+
+        // A clear zenith, which is blue and about as bright as documented
+        let zenith = <[Float; 3]>::from(sky.radiance(Vector::Y_AXIS));
+        assert!(zenith[2] > zenith[0], "{:?}", zenith);
+        let lum = sky.xyz(Unit::Y_AXIS).unwrap().luminance();
+        assert!((0.5..1.5).contains(&lum), "{}", lum);
+
+        // Brighter near the sun and towards the horizon than opposite the
+        // sun, and the ground below
+        let luminance = |w: Unit| sky.xyz(w).unwrap().luminance();
+        assert!(luminance(unit(0.0, 0.8, 1.0)) > lum);
+        assert!(luminance(unit(0.0, 0.05, -1.0)) > luminance(unit(0.0, 1.0, -0.5)));
+        assert_eq!(RGB::default(), sky.radiance(-Vector::Y_AXIS));
+        let grass = RGB::from([0.1, 0.2, 0.05]);
+        assert_eq!(grass, sky.ground(grass).radiance(-Vector::Y_AXIS));
+
+        // Haze makes the sky whiter
+        let hazy = Sky::new(unit(0.0, 1.0, 1.0), 8.0)
+            .xyz(Unit::Y_AXIS)
+            .unwrap();
+        let blueness = |xyz: XYZ| {
+            let [r, _, b] = <[Float; 3]>::from(RGB::from(xyz));
+            b / r
+        };
+        assert!(blueness(hazy) < blueness(sky.xyz(Unit::Y_AXIS).unwrap()));
+
+        // Other up axes give the same sky, turned
+        let z_up = Sky::new(unit(0.0, 1.0, 1.0), 3.0).up(Unit::Z_AXIS);
+        let a = sky.radiance(Vector::new(0.3, 0.5, 0.2));
+        let b = z_up.radiance(Vector::new(0.3, 0.2, 0.5));
+        assert!((a.max_component() - b.max_component()).abs() < 1e-9);
+    }
+}
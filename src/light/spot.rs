@@ -0,0 +1,287 @@
+use super::{EmissionSample, IesProfile, LightSample};
+use crate::{
+    color::RGB,
+    geo::{Frame, Point, Radians, Ray, Unit, Vector},
+    Float,
+};
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// A point light shining in a cone, as used for product and stage lighting.
+///
+/// Full [`intensity`] is emitted within the inner part of the cone, fading
+/// smoothly to nothing over the [`falloff`] width at its edge. An optional
+/// [`profile`] shapes the light further with a fixture's measured
+/// distribution.
+///
+/// Being a point, the light can only be reached by sampling it: bounce rays
+/// never hit it. So [`sample`] returns a unit density, [`pdf`] is always
+/// zero, and multiple importance sampling should give light samples all the
+/// weight; see [`is_delta`].
+///
+/// [`intensity`]: Self::intensity
+/// [`falloff`]: Self::falloff
+/// [`profile`]: Self::profile
+/// [`sample`]: Self::sample
+/// [`pdf`]: Self::pdf
+/// [`is_delta`]: Self::is_delta
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotLight {
+    position: Point,
+    frame: Frame,
+    intensity: RGB,
+    cos_cone: Float,
+    cos_falloff: Float,
+    cone: Float,
+    falloff: Float,
+    profile: Option<IesProfile>,
+}
+
+impl SpotLight {
+    /// A spot light at `position` pointing along `direction`, with the given
+    /// radiant `intensity` on its axis. The cone defaults to `30°` either
+    /// side of the axis, the last `5°` of which fade out.
+    pub fn new(position: impl Into<Point>, direction: Unit, intensity: RGB) -> Self {
+        let mut light = Self {
+            position: position.into(),
+            frame: Frame::from_normal(direction),
+            intensity,
+            cos_cone: 0.0,
+            cos_falloff: 0.0,
+            cone: PI / 6.0,
+            falloff: PI / 36.0,
+            profile: None,
+        };
+        light.update();
+        light
+    }
+
+    /// Set the angle between the axis and the cone's edge, up to `180°` for
+    /// light in every direction.
+    pub fn cone(mut self, angle: impl Into<Radians>) -> Self {
+        self.cone = angle.into().0.clamp(0.0, PI);
+        self.update();
+        self
+    }
+
+    /// Set the width of the cone's soft edge. Zero gives a hard edge, and
+    /// the cone's angle a light that fades all the way from its axis.
+    pub fn falloff(mut self, angle: impl Into<Radians>) -> Self {
+        self.falloff = angle.into().0.max(0.0);
+        self.update();
+        self
+    }
+
+    /// Shape the light with a measured profile, aimed along the light's
+    /// axis. The profile is scaled so its brightest direction has the
+    /// light's [`intensity`], and still cut off by the cone, so widen the
+    /// cone to use the profile alone.
+    ///
+    /// The rotation of the profile's horizontal angles about the axis is
+    /// arbitrary, which only matters for asymmetric profiles.
+    ///
+    /// [`intensity`]: Self::intensity
+    pub fn profile(mut self, profile: IesProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// The light's position.
+    #[inline]
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    /// The direction the light points.
+    #[inline]
+    pub fn direction(&self) -> Unit {
+        self.frame.z()
+    }
+
+    /// Whether the light is a delta distribution, which bounce rays can
+    /// never hit. Always `true`.
+    #[inline]
+    pub fn is_delta(&self) -> bool {
+        true
+    }
+
+    /// The radiant intensity leaving the light in direction `w`.
+    pub fn intensity(&self, w: Unit) -> RGB {
+        let local = self.frame.to_local_unit(w);
+        let cos = local.z();
+        let mut scale = smoothstep(self.cos_cone, self.cos_falloff, cos);
+        if let Some(profile) = &self.profile {
+            if scale > 0.0 {
+                let theta = cos.clamp(-1.0, 1.0).acos();
+                scale *= profile.relative(theta, local.y().atan2(local.x()));
+            }
+        }
+        self.intensity * scale
+    }
+
+    /// Sample the light's contribution at `point`. The sample `u` is unused,
+    /// since there's only one direction to the light, but taken for
+    /// uniformity with other lights.
+    ///
+    /// Returns `None` if the light doesn't shine on `point`.
+    pub fn sample(&self, point: Point, _u: [Float; 2]) -> Option<LightSample> {
+        let d = self.position - point;
+        let dist = d.len();
+        let wi = Unit::try_from(d).ok()?;
+        let li = self.intensity(-wi) / (dist * dist);
+        if li.is_black() {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            li,
+            pdf: 1.0,
+            dist,
+        })
+    }
+
+    /// The solid-angle density with which [`sample`] would choose `wi` from
+    /// `point`: always zero, as no direction found any other way reaches
+    /// the light.
+    ///
+    /// [`sample`]: Self::sample
+    #[inline]
+    pub fn pdf(&self, _point: Point, _wi: Unit) -> Float {
+        0.0
+    }
+
+    /// Sample a ray leaving the light, given a uniform sample `u` from the
+    /// unit square, for shooting photons.
+    ///
+    /// Directions are chosen uniformly within the cone. Returns `None` if
+    /// the cone is empty, or the sampled direction carries no light.
+    pub fn sample_emission(&self, u: [Float; 2]) -> Option<EmissionSample> {
+        let solid_angle = 2.0 * PI * (1.0 - self.cos_cone);
+        if solid_angle <= 0.0 {
+            return None;
+        }
+        let cos = 1.0 - u[0] * (1.0 - self.cos_cone);
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let (sin_phi, cos_phi) = (2.0 * PI * u[1]).sin_cos();
+        let local = Vector::new(sin * cos_phi, sin * sin_phi, cos);
+        let w = Unit::try_from(self.frame.to_world(local)).ok()?;
+        let weight = self.intensity(w) * solid_angle;
+        if weight.is_black() {
+            return None;
+        }
+        Some(EmissionSample {
+            ray: Ray::new(self.position, w.into()),
+            weight,
+        })
+    }
+
+    /// The total power the light emits, for choosing between lights or
+    /// shooting photons.
+    pub fn power(&self) -> RGB {
+        let Some(profile) = &self.profile else {
+            // The smooth step averages a half over the soft edge
+            let solid =
+                2.0 * PI * ((1.0 - self.cos_falloff) + (self.cos_falloff - self.cos_cone) / 2.0);
+            return self.intensity * solid;
+        };
+
+        // Integrate the profile over the cone numerically
+        const THETA: usize = 256;
+        const PHI: usize = 64;
+        let d_theta = self.cone / THETA as Float;
+        let d_phi = 2.0 * PI / PHI as Float;
+        let mut total = 0.0;
+        for i in 0..THETA {
+            let theta = (i as Float + 0.5) * d_theta;
+            let falloff = smoothstep(self.cos_cone, self.cos_falloff, theta.cos());
+            let ring: Float = (0..PHI)
+                .map(|j| profile.relative(theta, (j as Float + 0.5) * d_phi))
+                .sum();
+            total += falloff * ring * theta.sin() * d_theta * d_phi;
+        }
+        self.intensity * total
+    }
+
+    fn update(&mut self) {
+        self.cos_cone = self.cone.cos();
+        self.cos_falloff = (self.cone - self.falloff).max(0.0).cos();
+    }
+}
+
+// Smoothly rises from `0` at `lo` to `1` at `hi`, or steps if they're equal
+#[inline]
+fn smoothstep(lo: Float, hi: Float, x: Float) -> Float {
+    if lo >= hi {
+        return if x > lo { 1.0 } else { 0.0 };
+    }
+    let t = ((x - lo) / (hi - lo)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Degrees;
+    use rand::prelude::*;
+
+    fn unit(x: Float, y: Float, z: Float) -> Unit {
+        Unit::try_from(Vector::new(x, y, z)).unwrap()
+    }
+
+    fn downlight() -> SpotLight {
+        SpotLight::new([0.0, 4.0, 0.0], -Unit::Y_AXIS, RGB::splat(8.0))
+            .cone(Degrees(45.0))
+            .falloff(Radians(PI / 8.0))
+    }
+
+    #[test]
+    fn cone() {
+        let light = downlight();
+        assert_eq!(RGB::splat(8.0), light.intensity(-Unit::Y_AXIS));
+        assert_eq!(RGB::splat(8.0), light.intensity(unit(0.3, -1.0, 0.0)));
+        assert_eq!(RGB::default(), light.intensity(unit(1.1, -1.0, 0.0)));
+        assert_eq!(RGB::default(), light.intensity(Unit::Y_AXIS));
+        let edge = light.intensity(unit(0.8, -1.0, 0.0)).max_component();
+        assert!(0.0 < edge && edge < 8.0, "{}", edge);
+
+        // The inverse square law below it, and nothing outside the cone
+        let s = light.sample(Point::new(0.0, 0.0, 0.0), [0.5; 2]).unwrap();
+        assert_eq!(Unit::Y_AXIS, s.wi);
+        assert_eq!((RGB::splat(0.5), 1.0, 4.0), (s.li, s.pdf, s.dist));
+        assert!(light.sample(Point::new(8.0, 0.0, 0.0), [0.5; 2]).is_none());
+        assert!(light.is_delta());
+        assert_eq!(0.0, light.pdf(Point::ORIGIN, Unit::Y_AXIS));
+    }
+
+    #[test]
+    fn power() {
+        // A hard-edged hemisphere
+        let light = downlight().cone(Degrees(90.0)).falloff(Radians(0.0));
+        let power = light.power().max_component();
+        assert!((power - 16.0 * PI).abs() < 1e-9, "{}", power);
+
+        // A flat profile integrates to the same as none
+        let flat = "TILT=NONE\n1 -1 1 2 1 1 2 0 0 0\n1 1 0\n0 180\n0\n50 50\n";
+        let flat = IesProfile::parse(flat).unwrap();
+        let expected = downlight().power().max_component();
+        let profiled = downlight().profile(flat).power().max_component();
+        assert!(
+            (profiled - expected).abs() < 1e-3 * expected,
+            "{}",
+            profiled
+        );
+
+        // Emitted rays leave within the cone, and carry the power on average
+        let light = downlight();
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut total = 0.0;
+        for _ in 0..10000 {
+            let s = light.sample_emission(rng.gen()).unwrap();
+            assert_eq!(light.position(), s.ray.origin);
+            assert!(s.ray.direction.y < -0.7);
+            total += s.weight.max_component();
+        }
+        let mean = total / 10000.0;
+        assert!((mean - expected).abs() < 0.02 * expected, "{}", mean);
+    }
+}
@@ -1,12 +1,323 @@
+//! # Materials and scattering functions.
+//!
+//! A [`BSDF`] describes how light arriving at a surface point from one
+//! direction is scattered into another. Rather than a single "scatter" method
+//! that picks an outgoing ray and returns its attenuation, the trait separates
+//! the three queries a Monte Carlo integrator needs:
+//!
+//! * [`BSDF::f`] evaluates the scattering function for a pair of directions
+//! * [`BSDF::pdf`] returns the density with which [`BSDF::sample_f`] would have
+//!   chosen a given incident direction
+//! * [`BSDF::sample_f`] importance-samples an incident direction
+//!
+//! Having all three is what makes multiple importance sampling possible, since
+//! light samples must be weighted by the BSDF's density and vice-versa.
+//!
+//! All directions are unit vectors in world space pointing *away* from the
+//! surface. So `wo` is the negated direction of the ray that hit the surface.
+
 use rand::Rng;
+use std::ops::BitOr;
+
+use crate::{color::RGB, geo::Unit, shape::Intersection, Float};
+
+mod bump;
+pub use bump::*;
 
-use crate::{color::RGB, geo::Ray, shape::Intersection};
+mod dielectric;
+pub use dielectric::*;
 
 mod lambertian;
 pub use lambertian::*;
 
+mod layered;
+pub use layered::*;
+
+mod lobe;
+pub use lobe::*;
+
+mod ray_type;
+pub use ray_type::*;
+
+/// Classifies the lobes of a [`BSDF`].
+///
+/// Flags can be combined with `|` and queried with [`contains`].
+///
+/// [`contains`]: Self::contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BSDFFlags(u8);
+
+impl BSDFFlags {
+    /// No scattering.
+    pub const NONE: Self = Self(0);
+    /// Scattering into the hemisphere of the surface normal.
+    pub const REFLECTION: Self = Self(1 << 0);
+    /// Scattering into the opposite hemisphere.
+    pub const TRANSMISSION: Self = Self(1 << 1);
+    /// Lobes that spread light over the whole hemisphere.
+    pub const DIFFUSE: Self = Self(1 << 2);
+    /// Lobes concentrated around a preferred direction.
+    pub const GLOSSY: Self = Self(1 << 3);
+    /// Dirac-delta lobes, such as perfect mirrors.
+    pub const SPECULAR: Self = Self(1 << 4);
+
+    /// Returns `true` if all flags in `other` are set.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if the lobe is a Dirac delta, which means [`BSDF::f`]
+    /// and [`BSDF::pdf`] are zero for all directions and only
+    /// [`BSDF::sample_f`] is meaningful.
+    #[inline]
+    pub const fn is_specular(self) -> bool {
+        self.contains(Self::SPECULAR)
+    }
+}
+
+impl BitOr for BSDFFlags {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The result of sampling a [`BSDF`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BSDFSample {
+    /// The sampled incident direction.
+    pub wi: Unit,
+    /// The value of the scattering function for `(wo, wi)`.
+    pub f: RGB,
+    /// The solid-angle density with which `wi` was chosen.
+    pub pdf: Float,
+    /// The type of lobe that was sampled.
+    pub flags: BSDFFlags,
+}
+
+/// The core trait for bidirectional scattering distribution functions.
 pub trait BSDF {
-    fn scatter(&self, ray: &Ray, isec: &Intersection, rng: &mut impl Rng) -> Option<(RGB, Ray)>;
+    /// Evaluate the scattering function for the given pair of directions.
+    fn f(&self, wo: Unit, wi: Unit, isect: &Intersection) -> RGB;
+
+    /// The density with which [`sample_f`][Self::sample_f] would choose `wi`
+    /// given `wo`.
+    fn pdf(&self, wo: Unit, wi: Unit, isect: &Intersection) -> Float;
+
+    /// Sample an incident direction for the given outgoing direction.
+    ///
+    /// Returns `None` if no valid direction could be sampled, in which case
+    /// the path should be terminated.
+    fn sample_f(&self, wo: Unit, isect: &Intersection, rng: &mut impl Rng) -> Option<BSDFSample>;
+
+    /// Sample an incident direction for the given outgoing direction, from
+    /// a point `u` in the unit square.
+    ///
+    /// This lets a [`Sampler`] place the direction, so low-discrepancy
+    /// samples spread out over the lobe. `rng` is left for any further
+    /// decisions. By default `u` is ignored and this is just [`sample_f`].
+    ///
+    /// [`Sampler`]: crate::sampler::Sampler
+    /// [`sample_f`]: Self::sample_f
+    fn sample_f_with(
+        &self,
+        wo: Unit,
+        isect: &Intersection,
+        u: [Float; 2],
+        rng: &mut impl Rng,
+    ) -> Option<BSDFSample> {
+        let _ = u;
+        self.sample_f(wo, isect, rng)
+    }
+}
+
+/// Fresnel reflectance of a smooth dielectric boundary.
+///
+/// `cos_i` is the cosine of the angle between the incident direction and the
+/// normal, and `eta` is the ratio of the refractive indices (inside over
+/// outside). Negative `cos_i` means the light arrives from inside the medium.
+/// Returns `1.0` under total internal reflection.
+///
+/// See: <https://pbr-book.org/3ed-2018/Reflection_Models/Specular_Reflection_and_Transmission#FresnelReflectance>
+pub fn fresnel_dielectric(cos_i: Float, eta: Float) -> Float {
+    let (cos_i, eta) = match cos_i < 0.0 {
+        true => (-cos_i, eta.recip()),
+        false => (cos_i, eta),
+    };
+    let cos_i = cos_i.min(1.0);
+
+    // Snell's law
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parl * r_parl + r_perp * r_perp) / 2.0
 }
 
-pub enum Material {}
+/// A surface material.
+///
+/// This is a polymorphic enum over the various [`BSDF`] implementations, for
+/// the same static-dispatch reasons as [`Surface`].
+///
+/// [`Surface`]: crate::shape::Surface
+pub enum Material {
+    Bump(Box<Bump>),
+    Dielectric(Dielectric),
+    Dispersive(DispersiveDielectric),
+    Lambertian(Lambertian),
+    Plastic(Plastic),
+    RaySwitch(Box<RaySwitch>),
+}
+
+impl Material {
+    /// The kinds of lobe the material scatters with, combined.
+    ///
+    /// Integrators use this to tell surfaces that only have delta lobes,
+    /// which must be followed by sampling, from those that can be evaluated
+    /// for arbitrary directions. A [`RaySwitch`] reports its camera
+    /// material, as it does when used as a [`BSDF`].
+    pub fn lobes(&self) -> BSDFFlags {
+        match self {
+            Self::Bump(m) => m.material.lobes(),
+            Self::Dielectric(_) | Self::Dispersive(_) => {
+                BSDFFlags::REFLECTION | BSDFFlags::TRANSMISSION | BSDFFlags::SPECULAR
+            }
+            Self::Lambertian(_) => BSDFFlags::REFLECTION | BSDFFlags::DIFFUSE,
+            Self::Plastic(_) => BSDFFlags::REFLECTION | BSDFFlags::DIFFUSE | BSDFFlags::SPECULAR,
+            Self::RaySwitch(m) => m.camera.lobes(),
+        }
+    }
+
+    /// The material to shade a hit by the given type of ray with.
+    ///
+    /// This is the material itself, except for a [`RaySwitch`], which picks
+    /// one of its materials.
+    #[inline]
+    pub fn for_ray(&self, ray: RayType) -> &Material {
+        match self {
+            Self::RaySwitch(m) => m.get(ray).for_ray(ray),
+            _ => self,
+        }
+    }
+
+    /// The light passing straight through the surface along a shadow ray,
+    /// with `wo` pointing back along the ray: black for opaque materials,
+    /// and the tint (less Fresnel reflection) for a [`Dielectric`]. A
+    /// [`DispersiveDielectric`] uses its index at the
+    /// [`D_LINE`][DispersiveDielectric::D_LINE].
+    ///
+    /// Refraction is ignored, so glass casts colored shadows but no
+    /// caustics. A [`RaySwitch`] uses its indirect material, as shadows are
+    /// only seen by their effect on other surfaces.
+    #[inline]
+    pub fn transmittance(&self, wo: Unit, isect: &Intersection) -> RGB {
+        match self {
+            Self::Bump(m) => m.material.transmittance(wo, isect),
+            Self::Dielectric(m) => m.transmittance(wo, isect),
+            Self::Dispersive(m) => m.at(DispersiveDielectric::D_LINE).transmittance(wo, isect),
+            Self::Lambertian(_) | Self::Plastic(_) => RGB::default(),
+            Self::RaySwitch(m) => m.indirect.transmittance(wo, isect),
+        }
+    }
+}
+
+impl BSDF for Material {
+    #[inline]
+    fn f(&self, wo: Unit, wi: Unit, isect: &Intersection) -> RGB {
+        match self {
+            Self::Bump(m) => m.f(wo, wi, isect),
+            Self::Dielectric(m) => m.f(wo, wi, isect),
+            Self::Dispersive(m) => m.at(DispersiveDielectric::D_LINE).f(wo, wi, isect),
+            Self::Lambertian(m) => m.f(wo, wi, isect),
+            Self::Plastic(m) => m.f(wo, wi, isect),
+            Self::RaySwitch(m) => m.camera.f(wo, wi, isect),
+        }
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Unit, wi: Unit, isect: &Intersection) -> Float {
+        match self {
+            Self::Bump(m) => m.pdf(wo, wi, isect),
+            Self::Dielectric(m) => m.pdf(wo, wi, isect),
+            Self::Dispersive(m) => m.at(DispersiveDielectric::D_LINE).pdf(wo, wi, isect),
+            Self::Lambertian(m) => m.pdf(wo, wi, isect),
+            Self::Plastic(m) => m.pdf(wo, wi, isect),
+            Self::RaySwitch(m) => m.camera.pdf(wo, wi, isect),
+        }
+    }
+
+    #[inline]
+    fn sample_f(&self, wo: Unit, isect: &Intersection, rng: &mut impl Rng) -> Option<BSDFSample> {
+        match self {
+            Self::Bump(m) => m.sample_f(wo, isect, rng),
+            Self::Dielectric(m) => m.sample_f(wo, isect, rng),
+            Self::Dispersive(m) => m.at(DispersiveDielectric::D_LINE).sample_f(wo, isect, rng),
+            Self::Lambertian(m) => m.sample_f(wo, isect, rng),
+            Self::Plastic(m) => m.sample_f(wo, isect, rng),
+            Self::RaySwitch(m) => m.camera.sample_f(wo, isect, rng),
+        }
+    }
+
+    #[inline]
+    fn sample_f_with(
+        &self,
+        wo: Unit,
+        isect: &Intersection,
+        u: [Float; 2],
+        rng: &mut impl Rng,
+    ) -> Option<BSDFSample> {
+        match self {
+            Self::Bump(m) => m.sample_f_with(wo, isect, u, rng),
+            Self::Dielectric(m) => m.sample_f_with(wo, isect, u, rng),
+            Self::Dispersive(m) => m
+                .at(DispersiveDielectric::D_LINE)
+                .sample_f_with(wo, isect, u, rng),
+            Self::Lambertian(m) => m.sample_f_with(wo, isect, u, rng),
+            Self::Plastic(m) => m.sample_f_with(wo, isect, u, rng),
+            Self::RaySwitch(m) => m.camera.sample_f_with(wo, isect, u, rng),
+        }
+    }
+}
+
+impl From<Bump> for Material {
+    fn from(m: Bump) -> Self {
+        Self::Bump(Box::new(m))
+    }
+}
+
+impl From<Dielectric> for Material {
+    fn from(m: Dielectric) -> Self {
+        Self::Dielectric(m)
+    }
+}
+
+impl From<DispersiveDielectric> for Material {
+    fn from(m: DispersiveDielectric) -> Self {
+        Self::Dispersive(m)
+    }
+}
+
+impl From<Lambertian> for Material {
+    fn from(m: Lambertian) -> Self {
+        Self::Lambertian(m)
+    }
+}
+
+impl From<Plastic> for Material {
+    fn from(m: Plastic) -> Self {
+        Self::Plastic(m)
+    }
+}
+
+impl From<RaySwitch> for Material {
+    fn from(m: RaySwitch) -> Self {
+        Self::RaySwitch(Box::new(m))
+    }
+}
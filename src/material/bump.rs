@@ -0,0 +1,197 @@
+use rand::Rng;
+
+use super::{BSDFSample, Material, BSDF};
+use crate::{
+    color::RGB,
+    geo::{Frame, Unit, Vector},
+    shape::Intersection,
+    texture::{Texture, TextureContext},
+    Float,
+};
+
+/// Adds fine surface detail to a material by bending its shading normal,
+/// without changing the geometry.
+///
+/// The detail comes from either a height texture ([`height`]), whose slopes
+/// tilt the normal as a displacement of the surface would, or a normal map
+/// ([`normal_map`]), which stores the tilted normal directly. Either way the
+/// wrapped material is then shaded as though the surface faced that way.
+///
+/// Textures are evaluated with the hit's position and normal, as the tangent
+/// frame comes from the normal alone (see [`Frame::from_normal`]). That suits
+/// world-space textures such as noise, or image textures projected with a
+/// [`Triplanar`]; a normal map's tangents only line up with the surface
+/// where the frame does.
+///
+/// [`height`]: Self::height
+/// [`normal_map`]: Self::normal_map
+/// [`Triplanar`]: crate::texture::Triplanar
+pub struct Bump {
+    pub material: Material,
+    map: Map,
+}
+
+enum Map {
+    Height {
+        texture: Box<dyn Texture<Float>>,
+        scale: Float,
+    },
+    Normal(Box<dyn Texture<RGB>>),
+}
+
+impl Bump {
+    /// Bump `material` by the given height texture, in world units times
+    /// `scale`.
+    pub fn height(
+        material: impl Into<Material>,
+        texture: impl Texture<Float> + 'static,
+        scale: Float,
+    ) -> Self {
+        Self {
+            material: material.into(),
+            map: Map::Height {
+                texture: Box::new(texture),
+                scale,
+            },
+        }
+    }
+
+    /// Bend `material`'s normal by a tangent-space normal map. Colors in
+    /// `[0, 1]` map to components in `[-1, 1]`, with blue along the normal,
+    /// so an unperturbed map is `(0.5, 0.5, 1.0)`.
+    pub fn normal_map(material: impl Into<Material>, texture: impl Texture<RGB> + 'static) -> Self {
+        Self {
+            material: material.into(),
+            map: Map::Normal(Box::new(texture)),
+        }
+    }
+
+    /// The perturbed shading normal at a hit.
+    ///
+    /// Stays on the same side of the surface as the hit's normal, which is
+    /// returned unchanged if the map would tilt it past the surface.
+    pub fn shading_normal(&self, isect: &Intersection) -> Unit {
+        let frame = Frame::from_normal(isect.norm);
+        let ctx = TextureContext::from(isect);
+        let local = match &self.map {
+            Map::Height { texture, scale } => {
+                // Forward differences along the tangents, with a step that
+                // grows with distance from the origin to stay above rounding
+                let p = Vector::from(isect.point);
+                let delta = 1e-4 * (1.0 + p.x.abs().max(p.y.abs()).max(p.z.abs()));
+                let height = |offset: Unit| {
+                    let ctx = TextureContext {
+                        point: isect.point + Vector::from(offset) * delta,
+                        ..ctx
+                    };
+                    texture.evaluate(&ctx)
+                };
+                let h = texture.evaluate(&ctx);
+                let du = (height(frame.x()) - h) / delta;
+                let dv = (height(frame.y()) - h) / delta;
+                Vector::new(-scale * du, -scale * dv, 1.0)
+            }
+            Map::Normal(texture) => {
+                let [x, y, z] = <[Float; 3]>::from(texture.evaluate(&ctx));
+                Vector::new(2.0 * x - 1.0, 2.0 * y - 1.0, 2.0 * z - 1.0)
+            }
+        };
+        match local.z > 0.0 {
+            true => Unit::try_from(frame.to_world(local)).unwrap_or(isect.norm),
+            false => isect.norm,
+        }
+    }
+
+    // The hit as the wrapped material should see it
+    #[inline]
+    fn shade(&self, isect: &Intersection) -> Intersection {
+        Intersection {
+            norm: self.shading_normal(isect),
+            ..*isect
+        }
+    }
+}
+
+impl BSDF for Bump {
+    #[inline]
+    fn f(&self, wo: Unit, wi: Unit, isect: &Intersection) -> RGB {
+        self.material.f(wo, wi, &self.shade(isect))
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Unit, wi: Unit, isect: &Intersection) -> Float {
+        self.material.pdf(wo, wi, &self.shade(isect))
+    }
+
+    #[inline]
+    fn sample_f(&self, wo: Unit, isect: &Intersection, rng: &mut impl Rng) -> Option<BSDFSample> {
+        self.material.sample_f(wo, &self.shade(isect), rng)
+    }
+
+    #[inline]
+    fn sample_f_with(
+        &self,
+        wo: Unit,
+        isect: &Intersection,
+        u: [Float; 2],
+        rng: &mut impl Rng,
+    ) -> Option<BSDFSample> {
+        self.material.sample_f_with(wo, &self.shade(isect), u, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::Point,
+        material::Lambertian,
+        texture::{Constant, Expr},
+    };
+
+    fn isect() -> Intersection {
+        Intersection {
+            point: Point::new(0.3, -0.2, 0.0),
+            norm: Unit::Z_AXIS,
+            t: 1.0,
+        }
+    }
+
+    fn grey() -> Lambertian {
+        Lambertian::new(RGB::from([0.5, 0.5, 0.5]))
+    }
+
+    #[test]
+    fn height() {
+        // A ramp rising along `x` at 45 degrees
+        let ramp: Expr = "x".parse().unwrap();
+        let bump = Bump::height(grey(), ramp, 1.0);
+        let norm = Vector::from(bump.shading_normal(&isect()));
+        let expected = Vector::new(-1.0, 0.0, 1.0) / Float::sqrt(2.0);
+        assert!((norm - expected).len() < 1e-6, "{:?}", norm);
+
+        // Shading follows the tilted normal
+        let wo = Unit::Z_AXIS;
+        let wi = Unit::try_from(expected).unwrap();
+        let flat = grey();
+        assert!(bump.pdf(wo, wi, &isect()) > flat.pdf(wo, wi, &isect()));
+
+        // Flat heights leave the normal alone
+        let flat = Bump::height(grey(), Constant(2.0), 10.0);
+        assert_eq!(Unit::Z_AXIS, flat.shading_normal(&isect()));
+    }
+
+    #[test]
+    fn normal_map() {
+        let bump = Bump::normal_map(grey(), Constant(RGB::from([0.5, 0.5, 1.0])));
+        assert_eq!(Unit::Z_AXIS, bump.shading_normal(&isect()));
+
+        let bump = Bump::normal_map(grey(), Constant(RGB::from([1.0, 0.5, 1.0])));
+        let cos = bump.shading_normal(&isect()).z();
+        assert!((cos - Float::sqrt(0.5)).abs() < 1e-9);
+
+        // Pointing into the surface, so ignored
+        let bump = Bump::normal_map(grey(), Constant(RGB::from([1.0, 0.5, 0.2])));
+        assert_eq!(Unit::Z_AXIS, bump.shading_normal(&isect()));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{
+    color::RGB,
+    geo::{self, Unit},
+    shape::Intersection,
+    spectrum, Float,
+};
+use rand::prelude::*;
+
+use super::{fresnel_dielectric, BSDFFlags, BSDFSample, BSDF};
+
+/// A smooth dielectric boundary, such as glass or water.
+///
+/// Both reflection and refraction are perfectly specular; which one is sampled
+/// is chosen stochastically in proportion to the Fresnel reflectance.
+///
+/// Refracted light is filtered by the [`tint`], which is applied once per
+/// crossing of the boundary, so colored glass is tinted on the way in and
+/// again on the way out.
+///
+/// [`tint`]: Self::tint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dielectric {
+    eta: Float,
+    tint: RGB,
+}
+
+impl Dielectric {
+    /// Creates a clear dielectric with the given index of refraction
+    /// (relative to the medium on the side the normal points into).
+    pub const fn new(eta: Float) -> Self {
+        Self {
+            eta,
+            tint: RGB::splat(1.0),
+        }
+    }
+
+    /// Set the color refracted light is filtered by at each crossing.
+    /// Defaults to white.
+    pub const fn tint(mut self, tint: RGB) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// The light passing straight through the boundary, for shadow rays
+    /// that ignore refraction: the tint, less what Fresnel reflects.
+    ///
+    /// `wo` points back along the shadow ray, as for [`BSDF::f`].
+    #[inline]
+    pub fn transmittance(&self, wo: Unit, isect: &Intersection) -> RGB {
+        self.tint * (1.0 - fresnel_dielectric(wo.dot(isect.norm), self.eta))
+    }
+
+    // Reflect if `u < refl`, otherwise refract
+    fn sample_by(&self, wo: Unit, isect: &Intersection, u: Float) -> Option<BSDFSample> {
+        let cos_o = wo.dot(isect.norm);
+        let refl = fresnel_dielectric(cos_o, self.eta);
+
+        // Work relative to the normal on the same side as `wo`
+        let (n, cos_o, eta) = match cos_o < 0.0 {
+            true => (-isect.norm, -cos_o, self.eta.recip()),
+            false => (isect.norm, cos_o, self.eta),
+        };
+
+        if u < refl {
+            return Some(BSDFSample {
+                wi: geo::reflect(wo, n),
+                f: RGB::from([1.0, 1.0, 1.0]) * (refl / cos_o),
+                pdf: refl,
+                flags: BSDFFlags::REFLECTION | BSDFFlags::SPECULAR,
+            });
+        }
+
+        // Total internal reflection has `refl == 1` so can't get here, but
+        // `refract` guards against round-off anyway
+        let wi = geo::refract(wo, n, eta)?;
+        let cos_t = -wi.dot(n);
+
+        // Radiance is compressed into a smaller solid angle on entering a
+        // denser medium, hence the 1/eta^2
+        let trans = 1.0 - refl;
+        Some(BSDFSample {
+            wi,
+            f: self.tint * (trans / (cos_t * eta * eta)),
+            pdf: trans,
+            flags: BSDFFlags::TRANSMISSION | BSDFFlags::SPECULAR,
+        })
+    }
+}
+
+impl BSDF for Dielectric {
+    #[inline]
+    fn f(&self, _wo: Unit, _wi: Unit, _isect: &Intersection) -> RGB {
+        RGB::default()
+    }
+
+    #[inline]
+    fn pdf(&self, _wo: Unit, _wi: Unit, _isect: &Intersection) -> Float {
+        0.0
+    }
+
+    fn sample_f(&self, wo: Unit, isect: &Intersection, rng: &mut impl Rng) -> Option<BSDFSample> {
+        self.sample_by(wo, isect, rng.gen())
+    }
+
+    fn sample_f_with(
+        &self,
+        wo: Unit,
+        isect: &Intersection,
+        u: [Float; 2],
+        _rng: &mut impl Rng,
+    ) -> Option<BSDFSample> {
+        self.sample_by(wo, isect, u[0])
+    }
+}
+
+/// A dielectric whose index of refraction varies with wavelength.
+///
+/// The index follows the Sellmeier equation (see [`spectrum::sellmeier`]).
+/// Since every wavelength refracts differently, dispersion is only seen with a
+/// spectral integrator that traces each path at a single wavelength (see
+/// [`SingleWavelength`] and [`PathTracer::spectral`]), using [`at`] to get the
+/// BSDF for that wavelength. Other integrators render it as a plain
+/// [`Dielectric`] with its index at the [`D_LINE`].
+///
+/// [`SingleWavelength`]: crate::spectrum::SingleWavelength
+/// [`PathTracer::spectral`]: crate::integrator::PathTracer::spectral
+/// [`at`]: Self::at
+/// [`D_LINE`]: Self::D_LINE
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DispersiveDielectric {
+    bs: [Float; 3],
+    cs: [Float; 3],
+}
+
+impl DispersiveDielectric {
+    /// Schott N-BK7 borosilicate crown glass, a common optical glass.
+    pub const BK7: Self = Self::new(
+        [1.03961212, 0.231792344, 1.01046945],
+        [0.00600069867, 0.0200179144, 103.560653],
+    );
+
+    /// Fused silica.
+    pub const FUSED_SILICA: Self = Self::new(
+        [0.6961663, 0.4079426, 0.8974794],
+        [0.0046791, 0.0135121, 97.934],
+    );
+
+    /// The Fraunhofer d-line, `587.6nm`, the wavelength glasses' indices of
+    /// refraction are usually quoted at.
+    pub const D_LINE: Float = 587.6;
+
+    /// Creates a dispersive dielectric from Sellmeier coefficients. The `cs`
+    /// are in square micrometers, as they're usually published.
+    pub const fn new(bs: [Float; 3], cs: [Float; 3]) -> Self {
+        Self { bs, cs }
+    }
+
+    /// The index of refraction at the given wavelength, in nanometers.
+    #[inline]
+    pub fn ior(&self, wavelength: Float) -> Float {
+        spectrum::sellmeier(&self.bs, &self.cs, wavelength).sqrt()
+    }
+
+    /// The (non-dispersive) BSDF at the given wavelength, in nanometers.
+    #[inline]
+    pub fn at(&self, wavelength: Float) -> Dielectric {
+        Dielectric::new(self.ior(wavelength))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::{Point, Vector};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn bk7_ior() {
+        // Published values for the Fraunhofer d-line and F/C lines
+        assert_relative_eq!(
+            1.5168,
+            DispersiveDielectric::BK7.ior(DispersiveDielectric::D_LINE),
+            epsilon = 1e-4
+        );
+        assert!(DispersiveDielectric::BK7.ior(486.1) > DispersiveDielectric::BK7.ior(656.3));
+    }
+
+    #[test]
+    fn normal_incidence_passes_straight() {
+        let isect = Intersection {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            t: 1.0,
+        };
+        let glass = Dielectric::new(1.5);
+        let mut rng = StdRng::seed_from_u64(1234);
+
+        for _ in 0..100 {
+            let sample = glass.sample_f(Unit::Z_AXIS, &isect, &mut rng).unwrap();
+            let expected = match sample.flags.contains(BSDFFlags::TRANSMISSION) {
+                true => -Vector::Z_AXIS,
+                false => Vector::Z_AXIS,
+            };
+            assert_relative_eq!(expected, sample.wi.into());
+        }
+    }
+
+    #[test]
+    fn tinted_transmittance() {
+        let isect = Intersection {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            t: 1.0,
+        };
+        let green = RGB::from([0.2, 0.9, 0.2]);
+        let glass = Dielectric::new(1.5).tint(green);
+
+        // 4% is reflected straight on, from either side
+        let tr = glass.transmittance(Unit::Z_AXIS, &isect);
+        assert_relative_eq!(0.96 * 0.9, <[Float; 3]>::from(tr)[1], epsilon = 1e-9);
+        assert_eq!(tr, glass.transmittance(-Unit::Z_AXIS, &isect));
+
+        // Refraction is tinted too
+        let mut rng = StdRng::seed_from_u64(1234);
+        let refracted = (0..100)
+            .filter_map(|_| glass.sample_f(Unit::Z_AXIS, &isect, &mut rng))
+            .find(|s| s.flags.contains(BSDFFlags::TRANSMISSION))
+            .unwrap();
+        let [r, g, _] = <[Float; 3]>::from(refracted.f);
+        assert_relative_eq!(0.2 / 0.9, r / g, epsilon = 1e-9);
+    }
+}
@@ -1,32 +1,138 @@
 use crate::{
     color::RGB,
-    geo::{Ray, Vector},
+    geo::{self, Unit, Vector},
     shape::Intersection,
+    Float,
 };
 use approx::relative_eq;
 use rand::prelude::*;
 use rand_distr::UnitSphere;
 
-use super::BSDF;
+use super::{BSDFFlags, BSDFSample, BSDF};
 
+const FRAC_1_PI: Float = std::f64::consts::FRAC_1_PI as Float;
+
+/// An ideal diffuse reflector.
 pub struct Lambertian(RGB);
 
 impl Lambertian {
     pub const fn new(rgb: RGB) -> Self {
         Self(rgb)
     }
-}
 
-impl BSDF for Lambertian {
-    fn scatter(&self, _ray: &Ray, isect: &Intersection, rng: &mut impl Rng) -> Option<(RGB, Ray)> {
-        let mut scatter_dir = Vector::from(UnitSphere.sample(rng)) + isect.norm.into();
+    #[inline]
+    fn cos_theta(w: Unit, isect: &Intersection) -> Float {
+        Vector::from(w).dot(isect.norm.into())
+    }
+
+    // Scatter towards the normal offset by `on_sphere`, a uniform point on
+    // the unit sphere
+    fn scatter(&self, wo: Unit, isect: &Intersection, on_sphere: Vector) -> Option<BSDFSample> {
+        if Self::cos_theta(wo, isect) <= 0.0 {
+            return None;
+        }
+
+        // Offsetting a uniform point on the unit sphere by the normal gives a
+        // cosine-weighted direction about the normal.
+        let mut scatter_dir = on_sphere + isect.norm.into();
 
         // Catch degenrate scatter direction
         if relative_eq!(scatter_dir, Vector::ZERO, max_relative = 1e-8) {
             scatter_dir = isect.norm.into();
         }
 
-        let scattered = Ray::new(isect.point, scatter_dir);
-        Option::Some((self.0, scattered))
+        let wi = Unit::try_from(scatter_dir).ok()?;
+        Some(BSDFSample {
+            wi,
+            f: self.f(wo, wi, isect),
+            pdf: self.pdf(wo, wi, isect),
+            flags: BSDFFlags::REFLECTION | BSDFFlags::DIFFUSE,
+        })
+    }
+}
+
+impl BSDF for Lambertian {
+    fn f(&self, wo: Unit, wi: Unit, isect: &Intersection) -> RGB {
+        match Self::cos_theta(wo, isect) > 0.0 && Self::cos_theta(wi, isect) > 0.0 {
+            true => self.0 * FRAC_1_PI,
+            false => RGB::default(),
+        }
+    }
+
+    fn pdf(&self, wo: Unit, wi: Unit, isect: &Intersection) -> Float {
+        match Self::cos_theta(wo, isect) > 0.0 {
+            true => Self::cos_theta(wi, isect).max(0.0) * FRAC_1_PI,
+            false => 0.0,
+        }
+    }
+
+    fn sample_f(&self, wo: Unit, isect: &Intersection, rng: &mut impl Rng) -> Option<BSDFSample> {
+        self.scatter(wo, isect, UnitSphere.sample(rng).into())
+    }
+
+    fn sample_f_with(
+        &self,
+        wo: Unit,
+        isect: &Intersection,
+        u: [Float; 2],
+        _rng: &mut impl Rng,
+    ) -> Option<BSDFSample> {
+        self.scatter(wo, isect, geo::uniform_sphere(u).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Point;
+    use approx::assert_relative_eq;
+
+    fn isect() -> Intersection {
+        Intersection {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            t: 1.0,
+        }
+    }
+
+    #[test]
+    fn sample_matches_eval() {
+        let bsdf = Lambertian::new(RGB::from([0.5, 0.5, 0.5]));
+        let isect = isect();
+        let mut rng = StdRng::seed_from_u64(1234);
+
+        for _ in 0..100 {
+            let sample = bsdf.sample_f(Unit::Z_AXIS, &isect, &mut rng).unwrap();
+            assert!(sample.flags.contains(BSDFFlags::DIFFUSE));
+            assert_eq!(bsdf.f(Unit::Z_AXIS, sample.wi, &isect), sample.f);
+            assert_relative_eq!(bsdf.pdf(Unit::Z_AXIS, sample.wi, &isect), sample.pdf);
+        }
+
+        // From a grid over the unit square, directions are cosine-weighted
+        let n = 64;
+        let mut cos = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = [
+                    (i as Float + 0.5) / n as Float,
+                    (j as Float + 0.5) / n as Float,
+                ];
+                let sample = bsdf
+                    .sample_f_with(Unit::Z_AXIS, &isect, u, &mut rng)
+                    .unwrap();
+                assert_relative_eq!(bsdf.pdf(Unit::Z_AXIS, sample.wi, &isect), sample.pdf);
+                cos += sample.wi.dot(isect.norm);
+            }
+        }
+        assert_relative_eq!(2.0 / 3.0, cos / (n * n) as Float, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn no_transmission() {
+        let bsdf = Lambertian::new(RGB::from([0.5, 0.5, 0.5]));
+        let isect = isect();
+
+        assert_eq!(RGB::default(), bsdf.f(Unit::Z_AXIS, -Unit::Z_AXIS, &isect));
+        assert_eq!(0.0, bsdf.pdf(Unit::Z_AXIS, -Unit::Z_AXIS, &isect));
     }
 }
@@ -0,0 +1,130 @@
+use super::BSDF;
+use crate::{
+    color::RGB,
+    film::Buffer,
+    geo::{Point, Unit, Vector},
+    shape::Intersection,
+    Float,
+};
+
+const TAU: Float = std::f64::consts::TAU as Float;
+
+// The surface the lobe is evaluated on: at the origin, facing up.
+const ISECT: Intersection = Intersection {
+    point: Point::ORIGIN,
+    norm: Unit::Y_AXIS,
+    t: 1.0,
+};
+
+/// Plot a BSDF's lobe as seen looking down on the surface.
+///
+/// The BSDF is evaluated on a surface whose normal is `+Y`, so `wo` should be
+/// given in that frame. The upper hemisphere is projected orthographically
+/// onto a `size` by `size` image, with `+X` to the right and `+Z` down; each
+/// pixel inside the disk holds `f(wo, wi)` for the direction above it, and
+/// pixels outside are black.
+///
+/// The projection is area-preserving in projected solid angle, so averaging
+/// the pixels inside the disk and multiplying by `π` gives the directional
+/// albedo for `wo`. Values aren't normalized; scale them to taste before
+/// saving.
+///
+/// Only lobes in the hemisphere of the normal are shown, and specular lobes
+/// don't show up at all since [`BSDF::f`] is zero for them.
+pub fn plot_hemisphere(bsdf: &impl BSDF, wo: Unit, size: u32) -> Buffer<RGB> {
+    let mut img = Buffer::new(size, size);
+    for (px, py, pixel) in img.pixel_iter_mut() {
+        let x = 2.0 * (px as Float + 0.5) / size as Float - 1.0;
+        let z = 2.0 * (py as Float + 0.5) / size as Float - 1.0;
+        let r2 = x * x + z * z;
+        if r2 < 1.0 {
+            let wi = Unit::try_from(Vector::new(x, (1.0 - r2).sqrt(), z));
+            if let Ok(wi) = wi {
+                *pixel = bsdf.f(wo, wi, &ISECT);
+            }
+        }
+    }
+    img
+}
+
+/// Plot a slice through a BSDF's lobe as a polar plot.
+///
+/// The slice is the plane containing the normal (`+Y`) and `wo`, drawn with
+/// the normal pointing up and `wo` to the right, so reflection appears in the
+/// upper half of the image and transmission in the lower half. Each channel
+/// is filled out to a radius proportional to the cosine-weighted scattering
+/// function, `f(wo, wi) |cos θi|`, normalized so the largest value reaches the
+/// edge of the image.
+pub fn plot_polar(bsdf: &impl BSDF, wo: Unit, size: u32) -> Buffer<RGB> {
+    // The in-plane tangent points towards `wo`; pick any if `wo` is the normal
+    let wo_v = Vector::from(wo);
+    let tangent = Vector::new(wo_v.x, 0.0, wo_v.z);
+    let tangent = match tangent.len() > 1e-6 {
+        true => tangent / tangent.len(),
+        false => Vector::X_AXIS,
+    };
+
+    let lobe = |phi: Float| -> [Float; 3] {
+        let wi = tangent * phi.cos() + Vector::Y_AXIS * phi.sin();
+        match Unit::try_from(wi) {
+            Ok(wi) => (bsdf.f(wo, wi, &ISECT) * phi.sin().abs()).into(),
+            Err(_) => [0.0; 3],
+        }
+    };
+
+    let steps = 4 * size.max(1);
+    let max = (0..steps)
+        .map(|i| lobe(i as Float / steps as Float * TAU))
+        .flat_map(|v| v.into_iter())
+        .fold(0.0, Float::max);
+
+    let mut img = Buffer::new(size, size);
+    if max <= 0.0 || !max.is_finite() {
+        return img;
+    }
+    for (px, py, pixel) in img.pixel_iter_mut() {
+        let x = 2.0 * (px as Float + 0.5) / size as Float - 1.0;
+        let y = 1.0 - 2.0 * (py as Float + 0.5) / size as Float;
+        let r = (x * x + y * y).sqrt();
+        let value = lobe(y.atan2(x)).map(|c| match r <= c / max {
+            true => 1.0,
+            false => 0.0,
+        });
+        *pixel = RGB::from(value);
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn hemisphere_albedo() {
+        let albedo = 0.8;
+        let bsdf = Lambertian::new(RGB::from([albedo, albedo, albedo]));
+        let size = 256;
+        let img = plot_hemisphere(&bsdf, Unit::Y_AXIS, size);
+
+        let sum: Float = img.iter().map(|c| c.max_component()).sum();
+        let pixel_area = (2.0 / size as Float).powi(2);
+        assert_relative_eq!(albedo, sum * pixel_area, epsilon = 1e-2);
+        assert!(img.pixel_iter().next().unwrap().2.is_black());
+    }
+
+    #[test]
+    fn polar_lambertian() {
+        let bsdf = Lambertian::new(RGB::from([0.5, 0.5, 0.5]));
+        let img = plot_polar(&bsdf, Unit::Y_AXIS, 64);
+        let at = |x: u32, y: u32| img.pixel_iter().nth((y * 64 + x) as usize).unwrap().2;
+
+        // Just above the center, and near the top edge
+        assert!(!at(32, 30).is_black());
+        assert!(!at(32, 1).is_black());
+        // Below the surface, and off to the side
+        assert!(at(32, 40).is_black());
+        assert!(at(1, 20).is_black());
+    }
+}
@@ -0,0 +1,82 @@
+use super::Material;
+
+/// Why a ray was traced, for materials that look different depending on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RayType {
+    /// A ray from the camera, so the surface is seen directly.
+    Camera,
+    /// A bounce ray, so the surface is only seen via its effect on others.
+    Indirect,
+}
+
+impl RayType {
+    /// The type of a ray that has bounced `depth` times, with `0` for camera
+    /// rays.
+    #[inline]
+    pub const fn from_depth(depth: usize) -> Self {
+        match depth {
+            0 => Self::Camera,
+            _ => Self::Indirect,
+        }
+    }
+}
+
+/// Picks a material according to the type of ray that hit the surface.
+///
+/// Two uses: art direction, such as a backdrop that's a different color to
+/// the camera than in the light it bounces onto the scene; and speed, by
+/// swapping an expensive layered material for plain diffuse once the path
+/// has bounced and the detail won't show.
+///
+/// Integrators pick the material with [`Material::for_ray`]. Used directly
+/// as a [`BSDF`], it behaves as its camera material.
+///
+/// [`BSDF`]: super::BSDF
+pub struct RaySwitch {
+    pub camera: Material,
+    pub indirect: Material,
+}
+
+impl RaySwitch {
+    /// Use `camera` for camera rays and `indirect` for everything else.
+    pub fn new(camera: impl Into<Material>, indirect: impl Into<Material>) -> Self {
+        Self {
+            camera: camera.into(),
+            indirect: indirect.into(),
+        }
+    }
+
+    /// The material for rays of the given type.
+    #[inline]
+    pub fn get(&self, ray: RayType) -> &Material {
+        match ray {
+            RayType::Camera => &self.camera,
+            RayType::Indirect => &self.indirect,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::RGB,
+        material::{Lambertian, Plastic},
+    };
+
+    #[test]
+    fn for_ray() {
+        let grey = Lambertian::new(RGB::from([0.5, 0.5, 0.5]));
+        let red = Lambertian::new(RGB::from([0.8, 0.1, 0.1]));
+        let material = Material::from(RaySwitch::new(Plastic::new(red, 1.5), grey));
+
+        assert!(matches!(
+            material.for_ray(RayType::from_depth(0)),
+            Material::Plastic(_)
+        ));
+        assert!(matches!(
+            material.for_ray(RayType::from_depth(3)),
+            Material::Lambertian(_)
+        ));
+    }
+}
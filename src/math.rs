@@ -0,0 +1,35 @@
+//! # Numerical utilities.
+//!
+//! Supporting math that doesn't belong to the geometric primitives in
+//! [`geo`][crate::geo], such as tabulated functions, curves, counter-based
+//! random streams, a blue-noise dither mask and spherical harmonics. These
+//! are public because they turn up in the rest of the API: spectra load as
+//! [`PiecewiseLinearFn`]s, probes store [`Sh9`]s and deterministic renders
+//! hand out [`CounterRng`]s.
+//!
+//! Hashing, the spatial hash grid used by photon mapping and the 4-wide
+//! lanes used for packet intersection tests are internal to the crate.
+
+mod blue_noise;
+pub use blue_noise::*;
+
+mod counter;
+pub use counter::*;
+
+mod grid;
+pub(crate) use grid::*;
+
+mod hash;
+pub(crate) use hash::*;
+
+mod lanes;
+pub(crate) use lanes::*;
+
+mod piecewise;
+pub use piecewise::*;
+
+mod ramp;
+pub use ramp::*;
+
+mod sh;
+pub use sh::*;
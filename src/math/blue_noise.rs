@@ -0,0 +1,60 @@
+use crate::Float;
+
+/// The width and height of the bundled blue-noise mask, in pixels.
+pub const BLUE_NOISE_SIZE: u32 = 64;
+
+// Ranks from the void-and-cluster method, scaled to bytes so every value
+// appears equally often. Generated by `tools/blue_noise`.
+static MASK: &[u8; 4096] = include_bytes!("../../assets/blue_noise_64.bin");
+
+/// The blue-noise threshold for pixel `(x, y)`, in `[0, 1)`.
+///
+/// Thresholds are spread evenly over `[0, 1)`, like white noise, but
+/// neighbouring pixels always get very different ones, so the pattern has no
+/// clumps or low-frequency blotches. That makes it the usual choice for
+/// dithering: the error it adds is fine-grained and hard to see, where white
+/// noise looks grainy. The mask is [`BLUE_NOISE_SIZE`] pixels square and
+/// tiles seamlessly, so any coordinates work.
+///
+/// ```
+/// use gremlin::math::{blue_noise, BLUE_NOISE_SIZE};
+///
+/// let t = blue_noise(3, 5);
+/// assert!((0.0..1.0).contains(&t));
+/// assert_eq!(t, blue_noise(3 + BLUE_NOISE_SIZE, 5));
+/// ```
+///
+/// See: Ulichney, "The void-and-cluster method for dither array
+/// generation", SPIE 1993
+#[inline]
+pub fn blue_noise(x: u32, y: u32) -> Float {
+    let (x, y) = (x % BLUE_NOISE_SIZE, y % BLUE_NOISE_SIZE);
+    let v = MASK[(y * BLUE_NOISE_SIZE + x) as usize];
+    (v as Float + 0.5) / 256.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evenly_spread() {
+        // Every threshold equally often
+        let mut counts = [0; 256];
+        MASK.iter().for_each(|&v| counts[v as usize] += 1);
+        assert!(counts.iter().all(|&c| c == 16));
+
+        // Neighbours differ by more than the third two random values would
+        // on average
+        let n = BLUE_NOISE_SIZE;
+        let mut diff = 0.0;
+        for y in 0..n {
+            for x in 0..n {
+                diff += (blue_noise(x, y) - blue_noise(x + 1, y)).abs();
+                diff += (blue_noise(x, y) - blue_noise(x, y + 1)).abs();
+            }
+        }
+        let mean = diff / (2 * n * n) as Float;
+        assert!(mean > 0.38, "{}", mean);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{hash_keys, mix64};
+use rand::{Error, RngCore};
+
+/// A counter-based random number generator.
+///
+/// Rather than stepping an internal state, the `n`-th value is a hash of the
+/// stream's key and `n`, in the style of Philox or SplitMix. So any value of
+/// any stream can be produced directly, without generating the ones before
+/// it: a stream is fully described by its key and position, both of which
+/// can be [`seek`]ed to or passed to another machine.
+///
+/// The renderer keys a stream per sample of each pixel, so a single sample
+/// can be re-rendered on its own and get exactly the values it got in the
+/// full render.
+///
+/// [`seek`]: Self::seek
+///
+/// ```
+/// use gremlin::math::CounterRng;
+/// use rand::Rng;
+///
+/// let mut rng = CounterRng::from_keys(&[1234, 7, 3]);
+/// let values: Vec<f64> = (0..4).map(|_| rng.gen()).collect();
+///
+/// let mut again = CounterRng::from_keys(&[1234, 7, 3]);
+/// again.seek(2);
+/// assert_eq!(values[2], again.gen::<f64>());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CounterRng {
+    key: u64,
+    counter: u64,
+}
+
+impl CounterRng {
+    /// Create a generator for the stream with the given key, at its start.
+    #[inline]
+    pub fn new(key: u64) -> Self {
+        Self { key, counter: 0 }
+    }
+
+    /// Create a generator for the stream keyed by a sequence of indices,
+    /// such as a seed, pixel coordinates and sample number.
+    #[inline]
+    pub fn from_keys(keys: &[u64]) -> Self {
+        Self::new(hash_keys(keys))
+    }
+
+    /// The stream's key.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The number of 64-bit values drawn from the stream so far.
+    #[inline]
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Jump to the `counter`-th 64-bit value of the stream.
+    #[inline]
+    pub fn seek(&mut self, counter: u64) {
+        self.counter = counter;
+    }
+}
+
+impl RngCore for CounterRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        // Two rounds, so neighbouring keys and counters don't correlate
+        let v = mix64(self.key ^ mix64(self.counter));
+        self.counter = self.counter.wrapping_add(1);
+        v
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Float;
+    use rand::Rng;
+
+    #[test]
+    fn streams_are_uniform_and_independent() {
+        // Neighbouring pixels' streams shouldn't track one another
+        let n = 10_000;
+        let (mut sum, mut prod) = (0.0, 0.0);
+        for i in 0..n {
+            let a: Float = CounterRng::from_keys(&[0, i, 0]).gen();
+            let b: Float = CounterRng::from_keys(&[0, i + 1, 0]).gen();
+            sum += a;
+            prod += (a - 0.5) * (b - 0.5);
+        }
+        let n = n as Float;
+        assert!((sum / n - 0.5).abs() < 0.01, "{}", sum / n);
+        assert!((prod / n).abs() < 0.005, "{}", prod / n);
+    }
+}
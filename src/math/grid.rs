@@ -0,0 +1,128 @@
+use super::hash_keys;
+use crate::{
+    geo::{Point, Vector},
+    Float,
+};
+
+/// Points bucketed by a uniform grid, for finding those near a query point.
+///
+/// Rather than allocating every cell of the grid, cells are hashed into a
+/// table with about one bucket per point, so memory stays proportional to
+/// the number of points however sparse they are. Photon mapping uses it
+/// to find the photons landing near each point seen by the camera.
+///
+/// Queries are cheapest with a radius no bigger than the cell size, when
+/// they touch at most `27` cells.
+#[derive(Debug, Clone)]
+pub struct HashGrid<T> {
+    cell: Float,
+    // Bucket `b` holds `items[starts[b]..starts[b + 1]]`
+    starts: Vec<usize>,
+    items: Vec<(Point, T)>,
+}
+
+impl<T> HashGrid<T> {
+    /// Bucket `items` by their positions, into cells of the given size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` isn't positive and finite.
+    pub fn new(items: Vec<(Point, T)>, cell: Float) -> Self {
+        assert!(
+            cell > 0.0 && cell.is_finite(),
+            "Cell size must be positive, got {}",
+            cell
+        );
+        let buckets = items.len().max(1);
+        let mut grid = Self {
+            cell,
+            starts: vec![0; buckets + 1],
+            items: Vec::with_capacity(items.len()),
+        };
+
+        // Sort by bucket, then count each bucket's points
+        let mut keyed: Vec<(usize, (Point, T))> = items
+            .into_iter()
+            .map(|item| (grid.bucket(grid.cell_of(item.0)), item))
+            .collect();
+        keyed.sort_by_key(|&(k, _)| k);
+        for &(k, _) in &keyed {
+            grid.starts[k + 1] += 1;
+        }
+        for b in 0..buckets {
+            grid.starts[b + 1] += grid.starts[b];
+        }
+        grid.items = keyed.into_iter().map(|(_, item)| item).collect();
+        grid
+    }
+
+    /// Call `f` with every point within `radius` of `center`, and its item.
+    pub fn query(&self, center: Point, radius: Float, mut f: impl FnMut(Point, &T)) {
+        let extent = Vector::new(radius, radius, radius);
+        let lo = self.cell_of(center + -extent);
+        let hi = self.cell_of(center + extent);
+        let r2 = radius * radius;
+
+        // Cells can share a bucket, which must only be searched once
+        let mut seen: Vec<usize> = Vec::new();
+        for x in lo[0]..=hi[0] {
+            for y in lo[1]..=hi[1] {
+                for z in lo[2]..=hi[2] {
+                    let b = self.bucket([x, y, z]);
+                    if seen.contains(&b) {
+                        continue;
+                    }
+                    seen.push(b);
+                    for (p, item) in &self.items[self.starts[b]..self.starts[b + 1]] {
+                        if (*p - center).len_squared() <= r2 {
+                            f(*p, item);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, p: Point) -> [i64; 3] {
+        [p.x, p.y, p.z].map(|c| (c / self.cell).floor() as i64)
+    }
+
+    fn bucket(&self, cell: [i64; 3]) -> usize {
+        let h = hash_keys(&cell.map(|c| c as u64));
+        (h % (self.starts.len() - 1) as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut point = || Point::from([0; 3].map(|_| rng.gen_range(-2.0..2.0)));
+        let points: Vec<(Point, usize)> = (0..2000).map(|i| (point(), i)).collect();
+        let grid = HashGrid::new(points.clone(), 0.3);
+        assert_eq!(2000, grid.items.len());
+
+        for _ in 0..50 {
+            let center = point();
+            for radius in [0.1, 0.3, 0.7] {
+                let mut found = Vec::new();
+                grid.query(center, radius, |_, &i| found.push(i));
+                found.sort_unstable();
+                let expected: Vec<usize> = points
+                    .iter()
+                    .filter(|(p, _)| (*p - center).len() <= radius)
+                    .map(|&(_, i)| i)
+                    .collect();
+                assert_eq!(expected, found);
+            }
+        }
+
+        let empty = HashGrid::<()>::new(Vec::new(), 1.0);
+        assert!(empty.items.is_empty());
+        empty.query(Point::ORIGIN, 1.0, |_, _| panic!());
+    }
+}
@@ -0,0 +1,31 @@
+use crate::Float;
+
+/// Mix a 64-bit value into a well-distributed 64-bit hash.
+///
+/// This is the finalizer from SplitMix64. It's cheap, has good avalanche
+/// behaviour, and is a bijection, so distinct inputs never collide. Handy for
+/// deriving independent random streams from structured keys like pixel
+/// coordinates.
+///
+/// See: <https://prng.di.unimi.it/splitmix64.c>
+#[inline]
+pub fn mix64(v: u64) -> u64 {
+    let mut z = v.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Hash a sequence of keys into a single 64-bit value.
+#[inline]
+pub fn hash_keys(keys: &[u64]) -> u64 {
+    keys.iter().fold(0, |h, &k| mix64(h ^ k))
+}
+
+/// Map a hash to `[0, 1)` using its top 53 bits.
+#[inline]
+pub fn hash_to_unit(h: u64) -> Float {
+    let u = ((h >> 11) as f64 * (1.0 / (1u64 << 53) as f64)) as Float;
+    // Rounding to `f32` can reach 1
+    u.min(1.0 - Float::EPSILON / 2.0)
+}
@@ -0,0 +1,181 @@
+use crate::Float;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[cfg(all(feature = "simd", not(feature = "f32")))]
+type Inner = wide::f64x4;
+
+#[cfg(all(feature = "simd", feature = "f32"))]
+type Inner = wide::f32x4;
+
+#[cfg(not(feature = "simd"))]
+type Inner = [Float; 4];
+
+/// Four [`Float`]s operated on in lockstep.
+///
+/// With the `simd` feature, this wraps a `wide` vector type and each
+/// operation is a single SIMD instruction (where the target supports it).
+/// Without it, operations loop over a plain array, which the compiler can
+/// often still vectorize. Either way, results match the scalar operations
+/// lane for lane, including how [`min`] and [`max`] treat NaNs.
+///
+/// [`min`]: Self::min
+/// [`max`]: Self::max
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Float4(Inner);
+
+impl Float4 {
+    /// Construct with the same value in every lane.
+    #[inline]
+    pub fn splat(n: Float) -> Self {
+        #[cfg(feature = "simd")]
+        return Self(Inner::splat(n));
+        #[cfg(not(feature = "simd"))]
+        return Self([n; 4]);
+    }
+
+    /// Construct from an array of lanes.
+    #[inline]
+    pub fn new(lanes: [Float; 4]) -> Self {
+        #[cfg(feature = "simd")]
+        return Self(Inner::new(lanes));
+        #[cfg(not(feature = "simd"))]
+        return Self(lanes);
+    }
+
+    /// The lanes as an array.
+    #[inline]
+    pub fn to_array(self) -> [Float; 4] {
+        #[cfg(feature = "simd")]
+        return self.0.to_array();
+        #[cfg(not(feature = "simd"))]
+        return self.0;
+    }
+
+    /// Lane-wise minimum. If one lane is NaN, the other is chosen.
+    #[inline]
+    pub fn min(self, rhs: Self) -> Self {
+        #[cfg(feature = "simd")]
+        return Self(self.0.min(rhs.0));
+        #[cfg(not(feature = "simd"))]
+        return self.zip(rhs, Float::min);
+    }
+
+    /// Lane-wise maximum. If one lane is NaN, the other is chosen.
+    #[inline]
+    pub fn max(self, rhs: Self) -> Self {
+        #[cfg(feature = "simd")]
+        return Self(self.0.max(rhs.0));
+        #[cfg(not(feature = "simd"))]
+        return self.zip(rhs, Float::max);
+    }
+
+    #[cfg(not(feature = "simd"))]
+    #[inline]
+    fn zip(self, rhs: Self, f: impl Fn(Float, Float) -> Float) -> Self {
+        Self(std::array::from_fn(|i| f(self.0[i], rhs.0[i])))
+    }
+}
+
+macro_rules! lane_op {
+    ($trait:ident, $fn:ident, $op:tt) => {
+        impl $trait for Float4 {
+            type Output = Self;
+
+            #[inline]
+            fn $fn(self, rhs: Self) -> Self::Output {
+                #[cfg(feature = "simd")]
+                return Self(self.0 $op rhs.0);
+                #[cfg(not(feature = "simd"))]
+                return self.zip(rhs, |a, b| a $op b);
+            }
+        }
+    };
+}
+
+lane_op!(Add, add, +);
+lane_op!(Sub, sub, -);
+lane_op!(Mul, mul, *);
+lane_op!(Div, div, /);
+
+/// Four 3-vectors in structure-of-arrays layout: one [`Float4`] per
+/// component.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Vector4 {
+    pub x: Float4,
+    pub y: Float4,
+    pub z: Float4,
+}
+
+impl Vector4 {
+    /// Construct with the same vector in every lane.
+    #[inline]
+    pub fn splat(v: [Float; 3]) -> Self {
+        Self {
+            x: Float4::splat(v[0]),
+            y: Float4::splat(v[1]),
+            z: Float4::splat(v[2]),
+        }
+    }
+
+    /// Transpose four vectors into lanes.
+    #[inline]
+    pub fn new(vs: [[Float; 3]; 4]) -> Self {
+        Self {
+            x: Float4::new(vs.map(|v| v[0])),
+            y: Float4::new(vs.map(|v| v[1])),
+            z: Float4::new(vs.map(|v| v[2])),
+        }
+    }
+
+    /// Lane-wise dot product.
+    #[inline]
+    pub fn dot(self, rhs: Self) -> Float4 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    /// Lane-wise cross product.
+    #[inline]
+    pub fn cross(self, rhs: Self) -> Self {
+        Self {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+}
+
+impl Sub for Vector4 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_scalar() {
+        let a = Float4::new([1.0, -2.0, Float::NAN, 4.0]);
+        let b = Float4::new([3.0, 5.0, 1.0, Float::INFINITY]);
+        let sum = (a + b).to_array();
+        assert_eq!([4.0, 3.0], [sum[0], sum[1]]);
+        assert!(sum[2].is_nan());
+        let quot = (a / b).to_array();
+        assert_eq!([1.0 / 3.0, -0.4], [quot[0], quot[1]]);
+        assert_eq!([1.0, -2.0, 1.0, 4.0], a.min(b).to_array());
+        assert_eq!([3.0, 5.0, 1.0, Float::INFINITY], a.max(b).to_array());
+
+        let u = Vector4::splat([1.0, 0.0, 0.0]);
+        let v = Vector4::new([[0.0, 1.0, 0.0]; 4]);
+        assert_eq!([1.0; 4], u.cross(v).z.to_array());
+        assert_eq!([0.0; 4], u.dot(v).to_array());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::Float;
+
+/// A piecewise-linear function, defined by a table of `(x, y)` points.
+///
+/// This is the natural representation for measured data such as published
+/// reflectance or illuminant spectra, which are tabulated at irregular
+/// wavelengths. Between the tabulated points the function is linearly
+/// interpolated. Outside them, it's held constant at the nearest endpoint
+/// value, so data that stops short of the visible range doesn't fall off a
+/// cliff.
+///
+/// ```
+/// use gremlin::math::PiecewiseLinearFn;
+///
+/// let f = PiecewiseLinearFn::new([0.0, 1.0, 3.0], [0.0, 2.0, 2.0]);
+/// assert_eq!(1.0, f.y(0.5));
+/// assert_eq!(2.0, f.y(10.0));
+/// assert_eq!(5.0, f.integrate(0.0, 3.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseLinearFn {
+    xs: Vec<Float>,
+    ys: Vec<Float>,
+}
+
+impl PiecewiseLinearFn {
+    /// Creates a new function from the given `x` and `y` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the inputs are empty, have different lengths, or if the `x`
+    /// values are not strictly increasing.
+    pub fn new(xs: impl Into<Vec<Float>>, ys: impl Into<Vec<Float>>) -> Self {
+        let xs = xs.into();
+        let ys = ys.into();
+        assert!(!xs.is_empty(), "piecewise-linear function requires points");
+        assert_eq!(xs.len(), ys.len(), "x and y lengths must match");
+        assert!(
+            xs.windows(2).all(|w| w[0] < w[1]),
+            "x values must be strictly increasing"
+        );
+        Self { xs, ys }
+    }
+
+    /// The tabulated `x` values.
+    #[inline]
+    pub fn xs(&self) -> &[Float] {
+        &self.xs
+    }
+
+    /// The tabulated `y` values.
+    #[inline]
+    pub fn ys(&self) -> &[Float] {
+        &self.ys
+    }
+
+    /// Evaluate the function.
+    pub fn y(&self, x: Float) -> Float {
+        let last = self.xs.len() - 1;
+        if x <= self.xs[0] {
+            return self.ys[0];
+        }
+        if x >= self.xs[last] {
+            return self.ys[last];
+        }
+
+        // Index of the first point strictly greater than x. The checks above
+        // guarantee 1 <= i <= last.
+        let i = self.xs.partition_point(|&xi| xi <= x);
+        let (x0, x1) = (self.xs[i - 1], self.xs[i]);
+        let (y0, y1) = (self.ys[i - 1], self.ys[i]);
+        let t = (x - x0) / (x1 - x0);
+        y0 + t * (y1 - y0)
+    }
+
+    /// The definite integral over `[a, b]`.
+    ///
+    /// Exact, since the function is linear between consecutive breakpoints.
+    /// Returns a negative value if `b < a`.
+    pub fn integrate(&self, a: Float, b: Float) -> Float {
+        if b < a {
+            return -self.integrate(b, a);
+        }
+
+        let interior = self.xs.iter().copied().filter(|&x| a < x && x < b);
+        let points: Vec<Float> = std::iter::once(a)
+            .chain(interior)
+            .chain(std::iter::once(b))
+            .collect();
+
+        points
+            .windows(2)
+            .map(|w| 0.5 * (w[1] - w[0]) * (self.y(w[0]) + self.y(w[1])))
+            .sum()
+    }
+
+    /// The average value over `[a, b]`.
+    ///
+    /// Falls back to the point value if the interval is empty.
+    pub fn average_value(&self, a: Float, b: Float) -> Float {
+        match a == b {
+            true => self.y(a),
+            false => self.integrate(a, b) / (b - a),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn tent() -> PiecewiseLinearFn {
+        PiecewiseLinearFn::new([0.0, 1.0, 2.0], [0.0, 1.0, 0.0])
+    }
+
+    #[test]
+    fn evaluate() {
+        let f = tent();
+        assert_eq!(0.0, f.y(-1.0));
+        assert_eq!(0.0, f.y(0.0));
+        assert_eq!(0.5, f.y(0.5));
+        assert_eq!(1.0, f.y(1.0));
+        assert_eq!(0.25, f.y(1.75));
+        assert_eq!(0.0, f.y(3.0));
+    }
+
+    #[test]
+    fn integrate() {
+        let f = tent();
+        assert_relative_eq!(1.0, f.integrate(0.0, 2.0));
+        assert_relative_eq!(1.0, f.integrate(-5.0, 5.0));
+        assert_relative_eq!(0.375, f.integrate(0.5, 1.0));
+        assert_relative_eq!(-0.375, f.integrate(1.0, 0.5));
+        assert_relative_eq!(0.0, f.integrate(1.0, 1.0));
+    }
+
+    #[test]
+    fn extrapolate_constant() {
+        let f = PiecewiseLinearFn::new([1.0, 2.0], [3.0, 5.0]);
+        assert_relative_eq!(3.0, f.integrate(0.0, 1.0));
+        assert_relative_eq!(5.0, f.average_value(4.0, 6.0));
+        assert_relative_eq!(4.0, f.average_value(1.0, 2.0));
+    }
+}
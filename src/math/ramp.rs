@@ -0,0 +1,252 @@
+use crate::Float;
+
+/// How a [`Ramp`] interpolates between its knots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RampInterpolation {
+    /// Straight lines between knots, like [`PiecewiseLinearFn`].
+    ///
+    /// [`PiecewiseLinearFn`]: super::PiecewiseLinearFn
+    Linear,
+    /// An S-curve between each pair of knots, flat at every knot. Good for
+    /// eased transitions where each knot is a plateau.
+    Smoothstep,
+    /// A smooth cubic through all the knots (Fritsch-Carlson monotone
+    /// Hermite spline). Never overshoots, so a ramp between `0` and `1` stays
+    /// within `[0, 1]` and monotonic data stays monotonic.
+    #[default]
+    Cubic,
+}
+
+/// A 1D curve through editable knots, for remapping procedural controls.
+///
+/// Ramps are the usual artist-facing way to shape a parameter: light falloff
+/// over distance, density over height in a volume, or a texture value
+/// remapped through a color-ramp-style curve. Outside the knots the ramp is
+/// held constant at the nearest knot's value, as with
+/// [`PiecewiseLinearFn`].
+///
+/// ```
+/// use gremlin::math::Ramp;
+///
+/// let mut falloff = Ramp::new([0.0, 10.0], [1.0, 0.0]);
+/// falloff.insert(2.0, 0.9);
+/// assert_eq!(0.9, falloff.y(2.0));
+/// assert!(falloff.y(5.0) < 0.9 && falloff.y(5.0) > 0.0);
+/// assert_eq!(0.0, falloff.y(50.0));
+/// ```
+///
+/// [`PiecewiseLinearFn`]: super::PiecewiseLinearFn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ramp {
+    xs: Vec<Float>,
+    ys: Vec<Float>,
+    interpolation: RampInterpolation,
+    // Tangents at each knot for cubic interpolation, kept up to date on edits
+    slopes: Vec<Float>,
+}
+
+impl Ramp {
+    /// Creates a new cubic ramp through the given knots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the inputs are empty, have different lengths, or if the `x`
+    /// values are not strictly increasing.
+    pub fn new(xs: impl Into<Vec<Float>>, ys: impl Into<Vec<Float>>) -> Self {
+        let xs = xs.into();
+        let ys = ys.into();
+        assert!(!xs.is_empty(), "ramp requires knots");
+        assert_eq!(xs.len(), ys.len(), "x and y lengths must match");
+        assert!(
+            xs.windows(2).all(|w| w[0] < w[1]),
+            "x values must be strictly increasing"
+        );
+        let mut ramp = Self {
+            xs,
+            ys,
+            interpolation: RampInterpolation::default(),
+            slopes: Vec::new(),
+        };
+        ramp.update_slopes();
+        ramp
+    }
+
+    /// Set how the ramp interpolates between knots.
+    pub fn interpolation(mut self, interpolation: RampInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// The knots' `x` values.
+    #[inline]
+    pub fn xs(&self) -> &[Float] {
+        &self.xs
+    }
+
+    /// The knots' `y` values.
+    #[inline]
+    pub fn ys(&self) -> &[Float] {
+        &self.ys
+    }
+
+    /// Add a knot, replacing any existing knot at the same `x`. Returns the
+    /// index of the knot.
+    pub fn insert(&mut self, x: Float, y: Float) -> usize {
+        let idx = self.xs.partition_point(|&xi| xi < x);
+        if self.xs.get(idx) == Some(&x) {
+            self.ys[idx] = y;
+        } else {
+            self.xs.insert(idx, x);
+            self.ys.insert(idx, y);
+        }
+        self.update_slopes();
+        idx
+    }
+
+    /// Remove the knot at the given index, returning its `(x, y)`.
+    ///
+    /// Returns `None` if the index is out of range, or if it's the only knot
+    /// left, since a ramp needs at least one.
+    pub fn remove(&mut self, idx: usize) -> Option<(Float, Float)> {
+        if idx >= self.xs.len() || self.xs.len() == 1 {
+            return None;
+        }
+        let knot = (self.xs.remove(idx), self.ys.remove(idx));
+        self.update_slopes();
+        Some(knot)
+    }
+
+    /// Evaluate the ramp.
+    pub fn y(&self, x: Float) -> Float {
+        let last = self.xs.len() - 1;
+        if x <= self.xs[0] {
+            return self.ys[0];
+        }
+        if x >= self.xs[last] {
+            return self.ys[last];
+        }
+
+        let i = self.xs.partition_point(|&xi| xi <= x);
+        let (x0, x1) = (self.xs[i - 1], self.xs[i]);
+        let (y0, y1) = (self.ys[i - 1], self.ys[i]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+
+        match self.interpolation {
+            RampInterpolation::Linear => y0 + t * (y1 - y0),
+            RampInterpolation::Smoothstep => y0 + t * t * (3.0 - 2.0 * t) * (y1 - y0),
+            RampInterpolation::Cubic => {
+                // Cubic Hermite basis
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+                let h10 = t3 - 2.0 * t2 + t;
+                let h01 = -2.0 * t3 + 3.0 * t2;
+                let h11 = t3 - t2;
+                h00 * y0 + h10 * h * self.slopes[i - 1] + h01 * y1 + h11 * h * self.slopes[i]
+            }
+        }
+    }
+
+    // Fritsch-Carlson tangents: secant averages, zeroed at extrema and
+    // clamped so no segment overshoots.
+    //
+    // See: <https://en.wikipedia.org/wiki/Monotone_cubic_interpolation>
+    fn update_slopes(&mut self) {
+        let n = self.xs.len();
+        if n < 2 {
+            self.slopes = vec![0.0; n];
+            return;
+        }
+
+        let secants: Vec<Float> = (0..n - 1)
+            .map(|k| (self.ys[k + 1] - self.ys[k]) / (self.xs[k + 1] - self.xs[k]))
+            .collect();
+
+        let mut m = vec![0.0; n];
+        m[0] = secants[0];
+        m[n - 1] = secants[n - 2];
+        for k in 1..n - 1 {
+            let (d0, d1) = (secants[k - 1], secants[k]);
+            if d0 * d1 > 0.0 {
+                m[k] = 0.5 * (d0 + d1);
+            }
+        }
+
+        for (k, &d) in secants.iter().enumerate() {
+            if d == 0.0 {
+                m[k] = 0.0;
+                m[k + 1] = 0.0;
+                continue;
+            }
+            let a = m[k] / d;
+            let b = m[k + 1] / d;
+            let s = a * a + b * b;
+            if s > 9.0 {
+                let tau = 3.0 / s.sqrt();
+                m[k] = tau * a * d;
+                m[k + 1] = tau * b * d;
+            }
+        }
+        self.slopes = m;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn interpolates_knots() {
+        let xs = [0.0, 1.0, 2.5, 4.0];
+        let ys = [0.0, 2.0, 1.0, 3.0];
+        for interp in [
+            RampInterpolation::Linear,
+            RampInterpolation::Smoothstep,
+            RampInterpolation::Cubic,
+        ] {
+            let ramp = Ramp::new(xs, ys).interpolation(interp);
+            for (&x, &y) in xs.iter().zip(ys.iter()) {
+                assert_relative_eq!(y, ramp.y(x));
+            }
+            assert_eq!(0.0, ramp.y(-1.0));
+            assert_eq!(3.0, ramp.y(10.0));
+        }
+        let linear = Ramp::new(xs, ys).interpolation(RampInterpolation::Linear);
+        assert_relative_eq!(1.0, linear.y(0.5));
+        let smooth = Ramp::new(xs, ys).interpolation(RampInterpolation::Smoothstep);
+        assert_relative_eq!(1.0, smooth.y(0.5));
+        assert!(smooth.y(0.1) < linear.y(0.1));
+    }
+
+    #[test]
+    fn cubic_is_monotone() {
+        // A sharp step that a Catmull-Rom spline would overshoot
+        let ramp = Ramp::new([0.0, 1.0, 1.1, 2.0], [0.0, 0.0, 1.0, 1.0]);
+        let samples: Vec<Float> = (0..=200).map(|i| ramp.y(i as Float / 100.0)).collect();
+        assert!(samples.windows(2).all(|w| w[0] <= w[1]));
+        assert!(samples.iter().all(|&y| (0.0..=1.0).contains(&y)));
+
+        // Linear data is reproduced exactly
+        let ramp = Ramp::new([0.0, 1.0, 3.0], [1.0, 3.0, 7.0]);
+        assert_relative_eq!(2.0, ramp.y(0.5), epsilon = 1e-12);
+        assert_relative_eq!(6.0, ramp.y(2.5), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn edit_knots() {
+        let mut ramp = Ramp::new([0.0, 1.0], [0.0, 1.0]);
+        assert_eq!(1, ramp.insert(0.5, 0.8));
+        assert_eq!(&[0.0, 0.5, 1.0], ramp.xs());
+        assert_eq!(1, ramp.insert(0.5, 0.2));
+        assert_eq!(&[0.0, 0.2, 1.0], ramp.ys());
+        assert_relative_eq!(0.2, ramp.y(0.5));
+
+        assert_eq!(Some((0.5, 0.2)), ramp.remove(1));
+        assert_eq!(None, ramp.remove(5));
+        assert_eq!(Some((0.0, 0.0)), ramp.remove(0));
+        assert_eq!(None, ramp.remove(0));
+        assert_eq!(1.0, ramp.y(-3.0));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    color::RGB,
+    film::Buffer,
+    geo::{Transform, Unit, Vector},
+    Float,
+};
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// Coefficients of a function on the sphere of directions, in real spherical
+/// harmonics (SH), with `N` coefficients per color channel.
+///
+/// `N` is the square of the number of bands: `Sh<4>` stops at `l = 1`,
+/// [`Sh9`] at `l = 2` and [`Sh16`] at `l = 3`. Coefficients are in the usual
+/// order (band by band, `m = -l..=l` within each), over world `x`, `y` and
+/// `z`, without the Condon-Shortley phase.
+///
+/// Few bands keep only the broad shape of a function, which is all diffuse
+/// lighting needs: for any lighting, [`irradiance`] from nine coefficients
+/// is within a few percent of exact.
+///
+/// [`irradiance`]: Self::irradiance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sh<const N: usize> {
+    pub coeffs: [RGB; N],
+}
+
+/// Second order (`l <= 2`) spherical harmonics, the usual choice for
+/// irradiance.
+pub type Sh9 = Sh<9>;
+
+/// Third order (`l <= 3`) spherical harmonics.
+pub type Sh16 = Sh<16>;
+
+impl<const N: usize> Default for Sh<N> {
+    fn default() -> Self {
+        Self {
+            coeffs: [RGB::default(); N],
+        }
+    }
+}
+
+impl<const N: usize> Sh<N> {
+    /// The number of bands, `l_max + 1`.
+    pub const BANDS: usize = match N {
+        1 => 1,
+        4 => 2,
+        9 => 3,
+        16 => 4,
+        _ => panic!("SH coefficient count must be 1, 4, 9 or 16"),
+    };
+
+    /// The SH basis functions in the direction `w`.
+    pub fn basis(w: Unit) -> [Float; N] {
+        let all = basis16(w);
+        let mut out = [0.0; N];
+        out.copy_from_slice(&all[..Self::BANDS * Self::BANDS]);
+        out
+    }
+
+    /// Add `radiance` arriving from direction `w`, weighted by `weight`.
+    ///
+    /// Adding samples from `n` uniformly distributed directions, each
+    /// weighted by `4π / n`, projects the light onto the basis.
+    #[inline]
+    pub fn add(&mut self, w: Unit, radiance: RGB, weight: Float) {
+        for (c, y) in self.coeffs.iter_mut().zip(Self::basis(w)) {
+            *c += radiance * (y * weight);
+        }
+    }
+
+    /// Project radiance samples from uniformly distributed directions.
+    pub fn from_samples(samples: impl IntoIterator<Item = (Unit, RGB)>) -> Self {
+        let mut sum = Self::default();
+        let mut count = 0;
+        for (w, radiance) in samples {
+            sum.add(w, radiance, 1.0);
+            count += 1;
+        }
+        let scale = match count {
+            0 => 0.0,
+            n => 4.0 * PI / n as Float,
+        };
+        sum.coeffs.iter_mut().for_each(|c| *c *= scale);
+        sum
+    }
+
+    /// Project an environment map in the equirectangular (latitude and
+    /// longitude) layout, with `+y` up.
+    ///
+    /// The top row is straight up and the bottom straight down. Columns go
+    /// once around, starting towards `+x` and turning towards `+z`. Each
+    /// pixel is weighted by the solid angle it covers.
+    pub fn from_equirect(map: &Buffer<RGB>) -> Self {
+        let (width, height) = map.dimensions();
+        let mut sh = Self::default();
+        for (x, y, &radiance) in map.pixel_iter() {
+            let theta = PI * (y as Float + 0.5) / height as Float;
+            let phi = 2.0 * PI * (x as Float + 0.5) / width as Float;
+            let (sin_theta, cos_theta) = theta.sin_cos();
+            let w = Vector::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+            let area = (2.0 * PI / width as Float) * (PI / height as Float) * sin_theta;
+            if let Ok(w) = Unit::try_from(w) {
+                sh.add(w, radiance, area);
+            }
+        }
+        sh
+    }
+
+    /// The projected function in the direction `w`.
+    pub fn evaluate(&self, w: Unit) -> RGB {
+        let y = Self::basis(w);
+        (0..N).fold(RGB::default(), |acc, i| acc + self.coeffs[i] * y[i])
+    }
+
+    /// The irradiance on a surface facing `normal`, if the coefficients are
+    /// incoming radiance: the light weighted by the cosine to the normal.
+    ///
+    /// Divide by `π` and multiply by albedo for the light a diffuse surface
+    /// reflects. The cosine has no `l = 3` part, so that band is ignored.
+    ///
+    /// See: Ramamoorthi and Hanrahan, "An Efficient Representation for
+    /// Irradiance Environment Maps" (2001).
+    pub fn irradiance(&self, normal: Unit) -> RGB {
+        // The cosine lobe's coefficients for each band
+        const BAND: [Float; 4] = [PI, 2.0 * PI / 3.0, PI / 4.0, 0.0];
+        let y = Self::basis(normal);
+        (0..N).fold(RGB::default(), |acc, i| {
+            acc + self.coeffs[i] * (BAND[band(i)] * y[i])
+        })
+    }
+
+    /// The coefficients of the function turned by `rotation`, which must be
+    /// a pure rotation: the new function's value in direction `R w` is the
+    /// old one's in `w`.
+    ///
+    /// Rotation keeps each band separate, so this is exact.
+    pub fn rotated(&self, rotation: &Transform) -> Self {
+        let inverse = rotation.inverse();
+        let mut out = Self::default();
+        for l in 0..Self::BANDS {
+            let (start, len) = (l * l, 2 * l + 1);
+            // Within a band, the basis at the rotated directions is a linear
+            // mix of the basis at the original ones. Find the mix by least
+            // squares over directions spread evenly over the sphere, where
+            // it's exact and well conditioned.
+            let dirs = fibonacci_sphere(4 * len);
+            let at = |w: Unit| Self::basis(w)[start..start + len].to_vec();
+            let before: Vec<Vec<Float>> = dirs.iter().map(|&w| at(w)).collect();
+            let after: Vec<Vec<Float>> = dirs
+                .iter()
+                .map(|&w| at(Unit::try_from(inverse.apply_vector(w.into())).unwrap_or(w)))
+                .collect();
+
+            // Solve (BᵀB) M = BᵀA
+            let mut normal = vec![vec![0.0; 2 * len]; len];
+            for (b, a) in before.iter().zip(&after) {
+                for i in 0..len {
+                    for j in 0..len {
+                        normal[i][j] += b[i] * b[j];
+                        normal[i][len + j] += b[i] * a[j];
+                    }
+                }
+            }
+            let mix = solve(normal, len);
+
+            for (i, row) in mix.iter().enumerate() {
+                out.coeffs[start + i] = (0..len).fold(RGB::default(), |acc, j| {
+                    acc + self.coeffs[start + j] * row[j]
+                });
+            }
+        }
+        out
+    }
+}
+
+// The band of the coefficient at index `i`
+#[inline]
+fn band(i: usize) -> usize {
+    match i {
+        0 => 0,
+        1..=3 => 1,
+        4..=8 => 2,
+        _ => 3,
+    }
+}
+
+// All 16 basis functions up to `l = 3`
+fn basis16(w: Unit) -> [Float; 16] {
+    let (x, y, z) = (w.x(), w.y(), w.z());
+    let (x2, y2, z2) = (x * x, y * y, z * z);
+    [
+        0.282095,
+        0.488603 * y,
+        0.488603 * z,
+        0.488603 * x,
+        1.092548 * x * y,
+        1.092548 * y * z,
+        0.315392 * (3.0 * z2 - 1.0),
+        1.092548 * x * z,
+        0.546274 * (x2 - y2),
+        0.590044 * y * (3.0 * x2 - y2),
+        2.890611 * x * y * z,
+        0.457046 * y * (5.0 * z2 - 1.0),
+        0.373176 * z * (5.0 * z2 - 3.0),
+        0.457046 * x * (5.0 * z2 - 1.0),
+        1.445306 * z * (x2 - y2),
+        0.590044 * x * (x2 - 3.0 * y2),
+    ]
+}
+
+// `n` directions spread evenly over the sphere
+fn fibonacci_sphere(n: usize) -> Vec<Unit> {
+    let golden = PI * (3.0 - Float::sqrt(5.0));
+    (0..n)
+        .map(|i| {
+            let z = 1.0 - (2.0 * i as Float + 1.0) / n as Float;
+            let r = (1.0 - z * z).sqrt();
+            let phi = golden * i as Float;
+            Unit::try_from(Vector::new(r * phi.cos(), r * phi.sin(), z)).unwrap()
+        })
+        .collect()
+}
+
+// Gauss-Jordan elimination with partial pivoting, on an `n` by `2n`
+// augmented matrix. Returns the solution, so row `i` holds the weights of
+// the old coefficients in new coefficient `i`.
+fn solve(mut m: Vec<Vec<Float>>, n: usize) -> Vec<Vec<Float>> {
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+            .unwrap();
+        m.swap(col, pivot);
+        let p = m[col][col];
+        m[col].iter_mut().for_each(|v| *v /= p);
+        let pivot_row = m[col].clone();
+        for (row, values) in m.iter_mut().enumerate() {
+            if row != col {
+                let f = values[col];
+                for (v, p) in values.iter_mut().zip(&pivot_row) {
+                    *v -= f * p;
+                }
+            }
+        }
+    }
+    m.into_iter().map(|row| row[n..].to_vec()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Radians;
+    use rand::prelude::*;
+
+    fn red(c: RGB) -> Float {
+        <[Float; 3]>::from(c)[0]
+    }
+
+    // A lumpy function with parts in every band up to 3
+    fn lumpy(w: Unit) -> RGB {
+        let (x, y, z) = (w.x(), w.y(), w.z());
+        RGB::splat(1.0 + 0.5 * x - 0.3 * y * z + 0.2 * z * z * z + 0.4 * x * y)
+    }
+
+    #[test]
+    fn orthonormal() {
+        let dirs = fibonacci_sphere(4000);
+        let weight = 4.0 * PI / dirs.len() as Float;
+        for i in 0..16 {
+            for j in 0..16 {
+                let dot: Float = dirs
+                    .iter()
+                    .map(|&w| basis16(w)[i] * basis16(w)[j] * weight)
+                    .sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1e-2, "{} {} {}", i, j, dot);
+            }
+        }
+    }
+
+    #[test]
+    fn project_and_rotate() {
+        // Up to `l = 3`, projection recovers the function
+        let samples = fibonacci_sphere(4000).into_iter().map(|w| (w, lumpy(w)));
+        let sh = Sh16::from_samples(samples);
+        let mut rng = StdRng::seed_from_u64(11);
+        let random = |rng: &mut StdRng| {
+            let v = Vector::new(rng.gen(), rng.gen(), rng.gen()) - Vector::splat(0.5);
+            Unit::try_from(v).unwrap()
+        };
+        for _ in 0..20 {
+            let w = random(&mut rng);
+            assert!((red(sh.evaluate(w)) - red(lumpy(w))).abs() < 1e-2);
+        }
+
+        // Rotation turns the function with the directions
+        let rotation = Transform::rotate(
+            Radians(0.7),
+            Unit::try_from(Vector::new(1.0, 2.0, -1.0)).unwrap(),
+        );
+        let turned = sh.rotated(&rotation);
+        for _ in 0..20 {
+            let w = random(&mut rng);
+            let rw = Unit::try_from(rotation.apply_vector(w.into())).unwrap();
+            assert!((red(turned.evaluate(rw)) - red(sh.evaluate(w))).abs() < 1e-9);
+        }
+        let low = Sh9 {
+            coeffs: sh.coeffs[..9].try_into().unwrap(),
+        };
+        assert_eq!(&low.rotated(&rotation).coeffs[..], &turned.coeffs[..9]);
+    }
+
+    #[test]
+    fn environment_map() {
+        // The sky half of a map, lit from above
+        let mut map = Buffer::<RGB>::new(64, 32);
+        for (_, y, pixel) in map.pixel_iter_mut() {
+            *pixel = RGB::splat(if y < 16 { 1.0 } else { 0.0 });
+        }
+        let sh = Sh9::from_equirect(&map);
+        let up = red(sh.irradiance(Unit::Y_AXIS));
+        let side = red(sh.irradiance(Unit::X_AXIS));
+        assert!((up - PI).abs() < 0.05 * PI, "{}", up);
+        assert!((side - PI / 2.0).abs() < 0.05 * PI, "{}", side);
+        assert!(red(sh.irradiance(-Unit::Y_AXIS)).abs() < 0.05 * PI);
+    }
+}
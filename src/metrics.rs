@@ -43,6 +43,12 @@ impl Counter {
     }
 }
 
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// A [`f64`]-valued metric that can be incremented by arbitrary amounts.
 pub struct Quantity(AtomicU64);
 
@@ -82,6 +88,29 @@ impl Quantity {
     }
 }
 
+impl Default for Quantity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sum values in a fixed pairwise order.
+///
+/// [`Quantity`] accumulates in whatever order threads happen to finish, so
+/// its low-order bits can differ between runs. When reproducibility matters,
+/// gather per-tile values and reduce them with this instead; the result only
+/// depends on the order of `values`.
+pub fn pairwise_sum(values: &[f64]) -> f64 {
+    match values.len() {
+        0 => 0.0,
+        1 => values[0],
+        n => {
+            let (lhs, rhs) = values.split_at(n / 2);
+            pairwise_sum(lhs) + pairwise_sum(rhs)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +126,12 @@ mod tests {
         assert_eq!(1_000, c.get());
     }
 
+    #[test]
+    fn pairwise() {
+        assert_eq!(0.0, pairwise_sum(&[]));
+        assert_eq!(10.0, pairwise_sum(&[1.0, 2.0, 3.0, 4.0]));
+    }
+
     #[test]
     fn quantity_inc() {
         let q = Quantity::new();
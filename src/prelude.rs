@@ -3,11 +3,28 @@
 //! The purpose of this module is to alleviate imports of common structs and
 //! traits by adding a glob import to the top of modules.
 //!
+//! It holds what nearly every program driving Gremlin needs: [`Float`], the
+//! geometric primitives, the traits for cameras, shapes, materials and
+//! integrators (so their methods can be called), and the color and film
+//! types renders end up in. Everything more specialized is imported from
+//! its own module.
+//!
 //! ```
-//! # #![allow(unused_imports)]
 //! use gremlin::prelude::*;
+//!
+//! let ray = Ray::new(Point::new(0.0, 0.0, -1.0), Vector::Z_AXIS);
+//! let film = RGBFilm::new(4, 4);
+//! let rotate = Matrix::rotate(Degrees(90.0), Unit::Y_AXIS);
+//! # let _ = (ray, film, rotate);
 //! ```
 
 pub use crate::camera::Camera;
-pub use crate::shape::Shape;
+pub use crate::color::{Color, RGB, XYZ};
+pub use crate::film::{Buffer, Film, RGBFilm, SpectralFilm};
+pub use crate::geo::{
+    Bounds, Degrees, Length, Matrix, Point, Radians, Ray, Transform, Unit, Vector,
+};
+pub use crate::integrator::Integrator;
+pub use crate::material::BSDF;
+pub use crate::shape::{Bounded, Shape};
 pub use crate::Float;
@@ -0,0 +1,251 @@
+//! # Live render preview over HTTP.
+//!
+//! Only available with the `preview` feature. Starts a tiny HTTP server on a
+//! background thread that serves the most recently published snapshot of a
+//! render, so a remote or headless render can be watched from a browser:
+//!
+//! * `/` - a page that shows the snapshot and stats, refreshing periodically
+//! * `/snapshot.jpg` - the latest snapshot
+//! * `/stats.json` - the latest render statistics
+//!
+//! ```no_run
+//! use gremlin::film::RGBFilm;
+//! use gremlin::preview::{PreviewServer, PreviewStats};
+//!
+//! let film = RGBFilm::new(800, 600);
+//! let server = PreviewServer::bind("127.0.0.1:8080").unwrap();
+//! // ... after each pass ...
+//! server.publish(&film.to_snapshot(), PreviewStats::default());
+//! ```
+//!
+//! Until the first snapshot is published, the snapshot and stats are
+//! `503 Service Unavailable`.
+//!
+//! This is a diagnostics aid, not a web server: it understands just enough
+//! HTTP to answer `GET`s. Connections are served by a small fixed pool of
+//! threads, and dropped if the client stalls, so a slow client can't hold up
+//! the others for long; connections beyond what the pool has queued are
+//! closed straight away. Snapshots are baseline JPEGs, which browsers show as
+//! soon as they've fully arrived.
+
+use crate::{color::SRGB, film::Buffer};
+use image::{DynamicImage, ImageOutputFormat};
+use std::{
+    io::{self, BufRead, BufReader, Cursor, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+const JPEG_QUALITY: u8 = 85;
+
+// How long a client can stall reading or writing before it's dropped
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Threads serving connections, and connections waiting for one
+const WORKERS: usize = 4;
+const QUEUE: usize = 16;
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html><head><title>gremlin</title></head>
+<body style="background:#222;color:#ddd;font-family:monospace">
+<img id="snap" src="/snapshot.jpg"><pre id="stats"></pre>
+<script>
+setInterval(() => {
+  document.getElementById("snap").src = "/snapshot.jpg?" + Date.now();
+  fetch("/stats.json").then(r => r.text()).then(t => document.getElementById("stats").textContent = t);
+}, 2000);
+</script>
+</body></html>"#;
+
+/// Render statistics reported alongside a snapshot.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PreviewStats {
+    /// Samples per pixel taken so far.
+    pub samples: u32,
+    /// Total samples per pixel the render will take.
+    pub target_samples: u32,
+    /// Wall-clock time since the render started. Reported as `null` if
+    /// it isn't finite.
+    pub elapsed_secs: f64,
+    /// Number of rays traced so far.
+    pub rays: u64,
+}
+
+impl PreviewStats {
+    fn to_json(self) -> String {
+        // JSON has no NaN or infinity
+        let elapsed = match self.elapsed_secs.is_finite() {
+            true => self.elapsed_secs.to_string(),
+            false => "null".to_string(),
+        };
+        format!(
+            "{{\"samples\":{},\"target_samples\":{},\"elapsed_secs\":{},\"rays\":{}}}",
+            self.samples, self.target_samples, elapsed, self.rays
+        )
+    }
+}
+
+#[derive(Default)]
+struct Published {
+    jpeg: Vec<u8>,
+    stats: PreviewStats,
+    // Whether anything has been published yet
+    ready: bool,
+}
+
+/// A background HTTP server for watching renders.
+pub struct PreviewServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<Published>>,
+}
+
+impl PreviewServer {
+    /// Start serving on the given address.
+    ///
+    /// The server's threads run for the life of the process.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(Published::default()));
+
+        let (queue, waiting) = mpsc::sync_channel::<TcpStream>(QUEUE);
+        let waiting = Arc::new(Mutex::new(waiting));
+        for _ in 0..WORKERS {
+            let waiting = Arc::clone(&waiting);
+            let state = Arc::clone(&state);
+            thread::Builder::new()
+                .name("gremlin-preview-worker".into())
+                .spawn(move || loop {
+                    // Release the queue before serving, so the others can
+                    // take the next connections
+                    let next = waiting.lock().unwrap().recv();
+                    let Ok(stream) = next else {
+                        break;
+                    };
+                    // A misbehaving client shouldn't take the worker down
+                    let _ = handle(stream, &state);
+                })?;
+        }
+        thread::Builder::new()
+            .name("gremlin-preview".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // Dropping a connection the queue has no room for
+                    // closes it
+                    let _ = queue.try_send(stream);
+                }
+            })?;
+
+        Ok(Self { addr, state })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Publish a new snapshot and stats, replacing the previous ones.
+    pub fn publish<P: SRGB>(&self, snapshot: &Buffer<P>, stats: PreviewStats) {
+        let mut jpeg = Vec::new();
+        let encoded = DynamicImage::ImageRgb8(snapshot.to_image()).write_to(
+            &mut Cursor::new(&mut jpeg),
+            ImageOutputFormat::Jpeg(JPEG_QUALITY),
+        );
+
+        let mut state = self.state.lock().unwrap();
+        if encoded.is_ok() {
+            state.jpeg = jpeg;
+        }
+        state.stats = stats;
+        state.ready = true;
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<Published>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = path.split('?').next().unwrap_or("/");
+
+    let ready = state.lock().unwrap().ready;
+    let (status, content_type, body) = match path {
+        "/" => ("200 OK", "text/html", INDEX_HTML.as_bytes().to_vec()),
+        "/snapshot.jpg" | "/stats.json" if !ready => (
+            "503 Service Unavailable",
+            "text/plain",
+            b"nothing published yet".to_vec(),
+        ),
+        "/snapshot.jpg" => ("200 OK", "image/jpeg", state.lock().unwrap().jpeg.clone()),
+        "/stats.json" => (
+            "200 OK",
+            "application/json",
+            state.lock().unwrap().stats.to_json().into_bytes(),
+        ),
+        _ => ("404 Not Found", "text/plain", b"not found".to_vec()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::RGB;
+    use std::io::Read;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[test]
+    fn serves_stats_and_snapshot() {
+        let server = PreviewServer::bind("127.0.0.1:0").unwrap();
+        let unavailable = get(server.local_addr(), "/snapshot.jpg");
+        assert!(unavailable.starts_with("HTTP/1.1 503"));
+        assert!(get(server.local_addr(), "/").starts_with("HTTP/1.1 200 OK"));
+
+        // Clients that never send their requests don't hold up the others
+        let _stalled: Vec<_> = (0..WORKERS - 1)
+            .map(|_| TcpStream::connect(server.local_addr()).unwrap())
+            .collect();
+
+        let mut snapshot = Buffer::<RGB>::new(4, 4);
+        snapshot
+            .iter_mut()
+            .for_each(|p| *p = RGB::from([0.5, 0.5, 0.5]));
+        let stats = PreviewStats {
+            samples: 3,
+            target_samples: 16,
+            elapsed_secs: f64::NAN,
+            ..Default::default()
+        };
+        server.publish(&snapshot, stats);
+
+        let json = get(server.local_addr(), "/stats.json");
+        assert!(json.starts_with("HTTP/1.1 200 OK"));
+        assert!(json.contains("\"samples\":3"));
+        assert!(json.contains("\"elapsed_secs\":null"));
+
+        let jpeg = get(server.local_addr(), "/snapshot.jpg");
+        assert!(jpeg.contains("Content-Type: image/jpeg"));
+
+        let missing = get(server.local_addr(), "/nope");
+        assert!(missing.starts_with("HTTP/1.1 404"));
+    }
+}
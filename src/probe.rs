@@ -0,0 +1,294 @@
+//! # Irradiance probes.
+//!
+//! Game engines light moving objects from a grid of probes, each storing the
+//! light arriving at one point from every direction, compressed to nine
+//! spherical harmonic coefficients per color channel (see [`Sh9`]). This
+//! bakes such a grid with any [`Integrator`] and writes it out for an engine
+//! to load.
+//!
+//! ```no_run
+//! use gremlin::{geo::Bounds, integrator::Hacky, probe::{ProbeGrid, ProbeVolume}};
+//!
+//! let integrator = Hacky::default();
+//! let bounds = Bounds::from_corners([-5.0, 0.0, -5.0].into(), [5.0, 3.0, 5.0].into());
+//! let volume = ProbeVolume::bake(ProbeGrid::new(bounds, [8, 4, 8]), &integrator, 256, 0);
+//! volume.save("probes.json").unwrap();
+//! ```
+//!
+//! [`Integrator`]: crate::integrator::Integrator
+//! [`Sh9`]: crate::math::Sh9
+
+use crate::{
+    color::RGB,
+    geo::{self, Bounds, Point, Ray},
+    integrator::Integrator,
+    math::{hash_keys, Sh9},
+    Float,
+};
+use rand::prelude::*;
+use rayon::prelude::*;
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const PI: Float = std::f64::consts::PI as Float;
+
+/// Magic bytes at the start of the binary format.
+const MAGIC: &[u8; 4] = b"GSH2";
+
+/// Where to place probes: a regular grid filling some bounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeGrid {
+    bounds: Bounds,
+    counts: [u32; 3],
+}
+
+impl ProbeGrid {
+    /// Place `counts[0] * counts[1] * counts[2]` probes through `bounds`,
+    /// with the outermost probes on its faces. An axis with a single probe
+    /// puts it in the middle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any count is zero, or the bounds are empty.
+    pub fn new(bounds: Bounds, counts: [u32; 3]) -> Self {
+        assert!(
+            counts.iter().all(|&n| n > 0),
+            "Probe counts must be non-zero"
+        );
+        assert!(!bounds.is_empty(), "Probe bounds must not be empty");
+        Self { bounds, counts }
+    }
+
+    /// The bounds the probes fill.
+    #[inline]
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    /// The number of probes along each axis.
+    #[inline]
+    pub fn counts(&self) -> [u32; 3] {
+        self.counts
+    }
+
+    /// The total number of probes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.counts.iter().map(|&n| n as usize).product()
+    }
+
+    /// Whether there are no probes. Never true, as counts are non-zero.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The position of the probe at index `idx`, with `x` varying fastest,
+    /// then `y`, then `z`.
+    pub fn position(&self, idx: usize) -> Point {
+        let [nx, ny, _] = self.counts.map(|n| n as usize);
+        let cell = [idx % nx, (idx / nx) % ny, idx / (nx * ny)];
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        let at = |axis: usize, lo: Float, hi: Float| match self.counts[axis] {
+            1 => 0.5 * (lo + hi),
+            n => lo + (hi - lo) * cell[axis] as Float / (n - 1) as Float,
+        };
+        Point::new(
+            at(0, min.x, max.x),
+            at(1, min.y, max.y),
+            at(2, min.z, max.z),
+        )
+    }
+}
+
+/// A grid of baked probes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeVolume {
+    pub grid: ProbeGrid,
+    /// One per probe, in [`ProbeGrid::position`] order.
+    pub probes: Vec<Sh9>,
+}
+
+impl ProbeVolume {
+    /// Bake each probe from about `samples` rays in all directions, with
+    /// radiance from `integrator`.
+    ///
+    /// Directions are stratified over the sphere, so `samples` is rounded up
+    /// to a square number. The result only depends on `seed`, not on how
+    /// work is split between threads.
+    pub fn bake<I>(grid: ProbeGrid, integrator: &I, samples: u32, seed: u64) -> Self
+    where
+        I: Integrator<RGB>,
+    {
+        let side = (samples as Float).sqrt().ceil().max(1.0) as u32;
+        let weight = 4.0 * PI / (side * side) as Float;
+        let probes = (0..grid.len())
+            .into_par_iter()
+            .map(|idx| {
+                let mut rng = StdRng::seed_from_u64(hash_keys(&[seed, idx as u64]));
+                let origin = grid.position(idx);
+                let mut sh = Sh9::default();
+                for i in 0..side {
+                    for j in 0..side {
+                        let u = (i as Float + rng.gen::<Float>()) / side as Float;
+                        let v = (j as Float + rng.gen::<Float>()) / side as Float;
+                        let w = geo::uniform_sphere([u, v]);
+                        let radiance = integrator.radiance(&Ray::new(origin, w.into()), &mut rng);
+                        sh.add(w, radiance, weight);
+                    }
+                }
+                sh
+            })
+            .collect();
+        Self { grid, probes }
+    }
+
+    /// The probe at grid cell `(x, y, z)`.
+    #[inline]
+    pub fn probe(&self, x: u32, y: u32, z: u32) -> &Sh9 {
+        let [nx, ny, _] = self.grid.counts.map(|n| n as usize);
+        &self.probes[(z as usize * ny + y as usize) * nx + x as usize]
+    }
+
+    /// The volume as JSON: an object with the grid's `min` and `max`
+    /// corners, its `counts` along each axis, and `probes`, an array of 27
+    /// numbers per probe (the red, green and blue of each coefficient in
+    /// turn), in [`ProbeGrid::position`] order.
+    pub fn to_json(&self) -> String {
+        let (min, max) = (self.grid.bounds.min(), self.grid.bounds.max());
+        let [nx, ny, nz] = self.grid.counts;
+        let mut out = format!(
+            "{{\"min\":[{},{},{}],\"max\":[{},{},{}],\"counts\":[{},{},{}],\"probes\":[",
+            min.x, min.y, min.z, max.x, max.y, max.z, nx, ny, nz
+        );
+        for (i, probe) in self.probes.iter().enumerate() {
+            out.push_str(if i == 0 { "[" } else { ",[" });
+            for (j, v) in floats(probe).enumerate() {
+                let sep = if j == 0 { "" } else { "," };
+                write!(out, "{}{}", sep, v).unwrap();
+            }
+            out.push(']');
+        }
+        out.push_str("]}");
+        out
+    }
+
+    /// Write the volume in a compact binary format, all little-endian: the
+    /// magic bytes `GSH2`, the grid's counts as three `u32`s, its `min` and
+    /// `max` corners as six `f32`s, then 27 `f32`s per probe in the same
+    /// order as [`to_json`].
+    ///
+    /// [`to_json`]: Self::to_json
+    pub fn write_binary(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        for n in self.grid.counts {
+            w.write_all(&n.to_le_bytes())?;
+        }
+        let (min, max) = (self.grid.bounds.min(), self.grid.bounds.max());
+        for v in [min.x, min.y, min.z, max.x, max.y, max.z] {
+            w.write_all(&(v as f32).to_le_bytes())?;
+        }
+        for probe in &self.probes {
+            for v in floats(probe) {
+                w.write_all(&(v as f32).to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the volume to a file: as JSON if the extension is `.json`, and
+    /// in the binary format otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut w = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => {
+                w.write_all(self.to_json().as_bytes())?
+            }
+            _ => self.write_binary(&mut w)?,
+        }
+        w.flush()
+    }
+}
+
+// The coefficients of a probe as 27 floats
+fn floats(probe: &Sh9) -> impl Iterator<Item = Float> + '_ {
+    probe.coeffs.iter().flat_map(|&c| <[Float; 3]>::from(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Unit;
+
+    // Sky above, black below
+    struct Sky;
+
+    impl Integrator<RGB> for Sky {
+        fn radiance(&self, ray: &Ray, _rng: &mut impl Rng) -> RGB {
+            match ray.direction.z > 0.0 {
+                true => RGB::from([1.0, 0.5, 0.25]),
+                false => RGB::default(),
+            }
+        }
+    }
+
+    fn red(c: RGB) -> Float {
+        <[Float; 3]>::from(c)[0]
+    }
+
+    #[test]
+    fn irradiance() {
+        let bounds = Bounds::from_corners([0.0, 0.0, 0.0].into(), [2.0, 1.0, 1.0].into());
+        let grid = ProbeGrid::new(bounds, [3, 1, 2]);
+        assert_eq!(6, grid.len());
+        assert_eq!(Point::new(1.0, 0.5, 0.0), grid.position(1));
+        assert_eq!(Point::new(2.0, 0.5, 1.0), grid.position(5));
+
+        let volume = ProbeVolume::bake(grid, &Sky, 1000, 7);
+        assert_eq!(volume, ProbeVolume::bake(grid, &Sky, 1000, 7));
+        let sh = volume.probe(2, 0, 1);
+
+        // Facing the sky, a surface gets all of it: π. Facing sideways, half.
+        // Facing down, nothing. L2 is within a few percent of each.
+        let up = red(sh.irradiance(Unit::Z_AXIS));
+        let side = red(sh.irradiance(Unit::X_AXIS));
+        let down = red(sh.irradiance(-Unit::Z_AXIS));
+        assert!((up - PI).abs() < 0.05 * PI, "{}", up);
+        assert!((side - PI / 2.0).abs() < 0.05 * PI, "{}", side);
+        assert!(down.abs() < 0.05 * PI, "{}", down);
+        let [_, g, b] = <[Float; 3]>::from(sh.irradiance(Unit::Z_AXIS));
+        assert!((g - up / 2.0).abs() < 1e-9 && (b - up / 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn export() {
+        let bounds = Bounds::from_corners([0.0, 0.0, 0.0].into(), [1.0, 1.0, 1.0].into());
+        let mut probe = Sh9::default();
+        probe.coeffs[0] = RGB::from([1.0, 2.0, 3.0]);
+        let volume = ProbeVolume {
+            grid: ProbeGrid::new(bounds, [1, 1, 2]),
+            probes: vec![Sh9::default(), probe],
+        };
+
+        let json = volume.to_json();
+        assert!(json.starts_with("{\"min\":[0,0,0],\"max\":[1,1,1],\"counts\":[1,1,2]"));
+        assert!(json.contains(",[1,2,3,0,0,0,"));
+        assert!(json.ends_with("0]]}"));
+
+        let mut bin = Vec::new();
+        volume.write_binary(&mut bin).unwrap();
+        assert_eq!(4 + 12 + 24 + 2 * 27 * 4, bin.len());
+        assert_eq!(b"GSH2", &bin[..4]);
+        assert_eq!(2, u32::from_le_bytes(bin[12..16].try_into().unwrap()));
+        let first = 40 + 27 * 4;
+        assert_eq!(
+            2.0,
+            f32::from_le_bytes(bin[first + 4..first + 8].try_into().unwrap())
+        );
+    }
+}
@@ -0,0 +1,1216 @@
+//! # The top-level render loop.
+//!
+//! A [`Renderer`] drives a [`Camera`] and an [`Integrator`] over a [`Film`],
+//! taking care of parallelism and random number generation.
+//!
+//! ```no_run
+//! use gremlin::{camera::ThinLens, film::RGBFilm, integrator::Hacky, renderer::Renderer};
+//!
+//! let mut film = RGBFilm::new(800, 600);
+//! let cam = ThinLens::builder(film.dimensions()).build();
+//! let integrator = Hacky::default();
+//!
+//! Renderer::new(16).deterministic(1234).render(&mut film, &cam, &integrator);
+//! ```
+
+use crate::{
+    camera::Camera,
+    color::{Color, RGB},
+    film::{Buffer, Film, InvalidSamples, Metadata, Pixel, Tile, TiledFilm},
+    integrator::Integrator,
+    math::{self, CounterRng},
+    sampler::{HaltonSampler, IndependentSampler, SampleDimension, Sampler},
+    Float,
+};
+use rand::{rngs::ThreadRng, Rng};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use std::{
+    any::Any,
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+mod sequence;
+pub use sequence::*;
+
+/// Default number of film rows per tile.
+const DEFAULT_TILE_ROWS: u32 = 8;
+
+/// A handle for cancelling in-progress renders from another thread.
+///
+/// Clones share the same flag, so a GUI or server can hand one clone to a
+/// [`Renderer`] and keep another to [`cancel`] with. Renders check the token
+/// before starting each tile, so they stop promptly without tearing down the
+/// process, leaving the film partially rendered.
+///
+/// [`cancel`]: Self::cancel
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a new, uncancelled token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of any render using this token.
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if cancellation has been requested.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A tile that failed to render because of a panic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedTile {
+    pub tile: Tile,
+    /// The panic message, if it was a string.
+    pub message: Option<String>,
+}
+
+impl FailedTile {
+    fn new(tile: Tile, payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(msg) => Some(*msg),
+            Err(payload) => payload.downcast_ref::<&str>().map(|msg| msg.to_string()),
+        };
+        Self { tile, message }
+    }
+}
+
+/// Statistics about a finished render.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Tiles that panicked, and were left as they were before the render.
+    pub failed_tiles: Vec<FailedTile>,
+}
+
+impl RenderStats {
+    /// Returns `true` if every tile rendered successfully.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.failed_tiles.is_empty()
+    }
+
+    /// An error AOV for a film of the given size, with pixels in failed tiles
+    /// set to red and the rest black.
+    pub fn error_aov(&self, width: u32, height: u32) -> Buffer<RGB> {
+        let mut aov = Buffer::new(width, height);
+        for (px, py, pixel) in aov.pixel_iter_mut() {
+            let failed = self.failed_tiles.iter().any(|f| {
+                let t = &f.tile;
+                (t.x0..t.x0 + t.width).contains(&px) && (t.y0..t.y0 + t.height).contains(&py)
+            });
+            if failed {
+                *pixel = RGB::from([1.0, 0.0, 0.0]);
+            }
+        }
+        aov
+    }
+}
+
+/// The sample generators a [`Renderer`] can draw samples from. See
+/// [`Renderer::sampler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerType {
+    /// Uniform random values, from an [`IndependentSampler`].
+    Independent,
+    /// A low-discrepancy sequence, from a [`HaltonSampler`]. Antialiasing,
+    /// depth of field and the first bounces of a path converge faster than
+    /// with random values.
+    Halton,
+}
+
+impl SamplerType {
+    fn build(self, seed: u64) -> AnySampler {
+        match self {
+            Self::Independent => AnySampler::Independent(Box::new(IndependentSampler::new(seed))),
+            Self::Halton => AnySampler::Halton(HaltonSampler::new(seed)),
+        }
+    }
+}
+
+// The renderer's sampler, dispatched statically like `Material`
+#[derive(Debug, Clone)]
+enum AnySampler {
+    Independent(Box<IndependentSampler>),
+    Halton(HaltonSampler),
+}
+
+impl Sampler for AnySampler {
+    fn start_pixel_sample(&mut self, px: u32, py: u32, index: u64) {
+        match self {
+            Self::Independent(s) => s.start_pixel_sample(px, py, index),
+            Self::Halton(s) => s.start_pixel_sample(px, py, index),
+        }
+    }
+
+    #[inline]
+    fn get_1d(&mut self, dim: SampleDimension) -> Float {
+        match self {
+            Self::Independent(s) => s.get_1d(dim),
+            Self::Halton(s) => s.get_1d(dim),
+        }
+    }
+
+    #[inline]
+    fn get_2d(&mut self, dim: SampleDimension) -> [Float; 2] {
+        match self {
+            Self::Independent(s) => s.get_2d(dim),
+            Self::Halton(s) => s.get_2d(dim),
+        }
+    }
+}
+
+// Where a thread's samples come from: the sampler, if there is one, for the
+// camera, and otherwise the thread's generator when not deterministic
+struct SampleSource {
+    thread_rng: ThreadRng,
+    sampler: Option<AnySampler>,
+}
+
+/// Renders images.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    spp: u32,
+    tile_rows: u32,
+    seed: Option<u64>,
+    frame: u64,
+    first_sample: u64,
+    stable_jitter: bool,
+    invalid_samples: InvalidSamples,
+    median_of_means: u32,
+    sampler: Option<SamplerType>,
+    crop: Option<[u32; 4]>,
+    pool: Option<Arc<ThreadPool>>,
+    cancel: Option<CancellationToken>,
+}
+
+impl Renderer {
+    /// Create a new renderer taking the given number of samples per pixel.
+    pub fn new(spp: u32) -> Self {
+        Self {
+            spp,
+            tile_rows: DEFAULT_TILE_ROWS,
+            seed: None,
+            frame: 0,
+            first_sample: 0,
+            stable_jitter: false,
+            invalid_samples: InvalidSamples::default(),
+            median_of_means: 1,
+            sampler: None,
+            crop: None,
+            pool: None,
+            cancel: None,
+        }
+    }
+
+    /// Set the number of film rows in each tile, the unit of parallel work.
+    pub fn tile_rows(mut self, rows: u32) -> Self {
+        self.tile_rows = rows.max(1);
+        self
+    }
+
+    /// Make renders bit-exact reproducible from the given seed.
+    ///
+    /// Each sample of each pixel draws from its own [`CounterRng`] stream,
+    /// keyed by `seed`, the frame, the pixel coordinates and the sample index,
+    /// and samples are accumulated in a fixed order. So the output doesn't
+    /// depend on the thread count, scheduling or tile layout, and any sample
+    /// can be regenerated on its own with [`sample_rng`]. This is a little
+    /// slower than the default of per-thread generators.
+    ///
+    /// [`sample_rng`]: Self::sample_rng
+    pub fn deterministic(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the index of the frame being rendered, for animations.
+    ///
+    /// In deterministic mode, each frame gets its own random streams by
+    /// default, so noise is uncorrelated between frames.
+    pub fn frame(mut self, frame: u64) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// Start at the `first`-th sample of each pixel, rather than the `0`-th.
+    ///
+    /// In deterministic mode, this lets a render be split by sample across
+    /// machines: one renders samples `0..n`, the next `n..2n` and so on, and
+    /// [`merge`]ing their films gives the same samples as a single render
+    /// with all of them. Later passes can add samples to a film the same way.
+    ///
+    /// [`merge`]: crate::film::Film::merge
+    pub fn first_sample(mut self, first: u64) -> Self {
+        self.first_sample = first;
+        self
+    }
+
+    /// Reuse the same per-pixel random streams on every frame.
+    ///
+    /// When rendering an animation, uncorrelated noise between frames shows up
+    /// as flicker. With stable jitter, each pixel replays the same sample
+    /// sequence (lens positions, pixel offsets and so on) regardless of the
+    /// [`frame`] index, so static parts of the scene converge to the same
+    /// image each frame and residual noise stays put instead of crawling.
+    ///
+    /// Only has an effect in [`deterministic`] mode.
+    ///
+    /// [`frame`]: Self::frame
+    /// [`deterministic`]: Self::deterministic
+    pub fn stable_jitter(mut self, stable: bool) -> Self {
+        self.stable_jitter = stable;
+        self
+    }
+
+    /// Set how samples with NaN or infinite components are handled.
+    ///
+    /// Defaults to [`InvalidSamples::Zero`]. Use [`InvalidSamples::Keep`] to
+    /// make the offending pixels stand out in the output, or check
+    /// [`Film::invalid_pixels`] afterwards.
+    pub fn invalid_samples(mut self, policy: InvalidSamples) -> Self {
+        self.invalid_samples = policy;
+        self
+    }
+
+    /// Reject outlier samples, such as fireflies, by accumulating each
+    /// pixel's samples as the median of the means of `buckets` groups.
+    ///
+    /// Defaults to `1`, the plain average. See [`Pixel::add_median_of_means`]
+    /// for the trade-offs; with too few samples per bucket, it darkens the
+    /// image noticeably.
+    pub fn median_of_means(mut self, buckets: u32) -> Self {
+        self.median_of_means = buckets.max(1);
+        self
+    }
+
+    /// Draw each sample's position in the pixel and on the lens, and the
+    /// decisions at each bounce of its path, from the given kind of
+    /// [`Sampler`] rather than from the random stream. See
+    /// [`Camera::ray_sampled`] and [`Integrator::radiance_sampled`].
+    ///
+    /// In deterministic mode, the sampler is seeded from the render seed, so
+    /// renders stay reproducible.
+    pub fn sampler(mut self, sampler: SamplerType) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Only render pixels within the crop window from `(x0, y0)` (inclusive)
+    /// to `(x1, y1)` (exclusive), in raster coordinates.
+    ///
+    /// Pixels outside the window are left untouched, and pixels inside it get
+    /// exactly the samples they would in a full render (in deterministic
+    /// mode). So a noisy region can be re-rendered on its own for debugging,
+    /// and pasted back or extracted with [`Buffer::crop`].
+    ///
+    /// [`Buffer::crop`]: crate::film::Buffer::crop
+    pub fn crop(mut self, x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        self.crop = Some([x0, y0, x1, y1]);
+        self
+    }
+
+    /// Render on a dedicated pool of `threads` worker threads, rather than
+    /// rayon's global pool.
+    ///
+    /// Useful when embedding gremlin in an application that uses rayon for
+    /// other work, so renders neither starve nor get starved by it. Passing
+    /// `0` lets rayon pick the thread count, as for the global pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system refuses to spawn the threads.
+    pub fn with_threads(self, threads: usize) -> Self {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("gremlin-render-{}", i))
+            .build()
+            .expect("failed to create render thread pool");
+        self.thread_pool(Arc::new(pool))
+    }
+
+    /// Render on the given thread pool, rather than rayon's global pool.
+    ///
+    /// The pool can be shared with other renderers or the rest of the
+    /// application.
+    pub fn thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Stop rendering early once the given token is cancelled.
+    ///
+    /// Tiles already being rendered are finished, and the rest are skipped.
+    /// Check [`CancellationToken::is_cancelled`] after rendering to tell
+    /// whether the film is complete.
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// The number of samples per pixel.
+    #[inline]
+    pub fn spp(&self) -> u32 {
+        self.spp
+    }
+
+    /// The random stream the `sample`-th sample of the pixel at `(px, py)`
+    /// draws from, or `None` if not [`deterministic`].
+    ///
+    /// Passing it to the camera and integrator as the renderer does replays
+    /// that one sample exactly, for debugging a single path. With a
+    /// [`sampler`], the camera's samples come from that instead.
+    ///
+    /// [`deterministic`]: Self::deterministic
+    /// [`sampler`]: Self::sampler
+    pub fn sample_rng(&self, px: u32, py: u32, sample: u64) -> Option<CounterRng> {
+        self.frame_seed()
+            .map(|seed| sample_stream(seed, px, py, sample))
+    }
+
+    /// Render `spp` samples of the pixel at `(px, py)` alone, returning each
+    /// sample's value rather than adding them to a film.
+    ///
+    /// Samples are numbered from the [`first_sample`] as in a full render,
+    /// so when [`deterministic`] they're exactly the values that pixel gets
+    /// there, and an outlier in a render can be found and re-traced here.
+    /// No film or crop is involved, and the samples are taken on the calling
+    /// thread.
+    ///
+    /// `Li` can be anything the integrator measures. For a breakdown of
+    /// each sample by bounce, pass a [`PathTracer`]'s [`by_bounce`]
+    /// integrator, which measures [`BounceBreakdown`]s.
+    ///
+    /// ```
+    /// use gremlin::{camera::ThinLens, color::RGB, integrator::Hacky, renderer::Renderer, Float};
+    ///
+    /// let cam = ThinLens::builder((64, 48)).build();
+    /// let integrator = Hacky::default();
+    /// let renderer = Renderer::new(16).deterministic(1234);
+    /// let samples: Vec<RGB> = renderer.render_pixel(10, 20, 16, &cam, &integrator);
+    /// let brightest = samples.iter().map(|s| s.max_component()).fold(0.0, Float::max);
+    /// ```
+    ///
+    /// [`first_sample`]: Self::first_sample
+    /// [`deterministic`]: Self::deterministic
+    /// [`PathTracer`]: crate::integrator::PathTracer
+    /// [`by_bounce`]: crate::integrator::PathTracer::by_bounce
+    /// [`BounceBreakdown`]: crate::integrator::BounceBreakdown
+    pub fn render_pixel<Li>(
+        &self,
+        px: u32,
+        py: u32,
+        spp: u32,
+        cam: &impl Camera,
+        integrator: &impl Integrator<Li>,
+    ) -> Vec<Li> {
+        let seed = self.frame_seed();
+        let mut source = self.sample_source(seed.unwrap_or_else(rand::random));
+        let first = self.first_sample;
+        (first..first + spp as u64)
+            .map(|sample| Self::sample(px, py, sample, seed, &mut source, cam, integrator))
+            .collect()
+    }
+
+    /// The settings that affect the rendered image, for embedding in saved
+    /// images. Keys are prefixed with `gremlin.`.
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+        metadata
+            .insert("gremlin.spp", self.spp)
+            .insert("gremlin.frame", self.frame)
+            .insert("gremlin.first_sample", self.first_sample)
+            .insert(
+                "gremlin.invalid_samples",
+                format!("{:?}", self.invalid_samples),
+            );
+        if let Some(seed) = self.seed {
+            metadata
+                .insert("gremlin.seed", seed)
+                .insert("gremlin.stable_jitter", self.stable_jitter);
+        }
+        if self.median_of_means > 1 {
+            metadata.insert("gremlin.median_of_means", self.median_of_means);
+        }
+        if let Some(sampler) = self.sampler {
+            metadata.insert("gremlin.sampler", format!("{:?}", sampler));
+        }
+        if let Some([x0, y0, x1, y1]) = self.crop {
+            metadata.insert("gremlin.crop", format!("{} {} {} {}", x0, y0, x1, y1));
+        }
+        metadata
+    }
+
+    /// Render into the given film.
+    ///
+    /// A panic while rendering a tile (say, a failed assertion in a material)
+    /// doesn't abort the render: the tile is rolled back to its previous
+    /// contents, reported in the returned [`RenderStats`], and the rest of the
+    /// frame carries on. The panic message is still printed by the panic
+    /// hook. Builds with `panic = "abort"` can't recover, of course.
+    pub fn render<CS, Li>(
+        &self,
+        film: &mut Film<CS>,
+        cam: &impl Camera,
+        integrator: &impl Integrator<Li>,
+    ) -> RenderStats
+    where
+        Color<CS>: From<Li> + Copy + Send,
+        CS: Copy,
+    {
+        let width = film.width();
+        let tile_len = (width * self.tile_rows) as usize;
+        let seed = self.frame_seed();
+        let sampler_seed = seed.unwrap_or_else(rand::random);
+
+        let failed_tiles = self.install(|| {
+            film.par_chunks_mut(tile_len)
+                .enumerate()
+                .filter_map(|(index, pixels)| {
+                    if self.is_cancelled() {
+                        return None;
+                    }
+                    let tile = Tile {
+                        index,
+                        x0: 0,
+                        y0: index as u32 * self.tile_rows,
+                        width,
+                        height: pixels.len() as u32 / width,
+                    };
+                    let backup = pixels.to_vec();
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        let mut source = self.sample_source(sampler_seed);
+                        for (idx, pixel) in pixels.iter_mut().enumerate() {
+                            let idx = index * tile_len + idx;
+                            let px = idx as u32 % width;
+                            let py = idx as u32 / width;
+                            if !self.in_crop(px, py) {
+                                continue;
+                            }
+                            self.accumulate_pixel(
+                                pixel,
+                                px,
+                                py,
+                                seed,
+                                &mut source,
+                                cam,
+                                integrator,
+                            );
+                        }
+                    }));
+                    result.err().map(|payload| {
+                        pixels.copy_from_slice(&backup);
+                        FailedTile::new(tile, payload)
+                    })
+                })
+                .collect()
+        });
+
+        RenderStats { failed_tiles }
+    }
+
+    /// Render several layers in one pass, each into its own film, from an
+    /// integrator measuring a value per layer such as
+    /// [`PathTracer::layers`].
+    ///
+    /// Every film gets the same camera rays and paths, so the layers line up
+    /// sample for sample. Panics are isolated per tile as in [`render`], with
+    /// the tile rolled back in every film; an integrator returning the wrong
+    /// number of layers fails every tile it renders.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the films aren't all the same size.
+    ///
+    /// [`PathTracer::layers`]: crate::integrator::PathTracer::layers
+    /// [`render`]: Self::render
+    pub fn render_layers<CS, Li>(
+        &self,
+        films: &mut [Film<CS>],
+        cam: &impl Camera,
+        integrator: &impl Integrator<Vec<Li>>,
+    ) -> RenderStats
+    where
+        Color<CS>: From<Li> + Copy + Send,
+        CS: Copy,
+        Li: Clone,
+    {
+        let Some(first) = films.first() else {
+            return RenderStats::default();
+        };
+        let (width, height) = first.dimensions();
+        assert!(
+            films.iter().all(|f| f.dimensions() == (width, height)),
+            "layer film size mismatch"
+        );
+        let tile_len = (width * self.tile_rows) as usize;
+        let seed = self.frame_seed();
+        let sampler_seed = seed.unwrap_or_else(rand::random);
+
+        // Each tile's rows in every film
+        let mut tiles: Vec<Vec<&mut [Pixel<CS>]>> = Vec::new();
+        for film in films.iter_mut() {
+            for (index, pixels) in film.chunks_mut(tile_len).enumerate() {
+                match tiles.get_mut(index) {
+                    Some(tile) => tile.push(pixels),
+                    None => tiles.push(vec![pixels]),
+                }
+            }
+        }
+
+        let failed_tiles = self.install(|| {
+            tiles
+                .into_par_iter()
+                .enumerate()
+                .filter_map(|(index, mut layers)| {
+                    if self.is_cancelled() {
+                        return None;
+                    }
+                    let tile = Tile {
+                        index,
+                        x0: 0,
+                        y0: index as u32 * self.tile_rows,
+                        width,
+                        height: layers[0].len() as u32 / width,
+                    };
+                    let backup: Vec<_> = layers.iter().map(|pixels| pixels.to_vec()).collect();
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        let mut source = self.sample_source(sampler_seed);
+                        for idx in 0..layers[0].len() {
+                            let px = (index * tile_len + idx) as u32 % width;
+                            let py = (index * tile_len + idx) as u32 / width;
+                            if !self.in_crop(px, py) {
+                                continue;
+                            }
+                            let first = self.first_sample;
+                            let samples: Vec<Vec<Li>> = (first..first + self.spp as u64)
+                                .map(|sample| {
+                                    Self::sample(px, py, sample, seed, &mut source, cam, integrator)
+                                })
+                                .collect();
+                            assert!(
+                                samples.iter().all(|s| s.len() == layers.len()),
+                                "integrator measured the wrong number of layers"
+                            );
+                            for (layer, pixels) in layers.iter_mut().enumerate() {
+                                let samples = samples.iter().map(|s| s[layer].clone());
+                                self.add_samples(&mut pixels[idx], samples);
+                            }
+                        }
+                    }));
+                    result.err().map(|payload| {
+                        for (pixels, backup) in layers.iter_mut().zip(&backup) {
+                            pixels.copy_from_slice(backup);
+                        }
+                        FailedTile::new(tile, payload)
+                    })
+                })
+                .collect()
+        });
+
+        RenderStats { failed_tiles }
+    }
+
+    /// Render into an out-of-core film.
+    ///
+    /// Tiles are rendered in parallel, each loaded from disk, rendered and
+    /// stored again, so only about one tile per thread is in memory at once.
+    /// In deterministic mode the output is identical to [`render`]ing into an
+    /// in-memory film.
+    ///
+    /// Panics are isolated per tile as in [`render`]; a failed tile is simply
+    /// not stored.
+    ///
+    /// [`render`]: Self::render
+    pub fn render_tiled<CS, Li>(
+        &self,
+        film: &mut TiledFilm<CS>,
+        cam: &impl Camera,
+        integrator: &impl Integrator<Li>,
+    ) -> io::Result<RenderStats>
+    where
+        Color<CS>: From<Li> + Copy + Send,
+        CS: Copy + Send + Sync,
+    {
+        let seed = self.frame_seed();
+        let sampler_seed = seed.unwrap_or_else(rand::random);
+        let film = &*film;
+
+        let tiles: Vec<_> = film
+            .tiles()
+            .into_iter()
+            .filter(|t| match self.crop {
+                Some([x0, y0, x1, y1]) => {
+                    t.x0 < x1 && t.y0 < y1 && t.x0 + t.width > x0 && t.y0 + t.height > y0
+                }
+                None => true,
+            })
+            .collect();
+
+        let results: Vec<Option<FailedTile>> = self.install(|| {
+            tiles
+                .into_par_iter()
+                .map(|tile| {
+                    if self.is_cancelled() {
+                        return Ok(None);
+                    }
+                    let mut pixels = film.load_tile(&tile)?;
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        let mut source = self.sample_source(sampler_seed);
+                        for (px, py, pixel) in pixels.pixel_iter_mut() {
+                            let (px, py) = (tile.x0 + px, tile.y0 + py);
+                            if !self.in_crop(px, py) {
+                                continue;
+                            }
+                            self.accumulate_pixel(
+                                pixel,
+                                px,
+                                py,
+                                seed,
+                                &mut source,
+                                cam,
+                                integrator,
+                            );
+                        }
+                    }));
+                    match result {
+                        Ok(()) => film.store_tile(&tile, &pixels).map(|_| None),
+                        Err(payload) => Ok(Some(FailedTile::new(tile, payload))),
+                    }
+                })
+                .collect::<io::Result<_>>()
+        })?;
+
+        Ok(RenderStats {
+            failed_tiles: results.into_iter().flatten().collect(),
+        })
+    }
+
+    // Run `op` on the renderer's thread pool, or the current one if unset.
+    fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    #[inline]
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    #[inline]
+    fn in_crop(&self, px: u32, py: u32) -> bool {
+        match self.crop {
+            Some([x0, y0, x1, y1]) => (x0..x1).contains(&px) && (y0..y1).contains(&py),
+            None => true,
+        }
+    }
+
+    // The render seed for the current frame, if deterministic.
+    fn frame_seed(&self) -> Option<u64> {
+        self.seed.map(|seed| match self.stable_jitter {
+            true => seed,
+            false => math::hash_keys(&[seed, self.frame]),
+        })
+    }
+
+    // A fresh source of samples for a thread, with the sampler seeded from
+    // `sampler_seed` so every thread's samplers agree
+    fn sample_source(&self, sampler_seed: u64) -> SampleSource {
+        SampleSource {
+            thread_rng: rand::thread_rng(),
+            sampler: self.sampler.map(|s| s.build(sampler_seed)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[inline]
+    fn accumulate_pixel<CS, Li>(
+        &self,
+        pixel: &mut Pixel<CS>,
+        px: u32,
+        py: u32,
+        seed: Option<u64>,
+        source: &mut SampleSource,
+        cam: &impl Camera,
+        integrator: &impl Integrator<Li>,
+    ) where
+        Color<CS>: From<Li>,
+        CS: Copy,
+    {
+        let first = self.first_sample;
+        let samples = (first..first + self.spp as u64)
+            .map(|sample| Self::sample(px, py, sample, seed, source, cam, integrator));
+        self.add_samples(pixel, samples);
+    }
+
+    // Add a pixel's samples, averaged as the renderer is set to
+    #[inline]
+    fn add_samples<CS, Li>(&self, pixel: &mut Pixel<CS>, samples: impl Iterator<Item = Li>)
+    where
+        Color<CS>: From<Li>,
+        CS: Copy,
+    {
+        match self.median_of_means {
+            1 => samples.for_each(|s| pixel.add_sample_with(s, self.invalid_samples)),
+            buckets => pixel.add_median_of_means(samples, buckets as usize, self.invalid_samples),
+        }
+    }
+
+    // One sample of a pixel, from its own stream if deterministic
+    #[inline]
+    fn sample<Li>(
+        px: u32,
+        py: u32,
+        sample: u64,
+        seed: Option<u64>,
+        source: &mut SampleSource,
+        cam: &impl Camera,
+        integrator: &impl Integrator<Li>,
+    ) -> Li {
+        let sampler = &mut source.sampler;
+        if let Some(sampler) = sampler {
+            sampler.start_pixel_sample(px, py, sample);
+        }
+        match seed {
+            Some(seed) => {
+                let mut rng = sample_stream(seed, px, py, sample);
+                Self::trace(px, py, sampler, &mut rng, cam, integrator)
+            }
+            None => Self::trace(px, py, sampler, &mut source.thread_rng, cam, integrator),
+        }
+    }
+
+    #[inline]
+    fn trace<Li>(
+        px: u32,
+        py: u32,
+        sampler: &mut Option<AnySampler>,
+        rng: &mut impl Rng,
+        cam: &impl Camera,
+        integrator: &impl Integrator<Li>,
+    ) -> Li {
+        match sampler {
+            Some(sampler) => {
+                let ray = cam.ray_sampled(px, py, sampler, rng);
+                integrator.radiance_sampled(&ray, sampler, rng)
+            }
+            None => integrator.radiance(&cam.ray(px, py, rng), rng),
+        }
+    }
+}
+
+// The random stream for one sample of one pixel. Keyed by pixel rather than
+// tile, so tiled and in-memory renders get the same samples.
+#[inline]
+fn sample_stream(seed: u64, px: u32, py: u32, sample: u64) -> CounterRng {
+    CounterRng::from_keys(&[seed, px as u64, py as u64, sample])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::ThinLens,
+        color::RGB,
+        film::RGBFilm,
+        geo::{Degrees, Ray},
+        integrator::Hacky,
+        shape::{Sphere, Surface},
+        Float,
+    };
+
+    #[test]
+    fn deterministic_across_thread_counts() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let render = |threads, sampler| {
+            let mut film = RGBFilm::new(16, 8);
+            let cam = ThinLens::builder(film.dimensions())
+                .move_to([0.0, 0.0, 2.0])
+                .aperture(0.1)
+                .build();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let mut renderer = Renderer::new(4).tile_rows(1).deterministic(42);
+                if let Some(sampler) = sampler {
+                    renderer = renderer.sampler(sampler);
+                }
+                renderer.render(&mut film, &cam, &integrator)
+            });
+            film.to_snapshot()
+        };
+
+        assert!(render(1, None).iter().eq(render(4, None).iter()));
+        let halton = render(1, Some(SamplerType::Halton));
+        assert!(halton
+            .iter()
+            .eq(render(4, Some(SamplerType::Halton)).iter()));
+        assert!(!halton.iter().eq(render(1, None).iter()));
+    }
+
+    #[test]
+    fn sampler_stratifies_pixels() {
+        // Where in the pixel the camera ray passes through
+        struct Offset;
+        impl Integrator<RGB> for Offset {
+            fn radiance(&self, ray: &Ray, _rng: &mut impl rand::Rng) -> RGB {
+                let d = ray.direction;
+                RGB::from([(d.x / d.z).abs(), (d.y / d.z).abs(), 0.0])
+            }
+        }
+
+        // One pixel per unit of screen, so offsets are in `[0, 1)`
+        let cam = ThinLens::builder((2, 2)).fov(Degrees(90.0)).build();
+        for sampler in [SamplerType::Halton, SamplerType::Independent] {
+            let renderer = Renderer::new(16).deterministic(3).sampler(sampler);
+            let samples: Vec<RGB> = renderer.render_pixel(0, 0, 16, &cam, &Offset);
+            let mut strata = [0; 16];
+            for s in &samples {
+                let [x, _, _] = <[Float; 3]>::from(*s);
+                assert!((0.0..=1.0).contains(&x), "{}", x);
+                strata[((x * 16.0) as usize).min(15)] += 1;
+            }
+            // Halton puts one sample in each, random values rarely do
+            let hit = strata.iter().filter(|&&n| n > 0).count();
+            match sampler {
+                SamplerType::Halton => assert_eq!(16, hit),
+                SamplerType::Independent => assert!(hit < 16, "{:?}", strata),
+            }
+        }
+        assert_eq!(
+            Some("Halton"),
+            Renderer::new(1)
+                .sampler(SamplerType::Halton)
+                .metadata()
+                .get("gremlin.sampler")
+        );
+    }
+
+    #[test]
+    fn dedicated_thread_pool() {
+        struct ThreadCount;
+        impl Integrator<RGB> for ThreadCount {
+            fn radiance(&self, _ray: &Ray, _rng: &mut impl rand::Rng) -> RGB {
+                let n = rayon::current_num_threads() as Float;
+                RGB::from([n, n, n])
+            }
+        }
+
+        let mut film = RGBFilm::new(4, 4);
+        let cam = ThinLens::builder(film.dimensions()).build();
+        Renderer::new(1)
+            .with_threads(3)
+            .render(&mut film, &cam, &ThreadCount);
+        assert!(film
+            .iter()
+            .all(|p| p.to_color() == RGB::from([3.0, 3.0, 3.0])));
+
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        Renderer::new(1)
+            .thread_pool(pool)
+            .render(&mut film, &cam, &ThreadCount);
+        assert!(film
+            .iter()
+            .all(|p| p.to_color() == RGB::from([2.5, 2.5, 2.5])));
+    }
+
+    #[test]
+    fn cancellation() {
+        // Cancels the render as soon as the first sample is taken
+        struct Cancel(CancellationToken);
+        impl Integrator<RGB> for Cancel {
+            fn radiance(&self, _ray: &Ray, _rng: &mut impl rand::Rng) -> RGB {
+                self.0.cancel();
+                RGB::from([1.0, 1.0, 1.0])
+            }
+        }
+
+        let mut film = RGBFilm::new(8, 8);
+        let cam = ThinLens::builder(film.dimensions()).build();
+        let token = CancellationToken::new();
+        Renderer::new(2)
+            .tile_rows(1)
+            .with_threads(1)
+            .cancel_token(token.clone())
+            .render(&mut film, &cam, &Cancel(token.clone()));
+
+        // Only the tile in progress when cancelled is rendered
+        assert!(token.is_cancelled());
+        assert_eq!(8, film.iter().filter(|p| p.count() == 2).count());
+        assert_eq!(56, film.iter().filter(|p| p.count() == 0).count());
+    }
+
+    #[test]
+    fn panicking_tile() {
+        // Panics on rays through the bottom half of the image
+        struct Fragile;
+        impl Integrator<RGB> for Fragile {
+            fn radiance(&self, ray: &Ray, _rng: &mut impl rand::Rng) -> RGB {
+                assert!(ray.direction.y > 0.0, "fragile pixel");
+                RGB::from([1.0, 1.0, 1.0])
+            }
+        }
+
+        let mut film = RGBFilm::new(4, 4);
+        let cam = ThinLens::builder(film.dimensions()).build();
+        let stats = Renderer::new(1)
+            .tile_rows(2)
+            .deterministic(1)
+            .render(&mut film, &cam, &Fragile);
+
+        assert_eq!(1, stats.failed_tiles.len());
+        let failed = &stats.failed_tiles[0];
+        assert_eq!(
+            (0, 2, 4, 2),
+            (
+                failed.tile.x0,
+                failed.tile.y0,
+                failed.tile.width,
+                failed.tile.height
+            )
+        );
+        assert_eq!(Some("fragile pixel"), failed.message.as_deref());
+        for (_, py, pixel) in film.pixel_iter() {
+            assert_eq!(py < 2, pixel.count() == 1);
+        }
+
+        let aov = stats.error_aov(4, 4);
+        assert!(aov.pixel_iter().all(|(_, py, c)| (py < 2) == c.is_black()));
+    }
+
+    #[test]
+    fn layers_in_one_pass() {
+        // Where the ray points, which takes no random numbers
+        struct Direction;
+        impl Integrator<RGB> for Direction {
+            fn radiance(&self, ray: &Ray, _rng: &mut impl rand::Rng) -> RGB {
+                let d = ray.direction;
+                RGB::from([d.x.abs(), d.y.abs(), d.z.abs()])
+            }
+        }
+        struct Both(Hacky);
+        impl Integrator<Vec<RGB>> for Both {
+            fn radiance(&self, ray: &Ray, rng: &mut impl rand::Rng) -> Vec<RGB> {
+                vec![self.0.radiance(ray, rng), Direction.radiance(ray, rng)]
+            }
+        }
+
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let cam = ThinLens::builder((8, 6))
+            .move_to([0.0, 0.0, 2.0])
+            .aperture(0.1)
+            .build();
+        let renderer = Renderer::new(4)
+            .tile_rows(2)
+            .deterministic(5)
+            .median_of_means(2);
+
+        let mut beauty = RGBFilm::new(8, 6);
+        let mut direction = RGBFilm::new(8, 6);
+        renderer.render(&mut beauty, &cam, &integrator);
+        renderer.render(&mut direction, &cam, &Direction);
+
+        let mut films = vec![RGBFilm::new(8, 6), RGBFilm::new(8, 6)];
+        let stats = renderer.render_layers(&mut films, &cam, &Both(integrator));
+        assert!(stats.failed_tiles.is_empty());
+        assert!(films[0].iter().eq(beauty.iter()));
+        assert!(films[1].iter().eq(direction.iter()));
+    }
+
+    #[test]
+    fn stable_jitter_across_frames() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let render = |frame, stable| {
+            let mut film = RGBFilm::new(8, 8);
+            let cam = ThinLens::builder(film.dimensions())
+                .move_to([0.0, 0.0, 2.0])
+                .aperture(0.1)
+                .build();
+            Renderer::new(2)
+                .deterministic(42)
+                .frame(frame)
+                .stable_jitter(stable)
+                .render(&mut film, &cam, &integrator);
+            film.to_snapshot()
+        };
+
+        assert!(render(0, true).iter().eq(render(1, true).iter()));
+        assert!(!render(0, false).iter().eq(render(1, false).iter()));
+    }
+
+    #[test]
+    fn replay_one_sample() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let mut film = RGBFilm::new(8, 6);
+        let cam = ThinLens::builder(film.dimensions())
+            .move_to([0.0, 0.0, 2.0])
+            .aperture(0.1)
+            .build();
+        let renderer = Renderer::new(3).deterministic(5).frame(2);
+        renderer.render(&mut film, &cam, &integrator);
+
+        let sum = (0..3)
+            .map(|sample| {
+                let mut rng = renderer.sample_rng(3, 2, sample).unwrap();
+                let ray = cam.ray(3, 2, &mut rng);
+                integrator.radiance(&ray, &mut rng)
+            })
+            .fold(RGB::default(), |a, b| a + b);
+        assert_eq!(sum / 3.0, film[2 * 8 + 3].to_color());
+        assert!(Renderer::new(3).sample_rng(3, 2, 0).is_none());
+    }
+
+    #[test]
+    fn single_pixel() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let mut film = RGBFilm::new(8, 6);
+        let cam = ThinLens::builder(film.dimensions())
+            .move_to([0.0, 0.0, 2.0])
+            .build();
+        let renderer = Renderer::new(4).deterministic(9).first_sample(4);
+        renderer.render(&mut film, &cam, &integrator);
+
+        let samples: Vec<RGB> = renderer.render_pixel(4, 3, 4, &cam, &integrator);
+        assert_eq!(4, samples.len());
+        let sum = samples.iter().fold(RGB::default(), |a, &b| a + b);
+        assert_eq!(sum / 4.0, film[3 * 8 + 4].to_color());
+
+        // More samples carry on from where the render stopped
+        let more: Vec<RGB> = renderer.render_pixel(4, 3, 6, &cam, &integrator);
+        assert_eq!(samples[..], more[..4]);
+    }
+
+    #[test]
+    fn split_by_sample() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let mut full = RGBFilm::new(8, 6);
+        let cam = ThinLens::builder(full.dimensions())
+            .move_to([0.0, 0.0, 2.0])
+            .aperture(0.1)
+            .build();
+        Renderer::new(4)
+            .deterministic(9)
+            .render(&mut full, &cam, &integrator);
+
+        let mut merged = RGBFilm::new(8, 6);
+        for first in [0, 2] {
+            let mut part = RGBFilm::new(8, 6);
+            Renderer::new(2)
+                .deterministic(9)
+                .first_sample(first)
+                .render(&mut part, &cam, &integrator);
+            merged.merge(&part);
+        }
+
+        for (a, b) in full.iter().zip(merged.iter()) {
+            assert_eq!(a.count(), b.count());
+            let (a, b): ([Float; 3], [Float; 3]) = (a.to_color().into(), b.to_color().into());
+            assert!(
+                a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-4),
+                "{:?} {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn crop_window() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let mut full = RGBFilm::new(8, 6);
+        let cam = ThinLens::builder(full.dimensions())
+            .move_to([0.0, 0.0, 2.0])
+            .aperture(0.1)
+            .build();
+        let renderer = Renderer::new(2).deterministic(3);
+        renderer.render(&mut full, &cam, &integrator);
+
+        let mut cropped = RGBFilm::new(8, 6);
+        renderer
+            .clone()
+            .crop(2, 1, 5, 4)
+            .render(&mut cropped, &cam, &integrator);
+
+        for ((px, py, a), b) in full.pixel_iter().zip(cropped.iter()) {
+            match (2..5).contains(&px) && (1..4).contains(&py) {
+                true => assert_eq!(a, b),
+                false => assert_eq!(0, b.count()),
+            }
+        }
+        assert!(full
+            .crop(2, 1, 5, 4)
+            .iter()
+            .eq(cropped.crop(2, 1, 5, 4).iter()));
+    }
+
+    #[test]
+    fn tiled_matches_in_memory() {
+        let integrator = Hacky {
+            background: RGB::from([1.0, 1.0, 1.0]).into(),
+            surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 0.5))],
+            ..Default::default()
+        };
+        let mut film = RGBFilm::new(10, 6);
+        let cam = ThinLens::builder(film.dimensions())
+            .move_to([0.0, 0.0, 2.0])
+            .aperture(0.1)
+            .build();
+        let renderer = Renderer::new(2).deterministic(7);
+        renderer.render(&mut film, &cam, &integrator);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut tiled = TiledFilm::create(10, 6, 4, dir.path()).unwrap();
+        renderer
+            .render_tiled(&mut tiled, &cam, &integrator)
+            .unwrap();
+
+        let tiled = tiled.to_film().unwrap();
+        assert!(film.to_snapshot().iter().eq(tiled.to_snapshot().iter()));
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{
+    color::SRGB,
+    film::{Buffer, Metadata},
+};
+use image::ImageError;
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Renders an animation one frame at a time, picking up where it left off.
+///
+/// Long animation jobs get interrupted: machines reboot, jobs get preempted.
+/// A sequence keeps a manifest in its output directory listing each frame
+/// once its image is safely on disk, so re-running the same job skips
+/// straight to the first unfinished frame.
+///
+/// The manifest also records a hash of the render settings. Resuming with
+/// different settings would mix frames that don't match, so [`open`] refuses
+/// unless the settings hash is the same; use [`restart`] to start over.
+///
+/// ```no_run
+/// use gremlin::{film::RGBFilm, renderer::{Renderer, Sequence}};
+/// # use gremlin::{camera::{CameraPath, ThinLens}, integrator::Hacky};
+/// # let path = CameraPath::load("flythrough.csv").unwrap();
+/// # let template = ThinLens::builder((800, 600));
+/// # let integrator = Hacky::default();
+///
+/// let renderer = Renderer::new(16).deterministic(1234);
+/// let mut seq = Sequence::open("frames", &renderer.metadata()).unwrap();
+/// seq.run(path.frame_range(), |frame| {
+///     let mut film = RGBFilm::new(800, 600);
+///     let cam = path.camera(frame, &template);
+///     renderer.clone().frame(frame as u64).render(&mut film, &cam, &integrator);
+///     film.to_snapshot()
+/// })
+/// .unwrap();
+/// ```
+///
+/// [`open`]: Self::open
+/// [`restart`]: Self::restart
+#[derive(Debug)]
+pub struct Sequence {
+    dir: PathBuf,
+    settings: Metadata,
+    hash: u64,
+    done: BTreeSet<u32>,
+}
+
+/// Name of the manifest file in a sequence's output directory.
+const MANIFEST: &str = "manifest.txt";
+
+/// First line of a manifest file.
+const MANIFEST_HEADER: &str = "gremlin-sequence 1";
+
+impl Sequence {
+    /// Open the sequence in the given output directory, resuming from its
+    /// manifest if there is one.
+    ///
+    /// `settings` should describe everything that affects the rendered
+    /// frames, such as [`Renderer::metadata`] plus the scene and camera,
+    /// except for values that change per frame like `gremlin.frame`, which
+    /// is ignored. The directory is created if it doesn't exist.
+    ///
+    /// Frames listed in the manifest whose image has since been deleted are
+    /// rendered again.
+    ///
+    /// [`Renderer::metadata`]: super::Renderer::metadata
+    pub fn open(dir: impl AsRef<Path>, settings: &Metadata) -> Result<Self, SequenceError> {
+        let mut seq = Self::new(dir.as_ref(), settings)?;
+        match fs::read_to_string(seq.manifest_path()) {
+            Ok(manifest) => seq.read_manifest(&manifest)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => seq.write_header()?,
+            Err(e) => return Err(e.into()),
+        }
+        Ok(seq)
+    }
+
+    /// Open the sequence in the given output directory, discarding any
+    /// progress recorded in its manifest. Frame images already there are
+    /// overwritten as they're rendered.
+    pub fn restart(dir: impl AsRef<Path>, settings: &Metadata) -> Result<Self, SequenceError> {
+        let seq = Self::new(dir.as_ref(), settings)?;
+        seq.write_header()?;
+        Ok(seq)
+    }
+
+    fn new(dir: &Path, settings: &Metadata) -> Result<Self, SequenceError> {
+        fs::create_dir_all(dir)?;
+        let mut settings = settings.clone();
+        settings.remove("gremlin.frame");
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            hash: settings_hash(&settings),
+            settings,
+            done: BTreeSet::new(),
+        })
+    }
+
+    /// The output directory.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The hash of the render settings, as recorded in the manifest.
+    #[inline]
+    pub fn settings_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The path the given frame's image is saved to.
+    pub fn frame_path(&self, frame: u32) -> PathBuf {
+        self.dir.join(format!("frame-{:04}.png", frame))
+    }
+
+    /// Returns `true` if the given frame has been rendered and saved.
+    #[inline]
+    pub fn is_complete(&self, frame: u32) -> bool {
+        self.done.contains(&frame)
+    }
+
+    /// The frames rendered and saved so far, in order.
+    pub fn completed(&self) -> impl Iterator<Item = u32> + '_ {
+        self.done.iter().copied()
+    }
+
+    /// Save a rendered frame and record it in the manifest.
+    ///
+    /// The image embeds the settings and frame number as [`Metadata`]. It's
+    /// written under a temporary name and renamed into place before the
+    /// manifest is updated, so an interruption part way through never leaves
+    /// a truncated frame marked as done.
+    pub fn save_frame<P: SRGB>(
+        &mut self,
+        frame: u32,
+        image: &Buffer<P>,
+    ) -> Result<(), SequenceError> {
+        let path = self.frame_path(frame);
+        let partial = path.with_extension("partial.png");
+        let mut metadata = self.settings.clone();
+        metadata.insert("gremlin.frame", frame);
+        image.save_image_with_metadata(&partial, &metadata)?;
+        fs::rename(&partial, &path)?;
+
+        let mut manifest = fs::OpenOptions::new()
+            .append(true)
+            .open(self.manifest_path())?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        writeln!(manifest, "frame {} {}", frame, name)?;
+        manifest.sync_data()?;
+        self.done.insert(frame);
+        Ok(())
+    }
+
+    /// Render and save every frame not already complete, in order.
+    ///
+    /// `render` is called with each pending frame number and returns the
+    /// image to save. Returns the number of frames rendered.
+    pub fn run<F, P>(
+        &mut self,
+        frames: impl IntoIterator<Item = u32>,
+        mut render: F,
+    ) -> Result<usize, SequenceError>
+    where
+        F: FnMut(u32) -> Buffer<P>,
+        P: SRGB,
+    {
+        let mut rendered = 0;
+        for frame in frames {
+            if self.is_complete(frame) {
+                continue;
+            }
+            let image = render(frame);
+            self.save_frame(frame, &image)?;
+            rendered += 1;
+        }
+        Ok(rendered)
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dir.join(MANIFEST)
+    }
+
+    fn write_header(&self) -> io::Result<()> {
+        let header = format!("{}\nsettings {:016x}\n", MANIFEST_HEADER, self.hash);
+        fs::write(self.manifest_path(), header)
+    }
+
+    fn read_manifest(&mut self, manifest: &str) -> Result<(), SequenceError> {
+        let err = |line, msg| SequenceError::Manifest { line, msg };
+        let mut lines = manifest.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+        match lines.next() {
+            Some((_, MANIFEST_HEADER)) => {}
+            _ => return Err(err(1, "not a sequence manifest")),
+        }
+        let found = match lines.next() {
+            Some((_, line)) => line
+                .strip_prefix("settings ")
+                .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                .ok_or(err(2, "expected settings hash"))?,
+            None => return Err(err(2, "expected settings hash")),
+        };
+        if found != self.hash {
+            return Err(SequenceError::SettingsChanged {
+                expected: found,
+                found: self.hash,
+            });
+        }
+
+        for (line_no, line) in lines {
+            let mut words = line.split_whitespace();
+            let (Some("frame"), Some(frame), Some(name), None) =
+                (words.next(), words.next(), words.next(), words.next())
+            else {
+                // An interruption while appending can leave a partial last
+                // line; that frame just gets rendered again
+                if line_no == manifest.lines().count() {
+                    break;
+                }
+                return Err(err(line_no, "expected `frame N FILE`"));
+            };
+            let frame = frame
+                .parse()
+                .map_err(|_| err(line_no, "invalid frame number"))?;
+            if self.dir.join(name).is_file() {
+                self.done.insert(frame);
+            }
+        }
+        Ok(())
+    }
+}
+
+// FNV-1a over the entries. Stable across runs and platforms, unlike std's
+// `DefaultHasher`, since it's stored in manifests.
+fn settings_hash(settings: &Metadata) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (key, value) in settings.iter() {
+        for b in key.bytes().chain([0]).chain(value.bytes()).chain([0]) {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// An error from a [`Sequence`].
+#[derive(Debug)]
+pub enum SequenceError {
+    /// The manifest couldn't be read or written.
+    Io(io::Error),
+    /// A frame image couldn't be saved.
+    Image(ImageError),
+    /// The manifest couldn't be parsed. Line numbers start at `1`.
+    Manifest { line: usize, msg: &'static str },
+    /// The manifest was written with different render settings.
+    SettingsChanged { expected: u64, found: u64 },
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not access sequence manifest: {}", e),
+            Self::Image(e) => write!(f, "could not save frame: {}", e),
+            Self::Manifest { line, msg } => write!(f, "manifest line {}: {}", line, msg),
+            Self::SettingsChanged { expected, found } => write!(
+                f,
+                "render settings changed since the sequence was started \
+                 (hash {:016x}, now {:016x}); restart it to discard existing frames",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl Error for SequenceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SequenceError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ImageError> for SequenceError {
+    fn from(e: ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::RGB;
+
+    fn settings(spp: u32) -> Metadata {
+        let mut metadata = Metadata::new();
+        metadata
+            .insert("gremlin.spp", spp)
+            .insert("gremlin.frame", 7);
+        metadata
+    }
+
+    #[test]
+    fn resumes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("sequence");
+        let image = || Buffer::<RGB>::new(2, 2);
+
+        // Interrupted after frame 2
+        let mut seq = Sequence::open(&dir, &settings(16)).unwrap();
+        assert_eq!(2, seq.run(1..=2, |_| image()).unwrap());
+        drop(seq);
+
+        // The frame number doesn't count towards the settings
+        let mut seq = Sequence::open(&dir, &settings(16)).unwrap();
+        assert_eq!(vec![1, 2], seq.completed().collect::<Vec<_>>());
+        let mut frames = vec![];
+        let rendered = seq
+            .run(1..=4, |frame| {
+                frames.push(frame);
+                image()
+            })
+            .unwrap();
+        assert_eq!((2, vec![3, 4]), (rendered, frames));
+        let read = Metadata::read_png(seq.frame_path(3)).unwrap();
+        assert_eq!(Some("3"), read.get("gremlin.frame"));
+
+        // Deleted frames are rendered again
+        fs::remove_file(seq.frame_path(2)).unwrap();
+        let seq = Sequence::open(&dir, &settings(16)).unwrap();
+        assert!(!seq.is_complete(2) && seq.is_complete(4));
+
+        // Changed settings
+        let err = Sequence::open(&dir, &settings(32)).unwrap_err();
+        assert!(matches!(err, SequenceError::SettingsChanged { .. }));
+        let seq = Sequence::restart(&dir, &settings(32)).unwrap();
+        assert_eq!(0, seq.completed().count());
+    }
+}
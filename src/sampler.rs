@@ -0,0 +1,119 @@
+//! # Sample generation.
+//!
+//! Monte Carlo estimates converge faster when samples are well distributed,
+//! which is what low-discrepancy (quasi-Monte Carlo) sequences provide. But
+//! a QMC sequence only helps if each decision along a path consistently draws
+//! from the same dimension of the sequence: the lens sample must always come
+//! from the same dimensions, the second bounce's BSDF sample from the same
+//! dimensions, and so on. Otherwise dimensions get mixed up between paths and
+//! the sequence behaves no better than random numbers (or worse, correlates).
+//!
+//! So rather than handing out "the next random number", a [`Sampler`] is
+//! asked for a value for a specific [`SampleDimension`], which maps each
+//! purpose at each bounce to a fixed slot in the sequence.
+//!
+//! Cameras draw from a sampler in [`Camera::ray_sampled`], and integrators
+//! in [`Integrator::radiance_sampled`], which a [`Renderer`] given a
+//! [`sampler`] calls for every sample.
+//!
+//! [`Camera::ray_sampled`]: crate::camera::Camera::ray_sampled
+//! [`Integrator::radiance_sampled`]: crate::integrator::Integrator::radiance_sampled
+//! [`Renderer`]: crate::renderer::Renderer
+//! [`sampler`]: crate::renderer::Renderer::sampler
+
+use crate::Float;
+
+mod halton;
+pub use halton::*;
+
+mod independent;
+pub use independent::*;
+
+/// The purpose a sample value will be used for.
+///
+/// Bounces are counted from `0` at the first surface hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleDimension {
+    /// Position within the pixel (2D).
+    Pixel,
+    /// Position on the lens aperture (2D).
+    Lens,
+    /// Direction sampled from the BSDF at the given bounce (2D).
+    Bsdf(u32),
+    /// Position sampled on a light at the given bounce (2D).
+    Light(u32),
+    /// Choice of light at the given bounce (1D).
+    LightSelect(u32),
+    /// Russian roulette decision at the given bounce (1D).
+    Roulette(u32),
+}
+
+impl SampleDimension {
+    /// Number of dimensions consumed by the camera.
+    const CAMERA_DIMS: usize = 4;
+    /// Number of dimensions consumed per bounce.
+    const BOUNCE_DIMS: usize = 6;
+
+    /// The index of the first dimension in the sequence for this purpose.
+    ///
+    /// 2D purposes occupy this index and the next.
+    #[inline]
+    pub const fn index(self) -> usize {
+        match self {
+            Self::Pixel => 0,
+            Self::Lens => 2,
+            Self::Bsdf(b) => Self::bounce(b),
+            Self::Light(b) => Self::bounce(b) + 2,
+            Self::LightSelect(b) => Self::bounce(b) + 4,
+            Self::Roulette(b) => Self::bounce(b) + 5,
+        }
+    }
+
+    #[inline]
+    const fn bounce(b: u32) -> usize {
+        Self::CAMERA_DIMS + b as usize * Self::BOUNCE_DIMS
+    }
+}
+
+/// The core trait for sample generators.
+///
+/// Usage is: call [`start_pixel_sample`] for each sample of each pixel, then
+/// request values with [`get_1d`] and [`get_2d`], in any order.
+///
+/// [`start_pixel_sample`]: Self::start_pixel_sample
+/// [`get_1d`]: Self::get_1d
+/// [`get_2d`]: Self::get_2d
+pub trait Sampler: Send {
+    /// Begin generating the `index`-th sample for the pixel at `(px, py)`.
+    fn start_pixel_sample(&mut self, px: u32, py: u32, index: u64);
+
+    /// A value in `[0, 1)` for a 1D purpose.
+    fn get_1d(&mut self, dim: SampleDimension) -> Float;
+
+    /// A point in `[0, 1)^2` for a 2D purpose.
+    fn get_2d(&mut self, dim: SampleDimension) -> [Float; 2];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimensions_do_not_overlap() {
+        let mut used = std::collections::HashSet::new();
+        let mut claim = |dim: SampleDimension, n: usize| {
+            for i in 0..n {
+                assert!(used.insert(dim.index() + i), "{:?} overlaps", dim);
+            }
+        };
+        claim(SampleDimension::Pixel, 2);
+        claim(SampleDimension::Lens, 2);
+        for b in 0..4 {
+            claim(SampleDimension::Bsdf(b), 2);
+            claim(SampleDimension::Light(b), 2);
+            claim(SampleDimension::LightSelect(b), 1);
+            claim(SampleDimension::Roulette(b), 1);
+        }
+        assert_eq!(4 + 4 * 6, used.len());
+    }
+}
@@ -0,0 +1,192 @@
+use super::{SampleDimension, Sampler};
+use crate::{math, Float};
+
+/// The first few primes, one per Halton dimension.
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Largest float strictly less than one.
+const ONE_MINUS_EPSILON: Float = 1.0 - Float::EPSILON / 2.0;
+
+/// A low-discrepancy sampler based on the Halton sequence.
+///
+/// Dimension `d` of sample `i` is the radical inverse of `i` in the `d`-th
+/// prime base, with each digit shuffled by a random permutation of the
+/// digits in that base. Unscrambled, dimensions with nearby bases `b` and
+/// `b'` step by `1/b` and `1/b'` together, so their first points fall along
+/// a line and a bounce sampled from the pair gains nothing over random
+/// values. Every pixel walks the same sequence, decorrelated by a per-pixel
+/// Cranley-Patterson rotation (a toroidal shift) derived from the seed, so
+/// neighbouring pixels don't show the same pattern.
+///
+/// Dimensions beyond the prime table (very deep bounces) fall back to hashed
+/// pseudo-random values, which is where QMC stops paying off anyway.
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u64,
+}
+
+impl HaltonSampler {
+    /// Creates a new Halton sampler.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+        }
+    }
+
+    fn sample(&self, dim: usize) -> Float {
+        let (px, py) = self.pixel;
+        let keys = [self.seed, px as u64, py as u64, dim as u64];
+        match PRIMES.get(dim) {
+            Some(&base) => {
+                let shift = math::hash_to_unit(math::hash_keys(&keys));
+                let key = math::hash_keys(&[self.seed, dim as u64]);
+                let v = scrambled_radical_inverse(base, self.index, key) + shift;
+                (v - v.floor()).min(ONE_MINUS_EPSILON)
+            }
+            None => math::hash_to_unit(math::mix64(math::hash_keys(&keys) ^ self.index)),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, px: u32, py: u32, index: u64) {
+        self.pixel = (px, py);
+        self.index = index;
+    }
+
+    #[inline]
+    fn get_1d(&mut self, dim: SampleDimension) -> Float {
+        self.sample(dim.index())
+    }
+
+    #[inline]
+    fn get_2d(&mut self, dim: SampleDimension) -> [Float; 2] {
+        let d = dim.index();
+        [self.sample(d), self.sample(d + 1)]
+    }
+}
+
+/// The radical inverse of `i` in the given base (its digits mirrored about
+/// the radix point), with the digits in each position permuted by a
+/// permutation chosen by `key`.
+///
+/// The zeros past the last digit of `i` are permuted too, for as long as
+/// they can change the result.
+fn scrambled_radical_inverse(base: u64, mut i: u64, key: u64) -> Float {
+    let inv_base = 1.0 / base as Float;
+    let mut inv_base_n: Float = 1.0;
+    let mut v = 0.0;
+    let mut position = 0;
+    while 1.0 - (base - 1) as Float * inv_base_n < 1.0 {
+        let next = i / base;
+        let digit = (i - next * base) as u32;
+        let p = math::mix64(key ^ position) as u32;
+        inv_base_n *= inv_base;
+        v += permute(digit, base as u32, p) as Float * inv_base_n;
+        i = next;
+        position += 1;
+    }
+    v.min(ONE_MINUS_EPSILON)
+}
+
+/// Element `i` of a random permutation of `0..len` chosen by `p`, without
+/// building the permutation.
+///
+/// See: <https://graphics.pixar.com/library/MultiJitteredSampling/paper.pdf>
+fn permute(mut i: u32, len: u32, p: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    // A bijection on `0..=w`, walked until it lands back in `0..len`
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            return (i + p % len) % len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn scrambling_keeps_strata() {
+        for base in [2, 3, 5, 11, 131] {
+            for p in 0..20u32 {
+                let mut seen = vec![false; base as usize];
+                for i in 0..base {
+                    let j = permute(i, base, p.wrapping_mul(0x9e3779b9));
+                    assert!(!std::mem::replace(&mut seen[j as usize], true));
+                }
+            }
+
+            // Any `base^2` consecutive points land one in each stratum
+            let n = base * base;
+            for key in 0..4 {
+                let mut seen = vec![false; n as usize];
+                for i in 7..7 + n as u64 {
+                    let v = scrambled_radical_inverse(base as u64, i, key);
+                    let stratum = (v * n as Float) as usize;
+                    assert!(!std::mem::replace(&mut seen[stratum], true));
+                }
+            }
+        }
+        let v = |key| scrambled_radical_inverse(3, 5, key);
+        assert_ne!(v(0), v(1));
+    }
+
+    #[test]
+    fn dimensions_consistent_across_paths() {
+        let mut sampler = HaltonSampler::new(7);
+        sampler.start_pixel_sample(3, 4, 5);
+        let lens = sampler.get_2d(SampleDimension::Lens);
+        let bsdf = sampler.get_2d(SampleDimension::Bsdf(1));
+
+        // Request order does not matter, only the dimension.
+        sampler.start_pixel_sample(3, 4, 5);
+        assert_eq!(bsdf, sampler.get_2d(SampleDimension::Bsdf(1)));
+        assert_eq!(lens, sampler.get_2d(SampleDimension::Lens));
+    }
+
+    #[test]
+    fn stratified_in_pixel() {
+        // The first 2 samples in base 2 always land in opposite halves.
+        let mut sampler = HaltonSampler::new(1);
+        let mut xs = [0.0; 2];
+        for (i, x) in xs.iter_mut().enumerate() {
+            sampler.start_pixel_sample(0, 0, i as u64);
+            *x = sampler.get_2d(SampleDimension::Pixel)[0];
+            assert!((0.0..1.0).contains(x));
+        }
+        assert_relative_eq!(0.5, (xs[0] - xs[1]).abs());
+    }
+}
@@ -0,0 +1,42 @@
+use super::{SampleDimension, Sampler};
+use crate::{math, Float};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// A sampler that ignores dimensions and returns independent uniform random
+/// values.
+///
+/// Each pixel sample gets its own stream, derived from the seed, so results
+/// are reproducible. This is the baseline the QMC samplers are compared
+/// against.
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl IndependentSampler {
+    /// Creates a new independent sampler.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, px: u32, py: u32, index: u64) {
+        let key = math::hash_keys(&[self.seed, px as u64, py as u64, index]);
+        self.rng = StdRng::seed_from_u64(key);
+    }
+
+    #[inline]
+    fn get_1d(&mut self, _dim: SampleDimension) -> Float {
+        self.rng.gen()
+    }
+
+    #[inline]
+    fn get_2d(&mut self, _dim: SampleDimension) -> [Float; 2] {
+        [self.rng.gen(), self.rng.gen()]
+    }
+}
@@ -1,13 +1,665 @@
-use crate::{material::Material, shape::Surface};
+//! # Scene description.
+//!
+//! A [`Scene`] pairs surfaces with the materials they're made of. Before a
+//! long render it's worth calling [`Scene::validate`], which catches the kinds
+//! of mistakes that otherwise only show up as black pixels or NaNs an hour in.
+//!
+//! A scene can also carry a [`Clip`], cutting away part of the geometry to
+//! show sections through the interior of a model.
+//!
+//! Rays leaving a surface start a little way off it, by an amount the scene's
+//! [`OffsetPolicy`] scales to the primitive that was hit, so scenes modeled in
+//! millimeters and in kilometers both render without shadow acne.
 
-pub struct Scene {}
+use crate::{
+    color::RGB,
+    geo::{Point, Ray, RayCone, SpawnOffset, Unit, Vector},
+    integrator::{PathBudget, ShadowRays},
+    light::SpotLight,
+    material::{Lambertian, Material},
+    shape::{Bounded, Clip, ClipPlane, Intersection, Surface},
+    texture::TextureContext,
+    Float,
+};
+use rand::Rng;
+use std::fmt;
+
+mod budget;
+pub use budget::*;
+
+mod file;
+pub use file::*;
+
+/// A collection of primitives and their materials.
+#[derive(Default)]
+pub struct Scene {
+    surfaces: Vec<Surface>,
+    materials: Vec<Material>,
+    // The largest coordinate of each surface's bounds, for offsets
+    extents: Vec<Float>,
+    material_override: Option<MaterialOverride>,
+    clip: Clip,
+    offset_policy: OffsetPolicy,
+}
+
+/// A ray hit on one of a scene's surfaces, linked back to what it hit.
+///
+/// Returned by [`Scene::intersect`], this carries everything an integrator
+/// needs to shade the hit: the geometry, which primitive was hit, and the
+/// material to shade it with.
+#[derive(Clone, Copy)]
+pub struct SurfaceInteraction<'a> {
+    pub isect: Intersection,
+    /// Index of the surface hit, into [`Scene::surfaces`].
+    pub primitive: usize,
+    /// The material to shade the hit with, taking any [`MaterialOverride`]
+    /// into account.
+    pub material: &'a Material,
+    /// How far rays leaving the hit start off the surface, from the scene's
+    /// [`OffsetPolicy`].
+    pub offset: SpawnOffset,
+}
+
+impl SurfaceInteraction<'_> {
+    /// A ray leaving the hit in `direction`, offset by [`offset`] so it
+    /// doesn't hit the same surface again.
+    ///
+    /// [`offset`]: Self::offset
+    #[inline]
+    pub fn spawn(&self, direction: Vector) -> Ray {
+        self.offset
+            .spawn(self.isect.point, direction, self.isect.norm)
+    }
+
+    /// The texture context for the hit, with the primitive index as its
+    /// [`instance`] id.
+    ///
+    /// [`instance`]: TextureContext::instance
+    #[inline]
+    pub fn texture_context(&self) -> TextureContext {
+        TextureContext {
+            instance: self.primitive as u64,
+            ..TextureContext::from(&self.isect)
+        }
+    }
+
+    /// Like [`texture_context`], with the [`footprint`] of the `cone` around
+    /// `ray` where it hit, so textures can filter.
+    ///
+    /// [`texture_context`]: Self::texture_context
+    /// [`footprint`]: TextureContext::footprint
+    pub fn texture_context_with_cone(&self, ray: &Ray, cone: &RayCone) -> TextureContext {
+        let distance = self.isect.t * ray.direction.len();
+        let cos = ray.direction.dot(Vector::from(self.isect.norm)) / ray.direction.len();
+        TextureContext {
+            footprint: cone.footprint(distance, cos),
+            ..self.texture_context()
+        }
+    }
+}
+
+/// How a [`Scene`] picks the [`SpawnOffset`] for rays leaving its surfaces.
+///
+/// Hit points are only as precise as the coordinates they're computed from,
+/// so the offset that keeps rays from re-hitting a surface depends on the
+/// scale of the scene, and of the primitive hit: a kilometer-wide ground
+/// plane needs a bigger one than a millimeter-sized screw sitting on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OffsetPolicy {
+    /// Scale each offset to the primitive hit, with a
+    /// [`SpawnOffset::ShapeErrorBound`] over its bounds, times `scale`. The
+    /// default, with a `scale` of `1`.
+    PerPrimitive { scale: Float },
+    /// Use the same offset for every surface.
+    Uniform(SpawnOffset),
+}
+
+impl Default for OffsetPolicy {
+    fn default() -> Self {
+        Self::PerPrimitive { scale: 1.0 }
+    }
+}
+
+/// Replaces every material in a scene with a single one at render time.
+///
+/// The classic use is a "clay render": everything shaded as plain grey
+/// diffuse, so lighting and geometry can be reviewed without textures and
+/// shiny materials getting in the way.
+pub struct MaterialOverride {
+    material: Material,
+}
+
+impl MaterialOverride {
+    /// Albedo of the [`clay`] material.
+    ///
+    /// [`clay`]: Self::clay
+    pub const CLAY_ALBEDO: Float = 0.5;
+
+    /// Override all materials with the given one.
+    pub fn new(material: impl Into<Material>) -> Self {
+        Self {
+            material: material.into(),
+        }
+    }
+
+    /// Override all materials with mid-grey diffuse clay.
+    pub fn clay() -> Self {
+        let albedo = Self::CLAY_ALBEDO;
+        Self::new(Lambertian::new(RGB::from([albedo, albedo, albedo])))
+    }
+}
 
 impl Scene {
-    pub fn add_primitive<S, M>(&mut self, _surface: S, _material: M)
+    /// Adds a surface, made of the given material, to the scene.
+    pub fn add_primitive<S, M>(&mut self, surface: S, material: M)
     where
         Surface: From<S>,
         Material: From<M>,
     {
-        todo!()
+        let surface = Surface::from(surface);
+        let bounds = surface.bounds();
+        let corners = [bounds.min(), bounds.max()].map(|p| Vector::from(p).apply(Float::abs));
+        let extent = corners[0].max_component().max(corners[1].max_component());
+        // Unbounded shapes fall back to the hit point's own error
+        self.extents
+            .push(if extent.is_finite() { extent } else { 0.0 });
+        self.surfaces.push(surface);
+        self.materials.push(material.into());
+    }
+
+    /// The scene's surfaces.
+    #[inline]
+    pub fn surfaces(&self) -> &[Surface] {
+        &self.surfaces
+    }
+
+    /// The scene's materials. Indexed in parallel with [`surfaces`].
+    ///
+    /// These are the materials as added, ignoring any override; integrators
+    /// should use [`material`] instead.
+    ///
+    /// [`surfaces`]: Self::surfaces
+    /// [`material`]: Self::material
+    #[inline]
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// The material to shade the surface at index `idx` with, taking any
+    /// [`MaterialOverride`] into account.
+    #[inline]
+    pub fn material(&self, idx: usize) -> &Material {
+        match &self.material_override {
+            Some(ov) => &ov.material,
+            None => &self.materials[idx],
+        }
+    }
+
+    /// The offset for rays leaving the surface at index `idx`, from the
+    /// scene's [`OffsetPolicy`].
+    #[inline]
+    pub fn spawn_offset(&self, idx: usize) -> SpawnOffset {
+        match self.offset_policy {
+            OffsetPolicy::PerPrimitive { scale } => SpawnOffset::ShapeErrorBound {
+                scale,
+                extent: self.extents[idx],
+            },
+            OffsetPolicy::Uniform(offset) => offset,
+        }
+    }
+
+    /// Set how rays leaving the scene's surfaces are offset.
+    pub fn set_offset_policy(&mut self, policy: OffsetPolicy) {
+        self.offset_policy = policy;
+    }
+
+    /// The texture context for a hit on the surface at index `idx`, with the
+    /// index as its [`instance`] id.
+    ///
+    /// [`instance`]: TextureContext::instance
+    #[inline]
+    pub fn texture_context(&self, idx: usize, isect: &Intersection) -> TextureContext {
+        TextureContext {
+            instance: idx as u64,
+            ..TextureContext::from(isect)
+        }
+    }
+
+    /// Find the nearest surface the ray hits within `[t_min, t_max]`, along
+    /// with its material.
+    ///
+    /// Geometry removed by the scene's [`clip`] planes is skipped. Every
+    /// surface is tested in turn, so for large scenes build an acceleration
+    /// structure such as a [`Bvh`] over the surfaces instead, and use its
+    /// [`intersect_indexed`] index to look up the [`material`].
+    ///
+    /// [`clip`]: Self::clip
+    /// [`Bvh`]: crate::shape::Bvh
+    /// [`intersect_indexed`]: crate::shape::Bvh::intersect_indexed
+    /// [`material`]: Self::material
+    pub fn intersect(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<SurfaceInteraction<'_>> {
+        let mut nearest = None;
+        let mut t_max = t_max;
+        for (idx, surface) in self.surfaces.iter().enumerate() {
+            if let Some(isect) = self.clip.intersect(surface, ray, t_min, t_max) {
+                t_max = isect.t;
+                nearest = Some((idx, isect));
+            }
+        }
+        nearest.map(|(primitive, isect)| SurfaceInteraction {
+            isect,
+            primitive,
+            material: self.material(primitive),
+            offset: self.spawn_offset(primitive),
+        })
+    }
+
+    /// Returns `true` if anything blocks the ray within `[t_min, t_max]`,
+    /// treating every surface as opaque. See [`transmittance`] to let light
+    /// through glass.
+    ///
+    /// [`transmittance`]: Self::transmittance
+    pub fn occluded(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        self.surfaces
+            .iter()
+            .any(|surface| self.clip.intersect(surface, ray, t_min, t_max).is_some())
+    }
+
+    /// How much light gets along the ray within `[t_min, t_max]`, passing
+    /// through every surface in the way as its material allows (see
+    /// [`Material::transmittance`]). Black if something opaque blocks it.
+    ///
+    /// This is the shadow ray query for direct lighting, so glass casts
+    /// colored shadows. Holes in [`AlphaMasked`] shapes are skipped as they
+    /// are for any ray. Each surface found counts as a query against
+    /// `budget`, and running out of it blocks the ray.
+    ///
+    /// [`AlphaMasked`]: crate::shape::AlphaMasked
+    pub fn transmittance(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        shadow: &ShadowRays,
+        budget: &mut PathBudget,
+        rng: &mut impl Rng,
+    ) -> RGB {
+        let Ok(wo) = Unit::try_from(-ray.direction) else {
+            return RGB::default();
+        };
+        let hit = |ray: &Ray, t_min, t_max| {
+            let hit = self.intersect(ray, t_min, t_max)?;
+            Some((hit.isect, hit.material.transmittance(wo, &hit.isect)))
+        };
+        shadow.transmittance_by(ray, t_min, t_max, hit, budget, rng)
+    }
+
+    /// Split the scene into its surfaces and materials.
+    pub fn into_parts(self) -> (Vec<Surface>, Vec<Material>) {
+        (self.surfaces, self.materials)
+    }
+
+    /// Override the scene's materials at render time, or pass `None` to
+    /// restore them. The materials themselves are left untouched.
+    pub fn set_material_override(&mut self, material_override: Option<MaterialOverride>) {
+        self.material_override = material_override;
+    }
+
+    /// Cut away the geometry on one side of the plane.
+    pub fn add_clip_plane(&mut self, plane: ClipPlane) {
+        self.clip.push(plane);
+    }
+
+    /// The scene's clip planes. Integrators should intersect through this
+    /// (see [`Clip::intersect`]) so cut-away geometry isn't rendered.
+    #[inline]
+    pub fn clip(&self) -> &Clip {
+        &self.clip
+    }
+
+    /// Check the scene, a camera placed at `eye` and the `lights` that will
+    /// illuminate it for problems that would spoil a render.
+    ///
+    /// Primitives are checked for non-finite or degenerate geometry, and
+    /// lights, given by index into `lights`, for non-finite positions and
+    /// zero power. The camera is checked for being enclosed by geometry:
+    /// rays are cast from the eye in several directions, and if every one of
+    /// them first hits the back of a surface, the eye is taken to be inside a
+    /// closed surface (a sphere, voxel model, or a mesh of triangles wound to
+    /// face outwards). That issue names the surface hit by the first ray.
+    pub fn validate(&self, eye: Point, lights: &[SpotLight]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        self.validate_primitives(&mut report);
+        self.validate_camera(eye, &mut report);
+        Self::validate_lights(lights, &mut report);
+        report
+    }
+
+    fn validate_primitives(&self, report: &mut ValidationReport) {
+        for (idx, surface) in self.surfaces.iter().enumerate() {
+            // Voxel models and heightfields are placed in the world by an
+            // origin and a uniform scale; everything else by its coordinates
+            let (points, size, placed) = match surface {
+                Surface::Sphere(s) => (vec![s.center()], s.radius(), false),
+                Surface::Triangle(t) => (t.vertices().to_vec(), t.area(), false),
+                Surface::Voxels(v) => (vec![v.origin()], v.voxel_size(), true),
+                Surface::Curve(c) => (c.points().to_vec(), c.width()[0].max(c.width()[1]), false),
+                Surface::Heightfield(h) => (vec![h.origin()], h.cell_size(), true),
+            };
+            let finite =
+                size.is_finite() && points.into_iter().all(|p| Vector::from(p).is_finite());
+            if !finite && placed {
+                report.issues.push(Issue::NonFiniteTransform(idx));
+            } else if !finite {
+                report.issues.push(Issue::NonFiniteGeometry(idx));
+            } else if !size.is_normal() || size < 0.0 {
+                report.issues.push(Issue::DegeneratePrimitive(idx));
+            }
+        }
+    }
+
+    fn validate_camera(&self, eye: Point, report: &mut ValidationReport) {
+        if !Vector::from(eye).is_finite() {
+            report.issues.push(Issue::NonFiniteCamera);
+            return;
+        }
+
+        // Arbitrary directions unlikely to graze axis-aligned geometry, one
+        // in every other octant
+        const DIRECTIONS: [[Float; 3]; 4] = [
+            [0.5773, 0.5774, 0.5775],
+            [-0.5874, -0.5673, 0.5772],
+            [-0.5671, 0.5876, -0.5773],
+            [0.5775, -0.5772, -0.5876],
+        ];
+        let mut enclosing = None;
+        for dir in DIRECTIONS {
+            let ray = Ray::new(eye, Vector::from(dir));
+            match self.intersect(&ray, 0.0, Float::INFINITY) {
+                Some(hit) if Vector::from(hit.isect.norm).dot(ray.direction) > 0.0 => {
+                    enclosing.get_or_insert(hit.primitive);
+                }
+                _ => return,
+            }
+        }
+        if let Some(idx) = enclosing {
+            report.issues.push(Issue::CameraInsideGeometry(idx));
+        }
+    }
+
+    fn validate_lights(lights: &[SpotLight], report: &mut ValidationReport) {
+        for (idx, light) in lights.iter().enumerate() {
+            let power = <[Float; 3]>::from(light.power());
+            if !Vector::from(light.position()).is_finite() || !power.iter().all(|c| c.is_finite()) {
+                report.issues.push(Issue::NonFiniteEmitter(idx));
+            } else if power.iter().all(|&c| c <= 0.0) {
+                report.issues.push(Issue::ZeroPowerEmitter(idx));
+            }
+        }
+    }
+}
+
+/// A single problem found by [`Scene::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    /// The primitive at this index has NaN or infinite coordinates.
+    NonFiniteGeometry(usize),
+    /// The primitive at this index is placed in the world by a NaN or
+    /// infinite origin or scale.
+    NonFiniteTransform(usize),
+    /// The primitive at this index has zero, negative (or subnormal) area
+    /// or size.
+    DegeneratePrimitive(usize),
+    /// The primitive at this index uses a material whose texture isn't
+    /// defined.
+    MissingTexture(usize),
+    /// The light at this index has NaN or infinite position or power.
+    NonFiniteEmitter(usize),
+    /// The light at this index gives off no light.
+    ZeroPowerEmitter(usize),
+    /// The camera position has NaN or infinite coordinates.
+    NonFiniteCamera,
+    /// The camera is enclosed by the primitive at this index.
+    CameraInsideGeometry(usize),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFiniteGeometry(i) => write!(f, "primitive {} has non-finite coordinates", i),
+            Self::NonFiniteTransform(i) => write!(f, "primitive {} has a non-finite transform", i),
+            Self::DegeneratePrimitive(i) => write!(f, "primitive {} is degenerate", i),
+            Self::MissingTexture(i) => write!(f, "primitive {} uses an undefined texture", i),
+            Self::NonFiniteEmitter(i) => write!(f, "light {} has non-finite position or power", i),
+            Self::ZeroPowerEmitter(i) => write!(f, "light {} has zero power", i),
+            Self::NonFiniteCamera => write!(f, "camera has non-finite position"),
+            Self::CameraInsideGeometry(i) => write!(f, "camera is inside primitive {}", i),
+        }
+    }
+}
+
+/// The result of validating a scene.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no issues were found.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::RGB,
+        geo::Component,
+        integrator::WorkLimits,
+        material::{Dielectric, Lambertian, Plastic},
+        shape::{Heightfield, Sphere, Triangle},
+    };
+    use rand::prelude::*;
+
+    fn grey() -> Lambertian {
+        Lambertian::new(RGB::from([0.5, 0.5, 0.5]))
+    }
+
+    #[test]
+    fn degenerate_triangle() {
+        let mut scene = Scene::default();
+        scene.add_primitive(Sphere::new([0.0, 0.0, 0.0], 1.0), grey());
+        scene.add_primitive(
+            Triangle::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]),
+            grey(),
+        );
+        assert_eq!(
+            vec![Issue::DegeneratePrimitive(1)],
+            scene.validate(Point::new(0.0, 0.0, -5.0), &[]).issues
+        );
+    }
+
+    #[test]
+    fn validate_primitives() {
+        let mut scene = Scene::default();
+        scene.add_primitive(
+            Triangle::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            grey(),
+        );
+        scene.add_primitive(
+            Triangle::new(
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, Float::INFINITY, 0.0],
+            ),
+            grey(),
+        );
+        scene.add_primitive(
+            Heightfield::new(2, 2, vec![0.0; 4]).with_placement([Float::NAN, 0.0, 0.0], 1.0),
+            grey(),
+        );
+        scene.add_primitive(
+            Heightfield::new(2, 2, vec![0.0; 4]).with_placement(Point::ORIGIN, Float::INFINITY),
+            grey(),
+        );
+        assert_eq!(
+            vec![
+                Issue::NonFiniteGeometry(1),
+                Issue::NonFiniteTransform(2),
+                Issue::NonFiniteTransform(3),
+            ],
+            scene.validate(Point::new(0.0, 0.0, -5.0), &[]).issues
+        );
+    }
+
+    #[test]
+    fn validate_lights() {
+        let scene = Scene::default();
+        let lights = [
+            SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::splat(10.0)),
+            SpotLight::new([0.0, 5.0, 0.0], -Unit::Y_AXIS, RGB::default()),
+            SpotLight::new([0.0, Float::NAN, 0.0], -Unit::Y_AXIS, RGB::splat(10.0)),
+        ];
+        assert_eq!(
+            vec![Issue::ZeroPowerEmitter(1), Issue::NonFiniteEmitter(2)],
+            scene.validate(Point::new(0.0, 0.0, -5.0), &lights).issues
+        );
+    }
+
+    #[test]
+    fn clay_override() {
+        let mut scene = Scene::default();
+        scene.add_primitive(Sphere::new([0.0, 0.0, 0.0], 1.0), Plastic::new(grey(), 1.5));
+        assert!(matches!(scene.material(0), Material::Plastic(_)));
+
+        scene.set_material_override(Some(MaterialOverride::clay()));
+        assert!(matches!(scene.material(0), Material::Lambertian(_)));
+        assert!(matches!(scene.materials()[0], Material::Plastic(_)));
+
+        scene.set_material_override(None);
+        assert!(matches!(scene.material(0), Material::Plastic(_)));
+    }
+
+    #[test]
+    fn intersect() {
+        let mut scene = Scene::default();
+        scene.add_primitive(Sphere::new([0.0, 0.0, 5.0], 1.0), grey());
+        scene.add_primitive(Sphere::new([0.0, 0.0, 2.0], 0.5), Plastic::new(grey(), 1.5));
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+
+        let hit = scene.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!((1, 1.5), (hit.primitive, hit.isect.t));
+        assert!(matches!(hit.material, Material::Plastic(_)));
+        assert_eq!(1, hit.texture_context().instance);
+        assert_eq!(0.0, hit.texture_context().footprint);
+        let ctx = hit.texture_context_with_cone(&ray, &RayCone::new(0.0, 0.01));
+        assert!((ctx.footprint - 0.015).abs() < 1e-12);
+
+        let hit = scene.intersect(&ray, 3.0, Float::INFINITY).unwrap();
+        assert_eq!(0, hit.primitive);
+        assert!(scene.intersect(&ray, 0.0, 1.0).is_none());
+
+        // Clipped geometry is skipped
+        scene.add_clip_plane(ClipPlane::below(Component::Z, 2.0));
+        let hit = scene.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!((1, 2.5), (hit.primitive, hit.isect.t));
+        scene.add_clip_plane(ClipPlane::above(Component::Z, 3.0));
+        assert!(scene.intersect(&ray, 3.0, Float::INFINITY).is_none());
+    }
+
+    #[test]
+    fn offsets_scale_with_primitives() {
+        // A kilometers-wide sloping plane through the origin. Hits near the
+        // origin have tiny coordinates, but are computed from its vertices
+        let mut scene = Scene::default();
+        let (a, b, c) = ([-5e3, -1.5e3, -5e3], [0.0, 500.0, 5e3], [5e3, 500.0, -5e3]);
+        scene.add_primitive(Triangle::new(a, b, c), grey());
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..1000 {
+            let (x, z): (Float, Float) = (rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            let ray = Ray::new(Point::new(x, 3.0, z), Vector::new(0.3, -1.0, 0.2));
+            let hit = scene.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+            let back = hit.spawn(-ray.direction);
+            assert!(!scene.occluded(&back, 0.0, Float::INFINITY));
+        }
+
+        let fixed = SpawnOffset::Fixed(0.5);
+        scene.set_offset_policy(OffsetPolicy::Uniform(fixed));
+        assert_eq!(fixed, scene.spawn_offset(0));
+    }
+
+    #[test]
+    fn colored_shadows() {
+        let mut scene = Scene::default();
+        let green = RGB::from([0.2, 0.9, 0.2]);
+        scene.add_primitive(
+            Sphere::new([0.0, 0.0, 2.0], 0.5),
+            Dielectric::new(1.5).tint(green),
+        );
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+        let mut rng = StdRng::seed_from_u64(1);
+        let shadow = ShadowRays::default();
+        let budget = || WorkLimits::default().budget();
+
+        // In and out of the glass: tinted twice, less 4% reflected each time
+        assert!(scene.occluded(&ray, 0.0, Float::INFINITY));
+        let tr = scene.transmittance(&ray, 0.0, Float::INFINITY, &shadow, &mut budget(), &mut rng);
+        let expected = green * green * (0.96 * 0.96);
+        assert!(
+            (tr.max_component() - expected.max_component()).abs() < 1e-3,
+            "{:?}",
+            tr
+        );
+        assert!(
+            (tr.min_component() - expected.min_component()).abs() < 1e-3,
+            "{:?}",
+            tr
+        );
+
+        // Stopping inside the glass only crosses once
+        let tr = scene.transmittance(&ray, 0.0, 2.0, &shadow, &mut budget(), &mut rng);
+        assert!((tr.max_component() - 0.96 * 0.9).abs() < 1e-3, "{:?}", tr);
+
+        // Anything opaque blocks it all
+        scene.add_primitive(Sphere::new([0.0, 0.0, 5.0], 1.0), grey());
+        let tr = scene.transmittance(&ray, 0.0, Float::INFINITY, &shadow, &mut budget(), &mut rng);
+        assert_eq!(RGB::default(), tr);
+        assert!(!scene.occluded(&ray, 0.0, 1.0));
+    }
+
+    #[test]
+    fn camera_inside() {
+        let mut scene = Scene::default();
+        scene.add_primitive(Sphere::new([0.0, 0.0, 0.0], 1.0), grey());
+        assert!(scene.validate(Point::new(0.0, 0.0, -5.0), &[]).is_ok());
+        assert_eq!(
+            vec![Issue::CameraInsideGeometry(0)],
+            scene.validate(Point::ORIGIN, &[]).issues
+        );
+
+        // Under a lone triangle isn't inside anything
+        let mut scene = Scene::default();
+        scene.add_primitive(
+            Triangle::new([-1.0, 1.0, -1.0], [1.0, 1.0, -1.0], [0.0, 1.0, 1.0]),
+            grey(),
+        );
+        assert!(scene.validate(Point::ORIGIN, &[]).is_ok());
     }
 }
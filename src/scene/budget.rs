@@ -0,0 +1,155 @@
+use std::{error::Error, fmt};
+
+/// A scene allocation was refused because it would exceed the
+/// [`MemoryBudget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// What was being allocated.
+    pub what: &'static str,
+    /// Estimated size of the allocation, in bytes.
+    pub requested: usize,
+    /// Bytes left in the budget at the time.
+    pub remaining: usize,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} needs {} bytes but only {} remain in the memory budget",
+            self.what, self.requested, self.remaining
+        )
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+/// Tracks estimated memory use while loading a scene.
+///
+/// Loaders [`reserve`] the estimated size of each mesh, texture or
+/// acceleration structure before keeping it. When a reservation would exceed
+/// the limit, loading fails with a [`BudgetExceeded`] error naming the
+/// culprit, rather than the process being killed halfway through a build.
+/// Textures can instead be [`fit`] into the remaining budget at a lower
+/// resolution.
+///
+/// Estimates cover the big allocations only, so the real footprint will be
+/// somewhat higher; leave some headroom.
+///
+/// [`reserve`]: Self::reserve
+/// [`fit`]: Self::fit_image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: usize,
+}
+
+impl MemoryBudget {
+    /// A budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    /// A budget that never runs out, but still tracks usage.
+    pub fn unlimited() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    /// The budget, in bytes.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Bytes reserved so far.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Bytes left to reserve.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.limit - self.used
+    }
+
+    /// Reserve `bytes` for the named allocation, or fail if they don't fit.
+    pub fn reserve(&mut self, what: &'static str, bytes: usize) -> Result<(), BudgetExceeded> {
+        if bytes > self.remaining() {
+            return Err(BudgetExceeded {
+                what,
+                requested: bytes,
+                remaining: self.remaining(),
+            });
+        }
+        self.used += bytes;
+        Ok(())
+    }
+
+    /// Return a previous reservation to the budget.
+    pub fn release(&mut self, bytes: usize) {
+        self.used = self.used.saturating_sub(bytes);
+    }
+
+    /// Reserve memory for an image, halving its resolution until it fits.
+    ///
+    /// Returns the resolution to load the image at, which is the full
+    /// resolution if the budget allows. Fails only if even a 1x1 image
+    /// doesn't fit.
+    pub fn fit_image(
+        &mut self,
+        what: &'static str,
+        (width, height): (u32, u32),
+        bytes_per_pixel: usize,
+    ) -> Result<(u32, u32), BudgetExceeded> {
+        let (mut w, mut h) = (width.max(1), height.max(1));
+        loop {
+            let bytes = (w as usize)
+                .saturating_mul(h as usize)
+                .saturating_mul(bytes_per_pixel);
+            match self.reserve(what, bytes) {
+                Ok(()) => return Ok((w, h)),
+                Err(e) if w == 1 && h == 1 => return Err(e),
+                Err(_) => (w, h) = ((w / 2).max(1), (h / 2).max(1)),
+            }
+        }
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_and_release() {
+        let mut budget = MemoryBudget::new(100);
+        budget.reserve("mesh", 60).unwrap();
+        assert_eq!(
+            Err(BudgetExceeded {
+                what: "texture",
+                requested: 50,
+                remaining: 40
+            }),
+            budget.reserve("texture", 50)
+        );
+        assert_eq!(60, budget.used());
+        budget.release(60);
+        budget.reserve("texture", 50).unwrap();
+    }
+
+    #[test]
+    fn fit_image() {
+        let mut budget = MemoryBudget::new(1000);
+        // 64x64 RGB8 is 12 KiB; 16x16 is the first level under 1000 bytes
+        assert_eq!(Ok((16, 16)), budget.fit_image("albedo", (64, 64), 3));
+        assert_eq!(768, budget.used());
+        assert_eq!(Ok((16, 8)), budget.fit_image("roughness", (32, 16), 1));
+        assert!(budget.fit_image("normal", (4, 4), 1000).is_err());
+    }
+}
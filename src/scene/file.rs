@@ -0,0 +1,514 @@
+use super::{BudgetExceeded, Issue, MemoryBudget, Scene, ValidationReport};
+use crate::{
+    camera::{ThinLens, ThinLensBuilder},
+    color::RGB,
+    geo::{Degrees, Point, Unit, Vector},
+    light::{Environment, Sky, SpotLight},
+    material::{Bump, Dielectric, Lambertian, Material, Plastic},
+    shape::{ClipPlane, Sphere, Surface, Triangle, VoxLoadError, VoxModel},
+    texture::{ExprColor, ExprError},
+    Float,
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io, mem,
+    path::{Path, PathBuf},
+};
+
+/// Errors that can occur while loading a scene file.
+#[derive(Debug)]
+pub enum SceneFileError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file couldn't be parsed. Line numbers start at `1`.
+    Parse { line: usize, msg: &'static str },
+    /// A `.vox` model referenced by the scene couldn't be loaded.
+    Vox { line: usize, err: VoxLoadError },
+    /// Loading the scene would exceed its [`MemoryBudget`].
+    Budget { line: usize, err: BudgetExceeded },
+    /// A texture expression couldn't be parsed.
+    Expr { line: usize, err: ExprError },
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read scene file: {}", e),
+            Self::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+            Self::Vox { line, err } => write!(f, "line {}: {}", line, err),
+            Self::Budget { line, err } => write!(f, "line {}: {}", line, err),
+            Self::Expr { line, err } => write!(f, "line {}: {}", line, err),
+        }
+    }
+}
+
+impl Error for SceneFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Vox { err, .. } => Some(err),
+            Self::Budget { err, .. } => Some(err),
+            Self::Expr { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneFileError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A scene, camera and background loaded from a text scene file.
+///
+/// Scene files are line-based. Each line is a directive followed by
+/// whitespace-separated arguments; blank lines and `#` comments are ignored.
+///
+/// ```text
+/// # directive                  arguments
+/// camera                       ex ey ez  tx ty tz  fov  [aperture]
+/// background                   r g b
+/// sky                          sx sy sz  [turbidity]
+/// material lambertian          r g b
+/// material plastic             r g b  ior
+/// material glass               ior  [r g b]
+/// sphere                       cx cy cz  radius
+/// triangle                     x y z  x y z  x y z
+/// vox                          path  [ox oy oz  [voxel_size]]
+/// texture                      name  expr [, expr, expr]
+/// normalmap                    name
+/// clip                         px py pz  nx ny nz  [cap]
+/// ```
+///
+/// Primitives use the most recent `material` (grey diffuse until the first
+/// one). `vox` paths are relative to the scene file, and the whole model uses
+/// the current material rather than its palette.
+///
+/// `background` and `sky` set what rays that miss everything see: a plain
+/// color, or a daylight [`Sky`] with the sun in direction `s` and a
+/// turbidity of `3` unless given.
+///
+/// `glass` is a [`Dielectric`] tinted by the optional color, which also
+/// colors the shadows it casts (see [`Scene::transmittance`]).
+///
+/// `texture` defines a named [`ExprColor`] from the rest of the line. The
+/// built-in materials are plain colors, so textures are collected in
+/// [`textures`] for the caller to use, or used as a normal map.
+///
+/// `normalmap` [`Bump`]s the current material, until the next `material`,
+/// with the named texture as a tangent-space normal map. The texture has to
+/// be defined before the primitives using it; those that use a texture that
+/// isn't are left unbumped and reported by [`validate`].
+///
+/// `clip` adds a [`ClipPlane`] through `p`, cutting away everything on the
+/// side its normal `n` points to, and capping cut solids if `cap` is given.
+///
+/// [`textures`]: Self::textures
+/// [`validate`]: Self::validate
+pub struct SceneFile {
+    pub scene: Scene,
+    /// Camera position.
+    pub eye: Point,
+    /// Point the camera looks at, which is also kept in focus.
+    pub target: Point,
+    /// Field of view, in degrees.
+    pub fov: Float,
+    pub aperture: Float,
+    pub background: Environment,
+    /// Named procedural textures.
+    pub textures: HashMap<String, ExprColor>,
+    // Primitives whose normal map names an undefined texture
+    missing_textures: Vec<usize>,
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        Self {
+            scene: Scene::default(),
+            eye: Point::new(0.0, 0.0, -1.0),
+            target: Point::ORIGIN,
+            fov: 90.0,
+            aperture: 0.0,
+            background: Environment::default(),
+            textures: HashMap::new(),
+            missing_textures: Vec::new(),
+        }
+    }
+}
+
+impl SceneFile {
+    /// Load a scene file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneFileError> {
+        Self::load_with_budget(path, &mut MemoryBudget::unlimited())
+    }
+
+    /// Load a scene file, failing if its primitives and models would exceed
+    /// the given memory budget.
+    pub fn load_with_budget(
+        path: impl AsRef<Path>,
+        budget: &mut MemoryBudget,
+    ) -> Result<Self, SceneFileError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::parse_with_budget(&fs::read_to_string(path)?, dir, budget)
+    }
+
+    /// Parse a scene file's contents. Relative `vox` paths are resolved
+    /// against `dir`.
+    pub fn parse(data: &str, dir: impl AsRef<Path>) -> Result<Self, SceneFileError> {
+        Self::parse_with_budget(data, dir, &mut MemoryBudget::unlimited())
+    }
+
+    /// Parse a scene file's contents within a memory budget. See
+    /// [`load_with_budget`].
+    ///
+    /// Each primitive is accounted for before it's added. Voxel models are
+    /// accounted for once loaded, since their size isn't known up front;
+    /// the `.vox` format caps them at 256³ voxels.
+    ///
+    /// [`load_with_budget`]: Self::load_with_budget
+    pub fn parse_with_budget(
+        data: &str,
+        dir: impl AsRef<Path>,
+        budget: &mut MemoryBudget,
+    ) -> Result<Self, SceneFileError> {
+        const PRIMITIVE_SIZE: usize = mem::size_of::<Surface>() + mem::size_of::<Material>();
+
+        let mut file = Self::default();
+        let mut current = MaterialDesc::Lambertian(RGB::from([0.5, 0.5, 0.5]));
+        let mut normal_map = None;
+
+        for (idx, line) in data.lines().enumerate() {
+            let line_no = idx + 1;
+            let err = |msg| SceneFileError::Parse { line: line_no, msg };
+            let mut reserve = |what, bytes| {
+                budget
+                    .reserve(what, bytes)
+                    .map_err(|err| SceneFileError::Budget { line: line_no, err })
+            };
+            let line = line.split('#').next().unwrap_or("");
+            let mut words = line.split_whitespace();
+            let Some(directive) = words.next() else {
+                continue;
+            };
+
+            match directive {
+                "material" => {
+                    let kind = words.next().ok_or_else(|| err("missing material type"))?;
+                    let args = numbers(words).ok_or_else(|| err("expected a number"))?;
+                    current = match (kind, args.as_slice()) {
+                        ("lambertian", &[r, g, b]) => {
+                            MaterialDesc::Lambertian(RGB::from([r, g, b]))
+                        }
+                        ("plastic", &[r, g, b, ior]) => {
+                            MaterialDesc::Plastic(RGB::from([r, g, b]), ior)
+                        }
+                        ("glass", &[ior]) => MaterialDesc::Glass(ior, RGB::splat(1.0)),
+                        ("glass", &[ior, r, g, b]) => {
+                            MaterialDesc::Glass(ior, RGB::from([r, g, b]))
+                        }
+                        ("lambertian" | "plastic" | "glass", _) => {
+                            return Err(err("wrong number of arguments"))
+                        }
+                        _ => return Err(err("unknown material type")),
+                    };
+                    normal_map = None;
+                }
+                "normalmap" => {
+                    let name = words.next().ok_or_else(|| err("missing texture name"))?;
+                    if words.next().is_some() {
+                        return Err(err("wrong number of arguments"));
+                    }
+                    normal_map = Some(name);
+                }
+                "texture" => {
+                    let name = words.next().ok_or_else(|| err("missing texture name"))?;
+                    let src =
+                        line.trim_start()[directive.len()..].trim_start()[name.len()..].trim();
+                    let tex = ExprColor::parse(src)
+                        .map_err(|err| SceneFileError::Expr { line: line_no, err })?;
+                    file.textures.insert(name.to_string(), tex);
+                }
+                "clip" => {
+                    let mut args: Vec<&str> = words.collect();
+                    let cap = args.last() == Some(&"cap");
+                    if cap {
+                        args.pop();
+                    }
+                    let args = numbers(args.into_iter()).ok_or_else(|| err("expected a number"))?;
+                    let &[px, py, pz, nx, ny, nz] = args.as_slice() else {
+                        return Err(err("wrong number of arguments"));
+                    };
+                    let normal = Unit::try_from(Vector::new(nx, ny, nz))
+                        .map_err(|_| err("clip plane normal must be non-zero"))?;
+                    let plane = ClipPlane::new([px, py, pz], normal).capped(cap);
+                    file.scene.add_clip_plane(plane);
+                }
+                "vox" => {
+                    let rel = words.next().ok_or_else(|| err("missing vox path"))?;
+                    let args = numbers(words).ok_or_else(|| err("expected a number"))?;
+                    let (origin, size) = match *args.as_slice() {
+                        [] => (Point::ORIGIN, 1.0),
+                        [x, y, z] => (Point::new(x, y, z), 1.0),
+                        [x, y, z, s] => (Point::new(x, y, z), s),
+                        _ => return Err(err("wrong number of arguments")),
+                    };
+                    let path: PathBuf = dir.as_ref().join(rel);
+                    let model = VoxModel::load(path)
+                        .map_err(|err| SceneFileError::Vox { line: line_no, err })?;
+                    let svo = model.octree.with_placement(origin, size);
+                    reserve("voxel model", svo.memory_size() + PRIMITIVE_SIZE)?;
+                    let material = file.material(current, normal_map);
+                    file.scene.add_primitive(svo, material);
+                }
+                _ => {
+                    let args = numbers(words).ok_or_else(|| err("expected a number"))?;
+                    match (directive, args.as_slice()) {
+                        ("camera", &[ex, ey, ez, tx, ty, tz, fov, ref rest @ ..])
+                            if rest.len() <= 1 =>
+                        {
+                            let (eye, target) = (Point::new(ex, ey, ez), Point::new(tx, ty, tz));
+                            if eye == target {
+                                return Err(err("camera can't look at its own position"));
+                            }
+                            if Unit::try_from(Vector::Y_AXIS.cross(target - eye)).is_err() {
+                                return Err(err("camera can't look straight up or down"));
+                            }
+                            file.eye = eye;
+                            file.target = target;
+                            file.fov = fov;
+                            file.aperture = rest.first().copied().unwrap_or(0.0);
+                        }
+                        ("background", &[r, g, b]) => file.background = RGB::from([r, g, b]).into(),
+                        ("sky", &[x, y, z, ref rest @ ..]) if rest.len() <= 1 => {
+                            let sun = Unit::try_from(Vector::new(x, y, z))
+                                .map_err(|_| err("sun direction must be non-zero"))?;
+                            let turbidity = rest.first().copied().unwrap_or(3.0);
+                            if turbidity.is_nan() || turbidity < 1.0 {
+                                return Err(err("turbidity must be at least 1"));
+                            }
+                            file.background = Sky::new(sun, turbidity).into();
+                        }
+                        ("sphere", &[x, y, z, r]) => {
+                            if !r.is_normal() || r < 0.0 {
+                                return Err(err("sphere radius must be positive"));
+                            }
+                            reserve("sphere", PRIMITIVE_SIZE)?;
+                            let material = file.material(current, normal_map);
+                            file.scene
+                                .add_primitive(Sphere::new([x, y, z], r), material);
+                        }
+                        ("triangle", &[ax, ay, az, bx, by, bz, cx, cy, cz]) => {
+                            let tri = Triangle::new([ax, ay, az], [bx, by, bz], [cx, cy, cz]);
+                            reserve("triangle", PRIMITIVE_SIZE)?;
+                            let material = file.material(current, normal_map);
+                            file.scene.add_primitive(tri, material);
+                        }
+                        ("camera" | "background" | "sky" | "sphere" | "triangle", _) => {
+                            return Err(err("wrong number of arguments"))
+                        }
+                        _ => return Err(err("unknown directive")),
+                    }
+                }
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Check the scene, the file's camera and the `lights` that will
+    /// illuminate it for problems that would spoil a render, as
+    /// [`Scene::validate`] does, and for normal maps naming undefined
+    /// textures.
+    pub fn validate(&self, lights: &[SpotLight]) -> ValidationReport {
+        let mut report = self.scene.validate(self.eye, lights);
+        report.issues.extend(
+            self.missing_textures
+                .iter()
+                .map(|&idx| Issue::MissingTexture(idx)),
+        );
+        report
+    }
+
+    // The material for the next primitive, bumped by its normal map
+    fn material(&mut self, desc: MaterialDesc, normal_map: Option<&str>) -> Material {
+        let material = desc.build();
+        let Some(name) = normal_map else {
+            return material;
+        };
+        match self.textures.get(name) {
+            Some(tex) => Bump::normal_map(material, tex.clone()).into(),
+            None => {
+                self.missing_textures.push(self.scene.surfaces().len());
+                material
+            }
+        }
+    }
+
+    /// A camera builder for the given resolution, placed as described by the
+    /// scene file.
+    pub fn camera_builder(&self, dimensions: (u32, u32)) -> ThinLensBuilder {
+        let mut builder = ThinLens::builder(dimensions);
+        builder
+            .move_to(self.eye)
+            .look_at(self.target)
+            .fov(Degrees(self.fov))
+            .aperture(self.aperture)
+            .auto_focus();
+        builder
+    }
+}
+
+// Materials aren't `Clone`, so the current material is kept as a description
+// and built anew for each primitive.
+#[derive(Debug, Clone, Copy)]
+enum MaterialDesc {
+    Glass(Float, RGB),
+    Lambertian(RGB),
+    Plastic(RGB, Float),
+}
+
+impl MaterialDesc {
+    fn build(self) -> Material {
+        match self {
+            Self::Glass(ior, tint) => Dielectric::new(ior).tint(tint).into(),
+            Self::Lambertian(rgb) => Lambertian::new(rgb).into(),
+            Self::Plastic(rgb, ior) => Plastic::new(Lambertian::new(rgb), ior).into(),
+        }
+    }
+}
+
+fn numbers<'a>(words: impl Iterator<Item = &'a str>) -> Option<Vec<Float>> {
+    words.map(|w| w.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::Camera, shape::Surface};
+
+    #[test]
+    fn parse_scene() {
+        let data = "
+            # Two spheres on a ground triangle
+            camera 0 1 -5  0 0 0  40 0.1
+            background 0.5 0.7 1.0
+
+            sphere 0 0 0 1          # grey by default
+            material plastic 0.8 0.1 0.1 1.5
+            sphere 2 0 0 0.5
+            triangle -10 -1 -10  10 -1 -10  0 -1 10
+            material glass 1.5  0.2 0.9 0.2
+            sphere -2 0 0 0.5
+            texture marble  0.5 + 0.5 * sin(x + z), 0.5, 0.5
+            clip 0 0.5 0  0 2 0  cap
+        ";
+        let file = SceneFile::parse(data, "").unwrap();
+        assert_eq!(Point::new(0.0, 1.0, -5.0), file.eye);
+        assert_eq!(40.0, file.fov);
+        assert_eq!(0.1, file.aperture);
+        assert_eq!(
+            Environment::Constant(RGB::from([0.5, 0.7, 1.0])),
+            file.background
+        );
+
+        let scene = &file.scene;
+        assert_eq!(4, scene.surfaces().len());
+        assert!(matches!(scene.surfaces()[2], Surface::Triangle(_)));
+        assert!(matches!(scene.materials()[0], Material::Lambertian(_)));
+        assert!(matches!(scene.materials()[1], Material::Plastic(_)));
+        assert!(matches!(scene.materials()[2], Material::Plastic(_)));
+        assert!(matches!(scene.materials()[3], Material::Dielectric(_)));
+        assert!(file.textures.contains_key("marble"));
+
+        let planes = scene.clip().planes();
+        assert_eq!(1, planes.len());
+        assert_eq!(Unit::Y_AXIS, planes[0].normal());
+        assert!(planes[0].is_capped());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let line = |data| match SceneFile::parse(data, "") {
+            Err(SceneFileError::Parse { line, .. }) => line,
+            _ => 0,
+        };
+        assert_eq!(2, line("sphere 0 0 0 1\nsphere 0 0 0"));
+        assert_eq!(1, line("sphere 0 0 zero 1"));
+        assert_eq!(3, line("\n\ncube 1"));
+        assert_eq!(1, line("material glass 1 1 1"));
+        assert_eq!(1, line("clip 0 0 0  0 0 0"));
+        assert_eq!(1, line("sky 0 0 0"));
+        assert_eq!(1, line("sky 0 1 0  0.5"));
+        assert_eq!(1, line("sphere 0 0 0 -1"));
+        assert_eq!(2, line("\nsphere 0 0 0 0"));
+        assert_eq!(1, line("normalmap"));
+        assert_eq!(1, line("camera 0 0 0  0 0 0  40"));
+        assert_eq!(1, line("camera 0 5 0  0 0 0  40"));
+
+        // Placed at the builder's default target, which used to panic
+        let file = SceneFile::parse("camera 0 0 0  0 0 5  40", "").unwrap();
+        let cam = file.camera_builder((4, 4)).build();
+        let ray = cam.ray(2, 2, &mut rand::thread_rng());
+        assert!(ray.direction().z > 0.9 * ray.direction().len());
+        assert!(matches!(
+            SceneFile::parse("sky 1 1 0", "").unwrap().background,
+            Environment::Sky(_)
+        ));
+        assert!(matches!(
+            SceneFile::parse("\ntexture bad 1 +", ""),
+            Err(SceneFileError::Expr { line: 2, .. })
+        ));
+        assert!(matches!(
+            SceneFile::parse("vox missing.vox", "/nonexistent"),
+            Err(SceneFileError::Vox { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn validate() {
+        let data = "
+            camera 0 0 -5  0 0 0  40
+            texture flat  0.5, 0.5, 1
+            normalmap flat
+            sphere 0 0 0 1
+            normalmap bumpy
+            sphere 3 0 0 1
+            material lambertian 0.5 0.5 0.5
+            sphere -3 0 0 1
+        ";
+        let file = SceneFile::parse(data, "").unwrap();
+        assert!(matches!(file.scene.materials()[0], Material::Bump(_)));
+        assert!(matches!(file.scene.materials()[1], Material::Lambertian(_)));
+        assert_eq!(vec![Issue::MissingTexture(1)], file.validate(&[]).issues);
+
+        let inside = SceneFile::parse(
+            "camera 0 0 0  0 0 1  40
+sphere 0 0 0 1",
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![Issue::CameraInsideGeometry(0)],
+            inside.validate(&[]).issues
+        );
+    }
+
+    #[test]
+    fn memory_budget() {
+        let data = "sphere 0 0 0 1\nsphere 0 0 0 1\nsphere 0 0 0 1";
+        let primitive = mem::size_of::<Surface>() + mem::size_of::<Material>();
+
+        let mut budget = MemoryBudget::new(3 * primitive);
+        assert!(SceneFile::parse_with_budget(data, "", &mut budget).is_ok());
+        assert_eq!(0, budget.remaining());
+
+        let mut budget = MemoryBudget::new(2 * primitive + 1);
+        assert!(matches!(
+            SceneFile::parse_with_budget(data, "", &mut budget),
+            Err(SceneFileError::Budget { line: 3, .. })
+        ));
+    }
+}
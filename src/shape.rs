@@ -14,7 +14,7 @@
 //! Naming things is hard, especially when it comes to
 
 use crate::{
-    geo::{Point, Ray, Unit},
+    geo::{Bounds, Point, Ray, Unit, Vector},
     Float,
 };
 
@@ -23,6 +23,39 @@ use crate::{
 mod aggregate;
 pub use aggregate::*;
 
+mod alpha;
+pub use alpha::*;
+
+mod bvh;
+pub use bvh::*;
+
+mod check;
+pub use check::*;
+
+mod clip;
+pub use clip::*;
+
+mod curve;
+pub use curve::*;
+
+mod grid;
+pub use grid::*;
+
+mod hair;
+pub use hair::*;
+
+mod heightfield;
+pub use heightfield::*;
+
+mod mesh;
+pub use mesh::*;
+
+mod obj;
+pub use obj::*;
+
+mod sided;
+pub use sided::*;
+
 mod sphere;
 pub use sphere::*;
 
@@ -32,6 +65,12 @@ pub use surface::*;
 mod triangle;
 pub use triangle::*;
 
+mod vox;
+pub use vox::*;
+
+mod voxel;
+pub use voxel::*;
+
 // CORE DEFINITIONS
 
 /// Encapsulates all information related to a ray-object intersection.
@@ -42,6 +81,28 @@ pub struct Intersection {
     pub t: Float,
 }
 
+impl Intersection {
+    /// The same intersection with the normal reversed.
+    #[inline]
+    pub fn flipped(self) -> Self {
+        Self {
+            norm: -self.norm,
+            ..self
+        }
+    }
+
+    /// The same intersection with the normal on the side facing back along
+    /// `direction`, _i.e._ towards where a ray travelling in `direction` came
+    /// from.
+    #[inline]
+    pub fn facing(self, direction: Vector) -> Self {
+        match Vector::from(self.norm).dot(direction) > 0.0 {
+            true => self.flipped(),
+            false => self,
+        }
+    }
+}
+
 /// The core trait defining ray-object intersection.
 ///
 /// This trait encapsulates the main functionality needed for efficient
@@ -69,4 +130,41 @@ pub trait Shape {
     fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
         self.intersect(ray, t_min, t_max).is_some()
     }
+
+    /// Ray intersection test for many rays at once.
+    ///
+    /// Writes the [`Intersection`] record (or `None`) for each ray into the
+    /// corresponding slot of `hits`, exactly as [`intersect`] would.
+    ///
+    /// By default, this just loops over the rays. Rays that travel together,
+    /// such as primary rays from neighbouring pixels, tend to visit the same
+    /// parts of an aggregate, so aggregates like [`Bvh`] override this to
+    /// traverse once for the whole packet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rays` and `hits` have different lengths.
+    ///
+    /// [`intersect`]: Self::intersect
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        assert_eq!(rays.len(), hits.len(), "Need one hit slot per ray");
+        for (ray, hit) in rays.iter().zip(hits) {
+            *hit = self.intersect(ray, t_min, t_max);
+        }
+    }
+}
+
+/// Shapes with a finite extent.
+///
+/// Acceleration structures such as [`Bvh`] sort shapes by their bounds, and
+/// skip over whole groups of them when a ray misses the group's bounds.
+pub trait Bounded {
+    /// An axis-aligned box enclosing the shape.
+    fn bounds(&self) -> Bounds;
 }
@@ -4,6 +4,25 @@ use crate::{geo::Ray, Float};
 pub type DirectAggregate<S> = Vec<S>;
 
 impl<S: Shape> Shape for DirectAggregate<S> {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.as_slice().intersect(ray, t_min, t_max)
+    }
+
+    #[inline]
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        self.as_slice().intersect_packet(rays, t_min, t_max, hits)
+    }
+}
+
+/// Brute force: every shape is tested against every ray.
+impl<S: Shape> Shape for [S] {
     fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
         self.iter().fold(None, |curr, next| {
             let next = next.intersect(ray, t_min, t_max);
@@ -20,6 +39,26 @@ impl<S: Shape> Shape for DirectAggregate<S> {
             }
         })
     }
+
+    // Shapes in the outer loop, so each one stays in cache for every ray
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        assert_eq!(rays.len(), hits.len(), "Need one hit slot per ray");
+        hits.fill(None);
+        for shape in self {
+            for (ray, hit) in rays.iter().zip(hits.iter_mut()) {
+                let t_max = hit.map_or(t_max, |h| h.t);
+                if let Some(isect) = shape.intersect(ray, t_min, t_max) {
+                    *hit = Some(isect);
+                }
+            }
+        }
+    }
 }
 
 pub type DynamicAggregate = Vec<Box<dyn Shape>>;
@@ -0,0 +1,126 @@
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, Ray},
+    texture::{Texture, TextureContext},
+    Float,
+};
+
+/// A shape with holes cut out of it by a texture, such as a leaf or a chain
+/// link fence drawn on a single quad.
+///
+/// Hits where the mask is below the cutoff are transparent: the ray carries
+/// on as though the surface wasn't there, possibly hitting the same shape
+/// again farther along. The wrapper does this itself, by intersecting again
+/// from just past each transparent hit, so masked shapes work anywhere a
+/// shape does, including as the leaves of a [`Bvh`], which only ever sees
+/// their opaque hits. Shadow rays see the holes too.
+///
+/// The mask is evaluated with the hit's position and normal, so use a
+/// world-space texture, or project an image onto the shape with a
+/// [`Triplanar`].
+///
+/// [`Bvh`]: super::Bvh
+/// [`Triplanar`]: crate::texture::Triplanar
+pub struct AlphaMasked<S, T> {
+    pub shape: S,
+    pub mask: T,
+    cutoff: Float,
+}
+
+impl<S, T> AlphaMasked<S, T> {
+    /// Cut holes in `shape` where `mask` is below one half.
+    pub fn new(shape: S, mask: T) -> Self {
+        Self {
+            shape,
+            mask,
+            cutoff: 0.5,
+        }
+    }
+
+    /// Set the mask value below which the shape is transparent. Defaults to
+    /// `0.5`.
+    pub fn cutoff(mut self, cutoff: Float) -> Self {
+        self.cutoff = cutoff;
+        self
+    }
+}
+
+impl<S: Shape, T: Texture<Float>> AlphaMasked<S, T> {
+    #[inline]
+    fn is_opaque(&self, isect: &Intersection) -> bool {
+        self.mask.evaluate(&TextureContext::from(isect)) >= self.cutoff
+    }
+}
+
+impl<S: Shape, T: Texture<Float>> Shape for AlphaMasked<S, T> {
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        let mut t_min = t_min;
+        loop {
+            let isect = self.shape.intersect(ray, t_min, t_max)?;
+            if self.is_opaque(&isect) {
+                return Some(isect);
+            }
+            t_min = past(isect.t);
+        }
+    }
+}
+
+impl<S: Bounded, T> Bounded for AlphaMasked<S, T> {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.shape.bounds()
+    }
+}
+
+// The next `t` after a transparent hit. Far enough along not to hit the same
+// point again, and strictly increasing, so the search always ends.
+#[inline]
+fn past(t: Float) -> Float {
+    t + (t.abs() * 4.0 * Float::EPSILON).max(Float::MIN_POSITIVE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::{Point, Vector},
+        shape::{Bvh, Sphere, Triangle},
+        texture::Expr,
+    };
+
+    #[test]
+    fn holes() {
+        // Stripes along `x`, transparent where `x` is in [0.5, 1) mod 1
+        let stripes: Expr = "1 - step(0.5, fract(x))".parse().unwrap();
+        let quad = |z: Float| {
+            let tri = Triangle::new([-4.0, -4.0, z], [4.0, -4.0, z], [0.0, 4.0, z]);
+            AlphaMasked::new(tri, stripes.clone())
+        };
+        let ray = |x| Ray::new(Point::new(x, 0.0, -5.0), Vector::Z_AXIS);
+
+        let card = quad(0.0);
+        assert_eq!(
+            5.0,
+            card.intersect(&ray(0.25), 0.0, Float::INFINITY).unwrap().t
+        );
+        assert!(card.intersect(&ray(0.75), 0.0, Float::INFINITY).is_none());
+        assert!(!card.intersects(&ray(0.75), 0.0, Float::INFINITY));
+        assert!(card
+            .cutoff(0.0)
+            .intersects(&ray(0.75), 0.0, Float::INFINITY));
+
+        // In a hierarchy, rays pass through the holes to the cards behind
+        let bvh = Bvh::new(vec![quad(0.0), quad(1.0)]);
+        let stack = |x| bvh.intersect(&ray(x), 0.0, Float::INFINITY).map(|i| i.t);
+        assert_eq!(Some(5.0), stack(0.25));
+        assert_eq!(None, stack(0.75));
+
+        // A masked sphere shows its back through holes in its front
+        let front_hole: Expr = "step(0, z)".parse().unwrap();
+        let shell = AlphaMasked::new(Sphere::new([0.0, 0.0, 0.0], 1.0), front_hole);
+        assert_eq!(
+            6.0,
+            shell.intersect(&ray(0.0), 0.0, Float::INFINITY).unwrap().t
+        );
+    }
+}
@@ -0,0 +1,802 @@
+use super::{Bounded, Intersection, Shape, Triangle, Triangle4};
+use crate::{
+    geo::{Bounds, Bounds4, Point, Ray, SlabRay},
+    Float,
+};
+use std::fmt;
+
+/// The most shapes in a leaf. Matches the width of [`Triangle4`], so a leaf
+/// of triangles is a single packet.
+const LEAF_SIZE: usize = 4;
+
+/// Traversal stack depth. Each level of the tree pushes at most three more
+/// entries than it pops, and median splits keep the depth logarithmic, so
+/// this covers far more shapes than fit in memory.
+const STACK_SIZE: usize = 64;
+
+/// The most rays traversed together, so a packet's active rays fit in a
+/// `u64` mask. Longer slices are split into packets of this size.
+const PACKET_SIZE: usize = 64;
+
+/// A 4-wide bounding volume hierarchy.
+///
+/// Each node holds the bounds of up to four children side by side, so a
+/// ray is tested against all of them at once, and children are then visited
+/// nearest first. Leaves hold up to four shapes. Building splits each group
+/// of shapes at the median centroid along its longest axis, twice per node.
+///
+/// The shapes keep the order they're given in, so the indices returned by
+/// [`intersect_indexed`] match the input (_e.g._ for looking up materials).
+/// For triangles, [`TriangleBvh`] also tests each leaf as a single packet.
+///
+/// [`intersect_indexed`]: Self::intersect_indexed
+#[derive(Debug)]
+pub struct Bvh<S> {
+    shapes: Vec<S>,
+    // Shape indices, grouped so each leaf is a contiguous range
+    indices: Vec<u32>,
+    // (start, len) ranges into `indices`
+    leaves: Vec<(u32, u32)>,
+    nodes: Vec<Node>,
+    bounds: Bounds,
+}
+
+#[derive(Debug)]
+struct Node {
+    bounds: Bounds4,
+    children: [Child; 4],
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Child {
+    Empty,
+    Node(u32),
+    Leaf(u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Prim {
+    index: u32,
+    bounds: Bounds,
+    centroid: Point,
+}
+
+impl<S: Bounded> Bvh<S> {
+    /// Build a hierarchy over the given shapes.
+    pub fn new(shapes: Vec<S>) -> Self {
+        let mut prims: Vec<Prim> = shapes
+            .iter()
+            .enumerate()
+            .map(|(index, shape)| {
+                let bounds = shape.bounds();
+                Prim {
+                    index: index as u32,
+                    bounds,
+                    centroid: bounds.centroid(),
+                }
+            })
+            .collect();
+
+        let mut bvh = Self {
+            indices: Vec::with_capacity(shapes.len()),
+            leaves: Vec::new(),
+            nodes: Vec::new(),
+            bounds: union(&prims),
+            shapes,
+        };
+        if !prims.is_empty() {
+            bvh.build(&mut prims);
+        }
+        bvh
+    }
+
+    fn build(&mut self, prims: &mut [Prim]) -> u32 {
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: Bounds4::new(&[]),
+            children: [Child::Empty; 4],
+        });
+
+        let mut groups = Vec::with_capacity(4);
+        if prims.len() <= LEAF_SIZE {
+            groups.push(prims);
+        } else {
+            let (left, right) = partition(prims);
+            for half in [left, right] {
+                if half.len() > LEAF_SIZE {
+                    let (a, b) = partition(half);
+                    groups.extend([a, b]);
+                } else {
+                    groups.push(half);
+                }
+            }
+        }
+
+        let bounds: Vec<Bounds> = groups.iter().map(|g| union(g)).collect();
+        let mut children = [Child::Empty; 4];
+        for (child, group) in children.iter_mut().zip(groups) {
+            *child = match group.len() <= LEAF_SIZE {
+                true => {
+                    let start = self.indices.len() as u32;
+                    self.indices.extend(group.iter().map(|p| p.index));
+                    self.leaves.push((start, group.len() as u32));
+                    Child::Leaf(self.leaves.len() as u32 - 1)
+                }
+                false => Child::Node(self.build(group)),
+            };
+        }
+        self.nodes[idx] = Node {
+            bounds: Bounds4::new(&bounds),
+            children,
+        };
+        idx as u32
+    }
+
+    /// Measure the quality of the hierarchy. See [`BvhStats`].
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            shapes: self.shapes.len(),
+            nodes: self.nodes.len(),
+            leaves: self.leaves.len(),
+            ..Default::default()
+        };
+        let root_area = self.bounds.surface_area();
+        if self.nodes.is_empty() || root_area <= 0.0 {
+            return stats;
+        }
+        let mut overlap = 0.0;
+        self.gather(Child::Node(0), 1, root_area, &mut stats, &mut overlap);
+        stats.overlap = overlap / stats.nodes as Float;
+        stats
+    }
+
+    // Add the cost of the subtree under `child` to `stats`, and return its
+    // bounds
+    fn gather(
+        &self,
+        child: Child,
+        depth: usize,
+        root_area: Float,
+        stats: &mut BvhStats,
+        overlap: &mut Float,
+    ) -> Bounds {
+        match child {
+            Child::Empty => Bounds::EMPTY,
+            Child::Leaf(leaf) => {
+                let shapes = self.leaf(leaf);
+                let bounds = shapes.iter().fold(Bounds::EMPTY, |acc, &i| {
+                    acc.union(&self.shapes[i as usize].bounds())
+                });
+                stats.sah_cost += bounds.surface_area() / root_area * shapes.len() as Float;
+                stats.max_depth = stats.max_depth.max(depth);
+                bounds
+            }
+            Child::Node(idx) => {
+                let children = self.nodes[idx as usize].children;
+                let bounds = children.map(|c| self.gather(c, depth + 1, root_area, stats, overlap));
+                let node = bounds.iter().fold(Bounds::EMPTY, |acc, b| acc.union(b));
+                let area = node.surface_area();
+                stats.sah_cost += area / root_area;
+                if area > 0.0 {
+                    let mut shared = 0.0;
+                    for i in 0..4 {
+                        for j in i + 1..4 {
+                            shared += overlap_area(&bounds[i], &bounds[j]);
+                        }
+                    }
+                    *overlap += shared / area;
+                }
+                node
+            }
+        }
+    }
+}
+
+/// Quality measures of a [`Bvh`], for comparing how it was built.
+///
+/// Costs use the surface area heuristic (SAH): the chance a random ray
+/// through the whole hierarchy also passes through a node is the ratio of
+/// their surface areas. Visiting a node and testing a shape count as one
+/// unit each, so lower is better, and a perfect hierarchy costs about the
+/// depth plus the leaf size.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BvhStats {
+    /// The number of shapes.
+    pub shapes: usize,
+    /// The number of interior nodes.
+    pub nodes: usize,
+    /// The number of leaves.
+    pub leaves: usize,
+    /// The most nodes on a path from the root to a leaf.
+    pub max_depth: usize,
+    /// The expected cost of tracing a ray through the hierarchy.
+    pub sah_cost: Float,
+    /// How much sibling nodes overlap, on average: the surface area shared
+    /// by each pair of children, relative to their parent's. `0` means
+    /// siblings never overlap, so a ray only ever needs to enter one of them
+    /// at each point along it.
+    pub overlap: Float,
+}
+
+impl BvhStats {
+    /// The average number of shapes in a leaf.
+    #[inline]
+    pub fn average_leaf_size(&self) -> Float {
+        match self.leaves {
+            0 => 0.0,
+            n => self.shapes as Float / n as Float,
+        }
+    }
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} shapes, {} nodes, {} leaves ({:.2} shapes each), depth {}",
+            self.shapes,
+            self.nodes,
+            self.leaves,
+            self.average_leaf_size(),
+            self.max_depth
+        )?;
+        writeln!(
+            f,
+            "SAH cost {:.3}, overlap {:.3}",
+            self.sah_cost, self.overlap
+        )
+    }
+}
+
+impl<S> Bvh<S> {
+    /// The shapes, in the order given.
+    #[inline]
+    pub fn shapes(&self) -> &[S] {
+        &self.shapes
+    }
+
+    /// The number of shapes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    /// Whether there are no shapes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// The number of interior nodes.
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Indices of the shapes in the given leaf
+    #[inline]
+    fn leaf(&self, leaf: u32) -> &[u32] {
+        let (start, len) = self.leaves[leaf as usize];
+        &self.indices[start as usize..(start + len) as usize]
+    }
+
+    // Visit the leaves whose bounds the ray hits, nearest first. `visit` gets
+    // the leaf and current `t_max`, and returns the `t` of any nearer hit,
+    // which then culls everything farther away. If `first_hit` is set,
+    // traversal stops at the first leaf reporting a hit.
+    #[inline]
+    fn traverse(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        mut t_max: Float,
+        first_hit: bool,
+        mut visit: impl FnMut(u32, Float) -> Option<Float>,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let slab_ray = SlabRay::from(ray);
+        let mut stack = [(t_min, Child::Node(0)); STACK_SIZE];
+        let mut len = 1;
+
+        while len > 0 {
+            len -= 1;
+            let (t_near, child) = stack[len];
+            if t_near > t_max {
+                continue;
+            }
+            match child {
+                Child::Empty => {}
+                Child::Leaf(leaf) => {
+                    if let Some(t) = visit(leaf, t_max) {
+                        if first_hit {
+                            return;
+                        }
+                        t_max = t;
+                    }
+                }
+                Child::Node(idx) => {
+                    let node = &self.nodes[idx as usize];
+                    let hits = node.bounds.intersects_slab(&slab_ray, t_min, t_max);
+                    let mut order = [(0.0, Child::Empty); 4];
+                    let mut n = 0;
+                    for (hit, &child) in hits.into_iter().zip(&node.children) {
+                        if let Some(t) = hit {
+                            order[n] = (t, child);
+                            n += 1;
+                        }
+                    }
+                    // Push farthest first, so the nearest is popped next
+                    order[..n].sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+                    stack[len..len + n].copy_from_slice(&order[..n]);
+                    len += n;
+                }
+            }
+        }
+    }
+
+    // Visit leaves for a packet of up to `PACKET_SIZE` rays, each with its own
+    // `t_max`. Each stack entry carries a mask of the rays that hit its
+    // bounds, so the packet traverses together but only the rays that could
+    // hit a leaf visit it. `visit` gets the leaf, ray index and that ray's
+    // current `t_max`, and returns the `t` of any nearer hit.
+    fn traverse_packet(
+        &self,
+        rays: &[SlabRay],
+        t_min: Float,
+        t_max: &mut [Float],
+        mut visit: impl FnMut(u32, usize, Float) -> Option<Float>,
+    ) {
+        debug_assert!(rays.len() <= PACKET_SIZE);
+        if self.nodes.is_empty() || rays.is_empty() {
+            return;
+        }
+        let all = u64::MAX >> (PACKET_SIZE - rays.len());
+        let mut stack = [(Child::Node(0), all); STACK_SIZE];
+        let mut len = 1;
+
+        while len > 0 {
+            len -= 1;
+            let (child, mask) = stack[len];
+            match child {
+                Child::Empty => {}
+                Child::Leaf(leaf) => {
+                    for i in Bits(mask) {
+                        if let Some(t) = visit(leaf, i, t_max[i]) {
+                            t_max[i] = t;
+                        }
+                    }
+                }
+                Child::Node(idx) => {
+                    let node = &self.nodes[idx as usize];
+                    let mut masks = [0; 4];
+                    let mut nearest = [Float::INFINITY; 4];
+                    for i in Bits(mask) {
+                        let hits = node.bounds.intersects_slab(&rays[i], t_min, t_max[i]);
+                        for (lane, hit) in hits.into_iter().enumerate() {
+                            if let Some(t) = hit {
+                                masks[lane] |= 1 << i;
+                                nearest[lane] = nearest[lane].min(t);
+                            }
+                        }
+                    }
+                    let mut order = [(0.0, Child::Empty, 0); 4];
+                    let mut n = 0;
+                    for lane in 0..4 {
+                        if masks[lane] != 0 {
+                            order[n] = (nearest[lane], node.children[lane], masks[lane]);
+                            n += 1;
+                        }
+                    }
+                    // Push farthest first, so the nearest is popped next
+                    order[..n].sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+                    for (k, &(_, child, mask)) in order[..n].iter().enumerate() {
+                        stack[len + k] = (child, mask);
+                    }
+                    len += n;
+                }
+            }
+        }
+    }
+
+    // Split a slice of rays into packets, run `traverse_packet` on each and
+    // write a hit per ray.
+    fn intersect_packets(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+        mut hit: impl FnMut(u32, &Ray, Float) -> Option<Intersection>,
+    ) {
+        assert_eq!(rays.len(), hits.len(), "Need one hit slot per ray");
+        for (rays, hits) in rays.chunks(PACKET_SIZE).zip(hits.chunks_mut(PACKET_SIZE)) {
+            let slab_rays: Vec<SlabRay> = rays.iter().map(SlabRay::from).collect();
+            let mut t_maxes = [t_max; PACKET_SIZE];
+            hits.fill(None);
+            self.traverse_packet(&slab_rays, t_min, &mut t_maxes, |leaf, i, t_max| {
+                let isect = hit(leaf, &rays[i], t_max)?;
+                hits[i] = Some(isect);
+                Some(isect.t)
+            });
+        }
+    }
+}
+
+// Iterates over the indices of the set bits
+struct Bits(u64);
+
+impl Iterator for Bits {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let i = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(i)
+    }
+}
+
+impl<S: Shape> Bvh<S> {
+    // Nearest hit with the shapes in a leaf
+    #[inline]
+    fn leaf_intersect(
+        &self,
+        leaf: u32,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Intersection)> {
+        let mut t_max = t_max;
+        let mut nearest = None;
+        for &i in self.leaf(leaf) {
+            if let Some(isect) = self.shapes[i as usize].intersect(ray, t_min, t_max) {
+                t_max = isect.t;
+                nearest = Some((i as usize, isect));
+            }
+        }
+        nearest
+    }
+
+    /// Ray intersection test, returning the index of the shape hit along with
+    /// the [`Intersection`] record.
+    pub fn intersect_indexed(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Intersection)> {
+        let mut nearest = None;
+        self.traverse(ray, t_min, t_max, false, |leaf, t_max| {
+            let hit = self.leaf_intersect(leaf, ray, t_min, t_max)?;
+            nearest = Some(hit);
+            Some(hit.1.t)
+        });
+        nearest
+    }
+}
+
+impl<S: Shape> Shape for Bvh<S> {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.intersect_indexed(ray, t_min, t_max)
+            .map(|(_, isect)| isect)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        self.intersect_packets(rays, t_min, t_max, hits, |leaf, ray, t_max| {
+            self.leaf_intersect(leaf, ray, t_min, t_max)
+                .map(|(_, isect)| isect)
+        });
+    }
+
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        let mut found = false;
+        self.traverse(ray, t_min, t_max, true, |leaf, t_max| {
+            found = self
+                .leaf(leaf)
+                .iter()
+                .any(|&i| self.shapes[i as usize].intersects(ray, t_min, t_max));
+            found.then_some(t_max)
+        });
+        found
+    }
+}
+
+impl<S> Bounded for Bvh<S> {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+/// A [`Bvh`] over triangles that tests each leaf's four triangles at once.
+///
+/// Both the node bounds and the leaf triangles go four at a time, so with the
+/// `simd` feature enabled traversal runs almost entirely on SIMD registers.
+#[derive(Debug)]
+pub struct TriangleBvh {
+    bvh: Bvh<Triangle>,
+    // One per leaf
+    packets: Vec<Triangle4>,
+}
+
+impl TriangleBvh {
+    /// Build a hierarchy over the given triangles.
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let bvh = Bvh::new(triangles);
+        let packets = (0..bvh.leaves.len() as u32)
+            .map(|leaf| {
+                let triangles: Vec<Triangle> = bvh
+                    .leaf(leaf)
+                    .iter()
+                    .map(|&i| bvh.shapes[i as usize])
+                    .collect();
+                Triangle4::new(&triangles)
+            })
+            .collect();
+        Self { bvh, packets }
+    }
+
+    /// The triangles, in the order given.
+    #[inline]
+    pub fn triangles(&self) -> &[Triangle] {
+        self.bvh.shapes()
+    }
+
+    /// Measure the quality of the hierarchy. See [`BvhStats`].
+    pub fn stats(&self) -> BvhStats {
+        self.bvh.stats()
+    }
+
+    /// Ray intersection test, returning the index of the triangle hit along
+    /// with the [`Intersection`] record.
+    pub fn intersect_indexed(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Intersection)> {
+        let mut nearest = None;
+        self.bvh.traverse(ray, t_min, t_max, false, |leaf, t_max| {
+            let hit = self.leaf_intersect(leaf, ray, t_min, t_max)?;
+            nearest = Some(hit);
+            Some(hit.1.t)
+        });
+        nearest
+    }
+
+    #[inline]
+    fn leaf_intersect(
+        &self,
+        leaf: u32,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Intersection)> {
+        let (lane, t) = self.packets[leaf as usize].hit(ray, t_min, t_max)?;
+        let i = self.bvh.leaf(leaf)[lane] as usize;
+        let norm = self.bvh.shapes[i].normal()?;
+        let point = ray.at(t);
+        Some((i, Intersection { point, norm, t }))
+    }
+}
+
+impl Shape for TriangleBvh {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.intersect_indexed(ray, t_min, t_max)
+            .map(|(_, isect)| isect)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        self.bvh
+            .intersect_packets(rays, t_min, t_max, hits, |leaf, ray, t_max| {
+                self.leaf_intersect(leaf, ray, t_min, t_max)
+                    .map(|(_, isect)| isect)
+            });
+    }
+
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        let mut found = false;
+        self.bvh.traverse(ray, t_min, t_max, true, |leaf, t_max| {
+            found = self.packets[leaf as usize].hit(ray, t_min, t_max).is_some();
+            found.then_some(t_max)
+        });
+        found
+    }
+}
+
+impl Bounded for TriangleBvh {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.bvh.bounds
+    }
+}
+
+#[inline]
+fn union(prims: &[Prim]) -> Bounds {
+    prims
+        .iter()
+        .fold(Bounds::EMPTY, |acc, p| acc.union(&p.bounds))
+}
+
+// The surface area of the intersection of two bounds
+fn overlap_area(a: &Bounds, b: &Bounds) -> Float {
+    let min = Point::max(a.min(), b.min());
+    let max = Point::min(a.max(), b.max());
+    if min.x > max.x || min.y > max.y || min.z > max.z {
+        return 0.0;
+    }
+    Bounds::from_corners(min, max).surface_area()
+}
+
+// Split at the median centroid along the axis the centroids spread furthest.
+fn partition(prims: &mut [Prim]) -> (&mut [Prim], &mut [Prim]) {
+    let axis = prims
+        .iter()
+        .fold(Bounds::EMPTY, |acc, p| acc.include(p.centroid))
+        .longest_axis();
+    let mid = prims.len() / 2;
+    prims.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    prims.split_at_mut(mid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::Vector,
+        shape::{DirectAggregate, Sphere},
+    };
+    use rand::prelude::*;
+
+    fn random_triangles(n: usize, rng: &mut impl Rng) -> Vec<Triangle> {
+        (0..n)
+            .map(|_| {
+                let c = Point::new(
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                );
+                let mut vertex = || {
+                    c + Vector::new(
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                    )
+                };
+                Triangle::new(vertex(), vertex(), vertex())
+            })
+            .collect()
+    }
+
+    fn random_ray(rng: &mut impl Rng) -> Ray {
+        let origin = Point::new(
+            rng.gen_range(-15.0..15.0),
+            rng.gen_range(-15.0..15.0),
+            -20.0,
+        );
+        let target = Point::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), 0.0);
+        Ray::new(origin, target - origin)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let triangles = random_triangles(500, &mut rng);
+        let brute: DirectAggregate<Triangle> = triangles.clone();
+        let bvh = Bvh::new(triangles.clone());
+        let packed = TriangleBvh::new(triangles);
+        assert!(bvh.node_count() > 1);
+
+        let mut hits = 0;
+        for _ in 0..500 {
+            let ray = random_ray(&mut rng);
+            let expected = brute.intersect(&ray, 0.0, Float::INFINITY).map(|i| i.t);
+            assert_eq!(
+                expected,
+                bvh.intersect(&ray, 0.0, Float::INFINITY).map(|i| i.t)
+            );
+            assert_eq!(
+                expected,
+                packed.intersect(&ray, 0.0, Float::INFINITY).map(|i| i.t)
+            );
+            assert_eq!(
+                expected.is_some(),
+                bvh.intersects(&ray, 0.0, Float::INFINITY)
+            );
+            assert_eq!(
+                expected.is_some(),
+                packed.intersects(&ray, 0.0, Float::INFINITY)
+            );
+            hits += expected.is_some() as usize;
+        }
+        assert!(hits > 50);
+    }
+
+    #[test]
+    fn packets_match_single_rays() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let triangles = random_triangles(300, &mut rng);
+        let brute: DirectAggregate<Triangle> = triangles.clone();
+        let bvh = Bvh::new(triangles.clone());
+        let packed = TriangleBvh::new(triangles);
+
+        // More than one packet's worth, and not a multiple of the packet size
+        let rays: Vec<Ray> = (0..150).map(|_| random_ray(&mut rng)).collect();
+        let expected: Vec<_> = rays
+            .iter()
+            .map(|ray| brute.intersect(ray, 0.0, Float::INFINITY).map(|i| i.t))
+            .collect();
+
+        let shapes: [&dyn Shape; 3] = [&brute, &bvh, &packed];
+        for shape in shapes {
+            let mut hits = vec![None; rays.len()];
+            shape.intersect_packet(&rays, 0.0, Float::INFINITY, &mut hits);
+            let ts: Vec<_> = hits.iter().map(|h| h.map(|i| i.t)).collect();
+            assert_eq!(expected, ts);
+        }
+    }
+
+    #[test]
+    fn indexed() {
+        let spheres = vec![
+            Sphere::new(Point::new(0.0, 0.0, 10.0), 1.0),
+            Sphere::new(Point::new(0.0, 0.0, 5.0), 1.0),
+            Sphere::new(Point::new(5.0, 0.0, 5.0), 1.0),
+        ];
+        let bvh = Bvh::new(spheres);
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+        let (i, isect) = bvh.intersect_indexed(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(1, i);
+        assert_eq!(4.0, isect.t);
+        assert_eq!(None, bvh.intersect_indexed(&ray, 0.0, 3.0));
+
+        let empty: Bvh<Sphere> = Bvh::new(Vec::new());
+        assert!(empty.is_empty());
+        assert!(!empty.intersects(&ray, 0.0, Float::INFINITY));
+    }
+
+    #[test]
+    fn stats() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let bvh = TriangleBvh::new(random_triangles(500, &mut rng));
+        let stats = bvh.stats();
+        assert_eq!(500, stats.shapes);
+        assert!(stats.average_leaf_size() > 2.0 && stats.average_leaf_size() <= 4.0);
+        // Median splits keep the tree balanced: 500 shapes need four levels
+        // of nodes, plus the leaves
+        assert_eq!(5, stats.max_depth);
+        // The root is always visited, and random triangles overlap
+        assert!(stats.sah_cost > 1.0 && stats.sah_cost < 100.0);
+        assert!(stats.overlap > 0.0);
+
+        // Spread out along a line: the root and four disjoint leaves
+        let spheres = (0..16)
+            .map(|i| Sphere::new(Point::new(i as Float * 4.0, 0.0, 0.0), 1.0))
+            .collect();
+        let stats = Bvh::new(spheres).stats();
+        assert_eq!((1, 4, 2), (stats.nodes, stats.leaves, stats.max_depth));
+        assert_eq!(4.0, stats.average_leaf_size());
+        assert_eq!(0.0, stats.overlap);
+
+        assert_eq!(BvhStats::default(), Bvh::<Sphere>::new(Vec::new()).stats());
+    }
+}
@@ -0,0 +1,241 @@
+use super::Shape;
+use crate::{
+    camera::Camera,
+    geo::{Point, Ray, Vector},
+    Float,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::fmt;
+
+/// Cross-checks an acceleration structure against a brute-force reference.
+///
+/// Every ray is intersected against both, and any disagreement is recorded as
+/// a [`Mismatch`]: a hit one found and the other missed, hits at different
+/// distances, or a visibility query ([`Shape::intersects`]) that contradicts
+/// the reference. Bugs in a [`Bvh`] tend to show up as a handful of missing
+/// pixels on one particular scene; this finds the exact rays responsible.
+///
+/// Testing every ray against every shape is slow, so it's meant for a
+/// sampled subset of rays, _e.g._ a sparse grid of camera rays with
+/// [`camera`].
+///
+/// [`Bvh`]: super::Bvh
+/// [`camera`]: Self::camera
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrossCheck {
+    /// The number of rays tested.
+    pub rays: usize,
+    /// The number of rays the reference hit.
+    pub hits: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// A ray on which an acceleration structure disagreed with the reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mismatch {
+    pub origin: Point,
+    pub direction: Vector,
+    pub kind: MismatchKind,
+}
+
+/// How an acceleration structure disagreed with the reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MismatchKind {
+    /// The reference hit at `t`, but the acceleration structure missed.
+    Missed { t: Float },
+    /// The acceleration structure hit at `t`, but the reference missed.
+    Spurious { t: Float },
+    /// Both hit, at distances further apart than the tolerance.
+    Distance { expected: Float, actual: Float },
+    /// The visibility query disagreed with the reference.
+    Occlusion { expected: bool },
+}
+
+impl CrossCheck {
+    /// Check the given rays over `[t_min, ∞)`.
+    ///
+    /// Hit distances match if they differ by at most `epsilon`, relative to
+    /// the distance for hits further than 1 unit away.
+    pub fn run<'a, A, R>(
+        accel: &A,
+        reference: &R,
+        rays: impl IntoIterator<Item = &'a Ray>,
+        t_min: Float,
+        epsilon: Float,
+    ) -> Self
+    where
+        A: Shape + ?Sized,
+        R: Shape + ?Sized,
+    {
+        let mut check = Self::default();
+        for ray in rays {
+            check.ray(accel, reference, ray, t_min, epsilon);
+        }
+        check
+    }
+
+    /// Check camera rays through every `stride`th pixel in each direction of
+    /// an image with the given dimensions.
+    ///
+    /// Rays are generated from a fixed seed, so the same arguments always
+    /// check the same rays.
+    pub fn camera<A, R>(
+        accel: &A,
+        reference: &R,
+        camera: &impl Camera,
+        dimensions: (u32, u32),
+        stride: u32,
+        epsilon: Float,
+    ) -> Self
+    where
+        A: Shape + ?Sized,
+        R: Shape + ?Sized,
+    {
+        let stride = stride.max(1) as usize;
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut check = Self::default();
+        for py in (0..dimensions.1).step_by(stride) {
+            for px in (0..dimensions.0).step_by(stride) {
+                let ray = camera.ray(px, py, &mut rng);
+                check.ray(accel, reference, &ray, 0.0, epsilon);
+            }
+        }
+        check
+    }
+
+    /// Returns `true` if no mismatches were found.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    fn ray<A, R>(&mut self, accel: &A, reference: &R, ray: &Ray, t_min: Float, epsilon: Float)
+    where
+        A: Shape + ?Sized,
+        R: Shape + ?Sized,
+    {
+        let expected = reference
+            .intersect(ray, t_min, Float::INFINITY)
+            .map(|i| i.t);
+        let actual = accel.intersect(ray, t_min, Float::INFINITY).map(|i| i.t);
+        let occluded = accel.intersects(ray, t_min, Float::INFINITY);
+
+        self.rays += 1;
+        self.hits += expected.is_some() as usize;
+        let kind = match (expected, actual) {
+            (Some(t), None) => Some(MismatchKind::Missed { t }),
+            (None, Some(t)) => Some(MismatchKind::Spurious { t }),
+            (Some(expected), Some(actual))
+                if (expected - actual).abs() > epsilon * expected.abs().max(1.0) =>
+            {
+                Some(MismatchKind::Distance { expected, actual })
+            }
+            _ if occluded != expected.is_some() => Some(MismatchKind::Occlusion {
+                expected: expected.is_some(),
+            }),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            self.mismatches.push(Mismatch {
+                origin: ray.origin,
+                direction: ray.direction,
+                kind,
+            });
+        }
+    }
+}
+
+impl fmt::Display for CrossCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} rays, {} hits, {} mismatches",
+            self.rays,
+            self.hits,
+            self.mismatches.len()
+        )?;
+        for m in &self.mismatches {
+            writeln!(f, "{}", m)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (o, d) = (self.origin, self.direction);
+        write!(
+            f,
+            "ray ({}, {}, {}) -> ({}, {}, {}): ",
+            o.x, o.y, o.z, d.x, d.y, d.z
+        )?;
+        match self.kind {
+            MismatchKind::Missed { t } => write!(f, "missed hit at t = {}", t),
+            MismatchKind::Spurious { t } => write!(f, "spurious hit at t = {}", t),
+            MismatchKind::Distance { expected, actual } => {
+                write!(f, "hit at t = {}, expected {}", actual, expected)
+            }
+            MismatchKind::Occlusion { expected: true } => write!(f, "missed occlusion"),
+            MismatchKind::Occlusion { expected: false } => write!(f, "spurious occlusion"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::ThinLens,
+        shape::{Bvh, Intersection, Sphere},
+    };
+
+    // Ignores every other shape, like a traversal bug that skips children
+    struct Lossy(Vec<Sphere>);
+
+    impl Shape for Lossy {
+        fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+            self.0
+                .iter()
+                .step_by(2)
+                .filter_map(|s| s.intersect(ray, t_min, t_max))
+                .min_by(|a, b| a.t.total_cmp(&b.t))
+        }
+    }
+
+    fn spheres() -> Vec<Sphere> {
+        (0..20)
+            .map(|i| Sphere::new([(i % 5) as Float - 2.0, (i / 5) as Float - 1.5, 0.0], 0.4))
+            .collect()
+    }
+
+    #[test]
+    fn bvh_matches() {
+        let reference = spheres();
+        let bvh = Bvh::new(spheres());
+        let cam = ThinLens::builder((64, 48))
+            .move_to([0.0, 0.0, -10.0])
+            .look_at([0.0, 0.0, 0.0])
+            .build();
+        let check = CrossCheck::camera(&bvh, reference.as_slice(), &cam, (64, 48), 4, 1e-9);
+        assert_eq!(16 * 12, check.rays);
+        assert!(check.hits > 0);
+        assert!(check.is_ok(), "{}", check);
+    }
+
+    #[test]
+    fn finds_mismatches() {
+        let reference = spheres();
+        let lossy = Lossy(spheres());
+        let rays: Vec<Ray> = reference
+            .iter()
+            .map(|s| Ray::new(s.center() + Vector::new(0.0, 0.0, -5.0), Vector::Z_AXIS))
+            .collect();
+        let check = CrossCheck::run(&lossy, reference.as_slice(), &rays, 0.0, 1e-9);
+        assert_eq!(20, check.hits);
+        assert_eq!(10, check.mismatches.len());
+        assert!(check
+            .mismatches
+            .iter()
+            .all(|m| matches!(m.kind, MismatchKind::Missed { t } if (t - 4.6).abs() < 1e-6)));
+    }
+}
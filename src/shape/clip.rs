@@ -0,0 +1,242 @@
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, Component, Point, Ray, Unit, Vector},
+    Float,
+};
+
+/// A plane that cuts away the geometry on one side of it.
+///
+/// Everything on the side the normal points to is removed. With capping
+/// turned on, solids cut by the plane are closed off with a flat face where
+/// the cut is, so sections look solid rather than hollow. Capping relies on
+/// the shapes being closed with outward-facing normals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipPlane {
+    point: Point,
+    normal: Unit,
+    cap: bool,
+}
+
+impl ClipPlane {
+    /// A plane through `point`, removing everything on the side `normal`
+    /// points to. Uncapped by default.
+    pub fn new(point: impl Into<Point>, normal: Unit) -> Self {
+        Self {
+            point: point.into(),
+            normal,
+            cap: false,
+        }
+    }
+
+    /// An axis-aligned plane removing everything above `at` along `axis`.
+    pub fn above(axis: Component, at: Float) -> Self {
+        let (point, normal) = Self::axis(axis, at);
+        Self::new(point, normal)
+    }
+
+    /// An axis-aligned plane removing everything below `at` along `axis`.
+    pub fn below(axis: Component, at: Float) -> Self {
+        let (point, normal) = Self::axis(axis, at);
+        Self::new(point, -normal)
+    }
+
+    fn axis(axis: Component, at: Float) -> (Point, Unit) {
+        let mut point = Point::ORIGIN;
+        point[axis] = at;
+        let normal = match axis {
+            Component::X => Unit::X_AXIS,
+            Component::Y => Unit::Y_AXIS,
+            Component::Z => Unit::Z_AXIS,
+        };
+        (point, normal)
+    }
+
+    /// Whether to cap solids where the plane cuts them.
+    pub fn capped(mut self, cap: bool) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    /// A point on the plane.
+    #[inline]
+    pub fn point(&self) -> Point {
+        self.point
+    }
+
+    /// The plane's normal, pointing to the side that's removed.
+    #[inline]
+    pub fn normal(&self) -> Unit {
+        self.normal
+    }
+
+    /// Whether solids are capped where the plane cuts them.
+    #[inline]
+    pub fn is_capped(&self) -> bool {
+        self.cap
+    }
+}
+
+/// A set of clip planes, applied together.
+///
+/// The kept region is where every plane keeps geometry. It's convex, so each
+/// ray crosses it in a single interval, and clipping a shape just narrows the
+/// `[t_min, t_max]` interval it's intersected over.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Clip {
+    planes: Vec<ClipPlane>,
+}
+
+impl Clip {
+    /// Clip with the given planes.
+    pub fn new(planes: Vec<ClipPlane>) -> Self {
+        Self { planes }
+    }
+
+    /// Add a plane.
+    pub fn push(&mut self, plane: ClipPlane) {
+        self.planes.push(plane);
+    }
+
+    /// The clip planes.
+    #[inline]
+    pub fn planes(&self) -> &[ClipPlane] {
+        &self.planes
+    }
+
+    /// Returns `true` if there are no planes, so nothing is clipped.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.planes.is_empty()
+    }
+
+    /// Intersect a ray with the parts of `shape` that aren't clipped away,
+    /// including any caps.
+    pub fn intersect<S>(
+        &self,
+        shape: &S,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<Intersection>
+    where
+        S: Shape + ?Sized,
+    {
+        if self.planes.is_empty() {
+            return shape.intersect(ray, t_min, t_max);
+        }
+        let (t0, t1, entry) = self.interval(ray, t_min, t_max)?;
+
+        // Look past `t1` so the inside test below sees the far side of a
+        // solid even where another plane cuts it away.
+        let hit = shape.intersect(ray, t0, t_max);
+        if let Some(plane) = entry.filter(|p| p.cap) {
+            // Entering the kept region inside a solid: the first surface we
+            // hit is a back face
+            let inside = hit.is_some_and(|h| Vector::from(h.norm).dot(ray.direction) > 0.0);
+            if inside {
+                return Some(Intersection {
+                    point: ray.at(t0),
+                    norm: plane.normal,
+                    t: t0,
+                });
+            }
+        }
+        hit.filter(|h| h.t <= t1)
+    }
+
+    // The interval of the ray in the kept region, and the plane it enters
+    // through (if it starts outside).
+    fn interval(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(Float, Float, Option<&ClipPlane>)> {
+        let (mut t0, mut t1) = (t_min, t_max);
+        let mut entry = None;
+        for plane in &self.planes {
+            let n = Vector::from(plane.normal);
+            let dist = n.dot(ray.origin - plane.point);
+            let speed = n.dot(ray.direction);
+            if speed == 0.0 {
+                if dist > 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let t = -dist / speed;
+            if speed > 0.0 {
+                t1 = t1.min(t);
+            } else if t > t0 {
+                t0 = t;
+                entry = Some(plane);
+            }
+        }
+        (t0 <= t1).then_some((t0, t1, entry))
+    }
+}
+
+/// A shape with a [`Clip`] applied.
+#[derive(Debug, Default)]
+pub struct Clipped<S> {
+    pub shape: S,
+    pub clip: Clip,
+}
+
+impl<S> Clipped<S> {
+    /// Clip the shape with the given planes.
+    pub fn new(shape: S, clip: Clip) -> Self {
+        Self { shape, clip }
+    }
+}
+
+impl<S: Shape> Shape for Clipped<S> {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.clip.intersect(&self.shape, ray, t_min, t_max)
+    }
+}
+
+/// The bounds of the unclipped shape, which still enclose what's left.
+impl<S: Bounded> Bounded for Clipped<S> {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.shape.bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Sphere;
+
+    #[test]
+    fn cuts_and_caps() {
+        let sphere = Sphere::new(Point::ORIGIN, 1.0);
+        // Looking down at a sphere with its top half cut away
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), -Vector::Y_AXIS);
+        let plane = ClipPlane::above(Component::Y, 0.0);
+
+        let open = Clipped::new(sphere, Clip::new(vec![plane]));
+        let isect = open.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        // The inside of the bottom half
+        assert_eq!(6.0, isect.t);
+
+        let capped = Clipped::new(sphere, Clip::new(vec![plane.capped(true)]));
+        let isect = capped.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(5.0, isect.t);
+        assert_eq!(Unit::Y_AXIS, isect.norm);
+
+        // Rays that miss the solid don't hit the cap
+        let ray = Ray::new(Point::new(2.0, 5.0, 0.0), -Vector::Y_AXIS);
+        assert!(!capped.intersects(&ray, 0.0, Float::INFINITY));
+
+        // Rays in the kept region see the sphere as usual
+        let ray = Ray::new(Point::new(0.0, -5.0, 0.0), Vector::Y_AXIS);
+        assert_eq!(4.0, capped.intersect(&ray, 0.0, Float::INFINITY).unwrap().t);
+
+        // Everything is cut away
+        let gone = Clipped::new(sphere, Clip::new(vec![ClipPlane::below(Component::Y, 2.0)]));
+        assert!(!gone.intersects(&ray, 0.0, Float::INFINITY));
+    }
+}
@@ -0,0 +1,421 @@
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, Frame, Point, Ray, Unit, Vector},
+    Float,
+};
+
+/// How a [`Curve`] is widened into a surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveMode {
+    /// A flat strip that always faces the ray. The cheapest to shade, and
+    /// fine for hair too thin to show its roundness.
+    Flat,
+    /// A flat strip that faces the ray, with its normal bent across the width
+    /// so it shades like a tube.
+    Cylinder,
+    /// A strip lying flat across the given normals at the start and end of
+    /// the curve, interpolated in between. For grass blades and other shapes
+    /// that turn edge-on.
+    Ribbon([Unit; 2]),
+}
+
+/// A cubic Bézier curve with a width, for hair and fur.
+///
+/// Strands are much thinner than they are long, so meshing them into
+/// triangles takes a huge number of slivers. A curve is intersected directly
+/// instead, following pbrt: the curve is moved into a space where the ray
+/// runs down the `z` axis, then split in half recursively until each piece
+/// is close enough to a straight line to test against the ray's distance
+/// from it.
+///
+/// The width varies linearly from one end to the other, so tapering tips are
+/// just a width of zero at the end. A long strand is a chain of curves; see
+/// [`catmull_rom`].
+///
+/// [`catmull_rom`]: Self::catmull_rom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    points: [Point; 4],
+    width: [Float; 2],
+    mode: CurveMode,
+}
+
+// Pieces at the maximum depth are at most this fraction of the width from
+// a straight line
+const FLATNESS: Float = 0.05;
+
+const SQRT_2: Float = std::f64::consts::SQRT_2 as Float;
+
+// Don't split more than this many times, however curvy
+const MAX_DEPTH: u32 = 10;
+
+impl Curve {
+    /// Creates a curve with the given control points and widths at the start
+    /// and end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either width is negative or not finite, or if both are
+    /// zero.
+    pub fn new(points: [Point; 4], width: [Float; 2], mode: CurveMode) -> Self {
+        if width.iter().any(|w| !w.is_finite() || *w < 0.0) || width == [0.0, 0.0] {
+            panic!(
+                "Invalid widths {:?}; must be finite, non-negative and not both zero",
+                width
+            );
+        }
+        Self {
+            points,
+            width,
+            mode,
+        }
+    }
+
+    /// A chain of curves passing through each of the given points in turn,
+    /// with the width at each point given by `widths`.
+    ///
+    /// The curves form a uniform Catmull-Rom spline, so the chain is smooth
+    /// where the curves join. Spans with zero width at both ends are left
+    /// out, as are all of them if there are fewer than two points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points` and `widths` have different lengths, or if any
+    /// width is negative or not finite.
+    pub fn catmull_rom(points: &[Point], widths: &[Float], mode: CurveMode) -> Vec<Self> {
+        assert_eq!(points.len(), widths.len(), "Need one width per point");
+        let n = points.len();
+        (0..n.saturating_sub(1))
+            .filter(|&i| widths[i] != 0.0 || widths[i + 1] != 0.0)
+            .map(|i| {
+                let p0 = points[i.saturating_sub(1)];
+                let (p1, p2) = (points[i], points[i + 1]);
+                let p3 = points[(i + 2).min(n - 1)];
+                let cps = [p1, p1 + (p2 - p0) / 6.0, p2 + (p1 - p3) / 6.0, p2];
+                Self::new(cps, [widths[i], widths[i + 1]], mode)
+            })
+            .collect()
+    }
+
+    /// The control points.
+    #[inline]
+    pub const fn points(&self) -> [Point; 4] {
+        self.points
+    }
+
+    /// The widths at the start and end.
+    #[inline]
+    pub const fn width(&self) -> [Float; 2] {
+        self.width
+    }
+
+    /// How the curve is widened into a surface.
+    #[inline]
+    pub const fn mode(&self) -> CurveMode {
+        self.mode
+    }
+
+    /// The point on the curve at parameter `u` in `[0, 1]`.
+    pub fn at(&self, u: Float) -> Point {
+        Point::from(bezier(&self.points.map(Vector::from), u).0)
+    }
+
+    #[inline]
+    fn width_at(&self, u: Float) -> Float {
+        self.width[0] + (self.width[1] - self.width[0]) * u
+    }
+
+    fn ribbon_normal(normals: [Unit; 2], u: Float) -> Unit {
+        let [n0, n1] = normals.map(Vector::from);
+        let cos = n0.dot(n1).clamp(-1.0, 1.0);
+        let angle = cos.acos();
+        let n = match angle.sin() > 1e-4 {
+            true => (n0 * ((1.0 - u) * angle).sin() + n1 * (u * angle).sin()) / angle.sin(),
+            false => n0 * (1.0 - u) + n1 * u,
+        };
+        Unit::try_from(n).unwrap_or(normals[0])
+    }
+
+    fn nearest(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Hit> {
+        // In ray space the ray starts at the origin and runs down `z`, so the
+        // ray hits where the curve passes within half its width of the `z`
+        // axis
+        let len = ray.direction.len();
+        let frame = Frame::from_normal(Unit::try_from(ray.direction).ok()?);
+        let cps = self.points.map(|p| frame.to_local(p - ray.origin));
+
+        // Split until each piece is within `FLATNESS` of a line
+        let curviness = (0..2)
+            .map(|i| (cps[i] - cps[i + 1] * 2.0 + cps[i + 2]).apply(Float::abs))
+            .map(|v| v.max_component())
+            .fold(0.0, Float::max);
+        let eps = self.width[0].max(self.width[1]) * FLATNESS;
+        let depth = ((SQRT_2 * 6.0 * curviness / (8.0 * eps)).log2() / 2.0)
+            .clamp(0.0, MAX_DEPTH as Float) as u32;
+
+        let mut search = Search {
+            curve: self,
+            direction: frame.z(),
+            len,
+            z_range: (t_min * len, t_max * len),
+            hit: None,
+        };
+        search.recurse(cps, 0.0, 1.0, depth);
+        let mut hit = search.hit?;
+        hit.offset = frame.to_world(hit.offset);
+
+        // Which way the normal faces depends on the mode
+        let d = Vector::from(frame.z());
+        let tangent = Unit::try_from(bezier(&self.points.map(Vector::from), hit.u).1).ok();
+        let facing = match tangent {
+            Some(tan) => Unit::try_from(tan.cross(tan.cross(d))).unwrap_or(-frame.z()),
+            None => -frame.z(),
+        };
+        hit.norm = match (self.mode, tangent) {
+            (CurveMode::Flat, _) | (CurveMode::Cylinder, None) => facing,
+            (CurveMode::Cylinder, Some(tan)) => {
+                // Bend the normal towards the side of the tube the hit is on
+                let side = Vector::from(facing).cross(tan.into());
+                let s = (hit.offset.dot(side) / (0.5 * hit.width)).clamp(-1.0, 1.0);
+                Unit::try_from(side * s + Vector::from(facing) * (1.0 - s * s).sqrt())
+                    .unwrap_or(facing)
+            }
+            (CurveMode::Ribbon(normals), _) => Self::ribbon_normal(normals, hit.u),
+        };
+        Some(hit)
+    }
+}
+
+// The nearest hit found so far. `offset` is from the curve's center line to
+// the hit point.
+#[derive(Debug, Clone, Copy)]
+struct Hit {
+    t: Float,
+    u: Float,
+    width: Float,
+    offset: Vector,
+    norm: Unit,
+}
+
+struct Search<'a> {
+    curve: &'a Curve,
+    // World space ray direction, and its length
+    direction: Unit,
+    len: Float,
+    z_range: (Float, Float),
+    hit: Option<Hit>,
+}
+
+impl Search<'_> {
+    fn recurse(&mut self, cps: [Vector; 4], u0: Float, u1: Float, depth: u32) {
+        // The curve lies within its control points' bounds, widened by the
+        // width, which is largest at one end or the other
+        let half_width = 0.5 * self.curve.width_at(u0).max(self.curve.width_at(u1));
+        let lo = cps.iter().fold(cps[0], |acc, &p| Vector::min(acc, p));
+        let hi = cps.iter().fold(cps[0], |acc, &p| Vector::max(acc, p));
+        if lo.x - half_width > 0.0
+            || hi.x + half_width < 0.0
+            || lo.y - half_width > 0.0
+            || hi.y + half_width < 0.0
+            || lo.z - half_width > self.z_range.1
+            || hi.z + half_width < self.z_range.0
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let [a, b] = split(cps);
+            let mid = 0.5 * (u0 + u1);
+            self.recurse(a, u0, mid, depth - 1);
+            self.recurse(b, mid, u1, depth - 1);
+            return;
+        }
+
+        // The ray must pass between the lines perpendicular to the piece at
+        // each end, or it's the neighbouring piece's to hit
+        let [p0, p1, p2, p3] = cps;
+        if (p1.y - p0.y) * -p0.y + p0.x * (p0.x - p1.x) < 0.0
+            || (p2.y - p3.y) * -p3.y + p3.x * (p3.x - p2.x) < 0.0
+        {
+            return;
+        }
+
+        // Nearest point to the ray along the line through the piece's ends
+        let (dx, dy) = (p3.x - p0.x, p3.y - p0.y);
+        let denom = dx * dx + dy * dy;
+        if denom == 0.0 {
+            return;
+        }
+        let w = (-p0.x * dx - p0.y * dy) / denom;
+        let u = (u0 + (u1 - u0) * w).clamp(u0, u1);
+
+        let mut width = self.curve.width_at(u);
+        if let CurveMode::Ribbon(normals) = self.curve.mode {
+            // Ribbons narrow as they turn edge-on
+            width *= Curve::ribbon_normal(normals, u).dot(self.direction).abs();
+        }
+
+        let (pc, _) = bezier(&cps, w.clamp(0.0, 1.0));
+        if pc.x * pc.x + pc.y * pc.y > 0.25 * width * width {
+            return;
+        }
+        if pc.z < self.z_range.0 || pc.z > self.z_range.1 {
+            return;
+        }
+
+        self.z_range.1 = pc.z;
+        self.hit = Some(Hit {
+            t: pc.z / self.len,
+            u,
+            width,
+            offset: Vector::new(-pc.x, -pc.y, 0.0),
+            norm: Unit::Z_AXIS,
+        });
+    }
+}
+
+// Split a cubic Bézier in half, by de Casteljau's algorithm.
+#[inline]
+fn split([p0, p1, p2, p3]: [Vector; 4]) -> [[Vector; 4]; 2] {
+    let p01 = (p0 + p1) * 0.5;
+    let p12 = (p1 + p2) * 0.5;
+    let p23 = (p2 + p3) * 0.5;
+    let p012 = (p01 + p12) * 0.5;
+    let p123 = (p12 + p23) * 0.5;
+    let mid = (p012 + p123) * 0.5;
+    [[p0, p01, p012, mid], [mid, p123, p23, p3]]
+}
+
+// Evaluate a cubic Bézier and its derivative at `u`.
+#[inline]
+fn bezier(&[p0, p1, p2, p3]: &[Vector; 4], u: Float) -> (Vector, Vector) {
+    let lerp = |a: Vector, b: Vector| a * (1.0 - u) + b * u;
+    let (a, b, c) = (lerp(p0, p1), lerp(p1, p2), lerp(p2, p3));
+    let (d, e) = (lerp(a, b), lerp(b, c));
+    (lerp(d, e), (e - d) * 3.0)
+}
+
+impl Shape for Curve {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        let hit = self.nearest(ray, t_min, t_max)?;
+        Some(Intersection {
+            point: ray.at(hit.t),
+            norm: hit.norm,
+            t: hit.t,
+        })
+    }
+}
+
+impl Bounded for Curve {
+    fn bounds(&self) -> Bounds {
+        let r = 0.5 * self.width[0].max(self.width[1]);
+        let r = Vector::new(r, r, r);
+        self.points.iter().fold(Bounds::EMPTY, |acc, &p| {
+            acc.union(&Bounds::from_corners(p + -r, p + r))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn straight(mode: CurveMode) -> Curve {
+        let points = [-1.0, -0.5, 0.5, 1.0].map(|x| Point::new(x, 0.0, 0.0));
+        Curve::new(points, [0.2, 0.2], mode)
+    }
+
+    fn down(x: Float, y: Float) -> Ray {
+        Ray::new(Point::new(x, y, -5.0), Vector::Z_AXIS * 2.0)
+    }
+
+    #[test]
+    fn flat() {
+        let curve = straight(CurveMode::Flat);
+        let isect = curve
+            .intersect(&down(0.3, 0.05), 0.0, Float::INFINITY)
+            .unwrap();
+        assert!((isect.t - 2.5).abs() < 1e-9, "{}", isect.t);
+        assert_eq!(-Unit::Z_AXIS, isect.norm);
+        assert!(!curve.intersects(&down(0.3, 0.15), 0.0, Float::INFINITY));
+        assert!(!curve.intersects(&down(1.2, 0.0), 0.0, Float::INFINITY));
+        assert!(!curve.intersects(&down(0.3, 0.0), 0.0, 2.0));
+    }
+
+    #[test]
+    fn cylinder() {
+        let curve = straight(CurveMode::Cylinder);
+        let center = curve
+            .intersect(&down(0.0, 0.0), 0.0, Float::INFINITY)
+            .unwrap();
+        assert!(center.norm.z() < -0.99);
+        let side = curve
+            .intersect(&down(0.0, 0.09), 0.0, Float::INFINITY)
+            .unwrap();
+        assert!(side.norm.y() > 0.8, "{:?}", side.norm);
+    }
+
+    #[test]
+    fn ribbon() {
+        let curve = straight(CurveMode::Ribbon([Unit::Z_AXIS, Unit::Y_AXIS]));
+        // Facing the ray at the start, edge-on at the end
+        let start = curve.intersect(&down(-0.9, 0.05), 0.0, Float::INFINITY);
+        assert!(start.unwrap().norm.z() > 0.99);
+        assert!(!curve.intersects(&down(0.99, 0.0), 0.0, Float::INFINITY));
+    }
+
+    #[test]
+    fn matches_distance() {
+        // A bent curve, tested against its distance from densely sampled
+        // points, well away from the edges
+        let points = [
+            [-1.0, 0.0, 0.0],
+            [-0.5, 1.0, 0.5],
+            [0.5, -1.0, 0.0],
+            [1.0, 0.0, 0.5],
+        ];
+        let curve = Curve::new(points.map(Point::from), [0.1, 0.1], CurveMode::Flat);
+        let samples: Vec<Point> = (0..=2000).map(|i| curve.at(i as Float / 2000.0)).collect();
+        let mut hits = 0;
+        for i in 0..60 {
+            for j in 0..60 {
+                let (x, y) = (i as Float / 25.0 - 1.2, j as Float / 25.0 - 1.2);
+                let dist = samples
+                    .iter()
+                    .filter(|p| p.x.abs() < 1.0)
+                    .map(|p| ((p.x - x).powi(2) + (p.y - y).powi(2)).sqrt())
+                    .fold(Float::INFINITY, Float::min);
+                if (dist - 0.05).abs() < 0.01 || x.abs() > 0.95 {
+                    continue;
+                }
+                let hit = curve.intersects(&down(x, y), 0.0, Float::INFINITY);
+                assert_eq!(dist < 0.05, hit, "({}, {})", x, y);
+                hits += hit as usize;
+            }
+        }
+        assert!(hits > 20);
+    }
+
+    #[test]
+    fn catmull_rom() {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [3.0, 1.0, 1.0],
+        ]
+        .map(Point::from);
+        let curves = Curve::catmull_rom(&points, &[0.1, 0.1, 0.0, 0.0], CurveMode::Flat);
+        // The last span has no width
+        assert_eq!(2, curves.len());
+        assert_eq!(points[1], curves[0].at(1.0));
+        assert_eq!(points[1], curves[1].at(0.0));
+
+        // Smooth where the curves join
+        let [_, _, a, b] = curves[0].points();
+        let [c, d, _, _] = curves[1].points();
+        assert_eq!(b, c);
+        assert!((b - a).cross(d - c).len() < 1e-12);
+    }
+}
@@ -0,0 +1,325 @@
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, Component, Point, Ray, SlabRay},
+    Float,
+};
+
+/// A uniform grid acceleration structure.
+///
+/// Divides the scene's bounds into equal cells, lists the shapes overlapping
+/// each one, and walks a ray through the cells it crosses in order with a 3D
+/// DDA, stopping at the first cell with a hit. Building takes two passes over
+/// the shapes with no sorting, which suits scenes rebuilt every frame, and it
+/// makes a simple baseline to compare a [`Bvh`] against. It does poorly when shapes
+/// are unevenly spread, since most cells end up empty while a few hold
+/// nearly everything.
+///
+/// Like [`Bvh`], the shapes keep the order they're given in.
+///
+/// [`Bvh`]: super::Bvh
+#[derive(Debug)]
+pub struct UniformGrid<S> {
+    shapes: Vec<S>,
+    bounds: Bounds,
+    resolution: [u32; 3],
+    cell_size: [Float; 3],
+    // Shape indices for cell `i` are `items[cells[i]..cells[i + 1]]`
+    cells: Vec<u32>,
+    items: Vec<u32>,
+}
+
+impl<S: Bounded> UniformGrid<S> {
+    /// The default [`density`].
+    ///
+    /// [`density`]: Self::with_density
+    pub const DENSITY: Float = 3.0;
+
+    /// Build a grid over the given shapes at the default density.
+    pub fn new(shapes: Vec<S>) -> Self {
+        Self::with_density(shapes, Self::DENSITY)
+    }
+
+    /// Build a grid over the given shapes, with roughly `density` times the
+    /// cube root of the shape count cells along the longest axis. Other axes
+    /// get cells of about the same size, and no axis gets more than 256.
+    pub fn with_density(shapes: Vec<S>, density: Float) -> Self {
+        let shape_bounds: Vec<Bounds> = shapes.iter().map(Bounded::bounds).collect();
+        let bounds = shape_bounds
+            .iter()
+            .fold(Bounds::EMPTY, |acc, b| acc.union(b));
+
+        let extent = if bounds.is_empty() {
+            [0.0; 3]
+        } else {
+            bounds.diagonal().into()
+        };
+        let max_extent = extent.iter().copied().fold(0.0, Float::max);
+        let cells_per_unit = match max_extent > 0.0 {
+            true => density * (shapes.len() as Float).cbrt() / max_extent,
+            false => 0.0,
+        };
+        let resolution = extent.map(|e| ((e * cells_per_unit).round() as u32).clamp(1, 256));
+        let cell_size = [0, 1, 2].map(|a| extent[a] / resolution[a] as Float);
+
+        let mut grid = Self {
+            shapes,
+            bounds,
+            resolution,
+            cell_size,
+            cells: Vec::new(),
+            items: Vec::new(),
+        };
+
+        // Count, then fill, so the cell lists are one flat array
+        let cell_count = resolution.iter().map(|&n| n as usize).product::<usize>();
+        let mut counts = vec![0u32; cell_count + 1];
+        for b in &shape_bounds {
+            grid.for_each_cell(b, |cell| counts[cell] += 1);
+        }
+        let mut start = 0;
+        for count in counts.iter_mut() {
+            let n = *count;
+            *count = start;
+            start += n;
+        }
+        let mut fill = counts.clone();
+        let mut items = vec![0; start as usize];
+        for (i, b) in shape_bounds.iter().enumerate() {
+            grid.for_each_cell(b, |cell| {
+                items[fill[cell] as usize] = i as u32;
+                fill[cell] += 1;
+            });
+        }
+        grid.cells = counts;
+        grid.items = items;
+        grid
+    }
+
+    fn for_each_cell(&self, bounds: &Bounds, mut f: impl FnMut(usize)) {
+        if bounds.is_empty() {
+            return;
+        }
+        let lo = self.cell_of(bounds.min());
+        let hi = self.cell_of(bounds.max());
+        for z in lo[2]..=hi[2] {
+            for y in lo[1]..=hi[1] {
+                for x in lo[0]..=hi[0] {
+                    f(self.cell_index([x, y, z]));
+                }
+            }
+        }
+    }
+}
+
+impl<S> UniformGrid<S> {
+    /// The shapes, in the order given.
+    #[inline]
+    pub fn shapes(&self) -> &[S] {
+        &self.shapes
+    }
+
+    /// The number of cells along each axis.
+    #[inline]
+    pub fn resolution(&self) -> [u32; 3] {
+        self.resolution
+    }
+
+    // The cell containing `p`, clamped to the grid
+    #[inline]
+    fn cell_of(&self, p: Point) -> [i32; 3] {
+        [0, 1, 2].map(|a| {
+            let c = Component::XYZ[a];
+            let n = self.resolution[a] as i32;
+            match self.cell_size[a] > 0.0 {
+                true => {
+                    (((p[c] - self.bounds.min()[c]) / self.cell_size[a]) as i32).clamp(0, n - 1)
+                }
+                false => 0,
+            }
+        })
+    }
+
+    #[inline]
+    fn cell_index(&self, [x, y, z]: [i32; 3]) -> usize {
+        let [nx, ny, _] = self.resolution.map(|n| n as usize);
+        (z as usize * ny + y as usize) * nx + x as usize
+    }
+
+    #[inline]
+    fn cell(&self, idx: usize) -> &[u32] {
+        &self.items[self.cells[idx] as usize..self.cells[idx + 1] as usize]
+    }
+
+    // Walk the cells along the ray, nearest first. `visit` gets each cell's
+    // shapes and the `t` where the ray leaves the cell, and returns `true` to
+    // stop.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        mut visit: impl FnMut(&[u32], Float) -> bool,
+    ) {
+        if self.bounds.is_empty() {
+            return;
+        }
+        let slab_ray = SlabRay::from(ray);
+        let Some((t0, t1)) = self.bounds.intersects_slab(&slab_ray, t_min, t_max) else {
+            return;
+        };
+
+        let mut cell = self.cell_of(ray.at(t0));
+        let mut next = [Float::INFINITY; 3];
+        let mut delta = [0.0; 3];
+        let mut step = [0; 3];
+        let mut out = [0; 3];
+        for a in 0..3 {
+            let c = Component::XYZ[a];
+            let (dir, inv) = (ray.direction[c], slab_ray.inv_direction[c]);
+            let min = self.bounds.min()[c];
+            if dir > 0.0 {
+                let edge = min + (cell[a] + 1) as Float * self.cell_size[a];
+                next[a] = (edge - ray.origin[c]) * inv;
+                delta[a] = self.cell_size[a] * inv;
+                step[a] = 1;
+                out[a] = self.resolution[a] as i32;
+            } else if dir < 0.0 {
+                let edge = min + cell[a] as Float * self.cell_size[a];
+                next[a] = (edge - ray.origin[c]) * inv;
+                delta[a] = -self.cell_size[a] * inv;
+                step[a] = -1;
+                out[a] = -1;
+            }
+        }
+
+        loop {
+            let a = match (next[0] < next[1], next[0] < next[2], next[1] < next[2]) {
+                (true, true, _) => 0,
+                (false, _, true) => 1,
+                _ => 2,
+            };
+            let exit = next[a].min(t1);
+            if visit(self.cell(self.cell_index(cell)), exit) || next[a] > t1 {
+                return;
+            }
+            cell[a] += step[a];
+            if cell[a] == out[a] {
+                return;
+            }
+            next[a] += delta[a];
+        }
+    }
+}
+
+impl<S: Shape> UniformGrid<S> {
+    /// Ray intersection test, returning the index of the shape hit along with
+    /// the [`Intersection`] record.
+    pub fn intersect_indexed(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Intersection)> {
+        let mut nearest: Option<(usize, Intersection)> = None;
+        let mut t_max = t_max;
+        self.traverse(ray, t_min, t_max, |shapes, exit| {
+            for &i in shapes {
+                if let Some(isect) = self.shapes[i as usize].intersect(ray, t_min, t_max) {
+                    t_max = isect.t;
+                    nearest = Some((i as usize, isect));
+                }
+            }
+            // Shapes span several cells, so a hit beyond this one might not be
+            // the nearest yet
+            nearest.is_some_and(|(_, isect)| isect.t <= exit)
+        });
+        nearest
+    }
+}
+
+impl<S: Shape> Shape for UniformGrid<S> {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.intersect_indexed(ray, t_min, t_max)
+            .map(|(_, isect)| isect)
+    }
+
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        let mut found = false;
+        self.traverse(ray, t_min, t_max, |shapes, _| {
+            found = shapes
+                .iter()
+                .any(|&i| self.shapes[i as usize].intersects(ray, t_min, t_max));
+            found
+        });
+        found
+    }
+}
+
+impl<S> Bounded for UniformGrid<S> {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::Vector,
+        shape::{CrossCheck, Sphere},
+    };
+    use rand::prelude::*;
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let spheres: Vec<Sphere> = (0..400)
+            .map(|_| {
+                let c = [0, 1, 2].map(|_| rng.gen_range(-10.0..10.0));
+                Sphere::new(c, rng.gen_range(0.1..1.5))
+            })
+            .collect();
+        let grid = UniformGrid::new(spheres.clone());
+        assert!(grid.resolution().iter().all(|&n| n > 1));
+
+        let rays: Vec<Ray> = (0..1000)
+            .map(|_| {
+                let o = Point::new(
+                    rng.gen_range(-20.0..20.0),
+                    rng.gen_range(-20.0..20.0),
+                    rng.gen_range(-20.0..20.0),
+                );
+                let d = Vector::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                Ray::new(o, d)
+            })
+            .collect();
+        let check = CrossCheck::run(&grid, spheres.as_slice(), &rays, 0.0, 1e-9);
+        assert!(check.hits > 100);
+        assert!(check.is_ok(), "{}", check);
+
+        // Axis-aligned rays, and rays starting inside the grid
+        let ray = Ray::new(Point::new(0.0, 0.0, -30.0), Vector::Z_AXIS);
+        let inside = Ray::new(Point::new(1.0, -2.0, 0.5), -Vector::X_AXIS);
+        let check = CrossCheck::run(&grid, spheres.as_slice(), [&ray, &inside], 0.0, 1e-9);
+        assert!(check.is_ok(), "{}", check);
+    }
+
+    #[test]
+    fn degenerate() {
+        let empty: UniformGrid<Sphere> = UniformGrid::new(Vec::new());
+        let ray = Ray::new(Point::ORIGIN, Vector::Z_AXIS);
+        assert!(!empty.intersects(&ray, 0.0, Float::INFINITY));
+
+        let one = UniformGrid::new(vec![Sphere::new([0.0, 0.0, 5.0], 1.0)]);
+        assert_eq!(
+            Some(4.0),
+            one.intersect(&ray, 0.0, Float::INFINITY).map(|i| i.t)
+        );
+    }
+}
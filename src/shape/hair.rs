@@ -0,0 +1,234 @@
+use super::{Curve, CurveMode};
+use crate::{geo::Point, Float};
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// Errors that can occur while loading a hair file.
+#[derive(Debug)]
+pub enum HairLoadError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file is malformed.
+    Parse(&'static str),
+}
+
+impl fmt::Display for HairLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read hair file: {}", e),
+            Self::Parse(msg) => write!(f, "invalid hair file: {}", msg),
+        }
+    }
+}
+
+impl Error for HairLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HairLoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A single strand of hair: a polyline with a thickness at each point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HairStrand {
+    pub points: Vec<Point>,
+    pub thickness: Vec<Float>,
+}
+
+/// A hair model loaded from a `.hair` file.
+///
+/// The format, by Cem Yuksel, stores each strand as a polyline, optionally
+/// with per-point thickness, transparency and color. Only the points and
+/// thickness are loaded. Coordinates are kept as stored; most published
+/// models are Z-up.
+///
+/// See: <http://www.cemyuksel.com/research/hairmodels/>
+#[derive(Debug, Clone, PartialEq)]
+pub struct HairModel {
+    pub strands: Vec<HairStrand>,
+}
+
+// Bits of the header's array flags
+const HAS_SEGMENTS: u32 = 1 << 0;
+const HAS_POINTS: u32 = 1 << 1;
+const HAS_THICKNESS: u32 = 1 << 2;
+
+const HEADER_SIZE: usize = 128;
+
+impl HairModel {
+    /// Load a model from a `.hair` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, HairLoadError> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// Parse a model from the contents of a `.hair` file.
+    pub fn parse(data: &[u8]) -> Result<Self, HairLoadError> {
+        let mut r = Reader { data, pos: 0 };
+        if r.take(4)? != b"HAIR" {
+            return Err(HairLoadError::Parse("missing HAIR header"));
+        }
+        let strand_count = r.u32()? as usize;
+        let point_count = r.u32()? as usize;
+        let flags = r.u32()?;
+        let default_segments = r.u32()? as usize;
+        let default_thickness = r.f32()? as Float;
+        r.pos = HEADER_SIZE;
+
+        if flags & HAS_POINTS == 0 {
+            return Err(HairLoadError::Parse("file has no points"));
+        }
+        // Check the counts against the file size before allocating for them
+        if strand_count > point_count || point_count > data.len() / 12 {
+            return Err(HairLoadError::Parse("bad strand or point count"));
+        }
+
+        // Each strand has one more point than it has segments
+        let segments = match flags & HAS_SEGMENTS != 0 {
+            true => (0..strand_count)
+                .map(|_| r.u16().map(|n| n as usize))
+                .collect::<Result<Vec<_>, _>>()?,
+            false => vec![default_segments; strand_count],
+        };
+        let expected = segments.iter().map(|n| n + 1).sum::<usize>();
+        if expected != point_count {
+            return Err(HairLoadError::Parse("point count doesn't match segments"));
+        }
+
+        let points = (0..point_count)
+            .map(|_| {
+                Ok(Point::new(
+                    r.f32()? as Float,
+                    r.f32()? as Float,
+                    r.f32()? as Float,
+                ))
+            })
+            .collect::<Result<Vec<_>, HairLoadError>>()?;
+        let thickness = match flags & HAS_THICKNESS != 0 {
+            true => (0..point_count)
+                .map(|_| r.f32().map(|t| t as Float))
+                .collect::<Result<Vec<_>, _>>()?,
+            false => vec![default_thickness; point_count],
+        };
+
+        let mut start = 0;
+        let strands = segments
+            .iter()
+            .map(|n| {
+                let range = start..start + n + 1;
+                start = range.end;
+                HairStrand {
+                    points: points[range.clone()].to_vec(),
+                    thickness: thickness[range].to_vec(),
+                }
+            })
+            .collect();
+        Ok(Self { strands })
+    }
+
+    /// The strands as smooth chains of curves through their points (see
+    /// [`Curve::catmull_rom`]), with width equal to the thickness.
+    ///
+    /// Strands with negative or non-finite thickness are skipped.
+    pub fn curves(&self, mode: CurveMode) -> Vec<Curve> {
+        self.strands
+            .iter()
+            .filter(|s| s.thickness.iter().all(|t| t.is_finite() && *t >= 0.0))
+            .flat_map(|s| Curve::catmull_rom(&s.points, &s.thickness, mode))
+            .collect()
+    }
+}
+
+// Little-endian cursor over the file contents.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], HairLoadError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len());
+        let end = end.ok_or(HairLoadError::Parse("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, HairLoadError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, HairLoadError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, HairLoadError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(thickness: bool) -> Vec<u8> {
+        let flags = HAS_SEGMENTS | HAS_POINTS | if thickness { HAS_THICKNESS } else { 0 };
+        let mut out = b"HAIR".to_vec();
+        for v in [2u32, 5, flags, 1] {
+            out.extend(v.to_le_bytes());
+        }
+        out.extend(0.5f32.to_le_bytes());
+        out.resize(HEADER_SIZE, 0);
+
+        // Strands of two and one segments
+        out.extend(2u16.to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        for i in 0..5 {
+            for v in [i as f32, 0.0, 1.0] {
+                out.extend(v.to_le_bytes());
+            }
+        }
+        if thickness {
+            for t in [0.1f32, 0.05, 0.0, 0.25, 0.25] {
+                out.extend(t.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn parse_model() {
+        let model = HairModel::parse(&file(true)).unwrap();
+        assert_eq!(2, model.strands.len());
+        assert_eq!(3, model.strands[0].points.len());
+        assert_eq!(Point::new(3.0, 0.0, 1.0), model.strands[1].points[0]);
+        assert_eq!(vec![0.25, 0.25], model.strands[1].thickness);
+        assert_eq!(3, model.curves(CurveMode::Flat).len());
+
+        let model = HairModel::parse(&file(false)).unwrap();
+        assert_eq!(vec![0.5; 3], model.strands[0].thickness);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut data = file(true);
+        data.truncate(data.len() - 2);
+        assert!(matches!(
+            HairModel::parse(&data),
+            Err(HairLoadError::Parse(_))
+        ));
+        assert!(matches!(
+            HairModel::parse(b"VOX "),
+            Err(HairLoadError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,328 @@
+use super::{Bounded, Intersection, Shape, Triangle};
+use crate::{
+    geo::{Bounds, Point, Ray, SlabRay, Vector},
+    Float,
+};
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    DynamicImage, ImageError, ImageResult,
+};
+use std::path::Path;
+
+/// A terrain surface over a regular grid of elevations.
+///
+/// The grid has `columns` samples along `x` and `rows` along `z`, and each
+/// square cell between four neighbouring samples is split into two
+/// triangles. Rays walk the cells they pass over in order with a 2D DDA and
+/// only test the triangles there, so a terrain of millions of triangles
+/// costs about as much per ray as its width in cells, and takes one float
+/// per sample instead of a mesh's vertices and indices.
+///
+/// Sample `(x, z)` is at `origin + (x * cell_size, height, z * cell_size)`.
+/// Normals are those of the triangles, and always face up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightfield {
+    columns: u32,
+    rows: u32,
+    heights: Vec<Float>,
+    origin: Point,
+    cell_size: Float,
+    // Lowest and highest sample
+    range: (Float, Float),
+}
+
+impl Heightfield {
+    /// Creates a heightfield from the given heights, listed a row at a time
+    /// (`x` varying fastest), with its first sample at the origin and cells
+    /// of size 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two columns or rows, `heights` is the
+    /// wrong length, or any height isn't finite.
+    pub fn new(columns: u32, rows: u32, heights: Vec<Float>) -> Self {
+        if columns < 2 || rows < 2 {
+            panic!("Heightfield must be at least 2x2, got {}x{}", columns, rows);
+        }
+        let expected = columns as usize * rows as usize;
+        if heights.len() != expected {
+            panic!("Expected {} heights, got {}", expected, heights.len());
+        }
+        if !heights.iter().all(|h| h.is_finite()) {
+            panic!("Heights must be finite");
+        }
+        let range = heights
+            .iter()
+            .fold((Float::INFINITY, Float::NEG_INFINITY), |(lo, hi), &h| {
+                (lo.min(h), hi.max(h))
+            });
+        Self {
+            columns,
+            rows,
+            heights,
+            origin: Point::ORIGIN,
+            cell_size: 1.0,
+            range,
+        }
+    }
+
+    /// Creates a heightfield from an image's brightness, one sample per
+    /// pixel. Black is height 0 and white is `scale`; image rows run along
+    /// `z`.
+    ///
+    /// Fails if the image is smaller than 2x2.
+    pub fn from_image(image: &DynamicImage, scale: Float) -> ImageResult<Self> {
+        let luma = image.to_luma32f();
+        let (columns, rows) = luma.dimensions();
+        if columns < 2 || rows < 2 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let heights = luma.pixels().map(|p| p.0[0] as Float * scale).collect();
+        Ok(Self::new(columns, rows, heights))
+    }
+
+    /// Load an image and create a heightfield from it (see [`from_image`]).
+    ///
+    /// 16-bit grayscale PNGs are best, as 8 bits gives visible terraces.
+    ///
+    /// [`from_image`]: Self::from_image
+    pub fn load(path: impl AsRef<Path>, scale: Float) -> ImageResult<Self> {
+        Self::from_image(&image::open(path)?, scale)
+    }
+
+    /// Move and scale the heightfield so sample `(0, 0)` is above `origin`
+    /// and samples are `cell_size` apart. Heights are unchanged.
+    pub fn with_placement(mut self, origin: impl Into<Point>, cell_size: Float) -> Self {
+        self.origin = origin.into();
+        self.cell_size = cell_size;
+        self
+    }
+
+    /// The number of samples along `x` and `z`.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.columns, self.rows)
+    }
+
+    /// The world-space position of sample `(0, 0)` at height 0.
+    #[inline]
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// The world-space distance between neighbouring samples.
+    #[inline]
+    pub fn cell_size(&self) -> Float {
+        self.cell_size
+    }
+
+    /// The height of sample `(x, z)`.
+    #[inline]
+    pub fn height(&self, x: u32, z: u32) -> Float {
+        self.heights[z as usize * self.columns as usize + x as usize]
+    }
+
+    // The world-space position of sample `(x, z)`
+    #[inline]
+    fn sample(&self, x: u32, z: u32) -> Point {
+        self.origin
+            + Vector::new(
+                x as Float * self.cell_size,
+                self.height(x, z),
+                z as Float * self.cell_size,
+            )
+    }
+
+    // The two triangles of cell `(x, z)`, wound to face up
+    #[inline]
+    fn triangles(&self, x: u32, z: u32) -> [Triangle; 2] {
+        let p00 = self.sample(x, z);
+        let p10 = self.sample(x + 1, z);
+        let p01 = self.sample(x, z + 1);
+        let p11 = self.sample(x + 1, z + 1);
+        [Triangle::new(p00, p11, p10), Triangle::new(p00, p01, p11)]
+    }
+
+    // Walk the cells under the ray, nearest first, until `visit` returns
+    // `true`. A cell's triangles lie within its column, so the first cell
+    // with a hit has the nearest one.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        mut visit: impl FnMut(u32, u32) -> bool,
+    ) {
+        let slab_ray = SlabRay::from(ray);
+        let Some((t0, t1)) = self.bounds().intersects_slab(&slab_ray, t_min, t_max) else {
+            return;
+        };
+
+        // Axis 0 is `x` and axis 1 is `z`
+        let cells = [self.columns - 1, self.rows - 1];
+        let origin = [self.origin.x, self.origin.z];
+        let ray_origin = [ray.origin.x, ray.origin.z];
+        let dir = [ray.direction.x, ray.direction.z];
+        let inv = [slab_ray.inv_direction.x, slab_ray.inv_direction.z];
+        let entry = ray.at(t0);
+        let entry = [entry.x, entry.z];
+
+        let mut cell = [0; 2];
+        let mut next = [Float::INFINITY; 2];
+        let mut delta = [0.0; 2];
+        let mut step = [0; 2];
+        let mut out = [0; 2];
+        for a in 0..2 {
+            let c = ((entry[a] - origin[a]) / self.cell_size) as i64;
+            cell[a] = c.clamp(0, cells[a] as i64 - 1);
+            if dir[a] > 0.0 {
+                let edge = origin[a] + (cell[a] + 1) as Float * self.cell_size;
+                next[a] = (edge - ray_origin[a]) * inv[a];
+                delta[a] = self.cell_size * inv[a];
+                step[a] = 1;
+                out[a] = cells[a] as i64;
+            } else if dir[a] < 0.0 {
+                let edge = origin[a] + cell[a] as Float * self.cell_size;
+                next[a] = (edge - ray_origin[a]) * inv[a];
+                delta[a] = -self.cell_size * inv[a];
+                step[a] = -1;
+                out[a] = -1;
+            }
+        }
+
+        loop {
+            if visit(cell[0] as u32, cell[1] as u32) {
+                return;
+            }
+            let a = match next[0] < next[1] {
+                true => 0,
+                false => 1,
+            };
+            if next[a] > t1 {
+                return;
+            }
+            cell[a] += step[a];
+            if cell[a] == out[a] {
+                return;
+            }
+            next[a] += delta[a];
+        }
+    }
+}
+
+impl Shape for Heightfield {
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        let mut nearest: Option<Intersection> = None;
+        self.traverse(ray, t_min, t_max, |x, z| {
+            for tri in self.triangles(x, z) {
+                let t_max = nearest.map_or(t_max, |isect| isect.t);
+                if let Some(isect) = tri.intersect(ray, t_min, t_max) {
+                    nearest = Some(isect);
+                }
+            }
+            nearest.is_some()
+        });
+        nearest
+    }
+
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        let mut found = false;
+        self.traverse(ray, t_min, t_max, |x, z| {
+            found = self
+                .triangles(x, z)
+                .iter()
+                .any(|tri| tri.intersects(ray, t_min, t_max));
+            found
+        });
+        found
+    }
+}
+
+impl Bounded for Heightfield {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        let (lo, hi) = self.range;
+        let far = self.sample(self.columns - 1, self.rows - 1);
+        Bounds::from_corners(
+            self.origin + Vector::new(0.0, lo, 0.0),
+            Point::new(far.x, self.origin.y + hi, far.z),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::CrossCheck;
+    use rand::prelude::*;
+
+    fn terrain() -> Heightfield {
+        let mut rng = StdRng::seed_from_u64(7);
+        let heights = (0..20 * 12).map(|_| rng.gen_range(-1.0..2.0)).collect();
+        Heightfield::new(20, 12, heights).with_placement([-5.0, 1.0, -3.0], 0.5)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let field = terrain();
+        let (columns, rows) = field.dimensions();
+        let triangles: Vec<Triangle> = (0..rows - 1)
+            .flat_map(|z| (0..columns - 1).map(move |x| (x, z)))
+            .flat_map(|(x, z)| field.triangles(x, z))
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(8);
+        let rays: Vec<Ray> = (0..1000)
+            .map(|_| {
+                let o = Point::new(
+                    rng.gen_range(-8.0..8.0),
+                    rng.gen_range(-2.0..6.0),
+                    rng.gen_range(-6.0..6.0),
+                );
+                let d = Vector::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                Ray::new(o, d)
+            })
+            .collect();
+        let check = CrossCheck::run(&field, triangles.as_slice(), &rays, 0.0, 1e-9);
+        assert!(check.hits > 100);
+        assert!(check.is_ok(), "{}", check);
+
+        // Straight down, and along the grid lines
+        let down = Ray::new(Point::new(-2.0, 10.0, 1.0), -Vector::Y_AXIS);
+        let along = Ray::new(Point::new(-10.0, 1.5, -1.0), Vector::X_AXIS);
+        let check = CrossCheck::run(&field, triangles.as_slice(), [&down, &along], 0.0, 1e-9);
+        assert!(check.is_ok(), "{}", check);
+    }
+
+    #[test]
+    fn flat() {
+        let field = Heightfield::new(3, 3, vec![2.0; 9]);
+        let ray = Ray::new(Point::new(0.7, 5.0, 1.2), Vector::new(0.1, -1.0, 0.2));
+        let isect = field.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        assert!((isect.t - 3.0).abs() < 1e-9);
+        assert!((isect.norm.y() - 1.0).abs() < 1e-9);
+        assert!(!field.intersects(&ray, 0.0, 2.9));
+        assert_eq!(
+            Bounds::from_corners([0.0, 2.0, 0.0].into(), [2.0, 2.0, 2.0].into()),
+            field.bounds()
+        );
+    }
+
+    #[test]
+    fn from_image() {
+        let img = image::GrayImage::from_raw(2, 2, vec![0, 255, 0, 51]).unwrap();
+        let field = Heightfield::from_image(&DynamicImage::ImageLuma8(img), 10.0).unwrap();
+        assert_eq!((2, 2), field.dimensions());
+        assert_eq!(10.0, field.height(1, 0));
+        assert!((field.height(1, 1) - 2.0).abs() < 1e-6);
+
+        let tiny = image::GrayImage::new(1, 4);
+        assert!(Heightfield::from_image(&DynamicImage::ImageLuma8(tiny), 1.0).is_err());
+    }
+}
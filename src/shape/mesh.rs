@@ -0,0 +1,870 @@
+use super::{Bounded, Intersection, Shape, Triangle, TriangleBvh};
+use crate::{
+    geo::{Bounds, CoordinateSystem, Coords, Point, Ray, Unit, Vector},
+    Float,
+};
+use std::{collections::HashMap, error::Error, fmt};
+
+/// Errors that can occur while building a [`Mesh`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshError {
+    /// A triangle refers to a vertex that doesn't exist.
+    IndexOutOfRange { triangle: usize, index: u32 },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexOutOfRange { triangle, index } => write!(
+                f,
+                "triangle {} refers to vertex {}, which doesn't exist",
+                triangle, index
+            ),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+/// A per-vertex tangent frame, as used by tangent-space normal maps.
+///
+/// Follows the MikkTSpace convention: the tangent is stored as a unit vector
+/// orthogonal to the vertex normal, along with a handedness sign. The
+/// bitangent is then reconstructed at shading time as:
+///
+/// ```text
+/// bitangent = sign * cross(normal, tangent)
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tangent {
+    pub dir: Unit,
+    pub sign: Float,
+}
+
+/// An indexed triangle mesh.
+///
+/// Stores per-vertex attributes in flat arrays, with each triangle given by a
+/// triple of indices into those arrays. Use [`Mesh::builder`] to construct.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    positions: Vec<Point>,
+    normals: Vec<Unit>,
+    uvs: Vec<Coords<Float>>,
+    tangents: Vec<Tangent>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// Create a new mesh builder from the given vertex positions and triangle
+    /// indices.
+    ///
+    /// See [`MeshBuilder::new`] for details.
+    pub fn builder(positions: Vec<Point>, indices: Vec<[u32; 3]>) -> MeshBuilder {
+        MeshBuilder::new(positions, indices)
+    }
+
+    /// The number of triangles in the mesh.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns `true` if the mesh has no triangles.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Estimated memory used by the mesh, in bytes.
+    pub fn memory_size(&self) -> usize {
+        use std::mem::size_of;
+        size_of::<Self>()
+            + self.positions.len() * size_of::<Point>()
+            + self.normals.len() * size_of::<Unit>()
+            + self.uvs.len() * size_of::<Coords<Float>>()
+            + self.tangents.len() * size_of::<Tangent>()
+            + self.indices.len() * size_of::<[u32; 3]>()
+    }
+
+    /// The vertex positions.
+    #[inline]
+    pub fn positions(&self) -> &[Point] {
+        &self.positions
+    }
+
+    /// The per-vertex shading normals.
+    #[inline]
+    pub fn normals(&self) -> &[Unit] {
+        &self.normals
+    }
+
+    /// The per-vertex texture coordinates. Empty if the mesh has no UVs.
+    #[inline]
+    pub fn uvs(&self) -> &[Coords<Float>] {
+        &self.uvs
+    }
+
+    /// The per-vertex tangents. Empty if the mesh has no UVs.
+    #[inline]
+    pub fn tangents(&self) -> &[Tangent] {
+        &self.tangents
+    }
+
+    /// The triangle indices.
+    #[inline]
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    /// The triangle at index `idx`.
+    #[inline]
+    pub fn triangle(&self, idx: usize) -> Triangle {
+        Triangle::from(self.indices[idx].map(|i| self.positions[i as usize]))
+    }
+
+    /// The shading normal at a point on triangle `idx`, given by its
+    /// barycentric coordinates: the vertex normals, blended.
+    pub fn shading_normal(&self, idx: usize, barycentric: [Float; 3]) -> Unit {
+        let [i0, i1, i2] = self.indices[idx].map(|i| i as usize);
+        let [b0, b1, b2] = barycentric;
+        let n = Vector::from(self.normals[i0]) * b0
+            + Vector::from(self.normals[i1]) * b1
+            + Vector::from(self.normals[i2]) * b2;
+        Unit::try_from(n).unwrap_or(self.normals[i0])
+    }
+
+    /// Subdivide the mesh once with Loop's scheme.
+    ///
+    /// Each triangle is split into four, and every vertex is moved towards a
+    /// weighted average of its neighbours, so repeated subdivision converges
+    /// to a smooth surface. Vertices on the boundary of an open mesh are
+    /// smoothed using only their neighbours along it, so the boundary becomes
+    /// a smooth curve without being pulled inwards by the interior. Vertices
+    /// split along UV seams count as boundaries too, which keeps the seams
+    /// from tearing apart.
+    ///
+    /// UVs are interpolated linearly. Normals are recomputed from the new
+    /// faces, replacing any set explicitly.
+    ///
+    /// See: <https://www.microsoft.com/en-us/research/publication/smooth-subdivision-surfaces-based-on-triangles/>
+    pub fn subdivide(&self) -> Mesh {
+        let pos = |i: u32| Vector::from(self.positions[i as usize]);
+
+        // Each edge, and the vertices opposite it. Edges with one opposite
+        // vertex are on the boundary.
+        let mut edge_index: HashMap<(u32, u32), usize> = HashMap::new();
+        let mut edges: Vec<((u32, u32), Vec<u32>)> = Vec::new();
+        for tri in &self.indices {
+            for k in 0..3 {
+                let (a, b, c) = (tri[k], tri[(k + 1) % 3], tri[(k + 2) % 3]);
+                let key = (a.min(b), a.max(b));
+                let idx = *edge_index.entry(key).or_insert_with(|| {
+                    edges.push((key, Vec::new()));
+                    edges.len() - 1
+                });
+                edges[idx].1.push(c);
+            }
+        }
+
+        let mut neighbours = vec![Vec::new(); self.positions.len()];
+        let mut boundary = vec![Vec::new(); self.positions.len()];
+        for &((a, b), ref opposite) in &edges {
+            neighbours[a as usize].push(b);
+            neighbours[b as usize].push(a);
+            if opposite.len() == 1 {
+                boundary[a as usize].push(b);
+                boundary[b as usize].push(a);
+            }
+        }
+
+        let even = (0..self.positions.len()).map(|i| {
+            let v = pos(i as u32);
+            match (boundary[i].as_slice(), neighbours[i].len()) {
+                (&[b0, b1], _) => v * 0.75 + (pos(b0) + pos(b1)) * 0.125,
+                // Corners and non-manifold vertices stay put
+                (&[_, ..], _) | (_, 0) => v,
+                (_, n) => {
+                    let beta = match n {
+                        3 => 3.0 / 16.0,
+                        n => 3.0 / (8.0 * n as Float),
+                    };
+                    let sum = neighbours[i]
+                        .iter()
+                        .fold(Vector::ZERO, |acc, &j| acc + pos(j));
+                    v * (1.0 - n as Float * beta) + sum * beta
+                }
+            }
+        });
+        let odd = edges
+            .iter()
+            .map(|&((a, b), ref opposite)| match opposite[..] {
+                [c, d] => (pos(a) + pos(b)) * 0.375 + (pos(c) + pos(d)) * 0.125,
+                _ => (pos(a) + pos(b)) * 0.5,
+            });
+        let positions = even.chain(odd).map(Point::from).collect();
+
+        let first_odd = self.positions.len() as u32;
+        let odd_vertex = |a: u32, b: u32| first_odd + edge_index[&(a.min(b), a.max(b))] as u32;
+        let indices = self
+            .indices
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (odd_vertex(a, b), odd_vertex(b, c), odd_vertex(c, a));
+                [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+            })
+            .collect();
+
+        let mut builder = Mesh::builder(positions, indices);
+        if !self.uvs.is_empty() {
+            let uv = |i: u32| self.uvs[i as usize];
+            let mid = edges.iter().map(|&((a, b), _)| (uv(a) + uv(b)) * 0.5);
+            builder.uvs(self.uvs.iter().copied().chain(mid).collect());
+        }
+        builder
+            .build()
+            .expect("subdivision only refers to vertices it creates")
+    }
+
+    /// Convert the mesh from the given coordinate system into world space.
+    ///
+    /// Reverses triangle winding (and tangent handedness) if the conversion
+    /// changes handedness, so that front faces stay front faces.
+    pub fn convert_from(&mut self, cs: CoordinateSystem) {
+        let m = cs.to_world();
+        for p in &mut self.positions {
+            *p = m * *p;
+        }
+        for n in &mut self.normals {
+            *n = cs.convert_normal(*n);
+        }
+        for t in &mut self.tangents {
+            t.dir = cs.convert_normal(t.dir);
+        }
+        if cs.flips_winding() {
+            for tri in &mut self.indices {
+                tri.swap(1, 2);
+            }
+            for t in &mut self.tangents {
+                t.sign = -t.sign;
+            }
+        }
+    }
+}
+
+/// Builder for creating [`Mesh`] instances.
+pub struct MeshBuilder {
+    positions: Vec<Point>,
+    normals: Option<Vec<Unit>>,
+    uvs: Vec<Coords<Float>>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl MeshBuilder {
+    /// Create a new mesh builder from the given vertex positions and triangle
+    /// indices.
+    ///
+    /// By default, the mesh has no texture coordinates and smooth vertex
+    /// normals are computed from the area-weighted average of adjacent face
+    /// normals.
+    pub fn new(positions: Vec<Point>, indices: Vec<[u32; 3]>) -> Self {
+        Self {
+            positions,
+            normals: None,
+            uvs: Vec::new(),
+            indices,
+        }
+    }
+
+    /// Set explicit per-vertex normals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of normals does not match the number of vertices.
+    pub fn normals(&mut self, normals: Vec<Unit>) -> &mut Self {
+        assert_eq!(self.positions.len(), normals.len(), "normal count mismatch");
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Set per-vertex texture coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of UVs does not match the number of vertices.
+    pub fn uvs(&mut self, uvs: Vec<Coords<Float>>) -> &mut Self {
+        assert_eq!(self.positions.len(), uvs.len(), "uv count mismatch");
+        self.uvs = uvs;
+        self
+    }
+
+    /// Creates a new mesh from this builder.
+    ///
+    /// If the mesh has texture coordinates, MikkTSpace tangents are
+    /// generated here, so the cost is paid once at load time rather than per
+    /// shading point. Vertices whose triangles need different tangents, such
+    /// as along a mirrored UV seam, are split, so the mesh can end up with
+    /// more vertices than it was given.
+    ///
+    /// Fails if any triangle refers to a vertex index past the end of the
+    /// positions.
+    pub fn build(&self) -> Result<Mesh, MeshError> {
+        let count = self.positions.len();
+        for (triangle, tri) in self.indices.iter().enumerate() {
+            if let Some(&index) = tri.iter().find(|&&i| i as usize >= count) {
+                return Err(MeshError::IndexOutOfRange { triangle, index });
+            }
+        }
+
+        let mut normals = match &self.normals {
+            Some(normals) => normals.clone(),
+            None => self.smooth_normals(),
+        };
+        let mut positions = self.positions.clone();
+        let mut uvs = self.uvs.clone();
+        let mut indices = self.indices.clone();
+        let tangents = match uvs.is_empty() {
+            true => Vec::new(),
+            false => generate_tangents(&mut positions, &mut normals, &mut uvs, &mut indices),
+        };
+
+        Ok(Mesh {
+            positions,
+            normals,
+            uvs,
+            tangents,
+            indices,
+        })
+    }
+
+    fn smooth_normals(&self) -> Vec<Unit> {
+        let mut accum = vec![Vector::ZERO; self.positions.len()];
+        for tri in &self.indices {
+            let [p0, p1, p2] = tri.map(|i| self.positions[i as usize]);
+            // Unnormalized cross product is proportional to the face area
+            let face_norm = (p1 - p0).cross(p2 - p0);
+            for &i in tri {
+                accum[i as usize] += face_norm;
+            }
+        }
+        accum
+            .into_iter()
+            .map(|n| Unit::try_from(n).unwrap_or(Unit::Z_AXIS))
+            .collect()
+    }
+}
+
+/// The result of intersecting a ray with a [`MeshBvh`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshHit {
+    /// The hit, with the triangle's geometric normal.
+    pub isect: Intersection,
+    /// Index of the triangle hit.
+    pub triangle: usize,
+    /// Barycentric coordinates of the hit, with respect to each of the
+    /// triangle's vertices in turn.
+    pub barycentric: [Float; 3],
+    /// The interpolated vertex normal, on the same side as the geometric
+    /// normal.
+    pub shading_norm: Unit,
+}
+
+/// A [`Mesh`] with a hierarchy over its triangles, for intersecting rays
+/// with.
+///
+/// As a [`Shape`], hits report the interpolated vertex normal rather than
+/// the flat triangle normal, so low-poly meshes shade smoothly instead of
+/// showing their facets. Use [`hit`] for both.
+///
+/// [`hit`]: Self::hit
+#[derive(Debug)]
+pub struct MeshBvh {
+    mesh: Mesh,
+    bvh: TriangleBvh,
+}
+
+impl MeshBvh {
+    /// Build a hierarchy over the mesh's triangles.
+    pub fn new(mesh: Mesh) -> Self {
+        let triangles = (0..mesh.len()).map(|i| mesh.triangle(i)).collect();
+        Self {
+            bvh: TriangleBvh::new(triangles),
+            mesh,
+        }
+    }
+
+    /// The mesh.
+    #[inline]
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    /// Ray intersection test, returning the triangle hit and both its
+    /// geometric and shading normals.
+    pub fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<MeshHit> {
+        let (triangle, isect) = self.bvh.intersect_indexed(ray, t_min, t_max)?;
+        let barycentric = self.mesh.triangle(triangle).barycentric(isect.point);
+        let shading_norm = self.mesh.shading_normal(triangle, barycentric);
+        // Vertex normals can disagree with the face near silhouettes; keep
+        // the shading normal on the side rays actually leave from
+        let shading_norm = match isect.norm.dot(shading_norm) < 0.0 {
+            true => -shading_norm,
+            false => shading_norm,
+        };
+        Some(MeshHit {
+            isect,
+            triangle,
+            barycentric,
+            shading_norm,
+        })
+    }
+}
+
+impl Shape for MeshBvh {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        let hit = self.hit(ray, t_min, t_max)?;
+        Some(Intersection {
+            norm: hit.shading_norm,
+            ..hit.isect
+        })
+    }
+
+    #[inline]
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        self.bvh.intersects(ray, t_min, t_max)
+    }
+}
+
+impl Bounded for MeshBvh {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.bvh.bounds()
+    }
+}
+
+// The mesh as MikkTSpace sees it, collecting the tangent it generates for
+// each triangle corner
+struct MikkGeometry<'a> {
+    positions: &'a [Point],
+    normals: &'a [Unit],
+    uvs: &'a [Coords<Float>],
+    indices: &'a [[u32; 3]],
+    corners: Vec<Option<[f32; 4]>>,
+}
+
+impl MikkGeometry<'_> {
+    #[inline]
+    fn vertex(&self, face: usize, vert: usize) -> usize {
+        self.indices[face][vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for MikkGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        <[Float; 3]>::from(self.positions[self.vertex(face, vert)]).map(|c| c as f32)
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        <[Float; 3]>::from(self.normals[self.vertex(face, vert)]).map(|c| c as f32)
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let uv = self.uvs[self.vertex(face, vert)];
+        [uv.x as f32, uv.y as f32]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.corners[face * 3 + vert] = Some(tangent);
+    }
+}
+
+/// Generate MikkTSpace tangents, the frames that DCC tools bake normal maps
+/// against, using the reference implementation.
+///
+/// MikkTSpace gives each triangle corner its own tangent. Corners of a
+/// vertex usually agree, but where they don't, such as along the seam of a
+/// mirrored UV layout, the vertex is split, copying its position, normal
+/// and UVs and pointing the disagreeing corners at the copy. Corners
+/// without a usable tangent get an arbitrary frame around the normal.
+///
+/// See: <http://www.mikktspace.com/>
+fn generate_tangents(
+    positions: &mut Vec<Point>,
+    normals: &mut Vec<Unit>,
+    uvs: &mut Vec<Coords<Float>>,
+    indices: &mut [[u32; 3]],
+) -> Vec<Tangent> {
+    let mut geometry = MikkGeometry {
+        positions,
+        normals,
+        uvs,
+        indices,
+        corners: vec![None; indices.len() * 3],
+    };
+    bevy_mikktspace::generate_tangents(&mut geometry);
+    let corners = geometry.corners;
+
+    let mut tangents: Vec<Option<Tangent>> = vec![None; positions.len()];
+    // The vertex each original vertex's corners with a given tangent use
+    let mut splits: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+    for (corner, tangent) in corners.into_iter().enumerate() {
+        let vertex = &mut indices[corner / 3][corner % 3];
+        let n = normals[*vertex as usize];
+        let (tangent, key) = match tangent {
+            Some([x, y, z, w]) => {
+                match Unit::try_from(Vector::new(x as Float, y as Float, z as Float)) {
+                    Ok(dir) => {
+                        let sign = if w < 0.0 { -1.0 } else { 1.0 };
+                        (Tangent { dir, sign }, [x, y, z, w].map(f32::to_bits))
+                    }
+                    Err(_) => (fallback_tangent(n), [0; 4]),
+                }
+            }
+            None => (fallback_tangent(n), [0; 4]),
+        };
+
+        let original = *vertex;
+        match tangents[original as usize] {
+            None => {
+                tangents[original as usize] = Some(tangent);
+                splits.insert((original, key), original);
+            }
+            Some(_) => {
+                *vertex = *splits.entry((original, key)).or_insert_with(|| {
+                    let i = original as usize;
+                    positions.push(positions[i]);
+                    normals.push(normals[i]);
+                    uvs.push(uvs[i]);
+                    tangents.push(Some(tangent));
+                    (positions.len() - 1) as u32
+                });
+            }
+        }
+    }
+
+    tangents
+        .into_iter()
+        .zip(normals.iter())
+        .map(|(t, &n)| t.unwrap_or_else(|| fallback_tangent(n)))
+        .collect()
+}
+
+// An arbitrary frame around `n`, for vertices MikkTSpace couldn't handle or
+// no triangle uses
+fn fallback_tangent(n: Unit) -> Tangent {
+    Tangent {
+        dir: arbitrary_tangent(n.into()),
+        sign: 1.0,
+    }
+}
+
+// Any unit vector orthogonal to `n`.
+fn arbitrary_tangent(n: Vector) -> Unit {
+    let axis = match n.x.abs() > 0.9 {
+        true => Vector::Y_AXIS,
+        false => Vector::X_AXIS,
+    };
+    n.cross(axis).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn quad(flip_v: bool) -> Mesh {
+        let positions = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        let v = |v: Float| if flip_v { 1.0 - v } else { v };
+        let uvs = vec![
+            Coords::new(0.0, v(0.0)),
+            Coords::new(1.0, v(0.0)),
+            Coords::new(1.0, v(1.0)),
+            Coords::new(0.0, v(1.0)),
+        ];
+        Mesh::builder(positions, vec![[0, 1, 2], [0, 2, 3]])
+            .uvs(uvs)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn convert_left_handed() {
+        use crate::geo::{Handedness, UpAxis};
+
+        let mut mesh = quad(false);
+        mesh.convert_from(CoordinateSystem::new(UpAxis::Y, Handedness::Left));
+        assert_eq!([0, 2, 1], mesh.indices()[0]);
+        for (&n, t) in mesh.normals().iter().zip(mesh.tangents()) {
+            assert_relative_eq!(-Vector::Z_AXIS, n.into());
+            assert_eq!(-1.0, t.sign);
+        }
+    }
+
+    fn octahedron() -> Mesh {
+        let positions = [
+            Vector::X_AXIS,
+            Vector::Y_AXIS,
+            Vector::Z_AXIS,
+            -Vector::X_AXIS,
+            -Vector::Y_AXIS,
+            -Vector::Z_AXIS,
+        ];
+        let indices = vec![
+            [0, 1, 2],
+            [1, 3, 2],
+            [3, 4, 2],
+            [4, 0, 2],
+            [1, 0, 5],
+            [3, 1, 5],
+            [4, 3, 5],
+            [0, 4, 5],
+        ];
+        Mesh::builder(positions.map(Point::from).to_vec(), indices)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn interpolated_normals() {
+        let bvh = MeshBvh::new(octahedron());
+        // Aim close to the +x vertex
+        let target = Point::new(0.9, 0.05, 0.05);
+        let ray = Ray::new(
+            Point::new(3.0, 0.05, 0.05),
+            target - Point::new(3.0, 0.05, 0.05),
+        );
+        let hit = bvh.hit(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(0, hit.triangle);
+        assert_relative_eq!(
+            Vector::splat(1.0 / (3.0 as Float).sqrt()),
+            hit.isect.norm.into()
+        );
+        assert!(hit.shading_norm.x() > 0.9, "{:?}", hit.shading_norm);
+
+        let isect = bvh.intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(hit.shading_norm, isect.norm);
+    }
+
+    #[test]
+    fn loop_subdivision() {
+        let mesh = octahedron();
+        let fine = mesh.subdivide();
+        assert_eq!(32, fine.len());
+        // Six vertices plus one per edge
+        assert_eq!(18, fine.positions().len());
+        // Loop approximates rather than interpolates, so the surface shrinks:
+        // corners are averaged with their neighbours, and edge points sit
+        // between the edge and the opposite corners
+        let radius = |i: usize| Vector::from(fine.positions()[i]).len();
+        assert_relative_eq!(0.625, radius(0));
+        assert_relative_eq!(0.375 * (2.0 as Float).sqrt(), radius(6));
+
+        // Boundary edges only use the boundary, so they stay straight
+        let quad = quad(false).subdivide();
+        assert_eq!(8, quad.len());
+        assert_eq!(quad.uvs().len(), quad.positions().len());
+        assert!(quad.positions().iter().all(|p| p.z == 0.0));
+        assert!(quad.positions().contains(&Point::new(0.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn smooth_normals() {
+        let mesh = quad(false);
+        for &n in mesh.normals() {
+            assert_relative_eq!(Vector::Z_AXIS, n.into());
+        }
+    }
+
+    #[test]
+    fn tangents_follow_uv() {
+        let mesh = quad(false);
+        for t in mesh.tangents() {
+            assert_relative_eq!(Vector::X_AXIS, t.dir.into());
+            assert_eq!(1.0, t.sign);
+        }
+    }
+
+    #[test]
+    fn tangents_mirrored_uv() {
+        let mesh = quad(true);
+        for t in mesh.tangents() {
+            assert_relative_eq!(Vector::X_AXIS, t.dir.into());
+            assert_eq!(-1.0, t.sign);
+        }
+    }
+
+    #[test]
+    fn tangents_tiny_uv() {
+        // A texture tiled a billion times across the quad
+        let mesh = quad(false);
+        let uvs = mesh.uvs().iter().map(|&uv| uv * 1e-9).collect();
+        let positions = mesh.positions().to_vec();
+        let mesh = Mesh::builder(positions, vec![[0, 1, 2], [0, 2, 3]])
+            .uvs(uvs)
+            .build()
+            .unwrap();
+        for t in mesh.tangents() {
+            assert_relative_eq!(Vector::X_AXIS, t.dir.into());
+            assert_eq!(1.0, t.sign);
+        }
+    }
+
+    #[test]
+    fn tangents_match_reference() {
+        // The cube from the MikkTSpace regression tests, each side a fan of
+        // four triangles around its center, and the reference
+        // implementation's tangents for the first triangle of each side
+        let sides: [[([Float; 3], [Float; 2]); 5]; 6] = [
+            [
+                ([1.0, -1.0, 1.0], [0.0, 0.0]),
+                ([1.0, -1.0, -1.0], [0.0, 1.0]),
+                ([1.0, 1.0, -1.0], [1.0, 1.0]),
+                ([1.0, 1.0, 1.0], [1.0, 0.0]),
+                ([1.0, 0.0, 0.0], [0.5, 0.5]),
+            ],
+            [
+                ([-1.0, 1.0, 1.0], [1.0, 0.0]),
+                ([-1.0, 1.0, -1.0], [1.0, 1.0]),
+                ([-1.0, -1.0, -1.0], [0.0, 1.0]),
+                ([-1.0, -1.0, 1.0], [0.0, 0.0]),
+                ([-1.0, 0.0, 0.0], [0.5, 0.5]),
+            ],
+            [
+                ([1.0, 1.0, 1.0], [0.0, 0.0]),
+                ([1.0, 1.0, -1.0], [0.0, 1.0]),
+                ([-1.0, 1.0, -1.0], [0.0, 1.0]),
+                ([-1.0, 1.0, 1.0], [0.0, 0.0]),
+                ([0.0, 1.0, 0.0], [0.0, 0.5]),
+            ],
+            [
+                ([-1.0, -1.0, 1.0], [0.0, 0.0]),
+                ([-1.0, -1.0, -1.0], [0.0, 1.0]),
+                ([1.0, -1.0, -1.0], [0.0, 1.0]),
+                ([1.0, -1.0, 1.0], [0.0, 0.0]),
+                ([0.0, -1.0, 0.0], [0.0, 0.5]),
+            ],
+            [
+                ([-1.0, 1.0, 1.0], [0.0, 0.0]),
+                ([-1.0, -1.0, 1.0], [0.0, 1.0]),
+                ([1.0, -1.0, 1.0], [1.0, 1.0]),
+                ([1.0, 1.0, 1.0], [1.0, 0.0]),
+                ([0.0, 0.0, 1.0], [0.5, 0.5]),
+            ],
+            [
+                ([1.0, 1.0, -1.0], [1.0, 0.0]),
+                ([1.0, -1.0, -1.0], [1.0, 1.0]),
+                ([-1.0, -1.0, -1.0], [0.0, 1.0]),
+                ([-1.0, 1.0, -1.0], [0.0, 0.0]),
+                ([0.0, 0.0, -1.0], [0.5, 0.5]),
+            ],
+        ];
+        let (a, b) = (0.40824825, 0.81649655);
+        let expected: [[([Float; 3], Float); 3]; 6] = [
+            [
+                ([a, b, a], -1.0),
+                ([a, b, -a], -1.0),
+                ([0.0, 1.0, 0.0], -1.0),
+            ],
+            [([a, b, -a], 1.0), ([a, b, a], 1.0), ([0.0, 1.0, 0.0], 1.0)],
+            [([1.0, 0.0, 0.0], -1.0); 3],
+            [
+                ([-a, b, a], 1.0),
+                ([-a, b, -a], 1.0),
+                ([1.0, 0.0, 0.0], -1.0),
+            ],
+            [
+                ([b, a, a], -1.0),
+                ([b, -a, a], -1.0),
+                ([1.0, 0.0, 0.0], -1.0),
+            ],
+            [([b, -a, a], 1.0), ([b, a, a], 1.0), ([1.0, 0.0, 0.0], 1.0)],
+        ];
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+        for side in sides {
+            let base = positions.len() as u32;
+            for (i, j) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+                indices.push([base + i, base + j, base + 4]);
+            }
+            for (p, [u, v]) in side {
+                let p = Vector::from(p);
+                positions.push(Point::from(p / 2.0));
+                normals.push(p.normalize());
+                uvs.push(Coords::new(u, v));
+            }
+        }
+        let mesh = Mesh::builder(positions, indices)
+            .normals(normals)
+            .uvs(uvs)
+            .build()
+            .unwrap();
+
+        for (side, expected) in expected.iter().enumerate() {
+            let tri = mesh.indices()[side * 4];
+            for (&vertex, &(dir, sign)) in tri.iter().zip(expected) {
+                let t = mesh.tangents()[vertex as usize];
+                assert_relative_eq!(Vector::from(dir), t.dir.into(), epsilon = 1e-6);
+                assert_eq!(sign, t.sign, "side {}", side);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_split_vertices() {
+        // Two triangles sharing an edge, one mirrored in `u`, so the shared
+        // vertices need a tangent of each handedness
+        let positions = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+        ];
+        let uvs = vec![
+            Coords::new(0.0, 0.0),
+            Coords::new(0.0, 1.0),
+            Coords::new(1.0, 0.0),
+            Coords::new(1.0, 0.0),
+        ];
+        let mesh = Mesh::builder(positions, vec![[0, 2, 1], [0, 1, 3]])
+            .uvs(uvs)
+            .build()
+            .unwrap();
+        assert_eq!(6, mesh.positions().len());
+        for (tri, sign) in mesh.indices().iter().zip([1.0, -1.0]) {
+            for &i in tri {
+                let t = mesh.tangents()[i as usize];
+                assert_eq!(sign, t.sign);
+                assert_relative_eq!(Vector::X_AXIS * sign, t.dir.into(), epsilon = 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_indices() {
+        let positions = vec![Point::ORIGIN; 3];
+        let mesh = Mesh::builder(positions, vec![[0, 1, 2], [0, 3, 1]]).build();
+        assert_eq!(
+            MeshError::IndexOutOfRange {
+                triangle: 1,
+                index: 3
+            },
+            mesh.unwrap_err()
+        );
+    }
+}
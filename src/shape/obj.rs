@@ -0,0 +1,256 @@
+use super::Mesh;
+use crate::{
+    geo::{Coords, Point, Unit, Vector},
+    Float,
+};
+use std::{collections::HashMap, error::Error, fmt, fs, io, path::Path};
+
+/// Errors that can occur while loading a Wavefront OBJ file.
+#[derive(Debug)]
+pub enum ObjLoadError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// A line is malformed.
+    Parse { line: usize, msg: &'static str },
+}
+
+impl fmt::Display for ObjLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read obj file: {}", e),
+            Self::Parse { line, msg } => write!(f, "invalid obj file, line {}: {}", line, msg),
+        }
+    }
+}
+
+impl Error for ObjLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjLoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// A face corner's position, texture coordinate and normal indices, 0-based
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl Mesh {
+    /// Load a mesh from a Wavefront `.obj` file.
+    ///
+    /// See [`parse_obj`](Self::parse_obj).
+    pub fn load_obj(path: impl AsRef<Path>) -> Result<Self, ObjLoadError> {
+        Self::parse_obj(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a mesh from the contents of a Wavefront `.obj` file.
+    ///
+    /// All the faces in the file go into one mesh, with polygons split into
+    /// fans of triangles. Vertices are split wherever faces give the same
+    /// position different texture coordinates or normals, the way DCC tools
+    /// expect. If the faces have texture coordinates, tangents are generated
+    /// as the mesh is built, matching the ones normal maps are baked against;
+    /// if they don't have normals, smooth ones are computed.
+    ///
+    /// Groups, smoothing groups and materials are ignored. Coordinates are
+    /// kept as stored; most files are Y-up and right-handed.
+    pub fn parse_obj(src: &str) -> Result<Self, ObjLoadError> {
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+
+        let mut corners: HashMap<Corner, u32> = HashMap::new();
+        let mut vertices: Vec<Corner> = Vec::new();
+        let mut indices = Vec::new();
+        let mut first_layout = None;
+
+        for (idx, line) in src.lines().enumerate() {
+            let line_no = idx + 1;
+            let err = |msg| ObjLoadError::Parse { line: line_no, msg };
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+
+            match words.next() {
+                Some("v") => {
+                    let [x, y, z] = floats(&mut words).ok_or_else(|| err("bad vertex"))?;
+                    positions.push(Point::new(x, y, z));
+                }
+                Some("vt") => {
+                    let [u, v] = floats(&mut words).ok_or_else(|| err("bad texture coordinate"))?;
+                    uvs.push(Coords::new(u, v));
+                }
+                Some("vn") => {
+                    let [x, y, z] = floats(&mut words).ok_or_else(|| err("bad normal"))?;
+                    let n = Unit::try_from(Vector::new(x, y, z))
+                        .map_err(|_| err("zero-length normal"))?;
+                    normals.push(n);
+                }
+                Some("f") => {
+                    let face = words
+                        .map(|word| {
+                            corner(word, positions.len(), uvs.len(), normals.len())
+                                .ok_or_else(|| err("bad face vertex"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if face.len() < 3 {
+                        return Err(err("face has fewer than 3 vertices"));
+                    }
+                    // Vertex attributes have to be all or nothing
+                    let layout = (face[0].1.is_some(), face[0].2.is_some());
+                    if face
+                        .iter()
+                        .any(|c| (c.1.is_some(), c.2.is_some()) != layout)
+                    {
+                        return Err(err("face vertices have different attributes"));
+                    }
+                    if *first_layout.get_or_insert(layout) != layout {
+                        return Err(err("faces have different vertex attributes"));
+                    }
+                    let ids = face
+                        .into_iter()
+                        .map(|c| {
+                            *corners.entry(c).or_insert_with(|| {
+                                vertices.push(c);
+                                vertices.len() as u32 - 1
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    for k in 1..ids.len() - 1 {
+                        indices.push([ids[0], ids[k], ids[k + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (has_uvs, has_normals) = first_layout.unwrap_or_default();
+        let mut builder = Mesh::builder(vertices.iter().map(|c| positions[c.0]).collect(), indices);
+        if has_uvs {
+            builder.uvs(
+                vertices
+                    .iter()
+                    .filter_map(|c| c.1)
+                    .map(|i| uvs[i])
+                    .collect(),
+            );
+        }
+        if has_normals {
+            builder.normals(
+                vertices
+                    .iter()
+                    .filter_map(|c| c.2)
+                    .map(|i| normals[i])
+                    .collect(),
+            );
+        }
+        Ok(builder
+            .build()
+            .expect("face indices are checked as they're parsed"))
+    }
+}
+
+// Parse the next `N` words as finite floats. Any further words, such as a
+// vertex's optional `w`, are ignored.
+fn floats<'a, const N: usize>(words: &mut impl Iterator<Item = &'a str>) -> Option<[Float; N]> {
+    let mut vals = [0.0; N];
+    for v in &mut vals {
+        *v = words
+            .next()?
+            .parse()
+            .ok()
+            .filter(|v: &Float| v.is_finite())?;
+    }
+    Some(vals)
+}
+
+// Parse a face vertex, `v`, `v/vt`, `v//vn` or `v/vt/vn`, resolving its
+// 1-based (or negative, relative) indices against the counts so far
+fn corner(word: &str, positions: usize, uvs: usize, normals: usize) -> Option<Corner> {
+    let resolve = |s: &str, count: usize| -> Option<usize> {
+        let i: i64 = s.parse().ok()?;
+        let i = match i {
+            i if i > 0 => i - 1,
+            i if i < 0 => count as i64 + i,
+            _ => return None,
+        };
+        (0..count as i64).contains(&i).then_some(i as usize)
+    };
+    let mut parts = word.split('/');
+    let v = resolve(parts.next()?, positions)?;
+    let vt = match parts.next() {
+        None | Some("") => None,
+        Some(s) => Some(resolve(s, uvs)?),
+    };
+    let vn = match parts.next() {
+        None => None,
+        Some(s) => Some(resolve(s, normals)?),
+    };
+    match parts.next() {
+        Some(_) => None,
+        None => Some((v, vt, vn)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const QUAD: &str = "\
+# A unit quad, facing +z
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 2/2 3/3 4/4
+";
+
+    #[test]
+    fn tangents_on_load() {
+        let mesh = Mesh::parse_obj(QUAD).unwrap();
+        assert_eq!(2, mesh.len());
+        assert_eq!(4, mesh.positions().len());
+        assert_eq!(4, mesh.tangents().len());
+        for t in mesh.tangents() {
+            assert_relative_eq!(Vector::X_AXIS, t.dir.into());
+            assert_eq!(1.0, t.sign);
+        }
+    }
+
+    #[test]
+    fn split_seams() {
+        // The same positions with different normals become separate vertices
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nvn 0 0 -1\n\
+                   f 1//1 2//1 3//1\nf -3//2 -1//2 -2//2\n";
+        let mesh = Mesh::parse_obj(src).unwrap();
+        assert_eq!(6, mesh.positions().len());
+        assert!(mesh.tangents().is_empty());
+        assert_relative_eq!(-Vector::Z_AXIS, mesh.normals()[3].into());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse_err = |src| match Mesh::parse_obj(src) {
+            Err(ObjLoadError::Parse { line, .. }) => line,
+            other => panic!("{:?}", other.map(|m| m.len())),
+        };
+        assert_eq!(2, parse_err("v 0 0 0\nv 1 nan 0\n"));
+        assert_eq!(4, parse_err("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"));
+        assert_eq!(4, parse_err("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 0 2\n"));
+        assert_eq!(2, parse_err("v 0 0 0\nf 1 1\n"));
+        assert_eq!(
+            5,
+            parse_err("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3\n")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, Ray},
+    Float,
+};
+
+/// A shape with its normals reversed.
+///
+/// Normals are whatever the primitive's math produces: outwards for spheres,
+/// and by vertex winding for triangles. This turns them around, _e.g._ to
+/// point a light panel the other way without reordering its vertices, or to
+/// render the inside of a sphere enclosing the scene.
+#[derive(Debug, Default)]
+pub struct FlipNormals<S> {
+    pub shape: S,
+}
+
+impl<S> FlipNormals<S> {
+    /// Reverse the normals of the given shape.
+    pub fn new(shape: S) -> Self {
+        Self { shape }
+    }
+}
+
+impl<S: Shape> Shape for FlipNormals<S> {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.shape
+            .intersect(ray, t_min, t_max)
+            .map(Intersection::flipped)
+    }
+
+    #[inline]
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        self.shape.intersects(ray, t_min, t_max)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        self.shape.intersect_packet(rays, t_min, t_max, hits);
+        for hit in hits.iter_mut().flatten() {
+            *hit = hit.flipped();
+        }
+    }
+}
+
+impl<S: Bounded> Bounded for FlipNormals<S> {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.shape.bounds()
+    }
+}
+
+/// A shape that looks the same from both sides.
+///
+/// Normals always face the incoming ray, so thin geometry with no inside,
+/// like a quad used as a light panel or a leaf, shades and scatters light
+/// the same whichever side it's seen from. Solids shouldn't be two-sided:
+/// rays inside them would no longer be able to tell they're leaving.
+///
+/// To make every surface two-sided without wrapping them, see
+/// [`PathTracer::two_sided`] and [`Hacky::two_sided`].
+///
+/// [`PathTracer::two_sided`]: crate::integrator::PathTracer::two_sided
+/// [`Hacky::two_sided`]: crate::integrator::Hacky::two_sided
+#[derive(Debug, Default)]
+pub struct TwoSided<S> {
+    pub shape: S,
+}
+
+impl<S> TwoSided<S> {
+    /// Make the given shape two-sided.
+    pub fn new(shape: S) -> Self {
+        Self { shape }
+    }
+}
+
+impl<S: Shape> Shape for TwoSided<S> {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.shape
+            .intersect(ray, t_min, t_max)
+            .map(|isect| isect.facing(ray.direction))
+    }
+
+    #[inline]
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        self.shape.intersects(ray, t_min, t_max)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray],
+        t_min: Float,
+        t_max: Float,
+        hits: &mut [Option<Intersection>],
+    ) {
+        self.shape.intersect_packet(rays, t_min, t_max, hits);
+        for (ray, hit) in rays.iter().zip(hits) {
+            if let Some(isect) = hit {
+                *isect = isect.facing(ray.direction);
+            }
+        }
+    }
+}
+
+impl<S: Bounded> Bounded for TwoSided<S> {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        self.shape.bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geo::{Point, Unit, Vector},
+        shape::Triangle,
+    };
+
+    #[test]
+    fn flip_and_two_sided() {
+        // Normal is +Z
+        let tri = || Triangle::new([-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]);
+        let front = Ray::new(Point::new(0.0, 0.0, 1.0), -Vector::Z_AXIS);
+        let back = Ray::new(Point::new(0.0, 0.0, -1.0), Vector::Z_AXIS);
+        let norm = |s: &dyn Shape, ray: &Ray| s.intersect(ray, 0.0, Float::INFINITY).unwrap().norm;
+
+        assert_eq!(Unit::Z_AXIS, norm(&tri(), &front));
+        assert_eq!(Unit::Z_AXIS, norm(&tri(), &back));
+
+        let flipped = FlipNormals::new(tri());
+        assert_eq!(-Unit::Z_AXIS, norm(&flipped, &front));
+        assert_eq!(-Unit::Z_AXIS, norm(&flipped, &back));
+
+        let two_sided = TwoSided::new(tri());
+        assert_eq!(Unit::Z_AXIS, norm(&two_sided, &front));
+        assert_eq!(-Unit::Z_AXIS, norm(&two_sided, &back));
+
+        let rays = [front, back];
+        let mut hits = [None; 2];
+        two_sided.intersect_packet(&rays, 0.0, Float::INFINITY, &mut hits);
+        assert_eq!(-Unit::Z_AXIS, hits[1].unwrap().norm);
+    }
+}
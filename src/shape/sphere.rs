@@ -1,6 +1,6 @@
-use super::{Intersection, Shape};
+use super::{Bounded, Intersection, Shape};
 use crate::{
-    geo::{Point, Ray, Unit},
+    geo::{Bounds, Point, Ray, Unit, Vector},
     Float,
 };
 use std::{cmp::Ordering, mem};
@@ -28,6 +28,18 @@ impl Sphere {
         }
     }
 
+    /// The sphere's center.
+    #[inline]
+    pub const fn center(&self) -> Point {
+        self.center
+    }
+
+    /// The sphere's radius.
+    #[inline]
+    pub const fn radius(&self) -> Float {
+        self.radius
+    }
+
     fn solve_quadratic(a: Float, b: Float, c: Float) -> Option<(Float, Float)> {
         let discr = b.powi(2) - 4.0 * a * c;
         match discr.total_cmp(&0.0) {
@@ -86,17 +98,24 @@ impl Shape for Sphere {
     }
 }
 
+impl Bounded for Sphere {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        let r = Vector::new(self.radius, self.radius, self.radius);
+        Bounds::from_corners(self.center + -r, self.center + r)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::geo::Vector;
 
     #[test]
     fn intersect_two_points() {
         let s = Sphere::new(Point::new(10.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point::ORIGIN, Vector::X_AXIS);
 
-        assert_eq!(true, s.intersects(&ray, 0.0, Float::INFINITY));
+        assert!(s.intersects(&ray, 0.0, Float::INFINITY));
 
         let isect = s.intersect(&ray, 0.0, Float::INFINITY).unwrap();
         assert_eq!(Point::new(9.0, 0.0, 0.0), isect.point);
@@ -109,7 +128,7 @@ mod tests {
         let s = Sphere::new(Point::new(10.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point::ORIGIN, Vector::Y_AXIS);
 
-        assert_eq!(false, s.intersects(&ray, 0.0, Float::INFINITY));
+        assert!(!s.intersects(&ray, 0.0, Float::INFINITY));
         assert_eq!(None, s.intersect(&ray, 0.0, Float::INFINITY));
     }
 
@@ -118,10 +137,10 @@ mod tests {
         let s = Sphere::new(Point::new(10.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point::ORIGIN, Vector::X_AXIS);
 
-        assert_eq!(false, s.intersects(&ray, 0.0, 7.0));
+        assert!(!s.intersects(&ray, 0.0, 7.0));
         assert_eq!(None, s.intersect(&ray, 0.0, 7.0));
 
-        assert_eq!(false, s.intersects(&ray, 20.0, Float::INFINITY));
+        assert!(!s.intersects(&ray, 20.0, Float::INFINITY));
         assert_eq!(None, s.intersect(&ray, 20.0, Float::INFINITY));
     }
 }
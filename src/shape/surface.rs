@@ -1,5 +1,10 @@
-use super::{Intersection, Shape, Sphere, Triangle};
-use crate::{geo::Ray, Float};
+use super::{
+    Bounded, Curve, Heightfield, Intersection, Shape, SparseVoxelOctree, Sphere, Triangle,
+};
+use crate::{
+    geo::{Bounds, Ray},
+    Float,
+};
 
 /// A surface that supports ray-object intersection.
 ///
@@ -12,6 +17,9 @@ use crate::{geo::Ray, Float};
 pub enum Surface {
     Sphere(Sphere),
     Triangle(Triangle),
+    Voxels(SparseVoxelOctree),
+    Curve(Curve),
+    Heightfield(Heightfield),
 }
 
 impl Shape for Surface {
@@ -20,6 +28,9 @@ impl Shape for Surface {
         match self {
             Self::Sphere(s) => s.intersect(ray, t_min, t_max),
             Self::Triangle(t) => t.intersect(ray, t_min, t_max),
+            Self::Voxels(v) => v.intersect(ray, t_min, t_max),
+            Self::Curve(c) => c.intersect(ray, t_min, t_max),
+            Self::Heightfield(h) => h.intersect(ray, t_min, t_max),
         }
     }
 
@@ -28,6 +39,22 @@ impl Shape for Surface {
         match self {
             Self::Sphere(s) => s.intersects(ray, t_min, t_max),
             Self::Triangle(t) => t.intersects(ray, t_min, t_max),
+            Self::Voxels(v) => v.intersects(ray, t_min, t_max),
+            Self::Curve(c) => c.intersects(ray, t_min, t_max),
+            Self::Heightfield(h) => h.intersects(ray, t_min, t_max),
+        }
+    }
+}
+
+impl Bounded for Surface {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        match self {
+            Self::Sphere(s) => s.bounds(),
+            Self::Triangle(t) => t.bounds(),
+            Self::Voxels(v) => v.bounds(),
+            Self::Curve(c) => c.bounds(),
+            Self::Heightfield(h) => h.bounds(),
         }
     }
 }
@@ -43,3 +70,21 @@ impl From<Triangle> for Surface {
         Self::Triangle(triangle)
     }
 }
+
+impl From<SparseVoxelOctree> for Surface {
+    fn from(voxels: SparseVoxelOctree) -> Self {
+        Self::Voxels(voxels)
+    }
+}
+
+impl From<Curve> for Surface {
+    fn from(curve: Curve) -> Self {
+        Self::Curve(curve)
+    }
+}
+
+impl From<Heightfield> for Surface {
+    fn from(heightfield: Heightfield) -> Self {
+        Self::Heightfield(heightfield)
+    }
+}
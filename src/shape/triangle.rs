@@ -1,11 +1,336 @@
-use super::{Intersection, Shape};
-use crate::{geo::Ray, Float};
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, CoordinateSystem, Point, Ray, Unit},
+    math::{Float4, Vector4},
+    Float,
+};
 
-#[derive(Debug)]
-pub struct Triangle;
+/// A standalone triangle.
+///
+/// The geometric normal follows the right-hand rule over the vertices in the
+/// order given, _i.e._ counter-clockwise winding faces the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    vertices: [Point; 3],
+}
+
+impl Triangle {
+    /// Creates a new triangle with the given vertices.
+    pub fn new(p0: impl Into<Point>, p1: impl Into<Point>, p2: impl Into<Point>) -> Self {
+        Self {
+            vertices: [p0.into(), p1.into(), p2.into()],
+        }
+    }
+
+    /// The triangle's vertices.
+    #[inline]
+    pub const fn vertices(&self) -> [Point; 3] {
+        self.vertices
+    }
+
+    /// The triangle's area.
+    #[inline]
+    pub fn area(&self) -> Float {
+        let [p0, p1, p2] = self.vertices;
+        0.5 * (p1 - p0).cross(p2 - p0).len()
+    }
+
+    /// The barycentric coordinates of a point in the triangle's plane, with
+    /// respect to each vertex in turn.
+    ///
+    /// Coordinates are all in `[0, 1]` for points inside the triangle.
+    /// Returns NaNs for degenerate triangles.
+    pub fn barycentric(&self, point: Point) -> [Float; 3] {
+        let [p0, p1, p2] = self.vertices;
+        let v0 = p1 - p0;
+        let v1 = p2 - p0;
+        let v2 = point - p0;
+
+        let d00 = v0.dot(v0);
+        let d01 = v0.dot(v1);
+        let d11 = v1.dot(v1);
+        let d20 = v2.dot(v0);
+        let d21 = v2.dot(v1);
+        let inv_denom = (d00 * d11 - d01 * d01).recip();
+
+        let b1 = (d11 * d20 - d01 * d21) * inv_denom;
+        let b2 = (d00 * d21 - d01 * d20) * inv_denom;
+        [1.0 - b1 - b2, b1, b2]
+    }
+
+    /// Distance from a point in the triangle to its nearest edge.
+    ///
+    /// Each barycentric coordinate is the point's distance to the opposite
+    /// edge as a fraction of the altitude to that edge, so scaling by the
+    /// altitudes gives world-space distances.
+    pub fn edge_distance(&self, point: Point) -> Float {
+        let [p0, p1, p2] = self.vertices;
+        let double_area = 2.0 * self.area();
+        let edges = [p2 - p1, p0 - p2, p1 - p0];
+        self.barycentric(point)
+            .into_iter()
+            .zip(edges)
+            .map(|(b, edge)| b * double_area / edge.len())
+            .fold(Float::INFINITY, Float::min)
+    }
+
+    /// Convert the triangle from the given coordinate system into world
+    /// space, preserving which side is the front face.
+    pub fn convert_from(&self, cs: CoordinateSystem) -> Self {
+        let [p0, p1, p2] = self.vertices.map(|p| cs.convert_point(p));
+        match cs.flips_winding() {
+            true => Self::new(p0, p2, p1),
+            false => Self::new(p0, p1, p2),
+        }
+    }
+
+    // Möller-Trumbore. Returns `(t, b1, b2)` where `b1` and `b2` are the
+    // barycentric coordinates of the hit with respect to `p1` and `p2`.
+    //
+    // See: <https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm>
+    #[inline]
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<(Float, Float, Float)> {
+        let [p0, p1, p2] = self.vertices;
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+
+        // The determinant scales with the edges and the ray direction, so
+        // it's compared relative to them: parallel to within rounding error
+        // is a miss at any scale
+        let pvec = ray.direction.cross(e2);
+        let det = e1.dot(pvec);
+        let scale = e1.dot(e1) * e2.dot(e2) * ray.direction.dot(ray.direction);
+        if det * det <= Float::EPSILON * Float::EPSILON * scale {
+            return None;
+        }
+        let inv_det = det.recip();
+
+        let tvec = ray.origin - p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = tvec.cross(e1);
+        let b2 = ray.direction.dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(qvec) * inv_det;
+        match t_min <= t && t <= t_max {
+            true => Some((t, b1, b2)),
+            false => None,
+        }
+    }
+
+    pub(super) fn normal(&self) -> Option<Unit> {
+        let [p0, p1, p2] = self.vertices;
+        Unit::try_from((p1 - p0).cross(p2 - p0)).ok()
+    }
+}
 
 impl Shape for Triangle {
-    fn intersect(&self, _ray: &Ray, _t_min: Float, _t_max: Float) -> Option<Intersection> {
-        todo!()
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        let (t, _, _) = self.hit(ray, t_min, t_max)?;
+        let point = ray.at(t);
+        let norm = self.normal()?;
+        Some(Intersection { point, norm, t })
+    }
+
+    #[inline]
+    fn intersects(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        self.hit(ray, t_min, t_max).is_some()
+    }
+}
+
+impl Bounded for Triangle {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        let [p0, p1, p2] = self.vertices;
+        Bounds::from_corners(p0, p1).include(p2)
+    }
+}
+
+impl From<[Point; 3]> for Triangle {
+    #[inline]
+    fn from(vertices: [Point; 3]) -> Self {
+        Self { vertices }
+    }
+}
+
+/// Four triangles, laid out to test a ray against all of them at once.
+///
+/// Runs the same Möller-Trumbore test as [`Triangle`], with each vertex
+/// coordinate in its own [`Float4`] so the four triangles go through in
+/// lockstep. BVH leaves hold up to four triangles, so one packet covers a
+/// whole leaf. Enable the `simd` feature to run it on SIMD registers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Triangle4 {
+    p0: Vector4,
+    e1: Vector4,
+    e2: Vector4,
+}
+
+impl Triangle4 {
+    /// Pack up to four triangles. Missing lanes are degenerate, and never
+    /// intersect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if given more than four triangles.
+    pub fn new(triangles: &[Triangle]) -> Self {
+        assert!(
+            triangles.len() <= 4,
+            "Cannot pack {} triangles",
+            triangles.len()
+        );
+        let lane = |i: usize| {
+            triangles
+                .get(i)
+                .map_or([Point::ORIGIN; 3], Triangle::vertices)
+        };
+        let lanes = [lane(0), lane(1), lane(2), lane(3)];
+        Self {
+            p0: Vector4::new(lanes.map(|[p0, _, _]| p0.into())),
+            e1: Vector4::new(lanes.map(|[p0, p1, _]| (p1 - p0).into())),
+            e2: Vector4::new(lanes.map(|[p0, _, p2]| (p2 - p0).into())),
+        }
+    }
+
+    /// Test a ray against all four triangles.
+    ///
+    /// Returns the lane and ray parameter value of the nearest hit within
+    /// `[t_min, t_max]`, if any. Agrees with [`Triangle::intersect`] lane for
+    /// lane; ties go to the lowest lane.
+    #[inline]
+    pub fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<(usize, Float)> {
+        let origin = Vector4::splat(ray.origin.into());
+        let direction = Vector4::splat(ray.direction.into());
+
+        let pvec = direction.cross(self.e2);
+        let det = self.e1.dot(pvec);
+        let scale = self.e1.dot(self.e1) * self.e2.dot(self.e2) * direction.dot(direction);
+        let inv_det = Float4::splat(1.0) / det;
+
+        let tvec = origin - self.p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        let qvec = tvec.cross(self.e1);
+        let b2 = direction.dot(qvec) * inv_det;
+        let t = self.e2.dot(qvec) * inv_det;
+
+        let (det, scale) = (det.to_array(), scale.to_array());
+        let (b1, b2, t) = (b1.to_array(), b2.to_array(), t.to_array());
+        (0..4)
+            .filter(|&i| {
+                det[i] * det[i] > Float::EPSILON * Float::EPSILON * scale[i]
+                    && (0.0..=1.0).contains(&b1[i])
+                    && b2[i] >= 0.0
+                    && b1[i] + b2[i] <= 1.0
+                    && t_min <= t[i]
+                    && t[i] <= t_max
+            })
+            .map(|i| (i, t[i]))
+            .fold(None, |nearest, (i, t)| match nearest {
+                Some((_, t_near)) if t_near <= t => nearest,
+                _ => Some((i, t)),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Vector;
+
+    fn tri() -> Triangle {
+        Triangle::new([0.0, 0.0, 5.0], [1.0, 0.0, 5.0], [0.0, 1.0, 5.0])
+    }
+
+    #[test]
+    fn edge_distance() {
+        let t = tri();
+        let [b0, b1, b2] = t.barycentric(Point::new(0.25, 0.5, 5.0));
+        assert!((b0 - 0.25).abs() < 1e-9 && (b1 - 0.25).abs() < 1e-9 && (b2 - 0.5).abs() < 1e-9);
+        // Nearest edge is the hypotenuse
+        let expected = 0.25 / Float::sqrt(2.0);
+        assert!((t.edge_distance(Point::new(0.25, 0.5, 5.0)) - expected).abs() < 1e-9);
+        assert!(t.edge_distance(Point::new(0.5, 0.0, 5.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn intersect_inside() {
+        let ray = Ray::new(Point::new(0.25, 0.25, 0.0), Vector::Z_AXIS);
+        let isect = tri().intersect(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(5.0, isect.t);
+        assert_eq!(Point::new(0.25, 0.25, 5.0), isect.point);
+        assert_eq!(Unit::Z_AXIS, isect.norm);
+    }
+
+    #[test]
+    fn intersect_outside() {
+        let ray = Ray::new(Point::new(0.75, 0.75, 0.0), Vector::Z_AXIS);
+        assert!(!tri().intersects(&ray, 0.0, Float::INFINITY));
+
+        let ray = Ray::new(Point::new(0.25, 0.25, 0.0), Vector::Z_AXIS);
+        assert!(!tri().intersects(&ray, 0.0, 4.0));
+    }
+
+    #[test]
+    fn intersect_at_any_scale() {
+        for scale in [1e-9, 1.0, 1e9] {
+            let t = Triangle::new(
+                [0.0, 0.0, 5.0 * scale],
+                [scale, 0.0, 5.0 * scale],
+                [0.0, scale, 5.0 * scale],
+            );
+            let ray = Ray::new(Point::new(0.25 * scale, 0.25 * scale, 0.0), Vector::Z_AXIS);
+            assert!(t.intersects(&ray, 0.0, Float::INFINITY), "{}", scale);
+            let packet = Triangle4::new(&[t]);
+            assert!(
+                packet.hit(&ray, 0.0, Float::INFINITY).is_some(),
+                "{}",
+                scale
+            );
+
+            // Along the triangle's plane
+            let ray = Ray::new(
+                Point::new(-scale, 0.25 * scale, 5.0 * scale),
+                Vector::X_AXIS,
+            );
+            assert!(!t.intersects(&ray, 0.0, Float::INFINITY), "{}", scale);
+        }
+    }
+
+    #[test]
+    fn intersect_four() {
+        let triangles = [
+            tri(),
+            Triangle::new([0.0, 0.0, 3.0], [0.0, 1.0, 3.0], [1.0, 0.0, 3.0]),
+            Triangle::new([0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [2.0, 0.0, 1.0]),
+        ];
+        let packet = Triangle4::new(&triangles);
+        let rays = [
+            Ray::new(Point::new(0.25, 0.25, 0.0), Vector::Z_AXIS),
+            Ray::new(Point::new(0.25, 0.25, 4.0), Vector::Z_AXIS),
+            Ray::new(Point::new(0.75, 0.75, 0.0), Vector::Z_AXIS),
+            Ray::new(Point::new(0.2, 0.1, 0.0), Vector::new(0.1, 0.2, 1.0)),
+        ];
+        for ray in &rays {
+            let expected = triangles
+                .iter()
+                .enumerate()
+                .filter_map(|(i, t)| t.intersect(ray, 0.0, 10.0).map(|isect| (i, isect.t)))
+                .fold(
+                    None,
+                    |nearest: Option<(usize, Float)>, (i, t)| match nearest {
+                        Some((_, t_near)) if t_near <= t => nearest,
+                        _ => Some((i, t)),
+                    },
+                );
+            assert_eq!(expected, packet.hit(ray, 0.0, 10.0));
+        }
+        assert_eq!(Some((1, 3.0)), packet.hit(&rays[0], 0.0, Float::INFINITY));
     }
 }
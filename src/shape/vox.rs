@@ -0,0 +1,264 @@
+use super::SparseVoxelOctree;
+use crate::{
+    color::RGB,
+    material::{Lambertian, Material},
+    Float,
+};
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// Errors that can occur while loading a MagicaVoxel file.
+#[derive(Debug)]
+pub enum VoxLoadError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file is malformed.
+    Parse(&'static str),
+    /// The file contains no models.
+    Empty,
+}
+
+impl fmt::Display for VoxLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read vox file: {}", e),
+            Self::Parse(msg) => write!(f, "invalid vox file: {}", msg),
+            Self::Empty => write!(f, "vox file contains no models"),
+        }
+    }
+}
+
+impl Error for VoxLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VoxLoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A voxel model loaded from a MagicaVoxel `.vox` file.
+///
+/// Voxel material indices are the file's palette indices (`1..=255`), so
+/// [`materials`] can be indexed directly with the material of a
+/// [`VoxelHit`].
+///
+/// MagicaVoxel is Z-up; the model is rotated to be Y-up on load, with voxel
+/// `(0, 0, 0)` at the origin and unit-sized voxels.
+///
+/// [`materials`]: Self::materials
+/// [`VoxelHit`]: super::VoxelHit
+#[derive(Debug, Clone)]
+pub struct VoxModel {
+    pub octree: SparseVoxelOctree,
+    /// The palette, as linear RGB. Entry `0` is unused (empty voxels).
+    pub palette: [RGB; 256],
+}
+
+impl VoxModel {
+    /// Load a model from a `.vox` file.
+    ///
+    /// Only the first model in the file is loaded; scene graph, layer and
+    /// material extension chunks are ignored.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VoxLoadError> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// Parse a model from the contents of a `.vox` file.
+    ///
+    /// See [`load`] for details.
+    ///
+    /// [`load`]: Self::load
+    pub fn parse(data: &[u8]) -> Result<Self, VoxLoadError> {
+        let mut r = Reader { data, pos: 0 };
+        if r.take(4)? != b"VOX " {
+            return Err(VoxLoadError::Parse("missing VOX header"));
+        }
+        let _version = r.u32()?;
+        let (id, main) = r.chunk()?;
+        if id != b"MAIN" {
+            return Err(VoxLoadError::Parse("missing MAIN chunk"));
+        }
+
+        let mut size = None;
+        let mut voxels = None;
+        let mut palette = None;
+        let mut r = Reader { data: main, pos: 0 };
+        while r.pos < r.data.len() {
+            let (id, content) = r.chunk()?;
+            let mut c = Reader {
+                data: content,
+                pos: 0,
+            };
+            match id {
+                b"SIZE" if size.is_none() => size = Some([c.u32()?, c.u32()?, c.u32()?]),
+                b"XYZI" if voxels.is_none() => {
+                    let n = c.u32()? as usize;
+                    let bytes = c.take(
+                        n.checked_mul(4)
+                            .ok_or(VoxLoadError::Parse("bad voxel count"))?,
+                    )?;
+                    voxels = Some(
+                        bytes
+                            .chunks_exact(4)
+                            .map(|v| [v[0], v[1], v[2], v[3]])
+                            .collect::<Vec<_>>(),
+                    );
+                }
+                b"RGBA" => {
+                    let bytes = c.take(256 * 4)?;
+                    // Color `i` of the chunk is palette index `i + 1`
+                    let mut colors = [RGB::default(); 256];
+                    for (i, rgba) in bytes.chunks_exact(4).take(255).enumerate() {
+                        colors[i + 1] = RGB::from([rgba[0], rgba[1], rgba[2]].map(srgb_to_linear));
+                    }
+                    palette = Some(colors);
+                }
+                _ => {}
+            }
+        }
+
+        let [sx, sy, sz] = size.ok_or(VoxLoadError::Empty)?;
+        let voxels = voxels.ok_or(VoxLoadError::Parse("SIZE chunk without XYZI"))?;
+        // MagicaVoxel's limit, which also keeps the octree depth in range
+        if sx.max(sy).max(sz) > 256 {
+            return Err(VoxLoadError::Parse("model larger than 256 voxels"));
+        }
+
+        // Rotate Z-up to Y-up: (x, y, z) -> (x, z, -y)
+        let extent = sx.max(sy).max(sz).max(1);
+        let depth = u32::BITS - (extent - 1).leading_zeros();
+        let mut builder = SparseVoxelOctree::builder(depth);
+        for [x, y, z, color] in voxels {
+            let (x, y, z) = (x as u32, y as u32, z as u32);
+            if x >= sx || y >= sy || z >= sz || color == 0 {
+                return Err(VoxLoadError::Parse("voxel out of bounds"));
+            }
+            builder.set(x, z, sy - 1 - y, color as u16);
+        }
+
+        Ok(Self {
+            octree: builder.build(),
+            palette: palette.unwrap_or([RGB::from([0.5, 0.5, 0.5]); 256]),
+        })
+    }
+
+    /// Diffuse materials for each palette entry, indexed by voxel material.
+    pub fn materials(&self) -> Vec<Material> {
+        self.palette
+            .iter()
+            .map(|&rgb| Lambertian::new(rgb).into())
+            .collect()
+    }
+}
+
+fn srgb_to_linear(v: u8) -> Float {
+    let v = v as Float / 255.0;
+    match v <= 0.04045 {
+        true => v / 12.92,
+        false => ((v + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+// Little-endian cursor over the file contents.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], VoxLoadError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len());
+        let end = end.ok_or(VoxLoadError::Parse("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxLoadError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    // Reads a chunk header and returns its id and contents plus children.
+    fn chunk(&mut self) -> Result<(&'a [u8], &'a [u8]), VoxLoadError> {
+        let id = self.take(4)?;
+        let content = self.u32()? as usize;
+        let children = self.u32()? as usize;
+        let body = self.take(content.saturating_add(children))?;
+        Ok((id, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((content.len() as u32).to_le_bytes());
+        out.extend((children.len() as u32).to_le_bytes());
+        out.extend(content);
+        out.extend(children);
+        out
+    }
+
+    fn file(size: [u32; 3], with_palette: bool) -> Vec<u8> {
+        let size: Vec<u8> = size.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut xyzi = 2u32.to_le_bytes().to_vec();
+        xyzi.extend([0, 0, 0, 1, 2, 1, 0, 5]);
+        let mut children = chunk(b"SIZE", &size, &[]);
+        children.extend(chunk(b"XYZI", &xyzi, &[]));
+        if with_palette {
+            let mut rgba = vec![0u8; 1024];
+            rgba[4..8].copy_from_slice(&[255, 0, 0, 255]);
+            children.extend(chunk(b"RGBA", &rgba, &[]));
+        }
+
+        let mut out = b"VOX ".to_vec();
+        out.extend(150u32.to_le_bytes());
+        out.extend(chunk(b"MAIN", &[], &children));
+        out
+    }
+
+    #[test]
+    fn parse_model() {
+        let model = VoxModel::parse(&file([3, 2, 1], true)).unwrap();
+        let svo = &model.octree;
+        assert_eq!(4, svo.resolution());
+        // (x, y, z) -> (x, z, sy - 1 - y)
+        assert_eq!(Some(1), svo.voxel(0, 0, 1));
+        assert_eq!(Some(5), svo.voxel(2, 0, 0));
+        assert_eq!(RGB::from([1.0, 0.0, 0.0]), model.palette[2]);
+        assert_eq!(256, model.materials().len());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut data = file([3, 2, 1], false);
+        assert!(VoxModel::parse(&data).is_ok());
+        data.truncate(data.len() - 3);
+        assert!(matches!(
+            VoxModel::parse(&data),
+            Err(VoxLoadError::Parse(_))
+        ));
+        assert!(matches!(
+            VoxModel::parse(b"PNG "),
+            Err(VoxLoadError::Parse(_))
+        ));
+        assert!(VoxModel::parse(&file([256, 2, 1], false)).is_ok());
+        for size in [[3, 257, 1], [3, 2, (1 << 31) + 1]] {
+            assert!(matches!(
+                VoxModel::parse(&file(size, false)),
+                Err(VoxLoadError::Parse(_))
+            ));
+        }
+    }
+}
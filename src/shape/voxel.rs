@@ -0,0 +1,378 @@
+use super::{Bounded, Intersection, Shape};
+use crate::{
+    geo::{Bounds, Component, Point, Ray, SlabRay, Unit, Vector},
+    Float,
+};
+use std::collections::HashMap;
+
+/// A node in the octree. Branches store the index of the first of their 8
+/// children, which are laid out contiguously.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Empty,
+    Solid(u16),
+    Branch(u32),
+}
+
+/// The result of intersecting a ray with a [`SparseVoxelOctree`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelHit {
+    pub isect: Intersection,
+    /// The material index of the voxel that was hit.
+    pub material: u16,
+}
+
+/// A voxel volume stored as a sparse voxel octree.
+///
+/// Each voxel is either empty or solid with a material index (_e.g._ into a
+/// MagicaVoxel-style palette). Empty space and uniform solid regions collapse
+/// into single nodes, so rays skip through them in a handful of steps rather
+/// than testing the 12 triangles per voxel a mesh conversion would produce.
+///
+/// Voxel `(x, y, z)` occupies the cube from `origin + (x, y, z) * voxel_size`
+/// to `origin + (x + 1, y + 1, z + 1) * voxel_size`.
+#[derive(Debug, Clone)]
+pub struct SparseVoxelOctree {
+    origin: Point,
+    voxel_size: Float,
+    depth: u32,
+    root: Node,
+    nodes: Vec<Node>,
+}
+
+impl SparseVoxelOctree {
+    /// Create a new builder for an octree with `2^depth` voxels per side.
+    ///
+    /// See [`VoxelBuilder::new`] for details.
+    pub fn builder(depth: u32) -> VoxelBuilder {
+        VoxelBuilder::new(depth)
+    }
+
+    /// The number of voxels along each side.
+    #[inline]
+    pub fn resolution(&self) -> u32 {
+        1 << self.depth
+    }
+
+    /// The world-space corner of voxel `(0, 0, 0)`.
+    #[inline]
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// The world-space edge length of a voxel.
+    #[inline]
+    pub fn voxel_size(&self) -> Float {
+        self.voxel_size
+    }
+
+    /// Move and scale the octree so voxel `(0, 0, 0)` has its corner at
+    /// `origin` and voxels have the given edge length.
+    pub fn with_placement(mut self, origin: impl Into<Point>, voxel_size: Float) -> Self {
+        self.origin = origin.into();
+        self.voxel_size = voxel_size;
+        self
+    }
+
+    /// Estimated memory used by the octree, in bytes.
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.nodes.len() * std::mem::size_of::<Node>()
+    }
+
+    /// The material of the voxel at the given coordinates, or `None` if it is
+    /// empty or out of range.
+    pub fn voxel(&self, x: u32, y: u32, z: u32) -> Option<u16> {
+        if x.max(y).max(z) >= self.resolution() {
+            return None;
+        }
+        let mut node = self.root;
+        let mut level = self.depth;
+        loop {
+            match node {
+                Node::Empty => return None,
+                Node::Solid(m) => return Some(m),
+                Node::Branch(first) => {
+                    level -= 1;
+                    let octant =
+                        ((x >> level) & 1) | ((y >> level) & 1) << 1 | ((z >> level) & 1) << 2;
+                    node = self.nodes[(first + octant) as usize];
+                }
+            }
+        }
+    }
+
+    /// Ray intersection test, also returning the material of the voxel hit.
+    ///
+    /// Traverses the octree front-to-back, descending only into children the
+    /// ray passes through, so the first solid node found is the closest hit.
+    /// Rays starting inside a solid voxel don't hit it.
+    pub fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<VoxelHit> {
+        let slab_ray = SlabRay::from(ray);
+        let size = self.voxel_size * self.resolution() as Float;
+        let (t, axis, material) =
+            self.traverse(self.root, self.origin, size, &slab_ray, t_min, t_max)?;
+
+        let sign = match ray.direction[axis] > 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+        let mut norm = Vector::ZERO;
+        norm[axis] = sign;
+
+        Some(VoxelHit {
+            isect: Intersection {
+                point: ray.at(t),
+                norm: Unit::try_from(norm).ok()?,
+                t,
+            },
+            material,
+        })
+    }
+
+    // Returns the entry `t`, entry face axis and material of the first solid
+    // node hit within the cube at `lo` with edge length `size`.
+    fn traverse(
+        &self,
+        node: Node,
+        lo: Point,
+        size: Float,
+        ray: &SlabRay,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(Float, Component, u16)> {
+        let (t_near, t_far, axis) = slab(lo, size, ray)?;
+        if t_near > t_max || t_far < t_min {
+            return None;
+        }
+
+        match node {
+            Node::Empty => None,
+            Node::Solid(m) => match t_near >= t_min {
+                true => Some((t_near, axis, m)),
+                false => None,
+            },
+            Node::Branch(first) => {
+                let half = size * 0.5;
+                let mut children: Vec<(Float, Node, Point)> = (0..8)
+                    .filter_map(|octant| {
+                        let child = self.nodes[(first + octant) as usize];
+                        if child == Node::Empty {
+                            return None;
+                        }
+                        let offset = Vector::new(
+                            (octant & 1) as Float,
+                            ((octant >> 1) & 1) as Float,
+                            ((octant >> 2) & 1) as Float,
+                        ) * half;
+                        let child_lo = lo + offset;
+                        let (t_near, _, _) = slab(child_lo, half, ray)?;
+                        Some((t_near, child, child_lo))
+                    })
+                    .collect();
+                children.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                children.into_iter().find_map(|(_, child, child_lo)| {
+                    self.traverse(child, child_lo, half, ray, t_min, t_max)
+                })
+            }
+        }
+    }
+}
+
+impl Shape for SparseVoxelOctree {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<Intersection> {
+        self.hit(ray, t_min, t_max).map(|hit| hit.isect)
+    }
+}
+
+impl Bounded for SparseVoxelOctree {
+    #[inline]
+    fn bounds(&self) -> Bounds {
+        let size = self.voxel_size * self.resolution() as Float;
+        Bounds::from_corners(self.origin, self.origin + Vector::new(size, size, size))
+    }
+}
+
+// Slab test against the cube at `lo` with edge length `size`. Returns the
+// entry and exit `t` and the axis of the entry face.
+#[inline]
+fn slab(lo: Point, size: Float, ray: &SlabRay) -> Option<(Float, Float, Component)> {
+    let mut t0 = Float::NEG_INFINITY;
+    let mut t1 = Float::INFINITY;
+    let mut axis = Component::X;
+    for &i in Component::XYZ.iter() {
+        let a = (lo[i] - ray.origin[i]) * ray.inv_direction[i];
+        let b = (lo[i] + size - ray.origin[i]) * ray.inv_direction[i];
+        let (near, far) = if a < b { (a, b) } else { (b, a) };
+        if near > t0 {
+            t0 = near;
+            axis = i;
+        }
+        t1 = t1.min(far);
+    }
+    match t0 <= t1 {
+        true => Some((t0, t1, axis)),
+        false => None,
+    }
+}
+
+/// Builder for creating [`SparseVoxelOctree`] instances.
+pub struct VoxelBuilder {
+    depth: u32,
+    origin: Point,
+    voxel_size: Float,
+    voxels: HashMap<[u32; 3], u16>,
+}
+
+impl VoxelBuilder {
+    /// Create a new builder for an octree with `2^depth` voxels per side.
+    ///
+    /// By default, voxels have unit size and voxel `(0, 0, 0)` is at the
+    /// origin.
+    pub fn new(depth: u32) -> Self {
+        Self {
+            depth,
+            origin: Point::ORIGIN,
+            voxel_size: 1.0,
+            voxels: HashMap::new(),
+        }
+    }
+
+    /// Set the world-space corner of voxel `(0, 0, 0)`.
+    pub fn origin(&mut self, origin: impl Into<Point>) -> &mut Self {
+        self.origin = origin.into();
+        self
+    }
+
+    /// Set the world-space edge length of a voxel.
+    pub fn voxel_size(&mut self, size: Float) -> &mut Self {
+        self.voxel_size = size;
+        self
+    }
+
+    /// Fill the voxel at the given coordinates with a material.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are out of range.
+    pub fn set(&mut self, x: u32, y: u32, z: u32, material: u16) -> &mut Self {
+        assert!(x.max(y).max(z) < 1 << self.depth, "voxel out of range");
+        self.voxels.insert([x, y, z], material);
+        self
+    }
+
+    /// Creates a new voxel octree from this builder.
+    pub fn build(&self) -> SparseVoxelOctree {
+        let mut voxels: Vec<_> = self.voxels.iter().map(|(&k, &v)| (k, v)).collect();
+        let mut nodes = Vec::new();
+        let root = build_node(&mut voxels, [0, 0, 0], self.depth, &mut nodes);
+        SparseVoxelOctree {
+            origin: self.origin,
+            voxel_size: self.voxel_size,
+            depth: self.depth,
+            root,
+            nodes,
+        }
+    }
+}
+
+// Build the node covering the cube at `corner` with `2^level` voxels per
+// side, given the voxels inside it.
+fn build_node(
+    voxels: &mut [([u32; 3], u16)],
+    corner: [u32; 3],
+    level: u32,
+    nodes: &mut Vec<Node>,
+) -> Node {
+    if voxels.is_empty() {
+        return Node::Empty;
+    }
+    if level == 0 {
+        return Node::Solid(voxels[0].1);
+    }
+
+    let half = 1 << (level - 1);
+    let octant = |[x, y, z]: [u32; 3]| -> u32 {
+        ((x - corner[0] >= half) as u32)
+            | ((y - corner[1] >= half) as u32) << 1
+            | ((z - corner[2] >= half) as u32) << 2
+    };
+    voxels.sort_unstable_by_key(|&(p, _)| octant(p));
+
+    let first = nodes.len();
+    nodes.extend([Node::Empty; 8]);
+    let mut rest = voxels;
+    for i in 0..8 {
+        let n = rest.iter().take_while(|&&(p, _)| octant(p) == i).count();
+        let (children, tail) = rest.split_at_mut(n);
+        rest = tail;
+        let child_corner = [
+            corner[0] + (i & 1) * half,
+            corner[1] + ((i >> 1) & 1) * half,
+            corner[2] + ((i >> 2) & 1) * half,
+        ];
+        let child = build_node(children, child_corner, level - 1, nodes);
+        nodes[first + i as usize] = child;
+    }
+
+    // Collapse uniform solid regions into a single node
+    let children = &nodes[first..first + 8];
+    match children[0] {
+        Node::Solid(m) if children.iter().all(|&c| c == Node::Solid(m)) => {
+            nodes.truncate(first);
+            Node::Solid(m)
+        }
+        _ => Node::Branch(first as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_and_collapse() {
+        let mut builder = SparseVoxelOctree::builder(2);
+        for x in 0..2 {
+            for y in 0..2 {
+                for z in 0..2 {
+                    builder.set(x, y, z, 7);
+                }
+            }
+        }
+        builder.set(3, 3, 3, 1);
+        let svo = builder.build();
+
+        assert_eq!(Some(7), svo.voxel(1, 0, 1));
+        assert_eq!(Some(1), svo.voxel(3, 3, 3));
+        assert_eq!(None, svo.voxel(2, 0, 0));
+        assert_eq!(None, svo.voxel(4, 0, 0));
+        // Root branch, plus one branch for the lone voxel's octant
+        assert_eq!(16, svo.nodes.len());
+    }
+
+    #[test]
+    fn hit_nearest_voxel() {
+        let svo = SparseVoxelOctree::builder(3)
+            .origin([-4.0, -4.0, -4.0])
+            .set(4, 4, 2, 1)
+            .set(4, 4, 6, 2)
+            .build();
+
+        // Voxel (4, 4, 2) spans z in [-2, -1]
+        let ray = Ray::new(Point::new(0.5, 0.5, -10.0), Vector::Z_AXIS);
+        let hit = svo.hit(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(1, hit.material);
+        assert_eq!(8.0, hit.isect.t);
+        assert_eq!(Vector::new(0.0, 0.0, -1.0), hit.isect.norm.into());
+
+        let ray = Ray::new(Point::new(0.5, 0.5, 10.0), -Vector::Z_AXIS);
+        let hit = svo.hit(&ray, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(2, hit.material);
+        assert_eq!(7.0, hit.isect.t);
+
+        let ray = Ray::new(Point::new(1.5, 0.5, -10.0), Vector::Z_AXIS);
+        assert!(svo.hit(&ray, 0.0, Float::INFINITY).is_none());
+    }
+}
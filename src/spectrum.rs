@@ -26,5 +26,17 @@
 mod continuous;
 pub use continuous::*;
 
+mod illuminant;
+pub use illuminant::*;
+
+mod loader;
+pub use loader::*;
+
 mod sampled;
 pub use sampled::*;
+
+mod sigmoid;
+pub use sigmoid::*;
+
+mod uplift;
+pub use uplift::*;
@@ -0,0 +1,124 @@
+use super::{blackbody, Sampled};
+use crate::{color::XYZ, Float};
+
+/// Spectral emission profiles for light sources.
+///
+/// Covers the CIE standard illuminants most often used to light scenes, plus
+/// ideal black bodies at arbitrary temperatures. The raw spectra have wildly
+/// different scales (the standard illuminants are normalized to `100` at
+/// `560nm`, black bodies are in absolute radiometric units), so in practice
+/// you'll want [`with_luminance`] to get something with a predictable
+/// brightness.
+///
+/// ```
+/// use gremlin::spectrum::Illuminant;
+///
+/// let warm = Illuminant::Blackbody(2700.0).with_luminance(1.0);
+/// let daylight = Illuminant::D65.with_luminance(1.0);
+/// ```
+///
+/// [`with_luminance`]: Self::with_luminance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Illuminant {
+    /// CIE standard illuminant D65, average noon daylight.
+    D65,
+    /// CIE standard illuminant A, a tungsten filament lamp.
+    A,
+    /// CIE standard illuminant E, equal energy at all wavelengths.
+    E,
+    /// An ideal black body at the given temperature, in Kelvins.
+    Blackbody(Float),
+}
+
+impl Illuminant {
+    /// The raw spectral power distribution.
+    pub fn spectrum(&self) -> Sampled {
+        match *self {
+            Self::D65 => consts::D65,
+            Self::A => {
+                // Illuminant A is defined as a 2856K Planckian radiator,
+                // normalized to 100 at 560nm
+                let norm = 100.0 / blackbody(2856.0, 560.0);
+                Sampled::from(|w| blackbody(2856.0, w) * norm)
+            }
+            Self::E => Sampled::splat(100.0),
+            Self::Blackbody(temp) => Sampled::from(|w| blackbody(temp, w)),
+        }
+    }
+
+    /// The spectral power distribution, scaled to have the given luminance
+    /// (the `Y` tristimulus value).
+    pub fn with_luminance(&self, luminance: Float) -> Sampled {
+        self.spectrum().with_luminance(luminance)
+    }
+}
+
+impl Sampled {
+    /// The luminance (the `Y` tristimulus value) of this spectrum.
+    #[inline]
+    pub fn luminance(&self) -> Float {
+        XYZ::from(self.clone()).luminance()
+    }
+
+    /// Scale this spectrum so it has the given luminance.
+    ///
+    /// Spectra with zero luminance (_e.g._ all energy outside the visible
+    /// range) are returned unchanged.
+    pub fn with_luminance(mut self, luminance: Float) -> Self {
+        let current = self.luminance();
+        if current > 0.0 {
+            let scale = luminance / current;
+            self.iter_mut().for_each(|v| *v *= scale);
+        }
+        self
+    }
+}
+
+mod consts {
+    use crate::spectrum::Sampled;
+
+    // CIE standard illuminant D65, 380-775nm in 5nm steps
+    #[rustfmt::skip]
+    pub const D65: Sampled = Sampled::new([
+         49.9755,  52.3118,  54.6482,  68.7015,  82.7549,  87.1204,  91.4860,  92.4589,
+         93.4318,  90.0570,  86.6823,  95.7736, 104.8650, 110.9360, 117.0080, 117.4100,
+        117.8120, 116.3360, 114.8610, 115.3920, 115.9230, 112.3670, 108.8110, 109.0820,
+        109.3540, 108.5780, 107.8020, 106.2960, 104.7900, 106.2390, 107.6890, 106.0470,
+        104.4050, 104.2250, 104.0460, 102.0230, 100.0000,  98.1671,  96.3342,  96.0611,
+         95.7880,  92.2368,  88.6856,  89.3459,  90.0062,  89.8026,  89.5991,  88.6489,
+         87.6987,  85.4936,  83.2886,  83.4939,  83.6992,  81.8630,  80.0268,  80.1207,
+         80.2146,  81.2462,  82.2778,  80.2810,  78.2842,  74.0027,  69.7213,  70.6652,
+         71.6091,  72.9790,  74.3490,  67.9765,  61.6040,  65.7448,  69.8856,  72.4863,
+         75.0870,  69.3398,  63.5927,  55.0054,  46.4182,  56.6118,  66.8054,  65.0941,
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::RGB;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn normalized_luminance() {
+        for illum in [
+            Illuminant::D65,
+            Illuminant::A,
+            Illuminant::E,
+            Illuminant::Blackbody(5000.0),
+        ] {
+            assert_relative_eq!(2.0, illum.with_luminance(2.0).luminance(), epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn a_is_warmer_than_d65() {
+        let a = RGB::from(XYZ::from(Illuminant::A.with_luminance(1.0)));
+        let d65 = RGB::from(XYZ::from(Illuminant::D65.with_luminance(1.0)));
+        let ratio = |c: RGB| {
+            let [r, _, b]: [Float; 3] = c.into();
+            r / b
+        };
+        assert!(ratio(a) > ratio(d65));
+    }
+}
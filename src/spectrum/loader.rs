@@ -0,0 +1,186 @@
+use super::Sampled;
+use crate::{math::PiecewiseLinearFn, Float};
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// Errors that can occur while loading measured spectral data.
+#[derive(Debug)]
+pub enum SpectrumLoadError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// A line couldn't be parsed. Line numbers start at `1`.
+    Parse { line: usize, msg: &'static str },
+    /// The file contained no data points.
+    Empty,
+}
+
+impl fmt::Display for SpectrumLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read spectrum: {}", e),
+            Self::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+            Self::Empty => write!(f, "spectrum contains no data"),
+        }
+    }
+}
+
+impl Error for SpectrumLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SpectrumLoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Parse tabulated spectral data.
+///
+/// Accepts both CSV and the whitespace-separated `.spd` format: each line is
+/// a wavelength (in nanometers) and a value, separated by a comma and/or
+/// whitespace, with no further columns. Blank lines and `#` comments are
+/// ignored, as is a single header line of column names at the start of the
+/// data (as most published CSV files have); a header can't contain numbers, so
+/// a malformed first data line is reported rather than skipped. Points
+/// needn't be sorted, but wavelengths must be unique, and wavelengths and
+/// values must be finite.
+///
+/// ```
+/// use gremlin::spectrum;
+///
+/// let data = "wavelength,reflectance\n400,0.0\n500,0.5\n600,1.0\n";
+/// let f = spectrum::parse_spectrum(data).unwrap();
+/// assert_eq!(0.25, f.y(450.0));
+/// ```
+pub fn parse_spectrum(data: &str) -> Result<PiecewiseLinearFn, SpectrumLoadError> {
+    // Each point with the line it came from, for reporting duplicates
+    let mut points: Vec<(Float, Float, usize)> = Vec::new();
+    // Set once past the point where a header may appear
+    let mut seen_header = false;
+
+    for (idx, line) in data.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .collect();
+        let parsed = match fields[..] {
+            [x, y] => x.parse::<Float>().ok().zip(y.parse::<Float>().ok()),
+            _ => None,
+        };
+
+        match parsed {
+            Some((x, y)) if !x.is_finite() || !y.is_finite() => {
+                return Err(SpectrumLoadError::Parse {
+                    line: line_no,
+                    msg: "wavelength and value must be finite",
+                })
+            }
+            Some((x, y)) => {
+                seen_header = true;
+                points.push((x, y, line_no));
+            }
+            None if !seen_header && fields.iter().all(|f| f.parse::<Float>().is_err()) => {
+                // Header line; only allowed before any data
+                seen_header = true;
+            }
+            None => {
+                return Err(SpectrumLoadError::Parse {
+                    line: line_no,
+                    msg: "expected a wavelength and a value",
+                })
+            }
+        }
+    }
+
+    if points.is_empty() {
+        return Err(SpectrumLoadError::Empty);
+    }
+
+    // Stable, so the second of two duplicates is the later line
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if let Some(w) = points.windows(2).find(|w| w[0].0 == w[1].0) {
+        return Err(SpectrumLoadError::Parse {
+            line: w[1].2,
+            msg: "duplicate wavelength",
+        });
+    }
+
+    let (xs, ys): (Vec<Float>, Vec<Float>) = points.into_iter().map(|(x, y, _)| (x, y)).unzip();
+    Ok(PiecewiseLinearFn::new(xs, ys))
+}
+
+/// Read tabulated spectral data from a CSV or `.spd` file.
+///
+/// See [`parse_spectrum`] for the accepted format.
+pub fn read_spectrum(path: impl AsRef<Path>) -> Result<PiecewiseLinearFn, SpectrumLoadError> {
+    parse_spectrum(&fs::read_to_string(path)?)
+}
+
+impl Sampled {
+    /// Load a spectrum from a CSV or `.spd` file, resampled onto the internal
+    /// wavelength grid.
+    ///
+    /// See [`parse_spectrum`] for the accepted format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SpectrumLoadError> {
+        read_spectrum(path).map(Sampled::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spd_format() {
+        let data = "# measured reflectance\n700 0.8\n300 0.2\n\n500   0.5 # peak\n";
+        let f = parse_spectrum(data).unwrap();
+        assert_eq!(&[300.0, 500.0, 700.0], f.xs());
+        assert_eq!(&[0.2, 0.5, 0.8], f.ys());
+    }
+
+    #[test]
+    fn bad_lines() {
+        assert!(matches!(
+            parse_spectrum("400,1\nfoo,bar\n"),
+            Err(SpectrumLoadError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_spectrum("nm,value\n"),
+            Err(SpectrumLoadError::Empty)
+        ));
+        assert!(matches!(
+            parse_spectrum("400,1\n500,3\n400,2\n"),
+            Err(SpectrumLoadError::Parse { line: 3, .. })
+        ));
+        assert!(matches!(
+            parse_spectrum("400,1,2\n500,3\n"),
+            Err(SpectrumLoadError::Parse { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_spectrum("# data\n400,1x\n500,3\n"),
+            Err(SpectrumLoadError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_spectrum("nm,value\n400,1\n500\n"),
+            Err(SpectrumLoadError::Parse { line: 3, .. })
+        ));
+        assert!(matches!(
+            parse_spectrum("400 1\nnan 2"),
+            Err(SpectrumLoadError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_spectrum("nm,value\n400 inf"),
+            Err(SpectrumLoadError::Parse { line: 2, .. })
+        ));
+    }
+}
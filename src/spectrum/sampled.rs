@@ -1,4 +1,4 @@
-use crate::Float;
+use crate::{math::PiecewiseLinearFn, Float};
 use std::ops::{Deref, DerefMut};
 
 // CONSTANTS
@@ -60,11 +60,35 @@ impl Sampled {
         spec
     }
 
+    /// The value of the spectrum at the given wavelength.
+    ///
+    /// Returns the value of the bin containing `wavelength`, or `0.0` outside
+    /// the sampled range.
+    #[inline]
+    pub fn at(&self, wavelength: Float) -> Float {
+        if !(consts::MIN..consts::MAX).contains(&wavelength) {
+            return 0.0;
+        }
+        let idx = ((wavelength - consts::MIN) / consts::STEP) as usize;
+        self.0[idx.min(consts::COUNT - 1)]
+    }
+
+    /// Map a uniform random number in `[0, 1)` to a wavelength, uniformly
+    /// distributed over the sampled range.
+    #[inline]
+    pub fn sample_wavelength(u: Float) -> Float {
+        consts::MIN + u * (consts::MAX - consts::MIN)
+    }
+
+    /// Number of bins, which is also the factor relating a single uniformly
+    /// sampled wavelength to a Riemann sum over all bins.
+    pub const COUNT: usize = consts::COUNT;
+
     /// Enumerates over the sampled spectrum.
     ///
     /// Yields pairs `(wavelength, &value)`.
     #[inline]
-    pub fn enumerate_values(&self) -> EnumerateValues {
+    pub fn enumerate_values(&self) -> EnumerateValues<'_> {
         EnumerateValues {
             values: self.0.iter(),
             current: consts::MIN,
@@ -75,7 +99,7 @@ impl Sampled {
     ///
     /// Yields pairs `(wavelength, &mut value)`.
     #[inline]
-    pub fn enumerate_values_mut(&mut self) -> EnumerateValuesMut {
+    pub fn enumerate_values_mut(&mut self) -> EnumerateValuesMut<'_> {
         EnumerateValuesMut {
             values: self.0.iter_mut(),
             current: consts::MIN,
@@ -109,6 +133,22 @@ impl DerefMut for Sampled {
     }
 }
 
+/// A spectral quantity carried at a single wavelength.
+///
+/// This is the radiance type of "hero wavelength" style spectral integrators,
+/// where each path is traced at one randomly chosen wavelength. That's the
+/// only way to get wavelength-dependent effects such as dispersion, since a
+/// path through a prism goes somewhere different for each wavelength. Use
+/// [`Sampled::sample_wavelength`] to pick the wavelength; converting to
+/// [`XYZ`] then accounts for the uniform sampling density.
+///
+/// [`XYZ`]: crate::color::XYZ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SingleWavelength {
+    pub wavelength: Float,
+    pub value: Float,
+}
+
 // ENUMERATIONS
 
 /// Enumerates `(wavelength, value)` pairs.
@@ -209,23 +249,23 @@ where
     }
 }
 
-// impl From<PiecewiseLinearFn> for Sampled {
-//     /// Creates a sampled spectrum from a piecewise-linear function.
-//     ///
-//     /// Uses the average value of the function over each wavelength interval.
-//     ///
-//     /// ```
-//     /// use gremlin::math::PiecewiseLinearFn;
-//     /// use gremlin::spectrum::Sampled;
-//     ///
-//     /// let f = PiecewiseLinearFn::new([380.0, 780.0], [0.0, 1.0]);
-//     /// let _ = Sampled::from(f);
-//     /// ```
-//     #[inline]
-//     fn from(f: PiecewiseLinearFn) -> Self {
-//         Self::from_fn(|w0, w1| f.integrate(w0, w1) / consts::STEP)
-//     }
-// }
+impl From<PiecewiseLinearFn> for Sampled {
+    /// Creates a sampled spectrum from a piecewise-linear function.
+    ///
+    /// Uses the average value of the function over each wavelength interval.
+    ///
+    /// ```
+    /// use gremlin::math::PiecewiseLinearFn;
+    /// use gremlin::spectrum::Sampled;
+    ///
+    /// let f = PiecewiseLinearFn::new([380.0, 780.0], [0.0, 1.0]);
+    /// let _ = Sampled::from(f);
+    /// ```
+    #[inline]
+    fn from(f: PiecewiseLinearFn) -> Self {
+        Self::from_fn(|w0, w1| f.integrate(w0, w1) / consts::STEP)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -244,4 +284,23 @@ mod tests {
         assert_eq!(385.0, wavelength);
         assert_eq!(0.0, value);
     }
+
+    #[test]
+    fn from_piecewise_linear() {
+        let f = PiecewiseLinearFn::new([380.0, 780.0], [0.0, 400.0]);
+        let s = Sampled::from(f);
+        // Average over [380, 385) of a line with unit slope
+        assert_eq!(2.5, s[0]);
+        assert_eq!(397.5, s[consts::COUNT - 1]);
+    }
+
+    #[test]
+    fn at() {
+        let s = Sampled::from(|w| w);
+        assert_eq!(380.0, s.at(380.0));
+        assert_eq!(380.0, s.at(384.9));
+        assert_eq!(385.0, s.at(385.0));
+        assert_eq!(0.0, s.at(780.0));
+        assert_eq!(0.0, s.at(100.0));
+    }
 }
@@ -0,0 +1,180 @@
+use super::{Illuminant, Sampled};
+use crate::{
+    color::{RGB, XYZ},
+    Float,
+};
+
+/// A smooth reflectance spectrum described by three coefficients.
+///
+/// The spectrum is a quadratic polynomial in wavelength pushed through a
+/// sigmoid, which keeps it within `[0, 1]` and makes it smooth enough to be a
+/// plausible real-world reflectance. Three coefficients per texel is far
+/// cheaper to store than a full [`Sampled`] spectrum, and evaluating at a
+/// single wavelength is just a few multiplies, so spectral renderers can
+/// evaluate it directly at each path's wavelength.
+///
+/// The polynomial is in terms of the wavelength normalized to `[0, 1]` over
+/// the visible range (`380nm` to `780nm`), which keeps the coefficients in a
+/// reasonable range.
+///
+/// See: Wenzel Jakob and Johannes Hanika, _A Low-Dimensional Function Space
+/// for Efficient Spectral Upsampling_, Computer Graphics Forum, 2019
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SigmoidPolynomial {
+    pub c0: Float,
+    pub c1: Float,
+    pub c2: Float,
+}
+
+impl SigmoidPolynomial {
+    const MIN: Float = 380.0;
+    const MAX: Float = 780.0;
+
+    /// Creates the spectrum `s(c0 x² + c1 x + c2)`, where `x` is the
+    /// normalized wavelength.
+    #[inline]
+    pub const fn new(c0: Float, c1: Float, c2: Float) -> Self {
+        Self { c0, c1, c2 }
+    }
+
+    /// The reflectance at the given wavelength, in nanometers.
+    #[inline]
+    pub fn at(&self, wavelength: Float) -> Float {
+        let x = (wavelength - Self::MIN) / (Self::MAX - Self::MIN);
+        let p = (self.c0 * x + self.c1) * x + self.c2;
+        if p.is_infinite() {
+            return if p > 0.0 { 1.0 } else { 0.0 };
+        }
+        0.5 + p / (2.0 * (1.0 + p * p).sqrt())
+    }
+
+    /// Find the coefficients of a spectrum with the given RGB reflectance.
+    ///
+    /// Reflectance is judged under the D65 white point of the RGB space, so
+    /// `[1, 1, 1]`-ish colors fit flat spectra. Components are clamped just
+    /// inside `(0, 1)`, since the sigmoid never quite reaches either end.
+    /// Solved with damped Gauss-Newton iteration; highly saturated colors
+    /// that no smooth spectrum can reproduce get the closest fit found.
+    pub fn fit(rgb: RGB) -> Self {
+        const EPS: Float = 1e-3;
+        const STEP: Float = 1e-4;
+
+        let target: [Float; 3] = rgb.into();
+        let target = target.map(|v| v.clamp(EPS, 1.0 - EPS));
+        let d65 = Illuminant::D65.with_luminance(1.0);
+        let residual = |c: [Float; 3]| {
+            let rgb: [Float; 3] = Self::new(c[0], c[1], c[2]).reflect(&d65).into();
+            [0, 1, 2].map(|i| rgb[i] - target[i])
+        };
+        let norm = |r: [Float; 3]| r.iter().map(|v| v * v).sum::<Float>();
+
+        let mut c = [0.0; 3];
+        let mut r = residual(c);
+        let mut damping = 1e-3;
+        for _ in 0..100 {
+            if norm(r) < 1e-10 {
+                break;
+            }
+            // Finite difference Jacobian, one column per coefficient
+            let jac = [0, 1, 2].map(|j| {
+                let mut c2 = c;
+                c2[j] += STEP;
+                let r2 = residual(c2);
+                [0, 1, 2].map(|i| (r2[i] - r[i]) / STEP)
+            });
+            // Levenberg-Marquardt: (JᵀJ + λI) δ = -Jᵀr
+            let mut a = [[0.0; 3]; 3];
+            let mut b = [0.0; 3];
+            for i in 0..3 {
+                for j in 0..3 {
+                    a[i][j] = (0..3).map(|k| jac[i][k] * jac[j][k]).sum();
+                }
+                a[i][i] += damping;
+                b[i] = -(0..3).map(|k| jac[i][k] * r[k]).sum::<Float>();
+            }
+            let Some(delta) = solve3(a, b) else {
+                break;
+            };
+            let next = [0, 1, 2].map(|i| c[i] + delta[i]);
+            let r_next = residual(next);
+            if norm(r_next) < norm(r) {
+                c = next;
+                r = r_next;
+                damping = (damping * 0.5).max(1e-9);
+            } else {
+                damping *= 10.0;
+                if damping > 1e6 {
+                    break;
+                }
+            }
+        }
+        Self::new(c[0], c[1], c[2])
+    }
+
+    // The RGB reflectance under the given (unit luminance) illuminant
+    fn reflect(&self, illuminant: &Sampled) -> RGB {
+        let mut spec = Sampled::from(*self);
+        spec.iter_mut()
+            .zip(illuminant.iter())
+            .for_each(|(v, l)| *v *= l);
+        RGB::from(XYZ::from(spec))
+    }
+}
+
+impl From<SigmoidPolynomial> for Sampled {
+    #[inline]
+    fn from(poly: SigmoidPolynomial) -> Self {
+        Sampled::from_fn(|w0, w1| poly.at(0.5 * (w0 + w1)))
+    }
+}
+
+// Cramer's rule
+fn solve3(a: [[Float; 3]; 3], b: [Float; 3]) -> Option<[Float; 3]> {
+    let det = |m: [[Float; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(a);
+    if !d.is_normal() {
+        return None;
+    }
+    Some([0, 1, 2].map(|col| {
+        let mut m = a;
+        for row in 0..3 {
+            m[row][col] = b[row];
+        }
+        det(m) / d
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_and_smooth() {
+        let poly = SigmoidPolynomial::new(-40.0, 30.0, -3.0);
+        let spec = Sampled::from(poly);
+        assert!(spec.iter().all(|&v| (0.0..=1.0).contains(&v)));
+        assert_eq!(0.5, SigmoidPolynomial::default().at(550.0));
+    }
+
+    #[test]
+    fn fit_round_trips() {
+        let d65 = Illuminant::D65.with_luminance(1.0);
+        for rgb in [
+            [0.5, 0.5, 0.5],
+            [0.8, 0.2, 0.1],
+            [0.1, 0.6, 0.2],
+            [0.2, 0.3, 0.7],
+            [0.9, 0.9, 0.1],
+        ] {
+            let poly = SigmoidPolynomial::fit(RGB::from(rgb));
+            let out: [Float; 3] = poly.reflect(&d65).into();
+            for i in 0..3 {
+                assert!((out[i] - rgb[i]).abs() < 1e-3, "{:?} -> {:?}", rgb, out);
+            }
+        }
+    }
+}
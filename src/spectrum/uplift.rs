@@ -0,0 +1,153 @@
+use super::{Illuminant, Sampled};
+use crate::{color::RGB, Float};
+
+/// What an RGB value being converted to a spectrum represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumKind {
+    /// A surface reflectance. The resulting spectrum is kept within `[0, 1]`
+    /// so surfaces never reflect more light than they receive.
+    Reflectance,
+    /// A light source's emission. White maps to the D65 white point of the
+    /// RGB space, so `[1, 1, 1]` renders as neutral white.
+    Illuminant,
+}
+
+impl Sampled {
+    /// Convert a linear RGB color into a plausible spectrum.
+    ///
+    /// RGB-to-spectrum conversion is underdetermined (many spectra map to the
+    /// same color), so this picks a smooth one using Smits' method: the color
+    /// is decomposed into white plus at most one secondary and one primary
+    /// component, each of which has a precomputed basis spectrum.
+    ///
+    /// See: Brian Smits, _An RGB-to-Spectrum Conversion for Reflectances_,
+    /// Journal of Graphics Tools, 1999
+    pub fn from_rgb(rgb: RGB, kind: SpectrumKind) -> Self {
+        let [r, g, b]: [Float; 3] = rgb.into();
+
+        let mut coeffs = [0.0; consts::BINS];
+        let mut add = |basis: &[Float; consts::BINS], weight: Float| {
+            for (c, b) in coeffs.iter_mut().zip(basis) {
+                *c += b * weight;
+            }
+        };
+
+        if r <= g && r <= b {
+            add(&consts::WHITE, r);
+            if g <= b {
+                add(&consts::CYAN, g - r);
+                add(&consts::BLUE, b - g);
+            } else {
+                add(&consts::CYAN, b - r);
+                add(&consts::GREEN, g - b);
+            }
+        } else if g <= r && g <= b {
+            add(&consts::WHITE, g);
+            if r <= b {
+                add(&consts::MAGENTA, r - g);
+                add(&consts::BLUE, b - r);
+            } else {
+                add(&consts::MAGENTA, b - g);
+                add(&consts::RED, r - b);
+            }
+        } else {
+            add(&consts::WHITE, b);
+            if r <= g {
+                add(&consts::YELLOW, r - b);
+                add(&consts::GREEN, g - r);
+            } else {
+                add(&consts::YELLOW, g - b);
+                add(&consts::RED, r - g);
+            }
+        }
+
+        let spec = Sampled::from(|w: Float| {
+            let t = (w - consts::MIN) / (consts::MAX - consts::MIN);
+            let bin = (t * consts::BINS as Float).clamp(0.0, (consts::BINS - 1) as Float);
+            coeffs[bin as usize]
+        });
+
+        match kind {
+            SpectrumKind::Reflectance => {
+                let mut spec = spec;
+                spec.iter_mut()
+                    .for_each(|v| *v = (*v * 0.94).clamp(0.0, 1.0));
+                spec
+            }
+            SpectrumKind::Illuminant => {
+                // Tint by D65 so that white comes out white, keeping the scale
+                // such that a unit-white light has unit luminance
+                let d65 = Illuminant::D65.with_luminance(1.0);
+                let mut spec = spec;
+                spec.iter_mut()
+                    .zip(d65.iter())
+                    .for_each(|(v, d)| *v = v.max(0.0) * d);
+                spec
+            }
+        }
+    }
+}
+
+mod consts {
+    use crate::Float;
+
+    pub const MIN: Float = 380.0;
+    pub const MAX: Float = 720.0;
+    pub const BINS: usize = 10;
+
+    // Smits' basis spectra, in 10 equal bins from 380nm to 720nm. Longer
+    // wavelengths reuse the last bin.
+    #[rustfmt::skip]
+    pub const WHITE: [Float; BINS] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+    #[rustfmt::skip]
+    pub const CYAN: [Float; BINS] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+    #[rustfmt::skip]
+    pub const MAGENTA: [Float; BINS] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+    #[rustfmt::skip]
+    pub const YELLOW: [Float; BINS] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+    #[rustfmt::skip]
+    pub const RED: [Float; BINS] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+    #[rustfmt::skip]
+    pub const GREEN: [Float; BINS] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+    #[rustfmt::skip]
+    pub const BLUE: [Float; BINS] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::XYZ;
+
+    fn roundtrip(rgb: [Float; 3], kind: SpectrumKind) -> [Float; 3] {
+        let spec = Sampled::from_rgb(RGB::from(rgb), kind);
+        RGB::from(XYZ::from(spec)).into()
+    }
+
+    #[test]
+    fn primaries_stay_dominant() {
+        for (i, rgb) in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+            .into_iter()
+            .enumerate()
+        {
+            let out = roundtrip(rgb, SpectrumKind::Reflectance);
+            let argmax = (0..3).max_by(|&a, &b| out[a].total_cmp(&out[b])).unwrap();
+            assert_eq!(i, argmax);
+        }
+    }
+
+    #[test]
+    fn white_light_is_neutral() {
+        let [r, g, b] = roundtrip([1.0, 1.0, 1.0], SpectrumKind::Illuminant);
+        assert!(
+            (r - g).abs() < 0.02 && (g - b).abs() < 0.02,
+            "{:?}",
+            [r, g, b]
+        );
+    }
+
+    #[test]
+    fn reflectance_is_bounded() {
+        let spec = Sampled::from_rgb(RGB::from([2.0, 0.5, 3.0]), SpectrumKind::Reflectance);
+        assert!(spec.iter().all(|&v| (0.0..=1.0).contains(&v)));
+    }
+}
@@ -0,0 +1,100 @@
+//! # Textures.
+//!
+//! A texture is a function from a point on a surface to some value, typically
+//! a color or a scalar such as roughness. Textures are generic over the type
+//! of value they produce, so the same machinery (projections, remapping, etc.)
+//! works for both.
+//!
+//! ```
+//! use gremlin::color::RGB;
+//! use gremlin::texture::{Checkerboard, Texture, Triplanar};
+//!
+//! let checker = Checkerboard::new(RGB::from([0.0, 0.0, 0.0]), RGB::from([1.0, 1.0, 1.0]));
+//! let _tex = Triplanar::new(checker, 0.5);
+//! ```
+
+use crate::{
+    geo::{Coords, Point, Unit},
+    shape::Intersection,
+    Float,
+};
+
+// RE-EXPORTS
+
+mod bake;
+
+mod checker;
+pub use checker::*;
+
+mod expr;
+pub use expr::*;
+
+mod instance;
+pub use instance::*;
+
+mod noise;
+pub use noise::*;
+
+mod raster;
+pub use raster::*;
+
+mod spectral;
+pub use spectral::*;
+
+mod triplanar;
+pub use triplanar::*;
+
+// CORE DEFINITIONS
+
+/// The surface information a texture is evaluated against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureContext {
+    /// World-space position.
+    pub point: Point,
+    /// World-space surface normal.
+    pub norm: Unit,
+    /// Surface parameterization, if the shape provides one.
+    pub uv: Coords<Float>,
+    /// Time of the sample, for animated textures.
+    pub time: Float,
+    /// Identifier of the object hit, for per-instance variation. Scenes use
+    /// the primitive's index.
+    pub instance: u64,
+    /// World-space width of the area the sample stands for, such as a
+    /// [`RayCone`]'s footprint at the hit. Textures that can filter use it to
+    /// blur away detail too fine to show; `0` is a point sample.
+    ///
+    /// [`RayCone`]: crate::geo::RayCone
+    pub footprint: Float,
+}
+
+impl From<&Intersection> for TextureContext {
+    #[inline]
+    fn from(isect: &Intersection) -> Self {
+        Self {
+            point: isect.point,
+            norm: isect.norm,
+            uv: Coords::splat(0.0),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        }
+    }
+}
+
+/// The core trait for textures.
+pub trait Texture<T>: Send + Sync {
+    /// Evaluate the texture at the given surface point.
+    fn evaluate(&self, ctx: &TextureContext) -> T;
+}
+
+/// A texture with the same value everywhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constant<T>(pub T);
+
+impl<T: Copy + Send + Sync> Texture<T> for Constant<T> {
+    #[inline]
+    fn evaluate(&self, _ctx: &TextureContext) -> T {
+        self.0
+    }
+}
@@ -0,0 +1,98 @@
+use super::{ImageTexture, Texture, TextureContext};
+use crate::{
+    geo::{Coords, Point, Unit},
+    Float,
+};
+use rayon::prelude::*;
+
+impl<T> ImageTexture<T> {
+    /// Evaluate `texture` at the center of each texel of a `width` by
+    /// `height` image.
+    ///
+    /// Only `uv` varies: the rest of the context is the origin, facing `+z`,
+    /// at time `0`, and instance `0`. Use [`bake_with`] for textures that
+    /// depend on those.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero.
+    ///
+    /// [`bake_with`]: Self::bake_with
+    pub fn bake(texture: &impl Texture<T>, width: u32, height: u32) -> Self
+    where
+        T: Send,
+    {
+        let base = TextureContext {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            uv: Coords::splat(0.0),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        };
+        Self::bake_with(texture, width, height, &base)
+    }
+
+    /// Like [`bake`], but with the rest of the context taken from `base`.
+    ///
+    /// Baking only captures how the texture varies with `uv`, so textures
+    /// that vary with position, time or instance bake to a snapshot of one
+    /// of them.
+    ///
+    /// [`bake`]: Self::bake
+    pub fn bake_with(
+        texture: &impl Texture<T>,
+        width: u32,
+        height: u32,
+        base: &TextureContext,
+    ) -> Self
+    where
+        T: Send,
+    {
+        let texels = (0..width as usize * height as usize)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % width as usize, i / width as usize);
+                let u = (x as Float + 0.5) / width as Float;
+                let v = 1.0 - (y as Float + 0.5) / height as Float;
+                let ctx = TextureContext {
+                    uv: Coords::new(u, v),
+                    ..*base
+                };
+                texture.evaluate(&ctx)
+            })
+            .collect();
+        Self::new(width, height, texels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Expr;
+
+    fn ctx(u: Float, v: Float) -> TextureContext {
+        TextureContext {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            uv: Coords::new(u, v),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        }
+    }
+
+    #[test]
+    fn bake() {
+        let expr: Expr = "u + 2 * v".parse().unwrap();
+        let baked = ImageTexture::bake(&expr, 8, 4);
+        assert_eq!((8, 4), baked.dimensions());
+        assert_eq!(32 * std::mem::size_of::<Float>(), baked.memory_size());
+
+        // Exact at texel centers, and linear in between
+        for (u, v) in [(0.0625, 0.875), (0.5625, 0.125), (0.3, 0.4)] {
+            let expected = expr.evaluate(&ctx(u, v));
+            assert!((baked.evaluate(&ctx(u, v)) - expected).abs() < 1e-9);
+        }
+    }
+}
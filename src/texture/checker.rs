@@ -0,0 +1,29 @@
+use super::{Texture, TextureContext};
+
+/// A 2D checkerboard pattern over the `uv` parameterization.
+///
+/// Each unit square in `uv` space holds a 2x2 block of checks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkerboard<T> {
+    even: T,
+    odd: T,
+}
+
+impl<T> Checkerboard<T> {
+    /// Creates a new checkerboard alternating between the two values.
+    pub const fn new(even: T, odd: T) -> Self {
+        Self { even, odd }
+    }
+}
+
+impl<T: Copy + Send + Sync> Texture<T> for Checkerboard<T> {
+    #[inline]
+    fn evaluate(&self, ctx: &TextureContext) -> T {
+        let u = (ctx.uv.x * 2.0).floor() as i64;
+        let v = (ctx.uv.y * 2.0).floor() as i64;
+        match (u + v) % 2 == 0 {
+            true => self.even,
+            false => self.odd,
+        }
+    }
+}
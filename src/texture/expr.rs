@@ -0,0 +1,598 @@
+use super::{fbm, perlin, turbulence, InstanceRandom, Texture, TextureContext};
+use crate::{
+    color::RGB,
+    geo::{Coords, Point, Unit},
+    Float,
+};
+use std::{error::Error, fmt, str::FromStr};
+
+const PI: Float = std::f64::consts::PI as Float;
+
+// Octaves for the `fbm` and `turbulence` functions
+const EXPR_OCTAVES: u32 = 6;
+
+// How deeply parentheses, function calls, negation and powers can nest before
+// parsing gives up, rather than overflowing the stack
+const MAX_NESTING: usize = 256;
+
+/// An error in an expression, at the given character offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    pub pos: usize,
+    pub msg: &'static str,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.pos + 1, self.msg)
+    }
+}
+
+impl Error for ExprError {}
+
+/// A scalar texture computed from a math expression.
+///
+/// Expressions give scene files simple procedural patterns without writing
+/// Rust. They're parsed once up front, and evaluate without allocating.
+///
+/// The usual arithmetic operators `+ - * / % ^` are supported, along with
+/// parentheses and these names:
+///
+/// | Name                          | Value                                |
+/// |-------------------------------|--------------------------------------|
+/// | `x`, `y`, `z`                 | World-space position                 |
+/// | `nx`, `ny`, `nz`              | Surface normal                       |
+/// | `u`, `v`                      | Surface parameterization             |
+/// | `t`                           | Time                                 |
+/// | `rand`                        | Random value per instance, in `[0, 1)` |
+/// | `pi`                          | π                                    |
+/// | `sin cos tan abs floor fract sqrt exp ln` | One-argument functions   |
+/// | `min max pow step`            | Two-argument functions               |
+/// | `clamp mix smoothstep`        | Three-argument functions, as in GLSL |
+/// | `noise fbm turbulence`        | Noise at point `(a, b, c)`; see [`perlin`], [`fbm`] and [`turbulence`] (6 octaves) |
+///
+/// ```
+/// use gremlin::geo::{Coords, Point, Unit};
+/// use gremlin::texture::{Expr, Texture, TextureContext};
+///
+/// let stripes: Expr = "step(0.5, fract(x * 4))".parse().unwrap();
+/// let ctx = TextureContext {
+///     point: Point::new(0.2, 0.0, 0.0),
+///     norm: Unit::Y_AXIS,
+///     uv: Coords::splat(0.0),
+///     time: 0.0,
+///     instance: 0,
+///     footprint: 0.0,
+/// };
+/// assert_eq!(1.0, stripes.evaluate(&ctx));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr(Node);
+
+impl Expr {
+    /// Parse an expression.
+    pub fn parse(src: &str) -> Result<Self, ExprError> {
+        let mut parser = Parser::new(src);
+        let node = parser.expr()?;
+        parser.finish()?;
+        Ok(Self(node))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Texture<Float> for Expr {
+    #[inline]
+    fn evaluate(&self, ctx: &TextureContext) -> Float {
+        self.0.eval(ctx)
+    }
+}
+
+/// A color texture computed from math expressions, one per channel.
+///
+/// Parsed from either a single expression, giving a grey value, or three
+/// comma-separated expressions for red, green and blue. See [`Expr`] for the
+/// syntax.
+///
+/// ```
+/// use gremlin::texture::ExprColor;
+///
+/// let rings: ExprColor = "0.5 + 0.5 * sin(x * 10), 0.2, 0.1".parse().unwrap();
+/// let grey: ExprColor = "fract(t)".parse().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprColor([Node; 3]);
+
+impl ExprColor {
+    /// Parse one or three comma-separated expressions.
+    pub fn parse(src: &str) -> Result<Self, ExprError> {
+        let mut parser = Parser::new(src);
+        let mut channels = vec![parser.expr()?];
+        while parser.eat(',') {
+            channels.push(parser.expr()?);
+        }
+        parser.finish()?;
+        match <[Node; 3]>::try_from(channels) {
+            Ok(rgb) => Ok(Self(rgb)),
+            Err(mut channels) if channels.len() == 1 => {
+                let grey = channels.pop().unwrap();
+                Ok(Self([grey.clone(), grey.clone(), grey]))
+            }
+            Err(_) => Err(ExprError {
+                pos: src.len(),
+                msg: "expected 1 or 3 expressions",
+            }),
+        }
+    }
+}
+
+impl FromStr for ExprColor {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Texture<RGB> for ExprColor {
+    #[inline]
+    fn evaluate(&self, ctx: &TextureContext) -> RGB {
+        RGB::from(self.0.each_ref().map(|node| node.eval(ctx)))
+    }
+}
+
+// EXPRESSION TREE
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    X,
+    Y,
+    Z,
+    Nx,
+    Ny,
+    Nz,
+    U,
+    V,
+    T,
+    Rand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Abs,
+    Floor,
+    Fract,
+    Sqrt,
+    Exp,
+    Ln,
+    Min,
+    Max,
+    Pow,
+    Step,
+    Clamp,
+    Mix,
+    Smoothstep,
+    Noise,
+    Fbm,
+    Turbulence,
+}
+
+impl Func {
+    fn lookup(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Self::Sin,
+            "cos" => Self::Cos,
+            "tan" => Self::Tan,
+            "abs" => Self::Abs,
+            "floor" => Self::Floor,
+            "fract" => Self::Fract,
+            "sqrt" => Self::Sqrt,
+            "exp" => Self::Exp,
+            "ln" => Self::Ln,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "pow" => Self::Pow,
+            "step" => Self::Step,
+            "clamp" => Self::Clamp,
+            "mix" => Self::Mix,
+            "smoothstep" => Self::Smoothstep,
+            "noise" => Self::Noise,
+            "fbm" => Self::Fbm,
+            "turbulence" => Self::Turbulence,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::Min | Self::Max | Self::Pow | Self::Step => 2,
+            Self::Clamp | Self::Mix | Self::Smoothstep => 3,
+            Self::Noise | Self::Fbm | Self::Turbulence => 3,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, a: &[Float]) -> Float {
+        match self {
+            Self::Sin => a[0].sin(),
+            Self::Cos => a[0].cos(),
+            Self::Tan => a[0].tan(),
+            Self::Abs => a[0].abs(),
+            Self::Floor => a[0].floor(),
+            Self::Fract => a[0] - a[0].floor(),
+            Self::Sqrt => a[0].sqrt(),
+            Self::Exp => a[0].exp(),
+            Self::Ln => a[0].ln(),
+            Self::Min => a[0].min(a[1]),
+            Self::Max => a[0].max(a[1]),
+            Self::Pow => a[0].powf(a[1]),
+            Self::Step => match a[1] < a[0] {
+                true => 0.0,
+                false => 1.0,
+            },
+            Self::Clamp => a[0].max(a[1]).min(a[2]),
+            Self::Mix => a[0] + (a[1] - a[0]) * a[2],
+            Self::Smoothstep => {
+                let t = ((a[2] - a[0]) / (a[1] - a[0])).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+            Self::Noise => perlin(Point::new(a[0], a[1], a[2])),
+            Self::Fbm => fbm(Point::new(a[0], a[1], a[2]), EXPR_OCTAVES),
+            Self::Turbulence => turbulence(Point::new(a[0], a[1], a[2]), EXPR_OCTAVES),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Const(Float),
+    Var(Var),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, ctx: &TextureContext) -> Float {
+        match self {
+            Self::Const(c) => *c,
+            Self::Var(var) => match var {
+                Var::X => ctx.point.x,
+                Var::Y => ctx.point.y,
+                Var::Z => ctx.point.z,
+                Var::Nx => ctx.norm.x(),
+                Var::Ny => ctx.norm.y(),
+                Var::Nz => ctx.norm.z(),
+                Var::U => ctx.uv.x,
+                Var::V => ctx.uv.y,
+                Var::T => ctx.time,
+                Var::Rand => InstanceRandom::default().value(ctx.instance),
+            },
+            Self::Neg(a) => -a.eval(ctx),
+            Self::Binary(op, a, b) => {
+                let (a, b) = (a.eval(ctx), b.eval(ctx));
+                match op {
+                    BinOp::Add => a + b,
+                    BinOp::Sub => a - b,
+                    BinOp::Mul => a * b,
+                    BinOp::Div => a / b,
+                    BinOp::Rem => a.rem_euclid(b),
+                    BinOp::Pow => a.powf(b),
+                }
+            }
+            Self::Call(func, args) => {
+                let mut vals = [0.0; 3];
+                for (val, arg) in vals.iter_mut().zip(args) {
+                    *val = arg.eval(ctx);
+                }
+                func.apply(&vals)
+            }
+        }
+    }
+
+    // Evaluate nodes whose inputs are all constant, so expressions like
+    // `2 * pi` don't cost anything per sample.
+    fn fold(self) -> Self {
+        let is_const = |n: &Node| matches!(n, Node::Const(_));
+        let ctx = TextureContext {
+            point: Point::ORIGIN,
+            norm: Unit::Y_AXIS,
+            uv: Coords::splat(0.0),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        };
+        let foldable = match &self {
+            Self::Neg(a) => is_const(a),
+            Self::Binary(_, a, b) => is_const(a) && is_const(b),
+            Self::Call(_, args) => args.iter().all(is_const),
+            _ => false,
+        };
+        match foldable {
+            true => Self::Const(self.eval(&ctx)),
+            false => self,
+        }
+    }
+}
+
+// PARSER
+
+// Recursive descent, loosest binding first:
+//
+//   expr   = term (('+' | '-') term)*
+//   term   = unary (('*' | '/' | '%') unary)*
+//   unary  = '-' unary | power
+//   power  = atom ('^' unary)?
+//   atom   = number | name | name '(' expr (',' expr)* ')' | '(' expr ')'
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn err(&self, msg: &'static str) -> ExprError {
+        ExprError { pos: self.pos, msg }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+        self.src[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char, msg: &'static str) -> Result<(), ExprError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.err(msg)),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), ExprError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.err("unexpected character")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, ExprError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some('+') => BinOp::Add,
+                Some('-') => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(self.term()?)).fold();
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some('*') => BinOp::Mul,
+                Some('/') => BinOp::Div,
+                Some('%') => BinOp::Rem,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(self.unary()?)).fold();
+        }
+    }
+
+    // Every nested subexpression is parsed through here, so this is where
+    // the nesting is limited
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        if self.depth == MAX_NESTING {
+            return Err(self.err("expression nested too deeply"));
+        }
+        self.depth += 1;
+        let node = match self.eat('-') {
+            true => self.unary().map(|n| Node::Neg(Box::new(n)).fold()),
+            false => self.power(),
+        };
+        self.depth -= 1;
+        node
+    }
+
+    fn power(&mut self) -> Result<Node, ExprError> {
+        let base = self.atom()?;
+        match self.eat('^') {
+            true => Ok(Node::Binary(BinOp::Pow, Box::new(base), Box::new(self.unary()?)).fold()),
+            false => Ok(base),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, ExprError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let node = self.expr()?;
+                self.expect(')', "expected ')'")?;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let len = self.src[self.pos..]
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(self.src.len() - self.pos);
+                let num = self.src[self.pos..self.pos + len]
+                    .parse()
+                    .map_err(|_| self.err("invalid number"))?;
+                self.pos += len;
+                Ok(Node::Const(num))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let len = self.src[self.pos..]
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(self.src.len() - self.pos);
+                let name = &self.src[self.pos..self.pos + len];
+                self.pos += len;
+                self.name(name, start)
+            }
+            Some(_) => Err(self.err("unexpected character")),
+            None => Err(self.err("unexpected end of expression")),
+        }
+    }
+
+    fn name(&mut self, name: &str, start: usize) -> Result<Node, ExprError> {
+        let var = match name {
+            "x" => Var::X,
+            "y" => Var::Y,
+            "z" => Var::Z,
+            "nx" => Var::Nx,
+            "ny" => Var::Ny,
+            "nz" => Var::Nz,
+            "u" => Var::U,
+            "v" => Var::V,
+            "t" => Var::T,
+            "rand" => Var::Rand,
+            "pi" => return Ok(Node::Const(PI)),
+            _ => {
+                let func = Func::lookup(name).ok_or(ExprError {
+                    pos: start,
+                    msg: "unknown name",
+                })?;
+                self.expect('(', "expected '(' after function name")?;
+                let mut args = vec![self.expr()?];
+                while self.eat(',') {
+                    args.push(self.expr()?);
+                }
+                self.expect(')', "expected ')'")?;
+                if args.len() != func.arity() {
+                    return Err(ExprError {
+                        pos: start,
+                        msg: "wrong number of arguments",
+                    });
+                }
+                return Ok(Node::Call(func, args).fold());
+            }
+        };
+        Ok(Node::Var(var))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn ctx() -> TextureContext {
+        TextureContext {
+            point: Point::new(1.0, 2.0, 3.0),
+            norm: Unit::Z_AXIS,
+            uv: Coords::new(0.25, 0.75),
+            time: 2.0,
+            instance: 7,
+            footprint: 0.0,
+        }
+    }
+
+    fn eval(src: &str) -> Float {
+        Expr::parse(src).unwrap().evaluate(&ctx())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_relative_eq!(7.0, eval("1 + 2 * 3"));
+        assert_relative_eq!(9.0, eval("(1 + 2) * 3"));
+        assert_relative_eq!(-4.0, eval("-2^2"));
+        assert_relative_eq!(512.0, eval("2^3^2"));
+        assert_relative_eq!(0.5, eval("2^-1"));
+        assert_relative_eq!(1.0, eval("-3 % 2"));
+        assert_relative_eq!(1.0, eval("10 - 4 - 5"));
+        assert_relative_eq!(0.0, eval("sin(pi)"), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn variables_and_functions() {
+        assert_relative_eq!(6.0, eval("x + y + z"));
+        assert_relative_eq!(1.0, eval("nz - nx - ny"));
+        assert_relative_eq!(1.0, eval("u + v"));
+        assert_relative_eq!(2.0, eval("t"));
+        assert_relative_eq!(InstanceRandom::default().value(7), eval("rand"));
+        assert_relative_eq!(0.5, eval("clamp(x - 0.5, 0, 1)"));
+        assert_relative_eq!(1.5, eval("mix(1, 2, 0.5)"));
+        assert_relative_eq!(0.5, eval("smoothstep(0, 4, 2)"));
+        assert_relative_eq!(0.0, eval("step(3, y)"));
+        assert_relative_eq!(0.25, eval("fract(-u * 3)"));
+        let p = Point::new(0.5, 4.0, 1.5);
+        assert_relative_eq!(perlin(p), eval("noise(x / 2, y * 2, z / 2)"));
+        assert_relative_eq!(turbulence(p, 6), eval("turbulence(0.5, 4, 1.5)"));
+        // Classic marble
+        let marble = eval("0.5 + 0.5 * sin(x * 4 + 6 * turbulence(x, y, z))");
+        assert!((0.0..=1.0).contains(&marble));
+        assert_eq!(Expr(Node::Const(2.0 * PI)), Expr::parse("2 * pi").unwrap());
+
+        let color = ExprColor::parse("x, y * 0.5, max(u, v)").unwrap();
+        assert_eq!(RGB::from([1.0, 1.0, 0.75]), color.evaluate(&ctx()));
+        let grey = ExprColor::parse("u").unwrap();
+        assert_eq!(RGB::from([0.25, 0.25, 0.25]), grey.evaluate(&ctx()));
+    }
+
+    #[test]
+    fn errors() {
+        let pos = |src| Expr::parse(src).unwrap_err().pos;
+        assert_eq!(4, pos("1 + "));
+        assert_eq!(4, pos("1 + w"));
+        assert_eq!(2, pos("(1"));
+        assert_eq!(0, pos("min(1)"));
+        assert_eq!(2, pos("1 2"));
+        assert_eq!(0, pos("1..2"));
+        assert!(ExprColor::parse("1, 2").is_err());
+
+        // Deep nesting is an error rather than a stack overflow
+        let deep = |open: &str, close: &str| {
+            let n = 200_000;
+            format!("{}1{}", open.repeat(n), close.repeat(n))
+        };
+        for src in [
+            deep("(", ")"),
+            deep("-", ""),
+            deep("sin(", ")"),
+            deep("2^", ""),
+        ] {
+            let err = Expr::parse(&src).unwrap_err();
+            assert_eq!("expression nested too deeply", err.msg);
+        }
+        let nested = "(".repeat(250) + "x" + &")".repeat(250);
+        assert!(Expr::parse(&nested).is_ok());
+    }
+}
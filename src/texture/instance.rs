@@ -0,0 +1,70 @@
+use super::{Texture, TextureContext};
+use crate::{color::RGB, math, Float};
+
+/// A random value per object instance, constant across each one.
+///
+/// Gives many copies of the same object (a forest of one tree, a field of
+/// rocks) some variety without a unique material each: feed it through a
+/// [`Ramp`] or an expression to vary color, roughness or bump strength. The
+/// values are hashed from the [`instance`] id and a seed, so they're stable
+/// from frame to frame and between renders, and different seeds give
+/// independent values for the same instance.
+///
+/// As a color texture, each channel gets an independent value.
+///
+/// [`Ramp`]: crate::math::Ramp
+/// [`instance`]: TextureContext::instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InstanceRandom {
+    seed: u64,
+}
+
+impl InstanceRandom {
+    /// Creates a per-instance random texture with the given seed.
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// The value in `[0, 1)` for the given instance.
+    #[inline]
+    pub fn value(&self, instance: u64) -> Float {
+        self.channel(instance, 0)
+    }
+
+    #[inline]
+    fn channel(&self, instance: u64, channel: u64) -> Float {
+        math::hash_to_unit(math::hash_keys(&[self.seed, instance, channel]))
+    }
+}
+
+impl Texture<Float> for InstanceRandom {
+    #[inline]
+    fn evaluate(&self, ctx: &TextureContext) -> Float {
+        self.value(ctx.instance)
+    }
+}
+
+impl Texture<RGB> for InstanceRandom {
+    #[inline]
+    fn evaluate(&self, ctx: &TextureContext) -> RGB {
+        RGB::from([0, 1, 2].map(|c| self.channel(ctx.instance, c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_per_instance() {
+        let tex = InstanceRandom::new(1);
+        let values: Vec<Float> = (0..1000).map(|i| tex.value(i)).collect();
+        assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+        assert_eq!(values[10], tex.value(10));
+        assert_ne!(values[10], values[11]);
+        assert_ne!(values[10], InstanceRandom::new(2).value(10));
+
+        let mean = values.iter().sum::<Float>() / values.len() as Float;
+        assert!((mean - 0.5).abs() < 0.05);
+    }
+}
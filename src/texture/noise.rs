@@ -0,0 +1,243 @@
+use super::{Texture, TextureContext};
+use crate::{
+    geo::{Point, Vector},
+    math::hash_keys,
+    Float,
+};
+
+/// Gradient noise in 3D, after Perlin's improved noise.
+///
+/// Smooth and band-limited, with values roughly in `[-1, 1]`, averaging `0`,
+/// and exactly `0` at integer coordinates. Features are about one unit
+/// across, so scale the point to change their size.
+///
+/// Gradients come from hashing the lattice coordinates rather than a
+/// permutation table, so the noise doesn't repeat.
+///
+/// See: <https://mrl.cs.nyu.edu/~perlin/noise/>
+pub fn perlin(p: Point) -> Float {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+    let (xi, yi, zi) = (x0 as i64, y0 as i64, z0 as i64);
+
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let h = hash_keys(&[(xi + dx) as u64, (yi + dy) as u64, (zi + dz) as u64]);
+        gradient(h, x - dx as Float, y - dy as Float, z - dz as Float)
+    };
+    let lerp = |t: Float, a: Float, b: Float| a + t * (b - a);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+/// Fractional Brownian motion: `octaves` layers of [`perlin`] noise, each at
+/// double the frequency and half the amplitude of the last.
+///
+/// Gives natural-looking detail at every scale, as in clouds and rough
+/// stone. See [`FractalNoise`] to choose the frequency and amplitude steps.
+#[inline]
+pub fn fbm(p: Point, octaves: u32) -> Float {
+    fractal(p, octaves, 2.0, 0.5, |n| n)
+}
+
+/// Like [`fbm`], but summing the absolute value of each octave.
+///
+/// The creases where each octave crosses zero give the veins in marble and
+/// the billows in smoke. Always non-negative.
+#[inline]
+pub fn turbulence(p: Point, octaves: u32) -> Float {
+    fractal(p, octaves, 2.0, 0.5, Float::abs)
+}
+
+fn fractal(
+    p: Point,
+    octaves: u32,
+    lacunarity: Float,
+    gain: Float,
+    shape: impl Fn(Float) -> Float,
+) -> Float {
+    let p = Vector::from(p);
+    let (mut sum, mut freq, mut amp) = (0.0, 1.0, 1.0);
+    for _ in 0..octaves {
+        sum += amp * shape(perlin(Point::from(p * freq)));
+        freq *= lacunarity;
+        amp *= gain;
+    }
+    sum
+}
+
+// Smootherstep, so the noise has continuous second derivatives
+#[inline]
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// Dot product with one of 12 cube-edge directions picked by the hash, as in
+// Perlin's reference implementation
+#[inline]
+fn gradient(h: u64, x: Float, y: Float, z: Float) -> Float {
+    let h = h & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+/// Fractal noise over world-space position, as a scalar texture.
+///
+/// Octaves with features smaller than the sample's [`footprint`] would only
+/// alias, so they're skipped, which also makes distant noise cheaper.
+///
+/// [`footprint`]: TextureContext::footprint
+///
+/// ```
+/// use gremlin::texture::FractalNoise;
+///
+/// // Fine-grained turbulence, for marble veins
+/// let veins = FractalNoise::turbulence(8.0, 6).gain(0.6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FractalNoise {
+    scale: Float,
+    octaves: u32,
+    lacunarity: Float,
+    gain: Float,
+    turbulence: bool,
+}
+
+impl FractalNoise {
+    /// [`fbm`] noise with `octaves` layers. `scale` is the number of
+    /// features per unit of distance in the first layer.
+    pub fn fbm(scale: Float, octaves: u32) -> Self {
+        Self {
+            scale,
+            octaves,
+            lacunarity: 2.0,
+            gain: 0.5,
+            turbulence: false,
+        }
+    }
+
+    /// [`turbulence`] with `octaves` layers. `scale` is the number of
+    /// features per unit of distance in the first layer.
+    pub fn turbulence(scale: Float, octaves: u32) -> Self {
+        Self {
+            turbulence: true,
+            ..Self::fbm(scale, octaves)
+        }
+    }
+
+    /// Set how much the frequency grows from one octave to the next.
+    /// Defaults to `2.0`.
+    pub fn lacunarity(mut self, lacunarity: Float) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    /// Set how much the amplitude shrinks from one octave to the next.
+    /// Higher values give rougher noise. Defaults to `0.5`.
+    pub fn gain(mut self, gain: Float) -> Self {
+        self.gain = gain;
+        self
+    }
+}
+
+impl Texture<Float> for FractalNoise {
+    #[inline]
+    fn evaluate(&self, ctx: &TextureContext) -> Float {
+        let p = Point::from(Vector::from(ctx.point) * self.scale);
+        let octaves = match ctx.footprint * self.scale {
+            // Octave `i` has features `1 / (scale * lacunarity^i)` across
+            w if w > 0.0 && self.lacunarity > 1.0 => {
+                let visible = (-w.log2() / self.lacunarity.log2()).floor() + 1.0;
+                self.octaves.min(visible.max(0.0) as u32)
+            }
+            _ => self.octaves,
+        };
+        match self.turbulence {
+            true => fractal(p, octaves, self.lacunarity, self.gain, Float::abs),
+            false => fractal(p, octaves, self.lacunarity, self.gain, |n| n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::{Coords, Unit};
+    use rand::prelude::*;
+
+    #[test]
+    fn perlin_noise() {
+        assert_eq!(0.0, perlin(Point::new(3.0, -2.0, 7.0)));
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut sum = 0.0;
+        for _ in 0..10_000 {
+            let p = Point::new(
+                rng.gen_range(-50.0..50.0),
+                rng.gen_range(-50.0..50.0),
+                rng.gen_range(-50.0..50.0),
+            );
+            let n = perlin(p);
+            assert!(n.abs() <= 1.1, "{}", n);
+            sum += n;
+
+            // Continuous
+            let q = Point::from(Vector::from(p) + Vector::splat(1e-6));
+            assert!((perlin(q) - n).abs() < 1e-4);
+        }
+        assert!((sum / 10_000.0).abs() < 0.02, "{}", sum);
+    }
+
+    #[test]
+    fn fractal() {
+        let p = Point::new(0.3, 1.7, -2.2);
+        assert_eq!(perlin(p), fbm(p, 1));
+        assert_eq!(perlin(p).abs(), turbulence(p, 1));
+        assert!(turbulence(p, 6) >= 0.0);
+        assert_eq!(0.0, fbm(p, 0));
+
+        let ctx = TextureContext {
+            point: Point::new(0.15, 0.85, -1.1),
+            norm: Unit::Y_AXIS,
+            uv: Coords::splat(0.0),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        };
+        let p = Point::from(Vector::from(ctx.point) * 2.0);
+        assert_eq!(fbm(p, 4), FractalNoise::fbm(2.0, 4).evaluate(&ctx));
+
+        let rough = FractalNoise::turbulence(2.0, 3).gain(1.0).lacunarity(3.0);
+        let expected: Float = [1.0, 3.0, 9.0]
+            .iter()
+            .map(|&f| perlin(Point::from(Vector::from(p) * f)).abs())
+            .sum();
+        assert!((rough.evaluate(&ctx) - expected).abs() < 1e-12);
+
+        // Features of the third octave are 1/8 across, so they drop out
+        let blurred = TextureContext {
+            footprint: 0.15,
+            ..ctx
+        };
+        assert_eq!(fbm(p, 2), FractalNoise::fbm(2.0, 4).evaluate(&blurred));
+    }
+}
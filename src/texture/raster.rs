@@ -0,0 +1,440 @@
+use super::{Texture, TextureContext};
+use crate::{color::RGB, Float};
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    DynamicImage, ImageError, ImageResult,
+};
+use std::{
+    ops::{Add, Mul},
+    path::Path,
+};
+
+/// How texture coordinates outside `[0, 1]` map onto an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Tile the image.
+    #[default]
+    Repeat,
+    /// Extend the edge texels outward.
+    Clamp,
+    /// Tile the image, flipping every other copy so the edges line up.
+    Mirror,
+}
+
+impl WrapMode {
+    // Map a texel index onto `[0, n)`
+    #[inline]
+    fn apply(self, i: i64, n: u32) -> u32 {
+        let n = n as i64;
+        let i = match self {
+            Self::Repeat => i.rem_euclid(n),
+            Self::Clamp => i.clamp(0, n - 1),
+            Self::Mirror => match i.rem_euclid(2 * n) {
+                m if m < n => m,
+                m => 2 * n - 1 - m,
+            },
+        };
+        i as u32
+    }
+}
+
+/// How an [`ImageTexture`] blends texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// The nearest texel of the full-size image. Cheapest, but blocky up
+    /// close and noisy from afar.
+    Nearest,
+    /// Blend the four nearest texels, in the mip level closest to the
+    /// sample's footprint.
+    #[default]
+    Bilinear,
+    /// Bilinear in the two mip levels either side of the sample's footprint,
+    /// blended. Smooth at every distance, for about twice the cost.
+    Trilinear,
+}
+
+/// A texture stored as a grid of texels over `uv` space.
+///
+/// Load one from an image file with `ImageTexture::<RGB>::load` (or the
+/// scalar equivalent), or [`bake`] a procedural texture into one.
+///
+/// Sampling a detailed image from far away, where each sample covers many
+/// texels, picks out an arbitrary one of them and shimmers from sample to
+/// sample. [`with_mipmaps`] fixes that: it adds a pyramid of copies, each
+/// half the size of the last, and lookups read from the copy whose texels
+/// match the sample's [`footprint`]. Footprints are in world units, so set
+/// [`world_size`] to how big one repeat of the image is in the scene.
+///
+/// `v` runs up the image, and rows run down.
+///
+/// [`bake`]: Self::bake
+/// [`with_mipmaps`]: Self::with_mipmaps
+/// [`footprint`]: TextureContext::footprint
+/// [`world_size`]: Self::world_size
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageTexture<T> {
+    // The full-size image, then each mip level in turn down to 1x1
+    levels: Vec<Level<T>>,
+    wrap: WrapMode,
+    filter: Filter,
+    world_size: Float,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Level<T> {
+    width: u32,
+    height: u32,
+    texels: Vec<T>,
+}
+
+impl<T> ImageTexture<T> {
+    /// Creates a texture from texels listed a row at a time, rows from the
+    /// top. It repeats, blends bilinearly, and has no mipmaps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero or `texels` is the wrong length.
+    pub fn new(width: u32, height: u32, texels: Vec<T>) -> Self {
+        if width == 0 || height == 0 {
+            panic!("Image dimensions must be non-zero");
+        }
+        let expected = width as usize * height as usize;
+        if texels.len() != expected {
+            panic!("Expected {} texels, got {}", expected, texels.len());
+        }
+        Self {
+            levels: vec![Level {
+                width,
+                height,
+                texels,
+            }],
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+            world_size: 1.0,
+        }
+    }
+
+    /// Set how coordinates outside `[0, 1]` map onto the image. Defaults to
+    /// [`WrapMode::Repeat`].
+    pub fn wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Set how texels are blended. Defaults to [`Filter::Bilinear`].
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Set the world-space size of one repeat of the image, used to convert
+    /// footprints to texels. Defaults to `1.0`.
+    pub fn world_size(mut self, size: Float) -> Self {
+        self.world_size = size;
+        self
+    }
+
+    /// The image dimensions.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.levels[0].width, self.levels[0].height)
+    }
+
+    /// The number of mip levels, counting the full-size image.
+    #[inline]
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The texel at `(x, y)` of the full-size image.
+    #[inline]
+    pub fn texel(&self, x: u32, y: u32) -> &T {
+        self.levels[0].texel(x, y)
+    }
+
+    /// Memory used by the texels of every mip level, in bytes.
+    pub fn memory_size(&self) -> usize {
+        let texels: usize = self.levels.iter().map(|l| l.texels.len()).sum();
+        texels * std::mem::size_of::<T>()
+    }
+
+    // The mip level to read for a sample `footprint` across: `0` when it
+    // covers at most one texel of the full-size image, `1` when two, and so
+    // on
+    #[inline]
+    fn level_of_detail(&self, footprint: Float) -> Float {
+        let (width, height) = self.dimensions();
+        let texels = footprint / self.world_size * width.max(height) as Float;
+        match texels > 1.0 {
+            true => texels.log2(),
+            false => 0.0,
+        }
+    }
+
+    #[inline]
+    fn level(&self, lod: Float) -> &Level<T> {
+        &self.levels[(lod as usize).min(self.levels.len() - 1)]
+    }
+}
+
+impl<T> ImageTexture<T>
+where
+    T: Copy + Add<Output = T> + Mul<Float, Output = T>,
+{
+    /// Add mip levels, each half the size of the last down to 1x1, so
+    /// lookups can filter by footprint. Uses about a third more memory.
+    pub fn with_mipmaps(mut self) -> Self {
+        self.levels.truncate(1);
+        while let Some(last) = self.levels.last().filter(|l| l.width * l.height > 1) {
+            let next = last.downsample();
+            self.levels.push(next);
+        }
+        self
+    }
+}
+
+impl ImageTexture<RGB> {
+    /// Creates a color texture from an image, dropping any alpha.
+    ///
+    /// 8- and 16-bit images are taken to be sRGB and converted to linear;
+    /// floating-point ones, such as from EXR files, are already linear.
+    /// Fails if the image is empty.
+    pub fn from_image(image: &DynamicImage) -> ImageResult<Self> {
+        let linear = matches!(
+            image,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        );
+        let rgb = image.to_rgb32f();
+        check_dimensions(rgb.dimensions())?;
+        let texels = rgb
+            .pixels()
+            .map(|p| {
+                let vals = p.0.map(|c| c as Float);
+                match linear {
+                    true => RGB::from(vals),
+                    false => RGB::from_srgb(vals),
+                }
+            })
+            .collect();
+        Ok(Self::new(rgb.width(), rgb.height(), texels))
+    }
+
+    /// Load a color texture from a PNG, JPEG, EXR or other image file (see
+    /// [`from_image`]), with mipmaps.
+    ///
+    /// [`from_image`]: Self::from_image
+    pub fn load(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(Self::from_image(&image::open(path)?)?.with_mipmaps())
+    }
+}
+
+impl ImageTexture<Float> {
+    /// Creates a scalar texture from an image's brightness, for maps such as
+    /// roughness. Black is `0` and white `1`, without converting from sRGB,
+    /// as such maps store their values directly. Fails if the image is empty.
+    pub fn from_image(image: &DynamicImage) -> ImageResult<Self> {
+        let luma = image.to_luma32f();
+        check_dimensions(luma.dimensions())?;
+        let texels = luma.pixels().map(|p| p.0[0] as Float).collect();
+        Ok(Self::new(luma.width(), luma.height(), texels))
+    }
+
+    /// Load a scalar texture from an image file (see [`from_image`]), with
+    /// mipmaps.
+    ///
+    /// [`from_image`]: Self::from_image
+    pub fn load(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(Self::from_image(&image::open(path)?)?.with_mipmaps())
+    }
+}
+
+fn check_dimensions((width, height): (u32, u32)) -> ImageResult<()> {
+    match width == 0 || height == 0 {
+        true => Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        ))),
+        false => Ok(()),
+    }
+}
+
+impl<T> Level<T> {
+    #[inline]
+    fn texel(&self, x: u32, y: u32) -> &T {
+        &self.texels[y as usize * self.width as usize + x as usize]
+    }
+}
+
+impl<T> Level<T>
+where
+    T: Copy + Add<Output = T> + Mul<Float, Output = T>,
+{
+    // Halve each dimension, averaging blocks of 2x2 texels. A leftover odd
+    // row or column is folded into its neighbour's block.
+    fn downsample(&self) -> Self {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut texels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let xs = 2 * x..(2 * x + 2 + (x + 1 == width) as u32 * (self.width % 2));
+                let ys = 2 * y..(2 * y + 2 + (y + 1 == height) as u32 * (self.height % 2));
+                let xs = xs.start..xs.end.min(self.width);
+                let ys = ys.start..ys.end.min(self.height);
+                let n = (xs.len() * ys.len()) as Float;
+                let sum = ys
+                    .flat_map(|y| xs.clone().map(move |x| (x, y)))
+                    .map(|(x, y)| *self.texel(x, y))
+                    .reduce(|a, b| a + b)
+                    .unwrap();
+                texels.push(sum * (1.0 / n));
+            }
+        }
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    #[inline]
+    fn nearest(&self, u: Float, v: Float, wrap: WrapMode) -> T {
+        let x = (u * self.width as Float).floor() as i64;
+        let y = ((1.0 - v) * self.height as Float).floor() as i64;
+        *self.texel(wrap.apply(x, self.width), wrap.apply(y, self.height))
+    }
+
+    #[inline]
+    fn bilinear(&self, u: Float, v: Float, wrap: WrapMode) -> T {
+        // Texel centers are at half-integer coordinates
+        let fx = u * self.width as Float - 0.5;
+        let fy = (1.0 - v) * self.height as Float - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let (x0, x1) = (wrap.apply(x0, self.width), wrap.apply(x0 + 1, self.width));
+        let (y0, y1) = (wrap.apply(y0, self.height), wrap.apply(y0 + 1, self.height));
+
+        let top = *self.texel(x0, y0) * (1.0 - tx) + *self.texel(x1, y0) * tx;
+        let bottom = *self.texel(x0, y1) * (1.0 - tx) + *self.texel(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+impl<T> Texture<T> for ImageTexture<T>
+where
+    T: Copy + Add<Output = T> + Mul<Float, Output = T> + Send + Sync,
+{
+    fn evaluate(&self, ctx: &TextureContext) -> T {
+        let (u, v) = (ctx.uv.x, ctx.uv.y);
+        match self.filter {
+            Filter::Nearest => self.levels[0].nearest(u, v, self.wrap),
+            Filter::Bilinear => {
+                let lod = self.level_of_detail(ctx.footprint).round();
+                self.level(lod).bilinear(u, v, self.wrap)
+            }
+            Filter::Trilinear => {
+                let lod = self.level_of_detail(ctx.footprint);
+                let (fine, t) = (lod.floor(), lod - lod.floor());
+                let near = self.level(fine).bilinear(u, v, self.wrap);
+                match t > 0.0 && (fine as usize) + 1 < self.levels.len() {
+                    true => near * (1.0 - t) + self.level(fine + 1.0).bilinear(u, v, self.wrap) * t,
+                    false => near,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::{Coords, Point, Unit};
+
+    fn ctx(u: Float, v: Float, footprint: Float) -> TextureContext {
+        TextureContext {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            uv: Coords::new(u, v),
+            time: 0.0,
+            instance: 0,
+            footprint,
+        }
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let tex = ImageTexture::new(2, 1, vec![0.0, 1.0]);
+        assert_eq!(0.5, tex.evaluate(&ctx(0.5, 0.5, 0.0)));
+        assert_eq!(0.0, tex.evaluate(&ctx(1.25, 0.5, 0.0)));
+        // Halfway between the last texel and the first, wrapping around
+        assert_eq!(0.5, tex.evaluate(&ctx(0.0, 0.5, 0.0)));
+
+        let tex = tex.wrap(WrapMode::Clamp);
+        assert_eq!(0.0, tex.evaluate(&ctx(0.0, 0.5, 0.0)));
+        assert_eq!(1.0, tex.evaluate(&ctx(3.0, 0.5, 0.0)));
+
+        let tex = tex.wrap(WrapMode::Mirror);
+        assert_eq!(0.0, tex.evaluate(&ctx(0.0, 0.5, 0.0)));
+        assert_eq!(1.0, tex.evaluate(&ctx(1.25, 0.5, 0.0)));
+        assert_eq!(0.0, tex.evaluate(&ctx(1.75, 0.5, 0.0)));
+
+        let tex = tex.filter(Filter::Nearest);
+        assert_eq!(1.0, tex.evaluate(&ctx(0.6, 0.5, 0.0)));
+        assert_eq!(1.0, tex.evaluate(&ctx(1.2, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn mipmaps() {
+        let texels = (0..12).map(|i| i as Float).collect();
+        let tex = ImageTexture::new(4, 3, texels).with_mipmaps();
+        assert_eq!(3, tex.mip_levels());
+        assert_eq!((2, 1), (tex.levels[1].width, tex.levels[1].height));
+        // Odd rows fold into the last block, so every texel counts once
+        assert_eq!(vec![4.5, 6.5], tex.levels[1].texels);
+        assert_eq!(vec![5.5], tex.levels[2].texels);
+        assert_eq!(15 * std::mem::size_of::<Float>(), tex.memory_size());
+
+        // Bilinear picks the nearest level; a footprint of a whole image
+        // (four texels) reads the 1x1 level
+        let far = ctx(0.3, 0.8, 1.0);
+        assert_eq!(5.5, tex.evaluate(&far));
+        let tex = tex.world_size(4.0);
+        assert_eq!(
+            tex.levels[0].bilinear(0.3, 0.8, WrapMode::Repeat),
+            tex.evaluate(&far)
+        );
+
+        // Trilinear blends the levels either side: a footprint of three
+        // texels is between the 2x1 and 1x1 levels
+        let tex = tex.world_size(1.0).filter(Filter::Trilinear);
+        let lod = Float::log2(3.0);
+        let (a, b) = (
+            tex.levels[1].bilinear(0.3, 0.8, WrapMode::Repeat),
+            tex.levels[2].bilinear(0.3, 0.8, WrapMode::Repeat),
+        );
+        let expected = a * (2.0 - lod) + b * (lod - 1.0);
+        assert!((tex.evaluate(&ctx(0.3, 0.8, 0.75)) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn from_image() {
+        let img = image::RgbImage::from_raw(2, 1, vec![255, 0, 0, 0, 128, 255]).unwrap();
+        let tex = ImageTexture::<RGB>::from_image(&DynamicImage::ImageRgb8(img)).unwrap();
+        assert_eq!(RGB::from([1.0, 0.0, 0.0]), *tex.texel(0, 0));
+        let [_, g, b] = <[Float; 3]>::from(*tex.texel(1, 0));
+        assert!((g - 0.2158).abs() < 1e-3, "{}", g);
+        assert!((b - 1.0).abs() < 1e-9);
+
+        // Float images are already linear
+        let img = image::Rgb32FImage::from_raw(1, 1, vec![0.5, 0.25, 2.0]).unwrap();
+        let tex = ImageTexture::<RGB>::from_image(&DynamicImage::ImageRgb32F(img)).unwrap();
+        assert_eq!(RGB::from([0.5, 0.25, 2.0]), *tex.texel(0, 0));
+
+        let img = image::GrayImage::from_raw(2, 2, vec![0, 51, 102, 255]).unwrap();
+        let tex = ImageTexture::<Float>::from_image(&DynamicImage::ImageLuma8(img)).unwrap();
+        assert!((tex.texel(1, 0) - 0.2).abs() < 1e-6);
+
+        let empty = DynamicImage::ImageLuma8(image::GrayImage::new(0, 3));
+        assert!(ImageTexture::<Float>::from_image(&empty).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use super::TextureContext;
+use crate::{
+    color::RGB,
+    geo::Coords,
+    spectrum::{Sampled, SigmoidPolynomial},
+    Float,
+};
+
+/// A texture of spectral reflectance.
+///
+/// Evaluated at a single wavelength, which is what a spectral integrator
+/// tracing each path at one wavelength needs; there's no detour through RGB,
+/// so measured spectra render exactly.
+pub trait SpectralTexture: Send + Sync {
+    /// The reflectance at the given surface point and wavelength, in
+    /// nanometers.
+    fn reflectance(&self, ctx: &TextureContext, wavelength: Float) -> Float;
+
+    /// The full reflectance spectrum at the given surface point.
+    fn spectrum(&self, ctx: &TextureContext) -> Sampled {
+        Sampled::from_fn(|w0, w1| self.reflectance(ctx, 0.5 * (w0 + w1)))
+    }
+}
+
+/// The same spectrum everywhere.
+impl SpectralTexture for SigmoidPolynomial {
+    #[inline]
+    fn reflectance(&self, _ctx: &TextureContext, wavelength: Float) -> Float {
+        self.at(wavelength)
+    }
+}
+
+/// An image storing a sampled spectrum per texel.
+///
+/// Each texel has one channel per wavelength, as from a multispectral
+/// camera or a spectral scan. Between channels, reflectance is interpolated
+/// linearly; outside them, it's clamped to the nearest channel. Texels are
+/// looked up by the surface `uv`, nearest neighbour, and repeat outside
+/// `[0, 1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectralImage {
+    width: u32,
+    height: u32,
+    wavelengths: Vec<Float>,
+    texels: Vec<Float>,
+}
+
+impl SpectralImage {
+    /// Creates a spectral image with the given channel wavelengths. `texels`
+    /// holds each texel's channels in turn, rows from the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero, there are no channels, the
+    /// wavelengths aren't strictly increasing, or `texels` is the wrong
+    /// length.
+    pub fn new(width: u32, height: u32, wavelengths: Vec<Float>, texels: Vec<Float>) -> Self {
+        if width == 0 || height == 0 {
+            panic!("Image dimensions must be non-zero");
+        }
+        if wavelengths.is_empty() || wavelengths.windows(2).any(|w| w[0] >= w[1]) {
+            panic!("Channel wavelengths must be non-empty and strictly increasing");
+        }
+        let expected = width as usize * height as usize * wavelengths.len();
+        if texels.len() != expected {
+            panic!("Expected {} texel values, got {}", expected, texels.len());
+        }
+        Self {
+            width,
+            height,
+            wavelengths,
+            texels,
+        }
+    }
+
+    /// The image dimensions.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The channel wavelengths, in nanometers.
+    #[inline]
+    pub fn wavelengths(&self) -> &[Float] {
+        &self.wavelengths
+    }
+
+    /// The channel values of the texel at `(x, y)`.
+    #[inline]
+    pub fn texel(&self, x: u32, y: u32) -> &[Float] {
+        let n = self.wavelengths.len();
+        let start = (y as usize * self.width as usize + x as usize) * n;
+        &self.texels[start..start + n]
+    }
+
+    /// Interpolate a texel's channels at the given wavelength.
+    fn interpolate(&self, texel: &[Float], wavelength: Float) -> Float {
+        let ws = &self.wavelengths;
+        let i = ws.partition_point(|&w| w <= wavelength);
+        match i {
+            0 => texel[0],
+            i if i == ws.len() => texel[i - 1],
+            i => {
+                let t = (wavelength - ws[i - 1]) / (ws[i] - ws[i - 1]);
+                texel[i - 1] + t * (texel[i] - texel[i - 1])
+            }
+        }
+    }
+}
+
+impl SpectralTexture for SpectralImage {
+    #[inline]
+    fn reflectance(&self, ctx: &TextureContext, wavelength: Float) -> Float {
+        let (x, y) = texel_coords(ctx.uv, self.width, self.height);
+        self.interpolate(self.texel(x, y), wavelength)
+    }
+}
+
+/// An image storing [`SigmoidPolynomial`] coefficients per texel.
+///
+/// Three numbers per texel instead of one per channel, and typically built
+/// from an RGB image by fitting each texel's color once up front, so the
+/// render itself never converts from RGB. Texels are looked up by the surface
+/// `uv`, nearest neighbour, and repeat outside `[0, 1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoefficientImage {
+    width: u32,
+    height: u32,
+    coeffs: Vec<SigmoidPolynomial>,
+}
+
+impl CoefficientImage {
+    /// Creates an image from per-texel coefficients, rows from the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero or `coeffs` is the wrong length.
+    pub fn new(width: u32, height: u32, coeffs: Vec<SigmoidPolynomial>) -> Self {
+        if width == 0 || height == 0 {
+            panic!("Image dimensions must be non-zero");
+        }
+        let expected = width as usize * height as usize;
+        if coeffs.len() != expected {
+            panic!("Expected {} texels, got {}", expected, coeffs.len());
+        }
+        Self {
+            width,
+            height,
+            coeffs,
+        }
+    }
+
+    /// Fit coefficients to each texel of an RGB reflectance image (see
+    /// [`SigmoidPolynomial::fit`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero or `texels` is the wrong length.
+    pub fn from_rgb(width: u32, height: u32, texels: &[RGB]) -> Self {
+        // Images tend to repeat colors, so only fit each one once
+        let mut fitted: Vec<(RGB, SigmoidPolynomial)> = Vec::new();
+        let coeffs = texels
+            .iter()
+            .map(|&rgb| match fitted.iter().find(|(c, _)| *c == rgb) {
+                Some(&(_, poly)) => poly,
+                None => {
+                    let poly = SigmoidPolynomial::fit(rgb);
+                    if fitted.len() < 256 {
+                        fitted.push((rgb, poly));
+                    }
+                    poly
+                }
+            })
+            .collect();
+        Self::new(width, height, coeffs)
+    }
+
+    /// The image dimensions.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The coefficients of the texel at `(x, y)`.
+    #[inline]
+    pub fn texel(&self, x: u32, y: u32) -> SigmoidPolynomial {
+        self.coeffs[y as usize * self.width as usize + x as usize]
+    }
+}
+
+impl SpectralTexture for CoefficientImage {
+    #[inline]
+    fn reflectance(&self, ctx: &TextureContext, wavelength: Float) -> Float {
+        let (x, y) = texel_coords(ctx.uv, self.width, self.height);
+        self.texel(x, y).at(wavelength)
+    }
+}
+
+// Nearest texel to `uv`, repeating outside `[0, 1]`. `v` runs up the image,
+// rows run down.
+#[inline]
+fn texel_coords(uv: Coords<Float>, width: u32, height: u32) -> (u32, u32) {
+    let u = uv.x.rem_euclid(1.0);
+    let v = 1.0 - uv.y.rem_euclid(1.0);
+    let x = ((u * width as Float) as u32).min(width - 1);
+    let y = ((v * height as Float) as u32).min(height - 1);
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::{Point, Unit};
+
+    fn ctx(u: Float, v: Float) -> TextureContext {
+        TextureContext {
+            point: Point::ORIGIN,
+            norm: Unit::Z_AXIS,
+            uv: Coords::new(u, v),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        }
+    }
+
+    #[test]
+    fn spectral_image() {
+        // 2x1: a red-ish and a blue-ish texel, three channels
+        let img = SpectralImage::new(
+            2,
+            1,
+            vec![450.0, 550.0, 650.0],
+            vec![0.1, 0.2, 0.9, 0.8, 0.3, 0.1],
+        );
+        assert_eq!(0.9, img.reflectance(&ctx(0.25, 0.5), 650.0));
+        assert_eq!(0.9, img.reflectance(&ctx(0.25, 0.5), 700.0));
+        assert!((img.reflectance(&ctx(0.25, 0.5), 600.0) - 0.55).abs() < 1e-9);
+        assert_eq!(0.8, img.reflectance(&ctx(0.75, 0.5), 400.0));
+        // Repeats
+        assert_eq!(0.8, img.reflectance(&ctx(1.75, 0.5), 450.0));
+    }
+
+    #[test]
+    fn coefficient_image() {
+        let red = RGB::from([0.8, 0.1, 0.1]);
+        let img = CoefficientImage::from_rgb(1, 2, &[red, red]);
+        let r = img.reflectance(&ctx(0.5, 0.5), 650.0);
+        let b = img.reflectance(&ctx(0.5, 0.5), 450.0);
+        assert!(r > 0.5 && b < 0.2, "{} {}", r, b);
+        assert_eq!(img.texel(0, 0), img.texel(0, 1));
+    }
+}
@@ -0,0 +1,137 @@
+use super::{Texture, TextureContext};
+use crate::{
+    geo::{Coords, Matrix, Unit, Vector},
+    Float,
+};
+use std::ops::{Add, Mul};
+
+/// Which coordinate space a projection is computed in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectionSpace {
+    /// Project world-space positions. The pattern stays put while objects move
+    /// through it.
+    World,
+    /// Project positions after transforming by the given world-to-object
+    /// matrix. The pattern sticks to the object. The matrix should be rigid
+    /// (or uniformly scaled), since normals are transformed by it directly.
+    Object(Matrix),
+}
+
+/// A texture projection that doesn't require a surface parameterization.
+///
+/// Projects the wrapped 2D texture along each of the three coordinate axes and
+/// blends the results by how closely the surface normal aligns with each axis.
+/// Handy for terrain, scanned geometry, and anything else without usable UVs.
+///
+/// See: <https://bgolus.medium.com/normal-mapping-for-a-triplanar-shader-10bf39dca05a>
+#[derive(Debug, Clone)]
+pub struct Triplanar<Tx> {
+    inner: Tx,
+    scale: Float,
+    sharpness: Float,
+    space: ProjectionSpace,
+}
+
+impl<Tx> Triplanar<Tx> {
+    /// Wrap a texture in a world-space triplanar projection.
+    ///
+    /// `scale` is the number of texture repeats per unit of distance.
+    pub fn new(inner: Tx, scale: Float) -> Self {
+        Self {
+            inner,
+            scale,
+            sharpness: 4.0,
+            space: ProjectionSpace::World,
+        }
+    }
+
+    /// Set the blend sharpness. Higher values shrink the transition region
+    /// between projections. Defaults to `4.0`.
+    pub fn sharpness(mut self, sharpness: Float) -> Self {
+        self.sharpness = sharpness;
+        self
+    }
+
+    /// Set the coordinate space the projection is computed in.
+    pub fn space(mut self, space: ProjectionSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    fn weights(&self, norm: Vector) -> Vector {
+        let w = norm.apply(|c| c.abs().powf(self.sharpness));
+        w / (w.x + w.y + w.z)
+    }
+}
+
+impl<T, Tx> Texture<T> for Triplanar<Tx>
+where
+    T: Copy + Add<Output = T> + Mul<Float, Output = T>,
+    Tx: Texture<T>,
+{
+    fn evaluate(&self, ctx: &TextureContext) -> T {
+        let (point, norm) = match self.space {
+            ProjectionSpace::World => (ctx.point, Vector::from(ctx.norm)),
+            ProjectionSpace::Object(m) => (m * ctx.point, m * ctx.norm),
+        };
+        let p = Vector::from(point) * self.scale;
+        let w = self.weights(norm);
+
+        let sample = |u, v| {
+            let ctx = TextureContext {
+                uv: Coords::new(u, v),
+                norm: Unit::try_from(norm).unwrap_or(ctx.norm),
+                ..*ctx
+            };
+            self.inner.evaluate(&ctx)
+        };
+
+        sample(p.y, p.z) * w.x + sample(p.x, p.z) * w.y + sample(p.x, p.y) * w.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{geo::Point, texture::Constant};
+    use approx::assert_relative_eq;
+
+    struct U;
+    impl Texture<Float> for U {
+        fn evaluate(&self, ctx: &TextureContext) -> Float {
+            ctx.uv.x
+        }
+    }
+
+    #[test]
+    fn constant_is_preserved() {
+        let tex = Triplanar::new(Constant(0.25), 1.0);
+        let ctx = TextureContext {
+            point: Point::new(1.0, 2.0, 3.0),
+            norm: Vector::new(1.0, 1.0, 0.5).normalize(),
+            uv: Coords::splat(0.0),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        };
+        assert_relative_eq!(0.25, tex.evaluate(&ctx));
+    }
+
+    #[test]
+    fn axis_aligned_projection() {
+        let tex = Triplanar::new(U, 2.0);
+        let ctx = TextureContext {
+            point: Point::new(1.0, 2.0, 3.0),
+            norm: Unit::Z_AXIS,
+            uv: Coords::splat(0.0),
+            time: 0.0,
+            instance: 0,
+            footprint: 0.0,
+        };
+        // Facing +z, so only the xy projection contributes
+        assert_relative_eq!(2.0, tex.evaluate(&ctx));
+
+        let tex = tex.space(ProjectionSpace::Object(Matrix::shift(Vector::splat(1.0))));
+        assert_relative_eq!(4.0, tex.evaluate(&ctx));
+    }
+}
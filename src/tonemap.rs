@@ -0,0 +1,226 @@
+//! # Tone mapping.
+//!
+//! Rendered values are scene-referred, with no upper limit, while displays top
+//! out at `1`. Converting straight to sRGB clips everything brighter, crushing
+//! highlights in any scene with bright lights or sky. The operators here
+//! compress that range into what a display can show, and are applied to a
+//! film snapshot before it is quantized.
+//!
+//! Operators chain as tuples, applied left to right:
+//!
+//! ```
+//! use gremlin::{
+//!     color::RGB,
+//!     film::Buffer,
+//!     tonemap::{AcesFilmic, Exposure, ToneMap},
+//! };
+//!
+//! let mut snapshot = Buffer::<RGB>::new(2, 2);
+//! snapshot[0] = RGB::from([8.0, 8.0, 8.0]);
+//! (Exposure(-1.0), AcesFilmic).apply(&mut snapshot);
+//! assert!(snapshot[0].max_component() < 1.0);
+//! ```
+
+use crate::{
+    color::RGB,
+    film::Buffer,
+    geo::{Matrix, Vector},
+    Float,
+};
+use rayon::prelude::*;
+
+/// An operator taking scene-referred linear RGB towards display-referred
+/// linear RGB.
+pub trait ToneMap {
+    /// Map a single color.
+    fn map(&self, rgb: RGB) -> RGB;
+
+    /// Map every pixel of a snapshot in place.
+    fn apply(&self, snapshot: &mut Buffer<RGB>)
+    where
+        Self: Sync,
+    {
+        snapshot.par_iter_mut().for_each(|px| *px = self.map(*px));
+    }
+}
+
+impl<A: ToneMap, B: ToneMap> ToneMap for (A, B) {
+    #[inline]
+    fn map(&self, rgb: RGB) -> RGB {
+        self.1.map(self.0.map(rgb))
+    }
+}
+
+/// Scale brightness by the given number of stops. Each stop doubles it.
+///
+/// On its own this doesn't compress anything, but it sets which part of the
+/// range the operator that follows it treats as midtones.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Exposure(pub Float);
+
+impl ToneMap for Exposure {
+    #[inline]
+    fn map(&self, rgb: RGB) -> RGB {
+        rgb * self.0.exp2()
+    }
+}
+
+/// Reinhard's global operator, `L (1 + L / W²) / (1 + L)`, on luminance `L`
+/// with white point `W`.
+///
+/// Colors keep their hue and saturation. With the default, infinite white
+/// point, luminance approaches but never reaches `1`, so highlights turn grey
+/// rather than white; a finite white point maps luminance `W` to `1`.
+///
+/// See: Reinhard et al., "Photographic Tone Reproduction for Digital Images",
+/// SIGGRAPH 2002
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reinhard {
+    white: Float,
+}
+
+impl Reinhard {
+    /// The basic operator, `L / (1 + L)`.
+    pub fn new() -> Self {
+        Self {
+            white: Float::INFINITY,
+        }
+    }
+
+    /// Set the smallest luminance that maps to pure white.
+    pub fn white(mut self, white: Float) -> Self {
+        self.white = white;
+        self
+    }
+}
+
+impl Default for Reinhard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToneMap for Reinhard {
+    fn map(&self, rgb: RGB) -> RGB {
+        let lum = luminance(rgb);
+        if lum <= 0.0 {
+            return RGB::default();
+        }
+        let mapped = lum * (1.0 + lum / (self.white * self.white)) / (1.0 + lum);
+        rgb * (mapped / lum)
+    }
+}
+
+/// A fitted approximation of the ACES reference rendering transform and sRGB
+/// output device transform (RRT+ODT).
+///
+/// Highlights roll off smoothly towards white instead of clipping, and very
+/// bright saturated colors desaturate the way film does. The curve darkens
+/// midtones: mid-grey `0.18` comes out at about `0.11`, so it's usually paired
+/// with about half a stop of [`Exposure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AcesFilmic;
+
+impl ToneMap for AcesFilmic {
+    // Stephen Hill's fit. Linear sRGB goes into ACES AP1 (with the RRT's
+    // saturation tweak folded in), through a rational fit of the combined tone
+    // curves, and back out.
+    //
+    // See: <https://github.com/TheRealMJP/BakingLab/blob/master/BakingLab/ACES.hlsl>
+    fn map(&self, rgb: RGB) -> RGB {
+        #[rustfmt::skip]
+        const INPUT: Matrix = Matrix::new([
+            [0.59719, 0.35458, 0.04823, 0.0],
+            [0.07600, 0.90834, 0.01566, 0.0],
+            [0.02840, 0.13383, 0.83777, 0.0],
+            [0.0,     0.0,     0.0,     1.0],
+        ]);
+        #[rustfmt::skip]
+        const OUTPUT: Matrix = Matrix::new([
+            [ 1.60475, -0.53108, -0.07367, 0.0],
+            [-0.10208,  1.10813, -0.00605, 0.0],
+            [-0.00327, -0.07276,  1.07602, 0.0],
+            [ 0.0,      0.0,      0.0,     1.0],
+        ]);
+
+        let fit = |v: Float| {
+            let a = v * (v + 0.0245786) - 0.000090537;
+            let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+            a / b
+        };
+        let v = OUTPUT * (INPUT * Vector::from(<[Float; 3]>::from(rgb))).apply(fit);
+        RGB::from(<[Float; 3]>::from(v).map(|c| c.clamp(0.0, 1.0)))
+    }
+}
+
+// Rec. 709 luminance weights
+#[inline]
+fn luminance(rgb: RGB) -> Float {
+    let [r, g, b] = <[Float; 3]>::from(rgb);
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::SRGB;
+
+    fn grey(v: Float) -> RGB {
+        RGB::from([v, v, v])
+    }
+
+    #[test]
+    fn exposure_in_stops() {
+        assert_eq!(grey(0.4), Exposure(2.0).map(grey(0.1)));
+        assert_eq!(grey(0.05), Exposure(-1.0).map(grey(0.1)));
+    }
+
+    #[test]
+    fn reinhard_keeps_hue() {
+        assert_eq!(grey(0.5), Reinhard::new().map(grey(1.0)));
+        let bright = Reinhard::new().map(RGB::from([1000.0, 500.0, 0.0]));
+        let [r, g, b] = <[Float; 3]>::from(bright);
+        assert!(luminance(bright) < 1.0, "{:?}", bright);
+        assert!((r / g - 2.0).abs() < 1e-9 && b == 0.0, "{:?}", bright);
+        assert!(Reinhard::new().map(RGB::default()).is_black());
+
+        let white = Reinhard::new().white(4.0).map(grey(4.0));
+        assert!((white.max_component() - 1.0).abs() < 1e-9, "{:?}", white);
+    }
+
+    #[test]
+    fn aces_rolls_off_highlights() {
+        let mid = AcesFilmic.map(grey(0.18)).max_component();
+        assert!((mid - 0.106).abs() < 0.005, "{}", mid);
+        let lifted = (Exposure(0.55), AcesFilmic).map(grey(0.18));
+        assert!(
+            (lifted.max_component() - 0.18).abs() < 0.005,
+            "{:?}",
+            lifted
+        );
+
+        // Brighter stays brighter, rolling off below white
+        let mut prev = 0.0;
+        for stops in 0..5 {
+            let v = AcesFilmic
+                .map(grey((stops as Float).exp2()))
+                .max_component();
+            assert!(v > prev && v < 1.0, "{} stops: {}", stops, v);
+            prev = v;
+        }
+        assert!(AcesFilmic.map(RGB::default()).max_component() < 1e-3);
+    }
+
+    #[test]
+    fn apply_to_snapshot() {
+        let mut snapshot = Buffer::<RGB>::new(2, 1);
+        snapshot[0] = grey(2.0);
+        snapshot[1] = grey(4.0);
+        Reinhard::new().apply(&mut snapshot);
+        assert_eq!(Reinhard::new().map(grey(4.0)), snapshot[1]);
+
+        // Highlights that would both clip stay distinct
+        let [lo, hi] = [snapshot[0].to_srgb(), snapshot[1].to_srgb()];
+        assert!(lo[0] < hi[0], "{:?} {:?}", lo, hi);
+    }
+}
@@ -0,0 +1,198 @@
+//! Light transport tests against scenes with closed-form solutions.
+//!
+//! Each test sets up a scene simple enough to solve by hand and checks the
+//! integrator's Monte Carlo estimate against it. Estimates are compared within
+//! a few standard errors, so a correct implementation fails with negligible
+//! probability while a biased one (a wrong pdf, a missing cosine, a leaking
+//! bounce) shows up as a large deviation.
+
+use approx::assert_relative_eq;
+use gremlin::{
+    camera::ThinLens,
+    color::RGB,
+    film::RGBFilm,
+    geo::{Degrees, Point, Ray, Unit, Vector},
+    integrator::{BounceLimits, Hacky, Integrator},
+    material::{Lambertian, Plastic, BSDF},
+    renderer::Renderer,
+    shape::{Intersection, Sphere, Surface, Triangle},
+    Float,
+};
+use rand::prelude::*;
+use rand_distr::UnitSphere;
+
+/// Hacky's hard-coded diffuse albedo.
+const ALBEDO: Float = 0.5;
+
+/// Number of standard errors an estimate may deviate from the expected value.
+const SIGMAS: Float = 4.0;
+
+/// The mean of `samples` and its standard error.
+fn estimate(samples: &[Float]) -> (Float, Float) {
+    let n = samples.len() as Float;
+    let mean = samples.iter().sum::<Float>() / n;
+    let var = samples.iter().map(|s| (s - mean).powi(2)).sum::<Float>() / (n - 1.0);
+    (mean, (var / n).sqrt())
+}
+
+/// Assert that two estimates agree within [`SIGMAS`] combined standard errors.
+fn assert_agree((a, a_err): (Float, Float), (b, b_err): (Float, Float)) {
+    let tolerance = SIGMAS * a_err.hypot(b_err) + 1e-6;
+    assert!(
+        (a - b).abs() <= tolerance,
+        "estimate {} differs from {} by more than {}",
+        a,
+        b,
+        tolerance
+    );
+}
+
+/// Assert that the mean of `samples` matches `expected` within [`SIGMAS`]
+/// standard errors.
+fn assert_estimate(expected: Float, samples: &[Float]) {
+    assert_agree((expected, 0.0), estimate(samples));
+}
+
+fn luminance(rgb: RGB) -> Float {
+    let [r, g, b]: [Float; 3] = rgb.into();
+    (r + g + b) / 3.0
+}
+
+/// A diffuse sphere under a constant environment (the "furnace test").
+///
+/// Light leaving a convex object never hits it again, so every hit pixel is
+/// exactly one bounce off the environment: `albedo * L`. Misses see `L`.
+#[test]
+fn furnace_sphere() {
+    let sky = 0.8;
+    let integrator = Hacky {
+        background: RGB::from([sky, sky, sky]).into(),
+        surfaces: vec![Surface::from(Sphere::new([0.0, 0.0, 0.0], 1.0))],
+        ..Default::default()
+    };
+
+    let mut film = RGBFilm::new(32, 32);
+    let cam = ThinLens::builder(film.dimensions())
+        .move_to([0.0, 0.0, -4.0])
+        .look_at([0.0, 0.0, 0.0])
+        .fov(Degrees(30.0))
+        .build();
+    Renderer::new(8)
+        .deterministic(7)
+        .render(&mut film, &cam, &integrator);
+
+    let pixel = |x: u32, y: u32| {
+        let (_, _, p) = film.pixel_iter().nth((y * 32 + x) as usize).unwrap();
+        luminance(p.to_color())
+    };
+    assert_relative_eq!(ALBEDO * sky, pixel(16, 16), epsilon = 1e-4);
+    assert_relative_eq!(ALBEDO * sky, pixel(12, 18), epsilon = 1e-4);
+    assert_relative_eq!(sky, pixel(0, 0), epsilon = 1e-4);
+}
+
+/// A diffuse plane under a constant environment, shadowed by a sphere.
+///
+/// With a single bounce, a point on the plane sees the environment except
+/// where the sphere blocks it. For a sphere of radius `R` centered a height
+/// `H` directly above the point, the blocked fraction of cosine-weighted
+/// directions is the view factor `(R / H)^2`, so the radiance is
+/// `albedo * L * (1 - (R / H)^2)`.
+#[test]
+fn sphere_view_factor() {
+    let (radius, height) = (1.0, 2.0);
+    let integrator = Hacky {
+        background: RGB::from([1.0, 1.0, 1.0]).into(),
+        surfaces: vec![
+            Surface::from(Triangle::new(
+                [-1000.0, 0.0, -1000.0],
+                [-1000.0, 0.0, 3000.0],
+                [3000.0, 0.0, -1000.0],
+            )),
+            Surface::from(Sphere::new([0.0, height, 0.0], radius)),
+        ],
+        bounces: BounceLimits {
+            diffuse: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    // A grazing ray that passes beside the sphere and lands right beneath it
+    let ray = Ray::new(Point::new(10.0, 1.0, 0.0), Vector::new(-10.0, -1.0, 0.0));
+    let mut rng = StdRng::seed_from_u64(1);
+    let samples: Vec<Float> = (0..20_000)
+        .map(|_| luminance(integrator.radiance(&ray, &mut rng)))
+        .collect();
+
+    let view_factor = (radius / height) * (radius / height);
+    assert_estimate(ALBEDO * (1.0 - view_factor), &samples);
+}
+
+/// The white furnace test for BSDFs.
+///
+/// Under uniform unit illumination, reflected radiance is the directional
+/// albedo `∫ f cos θ dω`. Estimating it by importance sampling with
+/// `sample_f` and by uniform hemisphere sampling with `f` must agree, which
+/// checks `f`, `pdf` and `sample_f` against each other. A white Lambertian
+/// reflects everything; plastic must not create energy.
+#[test]
+fn bsdf_white_furnace() {
+    let isect = Intersection {
+        point: Point::ORIGIN,
+        norm: Unit::Y_AXIS,
+        t: 1.0,
+    };
+    let white = RGB::from([1.0, 1.0, 1.0]);
+    let mut rng = StdRng::seed_from_u64(2);
+    let n = 20_000;
+    let angles: [Float; 4] = [1.0, 0.7, 0.3, 0.05];
+
+    for cos_o in angles {
+        let sin_o = (1.0 - cos_o * cos_o).sqrt();
+        let wo = Unit::try_from(Vector::new(sin_o, cos_o, 0.0)).unwrap();
+
+        let importance = |bsdf: &dyn Fn(&mut StdRng) -> Option<Float>, rng: &mut StdRng| {
+            (0..n).map(|_| bsdf(rng).unwrap_or(0.0)).collect::<Vec<_>>()
+        };
+        let uniform = |bsdf: &dyn Fn(Unit) -> RGB, rng: &mut StdRng| -> Vec<Float> {
+            (0..n)
+                .map(|_| {
+                    let mut w = Vector::from(UnitSphere.sample(rng));
+                    w.y = w.y.abs();
+                    let wi = Unit::try_from(w).unwrap();
+                    luminance(bsdf(wi)) * w.y * 2.0 * std::f64::consts::PI as Float
+                })
+                .collect()
+        };
+
+        let lambertian = Lambertian::new(white);
+        let sampled = importance(
+            &|rng| {
+                let s = lambertian.sample_f(wo, &isect, rng)?;
+                let cos_i = Vector::from(s.wi).dot(isect.norm.into());
+                Some(luminance(s.f) * cos_i / s.pdf)
+            },
+            &mut rng,
+        );
+        assert_estimate(1.0, &sampled);
+        assert_estimate(1.0, &uniform(&|wi| lambertian.f(wo, wi, &isect), &mut rng));
+
+        // The coat's mirror lobe is a delta that uniform sampling can't see,
+        // so compare only the diffuse part
+        let plastic = Plastic::new(Lambertian::new(white), 1.5);
+        let sampled = importance(
+            &|rng| {
+                let s = plastic.sample_f(wo, &isect, rng)?;
+                if s.flags.is_specular() {
+                    return Some(0.0);
+                }
+                let cos_i = Vector::from(s.wi).dot(isect.norm.into());
+                Some(luminance(s.f) * cos_i / s.pdf)
+            },
+            &mut rng,
+        );
+        let diffuse = uniform(&|wi| plastic.f(wo, wi, &isect), &mut rng);
+        assert_agree(estimate(&sampled), estimate(&diffuse));
+        assert!(estimate(&sampled).0 <= 1.0);
+    }
+}
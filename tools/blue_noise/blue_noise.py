@@ -0,0 +1,82 @@
+"""Generate a tileable blue-noise threshold mask by void-and-cluster.
+
+Writes a square mask of 8-bit thresholds, row by row from the top, with
+every value appearing equally often. See Ulichney, "The void-and-cluster
+method for dither array generation", 1993.
+
+    python3 blue_noise.py [size] [output]
+"""
+import math
+import random
+import sys
+
+SIGMA = 1.5
+RADIUS = 6
+
+
+def main():
+    size = int(sys.argv[1]) if len(sys.argv) > 1 else 64
+    out = sys.argv[2] if len(sys.argv) > 2 else f"blue_noise_{size}.bin"
+    rng = random.Random(1993)
+    n = size * size
+
+    # Gaussian weights out to RADIUS, wrapping around the edges
+    kernel = [
+        (dx, dy, math.exp(-(dx * dx + dy * dy) / (2 * SIGMA * SIGMA)))
+        for dy in range(-RADIUS, RADIUS + 1)
+        for dx in range(-RADIUS, RADIUS + 1)
+    ]
+
+    def splat(energy, i, sign):
+        x, y = i % size, i // size
+        for dx, dy, w in kernel:
+            energy[(y + dy) % size * size + (x + dx) % size] += sign * w
+
+    def tightest_cluster(ones, energy):
+        return max((i for i in range(n) if ones[i]), key=energy.__getitem__)
+
+    def largest_void(ones, energy):
+        return min((i for i in range(n) if not ones[i]), key=energy.__getitem__)
+
+    # An initial pattern of a tenth of the pixels, spread evenly by moving
+    # the tightest cluster into the largest void until it stays put
+    ones = [False] * n
+    energy = [0.0] * n
+    for i in rng.sample(range(n), n // 10):
+        ones[i] = True
+        splat(energy, i, 1)
+    while True:
+        cluster = tightest_cluster(ones, energy)
+        ones[cluster] = False
+        splat(energy, cluster, -1)
+        void = largest_void(ones, energy)
+        ones[void] = True
+        splat(energy, void, 1)
+        if void == cluster:
+            break
+
+    ranks = [0] * n
+    initial = (list(ones), list(energy))
+    count = sum(ones)
+
+    # Rank the initial pattern by removing tightest clusters
+    for rank in range(count - 1, -1, -1):
+        cluster = tightest_cluster(ones, energy)
+        ones[cluster] = False
+        splat(energy, cluster, -1)
+        ranks[cluster] = rank
+
+    # Then fill in the rest, largest void first
+    ones, energy = initial
+    for rank in range(count, n):
+        void = largest_void(ones, energy)
+        ones[void] = True
+        splat(energy, void, 1)
+        ranks[void] = rank
+
+    with open(out, "wb") as f:
+        f.write(bytes(r * 256 // n for r in ranks))
+
+
+if __name__ == "__main__":
+    main()